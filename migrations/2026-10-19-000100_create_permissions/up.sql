@@ -0,0 +1,30 @@
+CREATE TABLE permissions (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(100) NOT NULL UNIQUE,
+    description VARCHAR(255),
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE role_permissions (
+    role_id INTEGER NOT NULL REFERENCES roles(id) ON DELETE CASCADE,
+    permission_id INTEGER NOT NULL REFERENCES permissions(id) ON DELETE CASCADE,
+    PRIMARY KEY (role_id, permission_id)
+);
+
+CREATE INDEX idx_role_permissions_permission_id ON role_permissions(permission_id);
+
+INSERT INTO permissions (name, description) VALUES
+    ('users.read', 'List and search user accounts'),
+    ('users.deactivate', 'Deactivate and reactivate user accounts'),
+    ('users.logout', 'Revoke every session of a user'),
+    ('users.assign_role', 'Change the role of a user'),
+    ('roles.read', 'List roles and permissions'),
+    ('roles.manage', 'Create roles and change their permissions'),
+    ('chats.moderate', 'Moderate chats without being a chat admin');
+
+INSERT INTO role_permissions (role_id, permission_id)
+SELECT 0, id FROM permissions;
+
+-- Seeded roles use explicit ids, so move the sequence past them before
+-- custom roles are created through the API.
+SELECT setval('roles_id_seq', (SELECT MAX(id) FROM roles));
@@ -0,0 +1,23 @@
+-- Each device publishes its own identity and prekeys. The foreign keys to
+-- devices are added by the migration that creates that table.
+CREATE TABLE identity_keys (
+    user_id UUID NOT NULL REFERENCES auth_users(id) ON DELETE CASCADE,
+    identity_key TEXT NOT NULL,
+    signed_prekey_id INTEGER NOT NULL,
+    signed_prekey TEXT NOT NULL,
+    signed_prekey_signature TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    device_id UUID PRIMARY KEY
+);
+
+CREATE INDEX idx_identity_keys_user_id ON identity_keys(user_id);
+
+CREATE TABLE one_time_prekeys (
+    id BIGSERIAL PRIMARY KEY,
+    key_id INTEGER NOT NULL,
+    public_key TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    device_id UUID NOT NULL,
+    UNIQUE (device_id, key_id)
+);
@@ -0,0 +1,10 @@
+ALTER TABLE one_time_prekeys DROP CONSTRAINT one_time_prekeys_device_id_fkey;
+ALTER TABLE identity_keys DROP CONSTRAINT identity_keys_device_id_fkey;
+
+DROP TABLE message_envelopes;
+
+ALTER TABLE messages DROP COLUMN sender_device_id;
+DELETE FROM messages WHERE encrypted_content IS NULL;
+ALTER TABLE messages ALTER COLUMN encrypted_content SET NOT NULL;
+
+DROP TABLE devices;
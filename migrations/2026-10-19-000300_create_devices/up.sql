@@ -0,0 +1,28 @@
+CREATE TABLE devices (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES auth_users(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_devices_user_id ON devices(user_id);
+
+-- Messages now carry one ciphertext per recipient device. The shared
+-- column stays readable for messages sent before devices existed.
+ALTER TABLE messages ALTER COLUMN encrypted_content DROP NOT NULL;
+ALTER TABLE messages ADD COLUMN sender_device_id UUID REFERENCES devices(id) ON DELETE SET NULL;
+
+CREATE TABLE message_envelopes (
+    message_id UUID NOT NULL REFERENCES messages(id) ON DELETE CASCADE,
+    device_id UUID NOT NULL REFERENCES devices(id) ON DELETE CASCADE,
+    ciphertext TEXT NOT NULL,
+    PRIMARY KEY (message_id, device_id)
+);
+
+CREATE INDEX idx_message_envelopes_device_id ON message_envelopes(device_id);
+
+-- Removing a device removes the keys it published.
+ALTER TABLE identity_keys ADD CONSTRAINT identity_keys_device_id_fkey
+    FOREIGN KEY (device_id) REFERENCES devices(id) ON DELETE CASCADE;
+ALTER TABLE one_time_prekeys ADD CONSTRAINT one_time_prekeys_device_id_fkey
+    FOREIGN KEY (device_id) REFERENCES devices(id) ON DELETE CASCADE;
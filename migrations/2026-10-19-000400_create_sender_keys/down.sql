@@ -0,0 +1,5 @@
+DROP TABLE IF EXISTS sender_keys;
+DROP TABLE IF EXISTS chat_rekeys;
+
+ALTER TABLE messages DROP COLUMN epoch;
+ALTER TABLE chats DROP COLUMN epoch;
@@ -0,0 +1,30 @@
+-- A chat's epoch advances on every membership change. Group messages and
+-- sender keys are bound to an epoch, so members who left cannot read
+-- anything encrypted after they were gone.
+ALTER TABLE chats ADD COLUMN epoch INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE messages ADD COLUMN epoch INTEGER;
+
+CREATE TABLE chat_rekeys (
+    chat_id UUID NOT NULL REFERENCES chats(id) ON DELETE CASCADE,
+    epoch INTEGER NOT NULL,
+    reason VARCHAR(20) NOT NULL,
+    user_id UUID REFERENCES auth_users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (chat_id, epoch)
+);
+
+-- A sender key, encrypted pairwise for one recipient device.
+CREATE TABLE sender_keys (
+    id BIGSERIAL PRIMARY KEY,
+    chat_id UUID NOT NULL REFERENCES chats(id) ON DELETE CASCADE,
+    epoch INTEGER NOT NULL,
+    sender_id UUID NOT NULL REFERENCES auth_users(id) ON DELETE CASCADE,
+    sender_device_id UUID NOT NULL REFERENCES devices(id) ON DELETE CASCADE,
+    recipient_device_id UUID NOT NULL REFERENCES devices(id) ON DELETE CASCADE,
+    ciphertext TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    CONSTRAINT sender_keys_chat_epoch_devices_key
+        UNIQUE (chat_id, epoch, sender_device_id, recipient_device_id)
+);
+
+CREATE INDEX idx_sender_keys_recipient ON sender_keys(recipient_device_id, chat_id, epoch);
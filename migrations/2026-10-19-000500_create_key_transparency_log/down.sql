@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS key_log_nodes;
+DROP TABLE IF EXISTS key_log_heads;
+DROP TABLE IF EXISTS key_log_entries;
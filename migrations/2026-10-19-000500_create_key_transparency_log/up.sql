@@ -0,0 +1,34 @@
+-- Append-only Merkle log (RFC 6962) of identity keys. Entries outlive the
+-- accounts and devices they name, so there are no foreign keys: deleting a
+-- leaf would change every later tree head.
+CREATE TABLE key_log_entries (
+    leaf_index BIGINT PRIMARY KEY,
+    user_id UUID NOT NULL,
+    device_id UUID NOT NULL,
+    identity_key TEXT NOT NULL,
+    timestamp_ms BIGINT NOT NULL,
+    leaf_hash BYTEA NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_key_log_entries_device ON key_log_entries(device_id, leaf_index);
+CREATE INDEX idx_key_log_entries_user ON key_log_entries(user_id, leaf_index);
+
+-- One signed tree head per tree size.
+CREATE TABLE key_log_heads (
+    tree_size BIGINT PRIMARY KEY,
+    timestamp_ms BIGINT NOT NULL,
+    root_hash BYTEA NOT NULL,
+    signature BYTEA NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+-- Roots of the perfect subtrees completed so far, level 0 being the leaves:
+-- the subtree of 2^level leaves starting at leaf node_index * 2^level.
+-- Appends and proofs read one row per level instead of every leaf.
+CREATE TABLE key_log_nodes (
+    level SMALLINT NOT NULL,
+    node_index BIGINT NOT NULL,
+    hash BYTEA NOT NULL,
+    PRIMARY KEY (level, node_index)
+);
@@ -0,0 +1,29 @@
+-- Encrypted attachment blobs live in the blob store; these rows track who
+-- uploaded them and how far an upload got. Each chunk is stored as its own
+-- blob, keyed by the attachment and the chunk's offset.
+CREATE TABLE attachments (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    uploader_id UUID NOT NULL REFERENCES auth_users(id) ON DELETE CASCADE,
+    size BIGINT NOT NULL,
+    uploaded_size BIGINT NOT NULL DEFAULT 0,
+    completed_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    CONSTRAINT attachments_size_check CHECK (size > 0 AND uploaded_size <= size)
+);
+
+CREATE INDEX idx_attachments_uploader_id ON attachments(uploader_id);
+
+CREATE TABLE attachment_chunks (
+    attachment_id UUID NOT NULL REFERENCES attachments(id) ON DELETE CASCADE,
+    chunk_offset BIGINT NOT NULL,
+    size BIGINT NOT NULL,
+    PRIMARY KEY (attachment_id, chunk_offset)
+);
+
+CREATE TABLE message_attachments (
+    message_id UUID NOT NULL REFERENCES messages(id) ON DELETE CASCADE,
+    attachment_id UUID NOT NULL REFERENCES attachments(id) ON DELETE CASCADE,
+    PRIMARY KEY (message_id, attachment_id)
+);
+
+CREATE INDEX idx_message_attachments_attachment_id ON message_attachments(attachment_id);
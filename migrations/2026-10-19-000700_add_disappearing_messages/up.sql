@@ -0,0 +1,12 @@
+-- Disappearing messages: a chat-wide timer stamps new messages with an
+-- expiry, after which they are hidden and eventually deleted.
+ALTER TABLE chats ADD COLUMN message_ttl_seconds INTEGER
+    CHECK (message_ttl_seconds > 0);
+
+ALTER TABLE messages ADD COLUMN expires_at TIMESTAMP;
+-- View-once messages lose each device's envelope once it is delivered.
+ALTER TABLE messages ADD COLUMN view_once BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE INDEX idx_messages_expires_at ON messages(expires_at)
+    WHERE expires_at IS NOT NULL;
+CREATE INDEX idx_messages_view_once ON messages(id) WHERE view_once;
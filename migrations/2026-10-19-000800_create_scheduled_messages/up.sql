@@ -0,0 +1,32 @@
+-- Messages composed now and posted to the chat at `send_at`. The content is
+-- kept exactly as the client sent it: either per-device envelopes (a JSON
+-- object keyed by device id) or a sender-key ciphertext and its epoch.
+-- A row is deleted in the transaction that posts it; one that can no longer
+-- be posted (the chat's devices or epoch moved on) is kept as `failed` with
+-- the reason, until the sender edits or cancels it.
+CREATE TABLE scheduled_messages (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    chat_id UUID NOT NULL REFERENCES chats(id) ON DELETE CASCADE,
+    sender_id UUID NOT NULL REFERENCES auth_users(id) ON DELETE CASCADE,
+    sender_device_id UUID NOT NULL REFERENCES devices(id) ON DELETE CASCADE,
+    send_at TIMESTAMP NOT NULL,
+    envelopes TEXT,
+    epoch INTEGER,
+    ciphertext TEXT,
+    attachment_ids UUID[] NOT NULL DEFAULT '{}',
+    view_once BOOLEAN NOT NULL DEFAULT FALSE,
+    status VARCHAR(16) NOT NULL DEFAULT 'pending',
+    error TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    CONSTRAINT scheduled_messages_body_check CHECK (
+        (envelopes IS NOT NULL AND epoch IS NULL AND ciphertext IS NULL)
+        OR (envelopes IS NULL AND epoch IS NOT NULL AND ciphertext IS NOT NULL)
+    ),
+    CONSTRAINT scheduled_messages_status_check CHECK (status IN ('pending', 'failed'))
+);
+
+-- The scheduler scans due rows in `send_at` order.
+CREATE INDEX idx_scheduled_messages_due ON scheduled_messages(send_at)
+    WHERE status = 'pending';
+CREATE INDEX idx_scheduled_messages_sender_id ON scheduled_messages(sender_id, send_at);
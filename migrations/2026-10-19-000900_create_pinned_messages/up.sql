@@ -0,0 +1,10 @@
+-- Messages pinned in a chat, listed newest first. A pin goes away with its
+-- message, including when a disappearing message is deleted.
+CREATE TABLE pinned_messages (
+    message_id UUID PRIMARY KEY REFERENCES messages(id) ON DELETE CASCADE,
+    chat_id UUID NOT NULL REFERENCES chats(id) ON DELETE CASCADE,
+    pinned_by UUID NOT NULL REFERENCES auth_users(id),
+    pinned_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_pinned_messages_chat_id ON pinned_messages(chat_id, pinned_at DESC);
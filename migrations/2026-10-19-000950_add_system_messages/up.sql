@@ -0,0 +1,5 @@
+-- Server-generated notices such as membership changes and key changes share
+-- the timeline with user messages; they carry an unencrypted JSON payload
+-- instead of ciphertext.
+ALTER TABLE messages ADD COLUMN kind VARCHAR(32) NOT NULL DEFAULT 'text';
+ALTER TABLE messages ADD COLUMN payload TEXT;
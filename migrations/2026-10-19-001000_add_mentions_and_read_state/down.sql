@@ -0,0 +1,11 @@
+ALTER TABLE chat_members DROP COLUMN muted;
+ALTER TABLE chat_members DROP COLUMN last_read_at;
+
+ALTER TABLE scheduled_messages DROP COLUMN mention_all;
+ALTER TABLE scheduled_messages DROP COLUMN mentions;
+
+DROP INDEX IF EXISTS idx_messages_mention_all;
+DROP INDEX IF EXISTS idx_messages_mentions;
+
+ALTER TABLE messages DROP COLUMN mention_all;
+ALTER TABLE messages DROP COLUMN mentions;
@@ -0,0 +1,16 @@
+-- Mentions travel unencrypted next to the ciphertext so the server can
+-- count and notify them. `mention_all` addresses every member.
+ALTER TABLE messages ADD COLUMN mentions UUID[] NOT NULL DEFAULT '{}';
+ALTER TABLE messages ADD COLUMN mention_all BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE INDEX idx_messages_mentions ON messages USING GIN (mentions);
+CREATE INDEX idx_messages_mention_all ON messages(chat_id, created_at)
+    WHERE mention_all;
+
+ALTER TABLE scheduled_messages ADD COLUMN mentions UUID[] NOT NULL DEFAULT '{}';
+ALTER TABLE scheduled_messages ADD COLUMN mention_all BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Messages after `last_read_at` are unread. Muted chats only notify the
+-- member when a message mentions them.
+ALTER TABLE chat_members ADD COLUMN last_read_at TIMESTAMP NOT NULL DEFAULT NOW();
+ALTER TABLE chat_members ADD COLUMN muted BOOLEAN NOT NULL DEFAULT FALSE;
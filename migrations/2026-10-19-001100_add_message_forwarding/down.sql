@@ -0,0 +1,6 @@
+ALTER TABLE messages DROP CONSTRAINT messages_forwarded_from_check;
+ALTER TABLE messages DROP COLUMN forwarded_from_sender_id;
+ALTER TABLE messages DROP COLUMN forwarded_from_message_id;
+ALTER TABLE messages DROP COLUMN forwarded_from_chat_id;
+
+ALTER TABLE chats DROP COLUMN allow_forwarding;
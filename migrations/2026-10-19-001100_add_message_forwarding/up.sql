@@ -0,0 +1,12 @@
+-- Chats can refuse to have their messages forwarded elsewhere.
+ALTER TABLE chats ADD COLUMN allow_forwarding BOOLEAN NOT NULL DEFAULT TRUE;
+
+-- Where a forwarded message first came from. There are no foreign keys, so
+-- the provenance outlives the original once it expires or is deleted.
+ALTER TABLE messages ADD COLUMN forwarded_from_chat_id UUID;
+ALTER TABLE messages ADD COLUMN forwarded_from_message_id UUID;
+ALTER TABLE messages ADD COLUMN forwarded_from_sender_id UUID;
+ALTER TABLE messages ADD CONSTRAINT messages_forwarded_from_check CHECK (
+    (forwarded_from_chat_id IS NULL) = (forwarded_from_message_id IS NULL)
+    AND (forwarded_from_chat_id IS NULL) = (forwarded_from_sender_id IS NULL)
+);
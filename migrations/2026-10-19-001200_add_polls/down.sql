@@ -0,0 +1,4 @@
+DROP TABLE poll_votes;
+DROP TABLE polls;
+
+ALTER TABLE chats DROP COLUMN allow_polls;
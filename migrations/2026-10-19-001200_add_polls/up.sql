@@ -0,0 +1,23 @@
+-- Polls keep their question, options and votes in the clear so the server
+-- can tally them. Chats that want no unencrypted content turn them off.
+ALTER TABLE chats ADD COLUMN allow_polls BOOLEAN NOT NULL DEFAULT TRUE;
+
+-- The structure of a `poll` message. It goes away with its message.
+CREATE TABLE polls (
+    message_id UUID PRIMARY KEY REFERENCES messages(id) ON DELETE CASCADE,
+    question TEXT NOT NULL,
+    options TEXT[] NOT NULL CHECK (cardinality(options) BETWEEN 2 AND 10),
+    multiple_choice BOOLEAN NOT NULL DEFAULT FALSE,
+    anonymous BOOLEAN NOT NULL DEFAULT FALSE,
+    closes_at TIMESTAMP
+);
+
+-- One row per option a member picked; options are indexes into
+-- `polls.options`.
+CREATE TABLE poll_votes (
+    message_id UUID NOT NULL REFERENCES polls(message_id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES auth_users(id) ON DELETE CASCADE,
+    option_index INTEGER NOT NULL CHECK (option_index >= 0),
+    voted_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (message_id, user_id, option_index)
+);
@@ -0,0 +1,5 @@
+DROP TABLE push_dead_letters;
+DROP TABLE push_jobs;
+DROP TABLE push_endpoints;
+
+ALTER TABLE devices DROP COLUMN last_seen_at;
@@ -0,0 +1,43 @@
+-- When each device last fetched messages. Devices seen recently count as
+-- online and are not sent wake-ups.
+ALTER TABLE devices ADD COLUMN last_seen_at TIMESTAMP NOT NULL DEFAULT NOW();
+
+-- Where to wake a device up while it is offline: a Web Push subscription or
+-- a plain webhook. One endpoint per device.
+CREATE TABLE push_endpoints (
+    device_id UUID PRIMARY KEY REFERENCES devices(id) ON DELETE CASCADE,
+    kind VARCHAR(16) NOT NULL,
+    url TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    CONSTRAINT push_endpoints_kind_check CHECK (kind IN ('webhook', 'web_push'))
+);
+
+-- Pending wake-ups. They carry no content, so a device needs at most one:
+-- once awake it fetches everything new. A claimed job has `next_attempt_at`
+-- pushed out by a lease, so other dispatchers leave it alone until the lease
+-- runs out.
+CREATE TABLE push_jobs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    device_id UUID NOT NULL UNIQUE REFERENCES push_endpoints(device_id) ON DELETE CASCADE,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    last_error TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_push_jobs_next_attempt_at ON push_jobs(next_attempt_at);
+
+-- Wake-ups given up on, kept for operators. They outlive the device.
+CREATE TABLE push_dead_letters (
+    id UUID PRIMARY KEY,
+    device_id UUID NOT NULL,
+    kind VARCHAR(16) NOT NULL,
+    url TEXT NOT NULL,
+    attempts INTEGER NOT NULL,
+    error TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    failed_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_push_dead_letters_failed_at ON push_dead_letters(failed_at);
@@ -0,0 +1,57 @@
+INSERT INTO permissions (name, description) VALUES
+    ('webhooks.manage', 'Manage webhooks for every chat');
+
+INSERT INTO role_permissions (role_id, permission_id)
+SELECT 0, id FROM permissions WHERE name = 'webhooks.manage';
+
+-- Subscriptions of external systems to chat events. A webhook without a chat
+-- receives the events of every chat. The secret signs each delivery.
+CREATE TABLE webhooks (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    created_by UUID NOT NULL REFERENCES auth_users(id) ON DELETE CASCADE,
+    chat_id UUID REFERENCES chats(id) ON DELETE CASCADE,
+    url TEXT NOT NULL,
+    secret TEXT NOT NULL,
+    events TEXT[] NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_webhooks_created_by ON webhooks(created_by);
+CREATE INDEX idx_webhooks_chat_id ON webhooks(chat_id);
+
+-- The outbox: events are written in the transaction of the change they
+-- describe and fanned out to the matching webhooks afterwards, so a crash
+-- between the two loses nothing.
+CREATE TABLE webhook_events (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    kind VARCHAR(32) NOT NULL,
+    chat_id UUID NOT NULL,
+    payload TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    dispatched_at TIMESTAMP
+);
+
+CREATE INDEX idx_webhook_events_undispatched ON webhook_events(created_at)
+    WHERE dispatched_at IS NULL;
+
+-- One row per event and webhook, doubling as the delivery log. A claimed
+-- pending delivery has `next_attempt_at` pushed out by a lease, like push
+-- jobs.
+CREATE TABLE webhook_deliveries (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    webhook_id UUID NOT NULL REFERENCES webhooks(id) ON DELETE CASCADE,
+    event_id UUID NOT NULL REFERENCES webhook_events(id) ON DELETE CASCADE,
+    status VARCHAR(16) NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    response_status INTEGER,
+    last_error TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMP,
+    CONSTRAINT webhook_deliveries_status_check CHECK (status IN ('pending', 'delivered', 'failed')),
+    UNIQUE (webhook_id, event_id)
+);
+
+CREATE INDEX idx_webhook_deliveries_due ON webhook_deliveries(next_attempt_at)
+    WHERE status = 'pending';
+CREATE INDEX idx_webhook_deliveries_webhook_id ON webhook_deliveries(webhook_id, created_at);
@@ -0,0 +1,99 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::auth::UserInfoResponse;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AssignRoleRequest {
+    #[schema(example = 1)]
+    pub role_id: i32,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ListUsersQuery {
+    #[schema(example = "john")]
+    #[serde(default)]
+    pub search: Option<String>,
+    #[schema(example = 50)]
+    #[serde(default = "default_limit")]
+    pub limit: i64,
+    #[schema(example = 0)]
+    #[serde(default)]
+    pub offset: i64,
+}
+
+fn default_limit() -> i64 {
+    50
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UserListResponse {
+    pub users: Vec<UserInfoResponse>,
+    #[schema(example = 120)]
+    pub total: i64,
+    #[schema(example = 50)]
+    pub limit: i64,
+    #[schema(example = 0)]
+    pub offset: i64,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateRoleRequest {
+    #[schema(example = "moderator")]
+    pub name: String,
+    #[serde(default)]
+    #[schema(example = "Handles abuse reports")]
+    pub description: Option<String>,
+    #[serde(default)]
+    #[schema(example = json!(["users.read", "chats.moderate"]))]
+    pub permissions: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SetRolePermissionsRequest {
+    #[schema(example = json!(["users.read", "users.deactivate"]))]
+    pub permissions: Vec<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RoleResponse {
+    #[schema(example = 1)]
+    pub id: i32,
+    #[schema(example = "user")]
+    pub name: String,
+    #[schema(example = "Default user role")]
+    pub description: Option<String>,
+    #[schema(example = json!(["users.read"]))]
+    pub permissions: Vec<String>,
+    #[schema(example = "2024-01-02 12:00:00")]
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PermissionResponse {
+    #[schema(example = "users.deactivate")]
+    pub name: String,
+    #[schema(example = "Deactivate and reactivate user accounts")]
+    pub description: Option<String>,
+}
+
+impl From<crate::usecase::RoleInfo> for RoleResponse {
+    fn from(info: crate::usecase::RoleInfo) -> Self {
+        Self {
+            id: info.role.id,
+            name: info.role.name,
+            description: info.role.description,
+            permissions: info.permissions,
+            created_at: info.role.created_at.to_string(),
+        }
+    }
+}
+
+impl From<crate::repository::auth::Permission> for PermissionResponse {
+    fn from(permission: crate::repository::auth::Permission) -> Self {
+        Self {
+            name: permission.name,
+            description: permission.description,
+        }
+    }
+}
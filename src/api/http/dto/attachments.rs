@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateUploadRequest {
+    /// Total size of the encrypted attachment in bytes.
+    #[schema(example = 20971520)]
+    pub size: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AttachmentResponse {
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440005")]
+    pub id: Uuid,
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub uploader_id: Uuid,
+    #[schema(example = 20971520)]
+    pub size: i64,
+    /// Bytes received so far; the offset of the next chunk.
+    #[schema(example = 8388608)]
+    pub uploaded_size: i64,
+    /// Whether the attachment can be sent and downloaded.
+    #[schema(example = false)]
+    pub complete: bool,
+    #[schema(example = "2024-01-02 12:00:00")]
+    pub created_at: String,
+}
+
+/// Body of a 409 `OFFSET_MISMATCH`: resume the upload at `expected_offset`.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct OffsetMismatchResponse {
+    #[schema(example = "Upload offset mismatch: expected 8388608")]
+    pub error: String,
+    #[schema(example = "OFFSET_MISMATCH")]
+    pub code: String,
+    #[schema(example = 8388608)]
+    pub expected_offset: i64,
+}
+
+impl From<crate::usecase::AttachmentInfo> for AttachmentResponse {
+    fn from(info: crate::usecase::AttachmentInfo) -> Self {
+        Self {
+            id: info.id,
+            uploader_id: info.uploader_id,
+            size: info.size,
+            uploaded_size: info.uploaded_size,
+            complete: info.complete,
+            created_at: info.created_at,
+        }
+    }
+}
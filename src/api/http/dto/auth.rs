@@ -8,9 +8,6 @@ pub struct RegisterRequest {
     pub username: String,
     #[schema(example = "SecurePass123")]
     pub password: String,
-    #[serde(default)]
-    #[schema(example = 1)]
-    pub role_id: Option<i32>,
 }
 
 #[derive(Debug, Deserialize, ToSchema)]
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 use uuid::Uuid;
@@ -14,10 +16,173 @@ pub struct InviteUserRequest {
     pub username: String,
 }
 
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RenameChatRequest {
+    #[schema(example = "Weekend Plans")]
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct MarkReadRequest {
+    /// The newest message the caller has seen.
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440004")]
+    pub message_id: Uuid,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SetMuteRequest {
+    #[schema(example = true)]
+    pub muted: bool,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SetMessageTtlRequest {
+    /// Lifetime of new messages in seconds; null turns the timer off.
+    #[schema(example = 86400)]
+    pub ttl_seconds: Option<i32>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SetForwardingRequest {
+    #[schema(example = false)]
+    pub allow_forwarding: bool,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SetPollsRequest {
+    #[schema(example = false)]
+    pub allow_polls: bool,
+}
+
+/// A poll. Its question and options are not end-to-end encrypted.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreatePollRequest {
+    #[schema(example = "Lunch on Friday?")]
+    pub question: String,
+    /// Between 2 and 10 distinct options.
+    #[schema(example = json!(["Pizza", "Sushi", "Tacos"]))]
+    pub options: Vec<String>,
+    /// Members may pick more than one option.
+    #[serde(default)]
+    pub multiple_choice: bool,
+    /// Tallies do not show who voted for what.
+    #[serde(default)]
+    pub anonymous: bool,
+    /// Stop taking votes at this time: RFC 3339, or `YYYY-MM-DD HH:MM:SS`
+    /// in UTC.
+    #[schema(example = "2024-01-05T12:00:00Z")]
+    #[serde(default)]
+    pub closes_at: Option<String>,
+}
+
+/// The caller's choice in a poll, replacing any earlier one.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct VoteRequest {
+    /// Indexes of the picked options; empty withdraws the vote. At most one
+    /// unless the poll is multiple choice.
+    #[schema(example = json!([1]))]
+    pub options: Vec<i32>,
+}
+
+/// A message from another chat, re-encrypted by the client for this one:
+/// either one ciphertext per device of the chat or a single sender-key
+/// message, as when sending.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ForwardMessageRequest {
+    /// The message being forwarded, in a chat the caller is a member of.
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440004")]
+    pub message_id: Uuid,
+    #[schema(example = json!({
+        "550e8400-e29b-41d4-a716-446655440003": "U2FsdGVkX1+vupppZksvRf5pq5g5XjFRIipRkwB0K1Y="
+    }))]
+    #[serde(default)]
+    pub envelopes: HashMap<Uuid, String>,
+    #[serde(default)]
+    pub sender_key: Option<SenderKeyMessage>,
+    /// Fully uploaded attachments of the sender, at most 10.
+    #[schema(example = json!(["550e8400-e29b-41d4-a716-446655440005"]))]
+    #[serde(default)]
+    pub attachment_ids: Vec<Uuid>,
+}
+
+/// Either one ciphertext per device of the chat, keyed by device id and
+/// leaving out the sending device, or a single sender-key message.
 #[derive(Debug, Deserialize, ToSchema)]
 pub struct SendMessageRequest {
+    #[schema(example = json!({
+        "550e8400-e29b-41d4-a716-446655440003": "U2FsdGVkX1+vupppZksvRf5pq5g5XjFRIipRkwB0K1Y="
+    }))]
+    #[serde(default)]
+    pub envelopes: HashMap<Uuid, String>,
+    #[serde(default)]
+    pub sender_key: Option<SenderKeyMessage>,
+    /// Fully uploaded attachments of the sender, at most 10.
+    #[schema(example = json!(["550e8400-e29b-41d4-a716-446655440005"]))]
+    #[serde(default)]
+    pub attachment_ids: Vec<Uuid>,
+    /// Deliver once to each recipient device; needs `envelopes`.
+    #[serde(default)]
+    pub view_once: bool,
+    /// Members the text mentions, at most 50. Sent in the clear so they are
+    /// notified even with the chat muted.
+    #[schema(example = json!(["550e8400-e29b-41d4-a716-446655440002"]))]
+    #[serde(default)]
+    pub mentions: Vec<Uuid>,
+    /// `@all`: mention every member. Only for the chat creator and
+    /// moderators.
+    #[serde(default)]
+    pub mention_all: bool,
+    /// Post the message at this time instead of now: RFC 3339, or
+    /// `YYYY-MM-DD HH:MM:SS` in UTC. The envelopes or sender-key message
+    /// must still match the chat then, or the message fails.
+    #[schema(example = "2024-01-03T09:00:00Z")]
+    #[serde(default)]
+    pub send_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SenderKeyMessage {
+    #[schema(example = 3)]
+    pub epoch: i32,
     #[schema(example = "U2FsdGVkX1+vupppZksvRf5pq5g5XjFRIipRkwB0K1Y=")]
-    pub encrypted_content: String,
+    pub ciphertext: String,
+}
+
+/// The caller device's sender key, encrypted for every other device in the
+/// chat.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct DistributeSenderKeyRequest {
+    #[schema(example = 3)]
+    pub epoch: i32,
+    #[schema(example = json!({
+        "550e8400-e29b-41d4-a716-446655440003": "U2FsdGVkX1+vupppZksvRf5pq5g5XjFRIipRkwB0K1Y="
+    }))]
+    pub sender_keys: HashMap<Uuid, String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct GetSenderKeysQuery {
+    /// Defaults to the chat's current epoch.
+    #[schema(example = 3)]
+    #[serde(default)]
+    pub epoch: Option<i32>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct GetRekeysQuery {
+    #[schema(example = 2)]
+    #[serde(default)]
+    pub since_epoch: i32,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct GetMentionsQuery {
+    #[schema(example = 50)]
+    #[serde(default = "default_limit")]
+    pub limit: i64,
+    #[schema(example = 0)]
+    #[serde(default)]
+    pub offset: i64,
 }
 
 #[derive(Debug, Deserialize, ToSchema)]
@@ -30,10 +195,37 @@ pub struct GetMessagesQuery {
     pub offset: i64,
 }
 
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct GetScheduledMessagesQuery {
+    /// Only messages scheduled for this chat.
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440001")]
+    pub chat_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct GetMembersQuery {
+    #[schema(example = 1)]
+    #[serde(default)]
+    pub role_id: Option<i32>,
+    #[schema(example = "jo")]
+    #[serde(default)]
+    pub username_prefix: Option<String>,
+    #[schema(example = 100)]
+    #[serde(default = "default_member_limit")]
+    pub limit: i64,
+    #[schema(example = 0)]
+    #[serde(default)]
+    pub offset: i64,
+}
+
 fn default_limit() -> i64 {
     50
 }
 
+fn default_member_limit() -> i64 {
+    100
+}
+
 #[derive(Debug, Serialize, ToSchema)]
 pub struct ChatResponse {
     #[schema(example = "550e8400-e29b-41d4-a716-446655440000")]
@@ -44,6 +236,68 @@ pub struct ChatResponse {
     pub created_by: Uuid,
     #[schema(example = "2024-01-02 12:00:00")]
     pub created_at: String,
+    #[schema(example = 3)]
+    pub epoch: i32,
+    /// Lifetime of new messages in seconds, if disappearing messages are on.
+    #[schema(example = 86400)]
+    pub message_ttl_seconds: Option<i32>,
+    /// Whether members may forward messages out of the chat.
+    #[schema(example = true)]
+    pub allow_forwarding: bool,
+    /// Whether members may post polls and vote.
+    #[schema(example = true)]
+    pub allow_polls: bool,
+    /// The most recently pinned message.
+    pub latest_pin: Option<PinResponse>,
+    /// The caller's unread counts; set when listing or fetching chats.
+    pub read_state: Option<ReadStateResponse>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReadStateResponse {
+    /// Unread messages from other members.
+    #[schema(example = 12)]
+    pub unread_count: i64,
+    /// Unread messages that mention the caller, by name or through `@all`.
+    #[schema(example = 2)]
+    pub mention_count: i64,
+    #[schema(example = "2024-01-02 12:00:00")]
+    pub last_read_at: String,
+    /// Muted chats only notify of messages that mention the caller.
+    #[schema(example = false)]
+    pub muted: bool,
+}
+
+/// A message that mentions the caller. Fetch the chat's messages for its
+/// content.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MentionResponse {
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440004")]
+    pub message_id: Uuid,
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440001")]
+    pub chat_id: Uuid,
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440002")]
+    pub sender_id: Uuid,
+    /// Mentioned through `@all` rather than by name.
+    #[schema(example = false)]
+    pub mention_all: bool,
+    /// Within what the caller has marked read in the chat.
+    #[schema(example = false)]
+    pub read: bool,
+    #[schema(example = "2024-01-02 12:00:00")]
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PinResponse {
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub message_id: Uuid,
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440001")]
+    pub chat_id: Uuid,
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440002")]
+    pub pinned_by: Uuid,
+    #[schema(example = "2024-01-02 12:00:00")]
+    pub pinned_at: String,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
@@ -54,8 +308,147 @@ pub struct MessageResponse {
     pub chat_id: Uuid,
     #[schema(example = "550e8400-e29b-41d4-a716-446655440002")]
     pub sender_id: Uuid,
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440003")]
+    pub sender_device_id: Option<Uuid>,
+    /// The envelope for the requesting device; null on the sender's copy.
     #[schema(example = "U2FsdGVkX1+vupppZksvRf5pq5g5XjFRIipRkwB0K1Y=")]
-    pub encrypted_content: String,
+    pub encrypted_content: Option<String>,
+    /// Epoch of the sender key that decrypts a sender-key message.
+    #[schema(example = 3)]
+    pub epoch: Option<i32>,
+    /// `text` or `poll` for user messages, or the type of a server notice:
+    /// `identity_key_changed`, `member_joined`, `member_invited`,
+    /// `member_left`, `member_removed`, `chat_renamed`,
+    /// `message_ttl_changed`, `forwarding_changed`, `polls_changed`,
+    /// `message_pinned` or `message_unpinned`.
+    #[schema(example = "text")]
+    pub kind: String,
+    /// Unencrypted details of a server notice.
+    #[schema(value_type = Option<Object>)]
+    pub payload: Option<serde_json::Value>,
+    #[schema(example = json!(["550e8400-e29b-41d4-a716-446655440005"]))]
+    pub attachment_ids: Vec<Uuid>,
+    /// When the message disappears, if the chat has a message timer.
+    #[schema(example = "2024-01-03 12:00:00")]
+    pub expires_at: Option<String>,
+    #[schema(example = false)]
+    pub view_once: bool,
+    /// Members the sender mentioned.
+    #[schema(example = json!(["550e8400-e29b-41d4-a716-446655440002"]))]
+    pub mentions: Vec<Uuid>,
+    /// The sender mentioned everyone.
+    #[schema(example = false)]
+    pub mention_all: bool,
+    /// Where the content first came from, if the message was forwarded.
+    pub forwarded_from: Option<ForwardedFromResponse>,
+    /// The poll of a `poll` message, with its current tallies.
+    pub poll: Option<PollResponse>,
+    #[schema(example = "2024-01-02 12:00:00")]
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PollResponse {
+    #[schema(example = "Lunch on Friday?")]
+    pub question: String,
+    pub options: Vec<PollOptionResponse>,
+    #[schema(example = false)]
+    pub multiple_choice: bool,
+    #[schema(example = false)]
+    pub anonymous: bool,
+    #[schema(example = "2024-01-05 12:00:00")]
+    pub closes_at: Option<String>,
+    #[schema(example = false)]
+    pub closed: bool,
+    /// Members who picked at least one option.
+    #[schema(example = 4)]
+    pub voter_count: i64,
+    /// Indexes of the options the caller picked.
+    #[schema(example = json!([1]))]
+    pub own_votes: Vec<i32>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PollOptionResponse {
+    #[schema(example = "Sushi")]
+    pub text: String,
+    #[schema(example = 3)]
+    pub vote_count: i64,
+    /// Who picked the option; null in anonymous polls.
+    #[schema(example = json!(["550e8400-e29b-41d4-a716-446655440002"]))]
+    pub voters: Option<Vec<Uuid>>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ForwardedFromResponse {
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440007")]
+    pub chat_id: Uuid,
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440008")]
+    pub message_id: Uuid,
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440009")]
+    pub sender_id: Uuid,
+}
+
+/// A message waiting to be posted. Its content is only kept for posting and
+/// is not returned.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ScheduledMessageResponse {
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440006")]
+    pub id: Uuid,
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440001")]
+    pub chat_id: Uuid,
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440003")]
+    pub sender_device_id: Uuid,
+    #[schema(example = "2024-01-03 09:00:00")]
+    pub send_at: String,
+    #[schema(example = json!(["550e8400-e29b-41d4-a716-446655440005"]))]
+    pub attachment_ids: Vec<Uuid>,
+    #[schema(example = false)]
+    pub view_once: bool,
+    #[schema(example = json!(["550e8400-e29b-41d4-a716-446655440002"]))]
+    pub mentions: Vec<Uuid>,
+    #[schema(example = false)]
+    pub mention_all: bool,
+    /// `pending`, or `failed` when the chat no longer accepted the message
+    /// at `send_at`; edit it to try again.
+    #[schema(example = "pending")]
+    pub status: String,
+    /// Why a failed message was not posted.
+    #[schema(example = json!(null))]
+    pub error: Option<String>,
+    #[schema(example = "2024-01-02 12:00:00")]
+    pub created_at: String,
+    #[schema(example = "2024-01-02 12:00:00")]
+    pub updated_at: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SenderKeyResponse {
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub sender_id: Uuid,
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440003")]
+    pub sender_device_id: Uuid,
+    #[schema(example = "U2FsdGVkX1+vupppZksvRf5pq5g5XjFRIipRkwB0K1Y=")]
+    pub ciphertext: String,
+    #[schema(example = "2024-01-02 12:00:00")]
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SenderKeyBundleResponse {
+    #[schema(example = 3)]
+    pub epoch: i32,
+    pub sender_keys: Vec<SenderKeyResponse>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RekeyResponse {
+    #[schema(example = 3)]
+    pub epoch: i32,
+    #[schema(example = "kick")]
+    pub reason: String,
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440001")]
+    pub user_id: Option<Uuid>,
     #[schema(example = "2024-01-02 12:00:00")]
     pub created_at: String,
 }
@@ -66,6 +459,10 @@ pub struct ChatMemberResponse {
     pub user_id: Uuid,
     #[schema(example = "john_doe")]
     pub username: String,
+    #[schema(example = 1)]
+    pub role_id: i32,
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440001")]
+    pub invited_by: Option<Uuid>,
     #[schema(example = "2024-01-02 12:00:00")]
     pub joined_at: String,
 }
@@ -77,6 +474,47 @@ impl From<crate::usecase::ChatInfo> for ChatResponse {
             name: info.name,
             created_by: info.created_by,
             created_at: info.created_at,
+            epoch: info.epoch,
+            message_ttl_seconds: info.message_ttl_seconds,
+            allow_forwarding: info.allow_forwarding,
+            allow_polls: info.allow_polls,
+            latest_pin: info.latest_pin.map(PinResponse::from),
+            read_state: info.read_state.map(ReadStateResponse::from),
+        }
+    }
+}
+
+impl From<crate::usecase::ReadStateInfo> for ReadStateResponse {
+    fn from(info: crate::usecase::ReadStateInfo) -> Self {
+        Self {
+            unread_count: info.unread_count,
+            mention_count: info.mention_count,
+            last_read_at: info.last_read_at,
+            muted: info.muted,
+        }
+    }
+}
+
+impl From<crate::usecase::MentionInfo> for MentionResponse {
+    fn from(info: crate::usecase::MentionInfo) -> Self {
+        Self {
+            message_id: info.message_id,
+            chat_id: info.chat_id,
+            sender_id: info.sender_id,
+            mention_all: info.mention_all,
+            read: info.read,
+            created_at: info.created_at,
+        }
+    }
+}
+
+impl From<crate::usecase::PinInfo> for PinResponse {
+    fn from(info: crate::usecase::PinInfo) -> Self {
+        Self {
+            message_id: info.message_id,
+            chat_id: info.chat_id,
+            pinned_by: info.pinned_by,
+            pinned_at: info.pinned_at,
         }
     }
 }
@@ -87,7 +525,99 @@ impl From<crate::usecase::MessageInfo> for MessageResponse {
             id: info.id,
             chat_id: info.chat_id,
             sender_id: info.sender_id,
+            sender_device_id: info.sender_device_id,
             encrypted_content: info.encrypted_content,
+            epoch: info.epoch,
+            kind: info.kind,
+            payload: info.payload,
+            attachment_ids: info.attachment_ids,
+            expires_at: info.expires_at,
+            view_once: info.view_once,
+            mentions: info.mentions,
+            mention_all: info.mention_all,
+            forwarded_from: info.forwarded_from.map(ForwardedFromResponse::from),
+            poll: info.poll.map(PollResponse::from),
+            created_at: info.created_at,
+        }
+    }
+}
+
+impl From<crate::usecase::ForwardedFrom> for ForwardedFromResponse {
+    fn from(origin: crate::usecase::ForwardedFrom) -> Self {
+        Self {
+            chat_id: origin.chat_id,
+            message_id: origin.message_id,
+            sender_id: origin.sender_id,
+        }
+    }
+}
+
+impl From<crate::usecase::PollInfo> for PollResponse {
+    fn from(info: crate::usecase::PollInfo) -> Self {
+        Self {
+            question: info.question,
+            options: info
+                .options
+                .into_iter()
+                .map(|option| PollOptionResponse {
+                    text: option.text,
+                    vote_count: option.vote_count,
+                    voters: option.voters,
+                })
+                .collect(),
+            multiple_choice: info.multiple_choice,
+            anonymous: info.anonymous,
+            closes_at: info.closes_at,
+            closed: info.closed,
+            voter_count: info.voter_count,
+            own_votes: info.own_votes,
+        }
+    }
+}
+
+impl From<crate::usecase::ScheduledMessageInfo> for ScheduledMessageResponse {
+    fn from(info: crate::usecase::ScheduledMessageInfo) -> Self {
+        Self {
+            id: info.id,
+            chat_id: info.chat_id,
+            sender_device_id: info.sender_device_id,
+            send_at: info.send_at,
+            attachment_ids: info.attachment_ids,
+            view_once: info.view_once,
+            mentions: info.mentions,
+            mention_all: info.mention_all,
+            status: info.status,
+            error: info.error,
+            created_at: info.created_at,
+            updated_at: info.updated_at,
+        }
+    }
+}
+
+impl From<crate::usecase::SenderKeyBundle> for SenderKeyBundleResponse {
+    fn from(bundle: crate::usecase::SenderKeyBundle) -> Self {
+        Self {
+            epoch: bundle.epoch,
+            sender_keys: bundle
+                .sender_keys
+                .into_iter()
+                .map(|key| SenderKeyResponse {
+                    sender_id: key.sender_id,
+                    sender_device_id: key.sender_device_id,
+                    ciphertext: key.ciphertext,
+                    created_at: key.created_at,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<crate::usecase::RekeyInfo> for RekeyResponse {
+    fn from(info: crate::usecase::RekeyInfo) -> Self {
+        Self {
+            epoch: info.epoch,
+            reason: info.reason,
+            user_id: info.user_id,
             created_at: info.created_at,
         }
     }
@@ -98,7 +628,35 @@ impl From<crate::usecase::ChatMemberInfo> for ChatMemberResponse {
         Self {
             user_id: info.user_id,
             username: info.username,
+            role_id: info.role_id,
+            invited_by: info.invited_by,
             joined_at: info.joined_at,
         }
     }
 }
+
+/// Body of a 409 `DEVICE_MISMATCH`: resend after encrypting for the missing
+/// devices and dropping the stale ones.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DeviceMismatchResponse {
+    #[schema(example = "Envelopes do not match the chat's devices")]
+    pub error: String,
+    #[schema(example = "DEVICE_MISMATCH")]
+    pub code: String,
+    #[schema(example = json!(["550e8400-e29b-41d4-a716-446655440003"]))]
+    pub missing_devices: Vec<Uuid>,
+    #[schema(example = json!([]))]
+    pub stale_devices: Vec<Uuid>,
+}
+
+/// Body of a 409 `STALE_EPOCH`: distribute a sender key for
+/// `current_epoch` and retry.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct StaleEpochResponse {
+    #[schema(example = "Chat epoch is now 4")]
+    pub error: String,
+    #[schema(example = "STALE_EPOCH")]
+    pub code: String,
+    #[schema(example = 4)]
+    pub current_epoch: i32,
+}
@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RegisterDeviceRequest {
+    #[schema(example = "Work laptop")]
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DeviceResponse {
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440003")]
+    pub id: Uuid,
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub user_id: Uuid,
+    #[schema(example = "Work laptop")]
+    pub name: String,
+    #[schema(example = "2024-01-02 12:00:00")]
+    pub created_at: String,
+}
+
+impl From<crate::usecase::DeviceInfo> for DeviceResponse {
+    fn from(info: crate::usecase::DeviceInfo) -> Self {
+        Self {
+            id: info.id,
+            user_id: info.user_id,
+            name: info.name,
+            created_at: info.created_at,
+        }
+    }
+}
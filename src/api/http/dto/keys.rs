@@ -0,0 +1,277 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SignedPrekey {
+    #[schema(example = 1)]
+    pub key_id: i32,
+    #[schema(example = "BQzQ1q0Vn6sQfPq6X0o7PpYdFf1bS4XwB6j2m8h9JtUg")]
+    pub public_key: String,
+    #[schema(
+        example = "3q2+7w3q2+7w3q2+7w3q2+7w3q2+7w3q2+7w3q2+7w3q2+7w3q2+7w3q2+7w3q2+7w3q2+7w3q2+7w3q2+7w=="
+    )]
+    pub signature: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct OneTimePrekey {
+    #[schema(example = 42)]
+    pub key_id: i32,
+    #[schema(example = "BQzQ1q0Vn6sQfPq6X0o7PpYdFf1bS4XwB6j2m8h9JtUg")]
+    pub public_key: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct PublishIdentityRequest {
+    #[schema(example = "BWv2n0k4sS2yQm7r1kqf3p6dV9xT8uJ0hL5gC3bA1eZ0")]
+    pub identity_key: String,
+    pub signed_prekey: SignedPrekey,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UploadPrekeysRequest {
+    pub prekeys: Vec<OneTimePrekey>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PrekeyStatusResponse {
+    #[schema(example = 87)]
+    pub remaining: i64,
+    #[schema(example = 10)]
+    pub low_watermark: i64,
+    #[schema(example = false)]
+    pub needs_replenish: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PrekeyBundleResponse {
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub user_id: Uuid,
+    pub devices: Vec<DeviceBundleResponse>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DeviceBundleResponse {
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440003")]
+    pub device_id: Uuid,
+    #[schema(example = "BWv2n0k4sS2yQm7r1kqf3p6dV9xT8uJ0hL5gC3bA1eZ0")]
+    pub identity_key: String,
+    pub signed_prekey: SignedPrekey,
+    pub one_time_prekey: Option<OneTimePrekey>,
+}
+
+/// A signed tree head. The signature is Ed25519 over the RFC 6962
+/// `TreeHeadSignature` encoding of timestamp, tree size and root hash.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TreeHeadResponse {
+    #[schema(example = 1024)]
+    pub tree_size: i64,
+    /// Milliseconds since the Unix epoch.
+    #[schema(example = 1792368000000i64)]
+    pub timestamp: i64,
+    #[schema(example = "3q2+7w3q2+7w3q2+7w3q2+7w3q2+7w3q2+7w3q2+7w0=")]
+    pub root_hash: String,
+    #[schema(
+        example = "3q2+7w3q2+7w3q2+7w3q2+7w3q2+7w3q2+7w3q2+7w3q2+7w3q2+7w3q2+7w3q2+7w3q2+7w3q2+7w3q2+7w=="
+    )]
+    pub signature: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LogPublicKeyResponse {
+    #[schema(example = "Ed25519")]
+    pub algorithm: String,
+    #[schema(example = "BWv2n0k4sS2yQm7r1kqf3p6dV9xT8uJ0hL5gC3bA1eZ0")]
+    pub public_key: String,
+}
+
+/// A log leaf. Its hash is SHA-256 over `0x00 || 0x00 || user_id ||
+/// device_id || timestamp (8 bytes, big endian) || identity key bytes`.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct KeyLogEntryResponse {
+    #[schema(example = 41)]
+    pub leaf_index: i64,
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub user_id: Uuid,
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440003")]
+    pub device_id: Uuid,
+    #[schema(example = "BWv2n0k4sS2yQm7r1kqf3p6dV9xT8uJ0hL5gC3bA1eZ0")]
+    pub identity_key: String,
+    #[schema(example = 1792368000000i64)]
+    pub timestamp: i64,
+    #[schema(example = "3q2+7w3q2+7w3q2+7w3q2+7w3q2+7w3q2+7w3q2+7w0=")]
+    pub leaf_hash: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct InclusionProofResponse {
+    pub entry: KeyLogEntryResponse,
+    /// Sibling hashes from the leaf up to the root.
+    pub audit_path: Vec<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct KeyInclusionResponse {
+    pub tree_head: TreeHeadResponse,
+    pub proofs: Vec<InclusionProofResponse>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ConsistencyProofResponse {
+    #[schema(example = 512)]
+    pub first: i64,
+    #[schema(example = 1024)]
+    pub second: i64,
+    pub proof: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct GetLogEntriesQuery {
+    #[schema(example = 0)]
+    #[serde(default)]
+    pub start: i64,
+    #[schema(example = 100)]
+    #[serde(default = "default_entries_limit")]
+    pub limit: i64,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct GetInclusionQuery {
+    #[schema(example = 1024)]
+    pub tree_size: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct GetConsistencyQuery {
+    #[schema(example = 512)]
+    pub first: i64,
+    #[schema(example = 1024)]
+    pub second: Option<i64>,
+}
+
+fn default_entries_limit() -> i64 {
+    100
+}
+
+impl From<crate::usecase::PrekeyStatus> for PrekeyStatusResponse {
+    fn from(status: crate::usecase::PrekeyStatus) -> Self {
+        Self {
+            remaining: status.remaining,
+            low_watermark: status.low_watermark,
+            needs_replenish: status.needs_replenish,
+        }
+    }
+}
+
+impl From<crate::usecase::PrekeyBundle> for PrekeyBundleResponse {
+    fn from(bundle: crate::usecase::PrekeyBundle) -> Self {
+        Self {
+            user_id: bundle.user_id,
+            devices: bundle
+                .devices
+                .into_iter()
+                .map(DeviceBundleResponse::from)
+                .collect(),
+        }
+    }
+}
+
+impl From<crate::usecase::DeviceBundle> for DeviceBundleResponse {
+    fn from(bundle: crate::usecase::DeviceBundle) -> Self {
+        Self {
+            device_id: bundle.device_id,
+            identity_key: bundle.identity_key,
+            signed_prekey: SignedPrekey::from(bundle.signed_prekey),
+            one_time_prekey: bundle.one_time_prekey.map(OneTimePrekey::from),
+        }
+    }
+}
+
+impl From<crate::usecase::SignedPrekeyInfo> for SignedPrekey {
+    fn from(info: crate::usecase::SignedPrekeyInfo) -> Self {
+        Self {
+            key_id: info.key_id,
+            public_key: info.public_key,
+            signature: info.signature,
+        }
+    }
+}
+
+impl From<SignedPrekey> for crate::usecase::SignedPrekeyInfo {
+    fn from(prekey: SignedPrekey) -> Self {
+        Self {
+            key_id: prekey.key_id,
+            public_key: prekey.public_key,
+            signature: prekey.signature,
+        }
+    }
+}
+
+impl From<crate::usecase::OneTimePrekeyInfo> for OneTimePrekey {
+    fn from(info: crate::usecase::OneTimePrekeyInfo) -> Self {
+        Self {
+            key_id: info.key_id,
+            public_key: info.public_key,
+        }
+    }
+}
+
+impl From<OneTimePrekey> for crate::usecase::OneTimePrekeyInfo {
+    fn from(prekey: OneTimePrekey) -> Self {
+        Self {
+            key_id: prekey.key_id,
+            public_key: prekey.public_key,
+        }
+    }
+}
+
+impl From<crate::usecase::TreeHeadInfo> for TreeHeadResponse {
+    fn from(head: crate::usecase::TreeHeadInfo) -> Self {
+        Self {
+            tree_size: head.tree_size,
+            timestamp: head.timestamp,
+            root_hash: head.root_hash,
+            signature: head.signature,
+        }
+    }
+}
+
+impl From<crate::usecase::KeyLogEntryInfo> for KeyLogEntryResponse {
+    fn from(entry: crate::usecase::KeyLogEntryInfo) -> Self {
+        Self {
+            leaf_index: entry.leaf_index,
+            user_id: entry.user_id,
+            device_id: entry.device_id,
+            identity_key: entry.identity_key,
+            timestamp: entry.timestamp,
+            leaf_hash: entry.leaf_hash,
+        }
+    }
+}
+
+impl From<crate::usecase::KeyInclusionProofs> for KeyInclusionResponse {
+    fn from(proofs: crate::usecase::KeyInclusionProofs) -> Self {
+        Self {
+            tree_head: proofs.tree_head.into(),
+            proofs: proofs
+                .proofs
+                .into_iter()
+                .map(|proof| InclusionProofResponse {
+                    entry: proof.entry.into(),
+                    audit_path: proof.audit_path,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<crate::usecase::ConsistencyProof> for ConsistencyProofResponse {
+    fn from(proof: crate::usecase::ConsistencyProof) -> Self {
+        Self {
+            first: proof.first,
+            second: proof.second,
+            proof: proof.proof,
+        }
+    }
+}
@@ -1,10 +1,38 @@
+pub mod admin;
+pub mod attachments;
 pub mod auth;
 pub mod chat;
 pub mod common;
+pub mod devices;
+pub mod keys;
+pub mod push;
+pub mod webhooks;
 
+pub use admin::{
+    AssignRoleRequest, CreateRoleRequest, ListUsersQuery, PermissionResponse, RoleResponse,
+    SetRolePermissionsRequest, UserListResponse,
+};
+pub use attachments::{AttachmentResponse, CreateUploadRequest, OffsetMismatchResponse};
 pub use auth::{AuthResponse, LoginRequest, RegisterRequest, UserInfoResponse, UserResponse};
 pub use chat::{
-    ChatMemberResponse, ChatResponse, CreateChatRequest, GetMessagesQuery, InviteUserRequest,
-    MessageResponse, SendMessageRequest,
+    ChatMemberResponse, ChatResponse, CreateChatRequest, CreatePollRequest, DeviceMismatchResponse,
+    DistributeSenderKeyRequest, ForwardMessageRequest, ForwardedFromResponse, GetMembersQuery,
+    GetMentionsQuery, GetMessagesQuery, GetRekeysQuery, GetScheduledMessagesQuery,
+    GetSenderKeysQuery, InviteUserRequest, MarkReadRequest, MentionResponse, MessageResponse,
+    PinResponse, PollOptionResponse, PollResponse, ReadStateResponse, RekeyResponse,
+    RenameChatRequest, ScheduledMessageResponse, SendMessageRequest, SenderKeyBundleResponse,
+    SenderKeyMessage, SenderKeyResponse, SetForwardingRequest, SetMessageTtlRequest,
+    SetMuteRequest, SetPollsRequest, StaleEpochResponse, VoteRequest,
 };
 pub use common::{ErrorResponse, MessageResponse as MsgResponse};
+pub use devices::{DeviceResponse, RegisterDeviceRequest};
+pub use keys::{
+    ConsistencyProofResponse, DeviceBundleResponse, GetConsistencyQuery, GetInclusionQuery,
+    GetLogEntriesQuery, InclusionProofResponse, KeyInclusionResponse, KeyLogEntryResponse,
+    LogPublicKeyResponse, OneTimePrekey, PrekeyBundleResponse, PrekeyStatusResponse,
+    PublishIdentityRequest, SignedPrekey, TreeHeadResponse, UploadPrekeysRequest,
+};
+pub use push::{PushEndpointResponse, SetPushEndpointRequest, VapidPublicKeyResponse};
+pub use webhooks::{
+    CreateWebhookRequest, GetWebhookDeliveriesQuery, WebhookDeliveryResponse, WebhookResponse,
+};
@@ -0,0 +1,51 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Where to wake the device up while it is offline. Wake-ups carry no
+/// content: a `webhook` gets `{"type": "wake_up", "device_id": ...}`, a
+/// `web_push` subscription an empty push signed with the server's VAPID
+/// key.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SetPushEndpointRequest {
+    #[schema(example = "web_push")]
+    pub kind: String,
+    #[schema(example = "https://push.example.com/send/abc123")]
+    pub url: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PushEndpointResponse {
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440003")]
+    pub device_id: Uuid,
+    #[schema(example = "web_push")]
+    pub kind: String,
+    #[schema(example = "https://push.example.com/send/abc123")]
+    pub url: String,
+    #[schema(example = "2024-01-02 12:00:00")]
+    pub created_at: String,
+    #[schema(example = "2024-01-02 12:00:00")]
+    pub updated_at: String,
+}
+
+/// The `applicationServerKey` for Web Push subscriptions.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct VapidPublicKeyResponse {
+    /// Uncompressed P-256 point, base64url without padding.
+    #[schema(
+        example = "BNcRdreALRFXTkOOUHK1EtK2wtaz5Ry4YfYCA_0QTpQtUbVlUls0VJXg7A8u-Ts1XbjhazAkj7I99e8QcYP7DkM"
+    )]
+    pub public_key: String,
+}
+
+impl From<crate::usecase::PushEndpointInfo> for PushEndpointResponse {
+    fn from(info: crate::usecase::PushEndpointInfo) -> Self {
+        Self {
+            device_id: info.device_id,
+            kind: info.kind,
+            url: info.url,
+            created_at: info.created_at,
+            updated_at: info.updated_at,
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Subscribes a URL to chat events. Without `chat_id` the webhook receives
+/// the events of every chat, which needs `webhooks.manage`.
+///
+/// Each delivery is a POST of `{"id", "type", "chat_id", "created_at",
+/// "data"}` with `x-webhook-id`, `x-webhook-event`, `x-webhook-timestamp`
+/// and `x-webhook-signature` headers. The signature is `sha256=` and the
+/// hex HMAC-SHA256, keyed with the webhook's secret, of
+/// `{timestamp}.{body}`.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateWebhookRequest {
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440001")]
+    pub chat_id: Option<Uuid>,
+    #[schema(example = "https://integrations.example.com/hooks/chat")]
+    pub url: String,
+    /// Any of `message.created`, `member.joined` and `chat.renamed`.
+    #[schema(example = json!(["message.created", "member.joined"]))]
+    pub events: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct GetWebhookDeliveriesQuery {
+    #[schema(example = 50)]
+    #[serde(default = "default_limit")]
+    pub limit: i64,
+    #[schema(example = 0)]
+    #[serde(default)]
+    pub offset: i64,
+}
+
+fn default_limit() -> i64 {
+    50
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WebhookResponse {
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440010")]
+    pub id: Uuid,
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440002")]
+    pub created_by: Uuid,
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440001")]
+    pub chat_id: Option<Uuid>,
+    #[schema(example = "https://integrations.example.com/hooks/chat")]
+    pub url: String,
+    #[schema(example = json!(["message.created", "member.joined"]))]
+    pub events: Vec<String>,
+    /// Signing key, only returned when the webhook is created.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "q3JZ6xk1c0m4Jt8yYtZJ3f2c9ZpQx0m3kGv8sQ1bX2E")]
+    pub secret: Option<String>,
+    #[schema(example = "2024-01-02 12:00:00")]
+    pub created_at: String,
+}
+
+/// One event sent, or being sent, to a webhook.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WebhookDeliveryResponse {
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440011")]
+    pub id: Uuid,
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440012")]
+    pub event_id: Uuid,
+    #[schema(example = "message.created")]
+    pub event: String,
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440001")]
+    pub chat_id: Uuid,
+    #[schema(example = json!({"message_id": "550e8400-e29b-41d4-a716-446655440013"}))]
+    pub payload: serde_json::Value,
+    /// `pending`, `delivered` or `failed`.
+    #[schema(example = "delivered")]
+    pub status: String,
+    #[schema(example = 1)]
+    pub attempts: i32,
+    #[schema(example = "2024-01-02 12:00:30")]
+    pub next_attempt_at: Option<String>,
+    #[schema(example = 200)]
+    pub response_status: Option<i32>,
+    pub last_error: Option<String>,
+    #[schema(example = "2024-01-02 12:00:00")]
+    pub created_at: String,
+    #[schema(example = "2024-01-02 12:00:01")]
+    pub completed_at: Option<String>,
+}
+
+impl From<crate::usecase::WebhookInfo> for WebhookResponse {
+    fn from(info: crate::usecase::WebhookInfo) -> Self {
+        Self {
+            id: info.id,
+            created_by: info.created_by,
+            chat_id: info.chat_id,
+            url: info.url,
+            events: info.events,
+            secret: info.secret,
+            created_at: info.created_at,
+        }
+    }
+}
+
+impl From<crate::usecase::WebhookDeliveryInfo> for WebhookDeliveryResponse {
+    fn from(info: crate::usecase::WebhookDeliveryInfo) -> Self {
+        Self {
+            id: info.id,
+            event_id: info.event_id,
+            event: info.event,
+            chat_id: info.chat_id,
+            payload: info.payload,
+            status: info.status,
+            attempts: info.attempts,
+            next_attempt_at: info.next_attempt_at,
+            response_status: info.response_status,
+            last_error: info.last_error,
+            created_at: info.created_at,
+            completed_at: info.completed_at,
+        }
+    }
+}
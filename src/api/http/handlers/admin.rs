@@ -0,0 +1,350 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use uuid::Uuid;
+
+use super::auth::error_response;
+use crate::api::http::dto::{
+    AssignRoleRequest, CreateRoleRequest, ErrorResponse, ListUsersQuery, PermissionResponse,
+    RoleResponse, SetRolePermissionsRequest, UserInfoResponse, UserListResponse,
+};
+use crate::api::http::middleware::RequirePermission;
+use crate::api::http::middleware::permission::{
+    RolesManage, RolesRead, UsersAssignRole, UsersDeactivate, UsersLogout, UsersRead,
+};
+use crate::api::http::state::AppState;
+
+#[utoipa::path(
+    get,
+    path = "/admin/users",
+    params(
+        ("search" = Option<String>, Query, description = "Case-insensitive username substring"),
+        ("limit" = Option<i64>, Query, description = "Number of users to return, at most 200"),
+        ("offset" = Option<i64>, Query, description = "Offset for pagination"),
+    ),
+    responses(
+        (status = 200, description = "Page of users", body = UserListResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Admin"
+)]
+pub async fn list_users(
+    State(state): State<AppState>,
+    _: RequirePermission<UsersRead>,
+    Query(query): Query<ListUsersQuery>,
+) -> impl IntoResponse {
+    match state
+        .uc
+        .auth
+        .list_users(query.search, query.limit, query.offset)
+        .await
+    {
+        Ok(page) => (
+            StatusCode::OK,
+            Json(UserListResponse {
+                users: page.users.into_iter().map(UserInfoResponse::from).collect(),
+                total: page.total,
+                limit: page.limit,
+                offset: page.offset,
+            })
+            .into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/users/{user_id}/deactivate",
+    params(
+        ("user_id" = Uuid, Path, description = "User ID")
+    ),
+    responses(
+        (status = 200, description = "User deactivated", body = UserInfoResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Missing permission, or the user is an admin or the caller", body = ErrorResponse),
+        (status = 404, description = "User not found", body = ErrorResponse),
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Admin"
+)]
+pub async fn deactivate_user(
+    State(state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+    operator: RequirePermission<UsersDeactivate>,
+) -> impl IntoResponse {
+    match state
+        .uc
+        .auth
+        .deactivate_user(operator.user.user_id, user_id)
+        .await
+    {
+        Ok(user_info) => (
+            StatusCode::OK,
+            Json(UserInfoResponse::from(user_info)).into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/users/{user_id}/reactivate",
+    params(
+        ("user_id" = Uuid, Path, description = "User ID")
+    ),
+    responses(
+        (status = 200, description = "User reactivated", body = UserInfoResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Missing permission, or the user is an admin or the caller", body = ErrorResponse),
+        (status = 404, description = "User not found", body = ErrorResponse),
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Admin"
+)]
+pub async fn reactivate_user(
+    State(state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+    operator: RequirePermission<UsersDeactivate>,
+) -> impl IntoResponse {
+    match state
+        .uc
+        .auth
+        .reactivate_user(operator.user.user_id, user_id)
+        .await
+    {
+        Ok(user_info) => (
+            StatusCode::OK,
+            Json(UserInfoResponse::from(user_info)).into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/admin/users/{user_id}/role",
+    params(
+        ("user_id" = Uuid, Path, description = "User ID")
+    ),
+    request_body = AssignRoleRequest,
+    responses(
+        (status = 200, description = "Role assigned", body = UserInfoResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Missing permission, or the role change involves the admin role or permissions the caller lacks", body = ErrorResponse),
+        (status = 404, description = "User or role not found", body = ErrorResponse),
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Admin"
+)]
+pub async fn assign_role(
+    State(state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+    assigner: RequirePermission<UsersAssignRole>,
+    Json(payload): Json<AssignRoleRequest>,
+) -> impl IntoResponse {
+    match state
+        .uc
+        .auth
+        .assign_role(assigner.user.user_id, user_id, payload.role_id)
+        .await
+    {
+        Ok(user_info) => (
+            StatusCode::OK,
+            Json(UserInfoResponse::from(user_info)).into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/users/{user_id}/logout",
+    params(
+        ("user_id" = Uuid, Path, description = "User ID")
+    ),
+    responses(
+        (status = 200, description = "All tokens of the user revoked"),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Missing permission, or the user is an admin or the caller", body = ErrorResponse),
+        (status = 404, description = "User not found", body = ErrorResponse),
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Admin"
+)]
+pub async fn force_logout(
+    State(state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+    operator: RequirePermission<UsersLogout>,
+) -> impl IntoResponse {
+    match state
+        .uc
+        .auth
+        .force_logout(operator.user.user_id, user_id)
+        .await
+    {
+        Ok(()) => (
+            StatusCode::OK,
+            Json(serde_json::json!({"message": "User logged out"})).into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/roles",
+    responses(
+        (status = 200, description = "List of roles", body = Vec<RoleResponse>),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Admin"
+)]
+pub async fn list_roles(
+    State(state): State<AppState>,
+    _: RequirePermission<RolesRead>,
+) -> impl IntoResponse {
+    match state.uc.auth.list_roles().await {
+        Ok(roles) => (
+            StatusCode::OK,
+            Json(
+                roles
+                    .into_iter()
+                    .map(RoleResponse::from)
+                    .collect::<Vec<_>>(),
+            )
+            .into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/roles",
+    request_body = CreateRoleRequest,
+    responses(
+        (status = 201, description = "Role created", body = RoleResponse),
+        (status = 400, description = "Invalid name or unknown permission", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Missing permission, or the role grants permissions the caller lacks", body = ErrorResponse),
+        (status = 409, description = "Role already exists", body = ErrorResponse),
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Admin"
+)]
+pub async fn create_role(
+    State(state): State<AppState>,
+    manager: RequirePermission<RolesManage>,
+    Json(payload): Json<CreateRoleRequest>,
+) -> impl IntoResponse {
+    match state
+        .uc
+        .auth
+        .create_role(
+            manager.user.user_id,
+            payload.name,
+            payload.description,
+            payload.permissions,
+        )
+        .await
+    {
+        Ok(role) => (
+            StatusCode::CREATED,
+            Json(RoleResponse::from(role)).into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/admin/roles/{role_id}/permissions",
+    params(
+        ("role_id" = i32, Path, description = "Role ID")
+    ),
+    request_body = SetRolePermissionsRequest,
+    responses(
+        (status = 200, description = "Role permissions replaced", body = RoleResponse),
+        (status = 400, description = "Unknown permission", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Missing permission, the built-in admin role, the caller's own role, or permissions the caller lacks", body = ErrorResponse),
+        (status = 404, description = "Role not found", body = ErrorResponse),
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Admin"
+)]
+pub async fn set_role_permissions(
+    State(state): State<AppState>,
+    Path(role_id): Path<i32>,
+    manager: RequirePermission<RolesManage>,
+    Json(payload): Json<SetRolePermissionsRequest>,
+) -> impl IntoResponse {
+    match state
+        .uc
+        .auth
+        .set_role_permissions(manager.user.user_id, role_id, payload.permissions)
+        .await
+    {
+        Ok(role) => (
+            StatusCode::OK,
+            Json(RoleResponse::from(role)).into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/permissions",
+    responses(
+        (status = 200, description = "List of permissions", body = Vec<PermissionResponse>),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Missing permission", body = ErrorResponse),
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Admin"
+)]
+pub async fn list_permissions(
+    State(state): State<AppState>,
+    _: RequirePermission<RolesRead>,
+) -> impl IntoResponse {
+    match state.uc.auth.list_permissions().await {
+        Ok(permissions) => (
+            StatusCode::OK,
+            Json(
+                permissions
+                    .into_iter()
+                    .map(PermissionResponse::from)
+                    .collect::<Vec<_>>(),
+            )
+            .into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
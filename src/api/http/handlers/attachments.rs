@@ -0,0 +1,316 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use axum::{
+    Extension, Json,
+    body::{Body, Bytes, to_bytes},
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use hyper::body::Frame;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::api::http::dto::{
+    AttachmentResponse, CreateUploadRequest, ErrorResponse, OffsetMismatchResponse,
+};
+use crate::api::http::middleware::AuthUser;
+use crate::api::http::state::AppState;
+use crate::usecase::{AttachmentDownload, AttachmentError};
+
+/// Header carrying the offset of an upload chunk.
+pub const UPLOAD_OFFSET_HEADER: &str = "Upload-Offset";
+
+#[utoipa::path(
+    post,
+    path = "/attachments",
+    request_body(content = Vec<u8>, content_type = "application/octet-stream", description = "The encrypted attachment"),
+    responses(
+        (status = 201, description = "Attachment stored", body = AttachmentResponse),
+        (status = 400, description = "Empty body or body shorter than Content-Length", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 411, description = "Missing Content-Length", body = ErrorResponse),
+        (status = 413, description = "Larger than a single request may carry, or over the caller's storage quota", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Attachments"
+)]
+pub async fn upload_attachment(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    headers: HeaderMap,
+    body: Body,
+) -> impl IntoResponse {
+    let data = match read_body(&headers, body, state.uc.attachments.max_chunk_size()).await {
+        Ok(data) => data,
+        Err(response) => return response,
+    };
+
+    match state.uc.attachments.upload(auth_user.user_id, data).await {
+        Ok(attachment) => (
+            StatusCode::CREATED,
+            Json(AttachmentResponse::from(attachment)).into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/attachments/uploads",
+    request_body = CreateUploadRequest,
+    responses(
+        (status = 201, description = "Resumable upload started", body = AttachmentResponse),
+        (status = 400, description = "Invalid size", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 413, description = "Attachment too large, or over the caller's storage quota", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Attachments"
+)]
+pub async fn create_upload(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Json(payload): Json<CreateUploadRequest>,
+) -> impl IntoResponse {
+    match state
+        .uc
+        .attachments
+        .create_upload(auth_user.user_id, payload.size)
+        .await
+    {
+        Ok(attachment) => (
+            StatusCode::CREATED,
+            Json(AttachmentResponse::from(attachment)).into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    patch,
+    path = "/attachments/uploads/{attachment_id}",
+    params(
+        ("attachment_id" = Uuid, Path, description = "Attachment ID"),
+        ("Upload-Offset" = i64, Header, description = "Offset of this chunk; must equal the bytes uploaded so far"),
+    ),
+    request_body(content = Vec<u8>, content_type = "application/octet-stream", description = "The next chunk"),
+    responses(
+        (status = 200, description = "Chunk stored", body = AttachmentResponse),
+        (status = 400, description = "Missing Upload-Offset, empty chunk, or chunk past the end", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "No such upload of the caller", body = ErrorResponse),
+        (status = 409, description = "Offset does not match the upload's progress", body = OffsetMismatchResponse),
+        (status = 411, description = "Missing Content-Length", body = ErrorResponse),
+        (status = 413, description = "Chunk too large", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Attachments"
+)]
+pub async fn upload_chunk(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(attachment_id): Path<Uuid>,
+    headers: HeaderMap,
+    body: Body,
+) -> impl IntoResponse {
+    let Some(offset) = headers
+        .get(UPLOAD_OFFSET_HEADER)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.trim().parse::<i64>().ok())
+    else {
+        return request_error(
+            StatusCode::BAD_REQUEST,
+            "INVALID_OFFSET",
+            "Upload-Offset header must be a byte offset",
+        );
+    };
+
+    let data = match read_body(&headers, body, state.uc.attachments.max_chunk_size()).await {
+        Ok(data) => data,
+        Err(response) => return response,
+    };
+
+    match state
+        .uc
+        .attachments
+        .upload_chunk(auth_user.user_id, attachment_id, offset, data)
+        .await
+    {
+        Ok(attachment) => (
+            StatusCode::OK,
+            Json(AttachmentResponse::from(attachment)).into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/attachments/uploads/{attachment_id}",
+    params(("attachment_id" = Uuid, Path, description = "Attachment ID")),
+    responses(
+        (status = 200, description = "Progress of the upload", body = AttachmentResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "No such upload of the caller", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Attachments"
+)]
+pub async fn get_upload(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(attachment_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match state
+        .uc
+        .attachments
+        .get_upload(auth_user.user_id, attachment_id)
+        .await
+    {
+        Ok(attachment) => (
+            StatusCode::OK,
+            Json(AttachmentResponse::from(attachment)).into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/attachments/{attachment_id}",
+    params(("attachment_id" = Uuid, Path, description = "Attachment ID")),
+    responses(
+        (status = 200, description = "The encrypted attachment", body = Vec<u8>, content_type = "application/octet-stream"),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "Not uploaded by the caller nor sent to one of their chats", body = ErrorResponse),
+        (status = 409, description = "Upload not complete", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Attachments"
+)]
+pub async fn download_attachment(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(attachment_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match state
+        .uc
+        .attachments
+        .download(auth_user.user_id, attachment_id)
+        .await
+    {
+        Ok(download) => (
+            StatusCode::OK,
+            (
+                [
+                    (header::CONTENT_TYPE, "application/octet-stream".to_string()),
+                    (header::CONTENT_LENGTH, download.size.to_string()),
+                ],
+                Body::new(ChunkBody::from(download)),
+            )
+                .into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+/// Response body fed by a download's chunks as they are read from storage.
+/// A failed read aborts the response, which the client sees as a short body.
+struct ChunkBody {
+    chunks: mpsc::Receiver<Result<Vec<u8>, AttachmentError>>,
+}
+
+impl From<AttachmentDownload> for ChunkBody {
+    fn from(download: AttachmentDownload) -> Self {
+        Self {
+            chunks: download.chunks,
+        }
+    }
+}
+
+impl hyper::body::Body for ChunkBody {
+    type Data = Bytes;
+    type Error = AttachmentError;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, AttachmentError>>> {
+        self.chunks
+            .poll_recv(cx)
+            .map(|chunk| chunk.map(|data| data.map(|data| Frame::data(Bytes::from(data)))))
+    }
+}
+
+/// Reads a raw request body of the declared `Content-Length`. Oversized
+/// bodies are refused from the header alone, before any of them is read.
+async fn read_body(
+    headers: &HeaderMap,
+    body: Body,
+    limit: usize,
+) -> Result<Vec<u8>, (StatusCode, Response)> {
+    let Some(declared) = headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.parse::<usize>().ok())
+    else {
+        return Err(request_error(
+            StatusCode::LENGTH_REQUIRED,
+            "LENGTH_REQUIRED",
+            "Content-Length header required",
+        ));
+    };
+
+    if declared > limit {
+        return Err(error_response(AttachmentError::ChunkTooLarge(limit)));
+    }
+
+    match to_bytes(body, declared).await {
+        Ok(data) if data.len() == declared => Ok(data.to_vec()),
+        _ => Err(request_error(
+            StatusCode::BAD_REQUEST,
+            "CONTENT_LENGTH_MISMATCH",
+            "Body does not match Content-Length",
+        )),
+    }
+}
+
+fn request_error(status: StatusCode, code: &str, error: &str) -> (StatusCode, Response) {
+    (
+        status,
+        Json(ErrorResponse {
+            error: error.to_string(),
+            code: code.to_string(),
+        })
+        .into_response(),
+    )
+}
+
+fn error_response(err: AttachmentError) -> (StatusCode, Response) {
+    let (status, code) = match &err {
+        AttachmentError::InvalidSize(_) => (StatusCode::BAD_REQUEST, "INVALID_SIZE"),
+        AttachmentError::TooLarge(_) => (StatusCode::PAYLOAD_TOO_LARGE, "ATTACHMENT_TOO_LARGE"),
+        AttachmentError::QuotaExceeded(_) => (StatusCode::PAYLOAD_TOO_LARGE, "QUOTA_EXCEEDED"),
+        AttachmentError::ChunkTooLarge(_) => (StatusCode::PAYLOAD_TOO_LARGE, "CHUNK_TOO_LARGE"),
+        AttachmentError::InvalidChunk(_) => (StatusCode::BAD_REQUEST, "INVALID_CHUNK"),
+        AttachmentError::NotFound => (StatusCode::NOT_FOUND, "ATTACHMENT_NOT_FOUND"),
+        AttachmentError::OffsetMismatch { expected } => {
+            return (
+                StatusCode::CONFLICT,
+                Json(OffsetMismatchResponse {
+                    error: err.to_string(),
+                    code: "OFFSET_MISMATCH".to_string(),
+                    expected_offset: *expected,
+                })
+                .into_response(),
+            );
+        }
+        AttachmentError::Incomplete => (StatusCode::CONFLICT, "UPLOAD_INCOMPLETE"),
+        AttachmentError::Unavailable => (StatusCode::SERVICE_UNAVAILABLE, "SERVICE_UNAVAILABLE"),
+        AttachmentError::Internal(_) => (StatusCode::INTERNAL_SERVER_ERROR, "INTERNAL_ERROR"),
+    };
+
+    request_error(status, code, &err.to_string())
+}
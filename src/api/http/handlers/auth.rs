@@ -1,7 +1,9 @@
+use std::net::{IpAddr, SocketAddr};
+
 use axum::{
     Extension, Json,
-    extract::{Path, State},
-    http::StatusCode,
+    extract::{ConnectInfo, Path, State},
+    http::{HeaderMap, HeaderValue, StatusCode, header},
     response::IntoResponse,
 };
 use uuid::Uuid;
@@ -31,7 +33,8 @@ pub async fn register(
     match state
         .uc
         .auth
-        .create_user(payload.username, payload.password, payload.role_id)
+        .create_user(payload.username, payload.password)
+        .await
     {
         Ok(user) => (
             StatusCode::CREATED,
@@ -49,14 +52,25 @@ pub async fn register(
         (status = 200, description = "Login successful", body = AuthResponse),
         (status = 401, description = "Invalid credentials", body = ErrorResponse),
         (status = 403, description = "User is deactivated", body = ErrorResponse),
+        (status = 429, description = "Too many failed login attempts", body = ErrorResponse),
     ),
     tag = "Authentication"
 )]
 pub async fn login(
     State(state): State<AppState>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
     Json(payload): Json<LoginRequest>,
 ) -> impl IntoResponse {
-    match state.uc.auth.login(payload.username, payload.password) {
+    let peer_ip = connect_info.map(|ConnectInfo(addr)| addr.ip());
+    let client_ip = client_ip(&state, &headers).or(peer_ip);
+
+    match state
+        .uc
+        .auth
+        .login(payload.username, payload.password, client_ip)
+        .await
+    {
         Ok(auth_response) => (
             StatusCode::OK,
             Json(AuthResponse {
@@ -69,6 +83,18 @@ pub async fn login(
     }
 }
 
+/// The client address from the proxy header, if one is configured. Only the
+/// last entry counts: it is the one the proxy itself added, while anything
+/// before it came from the client.
+fn client_ip(state: &AppState, headers: &HeaderMap) -> Option<IpAddr> {
+    let value = headers
+        .get(state.client_ip_header.as_ref()?)?
+        .to_str()
+        .ok()?;
+
+    value.rsplit(',').next()?.trim().parse().ok()
+}
+
 #[utoipa::path(
     get,
     path = "/auth/me",
@@ -86,7 +112,7 @@ pub async fn me(
     State(state): State<AppState>,
     Extension(auth_user): Extension<AuthUser>,
 ) -> impl IntoResponse {
-    match state.uc.auth.get_user_by_id(auth_user.user_id) {
+    match state.uc.auth.get_user_by_id(auth_user.user_id).await {
         Ok(user_info) => (
             StatusCode::OK,
             Json(UserInfoResponse::from(user_info)).into_response(),
@@ -116,7 +142,7 @@ pub async fn get_user_by_id(
     Path(user_id): Path<Uuid>,
     Extension(_auth_user): Extension<AuthUser>,
 ) -> impl IntoResponse {
-    match state.uc.auth.get_user_by_id(user_id) {
+    match state.uc.auth.get_user_by_id(user_id).await {
         Ok(user_info) => (
             StatusCode::OK,
             Json(UserInfoResponse::from(user_info)).into_response(),
@@ -125,29 +151,53 @@ pub async fn get_user_by_id(
     }
 }
 
-fn error_response(err: AuthError) -> (StatusCode, axum::response::Response) {
+#[utoipa::path(
+    get,
+    path = "/.well-known/jwks.json",
+    responses(
+        (status = 200, description = "JSON Web Key Set with the public keys that verify access tokens"),
+    ),
+    tag = "Authentication"
+)]
+pub async fn jwks(State(state): State<AppState>) -> impl IntoResponse {
+    (StatusCode::OK, Json(state.uc.auth.jwks().clone()))
+}
+
+pub(super) fn error_response(err: AuthError) -> (StatusCode, axum::response::Response) {
     let (status, code) = match &err {
         AuthError::UsernameExists => (StatusCode::CONFLICT, "USERNAME_EXISTS"),
         AuthError::InvalidUsername(_) => (StatusCode::BAD_REQUEST, "INVALID_USERNAME"),
         AuthError::InvalidPassword(_) => (StatusCode::BAD_REQUEST, "INVALID_PASSWORD"),
         AuthError::UserNotFound => (StatusCode::NOT_FOUND, "USER_NOT_FOUND"),
+        AuthError::RoleNotFound => (StatusCode::NOT_FOUND, "ROLE_NOT_FOUND"),
+        AuthError::RoleExists => (StatusCode::CONFLICT, "ROLE_EXISTS"),
+        AuthError::InvalidRoleName(_) => (StatusCode::BAD_REQUEST, "INVALID_ROLE_NAME"),
+        AuthError::UnknownPermission(_) => (StatusCode::BAD_REQUEST, "UNKNOWN_PERMISSION"),
+        AuthError::Forbidden => (StatusCode::FORBIDDEN, "FORBIDDEN"),
         AuthError::InvalidCredentials => (StatusCode::UNAUTHORIZED, "INVALID_CREDENTIALS"),
         AuthError::UserDeactivated => (StatusCode::FORBIDDEN, "USER_DEACTIVATED"),
+        AuthError::TooManyAttempts { .. } => (StatusCode::TOO_MANY_REQUESTS, "TOO_MANY_ATTEMPTS"),
         AuthError::TokenGenerationFailed(_) => {
             (StatusCode::INTERNAL_SERVER_ERROR, "TOKEN_GENERATION_FAILED")
         }
         AuthError::TokenValidationFailed(_) => {
             (StatusCode::UNAUTHORIZED, "TOKEN_VALIDATION_FAILED")
         }
+        AuthError::Unavailable => (StatusCode::SERVICE_UNAVAILABLE, "SERVICE_UNAVAILABLE"),
         AuthError::Internal(_) => (StatusCode::INTERNAL_SERVER_ERROR, "INTERNAL_ERROR"),
     };
 
-    (
-        status,
-        Json(ErrorResponse {
-            error: err.to_string(),
-            code: code.to_string(),
-        })
-        .into_response(),
-    )
+    let mut response = Json(ErrorResponse {
+        error: err.to_string(),
+        code: code.to_string(),
+    })
+    .into_response();
+
+    if let AuthError::TooManyAttempts { retry_after } = err {
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+    }
+
+    (status, response)
 }
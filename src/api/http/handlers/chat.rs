@@ -1,18 +1,29 @@
+use std::collections::HashMap;
+
 use axum::{
     Extension, Json,
     extract::{Path, Query, State},
     http::StatusCode,
     response::IntoResponse,
 };
+use chrono::{DateTime, NaiveDateTime};
 use uuid::Uuid;
 
 use crate::api::http::dto::{
-    ChatMemberResponse, ChatResponse, CreateChatRequest, ErrorResponse, GetMessagesQuery,
-    InviteUserRequest, MessageResponse, SendMessageRequest,
+    ChatMemberResponse, ChatResponse, CreateChatRequest, CreatePollRequest, DeviceMismatchResponse,
+    DeviceResponse, DistributeSenderKeyRequest, ErrorResponse, ForwardMessageRequest,
+    GetMembersQuery, GetMentionsQuery, GetMessagesQuery, GetRekeysQuery, GetScheduledMessagesQuery,
+    GetSenderKeysQuery, InviteUserRequest, MarkReadRequest, MentionResponse, MessageResponse,
+    PinResponse, PollResponse, ReadStateResponse, RekeyResponse, RenameChatRequest,
+    ScheduledMessageResponse, SendMessageRequest, SenderKeyBundleResponse, SenderKeyMessage,
+    SetForwardingRequest, SetMessageTtlRequest, SetMuteRequest, SetPollsRequest,
+    StaleEpochResponse, VoteRequest,
 };
-use crate::api::http::middleware::AuthUser;
+use crate::api::http::middleware::{AuthUser, CurrentDevice};
 use crate::api::http::state::AppState;
-use crate::usecase::ChatError;
+use crate::repository::chat::MemberFilter;
+use crate::usecase::auth::permissions;
+use crate::usecase::{ChatError, MessageBody, MessageOptions, PollDraft};
 
 #[utoipa::path(
     post,
@@ -31,7 +42,12 @@ pub async fn create_chat(
     Extension(auth_user): Extension<AuthUser>,
     Json(payload): Json<CreateChatRequest>,
 ) -> impl IntoResponse {
-    match state.uc.chat.create_chat(payload.name, auth_user.user_id) {
+    match state
+        .uc
+        .chat
+        .create_chat(payload.name, auth_user.user_id)
+        .await
+    {
         Ok(chat) => (
             StatusCode::CREATED,
             Json(ChatResponse::from(chat)).into_response(),
@@ -54,7 +70,7 @@ pub async fn get_my_chats(
     State(state): State<AppState>,
     Extension(auth_user): Extension<AuthUser>,
 ) -> impl IntoResponse {
-    match state.uc.chat.get_user_chats(auth_user.user_id) {
+    match state.uc.chat.get_user_chats(auth_user.user_id).await {
         Ok(chats) => (
             StatusCode::OK,
             Json(
@@ -87,7 +103,7 @@ pub async fn get_chat(
     Path(chat_id): Path<Uuid>,
     Extension(auth_user): Extension<AuthUser>,
 ) -> impl IntoResponse {
-    match state.uc.chat.get_chat(chat_id, auth_user.user_id) {
+    match state.uc.chat.get_chat(chat_id, auth_user.user_id).await {
         Ok(chat) => (
             StatusCode::OK,
             Json(ChatResponse::from(chat)).into_response(),
@@ -121,6 +137,7 @@ pub async fn invite_user(
         .uc
         .chat
         .invite_user_by_username(chat_id, payload.username, auth_user.user_id)
+        .await
     {
         Ok(()) => (
             StatusCode::OK,
@@ -133,7 +150,13 @@ pub async fn invite_user(
 #[utoipa::path(
     get,
     path = "/chats/{chat_id}/members",
-    params(("chat_id" = Uuid, Path, description = "Chat ID")),
+    params(
+        ("chat_id" = Uuid, Path, description = "Chat ID"),
+        ("role_id" = Option<i32>, Query, description = "Only members with this role"),
+        ("username_prefix" = Option<String>, Query, description = "Case-insensitive username prefix"),
+        ("limit" = Option<i64>, Query, description = "Number of members to return, at most 500"),
+        ("offset" = Option<i64>, Query, description = "Offset for pagination"),
+    ),
     responses(
         (status = 200, description = "List of chat members", body = Vec<ChatMemberResponse>),
         (status = 401, description = "Unauthorized", body = ErrorResponse),
@@ -145,9 +168,26 @@ pub async fn invite_user(
 pub async fn get_chat_members(
     State(state): State<AppState>,
     Path(chat_id): Path<Uuid>,
+    Query(query): Query<GetMembersQuery>,
     Extension(auth_user): Extension<AuthUser>,
 ) -> impl IntoResponse {
-    match state.uc.chat.get_chat_members(chat_id, auth_user.user_id) {
+    let filter = MemberFilter {
+        role_id: query.role_id,
+        username_prefix: query.username_prefix,
+    };
+
+    match state
+        .uc
+        .chat
+        .get_chat_members(
+            chat_id,
+            auth_user.user_id,
+            filter,
+            query.limit,
+            query.offset,
+        )
+        .await
+    {
         Ok(members) => (
             StatusCode::OK,
             Json(
@@ -162,15 +202,59 @@ pub async fn get_chat_members(
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/chats/{chat_id}/devices",
+    params(("chat_id" = Uuid, Path, description = "Chat ID")),
+    responses(
+        (status = 200, description = "Devices of every chat member", body = Vec<DeviceResponse>),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Not a member", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Chats"
+)]
+pub async fn get_chat_devices(
+    State(state): State<AppState>,
+    Path(chat_id): Path<Uuid>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> impl IntoResponse {
+    match state
+        .uc
+        .chat
+        .get_chat_devices(chat_id, auth_user.user_id)
+        .await
+    {
+        Ok(devices) => (
+            StatusCode::OK,
+            Json(
+                devices
+                    .into_iter()
+                    .map(DeviceResponse::from)
+                    .collect::<Vec<_>>(),
+            )
+            .into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
 #[utoipa::path(
     post,
     path = "/chats/{chat_id}/messages",
-    params(("chat_id" = Uuid, Path, description = "Chat ID")),
+    params(
+        ("chat_id" = Uuid, Path, description = "Chat ID"),
+        ("X-Device-Id" = Uuid, Header, description = "Sending device"),
+    ),
     request_body = SendMessageRequest,
     responses(
         (status = 201, description = "Message sent successfully", body = MessageResponse),
+        (status = 202, description = "Message scheduled for `send_at`", body = ScheduledMessageResponse),
+        (status = 400, description = "Missing X-Device-Id, or an unusable `send_at`", body = ErrorResponse),
         (status = 401, description = "Unauthorized", body = ErrorResponse),
-        (status = 403, description = "Not a member", body = ErrorResponse),
+        (status = 403, description = "Not a member, or `mention_all` without being the chat creator or a moderator", body = ErrorResponse),
+        (status = 400, description = "Both envelopes and a sender-key message given, a view-once sender-key message, an unusable attachment, or a mention of a non-member", body = ErrorResponse),
+        (status = 409, description = "Unknown sending device, envelopes out of date with the chat's devices, or a stale epoch", body = DeviceMismatchResponse),
     ),
     security(("bearer_auth" = [])),
     tag = "Messages"
@@ -179,12 +263,158 @@ pub async fn send_message(
     State(state): State<AppState>,
     Path(chat_id): Path<Uuid>,
     Extension(auth_user): Extension<AuthUser>,
+    CurrentDevice(device_id): CurrentDevice,
     Json(payload): Json<SendMessageRequest>,
 ) -> impl IntoResponse {
+    let send_at = match payload.send_at.as_deref().map(parse_send_at).transpose() {
+        Ok(send_at) => send_at,
+        Err(e) => return error_response(e),
+    };
+    let body = match message_body(payload.envelopes, payload.sender_key) {
+        Ok(body) => body,
+        Err(e) => return error_response(e),
+    };
+    let options = MessageOptions {
+        attachment_ids: payload.attachment_ids,
+        view_once: payload.view_once,
+        mentions: payload.mentions,
+        mention_all: payload.mention_all,
+    };
+
+    if let Some(send_at) = send_at {
+        return match state
+            .uc
+            .chat
+            .schedule_message(
+                chat_id,
+                auth_user.user_id,
+                device_id,
+                send_at,
+                body,
+                options,
+            )
+            .await
+        {
+            Ok(scheduled) => (
+                StatusCode::ACCEPTED,
+                Json(ScheduledMessageResponse::from(scheduled)).into_response(),
+            ),
+            Err(e) => error_response(e),
+        };
+    }
+
+    match state
+        .uc
+        .chat
+        .send_message(chat_id, auth_user.user_id, device_id, body, options)
+        .await
+    {
+        Ok(message) => (
+            StatusCode::CREATED,
+            Json(MessageResponse::from(message)).into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/chats/{chat_id}/messages/forward",
+    params(
+        ("chat_id" = Uuid, Path, description = "Chat to forward into"),
+        ("X-Device-Id" = Uuid, Header, description = "Sending device"),
+    ),
+    request_body = ForwardMessageRequest,
+    responses(
+        (status = 201, description = "Message forwarded with its provenance", body = MessageResponse),
+        (status = 400, description = "Missing X-Device-Id, both envelopes and a sender-key message given, a notice or view-once source, or an unusable attachment", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Not a member of either chat, or the source chat does not allow forwarding", body = ErrorResponse),
+        (status = 404, description = "Source message not found", body = ErrorResponse),
+        (status = 409, description = "Unknown sending device, envelopes out of date with the chat's devices, or a stale epoch", body = DeviceMismatchResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Messages"
+)]
+pub async fn forward_message(
+    State(state): State<AppState>,
+    Path(chat_id): Path<Uuid>,
+    Extension(auth_user): Extension<AuthUser>,
+    CurrentDevice(device_id): CurrentDevice,
+    Json(payload): Json<ForwardMessageRequest>,
+) -> impl IntoResponse {
+    let body = match message_body(payload.envelopes, payload.sender_key) {
+        Ok(body) => body,
+        Err(e) => return error_response(e),
+    };
+    let options = MessageOptions {
+        attachment_ids: payload.attachment_ids,
+        ..MessageOptions::default()
+    };
+
+    match state
+        .uc
+        .chat
+        .forward_message(
+            chat_id,
+            auth_user.user_id,
+            device_id,
+            payload.message_id,
+            body,
+            options,
+        )
+        .await
+    {
+        Ok(message) => (
+            StatusCode::CREATED,
+            Json(MessageResponse::from(message)).into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/chats/{chat_id}/polls",
+    params(("chat_id" = Uuid, Path, description = "Chat ID")),
+    request_body = CreatePollRequest,
+    responses(
+        (status = 201, description = "Poll posted as a `poll` message", body = MessageResponse),
+        (status = 400, description = "Invalid question, options or close time", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Not a member, or the chat does not allow polls", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Messages"
+)]
+pub async fn create_poll(
+    State(state): State<AppState>,
+    Path(chat_id): Path<Uuid>,
+    Extension(auth_user): Extension<AuthUser>,
+    Json(payload): Json<CreatePollRequest>,
+) -> impl IntoResponse {
+    let closes_at = match payload
+        .closes_at
+        .as_deref()
+        .map(parse_closes_at)
+        .transpose()
+    {
+        Ok(closes_at) => closes_at,
+        Err(e) => return error_response(e),
+    };
+    let draft = PollDraft {
+        question: payload.question,
+        options: payload.options,
+        multiple_choice: payload.multiple_choice,
+        anonymous: payload.anonymous,
+        closes_at,
+    };
+
     match state
         .uc
         .chat
-        .send_message(chat_id, auth_user.user_id, payload.encrypted_content)
+        .create_poll(chat_id, auth_user.user_id, draft)
+        .await
     {
         Ok(message) => (
             StatusCode::CREATED,
@@ -194,6 +424,45 @@ pub async fn send_message(
     }
 }
 
+#[utoipa::path(
+    put,
+    path = "/chats/{chat_id}/polls/{message_id}/votes",
+    params(
+        ("chat_id" = Uuid, Path, description = "Chat ID"),
+        ("message_id" = Uuid, Path, description = "The poll message"),
+    ),
+    request_body = VoteRequest,
+    responses(
+        (status = 200, description = "Vote recorded; returns the current tallies", body = PollResponse),
+        (status = 400, description = "Unknown option, or several options in a single-choice poll", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Not a member, or the chat does not allow polls", body = ErrorResponse),
+        (status = 404, description = "Poll not found", body = ErrorResponse),
+        (status = 409, description = "The poll is closed", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Messages"
+)]
+pub async fn vote(
+    State(state): State<AppState>,
+    Path((chat_id, message_id)): Path<(Uuid, Uuid)>,
+    Extension(auth_user): Extension<AuthUser>,
+    Json(payload): Json<VoteRequest>,
+) -> impl IntoResponse {
+    match state
+        .uc
+        .chat
+        .vote(chat_id, auth_user.user_id, message_id, payload.options)
+        .await
+    {
+        Ok(poll) => (
+            StatusCode::OK,
+            Json(PollResponse::from(poll)).into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
 #[utoipa::path(
     get,
     path = "/chats/{chat_id}/messages",
@@ -201,11 +470,14 @@ pub async fn send_message(
         ("chat_id" = Uuid, Path, description = "Chat ID"),
         ("limit" = Option<i64>, Query, description = "Number of messages to return"),
         ("offset" = Option<i64>, Query, description = "Offset for pagination"),
+        ("X-Device-Id" = Uuid, Header, description = "Reading device"),
     ),
     responses(
-        (status = 200, description = "List of messages", body = Vec<MessageResponse>),
+        (status = 200, description = "Messages with the envelope for the reading device", body = Vec<MessageResponse>),
+        (status = 400, description = "Missing X-Device-Id", body = ErrorResponse),
         (status = 401, description = "Unauthorized", body = ErrorResponse),
         (status = 403, description = "Not a member", body = ErrorResponse),
+        (status = 409, description = "Unknown device", body = ErrorResponse),
     ),
     security(("bearer_auth" = [])),
     tag = "Messages"
@@ -215,11 +487,19 @@ pub async fn get_messages(
     Path(chat_id): Path<Uuid>,
     Query(query): Query<GetMessagesQuery>,
     Extension(auth_user): Extension<AuthUser>,
+    CurrentDevice(device_id): CurrentDevice,
 ) -> impl IntoResponse {
     match state
         .uc
         .chat
-        .get_messages(chat_id, auth_user.user_id, query.limit, query.offset)
+        .get_messages(
+            chat_id,
+            auth_user.user_id,
+            device_id,
+            query.limit,
+            query.offset,
+        )
+        .await
     {
         Ok(messages) => (
             StatusCode::OK,
@@ -235,13 +515,772 @@ pub async fn get_messages(
     }
 }
 
+#[utoipa::path(
+    post,
+    path = "/chats/{chat_id}/leave",
+    params(("chat_id" = Uuid, Path, description = "Chat ID")),
+    responses(
+        (status = 200, description = "Left the chat; remaining members rekey"),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Not a member", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Chats"
+)]
+pub async fn leave_chat(
+    State(state): State<AppState>,
+    Path(chat_id): Path<Uuid>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> impl IntoResponse {
+    match state.uc.chat.leave_chat(chat_id, auth_user.user_id).await {
+        Ok(()) => (
+            StatusCode::OK,
+            Json(serde_json::json!({"message": "Left the chat"})).into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/chats/{chat_id}/members/{user_id}",
+    params(
+        ("chat_id" = Uuid, Path, description = "Chat ID"),
+        ("user_id" = Uuid, Path, description = "Member to remove"),
+    ),
+    responses(
+        (status = 200, description = "Member removed; remaining members rekey"),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Neither the chat creator nor a moderator, or removing the creator", body = ErrorResponse),
+        (status = 404, description = "Chat or member not found", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Chats"
+)]
+pub async fn remove_member(
+    State(state): State<AppState>,
+    Path((chat_id, user_id)): Path<(Uuid, Uuid)>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> impl IntoResponse {
+    let can_moderate = auth_user.has_permission(permissions::CHATS_MODERATE);
+
+    match state
+        .uc
+        .chat
+        .remove_member(chat_id, auth_user.user_id, can_moderate, user_id)
+        .await
+    {
+        Ok(()) => (
+            StatusCode::OK,
+            Json(serde_json::json!({"message": "Member removed"})).into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/chats/{chat_id}/name",
+    params(("chat_id" = Uuid, Path, description = "Chat ID")),
+    request_body = RenameChatRequest,
+    responses(
+        (status = 200, description = "Chat renamed and the change announced in the chat", body = ChatResponse),
+        (status = 400, description = "Invalid chat name", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Neither the chat creator nor a moderator", body = ErrorResponse),
+        (status = 404, description = "Chat not found", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Chats"
+)]
+pub async fn rename_chat(
+    State(state): State<AppState>,
+    Path(chat_id): Path<Uuid>,
+    Extension(auth_user): Extension<AuthUser>,
+    Json(payload): Json<RenameChatRequest>,
+) -> impl IntoResponse {
+    let can_moderate = auth_user.has_permission(permissions::CHATS_MODERATE);
+
+    match state
+        .uc
+        .chat
+        .rename_chat(chat_id, auth_user.user_id, can_moderate, payload.name)
+        .await
+    {
+        Ok(chat) => (
+            StatusCode::OK,
+            Json(ChatResponse::from(chat)).into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/chats/{chat_id}/message-ttl",
+    params(("chat_id" = Uuid, Path, description = "Chat ID")),
+    request_body = SetMessageTtlRequest,
+    responses(
+        (status = 200, description = "Timer set for new messages and announced in the chat", body = ChatResponse),
+        (status = 400, description = "Timer out of range", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Neither the chat creator nor a moderator", body = ErrorResponse),
+        (status = 404, description = "Chat not found", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Chats"
+)]
+pub async fn set_message_ttl(
+    State(state): State<AppState>,
+    Path(chat_id): Path<Uuid>,
+    Extension(auth_user): Extension<AuthUser>,
+    Json(payload): Json<SetMessageTtlRequest>,
+) -> impl IntoResponse {
+    let can_moderate = auth_user.has_permission(permissions::CHATS_MODERATE);
+
+    match state
+        .uc
+        .chat
+        .set_message_ttl(
+            chat_id,
+            auth_user.user_id,
+            can_moderate,
+            payload.ttl_seconds,
+        )
+        .await
+    {
+        Ok(chat) => (
+            StatusCode::OK,
+            Json(ChatResponse::from(chat)).into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/chats/{chat_id}/forwarding",
+    params(("chat_id" = Uuid, Path, description = "Chat ID")),
+    request_body = SetForwardingRequest,
+    responses(
+        (status = 200, description = "Forwarding setting changed and announced in the chat", body = ChatResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Neither the chat creator nor a moderator", body = ErrorResponse),
+        (status = 404, description = "Chat not found", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Chats"
+)]
+pub async fn set_allow_forwarding(
+    State(state): State<AppState>,
+    Path(chat_id): Path<Uuid>,
+    Extension(auth_user): Extension<AuthUser>,
+    Json(payload): Json<SetForwardingRequest>,
+) -> impl IntoResponse {
+    let can_moderate = auth_user.has_permission(permissions::CHATS_MODERATE);
+
+    match state
+        .uc
+        .chat
+        .set_allow_forwarding(
+            chat_id,
+            auth_user.user_id,
+            can_moderate,
+            payload.allow_forwarding,
+        )
+        .await
+    {
+        Ok(chat) => (
+            StatusCode::OK,
+            Json(ChatResponse::from(chat)).into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/chats/{chat_id}/allow-polls",
+    params(("chat_id" = Uuid, Path, description = "Chat ID")),
+    request_body = SetPollsRequest,
+    responses(
+        (status = 200, description = "Poll setting changed and announced in the chat", body = ChatResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Neither the chat creator nor a moderator", body = ErrorResponse),
+        (status = 404, description = "Chat not found", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Chats"
+)]
+pub async fn set_allow_polls(
+    State(state): State<AppState>,
+    Path(chat_id): Path<Uuid>,
+    Extension(auth_user): Extension<AuthUser>,
+    Json(payload): Json<SetPollsRequest>,
+) -> impl IntoResponse {
+    let can_moderate = auth_user.has_permission(permissions::CHATS_MODERATE);
+
+    match state
+        .uc
+        .chat
+        .set_allow_polls(
+            chat_id,
+            auth_user.user_id,
+            can_moderate,
+            payload.allow_polls,
+        )
+        .await
+    {
+        Ok(chat) => (
+            StatusCode::OK,
+            Json(ChatResponse::from(chat)).into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/chats/{chat_id}/read",
+    params(("chat_id" = Uuid, Path, description = "Chat ID")),
+    request_body = MarkReadRequest,
+    responses(
+        (status = 200, description = "Read marker moved up to the message", body = ReadStateResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Not a member", body = ErrorResponse),
+        (status = 404, description = "No such message in the chat", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Messages"
+)]
+pub async fn mark_read(
+    State(state): State<AppState>,
+    Path(chat_id): Path<Uuid>,
+    Extension(auth_user): Extension<AuthUser>,
+    Json(payload): Json<MarkReadRequest>,
+) -> impl IntoResponse {
+    match state
+        .uc
+        .chat
+        .mark_read(chat_id, auth_user.user_id, payload.message_id)
+        .await
+    {
+        Ok(read_state) => (
+            StatusCode::OK,
+            Json(ReadStateResponse::from(read_state)).into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/chats/{chat_id}/mute",
+    params(("chat_id" = Uuid, Path, description = "Chat ID")),
+    request_body = SetMuteRequest,
+    responses(
+        (status = 200, description = "Chat muted or unmuted for the caller", body = ReadStateResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Not a member", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Chats"
+)]
+pub async fn set_muted(
+    State(state): State<AppState>,
+    Path(chat_id): Path<Uuid>,
+    Extension(auth_user): Extension<AuthUser>,
+    Json(payload): Json<SetMuteRequest>,
+) -> impl IntoResponse {
+    match state
+        .uc
+        .chat
+        .set_muted(chat_id, auth_user.user_id, payload.muted)
+        .await
+    {
+        Ok(read_state) => (
+            StatusCode::OK,
+            Json(ReadStateResponse::from(read_state)).into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/mentions",
+    params(
+        ("limit" = Option<i64>, Query, description = "Number of mentions to return, at most 200"),
+        ("offset" = Option<i64>, Query, description = "Offset for pagination"),
+    ),
+    responses(
+        (status = 200, description = "Messages mentioning the caller across their chats, newest first", body = Vec<MentionResponse>),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Messages"
+)]
+pub async fn get_mentions(
+    State(state): State<AppState>,
+    Query(query): Query<GetMentionsQuery>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> impl IntoResponse {
+    match state
+        .uc
+        .chat
+        .get_mentions(auth_user.user_id, query.limit, query.offset)
+        .await
+    {
+        Ok(mentions) => (
+            StatusCode::OK,
+            Json(
+                mentions
+                    .into_iter()
+                    .map(MentionResponse::from)
+                    .collect::<Vec<_>>(),
+            )
+            .into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/chats/{chat_id}/pins",
+    params(("chat_id" = Uuid, Path, description = "Chat ID")),
+    responses(
+        (status = 200, description = "Pinned messages, newest first", body = Vec<PinResponse>),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Not a member", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Chats"
+)]
+pub async fn get_pins(
+    State(state): State<AppState>,
+    Path(chat_id): Path<Uuid>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> impl IntoResponse {
+    match state.uc.chat.get_pins(chat_id, auth_user.user_id).await {
+        Ok(pins) => (
+            StatusCode::OK,
+            Json(pins.into_iter().map(PinResponse::from).collect::<Vec<_>>()).into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/chats/{chat_id}/pins/{message_id}",
+    params(
+        ("chat_id" = Uuid, Path, description = "Chat ID"),
+        ("message_id" = Uuid, Path, description = "Message to pin"),
+    ),
+    responses(
+        (status = 200, description = "Message pinned and the pin announced in the chat; pinning it again changes nothing", body = PinResponse),
+        (status = 400, description = "A server notice or view-once message", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Neither the chat creator nor a moderator", body = ErrorResponse),
+        (status = 404, description = "Chat or message not found", body = ErrorResponse),
+        (status = 409, description = "The chat has 50 pins already", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Chats"
+)]
+pub async fn pin_message(
+    State(state): State<AppState>,
+    Path((chat_id, message_id)): Path<(Uuid, Uuid)>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> impl IntoResponse {
+    let can_moderate = auth_user.has_permission(permissions::CHATS_MODERATE);
+
+    match state
+        .uc
+        .chat
+        .pin_message(chat_id, auth_user.user_id, can_moderate, message_id)
+        .await
+    {
+        Ok(pin) => (StatusCode::OK, Json(PinResponse::from(pin)).into_response()),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/chats/{chat_id}/pins/{message_id}",
+    params(
+        ("chat_id" = Uuid, Path, description = "Chat ID"),
+        ("message_id" = Uuid, Path, description = "Message to unpin"),
+    ),
+    responses(
+        (status = 200, description = "Message unpinned and the change announced in the chat"),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Neither the chat creator nor a moderator", body = ErrorResponse),
+        (status = 404, description = "Chat not found or message not pinned", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Chats"
+)]
+pub async fn unpin_message(
+    State(state): State<AppState>,
+    Path((chat_id, message_id)): Path<(Uuid, Uuid)>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> impl IntoResponse {
+    let can_moderate = auth_user.has_permission(permissions::CHATS_MODERATE);
+
+    match state
+        .uc
+        .chat
+        .unpin_message(chat_id, auth_user.user_id, can_moderate, message_id)
+        .await
+    {
+        Ok(()) => (
+            StatusCode::OK,
+            Json(serde_json::json!({"message": "Message unpinned"})).into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/chats/{chat_id}/rekeys",
+    params(
+        ("chat_id" = Uuid, Path, description = "Chat ID"),
+        ("since_epoch" = Option<i32>, Query, description = "Only epochs after this one"),
+    ),
+    responses(
+        (status = 200, description = "Membership changes that require a new sender key", body = Vec<RekeyResponse>),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Not a member", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Sender keys"
+)]
+pub async fn get_rekeys(
+    State(state): State<AppState>,
+    Path(chat_id): Path<Uuid>,
+    Query(query): Query<GetRekeysQuery>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> impl IntoResponse {
+    match state
+        .uc
+        .chat
+        .get_rekeys(chat_id, auth_user.user_id, query.since_epoch)
+        .await
+    {
+        Ok(rekeys) => (
+            StatusCode::OK,
+            Json(
+                rekeys
+                    .into_iter()
+                    .map(RekeyResponse::from)
+                    .collect::<Vec<_>>(),
+            )
+            .into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/chats/{chat_id}/sender-keys",
+    params(
+        ("chat_id" = Uuid, Path, description = "Chat ID"),
+        ("X-Device-Id" = Uuid, Header, description = "Device whose sender key this is"),
+    ),
+    request_body = DistributeSenderKeyRequest,
+    responses(
+        (status = 200, description = "Sender key stored for every recipient device"),
+        (status = 400, description = "Missing X-Device-Id", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Not a member", body = ErrorResponse),
+        (status = 409, description = "Unknown device, recipients out of date with the chat's devices, or a stale epoch", body = StaleEpochResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Sender keys"
+)]
+pub async fn distribute_sender_key(
+    State(state): State<AppState>,
+    Path(chat_id): Path<Uuid>,
+    Extension(auth_user): Extension<AuthUser>,
+    CurrentDevice(device_id): CurrentDevice,
+    Json(payload): Json<DistributeSenderKeyRequest>,
+) -> impl IntoResponse {
+    match state
+        .uc
+        .chat
+        .distribute_sender_key(
+            chat_id,
+            auth_user.user_id,
+            device_id,
+            payload.epoch,
+            payload.sender_keys,
+        )
+        .await
+    {
+        Ok(()) => (
+            StatusCode::OK,
+            Json(serde_json::json!({"message": "Sender key distributed"})).into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/chats/{chat_id}/sender-keys",
+    params(
+        ("chat_id" = Uuid, Path, description = "Chat ID"),
+        ("epoch" = Option<i32>, Query, description = "Epoch to fetch; defaults to the current one"),
+        ("X-Device-Id" = Uuid, Header, description = "Receiving device"),
+    ),
+    responses(
+        (status = 200, description = "Sender keys addressed to the device", body = SenderKeyBundleResponse),
+        (status = 400, description = "Missing X-Device-Id", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Not a member", body = ErrorResponse),
+        (status = 409, description = "Unknown device", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Sender keys"
+)]
+pub async fn get_sender_keys(
+    State(state): State<AppState>,
+    Path(chat_id): Path<Uuid>,
+    Query(query): Query<GetSenderKeysQuery>,
+    Extension(auth_user): Extension<AuthUser>,
+    CurrentDevice(device_id): CurrentDevice,
+) -> impl IntoResponse {
+    match state
+        .uc
+        .chat
+        .get_sender_keys(chat_id, auth_user.user_id, device_id, query.epoch)
+        .await
+    {
+        Ok(bundle) => (
+            StatusCode::OK,
+            Json(SenderKeyBundleResponse::from(bundle)).into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/scheduled-messages",
+    params(("chat_id" = Option<Uuid>, Query, description = "Only messages for this chat")),
+    responses(
+        (status = 200, description = "The caller's pending and failed scheduled messages, soonest first", body = Vec<ScheduledMessageResponse>),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Messages"
+)]
+pub async fn list_scheduled_messages(
+    State(state): State<AppState>,
+    Query(query): Query<GetScheduledMessagesQuery>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> impl IntoResponse {
+    match state
+        .uc
+        .chat
+        .list_scheduled_messages(auth_user.user_id, query.chat_id)
+        .await
+    {
+        Ok(messages) => (
+            StatusCode::OK,
+            Json(
+                messages
+                    .into_iter()
+                    .map(ScheduledMessageResponse::from)
+                    .collect::<Vec<_>>(),
+            )
+            .into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/scheduled-messages/{scheduled_id}",
+    params(
+        ("scheduled_id" = Uuid, Path, description = "Scheduled message ID"),
+        ("X-Device-Id" = Uuid, Header, description = "Device that encrypted the new content"),
+    ),
+    request_body(content = SendMessageRequest, description = "The new content; `send_at` may be left out to keep the current time"),
+    responses(
+        (status = 200, description = "Scheduled message replaced; a failed one is pending again", body = ScheduledMessageResponse),
+        (status = 400, description = "Missing X-Device-Id, an unusable `send_at`, or invalid content", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "No longer a member of the chat", body = ErrorResponse),
+        (status = 404, description = "No such scheduled message, or it was already sent", body = ErrorResponse),
+        (status = 409, description = "Unknown device, envelopes out of date with the chat's devices, or a stale epoch", body = DeviceMismatchResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Messages"
+)]
+pub async fn update_scheduled_message(
+    State(state): State<AppState>,
+    Path(scheduled_id): Path<Uuid>,
+    Extension(auth_user): Extension<AuthUser>,
+    CurrentDevice(device_id): CurrentDevice,
+    Json(payload): Json<SendMessageRequest>,
+) -> impl IntoResponse {
+    let send_at = match payload.send_at.as_deref().map(parse_send_at).transpose() {
+        Ok(send_at) => send_at,
+        Err(e) => return error_response(e),
+    };
+    let body = match message_body(payload.envelopes, payload.sender_key) {
+        Ok(body) => body,
+        Err(e) => return error_response(e),
+    };
+
+    match state
+        .uc
+        .chat
+        .update_scheduled_message(
+            scheduled_id,
+            auth_user.user_id,
+            device_id,
+            send_at,
+            body,
+            MessageOptions {
+                attachment_ids: payload.attachment_ids,
+                view_once: payload.view_once,
+                mentions: payload.mentions,
+                mention_all: payload.mention_all,
+            },
+        )
+        .await
+    {
+        Ok(scheduled) => (
+            StatusCode::OK,
+            Json(ScheduledMessageResponse::from(scheduled)).into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/scheduled-messages/{scheduled_id}",
+    params(("scheduled_id" = Uuid, Path, description = "Scheduled message ID")),
+    responses(
+        (status = 200, description = "Scheduled message cancelled"),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "No such scheduled message, or it was already sent", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Messages"
+)]
+pub async fn cancel_scheduled_message(
+    State(state): State<AppState>,
+    Path(scheduled_id): Path<Uuid>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> impl IntoResponse {
+    match state
+        .uc
+        .chat
+        .cancel_scheduled_message(scheduled_id, auth_user.user_id)
+        .await
+    {
+        Ok(()) => (
+            StatusCode::OK,
+            Json(serde_json::json!({"message": "Scheduled message cancelled"})).into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+fn message_body(
+    envelopes: HashMap<Uuid, String>,
+    sender_key: Option<SenderKeyMessage>,
+) -> Result<MessageBody, ChatError> {
+    match sender_key {
+        None => Ok(MessageBody::Envelopes(envelopes)),
+        Some(message) if envelopes.is_empty() => Ok(MessageBody::SenderKey {
+            epoch: message.epoch,
+            ciphertext: message.ciphertext,
+        }),
+        Some(_) => Err(ChatError::InvalidMessage(
+            "Send either envelopes or a sender-key message".to_string(),
+        )),
+    }
+}
+
+/// RFC 3339, or the `YYYY-MM-DD HH:MM:SS` UTC form this API returns.
+fn parse_timestamp(value: &str) -> Option<NaiveDateTime> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|value| value.naive_utc())
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S"))
+        .ok()
+}
+
+fn parse_send_at(send_at: &str) -> Result<NaiveDateTime, ChatError> {
+    parse_timestamp(send_at).ok_or_else(|| {
+        ChatError::InvalidSchedule("send_at must be RFC 3339 or YYYY-MM-DD HH:MM:SS".to_string())
+    })
+}
+
+fn parse_closes_at(closes_at: &str) -> Result<NaiveDateTime, ChatError> {
+    parse_timestamp(closes_at).ok_or_else(|| {
+        ChatError::InvalidPoll("closes_at must be RFC 3339 or YYYY-MM-DD HH:MM:SS".to_string())
+    })
+}
+
 fn error_response(err: ChatError) -> (StatusCode, axum::response::Response) {
     let (status, code) = match &err {
         ChatError::ChatNotFound => (StatusCode::NOT_FOUND, "CHAT_NOT_FOUND"),
         ChatError::UserNotFound(_) => (StatusCode::NOT_FOUND, "USER_NOT_FOUND"),
         ChatError::NotMember => (StatusCode::FORBIDDEN, "NOT_MEMBER"),
         ChatError::AlreadyMember => (StatusCode::BAD_REQUEST, "ALREADY_MEMBER"),
+        ChatError::MemberNotFound => (StatusCode::NOT_FOUND, "MEMBER_NOT_FOUND"),
+        ChatError::Forbidden(_) => (StatusCode::FORBIDDEN, "FORBIDDEN"),
+        ChatError::InvalidMessage(_) => (StatusCode::BAD_REQUEST, "INVALID_MESSAGE"),
+        ChatError::InvalidMessageTtl(_) => (StatusCode::BAD_REQUEST, "INVALID_MESSAGE_TTL"),
+        ChatError::InvalidAttachment(_) => (StatusCode::BAD_REQUEST, "INVALID_ATTACHMENT"),
+        ChatError::InvalidSchedule(_) => (StatusCode::BAD_REQUEST, "INVALID_SCHEDULE"),
+        ChatError::ScheduledMessageNotFound => {
+            (StatusCode::NOT_FOUND, "SCHEDULED_MESSAGE_NOT_FOUND")
+        }
+        ChatError::MessageNotFound => (StatusCode::NOT_FOUND, "MESSAGE_NOT_FOUND"),
+        ChatError::ForwardingDisabled => (StatusCode::FORBIDDEN, "FORWARDING_DISABLED"),
+        ChatError::InvalidPoll(_) => (StatusCode::BAD_REQUEST, "INVALID_POLL"),
+        ChatError::PollClosed => (StatusCode::CONFLICT, "POLL_CLOSED"),
+        ChatError::PollsDisabled => (StatusCode::FORBIDDEN, "POLLS_DISABLED"),
+        ChatError::PinNotFound => (StatusCode::NOT_FOUND, "PIN_NOT_FOUND"),
+        ChatError::TooManyPins(_) => (StatusCode::CONFLICT, "PIN_LIMIT_REACHED"),
         ChatError::InvalidChatName(_) => (StatusCode::BAD_REQUEST, "INVALID_CHAT_NAME"),
+        ChatError::UnknownDevice => (StatusCode::CONFLICT, "UNKNOWN_DEVICE"),
+        ChatError::DeviceMismatch { missing, stale } => {
+            return (
+                StatusCode::CONFLICT,
+                Json(DeviceMismatchResponse {
+                    error: err.to_string(),
+                    code: "DEVICE_MISMATCH".to_string(),
+                    missing_devices: missing.clone(),
+                    stale_devices: stale.clone(),
+                })
+                .into_response(),
+            );
+        }
+        ChatError::StaleEpoch { current } => {
+            return (
+                StatusCode::CONFLICT,
+                Json(StaleEpochResponse {
+                    error: err.to_string(),
+                    code: "STALE_EPOCH".to_string(),
+                    current_epoch: *current,
+                })
+                .into_response(),
+            );
+        }
+        ChatError::Unavailable => (StatusCode::SERVICE_UNAVAILABLE, "SERVICE_UNAVAILABLE"),
         ChatError::Internal(_) => (StatusCode::INTERNAL_SERVER_ERROR, "INTERNAL_ERROR"),
     };
 
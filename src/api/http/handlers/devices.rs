@@ -0,0 +1,122 @@
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use uuid::Uuid;
+
+use crate::api::http::dto::{DeviceResponse, ErrorResponse, RegisterDeviceRequest};
+use crate::api::http::middleware::AuthUser;
+use crate::api::http::state::AppState;
+use crate::usecase::DeviceError;
+
+#[utoipa::path(
+    post,
+    path = "/devices",
+    request_body = RegisterDeviceRequest,
+    responses(
+        (status = 201, description = "Device registered", body = DeviceResponse),
+        (status = 400, description = "Invalid name or too many devices", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Devices"
+)]
+pub async fn register_device(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Json(payload): Json<RegisterDeviceRequest>,
+) -> impl IntoResponse {
+    match state
+        .uc
+        .devices
+        .register_device(auth_user.user_id, payload.name)
+        .await
+    {
+        Ok(device) => (
+            StatusCode::CREATED,
+            Json(DeviceResponse::from(device)).into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/devices",
+    responses(
+        (status = 200, description = "The caller's devices", body = Vec<DeviceResponse>),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Devices"
+)]
+pub async fn list_devices(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> impl IntoResponse {
+    match state.uc.devices.list_devices(auth_user.user_id).await {
+        Ok(devices) => (
+            StatusCode::OK,
+            Json(
+                devices
+                    .into_iter()
+                    .map(DeviceResponse::from)
+                    .collect::<Vec<_>>(),
+            )
+            .into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/devices/{device_id}",
+    params(("device_id" = Uuid, Path, description = "Device ID")),
+    responses(
+        (status = 200, description = "Device removed with its keys and pending envelopes"),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "No such device on this account", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Devices"
+)]
+pub async fn remove_device(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(device_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match state
+        .uc
+        .devices
+        .remove_device(auth_user.user_id, device_id)
+        .await
+    {
+        Ok(()) => (
+            StatusCode::OK,
+            Json(serde_json::json!({"message": "Device removed"})).into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+fn error_response(err: DeviceError) -> (StatusCode, axum::response::Response) {
+    let (status, code) = match &err {
+        DeviceError::InvalidDeviceName(_) => (StatusCode::BAD_REQUEST, "INVALID_DEVICE_NAME"),
+        DeviceError::UnknownDevice => (StatusCode::NOT_FOUND, "UNKNOWN_DEVICE"),
+        DeviceError::TooManyDevices(_) => (StatusCode::BAD_REQUEST, "TOO_MANY_DEVICES"),
+        DeviceError::Unavailable => (StatusCode::SERVICE_UNAVAILABLE, "SERVICE_UNAVAILABLE"),
+        DeviceError::Internal(_) => (StatusCode::INTERNAL_SERVER_ERROR, "INTERNAL_ERROR"),
+    };
+
+    (
+        status,
+        Json(ErrorResponse {
+            error: err.to_string(),
+            code: code.to_string(),
+        })
+        .into_response(),
+    )
+}
@@ -0,0 +1,309 @@
+use axum::{
+    Extension, Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use uuid::Uuid;
+
+use crate::api::http::dto::{
+    ConsistencyProofResponse, ErrorResponse, GetConsistencyQuery, GetInclusionQuery,
+    GetLogEntriesQuery, KeyInclusionResponse, KeyLogEntryResponse, LogPublicKeyResponse,
+    PrekeyBundleResponse, PrekeyStatusResponse, PublishIdentityRequest, TreeHeadResponse,
+    UploadPrekeysRequest,
+};
+use crate::api::http::middleware::{AuthUser, CurrentDevice};
+use crate::api::http::state::AppState;
+use crate::usecase::KeyError;
+
+#[utoipa::path(
+    put,
+    path = "/keys/identity",
+    params(("X-Device-Id" = Uuid, Header, description = "Device the keys belong to")),
+    request_body = PublishIdentityRequest,
+    responses(
+        (status = 200, description = "Identity key and signed prekey published", body = PrekeyStatusResponse),
+        (status = 400, description = "Malformed key or missing X-Device-Id", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 409, description = "Unknown device", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Keys"
+)]
+pub async fn publish_identity(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    CurrentDevice(device_id): CurrentDevice,
+    Json(payload): Json<PublishIdentityRequest>,
+) -> impl IntoResponse {
+    match state
+        .uc
+        .keys
+        .publish_identity(
+            auth_user.user_id,
+            device_id,
+            payload.identity_key,
+            payload.signed_prekey.into(),
+        )
+        .await
+    {
+        Ok(status) => (
+            StatusCode::OK,
+            Json(PrekeyStatusResponse::from(status)).into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/keys/prekeys",
+    params(("X-Device-Id" = Uuid, Header, description = "Device the keys belong to")),
+    request_body = UploadPrekeysRequest,
+    responses(
+        (status = 201, description = "One-time prekeys stored", body = PrekeyStatusResponse),
+        (status = 400, description = "Malformed key, too many prekeys or missing X-Device-Id", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "Identity key not published yet", body = ErrorResponse),
+        (status = 409, description = "Prekey id already in use, or unknown device", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Keys"
+)]
+pub async fn upload_prekeys(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    CurrentDevice(device_id): CurrentDevice,
+    Json(payload): Json<UploadPrekeysRequest>,
+) -> impl IntoResponse {
+    let prekeys = payload.prekeys.into_iter().map(Into::into).collect();
+
+    match state
+        .uc
+        .keys
+        .upload_prekeys(auth_user.user_id, device_id, prekeys)
+        .await
+    {
+        Ok(status) => (
+            StatusCode::CREATED,
+            Json(PrekeyStatusResponse::from(status)).into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/keys/prekeys/status",
+    params(("X-Device-Id" = Uuid, Header, description = "Device to report on")),
+    responses(
+        (status = 200, description = "Remaining one-time prekeys of the device", body = PrekeyStatusResponse),
+        (status = 400, description = "Missing X-Device-Id", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 409, description = "Unknown device", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Keys"
+)]
+pub async fn prekey_status(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    CurrentDevice(device_id): CurrentDevice,
+) -> impl IntoResponse {
+    match state
+        .uc
+        .keys
+        .prekey_status(auth_user.user_id, device_id)
+        .await
+    {
+        Ok(status) => (
+            StatusCode::OK,
+            Json(PrekeyStatusResponse::from(status)).into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+// A POST because every call consumes a one-time prekey of each device.
+#[utoipa::path(
+    post,
+    path = "/users/{user_id}/prekey-bundle",
+    params(("user_id" = Uuid, Path, description = "User ID")),
+    responses(
+        (status = 200, description = "One bundle per device; one_time_prekey is null once a device's stock is exhausted", body = PrekeyBundleResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "User has not published keys", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Keys"
+)]
+pub async fn fetch_bundle(
+    State(state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match state.uc.keys.fetch_bundle(user_id).await {
+        Ok(bundle) => (
+            StatusCode::OK,
+            Json(PrekeyBundleResponse::from(bundle)).into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/keys/log/head",
+    responses(
+        (status = 200, description = "Latest signed tree head of the key log", body = TreeHeadResponse),
+        (status = 404, description = "No key has been logged yet", body = ErrorResponse),
+    ),
+    tag = "Key transparency"
+)]
+pub async fn tree_head(State(state): State<AppState>) -> impl IntoResponse {
+    match state.uc.keys.tree_head().await {
+        Ok(head) => (
+            StatusCode::OK,
+            Json(TreeHeadResponse::from(head)).into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/keys/log/public-key",
+    responses(
+        (status = 200, description = "Key that verifies tree head signatures", body = LogPublicKeyResponse),
+    ),
+    tag = "Key transparency"
+)]
+pub async fn log_public_key(State(state): State<AppState>) -> impl IntoResponse {
+    Json(LogPublicKeyResponse {
+        algorithm: "Ed25519".to_string(),
+        public_key: state.uc.keys.log_public_key(),
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/keys/log/consistency",
+    params(
+        ("first" = i64, Query, description = "Size of the older tree"),
+        ("second" = Option<i64>, Query, description = "Size of the newer tree; the latest by default"),
+    ),
+    responses(
+        (status = 200, description = "Proof that the older tree is a prefix of the newer one", body = ConsistencyProofResponse),
+        (status = 400, description = "Tree size out of range", body = ErrorResponse),
+        (status = 404, description = "No key has been logged yet", body = ErrorResponse),
+    ),
+    tag = "Key transparency"
+)]
+pub async fn consistency_proof(
+    State(state): State<AppState>,
+    Query(query): Query<GetConsistencyQuery>,
+) -> impl IntoResponse {
+    match state
+        .uc
+        .keys
+        .consistency_proof(query.first, query.second)
+        .await
+    {
+        Ok(proof) => (
+            StatusCode::OK,
+            Json(ConsistencyProofResponse::from(proof)).into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/keys/log/entries",
+    params(
+        ("start" = Option<i64>, Query, description = "Index of the first leaf"),
+        ("limit" = Option<i64>, Query, description = "Number of leaves to return, at most 1000"),
+    ),
+    responses(
+        (status = 200, description = "Log leaves in order", body = Vec<KeyLogEntryResponse>),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Key transparency"
+)]
+pub async fn get_log_entries(
+    State(state): State<AppState>,
+    Query(query): Query<GetLogEntriesQuery>,
+) -> impl IntoResponse {
+    match state
+        .uc
+        .keys
+        .get_log_entries(query.start, query.limit)
+        .await
+    {
+        Ok(entries) => (
+            StatusCode::OK,
+            Json(
+                entries
+                    .into_iter()
+                    .map(KeyLogEntryResponse::from)
+                    .collect::<Vec<_>>(),
+            )
+            .into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/{user_id}/keys/inclusion",
+    params(
+        ("user_id" = Uuid, Path, description = "User ID"),
+        ("tree_size" = Option<i64>, Query, description = "Tree to prove against; the latest by default"),
+    ),
+    responses(
+        (status = 200, description = "Audit paths for the user's current identity keys", body = KeyInclusionResponse),
+        (status = 400, description = "Tree size out of range", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "No logged keys for the user in that tree", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Key transparency"
+)]
+pub async fn prove_keys(
+    State(state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+    Query(query): Query<GetInclusionQuery>,
+) -> impl IntoResponse {
+    match state.uc.keys.prove_keys(user_id, query.tree_size).await {
+        Ok(proofs) => (
+            StatusCode::OK,
+            Json(KeyInclusionResponse::from(proofs)).into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+fn error_response(err: KeyError) -> (StatusCode, axum::response::Response) {
+    let (status, code) = match &err {
+        KeyError::KeysNotFound => (StatusCode::NOT_FOUND, "KEYS_NOT_FOUND"),
+        KeyError::UnknownDevice => (StatusCode::CONFLICT, "UNKNOWN_DEVICE"),
+        KeyError::InvalidKey(_) => (StatusCode::BAD_REQUEST, "INVALID_KEY"),
+        KeyError::TooManyPrekeys(_) => (StatusCode::BAD_REQUEST, "TOO_MANY_PREKEYS"),
+        KeyError::DuplicatePrekey => (StatusCode::CONFLICT, "DUPLICATE_PREKEY"),
+        KeyError::LogEmpty => (StatusCode::NOT_FOUND, "KEY_LOG_EMPTY"),
+        KeyError::KeyNotLogged => (StatusCode::NOT_FOUND, "KEY_NOT_LOGGED"),
+        KeyError::InvalidTreeSize(_) => (StatusCode::BAD_REQUEST, "INVALID_TREE_SIZE"),
+        KeyError::Unavailable => (StatusCode::SERVICE_UNAVAILABLE, "SERVICE_UNAVAILABLE"),
+        KeyError::Internal(_) => (StatusCode::INTERNAL_SERVER_ERROR, "INTERNAL_ERROR"),
+    };
+
+    (
+        status,
+        Json(ErrorResponse {
+            error: err.to_string(),
+            code: code.to_string(),
+        })
+        .into_response(),
+    )
+}
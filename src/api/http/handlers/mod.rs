@@ -1,3 +1,9 @@
+pub mod admin;
+pub mod attachments;
 pub mod auth;
 pub mod chat;
+pub mod devices;
 pub mod health;
+pub mod keys;
+pub mod push;
+pub mod webhooks;
@@ -0,0 +1,151 @@
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use uuid::Uuid;
+
+use crate::api::http::dto::{
+    ErrorResponse, PushEndpointResponse, SetPushEndpointRequest, VapidPublicKeyResponse,
+};
+use crate::api::http::middleware::AuthUser;
+use crate::api::http::state::AppState;
+use crate::usecase::PushError;
+
+#[utoipa::path(
+    put,
+    path = "/devices/{device_id}/push-endpoint",
+    params(("device_id" = Uuid, Path, description = "Device ID")),
+    request_body = SetPushEndpointRequest,
+    responses(
+        (status = 200, description = "Endpoint set", body = PushEndpointResponse),
+        (status = 400, description = "Invalid kind or URL", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "No such device on this account", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Push"
+)]
+pub async fn set_push_endpoint(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(device_id): Path<Uuid>,
+    Json(payload): Json<SetPushEndpointRequest>,
+) -> impl IntoResponse {
+    match state
+        .uc
+        .push
+        .set_endpoint(auth_user.user_id, device_id, payload.kind, payload.url)
+        .await
+    {
+        Ok(endpoint) => (
+            StatusCode::OK,
+            Json(PushEndpointResponse::from(endpoint)).into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/devices/{device_id}/push-endpoint",
+    params(("device_id" = Uuid, Path, description = "Device ID")),
+    responses(
+        (status = 200, description = "The device's endpoint", body = PushEndpointResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "No such device, or it has no endpoint", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Push"
+)]
+pub async fn get_push_endpoint(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(device_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match state
+        .uc
+        .push
+        .get_endpoint(auth_user.user_id, device_id)
+        .await
+    {
+        Ok(endpoint) => (
+            StatusCode::OK,
+            Json(PushEndpointResponse::from(endpoint)).into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/devices/{device_id}/push-endpoint",
+    params(("device_id" = Uuid, Path, description = "Device ID")),
+    responses(
+        (status = 200, description = "Endpoint removed; the device gets no more wake-ups"),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "No such device, or it has no endpoint", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Push"
+)]
+pub async fn remove_push_endpoint(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(device_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match state
+        .uc
+        .push
+        .remove_endpoint(auth_user.user_id, device_id)
+        .await
+    {
+        Ok(()) => (
+            StatusCode::OK,
+            Json(serde_json::json!({"message": "Push endpoint removed"})).into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/push/vapid-public-key",
+    responses(
+        (status = 200, description = "Key to create Web Push subscriptions with", body = VapidPublicKeyResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "Web Push is not configured", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Push"
+)]
+pub async fn vapid_public_key(State(state): State<AppState>) -> impl IntoResponse {
+    match state.uc.push.vapid_public_key() {
+        Ok(public_key) => (
+            StatusCode::OK,
+            Json(VapidPublicKeyResponse { public_key }).into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+fn error_response(err: PushError) -> (StatusCode, axum::response::Response) {
+    let (status, code) = match &err {
+        PushError::InvalidEndpoint(_) => (StatusCode::BAD_REQUEST, "INVALID_PUSH_ENDPOINT"),
+        PushError::UnknownDevice => (StatusCode::NOT_FOUND, "UNKNOWN_DEVICE"),
+        PushError::EndpointNotFound => (StatusCode::NOT_FOUND, "PUSH_ENDPOINT_NOT_FOUND"),
+        PushError::WebPushDisabled => (StatusCode::NOT_FOUND, "WEB_PUSH_DISABLED"),
+        PushError::Unavailable => (StatusCode::SERVICE_UNAVAILABLE, "SERVICE_UNAVAILABLE"),
+        PushError::Internal(_) => (StatusCode::INTERNAL_SERVER_ERROR, "INTERNAL_ERROR"),
+    };
+
+    (
+        status,
+        Json(ErrorResponse {
+            error: err.to_string(),
+            code: code.to_string(),
+        })
+        .into_response(),
+    )
+}
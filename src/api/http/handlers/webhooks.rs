@@ -0,0 +1,222 @@
+use axum::{
+    Extension, Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use uuid::Uuid;
+
+use crate::api::http::dto::{
+    CreateWebhookRequest, ErrorResponse, GetWebhookDeliveriesQuery, WebhookDeliveryResponse,
+    WebhookResponse,
+};
+use crate::api::http::middleware::AuthUser;
+use crate::api::http::state::AppState;
+use crate::usecase::WebhookError;
+use crate::usecase::auth::permissions;
+
+#[utoipa::path(
+    post,
+    path = "/webhooks",
+    request_body = CreateWebhookRequest,
+    responses(
+        (status = 201, description = "Webhook created; its secret is only shown here", body = WebhookResponse),
+        (status = 400, description = "Invalid URL or events", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Not the chat creator, or a webhook for every chat without webhooks.manage", body = ErrorResponse),
+        (status = 404, description = "Chat not found", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Webhooks"
+)]
+pub async fn create_webhook(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Json(payload): Json<CreateWebhookRequest>,
+) -> impl IntoResponse {
+    let can_manage = auth_user.has_permission(permissions::WEBHOOKS_MANAGE);
+
+    match state
+        .uc
+        .webhooks
+        .create_webhook(
+            auth_user.user_id,
+            can_manage,
+            payload.chat_id,
+            payload.url,
+            payload.events,
+        )
+        .await
+    {
+        Ok(webhook) => (
+            StatusCode::CREATED,
+            Json(WebhookResponse::from(webhook)).into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/webhooks",
+    responses(
+        (status = 200, description = "Webhooks the caller created", body = Vec<WebhookResponse>),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Webhooks"
+)]
+pub async fn list_webhooks(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> impl IntoResponse {
+    match state.uc.webhooks.list_webhooks(auth_user.user_id).await {
+        Ok(webhooks) => (
+            StatusCode::OK,
+            Json(
+                webhooks
+                    .into_iter()
+                    .map(WebhookResponse::from)
+                    .collect::<Vec<_>>(),
+            )
+            .into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/webhooks/{webhook_id}",
+    params(("webhook_id" = Uuid, Path, description = "Webhook ID")),
+    responses(
+        (status = 200, description = "The webhook", body = WebhookResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "No such webhook of the caller", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Webhooks"
+)]
+pub async fn get_webhook(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(webhook_id): Path<Uuid>,
+) -> impl IntoResponse {
+    let can_manage = auth_user.has_permission(permissions::WEBHOOKS_MANAGE);
+
+    match state
+        .uc
+        .webhooks
+        .get_webhook(auth_user.user_id, can_manage, webhook_id)
+        .await
+    {
+        Ok(webhook) => (
+            StatusCode::OK,
+            Json(WebhookResponse::from(webhook)).into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/webhooks/{webhook_id}",
+    params(("webhook_id" = Uuid, Path, description = "Webhook ID")),
+    responses(
+        (status = 200, description = "Webhook removed; pending deliveries are dropped"),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "No such webhook of the caller", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Webhooks"
+)]
+pub async fn delete_webhook(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(webhook_id): Path<Uuid>,
+) -> impl IntoResponse {
+    let can_manage = auth_user.has_permission(permissions::WEBHOOKS_MANAGE);
+
+    match state
+        .uc
+        .webhooks
+        .delete_webhook(auth_user.user_id, can_manage, webhook_id)
+        .await
+    {
+        Ok(()) => (
+            StatusCode::OK,
+            Json(serde_json::json!({"message": "Webhook removed"})).into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/webhooks/{webhook_id}/deliveries",
+    params(
+        ("webhook_id" = Uuid, Path, description = "Webhook ID"),
+        ("limit" = Option<i64>, Query, description = "Number of deliveries to return, at most 200"),
+        ("offset" = Option<i64>, Query, description = "Offset for pagination"),
+    ),
+    responses(
+        (status = 200, description = "Delivery log, most recent first", body = Vec<WebhookDeliveryResponse>),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "No such webhook of the caller", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Webhooks"
+)]
+pub async fn list_webhook_deliveries(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(webhook_id): Path<Uuid>,
+    Query(query): Query<GetWebhookDeliveriesQuery>,
+) -> impl IntoResponse {
+    let can_manage = auth_user.has_permission(permissions::WEBHOOKS_MANAGE);
+
+    match state
+        .uc
+        .webhooks
+        .list_deliveries(
+            auth_user.user_id,
+            can_manage,
+            webhook_id,
+            query.limit,
+            query.offset,
+        )
+        .await
+    {
+        Ok(deliveries) => (
+            StatusCode::OK,
+            Json(
+                deliveries
+                    .into_iter()
+                    .map(WebhookDeliveryResponse::from)
+                    .collect::<Vec<_>>(),
+            )
+            .into_response(),
+        ),
+        Err(e) => error_response(e),
+    }
+}
+
+fn error_response(err: WebhookError) -> (StatusCode, axum::response::Response) {
+    let (status, code) = match &err {
+        WebhookError::InvalidWebhook(_) => (StatusCode::BAD_REQUEST, "INVALID_WEBHOOK"),
+        WebhookError::Forbidden(_) => (StatusCode::FORBIDDEN, "FORBIDDEN"),
+        WebhookError::ChatNotFound => (StatusCode::NOT_FOUND, "CHAT_NOT_FOUND"),
+        WebhookError::WebhookNotFound => (StatusCode::NOT_FOUND, "WEBHOOK_NOT_FOUND"),
+        WebhookError::Unavailable => (StatusCode::SERVICE_UNAVAILABLE, "SERVICE_UNAVAILABLE"),
+        WebhookError::Internal(_) => (StatusCode::INTERNAL_SERVER_ERROR, "INTERNAL_ERROR"),
+    };
+
+    (
+        status,
+        Json(ErrorResponse {
+            error: err.to_string(),
+            code: code.to_string(),
+        })
+        .into_response(),
+    )
+}
@@ -5,14 +5,24 @@ use axum::{
     middleware::Next,
     response::Response,
 };
+use std::collections::HashSet;
+
 use uuid::Uuid;
 
 use crate::api::http::state::AppState;
+use crate::usecase::AuthError;
 
 #[derive(Clone, Debug)]
 pub struct AuthUser {
     pub user_id: Uuid,
     pub role_id: i32,
+    pub permissions: HashSet<String>,
+}
+
+impl AuthUser {
+    pub fn has_permission(&self, permission: &str) -> bool {
+        self.permissions.contains(permission)
+    }
 }
 
 pub async fn auth_middleware(
@@ -30,15 +40,22 @@ pub async fn auth_middleware(
         _ => return Err(StatusCode::UNAUTHORIZED),
     };
 
-    let (user_id, role_id) = state
+    let principal = state
         .uc
         .auth
         .validate_token(token)
-        .map_err(|_| StatusCode::UNAUTHORIZED)?;
+        .await
+        .map_err(|e| match e {
+            AuthError::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+            AuthError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::UNAUTHORIZED,
+        })?;
 
-    request
-        .extensions_mut()
-        .insert(AuthUser { user_id, role_id });
+    request.extensions_mut().insert(AuthUser {
+        user_id: principal.user_id,
+        role_id: principal.role_id,
+        permissions: principal.permissions,
+    });
 
     Ok(next.run(request).await)
 }
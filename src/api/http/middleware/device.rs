@@ -0,0 +1,44 @@
+use axum::{
+    Json, async_trait,
+    extract::FromRequestParts,
+    http::{StatusCode, request::Parts},
+    response::{IntoResponse, Response},
+};
+use uuid::Uuid;
+
+use crate::api::http::dto::ErrorResponse;
+
+pub const DEVICE_ID_HEADER: &str = "x-device-id";
+
+/// Extractor for the device the request is made from, named by the
+/// `X-Device-Id` header. Whether the device belongs to the caller is checked
+/// by the use case.
+#[derive(Clone, Copy, Debug)]
+pub struct CurrentDevice(pub Uuid);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for CurrentDevice
+where
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .headers
+            .get(DEVICE_ID_HEADER)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| Uuid::parse_str(h.trim()).ok())
+            .map(Self)
+            .ok_or_else(|| {
+                (
+                    StatusCode::BAD_REQUEST,
+                    Json(ErrorResponse {
+                        error: "X-Device-Id header must name a registered device".to_string(),
+                        code: "DEVICE_ID_REQUIRED".to_string(),
+                    }),
+                )
+                    .into_response()
+            })
+    }
+}
@@ -1,3 +1,7 @@
 pub mod auth;
+pub mod device;
+pub mod permission;
 
 pub use auth::{AuthUser, auth_middleware};
+pub use device::{CurrentDevice, DEVICE_ID_HEADER};
+pub use permission::RequirePermission;
@@ -0,0 +1,78 @@
+use std::marker::PhantomData;
+
+use axum::{
+    Json, async_trait,
+    extract::FromRequestParts,
+    http::{StatusCode, request::Parts},
+    response::{IntoResponse, Response},
+};
+
+use super::auth::AuthUser;
+use crate::api::http::dto::ErrorResponse;
+use crate::usecase::auth::permissions;
+
+/// Type-level name of a permission, used by [`RequirePermission`].
+pub trait PermissionMarker: Send + Sync {
+    const NAME: &'static str;
+}
+
+macro_rules! permission_markers {
+    ($($marker:ident => $name:path),* $(,)?) => {
+        $(
+            pub struct $marker;
+
+            impl PermissionMarker for $marker {
+                const NAME: &'static str = $name;
+            }
+        )*
+    };
+}
+
+permission_markers! {
+    UsersRead => permissions::USERS_READ,
+    UsersDeactivate => permissions::USERS_DEACTIVATE,
+    UsersLogout => permissions::USERS_LOGOUT,
+    UsersAssignRole => permissions::USERS_ASSIGN_ROLE,
+    RolesRead => permissions::ROLES_READ,
+    RolesManage => permissions::ROLES_MANAGE,
+}
+
+/// Extractor that rejects the request with 403 unless the caller's role grants
+/// `P`. Requires `auth_middleware` to have run on the route.
+pub struct RequirePermission<P: PermissionMarker> {
+    pub user: AuthUser,
+    _marker: PhantomData<P>,
+}
+
+#[async_trait]
+impl<S, P> FromRequestParts<S> for RequirePermission<P>
+where
+    S: Send + Sync,
+    P: PermissionMarker,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let user = parts
+            .extensions
+            .get::<AuthUser>()
+            .cloned()
+            .ok_or_else(|| StatusCode::UNAUTHORIZED.into_response())?;
+
+        if !user.has_permission(P::NAME) {
+            return Err((
+                StatusCode::FORBIDDEN,
+                Json(ErrorResponse {
+                    error: format!("Missing permission: {}", P::NAME),
+                    code: "FORBIDDEN".to_string(),
+                }),
+            )
+                .into_response());
+        }
+
+        Ok(Self {
+            user,
+            _marker: PhantomData,
+        })
+    }
+}
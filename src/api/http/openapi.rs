@@ -1,9 +1,23 @@
 use utoipa::OpenApi;
 
 use super::dto::{
-    AuthResponse, ChatMemberResponse, ChatResponse, CreateChatRequest, ErrorResponse,
-    GetMessagesQuery, InviteUserRequest, LoginRequest, MessageResponse, RegisterRequest,
-    SendMessageRequest, UserInfoResponse, UserResponse,
+    AssignRoleRequest, AttachmentResponse, AuthResponse, ChatMemberResponse, ChatResponse,
+    ConsistencyProofResponse, CreateChatRequest, CreatePollRequest, CreateRoleRequest,
+    CreateUploadRequest, CreateWebhookRequest, DeviceBundleResponse, DeviceMismatchResponse,
+    DeviceResponse, DistributeSenderKeyRequest, ErrorResponse, ForwardMessageRequest,
+    ForwardedFromResponse, GetConsistencyQuery, GetInclusionQuery, GetLogEntriesQuery,
+    GetMembersQuery, GetMentionsQuery, GetMessagesQuery, GetRekeysQuery, GetScheduledMessagesQuery,
+    GetSenderKeysQuery, GetWebhookDeliveriesQuery, InclusionProofResponse, InviteUserRequest,
+    KeyInclusionResponse, KeyLogEntryResponse, ListUsersQuery, LogPublicKeyResponse, LoginRequest,
+    MarkReadRequest, MentionResponse, MessageResponse, OffsetMismatchResponse, OneTimePrekey,
+    PermissionResponse, PinResponse, PollOptionResponse, PollResponse, PrekeyBundleResponse,
+    PrekeyStatusResponse, PublishIdentityRequest, PushEndpointResponse, ReadStateResponse,
+    RegisterDeviceRequest, RegisterRequest, RekeyResponse, RenameChatRequest, RoleResponse,
+    ScheduledMessageResponse, SendMessageRequest, SenderKeyBundleResponse, SenderKeyMessage,
+    SenderKeyResponse, SetForwardingRequest, SetMessageTtlRequest, SetMuteRequest, SetPollsRequest,
+    SetPushEndpointRequest, SetRolePermissionsRequest, SignedPrekey, StaleEpochResponse,
+    TreeHeadResponse, UploadPrekeysRequest, UserInfoResponse, UserListResponse, UserResponse,
+    VapidPublicKeyResponse, VoteRequest, WebhookDeliveryResponse, WebhookResponse,
 };
 
 #[derive(OpenApi)]
@@ -13,13 +27,71 @@ use super::dto::{
         super::handlers::auth::login,
         super::handlers::auth::me,
         super::handlers::auth::get_user_by_id,
+        super::handlers::auth::jwks,
+        super::handlers::admin::list_users,
+        super::handlers::admin::deactivate_user,
+        super::handlers::admin::reactivate_user,
+        super::handlers::admin::assign_role,
+        super::handlers::admin::force_logout,
+        super::handlers::admin::list_roles,
+        super::handlers::admin::create_role,
+        super::handlers::admin::set_role_permissions,
+        super::handlers::admin::list_permissions,
         super::handlers::chat::create_chat,
         super::handlers::chat::get_my_chats,
         super::handlers::chat::get_chat,
         super::handlers::chat::invite_user,
         super::handlers::chat::get_chat_members,
+        super::handlers::chat::get_chat_devices,
         super::handlers::chat::send_message,
+        super::handlers::chat::forward_message,
+        super::handlers::chat::create_poll,
+        super::handlers::chat::vote,
         super::handlers::chat::get_messages,
+        super::handlers::chat::list_scheduled_messages,
+        super::handlers::chat::update_scheduled_message,
+        super::handlers::chat::cancel_scheduled_message,
+        super::handlers::chat::leave_chat,
+        super::handlers::chat::remove_member,
+        super::handlers::chat::rename_chat,
+        super::handlers::chat::set_message_ttl,
+        super::handlers::chat::set_allow_forwarding,
+        super::handlers::chat::set_allow_polls,
+        super::handlers::chat::mark_read,
+        super::handlers::chat::set_muted,
+        super::handlers::chat::get_mentions,
+        super::handlers::chat::get_pins,
+        super::handlers::chat::pin_message,
+        super::handlers::chat::unpin_message,
+        super::handlers::chat::get_rekeys,
+        super::handlers::chat::distribute_sender_key,
+        super::handlers::chat::get_sender_keys,
+        super::handlers::devices::register_device,
+        super::handlers::devices::list_devices,
+        super::handlers::devices::remove_device,
+        super::handlers::push::set_push_endpoint,
+        super::handlers::push::get_push_endpoint,
+        super::handlers::push::remove_push_endpoint,
+        super::handlers::push::vapid_public_key,
+        super::handlers::webhooks::create_webhook,
+        super::handlers::webhooks::list_webhooks,
+        super::handlers::webhooks::get_webhook,
+        super::handlers::webhooks::delete_webhook,
+        super::handlers::webhooks::list_webhook_deliveries,
+        super::handlers::keys::publish_identity,
+        super::handlers::keys::upload_prekeys,
+        super::handlers::keys::prekey_status,
+        super::handlers::keys::fetch_bundle,
+        super::handlers::keys::tree_head,
+        super::handlers::keys::log_public_key,
+        super::handlers::keys::consistency_proof,
+        super::handlers::keys::get_log_entries,
+        super::handlers::keys::prove_keys,
+        super::handlers::attachments::upload_attachment,
+        super::handlers::attachments::create_upload,
+        super::handlers::attachments::upload_chunk,
+        super::handlers::attachments::get_upload,
+        super::handlers::attachments::download_attachment,
     ),
     components(
         schemas(
@@ -28,21 +100,92 @@ use super::dto::{
             AuthResponse,
             UserResponse,
             UserInfoResponse,
+            AssignRoleRequest,
+            ListUsersQuery,
+            UserListResponse,
+            RoleResponse,
+            CreateRoleRequest,
+            SetRolePermissionsRequest,
+            PermissionResponse,
             ErrorResponse,
             CreateChatRequest,
             InviteUserRequest,
             SendMessageRequest,
+            GetMembersQuery,
             GetMessagesQuery,
             ChatResponse,
             MessageResponse,
+            ForwardMessageRequest,
+            ForwardedFromResponse,
+            CreatePollRequest,
+            VoteRequest,
+            PollResponse,
+            PollOptionResponse,
+            GetScheduledMessagesQuery,
+            ScheduledMessageResponse,
             ChatMemberResponse,
+            DeviceMismatchResponse,
+            SenderKeyMessage,
+            DistributeSenderKeyRequest,
+            GetSenderKeysQuery,
+            GetRekeysQuery,
+            SenderKeyResponse,
+            SenderKeyBundleResponse,
+            RekeyResponse,
+            StaleEpochResponse,
+            RenameChatRequest,
+            SetMessageTtlRequest,
+            SetForwardingRequest,
+            SetPollsRequest,
+            MarkReadRequest,
+            SetMuteRequest,
+            ReadStateResponse,
+            GetMentionsQuery,
+            MentionResponse,
+            PinResponse,
+            RegisterDeviceRequest,
+            DeviceResponse,
+            SetPushEndpointRequest,
+            PushEndpointResponse,
+            VapidPublicKeyResponse,
+            CreateWebhookRequest,
+            WebhookResponse,
+            GetWebhookDeliveriesQuery,
+            WebhookDeliveryResponse,
+            SignedPrekey,
+            OneTimePrekey,
+            PublishIdentityRequest,
+            UploadPrekeysRequest,
+            PrekeyStatusResponse,
+            PrekeyBundleResponse,
+            DeviceBundleResponse,
+            TreeHeadResponse,
+            LogPublicKeyResponse,
+            KeyLogEntryResponse,
+            InclusionProofResponse,
+            KeyInclusionResponse,
+            ConsistencyProofResponse,
+            GetLogEntriesQuery,
+            GetInclusionQuery,
+            GetConsistencyQuery,
+            AttachmentResponse,
+            CreateUploadRequest,
+            OffsetMismatchResponse,
         )
     ),
     tags(
         (name = "Authentication", description = "Authentication endpoints"),
         (name = "Users", description = "User management endpoints"),
+        (name = "Admin", description = "Administrative endpoints"),
         (name = "Chats", description = "Chat management endpoints"),
-        (name = "Messages", description = "Message endpoints")
+        (name = "Messages", description = "Message endpoints"),
+        (name = "Devices", description = "Devices registered under an account"),
+        (name = "Push", description = "Wake-up notifications for offline devices"),
+        (name = "Webhooks", description = "Signed deliveries of chat events to external systems"),
+        (name = "Sender keys", description = "Group sender-key distribution and rekeying"),
+        (name = "Keys", description = "End-to-end encryption key directory"),
+        (name = "Key transparency", description = "Append-only Merkle log of identity keys"),
+        (name = "Attachments", description = "Encrypted attachment uploads and downloads")
     ),
     modifiers(&SecurityAddon)
 )]
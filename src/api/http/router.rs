@@ -1,11 +1,11 @@
 use axum::{
     Router, middleware,
-    routing::{get, post},
+    routing::{delete, get, patch, post, put},
 };
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
-use super::handlers::{auth, chat, health};
+use super::handlers::{admin, attachments, auth, chat, devices, health, keys, push, webhooks};
 use super::middleware::auth_middleware;
 use super::openapi::ApiDoc;
 use super::state::AppState;
@@ -14,7 +14,11 @@ pub fn create_router(state: AppState) -> Router {
     let public_routes = Router::new()
         .route("/health", get(health::health))
         .route("/auth/register", post(auth::register))
-        .route("/auth/login", post(auth::login));
+        .route("/auth/login", post(auth::login))
+        .route("/.well-known/jwks.json", get(auth::jwks))
+        .route("/keys/log/head", get(keys::tree_head))
+        .route("/keys/log/public-key", get(keys::log_public_key))
+        .route("/keys/log/consistency", get(keys::consistency_proof));
 
     let protected_routes = Router::new()
         .route("/auth/me", get(auth::me))
@@ -24,8 +28,119 @@ pub fn create_router(state: AppState) -> Router {
         .route("/chats/:chat_id", get(chat::get_chat))
         .route("/chats/:chat_id/invite", post(chat::invite_user))
         .route("/chats/:chat_id/members", get(chat::get_chat_members))
+        .route("/chats/:chat_id/devices", get(chat::get_chat_devices))
+        .route("/chats/:chat_id/leave", post(chat::leave_chat))
+        .route(
+            "/chats/:chat_id/members/:user_id",
+            delete(chat::remove_member),
+        )
+        .route("/chats/:chat_id/name", put(chat::rename_chat))
+        .route("/chats/:chat_id/message-ttl", put(chat::set_message_ttl))
+        .route(
+            "/chats/:chat_id/forwarding",
+            put(chat::set_allow_forwarding),
+        )
+        .route("/chats/:chat_id/allow-polls", put(chat::set_allow_polls))
+        .route("/chats/:chat_id/read", put(chat::mark_read))
+        .route("/chats/:chat_id/mute", put(chat::set_muted))
+        .route("/chats/:chat_id/pins", get(chat::get_pins))
+        .route("/chats/:chat_id/pins/:message_id", put(chat::pin_message))
+        .route(
+            "/chats/:chat_id/pins/:message_id",
+            delete(chat::unpin_message),
+        )
+        .route("/chats/:chat_id/rekeys", get(chat::get_rekeys))
+        .route(
+            "/chats/:chat_id/sender-keys",
+            put(chat::distribute_sender_key),
+        )
+        .route("/chats/:chat_id/sender-keys", get(chat::get_sender_keys))
         .route("/chats/:chat_id/messages", post(chat::send_message))
         .route("/chats/:chat_id/messages", get(chat::get_messages))
+        .route(
+            "/chats/:chat_id/messages/forward",
+            post(chat::forward_message),
+        )
+        .route("/chats/:chat_id/polls", post(chat::create_poll))
+        .route("/chats/:chat_id/polls/:message_id/votes", put(chat::vote))
+        .route("/mentions", get(chat::get_mentions))
+        .route("/scheduled-messages", get(chat::list_scheduled_messages))
+        .route(
+            "/scheduled-messages/:scheduled_id",
+            put(chat::update_scheduled_message),
+        )
+        .route(
+            "/scheduled-messages/:scheduled_id",
+            delete(chat::cancel_scheduled_message),
+        )
+        .route("/devices", post(devices::register_device))
+        .route("/devices", get(devices::list_devices))
+        .route("/devices/:device_id", delete(devices::remove_device))
+        .route(
+            "/devices/:device_id/push-endpoint",
+            put(push::set_push_endpoint),
+        )
+        .route(
+            "/devices/:device_id/push-endpoint",
+            get(push::get_push_endpoint),
+        )
+        .route(
+            "/devices/:device_id/push-endpoint",
+            delete(push::remove_push_endpoint),
+        )
+        .route("/push/vapid-public-key", get(push::vapid_public_key))
+        .route("/webhooks", post(webhooks::create_webhook))
+        .route("/webhooks", get(webhooks::list_webhooks))
+        .route("/webhooks/:webhook_id", get(webhooks::get_webhook))
+        .route("/webhooks/:webhook_id", delete(webhooks::delete_webhook))
+        .route(
+            "/webhooks/:webhook_id/deliveries",
+            get(webhooks::list_webhook_deliveries),
+        )
+        .route("/keys/identity", put(keys::publish_identity))
+        .route("/keys/prekeys", post(keys::upload_prekeys))
+        .route("/keys/prekeys/status", get(keys::prekey_status))
+        .route("/users/:user_id/prekey-bundle", post(keys::fetch_bundle))
+        .route("/keys/log/entries", get(keys::get_log_entries))
+        .route("/users/:user_id/keys/inclusion", get(keys::prove_keys))
+        .route("/attachments", post(attachments::upload_attachment))
+        .route("/attachments/uploads", post(attachments::create_upload))
+        .route(
+            "/attachments/uploads/:attachment_id",
+            patch(attachments::upload_chunk),
+        )
+        .route(
+            "/attachments/uploads/:attachment_id",
+            get(attachments::get_upload),
+        )
+        .route(
+            "/attachments/:attachment_id",
+            get(attachments::download_attachment),
+        )
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth_middleware,
+        ));
+
+    let admin_routes = Router::new()
+        .route("/admin/users", get(admin::list_users))
+        .route(
+            "/admin/users/:user_id/deactivate",
+            post(admin::deactivate_user),
+        )
+        .route(
+            "/admin/users/:user_id/reactivate",
+            post(admin::reactivate_user),
+        )
+        .route("/admin/users/:user_id/role", put(admin::assign_role))
+        .route("/admin/users/:user_id/logout", post(admin::force_logout))
+        .route("/admin/roles", get(admin::list_roles))
+        .route("/admin/roles", post(admin::create_role))
+        .route(
+            "/admin/roles/:role_id/permissions",
+            put(admin::set_role_permissions),
+        )
+        .route("/admin/permissions", get(admin::list_permissions))
         .layer(middleware::from_fn_with_state(
             state.clone(),
             auth_middleware,
@@ -35,5 +150,6 @@ pub fn create_router(state: AppState) -> Router {
         .merge(SwaggerUi::new("/swagger").url("/api-docs/openapi.json", ApiDoc::openapi()))
         .merge(public_routes)
         .merge(protected_routes)
+        .merge(admin_routes)
         .with_state(state)
 }
@@ -1,5 +1,6 @@
 use std::net::SocketAddr;
 
+use axum::http::HeaderName;
 use tower_http::cors::{Any, CorsLayer};
 use tower_http::trace::TraceLayer;
 
@@ -13,11 +14,14 @@ pub struct HttpServer {
 }
 
 impl HttpServer {
-    pub fn new(host: String, port: u16, uc: Service) -> Self {
+    pub fn new(host: String, port: u16, client_ip_header: Option<HeaderName>, uc: Service) -> Self {
         let addr = format!("{}:{}", host, port)
             .parse()
             .expect("Invalid address");
-        let state = AppState::new(uc);
+        let mut state = AppState::new(uc);
+        if let Some(header) = client_ip_header {
+            state = state.with_client_ip_header(header);
+        }
 
         Self { addr, state }
     }
@@ -38,8 +42,11 @@ impl HttpServer {
             .await
             .map_err(|e| format!("Failed to bind: {}", e))?;
 
-        axum::serve(listener, app)
-            .await
-            .map_err(|e| format!("Server error: {}", e))
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+        .map_err(|e| format!("Server error: {}", e))
     }
 }
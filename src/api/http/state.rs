@@ -1,12 +1,25 @@
+use axum::http::HeaderName;
+
 use crate::usecase::Service;
 
 #[derive(Clone)]
 pub struct AppState {
     pub uc: Service,
+    /// Where a trusted reverse proxy puts the client address, if the server
+    /// runs behind one.
+    pub client_ip_header: Option<HeaderName>,
 }
 
 impl AppState {
     pub fn new(uc: Service) -> Self {
-        Self { uc }
+        Self {
+            uc,
+            client_ip_header: None,
+        }
+    }
+
+    pub fn with_client_ip_header(mut self, header: HeaderName) -> Self {
+        self.client_ip_header = Some(header);
+        self
     }
 }
@@ -1,8 +1,18 @@
 pub mod app;
 pub mod logger;
 pub mod postgres;
+pub mod push;
+pub mod reaper;
+pub mod scheduler;
+pub mod sweeper;
 pub mod telemetry;
+pub mod webhooks;
 
 pub use app::App;
 pub use logger::Logger;
 pub use postgres::Postgres;
+pub use push::PushDispatcher;
+pub use reaper::MessageReaper;
+pub use scheduler::MessageScheduler;
+pub use sweeper::UploadSweeper;
+pub use webhooks::WebhookDispatcher;
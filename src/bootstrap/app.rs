@@ -2,10 +2,15 @@ use std::sync::Arc;
 
 use super::logger::Logger;
 use super::postgres::Postgres;
+use super::push::PushDispatcher;
+use super::reaper::MessageReaper;
+use super::scheduler::MessageScheduler;
+use super::sweeper::UploadSweeper;
+use super::webhooks::WebhookDispatcher;
 use crate::api::http::HttpServer;
 use crate::config::Config;
 use crate::repository::Repository;
-use crate::usecase::Service;
+use crate::usecase::{Service, ServiceConfig};
 
 pub struct App {
     pub config: Arc<Config>,
@@ -16,7 +21,7 @@ pub struct App {
 }
 
 impl App {
-    pub fn new() -> Result<Self, String> {
+    pub async fn new() -> Result<Self, String> {
         let config = Arc::new(Config::new()?);
 
         super::telemetry::init_telemetry(&config.jaeger.endpoint, &config.jaeger.service_name)?;
@@ -24,7 +29,27 @@ impl App {
         let logger = Logger::new(&config.logger);
         let postgres = Postgres::new(&config.postgres)?;
         let repo = Repository::new(postgres.clone());
-        let uc = Service::new(repo.clone(), config.jwt.clone());
+        let uc = Service::new(
+            repo.clone(),
+            ServiceConfig {
+                jwt: config.jwt.clone(),
+                login_throttle: config.login_throttle.clone(),
+                password: config.password.clone(),
+                keys: config.keys.clone(),
+                attachments: config.attachments.clone(),
+                push: config.push.clone(),
+                webhooks: config.webhooks.clone(),
+            },
+        )?;
+
+        if let (Some(username), Some(password)) =
+            (config.admin.username.clone(), config.admin.password.clone())
+        {
+            uc.auth
+                .ensure_admin(username, password)
+                .await
+                .map_err(|e| format!("Failed to bootstrap admin account: {}", e))?;
+        }
 
         tracing::info!("Application initialized successfully");
 
@@ -41,9 +66,16 @@ impl App {
         tracing::info!("Application starting...");
         tracing::debug!("Config: {:?}", self.config);
 
+        MessageReaper::new(self.uc.chat.clone(), &self.config.message_reaper).spawn();
+        MessageScheduler::new(self.uc.chat.clone(), &self.config.message_scheduler).spawn();
+        UploadSweeper::new(self.uc.attachments.clone(), &self.config.attachments).spawn();
+        PushDispatcher::new(self.uc.push.clone(), &self.config.push).spawn();
+        WebhookDispatcher::new(self.uc.webhooks.clone(), &self.config.webhooks).spawn();
+
         let http_server = HttpServer::new(
             self.config.http.host.clone(),
             self.config.http.port,
+            self.config.http.client_ip_header.clone(),
             self.uc.clone(),
         );
 
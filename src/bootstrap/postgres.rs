@@ -1,7 +1,7 @@
 use std::sync::Arc;
 
 use diesel::pg::PgConnection;
-use diesel::r2d2::{ConnectionManager, Pool, PooledConnection};
+use diesel::r2d2::{ConnectionManager, Pool, PoolError, PooledConnection};
 use diesel_migrations::{EmbeddedMigrations, MigrationHarness, embed_migrations};
 
 use crate::config::postgres::PostgresConfig;
@@ -33,7 +33,9 @@ impl Postgres {
 
         let postgres = Arc::new(Postgres { pool });
 
-        let mut conn = postgres.conn()?;
+        let mut conn = postgres
+            .conn()
+            .map_err(|e| format!("Failed to get connection from pool: {}", e))?;
         conn.run_pending_migrations(MIGRATIONS)
             .map_err(|e| format!("Failed to run migrations: {}", e))?;
         tracing::info!("Database migrations applied successfully");
@@ -45,9 +47,7 @@ impl Postgres {
         &self.pool
     }
 
-    pub fn conn(&self) -> Result<PgPooledConnection, String> {
-        self.pool
-            .get()
-            .map_err(|e| format!("Failed to get connection from pool: {}", e))
+    pub fn conn(&self) -> Result<PgPooledConnection, PoolError> {
+        self.pool.get()
     }
 }
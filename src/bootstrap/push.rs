@@ -0,0 +1,62 @@
+use std::time::Duration;
+
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+
+use crate::config::push::PushConfig;
+use crate::usecase::PushService;
+
+/// Background task that sends queued wake-ups. Every instance may run one:
+/// claimed jobs are leased, and the others skip them.
+pub struct PushDispatcher {
+    push: PushService,
+    interval: Duration,
+    batch_size: i64,
+}
+
+impl PushDispatcher {
+    pub fn new(push: PushService, config: &PushConfig) -> Self {
+        Self {
+            push,
+            interval: Duration::from_secs(config.interval_seconds),
+            batch_size: config.batch_size,
+        }
+    }
+
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(self.interval);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            loop {
+                ticker.tick().await;
+
+                // A full batch means more may be due; keep going without
+                // waiting for the next tick.
+                loop {
+                    match self.push.dispatch_wake_ups(self.batch_size).await {
+                        Ok(dispatch) => {
+                            if dispatch.sent + dispatch.retried + dispatch.failed > 0 {
+                                tracing::info!(
+                                    sent = dispatch.sent,
+                                    skipped = dispatch.skipped,
+                                    retried = dispatch.retried,
+                                    failed = dispatch.failed,
+                                    "Dispatched wake-ups"
+                                );
+                            }
+
+                            if (dispatch.claimed as i64) < self.batch_size {
+                                break;
+                            }
+                        }
+                        Err(e) => {
+                            tracing::warn!("Failed to dispatch wake-ups: {}", e);
+                            break;
+                        }
+                    }
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,43 @@
+use std::time::Duration;
+
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+
+use crate::config::reaper::MessageReaperConfig;
+use crate::usecase::ChatService;
+
+/// Background task that deletes disappearing messages once they expire.
+/// Reads hide expired messages on their own, so the interval only bounds
+/// how long expired rows linger in the database.
+pub struct MessageReaper {
+    chat: ChatService,
+    interval: Duration,
+    batch_size: i64,
+}
+
+impl MessageReaper {
+    pub fn new(chat: ChatService, config: &MessageReaperConfig) -> Self {
+        Self {
+            chat,
+            interval: Duration::from_secs(config.interval_seconds),
+            batch_size: config.batch_size,
+        }
+    }
+
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(self.interval);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            loop {
+                ticker.tick().await;
+
+                match self.chat.reap_expired_messages(self.batch_size).await {
+                    Ok(0) => {}
+                    Ok(deleted) => tracing::info!(deleted, "Deleted expired messages"),
+                    Err(e) => tracing::warn!("Failed to delete expired messages: {}", e),
+                }
+            }
+        })
+    }
+}
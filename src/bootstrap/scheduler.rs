@@ -0,0 +1,61 @@
+use std::time::Duration;
+
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+
+use crate::config::scheduler::MessageSchedulerConfig;
+use crate::usecase::ChatService;
+
+/// Background task that posts scheduled messages once they are due. Every
+/// instance may run one: each message is claimed under a row lock that the
+/// others skip.
+pub struct MessageScheduler {
+    chat: ChatService,
+    interval: Duration,
+    batch_size: i64,
+}
+
+impl MessageScheduler {
+    pub fn new(chat: ChatService, config: &MessageSchedulerConfig) -> Self {
+        Self {
+            chat,
+            interval: Duration::from_secs(config.interval_seconds),
+            batch_size: config.batch_size,
+        }
+    }
+
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(self.interval);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            loop {
+                ticker.tick().await;
+
+                // A full batch means more may be due; keep going without
+                // waiting for the next tick.
+                loop {
+                    match self.chat.deliver_scheduled_messages(self.batch_size).await {
+                        Ok(delivery) => {
+                            if delivery.sent + delivery.failed > 0 {
+                                tracing::info!(
+                                    sent = delivery.sent,
+                                    failed = delivery.failed,
+                                    "Delivered scheduled messages"
+                                );
+                            }
+
+                            if ((delivery.sent + delivery.failed) as i64) < self.batch_size {
+                                break;
+                            }
+                        }
+                        Err(e) => {
+                            tracing::warn!("Failed to deliver scheduled messages: {}", e);
+                            break;
+                        }
+                    }
+                }
+            }
+        })
+    }
+}
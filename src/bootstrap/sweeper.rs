@@ -0,0 +1,44 @@
+use std::time::Duration;
+
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+
+use crate::config::attachments::AttachmentConfig;
+use crate::usecase::AttachmentService;
+
+/// Uploads deleted per pass of the sweep.
+const BATCH_SIZE: i64 = 500;
+
+/// Background task that deletes uploads that were abandoned part way or
+/// never sent, so their blobs stop counting against the store and the
+/// uploader's quota.
+pub struct UploadSweeper {
+    attachments: AttachmentService,
+    interval: Duration,
+}
+
+impl UploadSweeper {
+    pub fn new(attachments: AttachmentService, config: &AttachmentConfig) -> Self {
+        Self {
+            attachments,
+            interval: Duration::from_secs(config.sweep_interval_seconds),
+        }
+    }
+
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(self.interval);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            loop {
+                ticker.tick().await;
+
+                match self.attachments.sweep_stale_uploads(BATCH_SIZE).await {
+                    Ok(0) => {}
+                    Ok(deleted) => tracing::info!(deleted, "Deleted stale uploads"),
+                    Err(e) => tracing::warn!("Failed to delete stale uploads: {}", e),
+                }
+            }
+        })
+    }
+}
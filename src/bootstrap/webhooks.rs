@@ -0,0 +1,80 @@
+use std::time::Duration;
+
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+
+use crate::config::webhooks::WebhookConfig;
+use crate::usecase::WebhookService;
+
+/// Background task that fans outbox events out to webhooks and sends the
+/// deliveries that are due. Every instance may run one: outbox rows and
+/// claimed deliveries are locked or leased, so no event goes out twice.
+pub struct WebhookDispatcher {
+    webhooks: WebhookService,
+    interval: Duration,
+    batch_size: i64,
+}
+
+impl WebhookDispatcher {
+    pub fn new(webhooks: WebhookService, config: &WebhookConfig) -> Self {
+        Self {
+            webhooks,
+            interval: Duration::from_secs(config.interval_seconds),
+            batch_size: config.batch_size,
+        }
+    }
+
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(self.interval);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            loop {
+                ticker.tick().await;
+
+                self.dispatch_events().await;
+                self.deliver_due().await;
+            }
+        })
+    }
+
+    async fn dispatch_events(&self) {
+        loop {
+            match self.webhooks.dispatch_events(self.batch_size).await {
+                Ok(dispatched) if (dispatched as i64) < self.batch_size => break,
+                Ok(_) => {}
+                Err(e) => {
+                    tracing::warn!("Failed to dispatch webhook events: {}", e);
+                    break;
+                }
+            }
+        }
+    }
+
+    async fn deliver_due(&self) {
+        // A full batch means more may be due; keep going without waiting for
+        // the next tick.
+        loop {
+            match self.webhooks.deliver_due(self.batch_size).await {
+                Ok(dispatch) => {
+                    if dispatch.claimed > 0 {
+                        tracing::info!(
+                            delivered = dispatch.delivered,
+                            retried = dispatch.retried,
+                            failed = dispatch.failed,
+                            "Sent webhook deliveries"
+                        );
+                    }
+
+                    if (dispatch.claimed as i64) < self.batch_size {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to send webhook deliveries: {}", e);
+                    break;
+                }
+            }
+        }
+    }
+}
@@ -1,8 +1,17 @@
+pub mod admin;
+pub mod attachments;
 pub mod http;
 pub mod jwt;
+pub mod keys;
 pub mod logger;
+pub mod password;
 pub mod postgres;
+pub mod push;
+pub mod reaper;
 mod root;
+pub mod scheduler;
 pub mod telemetry;
+pub mod throttle;
+pub mod webhooks;
 
 pub use root::Config;
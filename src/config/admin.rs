@@ -0,0 +1,30 @@
+use std::env;
+use std::fmt;
+
+#[derive(Clone)]
+pub struct AdminConfig {
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+impl AdminConfig {
+    pub fn new() -> Result<Self, String> {
+        let username = env::var("ADMIN_USERNAME").ok();
+        let password = env::var("ADMIN_PASSWORD").ok();
+
+        if username.is_some() != password.is_some() {
+            return Err("ADMIN_USERNAME and ADMIN_PASSWORD must be set together".to_string());
+        }
+
+        Ok(Self { username, password })
+    }
+}
+
+impl fmt::Debug for AdminConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AdminConfig")
+            .field("username", &self.username)
+            .field("password", &self.password.as_ref().map(|_| "***"))
+            .finish()
+    }
+}
@@ -0,0 +1,129 @@
+use std::env;
+use std::fmt;
+use std::path::PathBuf;
+
+/// Where attachment blobs are kept.
+#[derive(Debug, Clone)]
+pub enum BlobStorageConfig {
+    Filesystem {
+        root: PathBuf,
+    },
+    S3(S3Config),
+    /// Process memory; for development and tests only.
+    Memory,
+}
+
+/// An S3-compatible bucket addressed path-style, such as MinIO.
+#[derive(Clone)]
+pub struct S3Config {
+    pub endpoint: String,
+    pub bucket: String,
+    pub region: String,
+    pub access_key: String,
+    pub secret_key: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct AttachmentConfig {
+    pub storage: BlobStorageConfig,
+    pub max_size: i64,
+    pub max_chunk_size: usize,
+    /// Bytes a user may have stored at once, counting unfinished uploads
+    /// at their full size.
+    pub user_quota: i64,
+    /// Age after which an upload no message references is deleted.
+    pub stale_upload_seconds: i64,
+    pub sweep_interval_seconds: u64,
+}
+
+impl AttachmentConfig {
+    pub fn new() -> Result<Self, String> {
+        let storage = match env::var("ATTACHMENT_STORAGE")
+            .unwrap_or_else(|_| "fs".to_string())
+            .as_str()
+        {
+            "fs" => BlobStorageConfig::Filesystem {
+                root: env::var("ATTACHMENT_FS_ROOT")
+                    .unwrap_or_else(|_| "data/attachments".to_string())
+                    .into(),
+            },
+            "s3" => BlobStorageConfig::S3(S3Config::new()?),
+            "memory" => BlobStorageConfig::Memory,
+            _ => return Err("Invalid ATTACHMENT_STORAGE, expected fs, s3 or memory".to_string()),
+        };
+
+        let max_size = env::var("ATTACHMENT_MAX_SIZE")
+            .unwrap_or_else(|_| "104857600".to_string())
+            .parse()
+            .map_err(|_| "Invalid ATTACHMENT_MAX_SIZE")?;
+
+        let max_chunk_size = env::var("ATTACHMENT_MAX_CHUNK_SIZE")
+            .unwrap_or_else(|_| "8388608".to_string())
+            .parse()
+            .map_err(|_| "Invalid ATTACHMENT_MAX_CHUNK_SIZE")?;
+
+        let user_quota: i64 = env::var("ATTACHMENT_USER_QUOTA")
+            .unwrap_or_else(|_| "1073741824".to_string())
+            .parse()
+            .map_err(|_| "Invalid ATTACHMENT_USER_QUOTA")?;
+
+        if user_quota <= 0 {
+            return Err("ATTACHMENT_USER_QUOTA must be positive".to_string());
+        }
+
+        let stale_upload_seconds: i64 = env::var("ATTACHMENT_STALE_UPLOAD_SECONDS")
+            .unwrap_or_else(|_| "86400".to_string())
+            .parse()
+            .map_err(|_| "Invalid ATTACHMENT_STALE_UPLOAD_SECONDS")?;
+
+        if stale_upload_seconds <= 0 {
+            return Err("ATTACHMENT_STALE_UPLOAD_SECONDS must be positive".to_string());
+        }
+
+        let sweep_interval_seconds: u64 = env::var("ATTACHMENT_SWEEP_INTERVAL_SECONDS")
+            .unwrap_or_else(|_| "3600".to_string())
+            .parse()
+            .map_err(|_| "Invalid ATTACHMENT_SWEEP_INTERVAL_SECONDS")?;
+
+        if sweep_interval_seconds == 0 {
+            return Err("ATTACHMENT_SWEEP_INTERVAL_SECONDS must be positive".to_string());
+        }
+
+        Ok(Self {
+            storage,
+            max_size,
+            max_chunk_size,
+            user_quota,
+            stale_upload_seconds,
+            sweep_interval_seconds,
+        })
+    }
+}
+
+impl S3Config {
+    fn new() -> Result<Self, String> {
+        let var = |name: &str| {
+            env::var(name).map_err(|_| format!("{} environment variable not set", name))
+        };
+
+        Ok(Self {
+            endpoint: var("S3_ENDPOINT")?,
+            bucket: var("S3_BUCKET")?,
+            region: env::var("S3_REGION").unwrap_or_else(|_| "us-east-1".to_string()),
+            access_key: var("S3_ACCESS_KEY")?,
+            secret_key: var("S3_SECRET_KEY")?,
+        })
+    }
+}
+
+impl fmt::Debug for S3Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("S3Config")
+            .field("endpoint", &self.endpoint)
+            .field("bucket", &self.bucket)
+            .field("region", &self.region)
+            .field("access_key", &self.access_key)
+            .field("secret_key", &"***")
+            .finish()
+    }
+}
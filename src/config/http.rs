@@ -1,9 +1,15 @@
 use std::env;
 
+use axum::http::HeaderName;
+
 #[derive(Debug, Clone)]
 pub struct HttpConfig {
     pub host: String,
     pub port: u16,
+    /// Header a reverse proxy in front of the server puts the client address
+    /// in. Without it the peer address counts as the client's, so behind a
+    /// proxy every client would share the proxy's address.
+    pub client_ip_header: Option<HeaderName>,
 }
 
 impl HttpConfig {
@@ -15,6 +21,15 @@ impl HttpConfig {
             .parse()
             .map_err(|_| "Invalid HTTP_PORT")?;
 
-        Ok(Self { host, port })
+        let client_ip_header = env::var("HTTP_CLIENT_IP_HEADER")
+            .ok()
+            .map(|name| HeaderName::try_from(name).map_err(|_| "Invalid HTTP_CLIENT_IP_HEADER"))
+            .transpose()?;
+
+        Ok(Self {
+            host,
+            port,
+            client_ip_header,
+        })
     }
 }
@@ -1,15 +1,115 @@
 use std::env;
+use std::fmt;
+use std::fs;
 
+use chrono::{DateTime, Utc};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JwtAlgorithm {
+    Hs256,
+    Rs256,
+    EdDsa,
+}
+
+/// Active asymmetric key: signs new tokens and is published in the JWKS.
+#[derive(Clone)]
+pub struct JwtSigningKey {
+    pub kid: String,
+    pub private_pem: String,
+    pub public_pem: String,
+}
+
+/// Retired public key that still verifies tokens issued before a rotation.
 #[derive(Debug, Clone)]
+pub struct JwtVerificationKey {
+    pub kid: String,
+    pub public_pem: String,
+}
+
+#[derive(Clone)]
 pub struct JwtConfig {
-    pub secret: String,
+    pub algorithm: JwtAlgorithm,
+    pub secret: Option<String>,
+    pub signing_key: Option<JwtSigningKey>,
+    pub retired_keys: Vec<JwtVerificationKey>,
+    /// After a move from HS256 to RS256/EdDSA, `kid`-less tokens signed with
+    /// `secret` are still accepted until then. Without it the secret is
+    /// ignored under asymmetric algorithms.
+    pub legacy_hs256_until: Option<DateTime<Utc>>,
     pub expiration_hours: i64,
 }
 
 impl JwtConfig {
     pub fn new() -> Result<Self, String> {
-        let secret =
-            env::var("JWT_SECRET").map_err(|_| "JWT_SECRET environment variable not set")?;
+        let algorithm = match env::var("JWT_ALGORITHM")
+            .unwrap_or_else(|_| "HS256".to_string())
+            .as_str()
+        {
+            "HS256" => JwtAlgorithm::Hs256,
+            "RS256" => JwtAlgorithm::Rs256,
+            "EdDSA" => JwtAlgorithm::EdDsa,
+            _ => return Err("Invalid JWT_ALGORITHM, expected HS256, RS256 or EdDSA".to_string()),
+        };
+
+        let secret = env::var("JWT_SECRET").ok();
+
+        let signing_key = match algorithm {
+            JwtAlgorithm::Hs256 => {
+                if secret.is_none() {
+                    return Err("JWT_SECRET environment variable not set".to_string());
+                }
+                None
+            }
+            JwtAlgorithm::Rs256 | JwtAlgorithm::EdDsa => {
+                let kid = env::var("JWT_KEY_ID")
+                    .map_err(|_| "JWT_KEY_ID environment variable not set")?;
+                let private_path = env::var("JWT_PRIVATE_KEY_PATH")
+                    .map_err(|_| "JWT_PRIVATE_KEY_PATH environment variable not set")?;
+                let public_path = env::var("JWT_PUBLIC_KEY_PATH")
+                    .map_err(|_| "JWT_PUBLIC_KEY_PATH environment variable not set")?;
+
+                Some(JwtSigningKey {
+                    kid,
+                    private_pem: read_pem(&private_path)?,
+                    public_pem: read_pem(&public_path)?,
+                })
+            }
+        };
+
+        let retired_keys = env::var("JWT_RETIRED_PUBLIC_KEYS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let (kid, path) = entry
+                    .split_once('=')
+                    .ok_or("Invalid JWT_RETIRED_PUBLIC_KEYS, expected kid=path entries")?;
+
+                Ok(JwtVerificationKey {
+                    kid: kid.trim().to_string(),
+                    public_pem: read_pem(path.trim())?,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let legacy_hs256_until = match env::var("JWT_LEGACY_HS256_UNTIL") {
+            Ok(until) => {
+                if algorithm == JwtAlgorithm::Hs256 {
+                    return Err(
+                        "JWT_LEGACY_HS256_UNTIL only applies to RS256 and EdDSA".to_string()
+                    );
+                }
+                if secret.is_none() {
+                    return Err("JWT_LEGACY_HS256_UNTIL needs JWT_SECRET".to_string());
+                }
+
+                let until = DateTime::parse_from_rfc3339(&until)
+                    .map_err(|_| "Invalid JWT_LEGACY_HS256_UNTIL, expected an RFC 3339 time")?;
+                Some(until.with_timezone(&Utc))
+            }
+            Err(_) => None,
+        };
 
         let expiration_hours = env::var("JWT_EXPIRATION_HOURS")
             .unwrap_or_else(|_| "24".to_string())
@@ -17,8 +117,39 @@ impl JwtConfig {
             .map_err(|_| "Invalid JWT_EXPIRATION_HOURS")?;
 
         Ok(Self {
+            algorithm,
             secret,
+            signing_key,
+            retired_keys,
+            legacy_hs256_until,
             expiration_hours,
         })
     }
 }
+
+fn read_pem(path: &str) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| format!("Failed to read key file {}: {}", path, e))
+}
+
+impl fmt::Debug for JwtSigningKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JwtSigningKey")
+            .field("kid", &self.kid)
+            .field("private_pem", &"***")
+            .field("public_pem", &self.public_pem)
+            .finish()
+    }
+}
+
+impl fmt::Debug for JwtConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JwtConfig")
+            .field("algorithm", &self.algorithm)
+            .field("secret", &self.secret.as_ref().map(|_| "***"))
+            .field("signing_key", &self.signing_key)
+            .field("retired_keys", &self.retired_keys)
+            .field("legacy_hs256_until", &self.legacy_hs256_until)
+            .field("expiration_hours", &self.expiration_hours)
+            .finish()
+    }
+}
@@ -0,0 +1,60 @@
+use std::env;
+use std::fmt;
+use std::fs;
+
+#[derive(Clone)]
+pub struct KeyDirectoryConfig {
+    pub prekey_low_watermark: i64,
+    pub max_prekeys_per_upload: usize,
+    pub max_stored_prekeys: i64,
+    /// PKCS#8 PEM of the Ed25519 key that signs key log tree heads.
+    pub log_signing_key_pem: Option<String>,
+}
+
+impl KeyDirectoryConfig {
+    pub fn new() -> Result<Self, String> {
+        let prekey_low_watermark = env::var("PREKEY_LOW_WATERMARK")
+            .unwrap_or_else(|_| "10".to_string())
+            .parse()
+            .map_err(|_| "Invalid PREKEY_LOW_WATERMARK")?;
+
+        let max_prekeys_per_upload = env::var("PREKEY_MAX_PER_UPLOAD")
+            .unwrap_or_else(|_| "100".to_string())
+            .parse()
+            .map_err(|_| "Invalid PREKEY_MAX_PER_UPLOAD")?;
+
+        let max_stored_prekeys = env::var("PREKEY_MAX_STORED")
+            .unwrap_or_else(|_| "500".to_string())
+            .parse()
+            .map_err(|_| "Invalid PREKEY_MAX_STORED")?;
+
+        let log_signing_key_pem = match env::var("KEY_LOG_SIGNING_KEY_PATH") {
+            Ok(path) => Some(
+                fs::read_to_string(&path)
+                    .map_err(|e| format!("Failed to read key file {}: {}", path, e))?,
+            ),
+            Err(_) => None,
+        };
+
+        Ok(Self {
+            prekey_low_watermark,
+            max_prekeys_per_upload,
+            max_stored_prekeys,
+            log_signing_key_pem,
+        })
+    }
+}
+
+impl fmt::Debug for KeyDirectoryConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyDirectoryConfig")
+            .field("prekey_low_watermark", &self.prekey_low_watermark)
+            .field("max_prekeys_per_upload", &self.max_prekeys_per_upload)
+            .field("max_stored_prekeys", &self.max_stored_prekeys)
+            .field(
+                "log_signing_key_pem",
+                &self.log_signing_key_pem.as_ref().map(|_| "***"),
+            )
+            .finish()
+    }
+}
@@ -0,0 +1,33 @@
+use std::env;
+
+#[derive(Debug, Clone)]
+pub struct PasswordHashConfig {
+    pub argon2_memory_kib: u32,
+    pub argon2_iterations: u32,
+    pub argon2_parallelism: u32,
+}
+
+impl PasswordHashConfig {
+    pub fn new() -> Result<Self, String> {
+        let argon2_memory_kib = env::var("ARGON2_MEMORY_KIB")
+            .unwrap_or_else(|_| "19456".to_string())
+            .parse()
+            .map_err(|_| "Invalid ARGON2_MEMORY_KIB")?;
+
+        let argon2_iterations = env::var("ARGON2_ITERATIONS")
+            .unwrap_or_else(|_| "2".to_string())
+            .parse()
+            .map_err(|_| "Invalid ARGON2_ITERATIONS")?;
+
+        let argon2_parallelism = env::var("ARGON2_PARALLELISM")
+            .unwrap_or_else(|_| "1".to_string())
+            .parse()
+            .map_err(|_| "Invalid ARGON2_PARALLELISM")?;
+
+        Ok(Self {
+            argon2_memory_kib,
+            argon2_iterations,
+            argon2_parallelism,
+        })
+    }
+}
@@ -0,0 +1,115 @@
+use std::env;
+use std::fmt;
+use std::fs;
+
+#[derive(Debug, Clone)]
+pub struct PushConfig {
+    pub interval_seconds: u64,
+    pub batch_size: i64,
+    /// Attempts per wake-up before it is dead-lettered.
+    pub max_attempts: i32,
+    /// Seconds before a failed wake-up is first retried.
+    pub retry_base_seconds: i64,
+    /// Devices that fetched messages this recently count as online and are
+    /// not woken up.
+    pub online_window_seconds: i64,
+    /// Signs Web Push requests; without it only webhook endpoints work.
+    pub vapid: Option<VapidConfig>,
+    /// Lets devices register endpoints on loopback or private addresses,
+    /// e.g. for a push gateway on the same network. Off by default, since
+    /// endpoint URLs come from users.
+    pub allow_private_endpoints: bool,
+}
+
+#[derive(Clone)]
+pub struct VapidConfig {
+    /// PKCS#8 PEM of the P-256 key that signs Web Push requests.
+    pub private_key_pem: String,
+    /// Contact for push services, a `mailto:` or `https:` URL.
+    pub subject: String,
+}
+
+impl PushConfig {
+    pub fn new() -> Result<Self, String> {
+        let interval_seconds: u64 = env::var("PUSH_DISPATCH_INTERVAL_SECONDS")
+            .unwrap_or_else(|_| "2".to_string())
+            .parse()
+            .map_err(|_| "Invalid PUSH_DISPATCH_INTERVAL_SECONDS")?;
+
+        if interval_seconds == 0 {
+            return Err("PUSH_DISPATCH_INTERVAL_SECONDS must be positive".to_string());
+        }
+
+        let batch_size: i64 = env::var("PUSH_BATCH_SIZE")
+            .unwrap_or_else(|_| "100".to_string())
+            .parse()
+            .map_err(|_| "Invalid PUSH_BATCH_SIZE")?;
+
+        if batch_size <= 0 {
+            return Err("PUSH_BATCH_SIZE must be positive".to_string());
+        }
+
+        let max_attempts: i32 = env::var("PUSH_MAX_ATTEMPTS")
+            .unwrap_or_else(|_| "8".to_string())
+            .parse()
+            .map_err(|_| "Invalid PUSH_MAX_ATTEMPTS")?;
+
+        if max_attempts <= 0 {
+            return Err("PUSH_MAX_ATTEMPTS must be positive".to_string());
+        }
+
+        let retry_base_seconds: i64 = env::var("PUSH_RETRY_BASE_SECONDS")
+            .unwrap_or_else(|_| "15".to_string())
+            .parse()
+            .map_err(|_| "Invalid PUSH_RETRY_BASE_SECONDS")?;
+
+        if retry_base_seconds <= 0 {
+            return Err("PUSH_RETRY_BASE_SECONDS must be positive".to_string());
+        }
+
+        let online_window_seconds: i64 = env::var("PUSH_ONLINE_WINDOW_SECONDS")
+            .unwrap_or_else(|_| "60".to_string())
+            .parse()
+            .map_err(|_| "Invalid PUSH_ONLINE_WINDOW_SECONDS")?;
+
+        if online_window_seconds < 0 {
+            return Err("PUSH_ONLINE_WINDOW_SECONDS cannot be negative".to_string());
+        }
+
+        let allow_private_endpoints: bool = env::var("PUSH_ALLOW_PRIVATE_ENDPOINTS")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse()
+            .map_err(|_| "Invalid PUSH_ALLOW_PRIVATE_ENDPOINTS")?;
+
+        let vapid = match env::var("PUSH_VAPID_PRIVATE_KEY_PATH") {
+            Ok(path) => Some(VapidConfig {
+                private_key_pem: fs::read_to_string(&path)
+                    .map_err(|e| format!("Failed to read key file {}: {}", path, e))?,
+                subject: env::var("PUSH_VAPID_SUBJECT").map_err(|_| {
+                    "PUSH_VAPID_SUBJECT must be set along with PUSH_VAPID_PRIVATE_KEY_PATH"
+                        .to_string()
+                })?,
+            }),
+            Err(_) => None,
+        };
+
+        Ok(Self {
+            interval_seconds,
+            batch_size,
+            max_attempts,
+            retry_base_seconds,
+            online_window_seconds,
+            vapid,
+            allow_private_endpoints,
+        })
+    }
+}
+
+impl fmt::Debug for VapidConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VapidConfig")
+            .field("private_key_pem", &"***")
+            .field("subject", &self.subject)
+            .finish()
+    }
+}
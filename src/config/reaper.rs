@@ -0,0 +1,34 @@
+use std::env;
+
+#[derive(Debug, Clone)]
+pub struct MessageReaperConfig {
+    pub interval_seconds: u64,
+    pub batch_size: i64,
+}
+
+impl MessageReaperConfig {
+    pub fn new() -> Result<Self, String> {
+        let interval_seconds: u64 = env::var("MESSAGE_REAPER_INTERVAL_SECONDS")
+            .unwrap_or_else(|_| "60".to_string())
+            .parse()
+            .map_err(|_| "Invalid MESSAGE_REAPER_INTERVAL_SECONDS")?;
+
+        if interval_seconds == 0 {
+            return Err("MESSAGE_REAPER_INTERVAL_SECONDS must be positive".to_string());
+        }
+
+        let batch_size: i64 = env::var("MESSAGE_REAPER_BATCH_SIZE")
+            .unwrap_or_else(|_| "1000".to_string())
+            .parse()
+            .map_err(|_| "Invalid MESSAGE_REAPER_BATCH_SIZE")?;
+
+        if batch_size <= 0 {
+            return Err("MESSAGE_REAPER_BATCH_SIZE must be positive".to_string());
+        }
+
+        Ok(Self {
+            interval_seconds,
+            batch_size,
+        })
+    }
+}
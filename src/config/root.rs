@@ -1,8 +1,17 @@
+use super::admin::AdminConfig;
+use super::attachments::AttachmentConfig;
 use super::http::HttpConfig;
 use super::jwt::JwtConfig;
+use super::keys::KeyDirectoryConfig;
 use super::logger::LoggerConfig;
+use super::password::PasswordHashConfig;
 use super::postgres::PostgresConfig;
+use super::push::PushConfig;
+use super::reaper::MessageReaperConfig;
+use super::scheduler::MessageSchedulerConfig;
 use super::telemetry::TelemetryConfig;
+use super::throttle::LoginThrottleConfig;
+use super::webhooks::WebhookConfig;
 
 #[derive(Debug)]
 pub struct Config {
@@ -11,6 +20,15 @@ pub struct Config {
     pub jaeger: TelemetryConfig,
     pub jwt: JwtConfig,
     pub http: HttpConfig,
+    pub login_throttle: LoginThrottleConfig,
+    pub admin: AdminConfig,
+    pub password: PasswordHashConfig,
+    pub keys: KeyDirectoryConfig,
+    pub attachments: AttachmentConfig,
+    pub message_reaper: MessageReaperConfig,
+    pub message_scheduler: MessageSchedulerConfig,
+    pub push: PushConfig,
+    pub webhooks: WebhookConfig,
 }
 
 impl Config {
@@ -22,6 +40,15 @@ impl Config {
         let jaeger = TelemetryConfig::new()?;
         let jwt = JwtConfig::new()?;
         let http = HttpConfig::new()?;
+        let login_throttle = LoginThrottleConfig::new()?;
+        let admin = AdminConfig::new()?;
+        let password = PasswordHashConfig::new()?;
+        let keys = KeyDirectoryConfig::new()?;
+        let attachments = AttachmentConfig::new()?;
+        let message_reaper = MessageReaperConfig::new()?;
+        let message_scheduler = MessageSchedulerConfig::new()?;
+        let push = PushConfig::new()?;
+        let webhooks = WebhookConfig::new()?;
 
         Ok(Config {
             postgres,
@@ -29,6 +56,15 @@ impl Config {
             jaeger,
             jwt,
             http,
+            login_throttle,
+            admin,
+            password,
+            keys,
+            attachments,
+            message_reaper,
+            message_scheduler,
+            push,
+            webhooks,
         })
     }
 }
@@ -0,0 +1,34 @@
+use std::env;
+
+#[derive(Debug, Clone)]
+pub struct MessageSchedulerConfig {
+    pub interval_seconds: u64,
+    pub batch_size: i64,
+}
+
+impl MessageSchedulerConfig {
+    pub fn new() -> Result<Self, String> {
+        let interval_seconds: u64 = env::var("MESSAGE_SCHEDULER_INTERVAL_SECONDS")
+            .unwrap_or_else(|_| "5".to_string())
+            .parse()
+            .map_err(|_| "Invalid MESSAGE_SCHEDULER_INTERVAL_SECONDS")?;
+
+        if interval_seconds == 0 {
+            return Err("MESSAGE_SCHEDULER_INTERVAL_SECONDS must be positive".to_string());
+        }
+
+        let batch_size: i64 = env::var("MESSAGE_SCHEDULER_BATCH_SIZE")
+            .unwrap_or_else(|_| "100".to_string())
+            .parse()
+            .map_err(|_| "Invalid MESSAGE_SCHEDULER_BATCH_SIZE")?;
+
+        if batch_size <= 0 {
+            return Err("MESSAGE_SCHEDULER_BATCH_SIZE must be positive".to_string());
+        }
+
+        Ok(Self {
+            interval_seconds,
+            batch_size,
+        })
+    }
+}
@@ -0,0 +1,47 @@
+use std::env;
+
+#[derive(Debug, Clone)]
+pub struct LoginThrottleConfig {
+    pub max_failures_per_username: u32,
+    pub max_failures_per_ip: u32,
+    pub failure_window_seconds: u64,
+    pub lockout_base_seconds: u64,
+    pub lockout_max_seconds: u64,
+}
+
+impl LoginThrottleConfig {
+    pub fn new() -> Result<Self, String> {
+        let max_failures_per_username = env::var("LOGIN_MAX_FAILURES_PER_USERNAME")
+            .unwrap_or_else(|_| "5".to_string())
+            .parse()
+            .map_err(|_| "Invalid LOGIN_MAX_FAILURES_PER_USERNAME")?;
+
+        let max_failures_per_ip = env::var("LOGIN_MAX_FAILURES_PER_IP")
+            .unwrap_or_else(|_| "20".to_string())
+            .parse()
+            .map_err(|_| "Invalid LOGIN_MAX_FAILURES_PER_IP")?;
+
+        let failure_window_seconds = env::var("LOGIN_FAILURE_WINDOW_SECONDS")
+            .unwrap_or_else(|_| "900".to_string())
+            .parse()
+            .map_err(|_| "Invalid LOGIN_FAILURE_WINDOW_SECONDS")?;
+
+        let lockout_base_seconds = env::var("LOGIN_LOCKOUT_BASE_SECONDS")
+            .unwrap_or_else(|_| "30".to_string())
+            .parse()
+            .map_err(|_| "Invalid LOGIN_LOCKOUT_BASE_SECONDS")?;
+
+        let lockout_max_seconds = env::var("LOGIN_LOCKOUT_MAX_SECONDS")
+            .unwrap_or_else(|_| "3600".to_string())
+            .parse()
+            .map_err(|_| "Invalid LOGIN_LOCKOUT_MAX_SECONDS")?;
+
+        Ok(Self {
+            max_failures_per_username,
+            max_failures_per_ip,
+            failure_window_seconds,
+            lockout_base_seconds,
+            lockout_max_seconds,
+        })
+    }
+}
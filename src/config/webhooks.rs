@@ -0,0 +1,68 @@
+use std::env;
+
+#[derive(Debug, Clone)]
+pub struct WebhookConfig {
+    pub interval_seconds: u64,
+    /// Events fanned out, and deliveries attempted, per round.
+    pub batch_size: i64,
+    /// Attempts per delivery before it is marked failed.
+    pub max_attempts: i32,
+    /// Seconds before a failed delivery is first retried.
+    pub retry_base_seconds: i64,
+    /// Lets webhooks post to loopback or private addresses. Off by default,
+    /// since chat creators pick webhook URLs.
+    pub allow_private_urls: bool,
+}
+
+impl WebhookConfig {
+    pub fn new() -> Result<Self, String> {
+        let interval_seconds: u64 = env::var("WEBHOOK_DISPATCH_INTERVAL_SECONDS")
+            .unwrap_or_else(|_| "2".to_string())
+            .parse()
+            .map_err(|_| "Invalid WEBHOOK_DISPATCH_INTERVAL_SECONDS")?;
+
+        if interval_seconds == 0 {
+            return Err("WEBHOOK_DISPATCH_INTERVAL_SECONDS must be positive".to_string());
+        }
+
+        let batch_size: i64 = env::var("WEBHOOK_BATCH_SIZE")
+            .unwrap_or_else(|_| "100".to_string())
+            .parse()
+            .map_err(|_| "Invalid WEBHOOK_BATCH_SIZE")?;
+
+        if batch_size <= 0 {
+            return Err("WEBHOOK_BATCH_SIZE must be positive".to_string());
+        }
+
+        let max_attempts: i32 = env::var("WEBHOOK_MAX_ATTEMPTS")
+            .unwrap_or_else(|_| "10".to_string())
+            .parse()
+            .map_err(|_| "Invalid WEBHOOK_MAX_ATTEMPTS")?;
+
+        if max_attempts <= 0 {
+            return Err("WEBHOOK_MAX_ATTEMPTS must be positive".to_string());
+        }
+
+        let retry_base_seconds: i64 = env::var("WEBHOOK_RETRY_BASE_SECONDS")
+            .unwrap_or_else(|_| "30".to_string())
+            .parse()
+            .map_err(|_| "Invalid WEBHOOK_RETRY_BASE_SECONDS")?;
+
+        if retry_base_seconds <= 0 {
+            return Err("WEBHOOK_RETRY_BASE_SECONDS must be positive".to_string());
+        }
+
+        let allow_private_urls: bool = env::var("WEBHOOK_ALLOW_PRIVATE_URLS")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse()
+            .map_err(|_| "Invalid WEBHOOK_ALLOW_PRIVATE_URLS")?;
+
+        Ok(Self {
+            interval_seconds,
+            batch_size,
+            max_attempts,
+            retry_base_seconds,
+            allow_private_urls,
+        })
+    }
+}
@@ -2,7 +2,7 @@ use msg_service::bootstrap::App;
 
 #[tokio::main]
 async fn main() {
-    match App::new() {
+    match App::new().await {
         Ok(app) => {
             if let Err(e) = app.run().await {
                 tracing::error!("Application error: {}", e);
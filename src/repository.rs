@@ -1,6 +1,24 @@
+pub mod attachments;
 pub mod auth;
+pub mod blobs;
 pub mod chat;
+pub mod connection;
+pub mod devices;
+pub mod error;
 mod factory;
+pub mod http_client;
+pub mod key_log;
+pub mod keys;
+pub mod memory;
+pub mod push;
+pub mod push_providers;
 mod root;
+pub mod scheduled_messages;
+pub mod sender_keys;
+pub mod transaction;
+pub mod webhook_sender;
+pub mod webhooks;
 
+pub use error::RepositoryError;
 pub use root::Repository;
+pub use transaction::{Transaction, UnitOfWork};
@@ -0,0 +1,7 @@
+pub mod memory;
+pub mod models;
+pub mod repo;
+
+pub use memory::InMemoryAttachmentRepository;
+pub use models::{Attachment, AttachmentChunk, NewAttachment};
+pub use repo::{AttachmentRepo, AttachmentRepository};
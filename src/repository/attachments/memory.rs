@@ -0,0 +1,244 @@
+use std::sync::Arc;
+
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+
+use super::models::{Attachment, AttachmentChunk, NewAttachment};
+use super::repo::AttachmentRepo;
+use crate::repository::RepositoryError;
+use crate::repository::memory::{MemoryStore, ensure_user, now};
+
+/// [`AttachmentRepo`] over a [`MemoryStore`].
+#[derive(Clone)]
+pub struct InMemoryAttachmentRepository {
+    store: Arc<MemoryStore>,
+}
+
+impl InMemoryAttachmentRepository {
+    pub fn new(store: Arc<MemoryStore>) -> Self {
+        Self { store }
+    }
+}
+
+impl AttachmentRepo for InMemoryAttachmentRepository {
+    fn create_attachment(&self, attachment: NewAttachment) -> Result<Attachment, RepositoryError> {
+        let mut tables = self.store.write();
+
+        ensure_user(&tables, attachment.uploader_id)?;
+
+        if attachment.size <= 0 {
+            return Err(RepositoryError::Conflict(
+                "attachment size must be positive".to_string(),
+            ));
+        }
+
+        let attachment = Attachment {
+            id: Uuid::new_v4(),
+            uploader_id: attachment.uploader_id,
+            size: attachment.size,
+            uploaded_size: 0,
+            completed_at: None,
+            created_at: now(),
+        };
+        tables.attachments.push(attachment.clone());
+
+        Ok(attachment)
+    }
+
+    fn find_attachment(&self, attachment_id: Uuid) -> Result<Option<Attachment>, RepositoryError> {
+        let tables = self.store.read();
+
+        Ok(tables
+            .attachments
+            .iter()
+            .find(|a| a.id == attachment_id)
+            .cloned())
+    }
+
+    // Transactions over the store detect concurrent writers at commit time,
+    // which stands in for the row lock.
+    fn lock_attachment(&self, attachment_id: Uuid) -> Result<Option<Attachment>, RepositoryError> {
+        self.find_attachment(attachment_id)
+    }
+
+    fn add_chunk(&self, chunk: AttachmentChunk) -> Result<Attachment, RepositoryError> {
+        let mut tables = self.store.write();
+
+        if tables
+            .attachment_chunks
+            .iter()
+            .any(|c| c.attachment_id == chunk.attachment_id && c.chunk_offset == chunk.chunk_offset)
+        {
+            return Err(RepositoryError::Conflict(format!(
+                "chunk at offset {} already exists",
+                chunk.chunk_offset
+            )));
+        }
+
+        let attachment = tables
+            .attachments
+            .iter_mut()
+            .find(|a| a.id == chunk.attachment_id)
+            .ok_or(RepositoryError::NotFound)?;
+
+        if attachment.uploaded_size + chunk.size > attachment.size {
+            return Err(RepositoryError::Conflict(
+                "chunk exceeds the attachment size".to_string(),
+            ));
+        }
+
+        attachment.uploaded_size += chunk.size;
+        if attachment.uploaded_size == attachment.size {
+            attachment.completed_at = Some(now());
+        }
+        let attachment = attachment.clone();
+
+        tables.attachment_chunks.push(chunk);
+
+        Ok(attachment)
+    }
+
+    fn get_chunks(&self, attachment_id: Uuid) -> Result<Vec<AttachmentChunk>, RepositoryError> {
+        let tables = self.store.read();
+
+        let mut chunks: Vec<AttachmentChunk> = tables
+            .attachment_chunks
+            .iter()
+            .filter(|c| c.attachment_id == attachment_id)
+            .cloned()
+            .collect();
+        chunks.sort_by_key(|c| c.chunk_offset);
+
+        Ok(chunks)
+    }
+
+    fn attach_to_message(
+        &self,
+        message_id: Uuid,
+        attachment_ids: &[Uuid],
+    ) -> Result<usize, RepositoryError> {
+        let mut tables = self.store.write();
+
+        if !tables.messages.iter().any(|m| m.id == message_id) {
+            return Err(RepositoryError::MissingReference(format!(
+                "message {} does not exist",
+                message_id
+            )));
+        }
+
+        for (index, attachment_id) in attachment_ids.iter().enumerate() {
+            if !tables.attachments.iter().any(|a| a.id == *attachment_id) {
+                return Err(RepositoryError::MissingReference(format!(
+                    "attachment {} does not exist",
+                    attachment_id
+                )));
+            }
+
+            if attachment_ids[..index].contains(attachment_id)
+                || tables
+                    .message_attachments
+                    .contains(&(message_id, *attachment_id))
+            {
+                return Err(RepositoryError::Conflict(format!(
+                    "attachment {} is already on the message",
+                    attachment_id
+                )));
+            }
+        }
+
+        for attachment_id in attachment_ids {
+            tables
+                .message_attachments
+                .push((message_id, *attachment_id));
+        }
+
+        Ok(attachment_ids.len())
+    }
+
+    fn get_message_attachments(
+        &self,
+        message_ids: &[Uuid],
+    ) -> Result<Vec<(Uuid, Uuid)>, RepositoryError> {
+        let tables = self.store.read();
+
+        Ok(tables
+            .message_attachments
+            .iter()
+            .filter(|(message_id, _)| message_ids.contains(message_id))
+            .copied()
+            .collect())
+    }
+
+    fn is_shared_with(&self, attachment_id: Uuid, user_id: Uuid) -> Result<bool, RepositoryError> {
+        let tables = self.store.read();
+        let now = now();
+
+        Ok(tables
+            .message_attachments
+            .iter()
+            .filter(|(_, a)| *a == attachment_id)
+            .filter_map(|(message_id, _)| tables.messages.iter().find(|m| m.id == *message_id))
+            .filter(|message| message.expires_at.is_none_or(|expires_at| expires_at > now))
+            .any(|message| {
+                tables
+                    .chat_members
+                    .iter()
+                    .any(|m| m.chat_id == message.chat_id && m.user_id == user_id)
+            }))
+    }
+
+    fn find_unattached(
+        &self,
+        created_before: NaiveDateTime,
+        limit: i64,
+    ) -> Result<Vec<Uuid>, RepositoryError> {
+        let tables = self.store.read();
+
+        let mut unattached: Vec<&Attachment> = tables
+            .attachments
+            .iter()
+            .filter(|a| a.created_at < created_before)
+            .filter(|a| !tables.message_attachments.iter().any(|(_, id)| *id == a.id))
+            .collect();
+        unattached.sort_by_key(|a| a.created_at);
+
+        Ok(unattached
+            .into_iter()
+            .take(limit.max(0) as usize)
+            .map(|a| a.id)
+            .collect())
+    }
+
+    fn storage_used(&self, uploader_id: Uuid) -> Result<i64, RepositoryError> {
+        let tables = self.store.read();
+
+        Ok(tables
+            .attachments
+            .iter()
+            .filter(|a| a.uploader_id == uploader_id)
+            .map(|a| a.size)
+            .sum())
+    }
+
+    fn delete_unattached(
+        &self,
+        attachment_ids: &[Uuid],
+    ) -> Result<Vec<AttachmentChunk>, RepositoryError> {
+        let mut tables = self.store.write();
+
+        let unattached: Vec<Uuid> = attachment_ids
+            .iter()
+            .copied()
+            .filter(|id| tables.attachments.iter().any(|a| a.id == *id))
+            .filter(|id| !tables.message_attachments.iter().any(|(_, a)| a == id))
+            .collect();
+
+        let (chunks, kept) = std::mem::take(&mut tables.attachment_chunks)
+            .into_iter()
+            .partition(|c| unattached.contains(&c.attachment_id));
+        tables.attachment_chunks = kept;
+        tables.attachments.retain(|a| !unattached.contains(&a.id));
+
+        Ok(chunks)
+    }
+}
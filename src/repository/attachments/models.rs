@@ -0,0 +1,41 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use uuid::Uuid;
+
+use crate::schema::{attachment_chunks, attachments};
+
+/// An encrypted blob uploaded by `uploader_id`. It is usable once
+/// `completed_at` is set, i.e. all `size` bytes arrived.
+#[derive(Debug, Clone, Queryable, Selectable, Identifiable)]
+#[diesel(table_name = attachments)]
+pub struct Attachment {
+    pub id: Uuid,
+    pub uploader_id: Uuid,
+    pub size: i64,
+    pub uploaded_size: i64,
+    pub completed_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = attachments)]
+pub struct NewAttachment {
+    pub uploader_id: Uuid,
+    pub size: i64,
+}
+
+/// A stored piece of an attachment, starting at `chunk_offset`.
+#[derive(Debug, Clone, Queryable, Selectable, Insertable)]
+#[diesel(table_name = attachment_chunks)]
+pub struct AttachmentChunk {
+    pub attachment_id: Uuid,
+    pub chunk_offset: i64,
+    pub size: i64,
+}
+
+impl AttachmentChunk {
+    /// Where the chunk's bytes live in the blob store.
+    pub fn blob_key(&self) -> String {
+        format!("{}/{:020}", self.attachment_id, self.chunk_offset)
+    }
+}
@@ -0,0 +1,290 @@
+use chrono::{NaiveDateTime, Utc};
+use diesel::dsl::sql;
+use diesel::prelude::*;
+use diesel::sql_types::BigInt;
+use uuid::Uuid;
+
+use super::models::{Attachment, AttachmentChunk, NewAttachment};
+use crate::repository::RepositoryError;
+use crate::repository::connection::PgSource;
+use crate::schema::{attachment_chunks, attachments, chat_members, message_attachments, messages};
+
+pub trait AttachmentRepo: Send + Sync {
+    fn create_attachment(&self, attachment: NewAttachment) -> Result<Attachment, RepositoryError>;
+
+    fn find_attachment(&self, attachment_id: Uuid) -> Result<Option<Attachment>, RepositoryError>;
+
+    /// Like [`AttachmentRepo::find_attachment`], but holds a row lock until
+    /// the surrounding transaction ends, so chunk appends are serialized.
+    fn lock_attachment(&self, attachment_id: Uuid) -> Result<Option<Attachment>, RepositoryError>;
+
+    /// Records a chunk and advances `uploaded_size`, completing the
+    /// attachment when it reaches `size`.
+    fn add_chunk(&self, chunk: AttachmentChunk) -> Result<Attachment, RepositoryError>;
+
+    /// Chunks in offset order.
+    fn get_chunks(&self, attachment_id: Uuid) -> Result<Vec<AttachmentChunk>, RepositoryError>;
+
+    fn attach_to_message(
+        &self,
+        message_id: Uuid,
+        attachment_ids: &[Uuid],
+    ) -> Result<usize, RepositoryError>;
+
+    /// `(message_id, attachment_id)` pairs of the given messages.
+    fn get_message_attachments(
+        &self,
+        message_ids: &[Uuid],
+    ) -> Result<Vec<(Uuid, Uuid)>, RepositoryError>;
+
+    /// Whether the user is in a chat with a message referencing the
+    /// attachment.
+    fn is_shared_with(&self, attachment_id: Uuid, user_id: Uuid) -> Result<bool, RepositoryError>;
+
+    /// Up to `limit` attachments created before `created_before` that no
+    /// message references, finished or not.
+    fn find_unattached(
+        &self,
+        created_before: NaiveDateTime,
+        limit: i64,
+    ) -> Result<Vec<Uuid>, RepositoryError>;
+
+    /// Total size of the user's attachments, unfinished ones included.
+    fn storage_used(&self, uploader_id: Uuid) -> Result<i64, RepositoryError>;
+
+    /// Deletes those of the given attachments that no message references
+    /// and returns their chunks, whose blobs are left to the caller.
+    fn delete_unattached(
+        &self,
+        attachment_ids: &[Uuid],
+    ) -> Result<Vec<AttachmentChunk>, RepositoryError>;
+}
+
+#[derive(Clone)]
+pub struct AttachmentRepository {
+    db: PgSource,
+}
+
+impl AttachmentRepository {
+    pub fn new(db: PgSource) -> Self {
+        Self { db }
+    }
+}
+
+impl AttachmentRepo for AttachmentRepository {
+    #[tracing::instrument(skip(self, attachment), fields(size = attachment.size))]
+    fn create_attachment(&self, attachment: NewAttachment) -> Result<Attachment, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        diesel::insert_into(attachments::table)
+            .values(&attachment)
+            .returning(Attachment::as_returning())
+            .get_result(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    fn find_attachment(&self, attachment_id: Uuid) -> Result<Option<Attachment>, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        attachments::table
+            .find(attachment_id)
+            .select(Attachment::as_select())
+            .first(&mut *conn)
+            .optional()
+            .map_err(RepositoryError::from)
+    }
+
+    fn lock_attachment(&self, attachment_id: Uuid) -> Result<Option<Attachment>, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        attachments::table
+            .find(attachment_id)
+            .select(Attachment::as_select())
+            .for_update()
+            .first(&mut *conn)
+            .optional()
+            .map_err(RepositoryError::from)
+    }
+
+    #[tracing::instrument(skip(self, chunk), fields(attachment_id = %chunk.attachment_id, offset = chunk.chunk_offset))]
+    fn add_chunk(&self, chunk: AttachmentChunk) -> Result<Attachment, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        conn.transaction(|conn| {
+            diesel::insert_into(attachment_chunks::table)
+                .values(&chunk)
+                .execute(conn)?;
+
+            let attachment = diesel::update(attachments::table.find(chunk.attachment_id))
+                .set(attachments::uploaded_size.eq(attachments::uploaded_size + chunk.size))
+                .returning(Attachment::as_returning())
+                .get_result::<Attachment>(conn)?;
+
+            if attachment.uploaded_size < attachment.size {
+                return Ok(attachment);
+            }
+
+            diesel::update(attachments::table.find(chunk.attachment_id))
+                .set(attachments::completed_at.eq(diesel::dsl::now.nullable()))
+                .returning(Attachment::as_returning())
+                .get_result(conn)
+        })
+        .map_err(RepositoryError::from)
+    }
+
+    fn get_chunks(&self, attachment_id: Uuid) -> Result<Vec<AttachmentChunk>, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        attachment_chunks::table
+            .filter(attachment_chunks::attachment_id.eq(attachment_id))
+            .order(attachment_chunks::chunk_offset.asc())
+            .select(AttachmentChunk::as_select())
+            .load(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    #[tracing::instrument(skip(self, attachment_ids), fields(count = attachment_ids.len()))]
+    fn attach_to_message(
+        &self,
+        message_id: Uuid,
+        attachment_ids: &[Uuid],
+    ) -> Result<usize, RepositoryError> {
+        if attachment_ids.is_empty() {
+            return Ok(0);
+        }
+
+        let mut conn = self.db.conn()?;
+
+        let rows: Vec<_> = attachment_ids
+            .iter()
+            .map(|attachment_id| {
+                (
+                    message_attachments::message_id.eq(message_id),
+                    message_attachments::attachment_id.eq(*attachment_id),
+                )
+            })
+            .collect();
+
+        diesel::insert_into(message_attachments::table)
+            .values(&rows)
+            .execute(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    fn get_message_attachments(
+        &self,
+        message_ids: &[Uuid],
+    ) -> Result<Vec<(Uuid, Uuid)>, RepositoryError> {
+        if message_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut conn = self.db.conn()?;
+
+        message_attachments::table
+            .filter(message_attachments::message_id.eq_any(message_ids))
+            .select((
+                message_attachments::message_id,
+                message_attachments::attachment_id,
+            ))
+            .load(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    fn is_shared_with(&self, attachment_id: Uuid, user_id: Uuid) -> Result<bool, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        diesel::select(diesel::dsl::exists(
+            message_attachments::table
+                .inner_join(messages::table)
+                .inner_join(
+                    chat_members::table.on(chat_members::chat_id
+                        .eq(messages::chat_id)
+                        .and(chat_members::user_id.eq(user_id))),
+                )
+                .filter(message_attachments::attachment_id.eq(attachment_id))
+                .filter(
+                    messages::expires_at
+                        .is_null()
+                        .or(messages::expires_at.gt(Utc::now().naive_utc())),
+                ),
+        ))
+        .get_result(&mut *conn)
+        .map_err(RepositoryError::from)
+    }
+
+    fn find_unattached(
+        &self,
+        created_before: NaiveDateTime,
+        limit: i64,
+    ) -> Result<Vec<Uuid>, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        attachments::table
+            .filter(attachments::created_at.lt(created_before))
+            .filter(diesel::dsl::not(diesel::dsl::exists(
+                message_attachments::table
+                    .filter(message_attachments::attachment_id.eq(attachments::id)),
+            )))
+            .order(attachments::created_at.asc())
+            .select(attachments::id)
+            .limit(limit)
+            .load(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    fn storage_used(&self, uploader_id: Uuid) -> Result<i64, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        attachments::table
+            .filter(attachments::uploader_id.eq(uploader_id))
+            // SUM over BIGINT is NUMERIC in Postgres.
+            .select(sql::<BigInt>("COALESCE(SUM(size), 0)::BIGINT"))
+            .first(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    #[tracing::instrument(skip(self, attachment_ids), fields(count = attachment_ids.len()))]
+    fn delete_unattached(
+        &self,
+        attachment_ids: &[Uuid],
+    ) -> Result<Vec<AttachmentChunk>, RepositoryError> {
+        if attachment_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut conn = self.db.conn()?;
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            // Locking first makes a concurrent send either commit its
+            // reference before the check below or fail on the foreign key.
+            let locked: Vec<Uuid> = attachments::table
+                .filter(attachments::id.eq_any(attachment_ids))
+                .select(attachments::id)
+                .for_update()
+                .load(conn)?;
+
+            let referenced: Vec<Uuid> = message_attachments::table
+                .filter(message_attachments::attachment_id.eq_any(&locked))
+                .select(message_attachments::attachment_id)
+                .distinct()
+                .load(conn)?;
+
+            let unattached: Vec<Uuid> = locked
+                .into_iter()
+                .filter(|id| !referenced.contains(id))
+                .collect();
+
+            let chunks = attachment_chunks::table
+                .filter(attachment_chunks::attachment_id.eq_any(&unattached))
+                .select(AttachmentChunk::as_select())
+                .load(conn)?;
+
+            diesel::delete(attachments::table.filter(attachments::id.eq_any(&unattached)))
+                .execute(conn)?;
+
+            Ok(chunks)
+        })
+        .map_err(RepositoryError::from)
+    }
+}
@@ -1,5 +1,7 @@
+pub mod memory;
 pub mod models;
 pub mod repo;
 
-pub use models::{AuthUser, NewAuthUser, Role};
-pub use repo::AuthRepository;
+pub use memory::InMemoryAuthRepository;
+pub use models::{AuthUser, NewAuthUser, NewRole, NewRolePermission, Permission, Role};
+pub use repo::{AuthRepo, AuthRepository};
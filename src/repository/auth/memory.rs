@@ -0,0 +1,300 @@
+use std::sync::Arc;
+
+use uuid::Uuid;
+
+use super::models::{AuthUser, NewAuthUser, NewRole, Permission, Role};
+use super::repo::AuthRepo;
+use crate::repository::RepositoryError;
+use crate::repository::memory::{MemoryStore, Tables, now, paginate};
+
+/// [`AuthRepo`] over a [`MemoryStore`].
+#[derive(Clone)]
+pub struct InMemoryAuthRepository {
+    store: Arc<MemoryStore>,
+}
+
+impl InMemoryAuthRepository {
+    pub fn new(store: Arc<MemoryStore>) -> Self {
+        Self { store }
+    }
+}
+
+impl AuthRepo for InMemoryAuthRepository {
+    fn create_user(&self, new_user: NewAuthUser) -> Result<AuthUser, RepositoryError> {
+        let mut tables = self.store.write();
+
+        if tables
+            .auth_users
+            .iter()
+            .any(|u| u.username == new_user.username)
+        {
+            return Err(RepositoryError::Conflict(
+                "duplicate key value violates unique constraint \"auth_users_username_key\""
+                    .to_string(),
+            ));
+        }
+        ensure_role(&tables, new_user.role_id)?;
+
+        let now = now();
+        let user = AuthUser {
+            id: Uuid::new_v4(),
+            username: new_user.username,
+            password_hash: new_user.password_hash,
+            role_id: new_user.role_id,
+            is_active: true,
+            created_at: now,
+            updated_at: now,
+            token_version: 0,
+        };
+        tables.auth_users.push(user.clone());
+
+        Ok(user)
+    }
+
+    fn find_by_id(&self, user_id: Uuid) -> Result<Option<AuthUser>, RepositoryError> {
+        let tables = self.store.read();
+        Ok(tables.auth_users.iter().find(|u| u.id == user_id).cloned())
+    }
+
+    fn find_by_username(&self, username: &str) -> Result<Option<AuthUser>, RepositoryError> {
+        let tables = self.store.read();
+        Ok(tables
+            .auth_users
+            .iter()
+            .find(|u| u.username == username)
+            .cloned())
+    }
+
+    fn username_exists(&self, username: &str) -> Result<bool, RepositoryError> {
+        let tables = self.store.read();
+        Ok(tables.auth_users.iter().any(|u| u.username == username))
+    }
+
+    fn find_role_by_id(&self, role_id: i32) -> Result<Option<Role>, RepositoryError> {
+        let tables = self.store.read();
+        Ok(tables.roles.iter().find(|r| r.id == role_id).cloned())
+    }
+
+    fn find_all_roles(&self) -> Result<Vec<Role>, RepositoryError> {
+        let tables = self.store.read();
+        let mut roles = tables.roles.clone();
+        roles.sort_by_key(|r| r.id);
+        Ok(roles)
+    }
+
+    fn create_role(&self, new_role: NewRole) -> Result<Role, RepositoryError> {
+        let mut tables = self.store.write();
+
+        if tables.roles.iter().any(|r| r.name == new_role.name) {
+            return Err(RepositoryError::Conflict(
+                "duplicate key value violates unique constraint \"roles_name_key\"".to_string(),
+            ));
+        }
+
+        let role = Role {
+            id: tables.roles.iter().map(|r| r.id).max().unwrap_or(0) + 1,
+            name: new_role.name,
+            description: new_role.description,
+            created_at: now(),
+        };
+        tables.roles.push(role.clone());
+
+        Ok(role)
+    }
+
+    fn role_name_exists(&self, name: &str) -> Result<bool, RepositoryError> {
+        let tables = self.store.read();
+        Ok(tables.roles.iter().any(|r| r.name == name))
+    }
+
+    fn find_all_permissions(&self) -> Result<Vec<Permission>, RepositoryError> {
+        let tables = self.store.read();
+        let mut permissions = tables.permissions.clone();
+        permissions.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(permissions)
+    }
+
+    fn find_permissions_by_names(
+        &self,
+        names: &[String],
+    ) -> Result<Vec<Permission>, RepositoryError> {
+        let tables = self.store.read();
+        Ok(tables
+            .permissions
+            .iter()
+            .filter(|p| names.contains(&p.name))
+            .cloned()
+            .collect())
+    }
+
+    fn find_role_permissions(&self, role_id: i32) -> Result<Vec<String>, RepositoryError> {
+        let tables = self.store.read();
+        let mut names: Vec<String> = role_permission_names(&tables)
+            .filter(|(id, _)| *id == role_id)
+            .map(|(_, name)| name)
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    fn find_all_role_permissions(&self) -> Result<Vec<(i32, String)>, RepositoryError> {
+        let tables = self.store.read();
+        let mut pairs: Vec<(i32, String)> = role_permission_names(&tables).collect();
+        pairs.sort_by(|a, b| a.1.cmp(&b.1));
+        Ok(pairs)
+    }
+
+    fn set_role_permissions(
+        &self,
+        role_id: i32,
+        permission_ids: &[i32],
+    ) -> Result<(), RepositoryError> {
+        let mut tables = self.store.write();
+
+        ensure_role(&tables, role_id)?;
+        if let Some(unknown) = permission_ids
+            .iter()
+            .find(|id| !tables.permissions.iter().any(|p| p.id == **id))
+        {
+            return Err(RepositoryError::MissingReference(format!(
+                "permission {} does not exist",
+                unknown
+            )));
+        }
+
+        tables.role_permissions.retain(|(id, _)| *id != role_id);
+        for &permission_id in permission_ids {
+            if !tables.role_permissions.contains(&(role_id, permission_id)) {
+                tables.role_permissions.push((role_id, permission_id));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn update_user_role(
+        &self,
+        user_id: Uuid,
+        new_role_id: i32,
+    ) -> Result<AuthUser, RepositoryError> {
+        let mut tables = self.store.write();
+        ensure_role(&tables, new_role_id)?;
+
+        update_user(&mut tables, user_id, |user| user.role_id = new_role_id)
+    }
+
+    fn update_password_hash(
+        &self,
+        user_id: Uuid,
+        password_hash: &str,
+    ) -> Result<(), RepositoryError> {
+        let mut tables = self.store.write();
+
+        // Like an UPDATE without RETURNING, a missing row is not an error.
+        match update_user(&mut tables, user_id, |user| {
+            user.password_hash = password_hash.to_string()
+        }) {
+            Ok(_) | Err(RepositoryError::NotFound) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn list_users(
+        &self,
+        search: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<(AuthUser, Role)>, RepositoryError> {
+        let tables = self.store.read();
+
+        let mut users: Vec<&AuthUser> = tables
+            .auth_users
+            .iter()
+            .filter(|u| matches_search(u, search))
+            .collect();
+        users.sort_by(|a, b| a.username.cmp(&b.username));
+
+        let rows = users.into_iter().filter_map(|user| {
+            let role = tables.roles.iter().find(|r| r.id == user.role_id)?;
+            Some((user.clone(), role.clone()))
+        });
+
+        Ok(paginate(rows, limit, offset))
+    }
+
+    fn count_users(&self, search: Option<&str>) -> Result<i64, RepositoryError> {
+        let tables = self.store.read();
+        Ok(tables
+            .auth_users
+            .iter()
+            .filter(|u| matches_search(u, search))
+            .count() as i64)
+    }
+
+    fn deactivate_user(&self, user_id: Uuid) -> Result<AuthUser, RepositoryError> {
+        let mut tables = self.store.write();
+        update_user(&mut tables, user_id, |user| {
+            user.is_active = false;
+            user.token_version += 1;
+        })
+    }
+
+    fn reactivate_user(&self, user_id: Uuid) -> Result<AuthUser, RepositoryError> {
+        let mut tables = self.store.write();
+        update_user(&mut tables, user_id, |user| user.is_active = true)
+    }
+
+    fn revoke_tokens(&self, user_id: Uuid) -> Result<AuthUser, RepositoryError> {
+        let mut tables = self.store.write();
+        update_user(&mut tables, user_id, |user| user.token_version += 1)
+    }
+}
+
+fn update_user(
+    tables: &mut Tables,
+    user_id: Uuid,
+    apply: impl FnOnce(&mut AuthUser),
+) -> Result<AuthUser, RepositoryError> {
+    let user = tables
+        .auth_users
+        .iter_mut()
+        .find(|u| u.id == user_id)
+        .ok_or(RepositoryError::NotFound)?;
+
+    apply(user);
+    user.updated_at = now();
+
+    Ok(user.clone())
+}
+
+fn ensure_role(tables: &Tables, role_id: i32) -> Result<(), RepositoryError> {
+    if tables.roles.iter().any(|r| r.id == role_id) {
+        Ok(())
+    } else {
+        Err(RepositoryError::MissingReference(format!(
+            "role {} does not exist",
+            role_id
+        )))
+    }
+}
+
+fn role_permission_names(tables: &Tables) -> impl Iterator<Item = (i32, String)> + '_ {
+    tables
+        .role_permissions
+        .iter()
+        .filter_map(|(role_id, permission_id)| {
+            let permission = tables.permissions.iter().find(|p| p.id == *permission_id)?;
+            Some((*role_id, permission.name.clone()))
+        })
+}
+
+/// Case-insensitive substring match, mirroring the escaped `ILIKE` query.
+fn matches_search(user: &AuthUser, search: Option<&str>) -> bool {
+    match search {
+        Some(search) => user
+            .username
+            .to_lowercase()
+            .contains(&search.to_lowercase()),
+        None => true,
+    }
+}
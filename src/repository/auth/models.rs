@@ -1,4 +1,4 @@
-use crate::schema::{auth_users, roles};
+use crate::schema::{auth_users, permissions, role_permissions, roles};
 use diesel::prelude::*;
 use uuid::Uuid;
 
@@ -11,6 +11,29 @@ pub struct Role {
     pub created_at: chrono::NaiveDateTime,
 }
 
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = roles)]
+pub struct NewRole {
+    pub name: String,
+    pub description: Option<String>,
+}
+
+#[derive(Queryable, Selectable, Debug, Clone)]
+#[diesel(table_name = permissions)]
+pub struct Permission {
+    pub id: i32,
+    pub name: String,
+    pub description: Option<String>,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = role_permissions)]
+pub struct NewRolePermission {
+    pub role_id: i32,
+    pub permission_id: i32,
+}
+
 #[derive(Queryable, Selectable, Debug, Clone)]
 #[diesel(table_name = auth_users)]
 pub struct AuthUser {
@@ -21,6 +44,7 @@ pub struct AuthUser {
     pub is_active: bool,
     pub created_at: chrono::NaiveDateTime,
     pub updated_at: chrono::NaiveDateTime,
+    pub token_version: i32,
 }
 
 #[derive(Insertable, Debug, Clone)]
@@ -1,112 +1,376 @@
-use super::models::{AuthUser, NewAuthUser, Role};
-use crate::bootstrap::postgres::Postgres;
-use crate::schema::{auth_users, roles};
+use super::models::{AuthUser, NewAuthUser, NewRole, NewRolePermission, Permission, Role};
+use crate::repository::RepositoryError;
+use crate::repository::connection::PgSource;
+use crate::schema::{auth_users, permissions, role_permissions, roles};
 use diesel::prelude::*;
-use std::sync::Arc;
 use uuid::Uuid;
 
+pub trait AuthRepo: Send + Sync {
+    fn create_user(&self, new_user: NewAuthUser) -> Result<AuthUser, RepositoryError>;
+
+    fn find_by_id(&self, user_id: Uuid) -> Result<Option<AuthUser>, RepositoryError>;
+
+    fn find_by_username(&self, username: &str) -> Result<Option<AuthUser>, RepositoryError>;
+
+    fn username_exists(&self, username: &str) -> Result<bool, RepositoryError>;
+
+    fn find_role_by_id(&self, role_id: i32) -> Result<Option<Role>, RepositoryError>;
+
+    fn find_all_roles(&self) -> Result<Vec<Role>, RepositoryError>;
+
+    fn create_role(&self, new_role: NewRole) -> Result<Role, RepositoryError>;
+
+    fn role_name_exists(&self, name: &str) -> Result<bool, RepositoryError>;
+
+    fn find_all_permissions(&self) -> Result<Vec<Permission>, RepositoryError>;
+
+    fn find_permissions_by_names(
+        &self,
+        names: &[String],
+    ) -> Result<Vec<Permission>, RepositoryError>;
+
+    fn find_role_permissions(&self, role_id: i32) -> Result<Vec<String>, RepositoryError>;
+
+    /// Returns `(role_id, permission_name)` pairs for every role.
+    fn find_all_role_permissions(&self) -> Result<Vec<(i32, String)>, RepositoryError>;
+
+    fn set_role_permissions(
+        &self,
+        role_id: i32,
+        permission_ids: &[i32],
+    ) -> Result<(), RepositoryError>;
+
+    fn update_user_role(
+        &self,
+        user_id: Uuid,
+        new_role_id: i32,
+    ) -> Result<AuthUser, RepositoryError>;
+
+    fn update_password_hash(
+        &self,
+        user_id: Uuid,
+        password_hash: &str,
+    ) -> Result<(), RepositoryError>;
+
+    fn list_users(
+        &self,
+        search: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<(AuthUser, Role)>, RepositoryError>;
+
+    fn count_users(&self, search: Option<&str>) -> Result<i64, RepositoryError>;
+
+    /// Deactivation also bumps `token_version` so outstanding tokens stop
+    /// working immediately instead of at their natural expiry.
+    fn deactivate_user(&self, user_id: Uuid) -> Result<AuthUser, RepositoryError>;
+
+    fn reactivate_user(&self, user_id: Uuid) -> Result<AuthUser, RepositoryError>;
+
+    fn revoke_tokens(&self, user_id: Uuid) -> Result<AuthUser, RepositoryError>;
+}
+
 pub struct AuthRepository {
-    postgres: Arc<Postgres>,
+    db: PgSource,
 }
 
 impl AuthRepository {
-    pub fn new(postgres: Arc<Postgres>) -> Self {
-        Self { postgres }
+    pub fn new(db: PgSource) -> Self {
+        Self { db }
     }
+}
 
+impl AuthRepo for AuthRepository {
     #[tracing::instrument(skip(self, new_user), fields(username = %new_user.username))]
-    pub fn create_user(&self, new_user: NewAuthUser) -> Result<AuthUser, String> {
-        let mut conn = self.postgres.conn()?;
+    fn create_user(&self, new_user: NewAuthUser) -> Result<AuthUser, RepositoryError> {
+        let mut conn = self.db.conn()?;
 
         diesel::insert_into(auth_users::table)
             .values(&new_user)
-            .get_result(&mut conn)
-            .map_err(|e| format!("Failed to create auth user: {}", e))
+            .get_result(&mut *conn)
+            .map_err(RepositoryError::from)
     }
 
     #[tracing::instrument(skip(self))]
-    pub fn find_by_id(&self, user_id: Uuid) -> Result<AuthUser, String> {
-        let mut conn = self.postgres.conn()?;
+    fn find_by_id(&self, user_id: Uuid) -> Result<Option<AuthUser>, RepositoryError> {
+        let mut conn = self.db.conn()?;
 
         auth_users::table
             .find(user_id)
-            .first(&mut conn)
-            .map_err(|e| format!("Failed to find auth user: {}", e))
+            .first(&mut *conn)
+            .optional()
+            .map_err(RepositoryError::from)
     }
 
     #[tracing::instrument(skip(self))]
-    pub fn find_by_username(&self, username: &str) -> Result<AuthUser, String> {
-        let mut conn = self.postgres.conn()?;
+    fn find_by_username(&self, username: &str) -> Result<Option<AuthUser>, RepositoryError> {
+        let mut conn = self.db.conn()?;
 
         auth_users::table
             .filter(auth_users::username.eq(username))
-            .first(&mut conn)
-            .map_err(|e| format!("Failed to find auth user by username: {}", e))
+            .first(&mut *conn)
+            .optional()
+            .map_err(RepositoryError::from)
     }
 
     #[tracing::instrument(skip(self))]
-    pub fn username_exists(&self, username: &str) -> Result<bool, String> {
-        let mut conn = self.postgres.conn()?;
+    fn username_exists(&self, username: &str) -> Result<bool, RepositoryError> {
+        let mut conn = self.db.conn()?;
 
         let count: i64 = auth_users::table
             .filter(auth_users::username.eq(username))
             .count()
-            .get_result(&mut conn)
-            .map_err(|e| format!("Failed to check username existence: {}", e))?;
+            .get_result(&mut *conn)?;
 
         Ok(count > 0)
     }
 
     #[tracing::instrument(skip(self))]
-    pub fn find_role_by_id(&self, role_id: i32) -> Result<Role, String> {
-        let mut conn = self.postgres.conn()?;
+    fn find_role_by_id(&self, role_id: i32) -> Result<Option<Role>, RepositoryError> {
+        let mut conn = self.db.conn()?;
 
         roles::table
             .find(role_id)
-            .first(&mut conn)
-            .map_err(|e| format!("Failed to find role: {}", e))
+            .first(&mut *conn)
+            .optional()
+            .map_err(RepositoryError::from)
     }
 
     #[tracing::instrument(skip(self))]
-    pub fn find_all_roles(&self) -> Result<Vec<Role>, String> {
-        let mut conn = self.postgres.conn()?;
+    fn find_all_roles(&self) -> Result<Vec<Role>, RepositoryError> {
+        let mut conn = self.db.conn()?;
 
         roles::table
-            .load(&mut conn)
-            .map_err(|e| format!("Failed to load roles: {}", e))
+            .order(roles::id.asc())
+            .load(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    #[tracing::instrument(skip(self, new_role), fields(name = %new_role.name))]
+    fn create_role(&self, new_role: NewRole) -> Result<Role, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        diesel::insert_into(roles::table)
+            .values(&new_role)
+            .get_result(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    #[tracing::instrument(skip(self))]
+    fn role_name_exists(&self, name: &str) -> Result<bool, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        let count: i64 = roles::table
+            .filter(roles::name.eq(name))
+            .count()
+            .get_result(&mut *conn)?;
+
+        Ok(count > 0)
+    }
+
+    #[tracing::instrument(skip(self))]
+    fn find_all_permissions(&self) -> Result<Vec<Permission>, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        permissions::table
+            .order(permissions::name.asc())
+            .load(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    #[tracing::instrument(skip(self))]
+    fn find_permissions_by_names(
+        &self,
+        names: &[String],
+    ) -> Result<Vec<Permission>, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        permissions::table
+            .filter(permissions::name.eq_any(names))
+            .load(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    #[tracing::instrument(skip(self))]
+    fn find_role_permissions(&self, role_id: i32) -> Result<Vec<String>, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        role_permissions::table
+            .inner_join(permissions::table)
+            .filter(role_permissions::role_id.eq(role_id))
+            .select(permissions::name)
+            .order(permissions::name.asc())
+            .load(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    #[tracing::instrument(skip(self))]
+    fn find_all_role_permissions(&self) -> Result<Vec<(i32, String)>, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        role_permissions::table
+            .inner_join(permissions::table)
+            .select((role_permissions::role_id, permissions::name))
+            .order(permissions::name.asc())
+            .load(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    #[tracing::instrument(skip(self, permission_ids))]
+    fn set_role_permissions(
+        &self,
+        role_id: i32,
+        permission_ids: &[i32],
+    ) -> Result<(), RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        let rows: Vec<NewRolePermission> = permission_ids
+            .iter()
+            .map(|&permission_id| NewRolePermission {
+                role_id,
+                permission_id,
+            })
+            .collect();
+
+        conn.transaction(|conn| {
+            diesel::delete(role_permissions::table.filter(role_permissions::role_id.eq(role_id)))
+                .execute(conn)?;
+
+            diesel::insert_into(role_permissions::table)
+                .values(&rows)
+                .execute(conn)?;
+
+            Ok(())
+        })
     }
 
     #[tracing::instrument(skip(self))]
-    pub fn update_user_role(&self, user_id: Uuid, new_role_id: i32) -> Result<AuthUser, String> {
-        let mut conn = self.postgres.conn()?;
+    fn update_user_role(
+        &self,
+        user_id: Uuid,
+        new_role_id: i32,
+    ) -> Result<AuthUser, RepositoryError> {
+        let mut conn = self.db.conn()?;
 
         diesel::update(auth_users::table.find(user_id))
             .set((
                 auth_users::role_id.eq(new_role_id),
                 auth_users::updated_at.eq(chrono::Utc::now().naive_utc()),
             ))
-            .get_result(&mut conn)
-            .map_err(|e| format!("Failed to update user role: {}", e))
+            .get_result(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    #[tracing::instrument(skip(self, password_hash))]
+    fn update_password_hash(
+        &self,
+        user_id: Uuid,
+        password_hash: &str,
+    ) -> Result<(), RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        diesel::update(auth_users::table.find(user_id))
+            .set((
+                auth_users::password_hash.eq(password_hash),
+                auth_users::updated_at.eq(chrono::Utc::now().naive_utc()),
+            ))
+            .execute(&mut *conn)
+            .map(|_| ())
+            .map_err(RepositoryError::from)
+    }
+
+    #[tracing::instrument(skip(self))]
+    fn list_users(
+        &self,
+        search: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<(AuthUser, Role)>, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        let mut query = auth_users::table.inner_join(roles::table).into_boxed();
+
+        if let Some(search) = search {
+            query = query.filter(auth_users::username.ilike(format!("%{}%", escape_like(search))));
+        }
+
+        query
+            .order(auth_users::username.asc())
+            .limit(limit)
+            .offset(offset)
+            .select((AuthUser::as_select(), Role::as_select()))
+            .load(&mut *conn)
+            .map_err(RepositoryError::from)
     }
 
     #[tracing::instrument(skip(self))]
-    pub fn deactivate_user(&self, user_id: Uuid) -> Result<AuthUser, String> {
-        let mut conn = self.postgres.conn()?;
+    fn count_users(&self, search: Option<&str>) -> Result<i64, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        let mut query = auth_users::table.into_boxed();
+
+        if let Some(search) = search {
+            query = query.filter(auth_users::username.ilike(format!("%{}%", escape_like(search))));
+        }
+
+        query
+            .count()
+            .get_result(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    #[tracing::instrument(skip(self))]
+    fn deactivate_user(&self, user_id: Uuid) -> Result<AuthUser, RepositoryError> {
+        let mut conn = self.db.conn()?;
 
         diesel::update(auth_users::table.find(user_id))
             .set((
                 auth_users::is_active.eq(false),
+                auth_users::token_version.eq(auth_users::token_version + 1),
                 auth_users::updated_at.eq(chrono::Utc::now().naive_utc()),
             ))
-            .get_result(&mut conn)
-            .map_err(|e| format!("Failed to deactivate user: {}", e))
+            .get_result(&mut *conn)
+            .map_err(RepositoryError::from)
     }
+
+    #[tracing::instrument(skip(self))]
+    fn reactivate_user(&self, user_id: Uuid) -> Result<AuthUser, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        diesel::update(auth_users::table.find(user_id))
+            .set((
+                auth_users::is_active.eq(true),
+                auth_users::updated_at.eq(chrono::Utc::now().naive_utc()),
+            ))
+            .get_result(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    #[tracing::instrument(skip(self))]
+    fn revoke_tokens(&self, user_id: Uuid) -> Result<AuthUser, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        diesel::update(auth_users::table.find(user_id))
+            .set((
+                auth_users::token_version.eq(auth_users::token_version + 1),
+                auth_users::updated_at.eq(chrono::Utc::now().naive_utc()),
+            ))
+            .get_result(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+}
+
+pub(crate) fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
 }
 
 impl Clone for AuthRepository {
     fn clone(&self) -> Self {
         Self {
-            postgres: self.postgres.clone(),
+            db: self.db.clone(),
         }
     }
 }
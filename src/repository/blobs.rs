@@ -0,0 +1,49 @@
+pub mod fs;
+pub mod memory;
+pub mod s3;
+pub mod sigv4;
+
+use std::sync::Arc;
+
+use thiserror::Error;
+
+use crate::config::attachments::BlobStorageConfig;
+
+pub use fs::FsBlobStore;
+pub use memory::InMemoryBlobStore;
+pub use s3::S3BlobStore;
+
+#[derive(Debug, Error)]
+pub enum BlobError {
+    #[error("Blob not found")]
+    NotFound,
+
+    /// The storage backend could not be reached; the request may succeed
+    /// on retry.
+    #[error("Blob storage unavailable: {0}")]
+    Unavailable(String),
+
+    #[error("Blob storage error: {0}")]
+    Internal(String),
+}
+
+/// Storage for opaque blobs addressed by key. Calls block, so use-case code
+/// runs them on the blocking pool like repository calls.
+pub trait BlobStore: Send + Sync {
+    /// Stores `data` under `key`, replacing any earlier blob.
+    fn put(&self, key: &str, data: &[u8]) -> Result<(), BlobError>;
+
+    fn get(&self, key: &str) -> Result<Vec<u8>, BlobError>;
+
+    /// Deleting a missing blob is not an error.
+    fn delete(&self, key: &str) -> Result<(), BlobError>;
+}
+
+/// Opens the configured backend.
+pub fn open(config: &BlobStorageConfig) -> Result<Arc<dyn BlobStore>, String> {
+    Ok(match config {
+        BlobStorageConfig::Filesystem { root } => Arc::new(FsBlobStore::new(root.clone())?),
+        BlobStorageConfig::S3(s3) => Arc::new(S3BlobStore::new(s3)?),
+        BlobStorageConfig::Memory => Arc::new(InMemoryBlobStore::new()),
+    })
+}
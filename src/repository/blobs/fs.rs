@@ -0,0 +1,77 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Component, Path, PathBuf};
+
+use uuid::Uuid;
+
+use super::{BlobError, BlobStore};
+
+/// [`BlobStore`] in a local directory; keys are relative paths under it.
+#[derive(Debug, Clone)]
+pub struct FsBlobStore {
+    root: PathBuf,
+}
+
+impl FsBlobStore {
+    pub fn new(root: PathBuf) -> Result<Self, String> {
+        fs::create_dir_all(&root).map_err(|e| {
+            format!(
+                "Failed to create attachment directory {}: {}",
+                root.display(),
+                e
+            )
+        })?;
+
+        Ok(Self { root })
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf, BlobError> {
+        let relative = Path::new(key);
+
+        if key.is_empty()
+            || !relative
+                .components()
+                .all(|c| matches!(c, Component::Normal(_)))
+        {
+            return Err(BlobError::Internal(format!("Invalid blob key {}", key)));
+        }
+
+        Ok(self.root.join(relative))
+    }
+}
+
+impl BlobStore for FsBlobStore {
+    fn put(&self, key: &str, data: &[u8]) -> Result<(), BlobError> {
+        let path = self.path(key)?;
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(io_error)?;
+        }
+
+        // Readers never see a partly written blob: write aside, then rename.
+        let staging = path.with_file_name(format!(".{}.tmp", Uuid::new_v4()));
+        fs::write(&staging, data).map_err(io_error)?;
+        fs::rename(&staging, &path).map_err(|e| {
+            let _ = fs::remove_file(&staging);
+            io_error(e)
+        })
+    }
+
+    fn get(&self, key: &str) -> Result<Vec<u8>, BlobError> {
+        fs::read(self.path(key)?).map_err(io_error)
+    }
+
+    fn delete(&self, key: &str) -> Result<(), BlobError> {
+        match fs::remove_file(self.path(key)?) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(io_error(e)),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn io_error(err: std::io::Error) -> BlobError {
+    match err.kind() {
+        ErrorKind::NotFound => BlobError::NotFound,
+        _ => BlobError::Internal(err.to_string()),
+    }
+}
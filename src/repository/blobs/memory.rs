@@ -0,0 +1,35 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use super::{BlobError, BlobStore};
+
+/// [`BlobStore`] in process memory.
+#[derive(Debug, Default)]
+pub struct InMemoryBlobStore {
+    blobs: Mutex<HashMap<String, Vec<u8>>>,
+}
+
+impl InMemoryBlobStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl BlobStore for InMemoryBlobStore {
+    fn put(&self, key: &str, data: &[u8]) -> Result<(), BlobError> {
+        let mut blobs = self.blobs.lock().unwrap_or_else(|e| e.into_inner());
+        blobs.insert(key.to_string(), data.to_vec());
+        Ok(())
+    }
+
+    fn get(&self, key: &str) -> Result<Vec<u8>, BlobError> {
+        let blobs = self.blobs.lock().unwrap_or_else(|e| e.into_inner());
+        blobs.get(key).cloned().ok_or(BlobError::NotFound)
+    }
+
+    fn delete(&self, key: &str) -> Result<(), BlobError> {
+        let mut blobs = self.blobs.lock().unwrap_or_else(|e| e.into_inner());
+        blobs.remove(key);
+        Ok(())
+    }
+}
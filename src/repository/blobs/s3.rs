@@ -0,0 +1,164 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use http_body_util::{BodyExt, Full};
+use hyper::body::Bytes;
+use hyper::{Method, Request, StatusCode, Uri};
+
+use super::sigv4::{self, CanonicalRequest, SigningParams};
+use super::{BlobError, BlobStore};
+use crate::config::attachments::S3Config;
+use crate::repository::http_client::{self, Destinations, HttpClient};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// [`BlobStore`] in an S3-compatible bucket, addressed path-style
+/// (`{endpoint}/{bucket}/{key}`). The endpoint may carry a path, e.g. for a
+/// store behind a reverse proxy; objects live under it.
+pub struct S3BlobStore {
+    client: HttpClient,
+    endpoint: String,
+    host: String,
+    /// The endpoint's path without a trailing slash; empty for none.
+    path_prefix: String,
+    bucket: String,
+    region: String,
+    access_key: String,
+    secret_key: String,
+}
+
+impl S3BlobStore {
+    pub fn new(config: &S3Config) -> Result<Self, String> {
+        let uri: Uri = config
+            .endpoint
+            .parse()
+            .map_err(|_| "Invalid S3_ENDPOINT".to_string())?;
+
+        let scheme = match uri.scheme_str() {
+            Some(scheme @ ("http" | "https")) => scheme,
+            _ => return Err("S3_ENDPOINT must be an http:// or https:// URL".to_string()),
+        };
+
+        let host = uri
+            .authority()
+            .ok_or_else(|| "S3_ENDPOINT has no host".to_string())?
+            .to_string();
+
+        if uri.query().is_some() {
+            return Err("S3_ENDPOINT cannot have a query string".to_string());
+        }
+
+        Ok(Self {
+            client: http_client::client(Destinations::Any),
+            endpoint: format!("{}://{}", scheme, host),
+            host,
+            path_prefix: uri.path().trim_end_matches('/').to_string(),
+            bucket: config.bucket.clone(),
+            region: config.region.clone(),
+            access_key: config.access_key.clone(),
+            secret_key: config.secret_key.clone(),
+        })
+    }
+
+    fn send(
+        &self,
+        method: Method,
+        key: &str,
+        body: &[u8],
+    ) -> Result<(StatusCode, Bytes), BlobError> {
+        let path = format!(
+            "{}/{}/{}",
+            self.path_prefix,
+            self.bucket,
+            sigv4::encode_path(key)
+        );
+        let amz_date = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+        let payload_hash = sigv4::hex_sha256(body);
+
+        let headers = [
+            ("host", self.host.as_str()),
+            ("x-amz-content-sha256", payload_hash.as_str()),
+            ("x-amz-date", amz_date.as_str()),
+        ];
+        let authorization = sigv4::authorization(
+            &CanonicalRequest {
+                method: method.as_str(),
+                path: &path,
+                query: "",
+                headers: &headers,
+                payload_hash: &payload_hash,
+            },
+            &SigningParams {
+                access_key: &self.access_key,
+                secret_key: &self.secret_key,
+                region: &self.region,
+                service: "s3",
+                amz_date: &amz_date,
+            },
+        );
+
+        let mut request = Request::builder()
+            .method(method)
+            .uri(format!("{}{}", self.endpoint, path))
+            .header("authorization", authorization);
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+        let request = request
+            .body(Full::new(Bytes::copy_from_slice(body)))
+            .map_err(|e| BlobError::Internal(e.to_string()))?;
+
+        http_client::block_on(async {
+            let response = tokio::time::timeout(REQUEST_TIMEOUT, self.client.request(request))
+                .await
+                .map_err(|_| BlobError::Unavailable("S3 request timed out".to_string()))?
+                .map_err(|e| BlobError::Unavailable(e.to_string()))?;
+
+            let status = response.status();
+            let body = response
+                .into_body()
+                .collect()
+                .await
+                .map_err(|e| BlobError::Unavailable(e.to_string()))?
+                .to_bytes();
+
+            Ok((status, body))
+        })
+        .map_err(BlobError::Internal)?
+    }
+}
+
+impl BlobStore for S3BlobStore {
+    fn put(&self, key: &str, data: &[u8]) -> Result<(), BlobError> {
+        let (status, body) = self.send(Method::PUT, key, data)?;
+        check_status(status, &body)
+    }
+
+    fn get(&self, key: &str) -> Result<Vec<u8>, BlobError> {
+        let (status, body) = self.send(Method::GET, key, &[])?;
+        check_status(status, &body)?;
+        Ok(body.to_vec())
+    }
+
+    fn delete(&self, key: &str) -> Result<(), BlobError> {
+        let (status, body) = self.send(Method::DELETE, key, &[])?;
+        match check_status(status, &body) {
+            Err(BlobError::NotFound) => Ok(()),
+            result => result,
+        }
+    }
+}
+
+fn check_status(status: StatusCode, body: &[u8]) -> Result<(), BlobError> {
+    if status.is_success() {
+        return Ok(());
+    }
+
+    let message = format!("S3 returned {}: {}", status, String::from_utf8_lossy(body));
+
+    Err(match status {
+        StatusCode::NOT_FOUND => BlobError::NotFound,
+        s if s.is_server_error() => BlobError::Unavailable(message),
+        _ => BlobError::Internal(message),
+    })
+}
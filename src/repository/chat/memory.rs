@@ -0,0 +1,729 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+
+use super::models::{
+    Chat, ChatMember, ChatMemberDetails, ChatRekey, MemberFilter, Mention, Message,
+    MessageEnvelope, MessageKind, NewMessage, NewPinnedMessage, PinnedMessage, Poll, PollVote,
+    ReadState, RekeyReason,
+};
+use super::repo::ChatRepo;
+use crate::repository::RepositoryError;
+use crate::repository::memory::{
+    MemoryStore, ensure_chat, ensure_device, ensure_user, now, paginate,
+};
+
+/// [`ChatRepo`] over a [`MemoryStore`].
+#[derive(Clone)]
+pub struct InMemoryChatRepository {
+    store: Arc<MemoryStore>,
+}
+
+impl InMemoryChatRepository {
+    pub fn new(store: Arc<MemoryStore>) -> Self {
+        Self { store }
+    }
+}
+
+impl ChatRepo for InMemoryChatRepository {
+    fn create_chat(&self, name: String, created_by: Uuid) -> Result<Chat, RepositoryError> {
+        let mut tables = self.store.write();
+        ensure_user(&tables, created_by)?;
+
+        let now = now();
+        let chat = Chat {
+            id: Uuid::new_v4(),
+            name,
+            created_by,
+            created_at: now,
+            updated_at: now,
+            epoch: 0,
+            message_ttl_seconds: None,
+            allow_forwarding: true,
+            allow_polls: true,
+        };
+        tables.chats.push(chat.clone());
+
+        Ok(chat)
+    }
+
+    fn find_chat_by_id(&self, chat_id: Uuid) -> Result<Option<Chat>, RepositoryError> {
+        let tables = self.store.read();
+        Ok(tables.chats.iter().find(|c| c.id == chat_id).cloned())
+    }
+
+    // Transactions over the store detect concurrent writers at commit time,
+    // which stands in for the row lock.
+    fn lock_chat(&self, chat_id: Uuid) -> Result<Option<Chat>, RepositoryError> {
+        self.find_chat_by_id(chat_id)
+    }
+
+    fn get_user_chats(&self, user_id: Uuid) -> Result<Vec<Chat>, RepositoryError> {
+        let tables = self.store.read();
+        Ok(tables
+            .chat_members
+            .iter()
+            .filter(|m| m.user_id == user_id)
+            .filter_map(|m| tables.chats.iter().find(|c| c.id == m.chat_id))
+            .cloned()
+            .collect())
+    }
+
+    fn add_member(
+        &self,
+        chat_id: Uuid,
+        user_id: Uuid,
+        invited_by: Option<Uuid>,
+    ) -> Result<ChatMember, RepositoryError> {
+        let mut tables = self.store.write();
+
+        ensure_chat(&tables, chat_id)?;
+        ensure_user(&tables, user_id)?;
+        if let Some(inviter) = invited_by {
+            ensure_user(&tables, inviter)?;
+        }
+
+        if tables
+            .chat_members
+            .iter()
+            .any(|m| m.chat_id == chat_id && m.user_id == user_id)
+        {
+            return Err(RepositoryError::Conflict(
+                "duplicate key value violates unique constraint \"chat_members_chat_id_user_id_key\""
+                    .to_string(),
+            ));
+        }
+
+        let now = now();
+        let member = ChatMember {
+            id: Uuid::new_v4(),
+            chat_id,
+            user_id,
+            invited_by,
+            joined_at: now,
+            last_read_at: now,
+            muted: false,
+        };
+        tables.chat_members.push(member.clone());
+
+        Ok(member)
+    }
+
+    fn remove_member(&self, chat_id: Uuid, user_id: Uuid) -> Result<(), RepositoryError> {
+        let mut tables = self.store.write();
+
+        let before = tables.chat_members.len();
+        tables
+            .chat_members
+            .retain(|m| m.chat_id != chat_id || m.user_id != user_id);
+
+        if tables.chat_members.len() == before {
+            return Err(RepositoryError::NotFound);
+        }
+
+        Ok(())
+    }
+
+    fn is_member(&self, chat_id: Uuid, user_id: Uuid) -> Result<bool, RepositoryError> {
+        let tables = self.store.read();
+        Ok(tables
+            .chat_members
+            .iter()
+            .any(|m| m.chat_id == chat_id && m.user_id == user_id))
+    }
+
+    fn mark_read(
+        &self,
+        chat_id: Uuid,
+        user_id: Uuid,
+        read_at: NaiveDateTime,
+    ) -> Result<(), RepositoryError> {
+        let mut tables = self.store.write();
+
+        if let Some(member) = tables
+            .chat_members
+            .iter_mut()
+            .find(|m| m.chat_id == chat_id && m.user_id == user_id)
+        {
+            member.last_read_at = member.last_read_at.max(read_at);
+        }
+
+        Ok(())
+    }
+
+    fn set_muted(&self, chat_id: Uuid, user_id: Uuid, muted: bool) -> Result<(), RepositoryError> {
+        let mut tables = self.store.write();
+
+        let member = tables
+            .chat_members
+            .iter_mut()
+            .find(|m| m.chat_id == chat_id && m.user_id == user_id)
+            .ok_or(RepositoryError::NotFound)?;
+        member.muted = muted;
+
+        Ok(())
+    }
+
+    fn rename_chat(&self, chat_id: Uuid, name: &str) -> Result<Chat, RepositoryError> {
+        let mut tables = self.store.write();
+
+        let chat = tables
+            .chats
+            .iter_mut()
+            .find(|c| c.id == chat_id)
+            .ok_or(RepositoryError::NotFound)?;
+        chat.name = name.to_string();
+        chat.updated_at = now();
+
+        Ok(chat.clone())
+    }
+
+    fn set_message_ttl(
+        &self,
+        chat_id: Uuid,
+        ttl_seconds: Option<i32>,
+    ) -> Result<Chat, RepositoryError> {
+        let mut tables = self.store.write();
+
+        let chat = tables
+            .chats
+            .iter_mut()
+            .find(|c| c.id == chat_id)
+            .ok_or(RepositoryError::NotFound)?;
+        chat.message_ttl_seconds = ttl_seconds;
+        chat.updated_at = now();
+
+        Ok(chat.clone())
+    }
+
+    fn set_allow_forwarding(&self, chat_id: Uuid, allow: bool) -> Result<Chat, RepositoryError> {
+        let mut tables = self.store.write();
+
+        let chat = tables
+            .chats
+            .iter_mut()
+            .find(|c| c.id == chat_id)
+            .ok_or(RepositoryError::NotFound)?;
+        chat.allow_forwarding = allow;
+        chat.updated_at = now();
+
+        Ok(chat.clone())
+    }
+
+    fn advance_epoch(
+        &self,
+        chat_id: Uuid,
+        reason: RekeyReason,
+        user_id: Uuid,
+    ) -> Result<ChatRekey, RepositoryError> {
+        let mut tables = self.store.write();
+        ensure_user(&tables, user_id)?;
+
+        let now = now();
+        let chat = tables
+            .chats
+            .iter_mut()
+            .find(|c| c.id == chat_id)
+            .ok_or(RepositoryError::NotFound)?;
+        chat.epoch += 1;
+        chat.updated_at = now;
+
+        let rekey = ChatRekey {
+            chat_id,
+            epoch: chat.epoch,
+            reason: reason.as_str().to_string(),
+            user_id: Some(user_id),
+            created_at: now,
+        };
+        tables.chat_rekeys.push(rekey.clone());
+
+        Ok(rekey)
+    }
+
+    fn get_rekeys(
+        &self,
+        chat_id: Uuid,
+        since_epoch: i32,
+    ) -> Result<Vec<ChatRekey>, RepositoryError> {
+        let tables = self.store.read();
+
+        let mut rekeys: Vec<ChatRekey> = tables
+            .chat_rekeys
+            .iter()
+            .filter(|r| r.chat_id == chat_id && r.epoch > since_epoch)
+            .cloned()
+            .collect();
+        rekeys.sort_by_key(|r| r.epoch);
+
+        Ok(rekeys)
+    }
+
+    fn get_chat_members(
+        &self,
+        chat_id: Uuid,
+        filter: &MemberFilter,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<ChatMemberDetails>, RepositoryError> {
+        let tables = self.store.read();
+        let prefix = filter.username_prefix.as_deref().map(str::to_lowercase);
+
+        let mut members: Vec<ChatMemberDetails> = tables
+            .chat_members
+            .iter()
+            .filter(|m| m.chat_id == chat_id)
+            .filter_map(|m| {
+                let user = tables.auth_users.iter().find(|u| u.id == m.user_id)?;
+                Some(ChatMemberDetails {
+                    user_id: m.user_id,
+                    username: user.username.clone(),
+                    role_id: user.role_id,
+                    invited_by: m.invited_by,
+                    joined_at: m.joined_at,
+                })
+            })
+            .filter(|m| filter.role_id.is_none_or(|role_id| m.role_id == role_id))
+            .filter(|m| {
+                prefix
+                    .as_deref()
+                    .is_none_or(|prefix| m.username.to_lowercase().starts_with(prefix))
+            })
+            .collect();
+        members.sort_by_key(|m| (m.joined_at, m.user_id));
+
+        Ok(paginate(members.into_iter(), limit, offset))
+    }
+
+    fn set_allow_polls(&self, chat_id: Uuid, allow: bool) -> Result<Chat, RepositoryError> {
+        let mut tables = self.store.write();
+
+        let chat = tables
+            .chats
+            .iter_mut()
+            .find(|c| c.id == chat_id)
+            .ok_or(RepositoryError::NotFound)?;
+        chat.allow_polls = allow;
+        chat.updated_at = now();
+
+        Ok(chat.clone())
+    }
+
+    fn create_message(
+        &self,
+        new_message: NewMessage,
+        envelopes: &HashMap<Uuid, String>,
+    ) -> Result<Message, RepositoryError> {
+        let mut tables = self.store.write();
+
+        ensure_chat(&tables, new_message.chat_id)?;
+        ensure_user(&tables, new_message.sender_id)?;
+        for device_id in new_message.sender_device_id.iter().chain(envelopes.keys()) {
+            ensure_device(&tables, *device_id)?;
+        }
+
+        let message = Message {
+            id: Uuid::new_v4(),
+            chat_id: new_message.chat_id,
+            sender_id: new_message.sender_id,
+            encrypted_content: new_message.encrypted_content,
+            created_at: now(),
+            sender_device_id: new_message.sender_device_id,
+            epoch: new_message.epoch,
+            kind: new_message.kind,
+            payload: new_message.payload,
+            expires_at: new_message.expires_at,
+            view_once: new_message.view_once,
+            mentions: new_message.mentions,
+            mention_all: new_message.mention_all,
+            forwarded_from_chat_id: new_message.forwarded_from_chat_id,
+            forwarded_from_message_id: new_message.forwarded_from_message_id,
+            forwarded_from_sender_id: new_message.forwarded_from_sender_id,
+        };
+        tables.messages.push(message.clone());
+        for (device_id, ciphertext) in envelopes {
+            tables.message_envelopes.push(MessageEnvelope {
+                message_id: message.id,
+                device_id: *device_id,
+                ciphertext: ciphertext.clone(),
+            });
+        }
+
+        Ok(message)
+    }
+
+    fn get_chat_messages(
+        &self,
+        chat_id: Uuid,
+        device_id: Uuid,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Message>, RepositoryError> {
+        let tables = self.store.read();
+        let now = now();
+
+        // Newest first; messages inserted within the same clock tick keep
+        // their reverse insertion order.
+        let mut messages: Vec<Message> = tables
+            .messages
+            .iter()
+            .rev()
+            .filter(|m| m.chat_id == chat_id)
+            .filter(|m| m.expires_at.is_none_or(|expires_at| expires_at > now))
+            .filter_map(|m| {
+                let envelope = tables
+                    .message_envelopes
+                    .iter()
+                    .find(|e| e.message_id == m.id && e.device_id == device_id)
+                    .map(|e| e.ciphertext.clone());
+                let encrypted_content = envelope.or_else(|| m.encrypted_content.clone());
+
+                if encrypted_content.is_none() && m.kind == MessageKind::Text.as_str() {
+                    return None;
+                }
+
+                Some(Message {
+                    encrypted_content,
+                    ..m.clone()
+                })
+            })
+            .collect();
+        messages.sort_by_key(|m| Reverse(m.created_at));
+
+        Ok(paginate(messages.into_iter(), limit, offset))
+    }
+
+    fn get_message_by_id(&self, message_id: Uuid) -> Result<Option<Message>, RepositoryError> {
+        let tables = self.store.read();
+        Ok(tables.messages.iter().find(|m| m.id == message_id).cloned())
+    }
+
+    fn consume_view_once(
+        &self,
+        device_id: Uuid,
+        message_ids: &[Uuid],
+    ) -> Result<Vec<Uuid>, RepositoryError> {
+        let mut tables = self.store.write();
+
+        let mut consumed = Vec::new();
+        tables.message_envelopes.retain(|e| {
+            let hit = e.device_id == device_id && message_ids.contains(&e.message_id);
+            if hit {
+                consumed.push(e.message_id);
+            }
+            !hit
+        });
+
+        Ok(consumed)
+    }
+
+    fn find_expired_messages(
+        &self,
+        now: NaiveDateTime,
+        limit: i64,
+    ) -> Result<Vec<Uuid>, RepositoryError> {
+        let tables = self.store.read();
+
+        Ok(tables
+            .messages
+            .iter()
+            .filter(|m| {
+                m.expires_at.is_some_and(|expires_at| expires_at <= now)
+                    || (m.view_once
+                        && !tables
+                            .message_envelopes
+                            .iter()
+                            .any(|e| e.message_id == m.id))
+            })
+            .map(|m| m.id)
+            .take(limit.max(0) as usize)
+            .collect())
+    }
+
+    fn delete_messages(&self, message_ids: &[Uuid]) -> Result<usize, RepositoryError> {
+        let mut tables = self.store.write();
+
+        let before = tables.messages.len();
+        tables.messages.retain(|m| !message_ids.contains(&m.id));
+        tables
+            .message_envelopes
+            .retain(|e| !message_ids.contains(&e.message_id));
+        tables
+            .message_attachments
+            .retain(|(message_id, _)| !message_ids.contains(message_id));
+        tables
+            .pinned_messages
+            .retain(|p| !message_ids.contains(&p.message_id));
+        tables
+            .polls
+            .retain(|p| !message_ids.contains(&p.message_id));
+        tables
+            .poll_votes
+            .retain(|v| !message_ids.contains(&v.message_id));
+
+        Ok(before - tables.messages.len())
+    }
+
+    fn pin_message(&self, pin: NewPinnedMessage) -> Result<PinnedMessage, RepositoryError> {
+        let mut tables = self.store.write();
+
+        ensure_chat(&tables, pin.chat_id)?;
+        ensure_user(&tables, pin.pinned_by)?;
+        if !tables.messages.iter().any(|m| m.id == pin.message_id) {
+            return Err(RepositoryError::MissingReference(format!(
+                "message {} does not exist",
+                pin.message_id
+            )));
+        }
+
+        if tables
+            .pinned_messages
+            .iter()
+            .any(|p| p.message_id == pin.message_id)
+        {
+            return Err(RepositoryError::Conflict(
+                "duplicate key value violates unique constraint \"pinned_messages_pkey\""
+                    .to_string(),
+            ));
+        }
+
+        let pin = PinnedMessage {
+            message_id: pin.message_id,
+            chat_id: pin.chat_id,
+            pinned_by: pin.pinned_by,
+            pinned_at: now(),
+        };
+        tables.pinned_messages.push(pin.clone());
+
+        Ok(pin)
+    }
+
+    fn find_pin(&self, message_id: Uuid) -> Result<Option<PinnedMessage>, RepositoryError> {
+        let tables = self.store.read();
+
+        Ok(tables
+            .pinned_messages
+            .iter()
+            .find(|p| p.message_id == message_id)
+            .cloned())
+    }
+
+    fn unpin_message(&self, chat_id: Uuid, message_id: Uuid) -> Result<(), RepositoryError> {
+        let mut tables = self.store.write();
+
+        let before = tables.pinned_messages.len();
+        tables
+            .pinned_messages
+            .retain(|p| p.chat_id != chat_id || p.message_id != message_id);
+
+        if tables.pinned_messages.len() == before {
+            return Err(RepositoryError::NotFound);
+        }
+
+        Ok(())
+    }
+
+    fn get_pins(&self, chat_id: Uuid) -> Result<Vec<PinnedMessage>, RepositoryError> {
+        let tables = self.store.read();
+        let now = now();
+
+        // Pushed in pin order, so newest first is the reverse.
+        Ok(tables
+            .pinned_messages
+            .iter()
+            .rev()
+            .filter(|p| p.chat_id == chat_id)
+            .filter(|p| {
+                tables.messages.iter().any(|m| {
+                    m.id == p.message_id && m.expires_at.is_none_or(|expires_at| expires_at > now)
+                })
+            })
+            .cloned()
+            .collect())
+    }
+
+    fn get_latest_pins(&self, chat_ids: &[Uuid]) -> Result<Vec<PinnedMessage>, RepositoryError> {
+        let mut latest = Vec::new();
+
+        for chat_id in chat_ids {
+            latest.extend(self.get_pins(*chat_id)?.into_iter().next());
+        }
+
+        Ok(latest)
+    }
+
+    fn create_poll(&self, poll: Poll) -> Result<Poll, RepositoryError> {
+        let mut tables = self.store.write();
+
+        if !tables.messages.iter().any(|m| m.id == poll.message_id) {
+            return Err(RepositoryError::MissingReference(format!(
+                "message {} does not exist",
+                poll.message_id
+            )));
+        }
+
+        if tables.polls.iter().any(|p| p.message_id == poll.message_id) {
+            return Err(RepositoryError::Conflict(
+                "duplicate key value violates unique constraint \"polls_pkey\"".to_string(),
+            ));
+        }
+
+        tables.polls.push(poll.clone());
+
+        Ok(poll)
+    }
+
+    fn get_polls(&self, message_ids: &[Uuid]) -> Result<Vec<Poll>, RepositoryError> {
+        let tables = self.store.read();
+
+        Ok(tables
+            .polls
+            .iter()
+            .filter(|p| message_ids.contains(&p.message_id))
+            .cloned()
+            .collect())
+    }
+
+    fn get_poll_votes(&self, message_ids: &[Uuid]) -> Result<Vec<PollVote>, RepositoryError> {
+        let tables = self.store.read();
+
+        Ok(tables
+            .poll_votes
+            .iter()
+            .filter(|v| message_ids.contains(&v.message_id))
+            .cloned()
+            .collect())
+    }
+
+    fn set_poll_votes(
+        &self,
+        message_id: Uuid,
+        user_id: Uuid,
+        option_indexes: &[i32],
+    ) -> Result<(), RepositoryError> {
+        let mut tables = self.store.write();
+
+        ensure_user(&tables, user_id)?;
+        if !tables.polls.iter().any(|p| p.message_id == message_id) {
+            return Err(RepositoryError::MissingReference(format!(
+                "poll {} does not exist",
+                message_id
+            )));
+        }
+
+        tables
+            .poll_votes
+            .retain(|v| v.message_id != message_id || v.user_id != user_id);
+        let now = now();
+        for option_index in option_indexes {
+            tables.poll_votes.push(PollVote {
+                message_id,
+                user_id,
+                option_index: *option_index,
+                voted_at: now,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn get_read_states(
+        &self,
+        user_id: Uuid,
+        chat_ids: &[Uuid],
+    ) -> Result<Vec<ReadState>, RepositoryError> {
+        let tables = self.store.read();
+        let now = now();
+
+        Ok(tables
+            .chat_members
+            .iter()
+            .filter(|m| m.user_id == user_id && chat_ids.contains(&m.chat_id))
+            .map(|member| {
+                let unread: Vec<&Message> = tables
+                    .messages
+                    .iter()
+                    .filter(|m| m.chat_id == member.chat_id && m.sender_id != user_id)
+                    .filter(|m| {
+                        m.kind == MessageKind::Text.as_str() || m.kind == MessageKind::Poll.as_str()
+                    })
+                    .filter(|m| m.created_at > member.last_read_at)
+                    .filter(|m| m.expires_at.is_none_or(|expires_at| expires_at > now))
+                    .collect();
+
+                ReadState {
+                    chat_id: member.chat_id,
+                    last_read_at: member.last_read_at,
+                    muted: member.muted,
+                    unread_count: unread.len() as i64,
+                    mention_count: unread
+                        .iter()
+                        .filter(|m| m.mention_all || m.mentions.contains(&user_id))
+                        .count() as i64,
+                }
+            })
+            .collect())
+    }
+
+    fn get_mentions(
+        &self,
+        user_id: Uuid,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Mention>, RepositoryError> {
+        let tables = self.store.read();
+        let now = now();
+
+        // Newest first; see get_chat_messages for the reverse iteration.
+        let mut mentions: Vec<Mention> = tables
+            .messages
+            .iter()
+            .rev()
+            .filter(|m| m.sender_id != user_id)
+            .filter(|m| m.mention_all || m.mentions.contains(&user_id))
+            .filter(|m| m.expires_at.is_none_or(|expires_at| expires_at > now))
+            .filter_map(|m| {
+                let member = tables.chat_members.iter().find(|member| {
+                    member.chat_id == m.chat_id
+                        && member.user_id == user_id
+                        && member.joined_at <= m.created_at
+                })?;
+
+                Some(Mention {
+                    message_id: m.id,
+                    chat_id: m.chat_id,
+                    sender_id: m.sender_id,
+                    mention_all: m.mention_all,
+                    created_at: m.created_at,
+                    last_read_at: member.last_read_at,
+                })
+            })
+            .collect();
+        mentions.sort_by_key(|m| Reverse(m.created_at));
+
+        Ok(paginate(mentions.into_iter(), limit, offset))
+    }
+
+    fn get_members_to_notify(
+        &self,
+        chat_id: Uuid,
+        sender_id: Uuid,
+        mentions: &[Uuid],
+        mention_all: bool,
+    ) -> Result<Vec<Uuid>, RepositoryError> {
+        let tables = self.store.read();
+
+        let mut user_ids: Vec<Uuid> = tables
+            .chat_members
+            .iter()
+            .filter(|m| m.chat_id == chat_id && m.user_id != sender_id)
+            .filter(|m| !m.muted || mention_all || mentions.contains(&m.user_id))
+            .map(|m| m.user_id)
+            .collect();
+        user_ids.sort();
+
+        Ok(user_ids)
+    }
+}
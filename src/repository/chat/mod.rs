@@ -1,5 +1,11 @@
+pub mod memory;
 pub mod models;
 pub mod repo;
 
-pub use models::{Chat, ChatMember, Message, NewChat, NewChatMember, NewMessage};
-pub use repo::ChatRepository;
+pub use memory::InMemoryChatRepository;
+pub use models::{
+    Chat, ChatMember, ChatMemberDetails, ChatRekey, MemberFilter, Mention, Message,
+    MessageEnvelope, MessageKind, NewChat, NewChatMember, NewMessage, NewPinnedMessage,
+    PinnedMessage, Poll, PollVote, ReadState, RekeyReason,
+};
+pub use repo::{ChatRepo, ChatRepository};
@@ -2,7 +2,10 @@ use chrono::NaiveDateTime;
 use diesel::prelude::*;
 use uuid::Uuid;
 
-use crate::schema::{chat_members, chats, messages};
+use crate::schema::{
+    chat_members, chat_rekeys, chats, message_envelopes, messages, pinned_messages, poll_votes,
+    polls,
+};
 
 #[derive(Debug, Clone, Queryable, Selectable, Identifiable)]
 #[diesel(table_name = chats)]
@@ -12,6 +15,14 @@ pub struct Chat {
     pub created_by: Uuid,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
+    /// Advanced by every membership change; see [`ChatRekey`].
+    pub epoch: i32,
+    /// Lifetime of new messages; `None` keeps them forever.
+    pub message_ttl_seconds: Option<i32>,
+    /// Whether members may forward the chat's messages to other chats.
+    pub allow_forwarding: bool,
+    /// Whether members may post polls, whose content is not encrypted.
+    pub allow_polls: bool,
 }
 
 #[derive(Debug, Insertable)]
@@ -29,6 +40,50 @@ pub struct ChatMember {
     pub user_id: Uuid,
     pub invited_by: Option<Uuid>,
     pub joined_at: NaiveDateTime,
+    /// Messages created after this are unread for the member.
+    pub last_read_at: NaiveDateTime,
+    /// Muted members are only notified of messages that mention them.
+    pub muted: bool,
+}
+
+/// A member's position in one chat: where they stopped reading and how
+/// many messages from others, and mentions of them, came in since.
+#[derive(Debug, Clone)]
+pub struct ReadState {
+    pub chat_id: Uuid,
+    pub last_read_at: NaiveDateTime,
+    pub muted: bool,
+    pub unread_count: i64,
+    pub mention_count: i64,
+}
+
+/// A message that mentions a member, by name or through `mention_all`.
+#[derive(Debug, Clone, Queryable)]
+pub struct Mention {
+    pub message_id: Uuid,
+    pub chat_id: Uuid,
+    pub sender_id: Uuid,
+    pub mention_all: bool,
+    pub created_at: NaiveDateTime,
+    /// The member's read marker in the chat.
+    pub last_read_at: NaiveDateTime,
+}
+
+/// A membership joined with the member's account.
+#[derive(Debug, Clone, Queryable)]
+pub struct ChatMemberDetails {
+    pub user_id: Uuid,
+    pub username: String,
+    pub role_id: i32,
+    pub invited_by: Option<Uuid>,
+    pub joined_at: NaiveDateTime,
+}
+
+/// Optional narrowing of a member listing; `None` fields match everyone.
+#[derive(Debug, Clone, Default)]
+pub struct MemberFilter {
+    pub role_id: Option<i32>,
+    pub username_prefix: Option<String>,
 }
 
 #[derive(Debug, Insertable)]
@@ -39,14 +94,36 @@ pub struct NewChatMember {
     pub invited_by: Option<Uuid>,
 }
 
+/// A message as seen by one device: `encrypted_content` holds that device's
+/// envelope, or the shared ciphertext of sender-key messages and of messages
+/// sent before devices. Server notices carry a JSON `payload` instead.
 #[derive(Debug, Clone, Queryable, Selectable, Identifiable)]
 #[diesel(table_name = messages)]
 pub struct Message {
     pub id: Uuid,
     pub chat_id: Uuid,
     pub sender_id: Uuid,
-    pub encrypted_content: String,
+    pub encrypted_content: Option<String>,
     pub created_at: NaiveDateTime,
+    pub sender_device_id: Option<Uuid>,
+    /// The chat epoch of a sender-key message; `None` for pairwise ones.
+    pub epoch: Option<i32>,
+    /// A [`MessageKind`] name.
+    pub kind: String,
+    pub payload: Option<String>,
+    /// When the message disappears; set from the chat's timer at send time.
+    pub expires_at: Option<NaiveDateTime>,
+    /// Each recipient device receives the message once.
+    pub view_once: bool,
+    /// Members the sender mentioned; kept in the clear for notifications.
+    pub mentions: Vec<Uuid>,
+    /// The message mentions every member.
+    pub mention_all: bool,
+    /// Set on forwarded messages: the chat, message and sender the content
+    /// first came from, however many forwards ago.
+    pub forwarded_from_chat_id: Option<Uuid>,
+    pub forwarded_from_message_id: Option<Uuid>,
+    pub forwarded_from_sender_id: Option<Uuid>,
 }
 
 #[derive(Debug, Insertable)]
@@ -54,5 +131,134 @@ pub struct Message {
 pub struct NewMessage {
     pub chat_id: Uuid,
     pub sender_id: Uuid,
-    pub encrypted_content: String,
+    pub sender_device_id: Option<Uuid>,
+    pub encrypted_content: Option<String>,
+    pub epoch: Option<i32>,
+    pub kind: String,
+    pub payload: Option<String>,
+    pub expires_at: Option<NaiveDateTime>,
+    pub view_once: bool,
+    pub mentions: Vec<Uuid>,
+    pub mention_all: bool,
+    pub forwarded_from_chat_id: Option<Uuid>,
+    pub forwarded_from_message_id: Option<Uuid>,
+    pub forwarded_from_sender_id: Option<Uuid>,
+}
+
+/// Ciphertext of a message for one recipient device.
+#[derive(Debug, Clone, Queryable, Selectable, Insertable)]
+#[diesel(table_name = message_envelopes)]
+pub struct MessageEnvelope {
+    pub message_id: Uuid,
+    pub device_id: Uuid,
+    pub ciphertext: String,
+}
+
+/// What a message is: user content, or a notice generated by the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageKind {
+    Text,
+    Poll,
+    IdentityKeyChanged,
+    MemberJoined,
+    MemberInvited,
+    MemberLeft,
+    MemberRemoved,
+    ChatRenamed,
+    MessageTtlChanged,
+    ForwardingChanged,
+    PollsChanged,
+    MessagePinned,
+    MessageUnpinned,
+}
+
+impl MessageKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Text => "text",
+            Self::Poll => "poll",
+            Self::IdentityKeyChanged => "identity_key_changed",
+            Self::MemberJoined => "member_joined",
+            Self::MemberInvited => "member_invited",
+            Self::MemberLeft => "member_left",
+            Self::MemberRemoved => "member_removed",
+            Self::ChatRenamed => "chat_renamed",
+            Self::MessageTtlChanged => "message_ttl_changed",
+            Self::ForwardingChanged => "forwarding_changed",
+            Self::PollsChanged => "polls_changed",
+            Self::MessagePinned => "message_pinned",
+            Self::MessageUnpinned => "message_unpinned",
+        }
+    }
+}
+
+/// Why a chat moved to a new epoch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RekeyReason {
+    Invite,
+    Leave,
+    Kick,
+}
+
+impl RekeyReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Invite => "invite",
+            Self::Leave => "leave",
+            Self::Kick => "kick",
+        }
+    }
+}
+
+/// A membership change that obliges every device to distribute a fresh
+/// sender key. `user_id` is the member who joined or left.
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = chat_rekeys)]
+pub struct ChatRekey {
+    pub chat_id: Uuid,
+    pub epoch: i32,
+    pub reason: String,
+    pub user_id: Option<Uuid>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Queryable, Selectable, Insertable)]
+#[diesel(table_name = pinned_messages)]
+pub struct PinnedMessage {
+    pub message_id: Uuid,
+    pub chat_id: Uuid,
+    pub pinned_by: Uuid,
+    pub pinned_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = pinned_messages)]
+pub struct NewPinnedMessage {
+    pub message_id: Uuid,
+    pub chat_id: Uuid,
+    pub pinned_by: Uuid,
+}
+
+/// The question and options of a `poll` message, stored in the clear.
+#[derive(Debug, Clone, Queryable, Selectable, Insertable)]
+#[diesel(table_name = polls)]
+pub struct Poll {
+    pub message_id: Uuid,
+    pub question: String,
+    pub options: Vec<String>,
+    pub multiple_choice: bool,
+    pub anonymous: bool,
+    /// Votes are refused from this time on.
+    pub closes_at: Option<NaiveDateTime>,
+}
+
+/// One option a member picked in a poll.
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = poll_votes)]
+pub struct PollVote {
+    pub message_id: Uuid,
+    pub user_id: Uuid,
+    /// Index into [`Poll::options`].
+    pub option_index: i32,
+    pub voted_at: NaiveDateTime,
 }
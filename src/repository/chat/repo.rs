@@ -1,61 +1,259 @@
+use chrono::{NaiveDateTime, Utc};
 use diesel::prelude::*;
-use std::sync::Arc;
+use diesel::sql_types::{Nullable, Text};
+use std::collections::HashMap;
 use uuid::Uuid;
 
-use super::models::{Chat, ChatMember, Message, NewChat, NewChatMember, NewMessage};
-use crate::bootstrap::postgres::Postgres;
-use crate::schema::{chat_members, chats, messages};
+use super::models::{
+    Chat, ChatMember, ChatMemberDetails, ChatRekey, MemberFilter, Mention, Message,
+    MessageEnvelope, MessageKind, NewChat, NewChatMember, NewMessage, NewPinnedMessage,
+    PinnedMessage, Poll, PollVote, ReadState, RekeyReason,
+};
+use crate::repository::RepositoryError;
+use crate::repository::auth::repo::escape_like;
+use crate::repository::connection::PgSource;
+use crate::schema::{
+    auth_users, chat_members, chat_rekeys, chats, message_envelopes, messages, pinned_messages,
+    poll_votes, polls,
+};
+
+diesel::define_sql_function!(fn coalesce(x: Nullable<Text>, y: Nullable<Text>) -> Nullable<Text>);
+
+pub trait ChatRepo: Send + Sync {
+    fn create_chat(&self, name: String, created_by: Uuid) -> Result<Chat, RepositoryError>;
+
+    fn find_chat_by_id(&self, chat_id: Uuid) -> Result<Option<Chat>, RepositoryError>;
+
+    /// Like [`ChatRepo::find_chat_by_id`], but holds a row lock until the
+    /// surrounding transaction ends, so changes to the chat's pins are
+    /// serialized.
+    fn lock_chat(&self, chat_id: Uuid) -> Result<Option<Chat>, RepositoryError>;
+
+    fn get_user_chats(&self, user_id: Uuid) -> Result<Vec<Chat>, RepositoryError>;
+
+    fn add_member(
+        &self,
+        chat_id: Uuid,
+        user_id: Uuid,
+        invited_by: Option<Uuid>,
+    ) -> Result<ChatMember, RepositoryError>;
+
+    /// Fails with [`RepositoryError::NotFound`] when the user is no member.
+    fn remove_member(&self, chat_id: Uuid, user_id: Uuid) -> Result<(), RepositoryError>;
+
+    fn is_member(&self, chat_id: Uuid, user_id: Uuid) -> Result<bool, RepositoryError>;
+
+    /// Moves the member's read marker forward to `read_at`; an earlier time
+    /// leaves it where it is.
+    fn mark_read(
+        &self,
+        chat_id: Uuid,
+        user_id: Uuid,
+        read_at: NaiveDateTime,
+    ) -> Result<(), RepositoryError>;
+
+    /// Fails with [`RepositoryError::NotFound`] when the user is no member.
+    fn set_muted(&self, chat_id: Uuid, user_id: Uuid, muted: bool) -> Result<(), RepositoryError>;
+
+    fn rename_chat(&self, chat_id: Uuid, name: &str) -> Result<Chat, RepositoryError>;
+
+    /// Sets the lifetime of messages sent from now on; `None` turns the
+    /// timer off.
+    fn set_message_ttl(
+        &self,
+        chat_id: Uuid,
+        ttl_seconds: Option<i32>,
+    ) -> Result<Chat, RepositoryError>;
+
+    fn set_allow_forwarding(&self, chat_id: Uuid, allow: bool) -> Result<Chat, RepositoryError>;
+
+    fn set_allow_polls(&self, chat_id: Uuid, allow: bool) -> Result<Chat, RepositoryError>;
+
+    /// Moves the chat to its next epoch and records why.
+    fn advance_epoch(
+        &self,
+        chat_id: Uuid,
+        reason: RekeyReason,
+        user_id: Uuid,
+    ) -> Result<ChatRekey, RepositoryError>;
+
+    /// Epoch changes after `since_epoch`, oldest first.
+    fn get_rekeys(
+        &self,
+        chat_id: Uuid,
+        since_epoch: i32,
+    ) -> Result<Vec<ChatRekey>, RepositoryError>;
+
+    /// Members ordered by join time, each with its account in one query.
+    fn get_chat_members(
+        &self,
+        chat_id: Uuid,
+        filter: &MemberFilter,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<ChatMemberDetails>, RepositoryError>;
+
+    /// Stores a message with one envelope per recipient device, atomically.
+    fn create_message(
+        &self,
+        new_message: NewMessage,
+        envelopes: &HashMap<Uuid, String>,
+    ) -> Result<Message, RepositoryError>;
+
+    /// Newest first, with each message's envelope for `device_id`. Messages
+    /// that carry nothing for the device are skipped, and so are expired
+    /// ones that were not deleted yet.
+    fn get_chat_messages(
+        &self,
+        chat_id: Uuid,
+        device_id: Uuid,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Message>, RepositoryError>;
+
+    fn get_message_by_id(&self, message_id: Uuid) -> Result<Option<Message>, RepositoryError>;
+
+    /// Deletes `device_id`'s envelopes of the given view-once messages and
+    /// returns the messages whose envelope this call removed.
+    fn consume_view_once(
+        &self,
+        device_id: Uuid,
+        message_ids: &[Uuid],
+    ) -> Result<Vec<Uuid>, RepositoryError>;
+
+    /// Up to `limit` messages that expired by `now`, or that are view-once
+    /// with no envelope left to deliver.
+    fn find_expired_messages(
+        &self,
+        now: NaiveDateTime,
+        limit: i64,
+    ) -> Result<Vec<Uuid>, RepositoryError>;
+
+    /// Hard-deletes the messages along with their envelopes, pins, polls
+    /// and attachment links. Returns how many went.
+    fn delete_messages(&self, message_ids: &[Uuid]) -> Result<usize, RepositoryError>;
+
+    fn pin_message(&self, pin: NewPinnedMessage) -> Result<PinnedMessage, RepositoryError>;
+
+    fn find_pin(&self, message_id: Uuid) -> Result<Option<PinnedMessage>, RepositoryError>;
+
+    fn unpin_message(&self, chat_id: Uuid, message_id: Uuid) -> Result<(), RepositoryError>;
+
+    /// Pins of the chat, newest first, leaving out expired messages that were
+    /// not deleted yet.
+    fn get_pins(&self, chat_id: Uuid) -> Result<Vec<PinnedMessage>, RepositoryError>;
+
+    /// The newest pin of each of the given chats that has one.
+    fn get_latest_pins(&self, chat_ids: &[Uuid]) -> Result<Vec<PinnedMessage>, RepositoryError>;
+
+    fn create_poll(&self, poll: Poll) -> Result<Poll, RepositoryError>;
+
+    /// The polls among the given messages.
+    fn get_polls(&self, message_ids: &[Uuid]) -> Result<Vec<Poll>, RepositoryError>;
+
+    /// Every vote in the given polls.
+    fn get_poll_votes(&self, message_ids: &[Uuid]) -> Result<Vec<PollVote>, RepositoryError>;
+
+    /// Replaces the user's votes in a poll; no options withdraws them.
+    fn set_poll_votes(
+        &self,
+        message_id: Uuid,
+        user_id: Uuid,
+        option_indexes: &[i32],
+    ) -> Result<(), RepositoryError>;
+
+    /// The user's read state in each of the given chats they belong to.
+    /// Unread counts cover unexpired text messages and polls from other
+    /// members.
+    fn get_read_states(
+        &self,
+        user_id: Uuid,
+        chat_ids: &[Uuid],
+    ) -> Result<Vec<ReadState>, RepositoryError>;
+
+    /// Messages from others that mention the user in chats they belong to,
+    /// newest first, from the time they joined.
+    fn get_mentions(
+        &self,
+        user_id: Uuid,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Mention>, RepositoryError>;
+
+    /// Members other than `sender_id` to notify of a message: those who did
+    /// not mute the chat, plus everyone the message mentions.
+    fn get_members_to_notify(
+        &self,
+        chat_id: Uuid,
+        sender_id: Uuid,
+        mentions: &[Uuid],
+        mention_all: bool,
+    ) -> Result<Vec<Uuid>, RepositoryError>;
+}
 
 #[derive(Clone)]
 pub struct ChatRepository {
-    postgres: Arc<Postgres>,
+    db: PgSource,
 }
 
 impl ChatRepository {
-    pub fn new(postgres: Arc<Postgres>) -> Self {
-        Self { postgres }
+    pub fn new(db: PgSource) -> Self {
+        Self { db }
     }
+}
 
-    pub fn create_chat(&self, name: String, created_by: Uuid) -> Result<Chat, String> {
-        let mut conn = self.postgres.conn()?;
+impl ChatRepo for ChatRepository {
+    fn create_chat(&self, name: String, created_by: Uuid) -> Result<Chat, RepositoryError> {
+        let mut conn = self.db.conn()?;
 
         let new_chat = NewChat { name, created_by };
 
         diesel::insert_into(chats::table)
             .values(&new_chat)
             .returning(Chat::as_returning())
-            .get_result(&mut conn)
-            .map_err(|e| format!("Failed to create chat: {}", e))
+            .get_result(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    fn find_chat_by_id(&self, chat_id: Uuid) -> Result<Option<Chat>, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        chats::table
+            .filter(chats::id.eq(chat_id))
+            .first::<Chat>(&mut *conn)
+            .optional()
+            .map_err(RepositoryError::from)
     }
 
-    pub fn find_chat_by_id(&self, chat_id: Uuid) -> Result<Option<Chat>, String> {
-        let mut conn = self.postgres.conn()?;
+    fn lock_chat(&self, chat_id: Uuid) -> Result<Option<Chat>, RepositoryError> {
+        let mut conn = self.db.conn()?;
 
         chats::table
             .filter(chats::id.eq(chat_id))
-            .first::<Chat>(&mut conn)
+            .for_update()
+            .first::<Chat>(&mut *conn)
             .optional()
-            .map_err(|e| format!("Failed to find chat: {}", e))
+            .map_err(RepositoryError::from)
     }
 
-    pub fn get_user_chats(&self, user_id: Uuid) -> Result<Vec<Chat>, String> {
-        let mut conn = self.postgres.conn()?;
+    fn get_user_chats(&self, user_id: Uuid) -> Result<Vec<Chat>, RepositoryError> {
+        let mut conn = self.db.conn()?;
 
         chat_members::table
             .inner_join(chats::table.on(chats::id.eq(chat_members::chat_id)))
             .filter(chat_members::user_id.eq(user_id))
             .select(Chat::as_select())
-            .load::<Chat>(&mut conn)
-            .map_err(|e| format!("Failed to get user chats: {}", e))
+            .load::<Chat>(&mut *conn)
+            .map_err(RepositoryError::from)
     }
 
-    pub fn add_member(
+    fn add_member(
         &self,
         chat_id: Uuid,
         user_id: Uuid,
         invited_by: Option<Uuid>,
-    ) -> Result<ChatMember, String> {
-        let mut conn = self.postgres.conn()?;
+    ) -> Result<ChatMember, RepositoryError> {
+        let mut conn = self.db.conn()?;
 
         let new_member = NewChatMember {
             chat_id,
@@ -66,77 +264,676 @@ impl ChatRepository {
         diesel::insert_into(chat_members::table)
             .values(&new_member)
             .returning(ChatMember::as_returning())
-            .get_result(&mut conn)
-            .map_err(|e| format!("Failed to add member: {}", e))
+            .get_result(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    fn remove_member(&self, chat_id: Uuid, user_id: Uuid) -> Result<(), RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        let deleted = diesel::delete(
+            chat_members::table
+                .filter(chat_members::chat_id.eq(chat_id))
+                .filter(chat_members::user_id.eq(user_id)),
+        )
+        .execute(&mut *conn)?;
+
+        if deleted == 0 {
+            return Err(RepositoryError::NotFound);
+        }
+
+        Ok(())
     }
 
-    pub fn is_member(&self, chat_id: Uuid, user_id: Uuid) -> Result<bool, String> {
-        let mut conn = self.postgres.conn()?;
+    fn is_member(&self, chat_id: Uuid, user_id: Uuid) -> Result<bool, RepositoryError> {
+        let mut conn = self.db.conn()?;
 
         let count: i64 = chat_members::table
             .filter(chat_members::chat_id.eq(chat_id))
             .filter(chat_members::user_id.eq(user_id))
             .count()
-            .get_result(&mut conn)
-            .map_err(|e| format!("Failed to check membership: {}", e))?;
+            .get_result(&mut *conn)?;
 
         Ok(count > 0)
     }
 
-    pub fn get_chat_members(&self, chat_id: Uuid) -> Result<Vec<ChatMember>, String> {
-        let mut conn = self.postgres.conn()?;
+    fn mark_read(
+        &self,
+        chat_id: Uuid,
+        user_id: Uuid,
+        read_at: NaiveDateTime,
+    ) -> Result<(), RepositoryError> {
+        let mut conn = self.db.conn()?;
 
-        chat_members::table
-            .filter(chat_members::chat_id.eq(chat_id))
-            .load::<ChatMember>(&mut conn)
-            .map_err(|e| format!("Failed to get chat members: {}", e))
+        diesel::update(
+            chat_members::table
+                .filter(chat_members::chat_id.eq(chat_id))
+                .filter(chat_members::user_id.eq(user_id))
+                .filter(chat_members::last_read_at.lt(read_at)),
+        )
+        .set(chat_members::last_read_at.eq(read_at))
+        .execute(&mut *conn)?;
+
+        Ok(())
+    }
+
+    fn set_muted(&self, chat_id: Uuid, user_id: Uuid, muted: bool) -> Result<(), RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        let updated = diesel::update(
+            chat_members::table
+                .filter(chat_members::chat_id.eq(chat_id))
+                .filter(chat_members::user_id.eq(user_id)),
+        )
+        .set(chat_members::muted.eq(muted))
+        .execute(&mut *conn)?;
+
+        if updated == 0 {
+            return Err(RepositoryError::NotFound);
+        }
+
+        Ok(())
+    }
+
+    fn rename_chat(&self, chat_id: Uuid, name: &str) -> Result<Chat, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        diesel::update(chats::table.find(chat_id))
+            .set((chats::name.eq(name), chats::updated_at.eq(diesel::dsl::now)))
+            .returning(Chat::as_returning())
+            .get_result(&mut *conn)
+            .map_err(RepositoryError::from)
     }
 
-    pub fn create_message(
+    fn set_message_ttl(
         &self,
         chat_id: Uuid,
-        sender_id: Uuid,
-        encrypted_content: String,
-    ) -> Result<Message, String> {
-        let mut conn = self.postgres.conn()?;
+        ttl_seconds: Option<i32>,
+    ) -> Result<Chat, RepositoryError> {
+        let mut conn = self.db.conn()?;
 
-        let new_message = NewMessage {
-            chat_id,
-            sender_id,
-            encrypted_content,
-        };
+        diesel::update(chats::table.find(chat_id))
+            .set((
+                chats::message_ttl_seconds.eq(ttl_seconds),
+                chats::updated_at.eq(diesel::dsl::now),
+            ))
+            .returning(Chat::as_returning())
+            .get_result(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    fn set_allow_forwarding(&self, chat_id: Uuid, allow: bool) -> Result<Chat, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        diesel::update(chats::table.find(chat_id))
+            .set((
+                chats::allow_forwarding.eq(allow),
+                chats::updated_at.eq(diesel::dsl::now),
+            ))
+            .returning(Chat::as_returning())
+            .get_result(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    fn set_allow_polls(&self, chat_id: Uuid, allow: bool) -> Result<Chat, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        diesel::update(chats::table.find(chat_id))
+            .set((
+                chats::allow_polls.eq(allow),
+                chats::updated_at.eq(diesel::dsl::now),
+            ))
+            .returning(Chat::as_returning())
+            .get_result(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
 
-        diesel::insert_into(messages::table)
-            .values(&new_message)
-            .returning(Message::as_returning())
-            .get_result(&mut conn)
-            .map_err(|e| format!("Failed to create message: {}", e))
+    #[tracing::instrument(skip(self))]
+    fn advance_epoch(
+        &self,
+        chat_id: Uuid,
+        reason: RekeyReason,
+        user_id: Uuid,
+    ) -> Result<ChatRekey, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        conn.transaction(|conn| {
+            // The row lock also orders concurrent membership changes.
+            let epoch: i32 = diesel::update(chats::table.find(chat_id))
+                .set((
+                    chats::epoch.eq(chats::epoch + 1),
+                    chats::updated_at.eq(diesel::dsl::now),
+                ))
+                .returning(chats::epoch)
+                .get_result(conn)?;
+
+            diesel::insert_into(chat_rekeys::table)
+                .values((
+                    chat_rekeys::chat_id.eq(chat_id),
+                    chat_rekeys::epoch.eq(epoch),
+                    chat_rekeys::reason.eq(reason.as_str()),
+                    chat_rekeys::user_id.eq(user_id),
+                ))
+                .returning(ChatRekey::as_returning())
+                .get_result(conn)
+                .map_err(RepositoryError::from)
+        })
     }
 
-    pub fn get_chat_messages(
+    fn get_rekeys(
         &self,
         chat_id: Uuid,
+        since_epoch: i32,
+    ) -> Result<Vec<ChatRekey>, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        chat_rekeys::table
+            .filter(chat_rekeys::chat_id.eq(chat_id))
+            .filter(chat_rekeys::epoch.gt(since_epoch))
+            .order(chat_rekeys::epoch.asc())
+            .select(ChatRekey::as_select())
+            .load(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    #[tracing::instrument(skip(self, filter))]
+    fn get_chat_members(
+        &self,
+        chat_id: Uuid,
+        filter: &MemberFilter,
         limit: i64,
         offset: i64,
-    ) -> Result<Vec<Message>, String> {
-        let mut conn = self.postgres.conn()?;
+    ) -> Result<Vec<ChatMemberDetails>, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        let mut query = chat_members::table
+            .inner_join(auth_users::table.on(auth_users::id.eq(chat_members::user_id)))
+            .filter(chat_members::chat_id.eq(chat_id))
+            .into_boxed();
+
+        if let Some(role_id) = filter.role_id {
+            query = query.filter(auth_users::role_id.eq(role_id));
+        }
+
+        if let Some(prefix) = &filter.username_prefix {
+            query = query.filter(auth_users::username.ilike(format!("{}%", escape_like(prefix))));
+        }
+
+        query
+            .order((chat_members::joined_at.asc(), chat_members::user_id.asc()))
+            .limit(limit)
+            .offset(offset)
+            .select((
+                chat_members::user_id,
+                auth_users::username,
+                auth_users::role_id,
+                chat_members::invited_by,
+                chat_members::joined_at,
+            ))
+            .load::<ChatMemberDetails>(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    #[tracing::instrument(skip(self, new_message, envelopes), fields(devices = envelopes.len()))]
+    fn create_message(
+        &self,
+        new_message: NewMessage,
+        envelopes: &HashMap<Uuid, String>,
+    ) -> Result<Message, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        conn.transaction(|conn| {
+            let message = diesel::insert_into(messages::table)
+                .values(&new_message)
+                .returning(Message::as_returning())
+                .get_result(conn)?;
+
+            let rows: Vec<MessageEnvelope> = envelopes
+                .iter()
+                .map(|(device_id, ciphertext)| MessageEnvelope {
+                    message_id: message.id,
+                    device_id: *device_id,
+                    ciphertext: ciphertext.clone(),
+                })
+                .collect();
+
+            if !rows.is_empty() {
+                diesel::insert_into(message_envelopes::table)
+                    .values(&rows)
+                    .execute(conn)?;
+            }
+
+            Ok(message)
+        })
+    }
+
+    fn get_chat_messages(
+        &self,
+        chat_id: Uuid,
+        device_id: Uuid,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Message>, RepositoryError> {
+        let mut conn = self.db.conn()?;
 
         messages::table
+            .left_join(
+                message_envelopes::table.on(message_envelopes::message_id
+                    .eq(messages::id)
+                    .and(message_envelopes::device_id.eq(device_id))),
+            )
             .filter(messages::chat_id.eq(chat_id))
+            .filter(
+                messages::expires_at
+                    .is_null()
+                    .or(messages::expires_at.gt(Utc::now().naive_utc())),
+            )
+            .filter(
+                message_envelopes::ciphertext
+                    .is_not_null()
+                    .or(messages::encrypted_content.is_not_null())
+                    .or(messages::kind.ne(MessageKind::Text.as_str())),
+            )
             .order(messages::created_at.desc())
             .limit(limit)
             .offset(offset)
-            .load::<Message>(&mut conn)
-            .map_err(|e| format!("Failed to get messages: {}", e))
+            .select((
+                messages::id,
+                messages::chat_id,
+                messages::sender_id,
+                coalesce(
+                    message_envelopes::ciphertext.nullable(),
+                    messages::encrypted_content,
+                ),
+                messages::created_at,
+                messages::sender_device_id,
+                messages::epoch,
+                messages::kind,
+                messages::payload,
+                messages::expires_at,
+                messages::view_once,
+                messages::mentions,
+                messages::mention_all,
+                messages::forwarded_from_chat_id,
+                messages::forwarded_from_message_id,
+                messages::forwarded_from_sender_id,
+            ))
+            .load::<Message>(&mut *conn)
+            .map_err(RepositoryError::from)
     }
 
-    pub fn get_message_by_id(&self, message_id: Uuid) -> Result<Option<Message>, String> {
-        let mut conn = self.postgres.conn()?;
+    fn get_message_by_id(&self, message_id: Uuid) -> Result<Option<Message>, RepositoryError> {
+        let mut conn = self.db.conn()?;
 
         messages::table
             .filter(messages::id.eq(message_id))
-            .first::<Message>(&mut conn)
+            .select(Message::as_select())
+            .first::<Message>(&mut *conn)
+            .optional()
+            .map_err(RepositoryError::from)
+    }
+
+    fn consume_view_once(
+        &self,
+        device_id: Uuid,
+        message_ids: &[Uuid],
+    ) -> Result<Vec<Uuid>, RepositoryError> {
+        if message_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut conn = self.db.conn()?;
+
+        diesel::delete(
+            message_envelopes::table
+                .filter(message_envelopes::device_id.eq(device_id))
+                .filter(message_envelopes::message_id.eq_any(message_ids)),
+        )
+        .returning(message_envelopes::message_id)
+        .get_results(&mut *conn)
+        .map_err(RepositoryError::from)
+    }
+
+    #[tracing::instrument(skip(self))]
+    fn find_expired_messages(
+        &self,
+        now: NaiveDateTime,
+        limit: i64,
+    ) -> Result<Vec<Uuid>, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        let mut ids: Vec<Uuid> = messages::table
+            .filter(messages::expires_at.le(now))
+            .select(messages::id)
+            .limit(limit)
+            .load(&mut *conn)?;
+
+        let remaining = limit - ids.len() as i64;
+        if remaining > 0 {
+            ids.extend(
+                messages::table
+                    .left_join(
+                        message_envelopes::table.on(message_envelopes::message_id.eq(messages::id)),
+                    )
+                    .filter(messages::view_once)
+                    .filter(message_envelopes::message_id.is_null())
+                    .select(messages::id)
+                    .limit(remaining)
+                    .load::<Uuid>(&mut *conn)?,
+            );
+        }
+
+        Ok(ids)
+    }
+
+    #[tracing::instrument(skip(self, message_ids), fields(count = message_ids.len()))]
+    fn delete_messages(&self, message_ids: &[Uuid]) -> Result<usize, RepositoryError> {
+        if message_ids.is_empty() {
+            return Ok(0);
+        }
+
+        let mut conn = self.db.conn()?;
+
+        diesel::delete(messages::table.filter(messages::id.eq_any(message_ids)))
+            .execute(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    #[tracing::instrument(skip(self, pin), fields(chat_id = %pin.chat_id, message_id = %pin.message_id))]
+    fn pin_message(&self, pin: NewPinnedMessage) -> Result<PinnedMessage, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        diesel::insert_into(pinned_messages::table)
+            .values(&pin)
+            .returning(PinnedMessage::as_returning())
+            .get_result(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    fn find_pin(&self, message_id: Uuid) -> Result<Option<PinnedMessage>, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        pinned_messages::table
+            .find(message_id)
+            .select(PinnedMessage::as_select())
+            .first(&mut *conn)
             .optional()
-            .map_err(|e| format!("Failed to find message: {}", e))
+            .map_err(RepositoryError::from)
+    }
+
+    #[tracing::instrument(skip(self))]
+    fn unpin_message(&self, chat_id: Uuid, message_id: Uuid) -> Result<(), RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        let deleted = diesel::delete(
+            pinned_messages::table
+                .filter(pinned_messages::chat_id.eq(chat_id))
+                .filter(pinned_messages::message_id.eq(message_id)),
+        )
+        .execute(&mut *conn)?;
+
+        if deleted == 0 {
+            return Err(RepositoryError::NotFound);
+        }
+
+        Ok(())
+    }
+
+    fn get_pins(&self, chat_id: Uuid) -> Result<Vec<PinnedMessage>, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        pinned_messages::table
+            .inner_join(messages::table)
+            .filter(pinned_messages::chat_id.eq(chat_id))
+            .filter(
+                messages::expires_at
+                    .is_null()
+                    .or(messages::expires_at.gt(Utc::now().naive_utc())),
+            )
+            .order((
+                pinned_messages::pinned_at.desc(),
+                pinned_messages::message_id.asc(),
+            ))
+            .select(PinnedMessage::as_select())
+            .load(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    fn get_latest_pins(&self, chat_ids: &[Uuid]) -> Result<Vec<PinnedMessage>, RepositoryError> {
+        if chat_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut conn = self.db.conn()?;
+
+        pinned_messages::table
+            .inner_join(messages::table)
+            .filter(pinned_messages::chat_id.eq_any(chat_ids))
+            .filter(
+                messages::expires_at
+                    .is_null()
+                    .or(messages::expires_at.gt(Utc::now().naive_utc())),
+            )
+            .distinct_on(pinned_messages::chat_id)
+            .order((
+                pinned_messages::chat_id,
+                pinned_messages::pinned_at.desc(),
+                pinned_messages::message_id.asc(),
+            ))
+            .select(PinnedMessage::as_select())
+            .load(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    fn create_poll(&self, poll: Poll) -> Result<Poll, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        diesel::insert_into(polls::table)
+            .values(&poll)
+            .returning(Poll::as_returning())
+            .get_result(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    fn get_polls(&self, message_ids: &[Uuid]) -> Result<Vec<Poll>, RepositoryError> {
+        if message_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut conn = self.db.conn()?;
+
+        polls::table
+            .filter(polls::message_id.eq_any(message_ids))
+            .select(Poll::as_select())
+            .load(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    fn get_poll_votes(&self, message_ids: &[Uuid]) -> Result<Vec<PollVote>, RepositoryError> {
+        if message_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut conn = self.db.conn()?;
+
+        poll_votes::table
+            .filter(poll_votes::message_id.eq_any(message_ids))
+            .order((poll_votes::voted_at.asc(), poll_votes::user_id.asc()))
+            .select(PollVote::as_select())
+            .load(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    fn set_poll_votes(
+        &self,
+        message_id: Uuid,
+        user_id: Uuid,
+        option_indexes: &[i32],
+    ) -> Result<(), RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        conn.transaction(|conn| {
+            diesel::delete(
+                poll_votes::table
+                    .filter(poll_votes::message_id.eq(message_id))
+                    .filter(poll_votes::user_id.eq(user_id)),
+            )
+            .execute(conn)?;
+
+            let votes: Vec<_> = option_indexes
+                .iter()
+                .map(|option_index| {
+                    (
+                        poll_votes::message_id.eq(message_id),
+                        poll_votes::user_id.eq(user_id),
+                        poll_votes::option_index.eq(option_index),
+                    )
+                })
+                .collect();
+            diesel::insert_into(poll_votes::table)
+                .values(&votes)
+                .execute(conn)?;
+
+            Ok(())
+        })
+    }
+
+    fn get_read_states(
+        &self,
+        user_id: Uuid,
+        chat_ids: &[Uuid],
+    ) -> Result<Vec<ReadState>, RepositoryError> {
+        if chat_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut conn = self.db.conn()?;
+        let now = Utc::now().naive_utc();
+
+        let members: Vec<ChatMember> = chat_members::table
+            .filter(chat_members::user_id.eq(user_id))
+            .filter(chat_members::chat_id.eq_any(chat_ids))
+            .select(ChatMember::as_select())
+            .load(&mut *conn)?;
+
+        let unread: HashMap<Uuid, i64> = messages::table
+            .inner_join(
+                chat_members::table.on(chat_members::chat_id
+                    .eq(messages::chat_id)
+                    .and(chat_members::user_id.eq(user_id))),
+            )
+            .filter(messages::chat_id.eq_any(chat_ids))
+            .filter(messages::sender_id.ne(user_id))
+            .filter(messages::kind.eq_any([MessageKind::Text.as_str(), MessageKind::Poll.as_str()]))
+            .filter(messages::created_at.gt(chat_members::last_read_at))
+            .filter(
+                messages::expires_at
+                    .is_null()
+                    .or(messages::expires_at.gt(now)),
+            )
+            .group_by(messages::chat_id)
+            .select((messages::chat_id, diesel::dsl::count_star()))
+            .load::<(Uuid, i64)>(&mut *conn)?
+            .into_iter()
+            .collect();
+
+        let mentioned: HashMap<Uuid, i64> = messages::table
+            .inner_join(
+                chat_members::table.on(chat_members::chat_id
+                    .eq(messages::chat_id)
+                    .and(chat_members::user_id.eq(user_id))),
+            )
+            .filter(messages::chat_id.eq_any(chat_ids))
+            .filter(messages::sender_id.ne(user_id))
+            .filter(messages::kind.eq_any([MessageKind::Text.as_str(), MessageKind::Poll.as_str()]))
+            .filter(messages::created_at.gt(chat_members::last_read_at))
+            .filter(
+                messages::expires_at
+                    .is_null()
+                    .or(messages::expires_at.gt(now)),
+            )
+            .filter(messages::mention_all.or(messages::mentions.contains(vec![user_id])))
+            .group_by(messages::chat_id)
+            .select((messages::chat_id, diesel::dsl::count_star()))
+            .load::<(Uuid, i64)>(&mut *conn)?
+            .into_iter()
+            .collect();
+
+        Ok(members
+            .into_iter()
+            .map(|member| ReadState {
+                chat_id: member.chat_id,
+                last_read_at: member.last_read_at,
+                muted: member.muted,
+                unread_count: unread.get(&member.chat_id).copied().unwrap_or(0),
+                mention_count: mentioned.get(&member.chat_id).copied().unwrap_or(0),
+            })
+            .collect())
+    }
+
+    fn get_mentions(
+        &self,
+        user_id: Uuid,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Mention>, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        messages::table
+            .inner_join(
+                chat_members::table.on(chat_members::chat_id
+                    .eq(messages::chat_id)
+                    .and(chat_members::user_id.eq(user_id))),
+            )
+            .filter(messages::sender_id.ne(user_id))
+            .filter(messages::created_at.ge(chat_members::joined_at))
+            .filter(messages::mention_all.or(messages::mentions.contains(vec![user_id])))
+            .filter(
+                messages::expires_at
+                    .is_null()
+                    .or(messages::expires_at.gt(Utc::now().naive_utc())),
+            )
+            .order((messages::created_at.desc(), messages::id.asc()))
+            .limit(limit)
+            .offset(offset)
+            .select((
+                messages::id,
+                messages::chat_id,
+                messages::sender_id,
+                messages::mention_all,
+                messages::created_at,
+                chat_members::last_read_at,
+            ))
+            .load::<Mention>(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    fn get_members_to_notify(
+        &self,
+        chat_id: Uuid,
+        sender_id: Uuid,
+        mentions: &[Uuid],
+        mention_all: bool,
+    ) -> Result<Vec<Uuid>, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        let mut query = chat_members::table
+            .filter(chat_members::chat_id.eq(chat_id))
+            .filter(chat_members::user_id.ne(sender_id))
+            .select(chat_members::user_id)
+            .into_boxed();
+
+        if !mention_all {
+            query = query.filter(
+                chat_members::muted
+                    .eq(false)
+                    .or(chat_members::user_id.eq_any(mentions)),
+            );
+        }
+
+        query
+            .order(chat_members::user_id.asc())
+            .load(&mut *conn)
+            .map_err(RepositoryError::from)
     }
 }
@@ -0,0 +1,104 @@
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use diesel::connection::{Connection, TransactionManager};
+use diesel::pg::PgConnection;
+
+use super::error::RepositoryError;
+use crate::bootstrap::postgres::{PgPooledConnection, Postgres};
+
+type PgTransactionManager = <PgConnection as Connection>::TransactionManager;
+
+/// Where a Postgres repository gets its connection: a fresh one from the pool
+/// per call, or the single connection of an open transaction.
+#[derive(Clone)]
+pub enum PgSource {
+    Pool(Arc<Postgres>),
+    Transaction(Arc<Mutex<PgPooledConnection>>),
+}
+
+pub enum PgConn<'a> {
+    Pooled(PgPooledConnection),
+    Transaction(MutexGuard<'a, PgPooledConnection>),
+}
+
+impl PgSource {
+    pub fn conn(&self) -> Result<PgConn<'_>, RepositoryError> {
+        match self {
+            Self::Pool(postgres) => Ok(PgConn::Pooled(postgres.conn()?)),
+            Self::Transaction(conn) => Ok(PgConn::Transaction(
+                conn.lock().unwrap_or_else(|e| e.into_inner()),
+            )),
+        }
+    }
+}
+
+impl Deref for PgConn<'_> {
+    type Target = PgConnection;
+
+    fn deref(&self) -> &PgConnection {
+        match self {
+            Self::Pooled(conn) => conn,
+            Self::Transaction(conn) => conn,
+        }
+    }
+}
+
+impl DerefMut for PgConn<'_> {
+    fn deref_mut(&mut self) -> &mut PgConnection {
+        match self {
+            Self::Pooled(conn) => conn,
+            Self::Transaction(conn) => conn,
+        }
+    }
+}
+
+/// A pooled connection with an open `BEGIN`. Dropping it without a commit
+/// rolls back, so a panicking use case never leaves a half-written change.
+pub(crate) struct PgTransaction {
+    conn: Arc<Mutex<PgPooledConnection>>,
+    finished: bool,
+}
+
+impl PgTransaction {
+    pub(crate) fn begin(postgres: &Postgres) -> Result<Self, RepositoryError> {
+        let mut conn = postgres.conn()?;
+        PgTransactionManager::begin_transaction(&mut *conn)?;
+
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+            finished: false,
+        })
+    }
+
+    pub(crate) fn source(&self) -> PgSource {
+        PgSource::Transaction(self.conn.clone())
+    }
+
+    pub(crate) fn commit(mut self) -> Result<(), RepositoryError> {
+        self.finished = true;
+        let mut conn = self.conn.lock().unwrap_or_else(|e| e.into_inner());
+        PgTransactionManager::commit_transaction(&mut **conn)?;
+        Ok(())
+    }
+
+    pub(crate) fn rollback(mut self) -> Result<(), RepositoryError> {
+        self.finished = true;
+        let mut conn = self.conn.lock().unwrap_or_else(|e| e.into_inner());
+        PgTransactionManager::rollback_transaction(&mut **conn)?;
+        Ok(())
+    }
+}
+
+impl Drop for PgTransaction {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+
+        let mut conn = self.conn.lock().unwrap_or_else(|e| e.into_inner());
+        if let Err(e) = PgTransactionManager::rollback_transaction(&mut **conn) {
+            tracing::warn!("Failed to roll back abandoned transaction: {}", e);
+        }
+    }
+}
@@ -0,0 +1,7 @@
+pub mod memory;
+pub mod models;
+pub mod repo;
+
+pub use memory::InMemoryDeviceRepository;
+pub use models::{Device, NewDevice};
+pub use repo::{DeviceRepo, DeviceRepository};
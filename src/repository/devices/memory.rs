@@ -0,0 +1,120 @@
+use std::sync::Arc;
+
+use uuid::Uuid;
+
+use super::models::{Device, NewDevice};
+use super::repo::DeviceRepo;
+use crate::repository::RepositoryError;
+use crate::repository::memory::{MemoryStore, ensure_user, now};
+
+/// [`DeviceRepo`] over a [`MemoryStore`].
+#[derive(Clone)]
+pub struct InMemoryDeviceRepository {
+    store: Arc<MemoryStore>,
+}
+
+impl InMemoryDeviceRepository {
+    pub fn new(store: Arc<MemoryStore>) -> Self {
+        Self { store }
+    }
+}
+
+impl DeviceRepo for InMemoryDeviceRepository {
+    fn create_device(&self, new_device: NewDevice) -> Result<Device, RepositoryError> {
+        let mut tables = self.store.write();
+        ensure_user(&tables, new_device.user_id)?;
+
+        let now = now();
+        let device = Device {
+            id: Uuid::new_v4(),
+            user_id: new_device.user_id,
+            name: new_device.name,
+            created_at: now,
+            last_seen_at: now,
+        };
+        tables.devices.push(device.clone());
+
+        Ok(device)
+    }
+
+    fn find_device(&self, device_id: Uuid) -> Result<Option<Device>, RepositoryError> {
+        let tables = self.store.read();
+        Ok(tables.devices.iter().find(|d| d.id == device_id).cloned())
+    }
+
+    fn list_user_devices(&self, user_id: Uuid) -> Result<Vec<Device>, RepositoryError> {
+        let tables = self.store.read();
+        Ok(tables
+            .devices
+            .iter()
+            .filter(|d| d.user_id == user_id)
+            .cloned()
+            .collect())
+    }
+
+    fn list_chat_devices(&self, chat_id: Uuid) -> Result<Vec<Device>, RepositoryError> {
+        let tables = self.store.read();
+        let mut devices: Vec<Device> = tables
+            .devices
+            .iter()
+            .filter(|d| {
+                tables
+                    .chat_members
+                    .iter()
+                    .any(|m| m.chat_id == chat_id && m.user_id == d.user_id)
+            })
+            .cloned()
+            .collect();
+        devices.sort_by_key(|d| d.user_id);
+        Ok(devices)
+    }
+
+    fn count_user_devices(&self, user_id: Uuid) -> Result<i64, RepositoryError> {
+        let tables = self.store.read();
+        Ok(tables
+            .devices
+            .iter()
+            .filter(|d| d.user_id == user_id)
+            .count() as i64)
+    }
+
+    fn touch_device(&self, device_id: Uuid) -> Result<(), RepositoryError> {
+        let mut tables = self.store.write();
+
+        if let Some(device) = tables.devices.iter_mut().find(|d| d.id == device_id) {
+            device.last_seen_at = now();
+        }
+
+        Ok(())
+    }
+
+    fn delete_device(&self, device_id: Uuid) -> Result<(), RepositoryError> {
+        let mut tables = self.store.write();
+
+        if !tables.devices.iter().any(|d| d.id == device_id) {
+            return Err(RepositoryError::NotFound);
+        }
+
+        tables.devices.retain(|d| d.id != device_id);
+        tables.identity_keys.retain(|k| k.device_id != device_id);
+        tables.one_time_prekeys.retain(|k| k.device_id != device_id);
+        tables
+            .message_envelopes
+            .retain(|e| e.device_id != device_id);
+        tables
+            .sender_keys
+            .retain(|k| k.sender_device_id != device_id && k.recipient_device_id != device_id);
+        tables
+            .scheduled_messages
+            .retain(|m| m.sender_device_id != device_id);
+        tables.push_endpoints.retain(|e| e.device_id != device_id);
+        tables.push_jobs.retain(|j| j.device_id != device_id);
+        for message in &mut tables.messages {
+            if message.sender_device_id == Some(device_id) {
+                message.sender_device_id = None;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,116 @@
+use diesel::prelude::*;
+use uuid::Uuid;
+
+use super::models::{Device, NewDevice};
+use crate::repository::RepositoryError;
+use crate::repository::connection::PgSource;
+use crate::schema::{chat_members, devices};
+
+pub trait DeviceRepo: Send + Sync {
+    fn create_device(&self, new_device: NewDevice) -> Result<Device, RepositoryError>;
+
+    fn find_device(&self, device_id: Uuid) -> Result<Option<Device>, RepositoryError>;
+
+    fn list_user_devices(&self, user_id: Uuid) -> Result<Vec<Device>, RepositoryError>;
+
+    /// Every device of every member of the chat.
+    fn list_chat_devices(&self, chat_id: Uuid) -> Result<Vec<Device>, RepositoryError>;
+
+    fn count_user_devices(&self, user_id: Uuid) -> Result<i64, RepositoryError>;
+
+    /// Records that the device is online now.
+    fn touch_device(&self, device_id: Uuid) -> Result<(), RepositoryError>;
+
+    fn delete_device(&self, device_id: Uuid) -> Result<(), RepositoryError>;
+}
+
+#[derive(Clone)]
+pub struct DeviceRepository {
+    db: PgSource,
+}
+
+impl DeviceRepository {
+    pub fn new(db: PgSource) -> Self {
+        Self { db }
+    }
+}
+
+impl DeviceRepo for DeviceRepository {
+    #[tracing::instrument(skip(self, new_device), fields(user_id = %new_device.user_id))]
+    fn create_device(&self, new_device: NewDevice) -> Result<Device, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        diesel::insert_into(devices::table)
+            .values(&new_device)
+            .returning(Device::as_returning())
+            .get_result(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    fn find_device(&self, device_id: Uuid) -> Result<Option<Device>, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        devices::table
+            .find(device_id)
+            .select(Device::as_select())
+            .first(&mut *conn)
+            .optional()
+            .map_err(RepositoryError::from)
+    }
+
+    fn list_user_devices(&self, user_id: Uuid) -> Result<Vec<Device>, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        devices::table
+            .filter(devices::user_id.eq(user_id))
+            .order((devices::created_at.asc(), devices::id.asc()))
+            .select(Device::as_select())
+            .load(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    fn list_chat_devices(&self, chat_id: Uuid) -> Result<Vec<Device>, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        devices::table
+            .inner_join(chat_members::table.on(chat_members::user_id.eq(devices::user_id)))
+            .filter(chat_members::chat_id.eq(chat_id))
+            .order((devices::user_id.asc(), devices::created_at.asc()))
+            .select(Device::as_select())
+            .load(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    fn count_user_devices(&self, user_id: Uuid) -> Result<i64, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        devices::table
+            .filter(devices::user_id.eq(user_id))
+            .count()
+            .get_result(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    fn touch_device(&self, device_id: Uuid) -> Result<(), RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        diesel::update(devices::table.find(device_id))
+            .set(devices::last_seen_at.eq(diesel::dsl::now))
+            .execute(&mut *conn)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(self))]
+    fn delete_device(&self, device_id: Uuid) -> Result<(), RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        let deleted = diesel::delete(devices::table.find(device_id)).execute(&mut *conn)?;
+
+        if deleted == 0 {
+            return Err(RepositoryError::NotFound);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,49 @@
+use diesel::r2d2::PoolError;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum RepositoryError {
+    #[error("Record not found")]
+    NotFound,
+
+    /// A unique constraint rejected the write.
+    #[error("Conflicting record: {0}")]
+    Conflict(String),
+
+    /// The write refers to a row that does not exist, e.g. one deleted
+    /// concurrently.
+    #[error("Missing referenced record: {0}")]
+    MissingReference(String),
+
+    /// The database could not be reached; the request may succeed on retry.
+    #[error("Database unavailable: {0}")]
+    Unavailable(String),
+
+    #[error("Database error: {0}")]
+    Internal(String),
+}
+
+impl From<DieselError> for RepositoryError {
+    fn from(err: DieselError) -> Self {
+        match err {
+            DieselError::NotFound => Self::NotFound,
+            DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, info) => {
+                Self::Conflict(info.message().to_string())
+            }
+            DieselError::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, info) => {
+                Self::MissingReference(info.message().to_string())
+            }
+            DieselError::DatabaseError(DatabaseErrorKind::ClosedConnection, info) => {
+                Self::Unavailable(info.message().to_string())
+            }
+            err => Self::Internal(err.to_string()),
+        }
+    }
+}
+
+impl From<PoolError> for RepositoryError {
+    fn from(err: PoolError) -> Self {
+        Self::Unavailable(err.to_string())
+    }
+}
@@ -1,22 +1,43 @@
+use super::attachments::repo::AttachmentRepository;
 use super::auth::repo::AuthRepository;
 use super::chat::repo::ChatRepository;
-use crate::bootstrap::postgres::Postgres;
+use super::connection::PgSource;
+use super::devices::repo::DeviceRepository;
+use super::key_log::repo::KeyLogRepository;
+use super::keys::repo::KeyRepository;
+use super::push::repo::PushRepository;
+use super::root::Repository;
+use super::scheduled_messages::repo::ScheduledMessageRepository;
+use super::sender_keys::repo::SenderKeyRepository;
+use super::transaction::UnitOfWork;
+use super::webhooks::repo::WebhookRepository;
 use std::sync::Arc;
 
+/// Builds the Postgres repositories over one connection source.
 pub(super) struct Factory {
-    postgres: Arc<Postgres>,
+    source: PgSource,
 }
 
 impl Factory {
-    pub(super) fn new(postgres: Arc<Postgres>) -> Self {
-        Self { postgres }
+    pub(super) fn new(source: PgSource) -> Self {
+        Self { source }
     }
 
-    pub(super) fn new_auth_repository(&self) -> AuthRepository {
-        AuthRepository::new(self.postgres.clone())
-    }
-
-    pub(super) fn new_chat_repository(&self) -> ChatRepository {
-        ChatRepository::new(self.postgres.clone())
+    /// `work` is `None` for repositories that already run inside a
+    /// transaction, so their own transactions join it.
+    pub(super) fn repository(&self, work: Option<Arc<dyn UnitOfWork>>) -> Repository {
+        Repository {
+            auth: Arc::new(AuthRepository::new(self.source.clone())),
+            chat: Arc::new(ChatRepository::new(self.source.clone())),
+            keys: Arc::new(KeyRepository::new(self.source.clone())),
+            devices: Arc::new(DeviceRepository::new(self.source.clone())),
+            sender_keys: Arc::new(SenderKeyRepository::new(self.source.clone())),
+            key_log: Arc::new(KeyLogRepository::new(self.source.clone())),
+            attachments: Arc::new(AttachmentRepository::new(self.source.clone())),
+            scheduled_messages: Arc::new(ScheduledMessageRepository::new(self.source.clone())),
+            push: Arc::new(PushRepository::new(self.source.clone())),
+            webhooks: Arc::new(WebhookRepository::new(self.source.clone())),
+            work,
+        }
     }
 }
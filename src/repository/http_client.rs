@@ -0,0 +1,191 @@
+//! The HTTP client behind the adapters that call other services: the S3
+//! blob store, push providers and webhook senders.
+//!
+//! Those adapters implement blocking traits, so each call drives its
+//! request to completion with [`block_on`]. Like repository calls, they
+//! must run on tokio's blocking pool.
+
+use std::future::Future;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use http_body_util::Full;
+use hyper::Uri;
+use hyper::body::Bytes;
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
+use hyper_util::client::legacy::Client;
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::client::legacy::connect::dns::{GaiResolver, Name};
+use hyper_util::rt::TokioExecutor;
+use tokio::runtime::Handle;
+use tower::Service;
+
+pub type HttpClient = Client<HttpsConnector<HttpConnector<Resolver>>, Full<Bytes>>;
+
+/// Which addresses a client may connect to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Destinations {
+    /// Anything, for services the operator configures.
+    Any,
+    /// Public addresses only, for URLs that users hand in. Keeps them from
+    /// reaching loopback, the private network or cloud metadata services.
+    PublicOnly,
+}
+
+/// A client for `http://` and `https://` URLs. Servers are verified
+/// against the Mozilla root certificates bundled with the binary, so it
+/// does not depend on the host's CA store.
+pub fn client(destinations: Destinations) -> HttpClient {
+    let mut http = HttpConnector::new_with_resolver(Resolver {
+        inner: GaiResolver::new(),
+        destinations,
+    });
+    http.enforce_http(false);
+
+    let connector = HttpsConnectorBuilder::new()
+        .with_webpki_roots()
+        .https_or_http()
+        .enable_http1()
+        .wrap_connector(http);
+
+    Client::builder(TokioExecutor::new()).build(connector)
+}
+
+/// Parses an `http://` or `https://` URL a client may be pointed at.
+///
+/// Host names are checked again when they are resolved, since the name may
+/// point somewhere else by the time a request is sent; IP literals are
+/// never resolved, so they are checked here.
+pub fn parse_url(url: &str, destinations: Destinations) -> Result<Uri, String> {
+    let uri: Uri = url.parse().map_err(|_| "Invalid URL".to_string())?;
+
+    if !matches!(uri.scheme_str(), Some("http" | "https")) {
+        return Err("URL must be an http:// or https:// URL".to_string());
+    }
+
+    let host = match uri.host() {
+        Some(host) if !host.is_empty() => host,
+        _ => return Err("URL has no host".to_string()),
+    };
+
+    if destinations == Destinations::PublicOnly {
+        let literal = host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>();
+
+        let private = match literal {
+            Ok(ip) => !is_public(ip),
+            Err(_) => {
+                let host = host.trim_end_matches('.').to_ascii_lowercase();
+                host == "localhost" || host.ends_with(".localhost")
+            }
+        };
+
+        if private {
+            return Err("URL must point at a public address".to_string());
+        }
+    }
+
+    Ok(uri)
+}
+
+/// Runs `future` on the ambient tokio runtime.
+pub fn block_on<F: Future>(future: F) -> Result<F::Output, String> {
+    let runtime =
+        Handle::try_current().map_err(|_| "Outgoing requests need a tokio runtime".to_string())?;
+
+    Ok(runtime.block_on(future))
+}
+
+/// Resolves host names through the system resolver, dropping the addresses
+/// [`Destinations`] rules out. Checking the resolved addresses rather than
+/// the name is what stops a public name that points at a private address.
+#[derive(Clone)]
+pub struct Resolver {
+    inner: GaiResolver,
+    destinations: Destinations,
+}
+
+type Addrs = std::vec::IntoIter<SocketAddr>;
+
+impl Service<Name> for Resolver {
+    type Response = Addrs;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Addrs, io::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, name: Name) -> Self::Future {
+        let destinations = self.destinations;
+        let resolving = self.inner.call(name);
+
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = resolving
+                .await?
+                .filter(|addr| destinations == Destinations::Any || is_public(addr.ip()))
+                .collect();
+
+            if addrs.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    "Host does not resolve to a public address",
+                ));
+            }
+
+            Ok(addrs.into_iter())
+        })
+    }
+}
+
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => is_public_v6(ip),
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        // Includes 169.254.169.254, where cloud metadata services live.
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || ip.is_documentation()
+        // "This network", carrier-grade NAT and the IETF/benchmarking blocks.
+        || a == 0
+        || (a == 100 && (64..128).contains(&b))
+        || (a == 192 && b == 0)
+        || (a == 198 && (18..20).contains(&b))
+        || a >= 240)
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    let segments = ip.segments();
+
+    // IPv4-mapped (::ffff:a.b.c.d) and NAT64 (64:ff9b::a.b.c.d) addresses
+    // reach the embedded IPv4 address.
+    if let Some(v4) = ip.to_ipv4_mapped() {
+        return is_public_v4(v4);
+    }
+    if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+        let [.., a, b, c, d] = ip.octets();
+        return is_public_v4(Ipv4Addr::new(a, b, c, d));
+    }
+
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_multicast()
+        || ip.is_unique_local()
+        || ip.is_unicast_link_local()
+        // Documentation prefix 2001:db8::/32.
+        || (segments[0] == 0x2001 && segments[1] == 0x0db8))
+}
@@ -0,0 +1,7 @@
+pub mod memory;
+pub mod models;
+pub mod repo;
+
+pub use memory::InMemoryKeyLogRepository;
+pub use models::{KeyLogEntry, KeyLogNode, NewKeyLogEntry, SignedTreeHead};
+pub use repo::{KeyLogRepo, KeyLogRepository};
@@ -0,0 +1,132 @@
+use std::sync::Arc;
+
+use uuid::Uuid;
+
+use super::models::{KeyLogEntry, KeyLogNode, NewKeyLogEntry, SignedTreeHead};
+use super::repo::KeyLogRepo;
+use crate::repository::RepositoryError;
+use crate::repository::memory::{MemoryStore, now};
+
+/// [`KeyLogRepo`] over a [`MemoryStore`]. Appends are serialized by the
+/// store's lock.
+#[derive(Clone)]
+pub struct InMemoryKeyLogRepository {
+    store: Arc<MemoryStore>,
+}
+
+impl InMemoryKeyLogRepository {
+    pub fn new(store: Arc<MemoryStore>) -> Self {
+        Self { store }
+    }
+}
+
+impl KeyLogRepo for InMemoryKeyLogRepository {
+    fn append_entry(&self, entry: NewKeyLogEntry) -> Result<KeyLogEntry, RepositoryError> {
+        let mut tables = self.store.write();
+
+        let entry = KeyLogEntry {
+            leaf_index: tables.key_log_entries.len() as i64,
+            user_id: entry.user_id,
+            device_id: entry.device_id,
+            identity_key: entry.identity_key,
+            timestamp_ms: entry.timestamp_ms,
+            leaf_hash: entry.leaf_hash,
+            created_at: now(),
+        };
+        tables.key_log_entries.push(entry.clone());
+
+        Ok(entry)
+    }
+
+    fn get_entries(&self, start: i64, limit: i64) -> Result<Vec<KeyLogEntry>, RepositoryError> {
+        let tables = self.store.read();
+
+        Ok(tables
+            .key_log_entries
+            .iter()
+            .filter(|e| e.leaf_index >= start)
+            .take(limit.max(0) as usize)
+            .cloned()
+            .collect())
+    }
+
+    fn find_latest_entry(&self, device_id: Uuid) -> Result<Option<KeyLogEntry>, RepositoryError> {
+        let tables = self.store.read();
+
+        Ok(tables
+            .key_log_entries
+            .iter()
+            .rev()
+            .find(|e| e.device_id == device_id)
+            .cloned())
+    }
+
+    fn add_nodes(&self, nodes: Vec<KeyLogNode>) -> Result<(), RepositoryError> {
+        let mut tables = self.store.write();
+
+        for node in &nodes {
+            if tables
+                .key_log_nodes
+                .iter()
+                .any(|n| n.level == node.level && n.node_index == node.node_index)
+            {
+                return Err(RepositoryError::Conflict(format!(
+                    "node {} at level {} already exists",
+                    node.node_index, node.level
+                )));
+            }
+        }
+        tables.key_log_nodes.extend(nodes);
+
+        Ok(())
+    }
+
+    fn get_nodes(&self, ids: &[(i16, i64)]) -> Result<Vec<KeyLogNode>, RepositoryError> {
+        let tables = self.store.read();
+
+        Ok(tables
+            .key_log_nodes
+            .iter()
+            .filter(|n| ids.contains(&(n.level, n.node_index)))
+            .cloned()
+            .collect())
+    }
+
+    fn add_head(&self, head: SignedTreeHead) -> Result<SignedTreeHead, RepositoryError> {
+        let mut tables = self.store.write();
+
+        if tables
+            .key_log_heads
+            .iter()
+            .any(|h| h.tree_size == head.tree_size)
+        {
+            return Err(RepositoryError::Conflict(format!(
+                "tree head for size {} already exists",
+                head.tree_size
+            )));
+        }
+        tables.key_log_heads.push(head.clone());
+
+        Ok(head)
+    }
+
+    fn find_head(&self, tree_size: i64) -> Result<Option<SignedTreeHead>, RepositoryError> {
+        let tables = self.store.read();
+
+        Ok(tables
+            .key_log_heads
+            .iter()
+            .find(|h| h.tree_size == tree_size)
+            .cloned())
+    }
+
+    fn latest_head(&self) -> Result<Option<SignedTreeHead>, RepositoryError> {
+        let tables = self.store.read();
+
+        Ok(tables
+            .key_log_heads
+            .iter()
+            .max_by_key(|h| h.tree_size)
+            .cloned())
+    }
+}
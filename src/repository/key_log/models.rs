@@ -0,0 +1,48 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use uuid::Uuid;
+
+use crate::schema::{key_log_entries, key_log_heads, key_log_nodes};
+
+/// A leaf of the key transparency log: the identity key a device had from
+/// `timestamp_ms` on. `leaf_hash` is the RFC 6962 hash of the leaf input.
+#[derive(Debug, Clone, Queryable, Selectable, Identifiable)]
+#[diesel(table_name = key_log_entries, primary_key(leaf_index))]
+pub struct KeyLogEntry {
+    pub leaf_index: i64,
+    pub user_id: Uuid,
+    pub device_id: Uuid,
+    pub identity_key: String,
+    pub timestamp_ms: i64,
+    pub leaf_hash: Vec<u8>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewKeyLogEntry {
+    pub user_id: Uuid,
+    pub device_id: Uuid,
+    pub identity_key: String,
+    pub timestamp_ms: i64,
+    pub leaf_hash: Vec<u8>,
+}
+
+/// The root of the log at `tree_size` leaves, signed by the server.
+#[derive(Debug, Clone, Queryable, Selectable, Insertable)]
+#[diesel(table_name = key_log_heads)]
+pub struct SignedTreeHead {
+    pub tree_size: i64,
+    pub timestamp_ms: i64,
+    pub root_hash: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+/// The root of the perfect subtree of `2^level` leaves starting at leaf
+/// `node_index << level`; at level 0, a leaf hash.
+#[derive(Debug, Clone, Queryable, Selectable, Insertable)]
+#[diesel(table_name = key_log_nodes)]
+pub struct KeyLogNode {
+    pub level: i16,
+    pub node_index: i64,
+    pub hash: Vec<u8>,
+}
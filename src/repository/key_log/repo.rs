@@ -0,0 +1,165 @@
+use diesel::prelude::*;
+use uuid::Uuid;
+
+use super::models::{KeyLogEntry, KeyLogNode, NewKeyLogEntry, SignedTreeHead};
+use crate::repository::RepositoryError;
+use crate::repository::connection::PgSource;
+use crate::schema::{key_log_entries, key_log_heads, key_log_nodes};
+
+pub trait KeyLogRepo: Send + Sync {
+    /// Appends a leaf at the next index. Appends are serialized until the
+    /// surrounding transaction ends, so the caller can sign the new head
+    /// before anyone else extends the tree.
+    fn append_entry(&self, entry: NewKeyLogEntry) -> Result<KeyLogEntry, RepositoryError>;
+
+    /// Leaves from `start` on, in log order.
+    fn get_entries(&self, start: i64, limit: i64) -> Result<Vec<KeyLogEntry>, RepositoryError>;
+
+    /// The most recent leaf logged for the device, if any.
+    fn find_latest_entry(&self, device_id: Uuid) -> Result<Option<KeyLogEntry>, RepositoryError>;
+
+    /// Stores subtree roots completed by an append.
+    fn add_nodes(&self, nodes: Vec<KeyLogNode>) -> Result<(), RepositoryError>;
+
+    /// The stored roots among `(level, node_index)` pairs, in no particular
+    /// order.
+    fn get_nodes(&self, ids: &[(i16, i64)]) -> Result<Vec<KeyLogNode>, RepositoryError>;
+
+    fn add_head(&self, head: SignedTreeHead) -> Result<SignedTreeHead, RepositoryError>;
+
+    fn find_head(&self, tree_size: i64) -> Result<Option<SignedTreeHead>, RepositoryError>;
+
+    fn latest_head(&self) -> Result<Option<SignedTreeHead>, RepositoryError>;
+}
+
+#[derive(Clone)]
+pub struct KeyLogRepository {
+    db: PgSource,
+}
+
+impl KeyLogRepository {
+    pub fn new(db: PgSource) -> Self {
+        Self { db }
+    }
+}
+
+impl KeyLogRepo for KeyLogRepository {
+    #[tracing::instrument(skip(self, entry), fields(device_id = %entry.device_id))]
+    fn append_entry(&self, entry: NewKeyLogEntry) -> Result<KeyLogEntry, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        conn.transaction(|conn| {
+            // Readers are not blocked; concurrent appenders queue here until
+            // the outer transaction commits.
+            diesel::sql_query("LOCK TABLE key_log_entries IN SHARE ROW EXCLUSIVE MODE")
+                .execute(conn)?;
+
+            let leaf_index: i64 = key_log_entries::table
+                .select(diesel::dsl::max(key_log_entries::leaf_index))
+                .first::<Option<i64>>(conn)?
+                .map_or(0, |last| last + 1);
+
+            diesel::insert_into(key_log_entries::table)
+                .values((
+                    key_log_entries::leaf_index.eq(leaf_index),
+                    key_log_entries::user_id.eq(entry.user_id),
+                    key_log_entries::device_id.eq(entry.device_id),
+                    key_log_entries::identity_key.eq(&entry.identity_key),
+                    key_log_entries::timestamp_ms.eq(entry.timestamp_ms),
+                    key_log_entries::leaf_hash.eq(&entry.leaf_hash),
+                ))
+                .returning(KeyLogEntry::as_returning())
+                .get_result(conn)
+        })
+        .map_err(RepositoryError::from)
+    }
+
+    fn get_entries(&self, start: i64, limit: i64) -> Result<Vec<KeyLogEntry>, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        key_log_entries::table
+            .filter(key_log_entries::leaf_index.ge(start))
+            .order(key_log_entries::leaf_index.asc())
+            .limit(limit)
+            .select(KeyLogEntry::as_select())
+            .load(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    fn find_latest_entry(&self, device_id: Uuid) -> Result<Option<KeyLogEntry>, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        key_log_entries::table
+            .filter(key_log_entries::device_id.eq(device_id))
+            .order(key_log_entries::leaf_index.desc())
+            .select(KeyLogEntry::as_select())
+            .first(&mut *conn)
+            .optional()
+            .map_err(RepositoryError::from)
+    }
+
+    #[tracing::instrument(skip(self, nodes), fields(count = nodes.len()))]
+    fn add_nodes(&self, nodes: Vec<KeyLogNode>) -> Result<(), RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        diesel::insert_into(key_log_nodes::table)
+            .values(&nodes)
+            .execute(&mut *conn)
+            .map(|_| ())
+            .map_err(RepositoryError::from)
+    }
+
+    fn get_nodes(&self, ids: &[(i16, i64)]) -> Result<Vec<KeyLogNode>, RepositoryError> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut conn = self.db.conn()?;
+
+        let mut query = key_log_nodes::table
+            .select(KeyLogNode::as_select())
+            .into_boxed();
+        for (level, node_index) in ids {
+            query = query.or_filter(
+                key_log_nodes::level
+                    .eq(*level)
+                    .and(key_log_nodes::node_index.eq(*node_index)),
+            );
+        }
+
+        query.load(&mut *conn).map_err(RepositoryError::from)
+    }
+
+    #[tracing::instrument(skip(self, head), fields(tree_size = head.tree_size))]
+    fn add_head(&self, head: SignedTreeHead) -> Result<SignedTreeHead, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        diesel::insert_into(key_log_heads::table)
+            .values(&head)
+            .returning(SignedTreeHead::as_returning())
+            .get_result(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    fn find_head(&self, tree_size: i64) -> Result<Option<SignedTreeHead>, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        key_log_heads::table
+            .find(tree_size)
+            .select(SignedTreeHead::as_select())
+            .first(&mut *conn)
+            .optional()
+            .map_err(RepositoryError::from)
+    }
+
+    fn latest_head(&self) -> Result<Option<SignedTreeHead>, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        key_log_heads::table
+            .order(key_log_heads::tree_size.desc())
+            .select(SignedTreeHead::as_select())
+            .first(&mut *conn)
+            .optional()
+            .map_err(RepositoryError::from)
+    }
+}
@@ -0,0 +1,7 @@
+pub mod memory;
+pub mod models;
+pub mod repo;
+
+pub use memory::InMemoryKeyRepository;
+pub use models::{IdentityKey, NewIdentityKey, NewOneTimePrekey, OneTimePrekey};
+pub use repo::{KeyRepo, KeyRepository};
@@ -0,0 +1,151 @@
+use std::sync::Arc;
+
+use uuid::Uuid;
+
+use super::models::{IdentityKey, NewIdentityKey, NewOneTimePrekey, OneTimePrekey};
+use super::repo::KeyRepo;
+use crate::repository::RepositoryError;
+use crate::repository::memory::{MemoryStore, ensure_device, ensure_user, now};
+
+/// [`KeyRepo`] over a [`MemoryStore`].
+#[derive(Clone)]
+pub struct InMemoryKeyRepository {
+    store: Arc<MemoryStore>,
+}
+
+impl InMemoryKeyRepository {
+    pub fn new(store: Arc<MemoryStore>) -> Self {
+        Self { store }
+    }
+}
+
+impl KeyRepo for InMemoryKeyRepository {
+    fn upsert_identity(&self, key: NewIdentityKey) -> Result<IdentityKey, RepositoryError> {
+        let mut tables = self.store.write();
+        ensure_user(&tables, key.user_id)?;
+        ensure_device(&tables, key.device_id)?;
+
+        let now = now();
+        let created_at = tables
+            .identity_keys
+            .iter()
+            .find(|k| k.device_id == key.device_id)
+            .map_or(now, |k| k.created_at);
+        let identity = IdentityKey {
+            user_id: key.user_id,
+            identity_key: key.identity_key,
+            signed_prekey_id: key.signed_prekey_id,
+            signed_prekey: key.signed_prekey,
+            signed_prekey_signature: key.signed_prekey_signature,
+            created_at,
+            updated_at: now,
+            device_id: key.device_id,
+        };
+
+        tables
+            .identity_keys
+            .retain(|k| k.device_id != identity.device_id);
+        tables.identity_keys.push(identity.clone());
+
+        Ok(identity)
+    }
+
+    fn find_identity(&self, device_id: Uuid) -> Result<Option<IdentityKey>, RepositoryError> {
+        let tables = self.store.read();
+        Ok(tables
+            .identity_keys
+            .iter()
+            .find(|k| k.device_id == device_id)
+            .cloned())
+    }
+
+    fn find_user_identities(&self, user_id: Uuid) -> Result<Vec<IdentityKey>, RepositoryError> {
+        let tables = self.store.read();
+        let mut identities: Vec<IdentityKey> = tables
+            .identity_keys
+            .iter()
+            .filter(|k| k.user_id == user_id)
+            .cloned()
+            .collect();
+        identities.sort_by_key(|k| k.created_at);
+        Ok(identities)
+    }
+
+    fn add_prekeys(&self, prekeys: &[NewOneTimePrekey]) -> Result<usize, RepositoryError> {
+        let mut tables = self.store.write();
+
+        // Validate the whole batch first, like a single multi-row INSERT.
+        for (index, prekey) in prekeys.iter().enumerate() {
+            ensure_device(&tables, prekey.device_id)?;
+
+            let same_key = |device_id: Uuid, key_id: i32| {
+                device_id == prekey.device_id && key_id == prekey.key_id
+            };
+            let taken = tables
+                .one_time_prekeys
+                .iter()
+                .any(|k| same_key(k.device_id, k.key_id))
+                || prekeys[..index]
+                    .iter()
+                    .any(|k| same_key(k.device_id, k.key_id));
+            if taken {
+                return Err(RepositoryError::Conflict(
+                    "duplicate key value violates unique constraint \
+                     \"one_time_prekeys_device_id_key_id_key\""
+                        .to_string(),
+                ));
+            }
+        }
+
+        let now = now();
+        let last_id = tables
+            .one_time_prekeys
+            .iter()
+            .map(|k| k.id)
+            .max()
+            .unwrap_or(0);
+        for (id, prekey) in (last_id + 1..).zip(prekeys) {
+            tables.one_time_prekeys.push(OneTimePrekey {
+                id,
+                key_id: prekey.key_id,
+                public_key: prekey.public_key.clone(),
+                created_at: now,
+                device_id: prekey.device_id,
+            });
+        }
+
+        Ok(prekeys.len())
+    }
+
+    fn count_prekeys(&self, device_id: Uuid) -> Result<i64, RepositoryError> {
+        let tables = self.store.read();
+        Ok(tables
+            .one_time_prekeys
+            .iter()
+            .filter(|k| k.device_id == device_id)
+            .count() as i64)
+    }
+
+    fn take_prekey(&self, device_id: Uuid) -> Result<Option<OneTimePrekey>, RepositoryError> {
+        let mut tables = self.store.write();
+
+        let position = tables
+            .one_time_prekeys
+            .iter()
+            .enumerate()
+            .filter(|(_, k)| k.device_id == device_id)
+            .min_by_key(|(_, k)| k.key_id)
+            .map(|(position, _)| position);
+
+        Ok(position.map(|position| tables.one_time_prekeys.remove(position)))
+    }
+
+    fn delete_prekeys(&self, device_id: Uuid) -> Result<usize, RepositoryError> {
+        let mut tables = self.store.write();
+
+        let before = tables.one_time_prekeys.len();
+        tables.one_time_prekeys.retain(|k| k.device_id != device_id);
+
+        Ok(before - tables.one_time_prekeys.len())
+    }
+}
@@ -0,0 +1,49 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use uuid::Uuid;
+
+use crate::schema::{identity_keys, one_time_prekeys};
+
+/// A device's long-term identity key and current signed prekey. Keys are
+/// opaque base64 strings; clients verify the signature, not the server.
+#[derive(Debug, Clone, Queryable, Selectable, Identifiable)]
+#[diesel(table_name = identity_keys, primary_key(device_id))]
+pub struct IdentityKey {
+    pub user_id: Uuid,
+    pub identity_key: String,
+    pub signed_prekey_id: i32,
+    pub signed_prekey: String,
+    pub signed_prekey_signature: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    pub device_id: Uuid,
+}
+
+#[derive(Debug, Clone, Insertable, AsChangeset)]
+#[diesel(table_name = identity_keys)]
+pub struct NewIdentityKey {
+    pub device_id: Uuid,
+    pub user_id: Uuid,
+    pub identity_key: String,
+    pub signed_prekey_id: i32,
+    pub signed_prekey: String,
+    pub signed_prekey_signature: String,
+}
+
+#[derive(Debug, Clone, Queryable, QueryableByName, Selectable, Identifiable)]
+#[diesel(table_name = one_time_prekeys)]
+pub struct OneTimePrekey {
+    pub id: i64,
+    pub key_id: i32,
+    pub public_key: String,
+    pub created_at: NaiveDateTime,
+    pub device_id: Uuid,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = one_time_prekeys)]
+pub struct NewOneTimePrekey {
+    pub device_id: Uuid,
+    pub key_id: i32,
+    pub public_key: String,
+}
@@ -0,0 +1,126 @@
+use diesel::prelude::*;
+use diesel::sql_types;
+use uuid::Uuid;
+
+use super::models::{IdentityKey, NewIdentityKey, NewOneTimePrekey, OneTimePrekey};
+use crate::repository::RepositoryError;
+use crate::repository::connection::PgSource;
+use crate::schema::{identity_keys, one_time_prekeys};
+
+pub trait KeyRepo: Send + Sync {
+    /// Inserts or replaces the identity and signed prekey of a device.
+    fn upsert_identity(&self, key: NewIdentityKey) -> Result<IdentityKey, RepositoryError>;
+
+    fn find_identity(&self, device_id: Uuid) -> Result<Option<IdentityKey>, RepositoryError>;
+
+    /// Identity keys of every device of the user that has published one.
+    fn find_user_identities(&self, user_id: Uuid) -> Result<Vec<IdentityKey>, RepositoryError>;
+
+    fn add_prekeys(&self, prekeys: &[NewOneTimePrekey]) -> Result<usize, RepositoryError>;
+
+    fn count_prekeys(&self, device_id: Uuid) -> Result<i64, RepositoryError>;
+
+    /// Removes and returns the device's lowest-numbered one-time prekey.
+    /// Concurrent callers never receive the same key.
+    fn take_prekey(&self, device_id: Uuid) -> Result<Option<OneTimePrekey>, RepositoryError>;
+
+    fn delete_prekeys(&self, device_id: Uuid) -> Result<usize, RepositoryError>;
+}
+
+#[derive(Clone)]
+pub struct KeyRepository {
+    db: PgSource,
+}
+
+impl KeyRepository {
+    pub fn new(db: PgSource) -> Self {
+        Self { db }
+    }
+}
+
+impl KeyRepo for KeyRepository {
+    #[tracing::instrument(skip(self, key), fields(device_id = %key.device_id))]
+    fn upsert_identity(&self, key: NewIdentityKey) -> Result<IdentityKey, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        diesel::insert_into(identity_keys::table)
+            .values(&key)
+            .on_conflict(identity_keys::device_id)
+            .do_update()
+            .set((&key, identity_keys::updated_at.eq(diesel::dsl::now)))
+            .get_result(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    fn find_identity(&self, device_id: Uuid) -> Result<Option<IdentityKey>, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        identity_keys::table
+            .find(device_id)
+            .first(&mut *conn)
+            .optional()
+            .map_err(RepositoryError::from)
+    }
+
+    fn find_user_identities(&self, user_id: Uuid) -> Result<Vec<IdentityKey>, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        identity_keys::table
+            .filter(identity_keys::user_id.eq(user_id))
+            .order(identity_keys::created_at.asc())
+            .load(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    #[tracing::instrument(skip(self, prekeys), fields(count = prekeys.len()))]
+    fn add_prekeys(&self, prekeys: &[NewOneTimePrekey]) -> Result<usize, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        diesel::insert_into(one_time_prekeys::table)
+            .values(prekeys)
+            .execute(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    fn count_prekeys(&self, device_id: Uuid) -> Result<i64, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        one_time_prekeys::table
+            .filter(one_time_prekeys::device_id.eq(device_id))
+            .count()
+            .get_result(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    #[tracing::instrument(skip(self))]
+    fn take_prekey(&self, device_id: Uuid) -> Result<Option<OneTimePrekey>, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        // SKIP LOCKED lets concurrent fetches for the same device each claim a
+        // different row instead of queueing behind the first one.
+        diesel::sql_query(
+            "DELETE FROM one_time_prekeys \
+             WHERE id = ( \
+                 SELECT id FROM one_time_prekeys \
+                 WHERE device_id = $1 \
+                 ORDER BY key_id \
+                 LIMIT 1 \
+                 FOR UPDATE SKIP LOCKED \
+             ) \
+             RETURNING id, key_id, public_key, created_at, device_id",
+        )
+        .bind::<sql_types::Uuid, _>(device_id)
+        .get_result::<OneTimePrekey>(&mut *conn)
+        .optional()
+        .map_err(RepositoryError::from)
+    }
+
+    #[tracing::instrument(skip(self))]
+    fn delete_prekeys(&self, device_id: Uuid) -> Result<usize, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        diesel::delete(one_time_prekeys::table.filter(one_time_prekeys::device_id.eq(device_id)))
+            .execute(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+}
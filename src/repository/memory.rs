@@ -0,0 +1,246 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use chrono::{NaiveDateTime, Utc};
+use uuid::Uuid;
+
+use super::attachments::{Attachment, AttachmentChunk, InMemoryAttachmentRepository};
+use super::auth::{AuthUser, InMemoryAuthRepository, Permission, Role};
+use super::chat::{
+    Chat, ChatMember, ChatRekey, InMemoryChatRepository, Message, MessageEnvelope, PinnedMessage,
+    Poll, PollVote,
+};
+use super::devices::{Device, InMemoryDeviceRepository};
+use super::error::RepositoryError;
+use super::key_log::{InMemoryKeyLogRepository, KeyLogEntry, KeyLogNode, SignedTreeHead};
+use super::keys::{IdentityKey, InMemoryKeyRepository, OneTimePrekey};
+use super::push::{InMemoryPushRepository, PushDeadLetter, PushEndpoint, PushJob};
+use super::root::Repository;
+use super::scheduled_messages::{InMemoryScheduledMessageRepository, ScheduledMessage};
+use super::sender_keys::{InMemorySenderKeyRepository, SenderKey};
+use super::transaction::{Transaction, UnitOfWork};
+use super::webhooks::{InMemoryWebhookRepository, Webhook, WebhookDelivery, WebhookEvent};
+
+/// Rows of every table, kept in insertion order like a heap table without
+/// an `ORDER BY`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Tables {
+    pub auth_users: Vec<AuthUser>,
+    pub roles: Vec<Role>,
+    pub permissions: Vec<Permission>,
+    pub role_permissions: Vec<(i32, i32)>,
+    pub chats: Vec<Chat>,
+    pub chat_members: Vec<ChatMember>,
+    pub chat_rekeys: Vec<ChatRekey>,
+    pub messages: Vec<Message>,
+    pub message_envelopes: Vec<MessageEnvelope>,
+    pub devices: Vec<Device>,
+    pub identity_keys: Vec<IdentityKey>,
+    pub one_time_prekeys: Vec<OneTimePrekey>,
+    pub sender_keys: Vec<SenderKey>,
+    pub key_log_entries: Vec<KeyLogEntry>,
+    pub key_log_heads: Vec<SignedTreeHead>,
+    pub key_log_nodes: Vec<KeyLogNode>,
+    pub attachments: Vec<Attachment>,
+    pub attachment_chunks: Vec<AttachmentChunk>,
+    pub message_attachments: Vec<(Uuid, Uuid)>,
+    pub pinned_messages: Vec<PinnedMessage>,
+    pub polls: Vec<Poll>,
+    pub poll_votes: Vec<PollVote>,
+    pub scheduled_messages: Vec<ScheduledMessage>,
+    pub push_endpoints: Vec<PushEndpoint>,
+    pub push_jobs: Vec<PushJob>,
+    pub push_dead_letters: Vec<PushDeadLetter>,
+    pub webhooks: Vec<Webhook>,
+    pub webhook_events: Vec<WebhookEvent>,
+    pub webhook_deliveries: Vec<WebhookDelivery>,
+    /// Bumped by every write, so a transaction can tell whether the store
+    /// changed underneath it.
+    generation: u64,
+}
+
+/// Process-local stand-in for the database, shared by the in-memory
+/// repositories so cross-table lookups see the same data.
+///
+/// The repositories over it enforce the unique, foreign-key and check
+/// constraints of the Postgres schema and cascade deletes the same way, so
+/// tests against them catch the errors the database would raise. It is
+/// seeded with the same roles and permissions as the migrations.
+#[derive(Debug)]
+pub struct MemoryStore {
+    tables: Mutex<Tables>,
+}
+
+const SEEDED_ROLES: [(i32, &str, &str); 2] = [
+    (0, "admin", "Administrator with full access"),
+    (1, "user", "Default user role"),
+];
+
+const SEEDED_PERMISSIONS: [(&str, &str); 8] = [
+    ("users.read", "List and search user accounts"),
+    (
+        "users.deactivate",
+        "Deactivate and reactivate user accounts",
+    ),
+    ("users.logout", "Revoke every session of a user"),
+    ("users.assign_role", "Change the role of a user"),
+    ("roles.read", "List roles and permissions"),
+    ("roles.manage", "Create roles and change their permissions"),
+    (
+        "chats.moderate",
+        "Moderate chats without being a chat admin",
+    ),
+    ("webhooks.manage", "Manage webhooks for every chat"),
+];
+
+impl MemoryStore {
+    pub fn new() -> Arc<Self> {
+        let now = now();
+        let mut tables = Tables::default();
+
+        for (id, name, description) in SEEDED_ROLES {
+            tables.roles.push(Role {
+                id,
+                name: name.to_string(),
+                description: Some(description.to_string()),
+                created_at: now,
+            });
+        }
+
+        for (index, (name, description)) in SEEDED_PERMISSIONS.into_iter().enumerate() {
+            let id = index as i32 + 1;
+            tables.permissions.push(Permission {
+                id,
+                name: name.to_string(),
+                description: Some(description.to_string()),
+                created_at: now,
+            });
+            tables.role_permissions.push((0, id));
+        }
+
+        Arc::new(Self {
+            tables: Mutex::new(tables),
+        })
+    }
+
+    /// Repositories over this store. `work` is `None` inside a transaction,
+    /// so nested transactions join it.
+    pub(crate) fn repository(self: &Arc<Self>, work: Option<Arc<dyn UnitOfWork>>) -> Repository {
+        Repository {
+            auth: Arc::new(InMemoryAuthRepository::new(self.clone())),
+            chat: Arc::new(InMemoryChatRepository::new(self.clone())),
+            keys: Arc::new(InMemoryKeyRepository::new(self.clone())),
+            devices: Arc::new(InMemoryDeviceRepository::new(self.clone())),
+            sender_keys: Arc::new(InMemorySenderKeyRepository::new(self.clone())),
+            key_log: Arc::new(InMemoryKeyLogRepository::new(self.clone())),
+            attachments: Arc::new(InMemoryAttachmentRepository::new(self.clone())),
+            scheduled_messages: Arc::new(InMemoryScheduledMessageRepository::new(self.clone())),
+            push: Arc::new(InMemoryPushRepository::new(self.clone())),
+            webhooks: Arc::new(InMemoryWebhookRepository::new(self.clone())),
+            work,
+        }
+    }
+
+    pub(crate) fn read(&self) -> MutexGuard<'_, Tables> {
+        self.tables.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub(crate) fn write(&self) -> MutexGuard<'_, Tables> {
+        let mut tables = self.read();
+        tables.generation += 1;
+        tables
+    }
+}
+
+impl UnitOfWork for Arc<MemoryStore> {
+    fn begin(&self) -> Result<Box<dyn Transaction>, RepositoryError> {
+        let tables = self.read().clone();
+
+        Ok(Box::new(MemoryTransaction {
+            store: self.clone(),
+            base_generation: tables.generation,
+            scratch: Arc::new(MemoryStore {
+                tables: Mutex::new(tables),
+            }),
+        }))
+    }
+}
+
+/// Works on a private copy of the tables and swaps it in on commit. Rolling
+/// back just drops the copy.
+struct MemoryTransaction {
+    store: Arc<MemoryStore>,
+    base_generation: u64,
+    scratch: Arc<MemoryStore>,
+}
+
+impl Transaction for MemoryTransaction {
+    fn repository(&self) -> Repository {
+        self.scratch.repository(None)
+    }
+
+    fn commit(self: Box<Self>) -> Result<(), RepositoryError> {
+        let mut tables = self.store.write();
+
+        // Mirrors a serialization failure: committing would silently discard
+        // whatever was written since this transaction began.
+        if tables.generation != self.base_generation + 1 {
+            return Err(RepositoryError::Conflict(
+                "could not serialize access due to concurrent update".to_string(),
+            ));
+        }
+
+        let generation = tables.generation;
+        *tables = self.scratch.read().clone();
+        tables.generation = generation;
+
+        Ok(())
+    }
+
+    fn rollback(self: Box<Self>) -> Result<(), RepositoryError> {
+        Ok(())
+    }
+}
+
+pub(crate) fn ensure_user(tables: &Tables, user_id: Uuid) -> Result<(), RepositoryError> {
+    if tables.auth_users.iter().any(|u| u.id == user_id) {
+        Ok(())
+    } else {
+        Err(RepositoryError::MissingReference(format!(
+            "user {} does not exist",
+            user_id
+        )))
+    }
+}
+
+pub(crate) fn ensure_chat(tables: &Tables, chat_id: Uuid) -> Result<(), RepositoryError> {
+    if tables.chats.iter().any(|c| c.id == chat_id) {
+        Ok(())
+    } else {
+        Err(RepositoryError::MissingReference(format!(
+            "chat {} does not exist",
+            chat_id
+        )))
+    }
+}
+
+pub(crate) fn ensure_device(tables: &Tables, device_id: Uuid) -> Result<(), RepositoryError> {
+    if tables.devices.iter().any(|d| d.id == device_id) {
+        Ok(())
+    } else {
+        Err(RepositoryError::MissingReference(format!(
+            "device {} does not exist",
+            device_id
+        )))
+    }
+}
+
+pub(crate) fn now() -> NaiveDateTime {
+    Utc::now().naive_utc()
+}
+
+/// Applies `LIMIT`/`OFFSET` to rows that are already in query order.
+pub(crate) fn paginate<T>(rows: impl Iterator<Item = T>, limit: i64, offset: i64) -> Vec<T> {
+    rows.skip(offset.max(0) as usize)
+        .take(limit.max(0) as usize)
+        .collect()
+}
@@ -0,0 +1,7 @@
+pub mod memory;
+pub mod models;
+pub mod repo;
+
+pub use memory::InMemoryPushRepository;
+pub use models::{NewPushEndpoint, PushDeadLetter, PushEndpoint, PushEndpointKind, PushJob};
+pub use repo::{PushRepo, PushRepository};
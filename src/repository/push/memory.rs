@@ -0,0 +1,211 @@
+use std::sync::Arc;
+
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+
+use super::models::{NewPushEndpoint, PushDeadLetter, PushEndpoint, PushEndpointKind, PushJob};
+use super::repo::PushRepo;
+use crate::repository::RepositoryError;
+use crate::repository::memory::{MemoryStore, ensure_device, now, paginate};
+
+/// [`PushRepo`] over a [`MemoryStore`].
+#[derive(Clone)]
+pub struct InMemoryPushRepository {
+    store: Arc<MemoryStore>,
+}
+
+impl InMemoryPushRepository {
+    pub fn new(store: Arc<MemoryStore>) -> Self {
+        Self { store }
+    }
+}
+
+impl PushRepo for InMemoryPushRepository {
+    fn upsert_endpoint(&self, endpoint: NewPushEndpoint) -> Result<PushEndpoint, RepositoryError> {
+        let mut tables = self.store.write();
+        ensure_device(&tables, endpoint.device_id)?;
+
+        if PushEndpointKind::parse(&endpoint.kind).is_none() {
+            return Err(RepositoryError::Conflict(format!(
+                "invalid push endpoint kind {}",
+                endpoint.kind
+            )));
+        }
+
+        let now = now();
+        if let Some(existing) = tables
+            .push_endpoints
+            .iter_mut()
+            .find(|e| e.device_id == endpoint.device_id)
+        {
+            existing.kind = endpoint.kind;
+            existing.url = endpoint.url;
+            existing.updated_at = now;
+            return Ok(existing.clone());
+        }
+
+        let endpoint = PushEndpoint {
+            device_id: endpoint.device_id,
+            kind: endpoint.kind,
+            url: endpoint.url,
+            created_at: now,
+            updated_at: now,
+        };
+        tables.push_endpoints.push(endpoint.clone());
+
+        Ok(endpoint)
+    }
+
+    fn find_endpoint(&self, device_id: Uuid) -> Result<Option<PushEndpoint>, RepositoryError> {
+        let tables = self.store.read();
+        Ok(tables
+            .push_endpoints
+            .iter()
+            .find(|e| e.device_id == device_id)
+            .cloned())
+    }
+
+    fn delete_endpoint(&self, device_id: Uuid) -> Result<(), RepositoryError> {
+        let mut tables = self.store.write();
+
+        if !tables
+            .push_endpoints
+            .iter()
+            .any(|e| e.device_id == device_id)
+        {
+            return Err(RepositoryError::NotFound);
+        }
+
+        tables.push_endpoints.retain(|e| e.device_id != device_id);
+        tables.push_jobs.retain(|j| j.device_id != device_id);
+
+        Ok(())
+    }
+
+    fn enqueue_wake_ups(&self, user_ids: &[Uuid]) -> Result<usize, RepositoryError> {
+        let mut tables = self.store.write();
+
+        let device_ids: Vec<Uuid> = tables
+            .push_endpoints
+            .iter()
+            .map(|e| e.device_id)
+            .filter(|device_id| {
+                tables
+                    .devices
+                    .iter()
+                    .any(|d| d.id == *device_id && user_ids.contains(&d.user_id))
+            })
+            .filter(|device_id| !tables.push_jobs.iter().any(|j| j.device_id == *device_id))
+            .collect();
+
+        let now = now();
+        for device_id in &device_ids {
+            tables.push_jobs.push(PushJob {
+                id: Uuid::new_v4(),
+                device_id: *device_id,
+                attempts: 0,
+                next_attempt_at: now,
+                last_error: None,
+                created_at: now,
+            });
+        }
+
+        Ok(device_ids.len())
+    }
+
+    fn claim_due_jobs(
+        &self,
+        now: NaiveDateTime,
+        lease_until: NaiveDateTime,
+        limit: i64,
+    ) -> Result<Vec<PushJob>, RepositoryError> {
+        let mut tables = self.store.write();
+
+        let mut due: Vec<&mut PushJob> = tables
+            .push_jobs
+            .iter_mut()
+            .filter(|j| j.next_attempt_at <= now)
+            .collect();
+        due.sort_by_key(|j| j.next_attempt_at);
+
+        Ok(due
+            .into_iter()
+            .take(limit.max(0) as usize)
+            .map(|job| {
+                job.next_attempt_at = lease_until;
+                job.clone()
+            })
+            .collect())
+    }
+
+    fn delete_job(&self, id: Uuid) -> Result<(), RepositoryError> {
+        let mut tables = self.store.write();
+        tables.push_jobs.retain(|j| j.id != id);
+        Ok(())
+    }
+
+    fn reschedule_job(
+        &self,
+        id: Uuid,
+        next_attempt_at: NaiveDateTime,
+        error: &str,
+    ) -> Result<PushJob, RepositoryError> {
+        let mut tables = self.store.write();
+
+        let job = tables
+            .push_jobs
+            .iter_mut()
+            .find(|j| j.id == id)
+            .ok_or(RepositoryError::NotFound)?;
+        job.attempts += 1;
+        job.next_attempt_at = next_attempt_at;
+        job.last_error = Some(error.to_string());
+
+        Ok(job.clone())
+    }
+
+    fn dead_letter_job(&self, id: Uuid, error: &str) -> Result<PushDeadLetter, RepositoryError> {
+        let mut tables = self.store.write();
+
+        let job = tables
+            .push_jobs
+            .iter()
+            .find(|j| j.id == id)
+            .cloned()
+            .ok_or(RepositoryError::NotFound)?;
+        let endpoint = tables
+            .push_endpoints
+            .iter()
+            .find(|e| e.device_id == job.device_id)
+            .cloned()
+            .ok_or(RepositoryError::NotFound)?;
+
+        let dead_letter = PushDeadLetter {
+            id: job.id,
+            device_id: job.device_id,
+            kind: endpoint.kind,
+            url: endpoint.url,
+            attempts: job.attempts + 1,
+            error: error.to_string(),
+            created_at: job.created_at,
+            failed_at: now(),
+        };
+        tables.push_dead_letters.push(dead_letter.clone());
+        tables.push_jobs.retain(|j| j.id != id);
+
+        Ok(dead_letter)
+    }
+
+    fn list_dead_letters(
+        &self,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<PushDeadLetter>, RepositoryError> {
+        let tables = self.store.read();
+
+        let mut dead_letters: Vec<PushDeadLetter> = tables.push_dead_letters.clone();
+        dead_letters.sort_by(|a, b| b.failed_at.cmp(&a.failed_at).then(a.id.cmp(&b.id)));
+
+        Ok(paginate(dead_letters.into_iter(), limit, offset))
+    }
+}
@@ -0,0 +1,78 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use uuid::Uuid;
+
+use crate::schema::{push_dead_letters, push_endpoints, push_jobs};
+
+/// Where a device is woken up while offline.
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = push_endpoints)]
+pub struct PushEndpoint {
+    pub device_id: Uuid,
+    /// A [`PushEndpointKind`] name.
+    pub kind: String,
+    pub url: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = push_endpoints)]
+pub struct NewPushEndpoint {
+    pub device_id: Uuid,
+    pub kind: String,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushEndpointKind {
+    /// An HTTP or HTTPS endpoint that takes a small JSON body.
+    Webhook,
+    /// A Web Push subscription, sent an empty push signed with VAPID.
+    WebPush,
+}
+
+impl PushEndpointKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Webhook => "webhook",
+            Self::WebPush => "web_push",
+        }
+    }
+
+    pub fn parse(kind: &str) -> Option<Self> {
+        match kind {
+            "webhook" => Some(Self::Webhook),
+            "web_push" => Some(Self::WebPush),
+            _ => None,
+        }
+    }
+}
+
+/// A pending wake-up of one device.
+#[derive(Debug, Clone, Queryable, Selectable, Identifiable)]
+#[diesel(table_name = push_jobs)]
+pub struct PushJob {
+    pub id: Uuid,
+    pub device_id: Uuid,
+    /// Failed attempts so far.
+    pub attempts: i32,
+    pub next_attempt_at: NaiveDateTime,
+    pub last_error: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+/// A wake-up given up on, with the endpoint it was meant for.
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = push_dead_letters)]
+pub struct PushDeadLetter {
+    /// The id the job had.
+    pub id: Uuid,
+    pub device_id: Uuid,
+    pub kind: String,
+    pub url: String,
+    pub attempts: i32,
+    pub error: String,
+    pub created_at: NaiveDateTime,
+    pub failed_at: NaiveDateTime,
+}
@@ -0,0 +1,230 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use uuid::Uuid;
+
+use super::models::{NewPushEndpoint, PushDeadLetter, PushEndpoint, PushJob};
+use crate::repository::RepositoryError;
+use crate::repository::connection::PgSource;
+use crate::schema::{devices, push_dead_letters, push_endpoints, push_jobs};
+
+pub trait PushRepo: Send + Sync {
+    /// Sets the device's endpoint, replacing any earlier one.
+    fn upsert_endpoint(&self, endpoint: NewPushEndpoint) -> Result<PushEndpoint, RepositoryError>;
+
+    fn find_endpoint(&self, device_id: Uuid) -> Result<Option<PushEndpoint>, RepositoryError>;
+
+    /// Removes the endpoint along with its pending wake-up.
+    fn delete_endpoint(&self, device_id: Uuid) -> Result<(), RepositoryError>;
+
+    /// Queues a wake-up for every device of `user_ids` that has an endpoint
+    /// and none pending yet. Returns how many were queued.
+    fn enqueue_wake_ups(&self, user_ids: &[Uuid]) -> Result<usize, RepositoryError>;
+
+    /// Claims up to `limit` jobs due at `now` by moving their next attempt to
+    /// `lease_until`. Rows other transactions hold are skipped, so concurrent
+    /// dispatchers never claim the same job.
+    fn claim_due_jobs(
+        &self,
+        now: NaiveDateTime,
+        lease_until: NaiveDateTime,
+        limit: i64,
+    ) -> Result<Vec<PushJob>, RepositoryError>;
+
+    fn delete_job(&self, id: Uuid) -> Result<(), RepositoryError>;
+
+    /// Counts a failed attempt and sets when to try again.
+    fn reschedule_job(
+        &self,
+        id: Uuid,
+        next_attempt_at: NaiveDateTime,
+        error: &str,
+    ) -> Result<PushJob, RepositoryError>;
+
+    /// Gives up on the job, moving it to the dead letters with the final
+    /// attempt counted.
+    fn dead_letter_job(&self, id: Uuid, error: &str) -> Result<PushDeadLetter, RepositoryError>;
+
+    /// Dead letters, most recent first.
+    fn list_dead_letters(
+        &self,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<PushDeadLetter>, RepositoryError>;
+}
+
+#[derive(Clone)]
+pub struct PushRepository {
+    db: PgSource,
+}
+
+impl PushRepository {
+    pub fn new(db: PgSource) -> Self {
+        Self { db }
+    }
+}
+
+impl PushRepo for PushRepository {
+    #[tracing::instrument(skip(self, endpoint), fields(device_id = %endpoint.device_id))]
+    fn upsert_endpoint(&self, endpoint: NewPushEndpoint) -> Result<PushEndpoint, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        diesel::insert_into(push_endpoints::table)
+            .values(&endpoint)
+            .on_conflict(push_endpoints::device_id)
+            .do_update()
+            .set((
+                push_endpoints::kind.eq(&endpoint.kind),
+                push_endpoints::url.eq(&endpoint.url),
+                push_endpoints::updated_at.eq(diesel::dsl::now),
+            ))
+            .returning(PushEndpoint::as_returning())
+            .get_result(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    fn find_endpoint(&self, device_id: Uuid) -> Result<Option<PushEndpoint>, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        push_endpoints::table
+            .find(device_id)
+            .select(PushEndpoint::as_select())
+            .first(&mut *conn)
+            .optional()
+            .map_err(RepositoryError::from)
+    }
+
+    #[tracing::instrument(skip(self))]
+    fn delete_endpoint(&self, device_id: Uuid) -> Result<(), RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        let deleted = diesel::delete(push_endpoints::table.find(device_id)).execute(&mut *conn)?;
+
+        if deleted == 0 {
+            return Err(RepositoryError::NotFound);
+        }
+
+        Ok(())
+    }
+
+    fn enqueue_wake_ups(&self, user_ids: &[Uuid]) -> Result<usize, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        diesel::insert_into(push_jobs::table)
+            .values(
+                push_endpoints::table
+                    .inner_join(devices::table)
+                    .filter(devices::user_id.eq_any(user_ids))
+                    .select(push_endpoints::device_id),
+            )
+            .into_columns(push_jobs::device_id)
+            .on_conflict(push_jobs::device_id)
+            .do_nothing()
+            .execute(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    fn claim_due_jobs(
+        &self,
+        now: NaiveDateTime,
+        lease_until: NaiveDateTime,
+        limit: i64,
+    ) -> Result<Vec<PushJob>, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        conn.transaction(|conn| {
+            let ids: Vec<Uuid> = push_jobs::table
+                .filter(push_jobs::next_attempt_at.le(now))
+                .order(push_jobs::next_attempt_at.asc())
+                .limit(limit)
+                .select(push_jobs::id)
+                .for_update()
+                .skip_locked()
+                .load(conn)?;
+
+            diesel::update(push_jobs::table.filter(push_jobs::id.eq_any(&ids)))
+                .set(push_jobs::next_attempt_at.eq(lease_until))
+                .returning(PushJob::as_returning())
+                .get_results(conn)
+        })
+        .map_err(RepositoryError::from)
+    }
+
+    fn delete_job(&self, id: Uuid) -> Result<(), RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        diesel::delete(push_jobs::table.find(id)).execute(&mut *conn)?;
+
+        Ok(())
+    }
+
+    fn reschedule_job(
+        &self,
+        id: Uuid,
+        next_attempt_at: NaiveDateTime,
+        error: &str,
+    ) -> Result<PushJob, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        diesel::update(push_jobs::table.find(id))
+            .set((
+                push_jobs::attempts.eq(push_jobs::attempts + 1),
+                push_jobs::next_attempt_at.eq(next_attempt_at),
+                push_jobs::last_error.eq(error),
+            ))
+            .returning(PushJob::as_returning())
+            .get_result(&mut *conn)
+            .optional()?
+            .ok_or(RepositoryError::NotFound)
+    }
+
+    #[tracing::instrument(skip(self, error))]
+    fn dead_letter_job(&self, id: Uuid, error: &str) -> Result<PushDeadLetter, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        conn.transaction(|conn| {
+            let (job, endpoint) = push_jobs::table
+                .inner_join(push_endpoints::table)
+                .filter(push_jobs::id.eq(id))
+                .select((PushJob::as_select(), PushEndpoint::as_select()))
+                .first::<(PushJob, PushEndpoint)>(conn)
+                .optional()?
+                .ok_or(RepositoryError::NotFound)?;
+
+            let dead_letter = diesel::insert_into(push_dead_letters::table)
+                .values((
+                    push_dead_letters::id.eq(job.id),
+                    push_dead_letters::device_id.eq(job.device_id),
+                    push_dead_letters::kind.eq(endpoint.kind),
+                    push_dead_letters::url.eq(endpoint.url),
+                    push_dead_letters::attempts.eq(job.attempts + 1),
+                    push_dead_letters::error.eq(error),
+                    push_dead_letters::created_at.eq(job.created_at),
+                ))
+                .returning(PushDeadLetter::as_returning())
+                .get_result(conn)?;
+
+            diesel::delete(push_jobs::table.find(id)).execute(conn)?;
+
+            Ok(dead_letter)
+        })
+    }
+
+    fn list_dead_letters(
+        &self,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<PushDeadLetter>, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        push_dead_letters::table
+            .order((
+                push_dead_letters::failed_at.desc(),
+                push_dead_letters::id.asc(),
+            ))
+            .limit(limit)
+            .offset(offset)
+            .select(PushDeadLetter::as_select())
+            .load(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+}
@@ -0,0 +1,50 @@
+pub mod http;
+pub mod vapid;
+
+use std::sync::Arc;
+
+use thiserror::Error;
+
+use super::push::{PushEndpoint, PushEndpointKind};
+use crate::config::push::PushConfig;
+
+pub use http::HttpPushProvider;
+pub use vapid::VapidSigner;
+
+#[derive(Debug, Error)]
+pub enum PushProviderError {
+    /// The endpoint no longer exists, e.g. the subscription was revoked;
+    /// nothing should be sent to it again.
+    #[error("Push endpoint gone")]
+    Gone,
+
+    /// The endpoint refused the wake-up; retrying will not help.
+    #[error("Push rejected: {0}")]
+    Rejected(String),
+
+    /// The endpoint could not be reached; the wake-up may succeed on retry.
+    #[error("Push endpoint unavailable: {0}")]
+    Unavailable(String),
+}
+
+/// Delivers wake-ups to devices. Wake-ups carry no message content: the
+/// device fetches new messages itself once awake. Calls block, so use-case
+/// code runs them on the blocking pool like repository calls.
+pub trait PushProvider: Send + Sync {
+    /// Whether wake-ups can be sent to this endpoint, checked when a device
+    /// registers it.
+    fn check_endpoint(&self, kind: PushEndpointKind, url: &str) -> Result<(), String>;
+
+    fn wake(&self, endpoint: &PushEndpoint) -> Result<(), PushProviderError>;
+
+    /// The key browsers create Web Push subscriptions with, if the provider
+    /// signs its requests with one.
+    fn vapid_public_key(&self) -> Option<&[u8]> {
+        None
+    }
+}
+
+/// Opens the configured provider.
+pub fn open(config: &PushConfig) -> Result<Arc<dyn PushProvider>, String> {
+    Ok(Arc::new(HttpPushProvider::new(config)?))
+}
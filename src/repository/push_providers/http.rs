@@ -0,0 +1,159 @@
+use std::time::Duration;
+
+use http_body_util::{BodyExt, Full};
+use hyper::body::Bytes;
+use hyper::{Method, Request, StatusCode, Uri};
+
+use super::vapid::VapidSigner;
+use super::{PushProvider, PushProviderError};
+use crate::config::push::PushConfig;
+use crate::repository::http_client::{self, Destinations, HttpClient};
+use crate::repository::push::{PushEndpoint, PushEndpointKind};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_URL_LENGTH: usize = 2048;
+/// How long a push service may hold a wake-up for a device that is off.
+const WEB_PUSH_TTL_SECONDS: u32 = 86400;
+
+/// [`PushProvider`] that POSTs to the endpoint's URL: a small JSON body for
+/// webhooks, an empty VAPID-signed push for Web Push subscriptions.
+pub struct HttpPushProvider {
+    client: HttpClient,
+    destinations: Destinations,
+    vapid: Option<VapidSigner>,
+}
+
+impl HttpPushProvider {
+    pub fn new(config: &PushConfig) -> Result<Self, String> {
+        let destinations = if config.allow_private_endpoints {
+            Destinations::Any
+        } else {
+            Destinations::PublicOnly
+        };
+
+        Ok(Self {
+            client: http_client::client(destinations),
+            destinations,
+            vapid: config.vapid.as_ref().map(VapidSigner::new).transpose()?,
+        })
+    }
+
+    fn parse_url(&self, url: &str) -> Result<Uri, String> {
+        if url.len() > MAX_URL_LENGTH {
+            return Err(format!(
+                "Endpoint URL is limited to {} characters",
+                MAX_URL_LENGTH
+            ));
+        }
+
+        http_client::parse_url(url, self.destinations)
+    }
+
+    fn request(&self, endpoint: &PushEndpoint) -> Result<Request<Full<Bytes>>, PushProviderError> {
+        let uri = self
+            .parse_url(&endpoint.url)
+            .map_err(PushProviderError::Rejected)?;
+        let request = Request::builder().method(Method::POST).uri(&uri);
+
+        let request = match PushEndpointKind::parse(&endpoint.kind) {
+            Some(PushEndpointKind::Webhook) => {
+                let body = serde_json::json!({
+                    "type": "wake_up",
+                    "device_id": endpoint.device_id,
+                });
+
+                request
+                    .header("content-type", "application/json")
+                    .body(Full::new(Bytes::from(body.to_string())))
+            }
+            Some(PushEndpointKind::WebPush) => {
+                let vapid = self.vapid.as_ref().ok_or_else(|| {
+                    PushProviderError::Rejected("VAPID is not configured".to_string())
+                })?;
+                // The audience is the push service's origin.
+                let audience = format!(
+                    "{}://{}",
+                    uri.scheme_str().unwrap_or_default(),
+                    uri.authority().map_or("", |a| a.as_str())
+                );
+                let authorization = vapid
+                    .authorization(&audience)
+                    .map_err(PushProviderError::Rejected)?;
+
+                request
+                    .header("authorization", authorization)
+                    .header("ttl", WEB_PUSH_TTL_SECONDS)
+                    .header("urgency", "high")
+                    .header("content-length", 0)
+                    .body(Full::new(Bytes::new()))
+            }
+            None => {
+                return Err(PushProviderError::Rejected(format!(
+                    "Unknown endpoint kind {}",
+                    endpoint.kind
+                )));
+            }
+        };
+
+        request.map_err(|e| PushProviderError::Rejected(e.to_string()))
+    }
+}
+
+impl PushProvider for HttpPushProvider {
+    fn check_endpoint(&self, kind: PushEndpointKind, url: &str) -> Result<(), String> {
+        self.parse_url(url)?;
+
+        if kind == PushEndpointKind::WebPush && self.vapid.is_none() {
+            return Err("Web Push is not configured on this server".to_string());
+        }
+
+        Ok(())
+    }
+
+    fn wake(&self, endpoint: &PushEndpoint) -> Result<(), PushProviderError> {
+        let request = self.request(endpoint)?;
+
+        let (status, body) = http_client::block_on(async {
+            let response = tokio::time::timeout(REQUEST_TIMEOUT, self.client.request(request))
+                .await
+                .map_err(|_| PushProviderError::Unavailable("Push request timed out".to_string()))?
+                .map_err(|e| PushProviderError::Unavailable(e.to_string()))?;
+
+            let status = response.status();
+            let body = response
+                .into_body()
+                .collect()
+                .await
+                .map_err(|e| PushProviderError::Unavailable(e.to_string()))?
+                .to_bytes();
+
+            Ok::<_, PushProviderError>((status, body))
+        })
+        .map_err(PushProviderError::Unavailable)??;
+
+        check_status(status, &body)
+    }
+
+    fn vapid_public_key(&self) -> Option<&[u8]> {
+        self.vapid.as_ref().map(VapidSigner::public_key)
+    }
+}
+
+fn check_status(status: StatusCode, body: &[u8]) -> Result<(), PushProviderError> {
+    if status.is_success() {
+        return Ok(());
+    }
+
+    // Errors end up in the job row; keep only the start of the body.
+    let body: String = String::from_utf8_lossy(body).chars().take(200).collect();
+    let message = format!("Push endpoint returned {}: {}", status, body);
+
+    Err(match status {
+        StatusCode::NOT_FOUND | StatusCode::GONE => PushProviderError::Gone,
+        StatusCode::REQUEST_TIMEOUT | StatusCode::TOO_MANY_REQUESTS => {
+            PushProviderError::Unavailable(message)
+        }
+        s if s.is_server_error() => PushProviderError::Unavailable(message),
+        _ => PushProviderError::Rejected(message),
+    })
+}
@@ -0,0 +1,69 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use chrono::{Duration, Utc};
+use ring::rand::SystemRandom;
+use ring::signature::{ECDSA_P256_SHA256_FIXED_SIGNING, EcdsaKeyPair, KeyPair};
+
+use crate::config::push::VapidConfig;
+
+/// How long a signed request stays valid; push services refuse more than a
+/// day.
+const TOKEN_LIFETIME_HOURS: i64 = 12;
+
+/// Signs Web Push requests as the application server (RFC 8292), with an
+/// ES256 token over the push service's origin.
+pub struct VapidSigner {
+    key_pair: EcdsaKeyPair,
+    subject: String,
+    rng: SystemRandom,
+}
+
+impl VapidSigner {
+    pub fn new(config: &VapidConfig) -> Result<Self, String> {
+        let rng = SystemRandom::new();
+        let parsed = pem::parse(&config.private_key_pem)
+            .map_err(|e| format!("Invalid VAPID private key: {}", e))?;
+        let key_pair =
+            EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, parsed.contents(), &rng)
+                .map_err(|e| format!("Invalid VAPID private key: {}", e))?;
+
+        Ok(Self {
+            key_pair,
+            subject: config.subject.clone(),
+            rng,
+        })
+    }
+
+    /// The uncompressed P-256 public key.
+    pub fn public_key(&self) -> &[u8] {
+        self.key_pair.public_key().as_ref()
+    }
+
+    /// The `Authorization` header for a push to `audience`, the origin of
+    /// the endpoint.
+    pub fn authorization(&self, audience: &str) -> Result<String, String> {
+        let header = serde_json::json!({"typ": "JWT", "alg": "ES256"});
+        let claims = serde_json::json!({
+            "aud": audience,
+            "exp": (Utc::now() + Duration::hours(TOKEN_LIFETIME_HOURS)).timestamp(),
+            "sub": self.subject,
+        });
+
+        let signing_input = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(header.to_string()),
+            URL_SAFE_NO_PAD.encode(claims.to_string())
+        );
+        let signature = self
+            .key_pair
+            .sign(&self.rng, signing_input.as_bytes())
+            .map_err(|_| "Failed to sign VAPID token".to_string())?;
+
+        Ok(format!(
+            "vapid t={}.{}, k={}",
+            signing_input,
+            URL_SAFE_NO_PAD.encode(signature.as_ref()),
+            URL_SAFE_NO_PAD.encode(self.public_key())
+        ))
+    }
+}
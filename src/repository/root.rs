@@ -1,21 +1,76 @@
-use super::auth::repo::AuthRepository;
-use super::chat::repo::ChatRepository;
+use super::attachments::repo::AttachmentRepo;
+use super::auth::repo::AuthRepo;
+use super::chat::repo::ChatRepo;
+use super::connection::PgSource;
+use super::devices::repo::DeviceRepo;
+use super::error::RepositoryError;
 use super::factory::Factory;
+use super::key_log::repo::KeyLogRepo;
+use super::keys::repo::KeyRepo;
+use super::memory::MemoryStore;
+use super::push::repo::PushRepo;
+use super::scheduled_messages::repo::ScheduledMessageRepo;
+use super::sender_keys::repo::SenderKeyRepo;
+use super::transaction::{PgUnitOfWork, UnitOfWork};
+use super::webhooks::repo::WebhookRepo;
 use crate::bootstrap::postgres::Postgres;
 use std::sync::Arc;
 
 pub struct Repository {
-    pub auth: AuthRepository,
-    pub chat: ChatRepository,
+    pub auth: Arc<dyn AuthRepo>,
+    pub chat: Arc<dyn ChatRepo>,
+    pub keys: Arc<dyn KeyRepo>,
+    pub devices: Arc<dyn DeviceRepo>,
+    pub sender_keys: Arc<dyn SenderKeyRepo>,
+    pub key_log: Arc<dyn KeyLogRepo>,
+    pub attachments: Arc<dyn AttachmentRepo>,
+    pub scheduled_messages: Arc<dyn ScheduledMessageRepo>,
+    pub push: Arc<dyn PushRepo>,
+    pub webhooks: Arc<dyn WebhookRepo>,
+    pub(super) work: Option<Arc<dyn UnitOfWork>>,
 }
 
 impl Repository {
     pub fn new(postgres: Arc<Postgres>) -> Self {
-        let factory = Factory::new(postgres);
+        let work = Arc::new(PgUnitOfWork::new(postgres.clone()));
 
-        Self {
-            auth: factory.new_auth_repository(),
-            chat: factory.new_chat_repository(),
+        Factory::new(PgSource::Pool(postgres)).repository(Some(work))
+    }
+
+    /// Repositories backed by a fresh, seeded [`MemoryStore`] instead of
+    /// Postgres, so the use-case and HTTP layers can run without a database.
+    pub fn in_memory() -> Self {
+        let store = MemoryStore::new();
+
+        store.repository(Some(Arc::new(store.clone())))
+    }
+
+    /// Runs `f` against repositories that share one transaction. It commits
+    /// when `f` returns `Ok` and rolls back otherwise.
+    ///
+    /// Calling this on the repository handed to `f` joins the outer
+    /// transaction instead of opening a new one.
+    pub fn transaction<T, E>(&self, f: impl FnOnce(&Repository) -> Result<T, E>) -> Result<T, E>
+    where
+        E: From<RepositoryError>,
+    {
+        let Some(work) = &self.work else {
+            return f(self);
+        };
+
+        let tx = work.begin()?;
+
+        match f(&tx.repository()) {
+            Ok(value) => {
+                tx.commit()?;
+                Ok(value)
+            }
+            Err(e) => {
+                if let Err(rollback) = tx.rollback() {
+                    tracing::warn!("Failed to roll back transaction: {}", rollback);
+                }
+                Err(e)
+            }
         }
     }
 }
@@ -25,6 +80,15 @@ impl Clone for Repository {
         Self {
             auth: self.auth.clone(),
             chat: self.chat.clone(),
+            keys: self.keys.clone(),
+            devices: self.devices.clone(),
+            sender_keys: self.sender_keys.clone(),
+            key_log: self.key_log.clone(),
+            attachments: self.attachments.clone(),
+            scheduled_messages: self.scheduled_messages.clone(),
+            push: self.push.clone(),
+            webhooks: self.webhooks.clone(),
+            work: self.work.clone(),
         }
     }
 }
@@ -0,0 +1,9 @@
+pub mod memory;
+pub mod models;
+pub mod repo;
+
+pub use memory::InMemoryScheduledMessageRepository;
+pub use models::{
+    NewScheduledMessage, ScheduledMessage, ScheduledMessageContent, ScheduledMessageStatus,
+};
+pub use repo::{ScheduledMessageRepo, ScheduledMessageRepository};
@@ -0,0 +1,196 @@
+use std::sync::Arc;
+
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+
+use super::models::{
+    NewScheduledMessage, ScheduledMessage, ScheduledMessageContent, ScheduledMessageStatus,
+};
+use super::repo::ScheduledMessageRepo;
+use crate::repository::RepositoryError;
+use crate::repository::memory::{MemoryStore, ensure_chat, ensure_device, ensure_user, now};
+
+/// [`ScheduledMessageRepo`] over a [`MemoryStore`].
+#[derive(Clone)]
+pub struct InMemoryScheduledMessageRepository {
+    store: Arc<MemoryStore>,
+}
+
+impl InMemoryScheduledMessageRepository {
+    pub fn new(store: Arc<MemoryStore>) -> Self {
+        Self { store }
+    }
+}
+
+fn ensure_body(
+    envelopes: &Option<String>,
+    epoch: Option<i32>,
+    ciphertext: &Option<String>,
+) -> Result<(), RepositoryError> {
+    match (envelopes, epoch, ciphertext) {
+        (Some(_), None, None) | (None, Some(_), Some(_)) => Ok(()),
+        _ => Err(RepositoryError::Conflict(
+            "scheduled message needs either envelopes or a sender-key ciphertext".to_string(),
+        )),
+    }
+}
+
+impl ScheduledMessageRepo for InMemoryScheduledMessageRepository {
+    fn create_scheduled_message(
+        &self,
+        message: NewScheduledMessage,
+    ) -> Result<ScheduledMessage, RepositoryError> {
+        let mut tables = self.store.write();
+
+        ensure_chat(&tables, message.chat_id)?;
+        ensure_user(&tables, message.sender_id)?;
+        ensure_device(&tables, message.sender_device_id)?;
+        ensure_body(&message.envelopes, message.epoch, &message.ciphertext)?;
+
+        let now = now();
+        let message = ScheduledMessage {
+            id: Uuid::new_v4(),
+            chat_id: message.chat_id,
+            sender_id: message.sender_id,
+            sender_device_id: message.sender_device_id,
+            send_at: message.send_at,
+            envelopes: message.envelopes,
+            epoch: message.epoch,
+            ciphertext: message.ciphertext,
+            attachment_ids: message.attachment_ids,
+            view_once: message.view_once,
+            mentions: message.mentions,
+            mention_all: message.mention_all,
+            status: ScheduledMessageStatus::Pending.as_str().to_string(),
+            error: None,
+            created_at: now,
+            updated_at: now,
+        };
+        tables.scheduled_messages.push(message.clone());
+
+        Ok(message)
+    }
+
+    fn find_scheduled_message(
+        &self,
+        id: Uuid,
+    ) -> Result<Option<ScheduledMessage>, RepositoryError> {
+        let tables = self.store.read();
+
+        Ok(tables
+            .scheduled_messages
+            .iter()
+            .find(|m| m.id == id)
+            .cloned())
+    }
+
+    // Transactions over the store detect concurrent writers at commit time,
+    // which stands in for the row lock.
+    fn lock_scheduled_message(
+        &self,
+        id: Uuid,
+    ) -> Result<Option<ScheduledMessage>, RepositoryError> {
+        self.find_scheduled_message(id)
+    }
+
+    fn list_scheduled_messages(
+        &self,
+        sender_id: Uuid,
+        chat_id: Option<Uuid>,
+    ) -> Result<Vec<ScheduledMessage>, RepositoryError> {
+        let tables = self.store.read();
+
+        let mut messages: Vec<ScheduledMessage> = tables
+            .scheduled_messages
+            .iter()
+            .filter(|m| m.sender_id == sender_id)
+            .filter(|m| chat_id.is_none_or(|chat_id| m.chat_id == chat_id))
+            .cloned()
+            .collect();
+        messages.sort_by_key(|m| (m.send_at, m.created_at));
+
+        Ok(messages)
+    }
+
+    fn count_scheduled_messages(&self, sender_id: Uuid) -> Result<i64, RepositoryError> {
+        let tables = self.store.read();
+
+        Ok(tables
+            .scheduled_messages
+            .iter()
+            .filter(|m| m.sender_id == sender_id)
+            .count() as i64)
+    }
+
+    fn update_scheduled_message(
+        &self,
+        id: Uuid,
+        content: ScheduledMessageContent,
+    ) -> Result<ScheduledMessage, RepositoryError> {
+        let mut tables = self.store.write();
+
+        ensure_device(&tables, content.sender_device_id)?;
+        ensure_body(&content.envelopes, content.epoch, &content.ciphertext)?;
+
+        let message = tables
+            .scheduled_messages
+            .iter_mut()
+            .find(|m| m.id == id)
+            .ok_or(RepositoryError::NotFound)?;
+
+        message.sender_device_id = content.sender_device_id;
+        message.send_at = content.send_at;
+        message.envelopes = content.envelopes;
+        message.epoch = content.epoch;
+        message.ciphertext = content.ciphertext;
+        message.attachment_ids = content.attachment_ids;
+        message.view_once = content.view_once;
+        message.mentions = content.mentions;
+        message.mention_all = content.mention_all;
+        message.status = ScheduledMessageStatus::Pending.as_str().to_string();
+        message.error = None;
+        message.updated_at = now();
+
+        Ok(message.clone())
+    }
+
+    fn delete_scheduled_message(&self, id: Uuid) -> Result<(), RepositoryError> {
+        let mut tables = self.store.write();
+
+        if !tables.scheduled_messages.iter().any(|m| m.id == id) {
+            return Err(RepositoryError::NotFound);
+        }
+
+        tables.scheduled_messages.retain(|m| m.id != id);
+
+        Ok(())
+    }
+
+    // A single store has no concurrent schedulers to skip.
+    fn claim_due_scheduled_message(
+        &self,
+        now: NaiveDateTime,
+    ) -> Result<Option<ScheduledMessage>, RepositoryError> {
+        let tables = self.store.read();
+
+        Ok(tables
+            .scheduled_messages
+            .iter()
+            .filter(|m| m.status == ScheduledMessageStatus::Pending.as_str())
+            .filter(|m| m.send_at <= now)
+            .min_by_key(|m| m.send_at)
+            .cloned())
+    }
+
+    fn mark_scheduled_message_failed(&self, id: Uuid, error: &str) -> Result<(), RepositoryError> {
+        let mut tables = self.store.write();
+
+        if let Some(message) = tables.scheduled_messages.iter_mut().find(|m| m.id == id) {
+            message.status = ScheduledMessageStatus::Failed.as_str().to_string();
+            message.error = Some(error.to_string());
+            message.updated_at = now();
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,78 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use uuid::Uuid;
+
+use crate::schema::scheduled_messages;
+
+/// A message waiting for `send_at`. Exactly one of `envelopes` (a JSON
+/// object of per-device ciphertexts) and `epoch`/`ciphertext` is set.
+#[derive(Debug, Clone, Queryable, Selectable, Identifiable)]
+#[diesel(table_name = scheduled_messages)]
+pub struct ScheduledMessage {
+    pub id: Uuid,
+    pub chat_id: Uuid,
+    pub sender_id: Uuid,
+    pub sender_device_id: Uuid,
+    pub send_at: NaiveDateTime,
+    pub envelopes: Option<String>,
+    pub epoch: Option<i32>,
+    pub ciphertext: Option<String>,
+    pub attachment_ids: Vec<Uuid>,
+    pub view_once: bool,
+    /// A [`ScheduledMessageStatus`] name.
+    pub status: String,
+    /// Why posting failed, for `failed` rows.
+    pub error: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    pub mentions: Vec<Uuid>,
+    pub mention_all: bool,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = scheduled_messages)]
+pub struct NewScheduledMessage {
+    pub chat_id: Uuid,
+    pub sender_id: Uuid,
+    pub sender_device_id: Uuid,
+    pub send_at: NaiveDateTime,
+    pub envelopes: Option<String>,
+    pub epoch: Option<i32>,
+    pub ciphertext: Option<String>,
+    pub attachment_ids: Vec<Uuid>,
+    pub view_once: bool,
+    pub mentions: Vec<Uuid>,
+    pub mention_all: bool,
+}
+
+/// What an edit replaces: everything the sender chose. It also puts the row
+/// back to `pending`.
+#[derive(Debug, Clone, AsChangeset)]
+#[diesel(table_name = scheduled_messages, treat_none_as_null = true)]
+pub struct ScheduledMessageContent {
+    pub sender_device_id: Uuid,
+    pub send_at: NaiveDateTime,
+    pub envelopes: Option<String>,
+    pub epoch: Option<i32>,
+    pub ciphertext: Option<String>,
+    pub attachment_ids: Vec<Uuid>,
+    pub view_once: bool,
+    pub mentions: Vec<Uuid>,
+    pub mention_all: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScheduledMessageStatus {
+    Pending,
+    /// The scheduler could not post the message; see `error`.
+    Failed,
+}
+
+impl ScheduledMessageStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Failed => "failed",
+        }
+    }
+}
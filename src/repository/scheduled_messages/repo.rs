@@ -0,0 +1,212 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use uuid::Uuid;
+
+use super::models::{
+    NewScheduledMessage, ScheduledMessage, ScheduledMessageContent, ScheduledMessageStatus,
+};
+use crate::repository::RepositoryError;
+use crate::repository::connection::PgSource;
+use crate::schema::scheduled_messages;
+
+pub trait ScheduledMessageRepo: Send + Sync {
+    fn create_scheduled_message(
+        &self,
+        message: NewScheduledMessage,
+    ) -> Result<ScheduledMessage, RepositoryError>;
+
+    fn find_scheduled_message(&self, id: Uuid)
+    -> Result<Option<ScheduledMessage>, RepositoryError>;
+
+    /// Like [`ScheduledMessageRepo::find_scheduled_message`], but holds a row
+    /// lock until the surrounding transaction ends. It waits for a scheduler
+    /// that is posting the message, and then finds it gone.
+    fn lock_scheduled_message(&self, id: Uuid)
+    -> Result<Option<ScheduledMessage>, RepositoryError>;
+
+    /// The sender's scheduled messages in `send_at` order, optionally of one
+    /// chat.
+    fn list_scheduled_messages(
+        &self,
+        sender_id: Uuid,
+        chat_id: Option<Uuid>,
+    ) -> Result<Vec<ScheduledMessage>, RepositoryError>;
+
+    fn count_scheduled_messages(&self, sender_id: Uuid) -> Result<i64, RepositoryError>;
+
+    /// Replaces the content and schedule, and makes the message pending
+    /// again.
+    fn update_scheduled_message(
+        &self,
+        id: Uuid,
+        content: ScheduledMessageContent,
+    ) -> Result<ScheduledMessage, RepositoryError>;
+
+    fn delete_scheduled_message(&self, id: Uuid) -> Result<(), RepositoryError>;
+
+    /// Locks the earliest pending message due at `now`, skipping rows other
+    /// transactions hold, so concurrent schedulers never claim the same one.
+    fn claim_due_scheduled_message(
+        &self,
+        now: NaiveDateTime,
+    ) -> Result<Option<ScheduledMessage>, RepositoryError>;
+
+    fn mark_scheduled_message_failed(&self, id: Uuid, error: &str) -> Result<(), RepositoryError>;
+}
+
+#[derive(Clone)]
+pub struct ScheduledMessageRepository {
+    db: PgSource,
+}
+
+impl ScheduledMessageRepository {
+    pub fn new(db: PgSource) -> Self {
+        Self { db }
+    }
+}
+
+impl ScheduledMessageRepo for ScheduledMessageRepository {
+    #[tracing::instrument(skip(self, message), fields(chat_id = %message.chat_id))]
+    fn create_scheduled_message(
+        &self,
+        message: NewScheduledMessage,
+    ) -> Result<ScheduledMessage, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        diesel::insert_into(scheduled_messages::table)
+            .values(&message)
+            .returning(ScheduledMessage::as_returning())
+            .get_result(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    fn find_scheduled_message(
+        &self,
+        id: Uuid,
+    ) -> Result<Option<ScheduledMessage>, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        scheduled_messages::table
+            .find(id)
+            .select(ScheduledMessage::as_select())
+            .first(&mut *conn)
+            .optional()
+            .map_err(RepositoryError::from)
+    }
+
+    fn lock_scheduled_message(
+        &self,
+        id: Uuid,
+    ) -> Result<Option<ScheduledMessage>, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        scheduled_messages::table
+            .find(id)
+            .select(ScheduledMessage::as_select())
+            .for_update()
+            .first(&mut *conn)
+            .optional()
+            .map_err(RepositoryError::from)
+    }
+
+    fn list_scheduled_messages(
+        &self,
+        sender_id: Uuid,
+        chat_id: Option<Uuid>,
+    ) -> Result<Vec<ScheduledMessage>, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        let mut query = scheduled_messages::table
+            .filter(scheduled_messages::sender_id.eq(sender_id))
+            .into_boxed();
+
+        if let Some(chat_id) = chat_id {
+            query = query.filter(scheduled_messages::chat_id.eq(chat_id));
+        }
+
+        query
+            .order((
+                scheduled_messages::send_at.asc(),
+                scheduled_messages::created_at.asc(),
+            ))
+            .select(ScheduledMessage::as_select())
+            .load(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    fn count_scheduled_messages(&self, sender_id: Uuid) -> Result<i64, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        scheduled_messages::table
+            .filter(scheduled_messages::sender_id.eq(sender_id))
+            .count()
+            .get_result(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    #[tracing::instrument(skip(self, content))]
+    fn update_scheduled_message(
+        &self,
+        id: Uuid,
+        content: ScheduledMessageContent,
+    ) -> Result<ScheduledMessage, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        diesel::update(scheduled_messages::table.find(id))
+            .set((
+                &content,
+                scheduled_messages::status.eq(ScheduledMessageStatus::Pending.as_str()),
+                scheduled_messages::error.eq(None::<String>),
+                scheduled_messages::updated_at.eq(diesel::dsl::now),
+            ))
+            .returning(ScheduledMessage::as_returning())
+            .get_result(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    #[tracing::instrument(skip(self))]
+    fn delete_scheduled_message(&self, id: Uuid) -> Result<(), RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        let deleted = diesel::delete(scheduled_messages::table.find(id)).execute(&mut *conn)?;
+
+        if deleted == 0 {
+            return Err(RepositoryError::NotFound);
+        }
+
+        Ok(())
+    }
+
+    fn claim_due_scheduled_message(
+        &self,
+        now: NaiveDateTime,
+    ) -> Result<Option<ScheduledMessage>, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        scheduled_messages::table
+            .filter(scheduled_messages::status.eq(ScheduledMessageStatus::Pending.as_str()))
+            .filter(scheduled_messages::send_at.le(now))
+            .order(scheduled_messages::send_at.asc())
+            .select(ScheduledMessage::as_select())
+            .for_update()
+            .skip_locked()
+            .first(&mut *conn)
+            .optional()
+            .map_err(RepositoryError::from)
+    }
+
+    #[tracing::instrument(skip(self, error))]
+    fn mark_scheduled_message_failed(&self, id: Uuid, error: &str) -> Result<(), RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        diesel::update(scheduled_messages::table.find(id))
+            .set((
+                scheduled_messages::status.eq(ScheduledMessageStatus::Failed.as_str()),
+                scheduled_messages::error.eq(error),
+                scheduled_messages::updated_at.eq(diesel::dsl::now),
+            ))
+            .execute(&mut *conn)?;
+
+        Ok(())
+    }
+}
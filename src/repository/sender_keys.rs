@@ -0,0 +1,7 @@
+pub mod memory;
+pub mod models;
+pub mod repo;
+
+pub use memory::InMemorySenderKeyRepository;
+pub use models::{NewSenderKey, SenderKey};
+pub use repo::{SenderKeyRepo, SenderKeyRepository};
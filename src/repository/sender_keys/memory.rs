@@ -0,0 +1,111 @@
+use std::sync::Arc;
+
+use uuid::Uuid;
+
+use super::models::{NewSenderKey, SenderKey};
+use super::repo::SenderKeyRepo;
+use crate::repository::RepositoryError;
+use crate::repository::memory::{MemoryStore, ensure_chat, ensure_device, ensure_user, now};
+
+/// [`SenderKeyRepo`] over a [`MemoryStore`].
+#[derive(Clone)]
+pub struct InMemorySenderKeyRepository {
+    store: Arc<MemoryStore>,
+}
+
+impl InMemorySenderKeyRepository {
+    pub fn new(store: Arc<MemoryStore>) -> Self {
+        Self { store }
+    }
+}
+
+impl SenderKeyRepo for InMemorySenderKeyRepository {
+    fn store_sender_keys(&self, keys: &[NewSenderKey]) -> Result<usize, RepositoryError> {
+        let mut tables = self.store.write();
+
+        for key in keys {
+            ensure_chat(&tables, key.chat_id)?;
+            ensure_user(&tables, key.sender_id)?;
+            ensure_device(&tables, key.sender_device_id)?;
+            ensure_device(&tables, key.recipient_device_id)?;
+        }
+
+        let now = now();
+        for key in keys {
+            let existing = tables.sender_keys.iter_mut().find(|k| {
+                k.chat_id == key.chat_id
+                    && k.epoch == key.epoch
+                    && k.sender_device_id == key.sender_device_id
+                    && k.recipient_device_id == key.recipient_device_id
+            });
+
+            match existing {
+                Some(existing) => {
+                    existing.ciphertext = key.ciphertext.clone();
+                    existing.created_at = now;
+                }
+                None => {
+                    let id = tables.sender_keys.iter().map(|k| k.id).max().unwrap_or(0) + 1;
+                    tables.sender_keys.push(SenderKey {
+                        id,
+                        chat_id: key.chat_id,
+                        epoch: key.epoch,
+                        sender_id: key.sender_id,
+                        sender_device_id: key.sender_device_id,
+                        recipient_device_id: key.recipient_device_id,
+                        ciphertext: key.ciphertext.clone(),
+                        created_at: now,
+                    });
+                }
+            }
+        }
+
+        Ok(keys.len())
+    }
+
+    fn get_sender_keys(
+        &self,
+        chat_id: Uuid,
+        epoch: i32,
+        recipient_device_id: Uuid,
+    ) -> Result<Vec<SenderKey>, RepositoryError> {
+        let tables = self.store.read();
+
+        let mut keys: Vec<SenderKey> = tables
+            .sender_keys
+            .iter()
+            .filter(|k| {
+                k.chat_id == chat_id
+                    && k.epoch == epoch
+                    && k.recipient_device_id == recipient_device_id
+            })
+            .cloned()
+            .collect();
+        keys.sort_by_key(|k| (k.created_at, k.id));
+
+        Ok(keys)
+    }
+
+    fn delete_user_sender_keys(
+        &self,
+        chat_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<usize, RepositoryError> {
+        let mut tables = self.store.write();
+
+        let user_devices: Vec<Uuid> = tables
+            .devices
+            .iter()
+            .filter(|d| d.user_id == user_id)
+            .map(|d| d.id)
+            .collect();
+
+        let before = tables.sender_keys.len();
+        tables.sender_keys.retain(|k| {
+            k.chat_id != chat_id
+                || (k.sender_id != user_id && !user_devices.contains(&k.recipient_device_id))
+        });
+
+        Ok(before - tables.sender_keys.len())
+    }
+}
@@ -0,0 +1,31 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use uuid::Uuid;
+
+use crate::schema::sender_keys;
+
+/// A sender key of `sender_device_id` for one chat epoch, encrypted for
+/// `recipient_device_id` over their pairwise session.
+#[derive(Debug, Clone, Queryable, Selectable, Identifiable)]
+#[diesel(table_name = sender_keys)]
+pub struct SenderKey {
+    pub id: i64,
+    pub chat_id: Uuid,
+    pub epoch: i32,
+    pub sender_id: Uuid,
+    pub sender_device_id: Uuid,
+    pub recipient_device_id: Uuid,
+    pub ciphertext: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = sender_keys)]
+pub struct NewSenderKey {
+    pub chat_id: Uuid,
+    pub epoch: i32,
+    pub sender_id: Uuid,
+    pub sender_device_id: Uuid,
+    pub recipient_device_id: Uuid,
+    pub ciphertext: String,
+}
@@ -0,0 +1,109 @@
+use diesel::prelude::*;
+use diesel::upsert::excluded;
+use uuid::Uuid;
+
+use super::models::{NewSenderKey, SenderKey};
+use crate::repository::RepositoryError;
+use crate::repository::connection::PgSource;
+use crate::schema::{devices, sender_keys};
+
+pub trait SenderKeyRepo: Send + Sync {
+    /// Stores the keys, replacing any earlier copy a sender device sent the
+    /// same recipient device for the same epoch.
+    fn store_sender_keys(&self, keys: &[NewSenderKey]) -> Result<usize, RepositoryError>;
+
+    /// Keys addressed to the device for one epoch of the chat, oldest first.
+    fn get_sender_keys(
+        &self,
+        chat_id: Uuid,
+        epoch: i32,
+        recipient_device_id: Uuid,
+    ) -> Result<Vec<SenderKey>, RepositoryError>;
+
+    /// Drops every key the user sent or received in the chat, in any epoch.
+    fn delete_user_sender_keys(
+        &self,
+        chat_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<usize, RepositoryError>;
+}
+
+#[derive(Clone)]
+pub struct SenderKeyRepository {
+    db: PgSource,
+}
+
+impl SenderKeyRepository {
+    pub fn new(db: PgSource) -> Self {
+        Self { db }
+    }
+}
+
+impl SenderKeyRepo for SenderKeyRepository {
+    #[tracing::instrument(skip(self, keys), fields(count = keys.len()))]
+    fn store_sender_keys(&self, keys: &[NewSenderKey]) -> Result<usize, RepositoryError> {
+        if keys.is_empty() {
+            return Ok(0);
+        }
+
+        let mut conn = self.db.conn()?;
+
+        diesel::insert_into(sender_keys::table)
+            .values(keys)
+            .on_conflict((
+                sender_keys::chat_id,
+                sender_keys::epoch,
+                sender_keys::sender_device_id,
+                sender_keys::recipient_device_id,
+            ))
+            .do_update()
+            .set((
+                sender_keys::ciphertext.eq(excluded(sender_keys::ciphertext)),
+                sender_keys::created_at.eq(diesel::dsl::now),
+            ))
+            .execute(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    fn get_sender_keys(
+        &self,
+        chat_id: Uuid,
+        epoch: i32,
+        recipient_device_id: Uuid,
+    ) -> Result<Vec<SenderKey>, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        sender_keys::table
+            .filter(sender_keys::recipient_device_id.eq(recipient_device_id))
+            .filter(sender_keys::chat_id.eq(chat_id))
+            .filter(sender_keys::epoch.eq(epoch))
+            .order((sender_keys::created_at.asc(), sender_keys::id.asc()))
+            .select(SenderKey::as_select())
+            .load(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    fn delete_user_sender_keys(
+        &self,
+        chat_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<usize, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        let user_devices = devices::table
+            .filter(devices::user_id.eq(user_id))
+            .select(devices::id);
+
+        diesel::delete(
+            sender_keys::table
+                .filter(sender_keys::chat_id.eq(chat_id))
+                .filter(
+                    sender_keys::sender_id
+                        .eq(user_id)
+                        .or(sender_keys::recipient_device_id.eq_any(user_devices)),
+                ),
+        )
+        .execute(&mut *conn)
+        .map_err(RepositoryError::from)
+    }
+}
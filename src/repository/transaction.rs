@@ -0,0 +1,52 @@
+use std::sync::Arc;
+
+use super::connection::PgTransaction;
+use super::error::RepositoryError;
+use super::factory::Factory;
+use super::root::Repository;
+use crate::bootstrap::postgres::Postgres;
+
+/// Starts transactions for a storage backend.
+pub trait UnitOfWork: Send + Sync {
+    fn begin(&self) -> Result<Box<dyn Transaction>, RepositoryError>;
+}
+
+/// An open transaction. Repositories handed out by [`Transaction::repository`]
+/// see its uncommitted writes; nobody else does until [`Transaction::commit`].
+pub trait Transaction {
+    fn repository(&self) -> Repository;
+
+    fn commit(self: Box<Self>) -> Result<(), RepositoryError>;
+
+    fn rollback(self: Box<Self>) -> Result<(), RepositoryError>;
+}
+
+pub(super) struct PgUnitOfWork {
+    postgres: Arc<Postgres>,
+}
+
+impl PgUnitOfWork {
+    pub(super) fn new(postgres: Arc<Postgres>) -> Self {
+        Self { postgres }
+    }
+}
+
+impl UnitOfWork for PgUnitOfWork {
+    fn begin(&self) -> Result<Box<dyn Transaction>, RepositoryError> {
+        Ok(Box::new(PgTransaction::begin(&self.postgres)?))
+    }
+}
+
+impl Transaction for PgTransaction {
+    fn repository(&self) -> Repository {
+        Factory::new(self.source()).repository(None)
+    }
+
+    fn commit(self: Box<Self>) -> Result<(), RepositoryError> {
+        PgTransaction::commit(*self)
+    }
+
+    fn rollback(self: Box<Self>) -> Result<(), RepositoryError> {
+        PgTransaction::rollback(*self)
+    }
+}
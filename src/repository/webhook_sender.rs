@@ -0,0 +1,39 @@
+pub mod http;
+
+use std::sync::Arc;
+
+use crate::config::webhooks::WebhookConfig;
+
+pub use http::HttpWebhookSender;
+
+/// What a webhook endpoint answered.
+#[derive(Debug, Clone)]
+pub struct WebhookResponse {
+    pub status: u16,
+    /// A short, sanitized start of the response body for the delivery log;
+    /// the body itself is never stored.
+    pub excerpt: String,
+}
+
+/// Posts event payloads to webhook URLs. Signing is up to the caller, so
+/// every sender delivers the same bytes. Calls block, so use-case code runs
+/// them on the blocking pool like repository calls.
+pub trait WebhookSender: Send + Sync {
+    /// Whether deliveries can be made to this URL, checked when a webhook is
+    /// created.
+    fn check_url(&self, url: &str) -> Result<(), String>;
+
+    /// POSTs the JSON `body` to `url`. Any answer is returned, whatever its
+    /// status; errors mean the endpoint could not be reached.
+    fn post(
+        &self,
+        url: &str,
+        headers: &[(&'static str, String)],
+        body: String,
+    ) -> Result<WebhookResponse, String>;
+}
+
+/// Opens the sender deliveries go through.
+pub fn open(config: &WebhookConfig) -> Arc<dyn WebhookSender> {
+    Arc::new(HttpWebhookSender::new(config))
+}
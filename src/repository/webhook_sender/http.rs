@@ -0,0 +1,112 @@
+use std::time::Duration;
+
+use http_body_util::{BodyExt, Full};
+use hyper::body::Bytes;
+use hyper::{Method, Request, Uri};
+
+use super::{WebhookResponse, WebhookSender};
+use crate::config::webhooks::WebhookConfig;
+use crate::repository::http_client::{self, Destinations, HttpClient};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_URL_LENGTH: usize = 2048;
+/// Characters of a response body kept for the delivery log.
+const MAX_EXCERPT_CHARS: usize = 100;
+/// Bytes of a response body read to make the excerpt; the rest is dropped.
+const MAX_READ_BYTES: usize = 4 * MAX_EXCERPT_CHARS;
+
+/// [`WebhookSender`] over HTTP and HTTPS.
+pub struct HttpWebhookSender {
+    client: HttpClient,
+    destinations: Destinations,
+}
+
+impl HttpWebhookSender {
+    pub fn new(config: &WebhookConfig) -> Self {
+        let destinations = if config.allow_private_urls {
+            Destinations::Any
+        } else {
+            Destinations::PublicOnly
+        };
+
+        Self {
+            client: http_client::client(destinations),
+            destinations,
+        }
+    }
+
+    fn parse_url(&self, url: &str) -> Result<Uri, String> {
+        if url.len() > MAX_URL_LENGTH {
+            return Err(format!(
+                "Webhook URL is limited to {} characters",
+                MAX_URL_LENGTH
+            ));
+        }
+
+        http_client::parse_url(url, self.destinations)
+    }
+}
+
+impl WebhookSender for HttpWebhookSender {
+    fn check_url(&self, url: &str) -> Result<(), String> {
+        self.parse_url(url).map(|_| ())
+    }
+
+    fn post(
+        &self,
+        url: &str,
+        headers: &[(&'static str, String)],
+        body: String,
+    ) -> Result<WebhookResponse, String> {
+        let uri = self.parse_url(url)?;
+
+        let mut request = Request::builder()
+            .method(Method::POST)
+            .uri(uri)
+            .header("content-type", "application/json");
+        for (name, value) in headers {
+            request = request.header(*name, value);
+        }
+        let request = request
+            .body(Full::new(Bytes::from(body)))
+            .map_err(|e| e.to_string())?;
+
+        http_client::block_on(async {
+            let response = tokio::time::timeout(REQUEST_TIMEOUT, self.client.request(request))
+                .await
+                .map_err(|_| "Webhook request timed out".to_string())?
+                .map_err(|e| e.to_string())?;
+
+            let status = response.status().as_u16();
+            let mut body = response.into_body();
+            let mut read = Vec::new();
+            while read.len() < MAX_READ_BYTES {
+                match body.frame().await {
+                    Some(frame) => {
+                        if let Ok(data) = frame.map_err(|e| e.to_string())?.into_data() {
+                            read.extend_from_slice(&data);
+                        }
+                    }
+                    None => break,
+                }
+            }
+
+            Ok(WebhookResponse {
+                status,
+                excerpt: excerpt(&read),
+            })
+        })?
+    }
+}
+
+/// The start of a response body as one line of printable text, so receivers
+/// cannot put arbitrary content into the delivery log.
+fn excerpt(body: &[u8]) -> String {
+    let text: String = String::from_utf8_lossy(body)
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .take(MAX_EXCERPT_CHARS)
+        .collect();
+
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
@@ -0,0 +1,10 @@
+pub mod memory;
+pub mod models;
+pub mod repo;
+
+pub use memory::InMemoryWebhookRepository;
+pub use models::{
+    NewWebhook, NewWebhookEvent, Webhook, WebhookDelivery, WebhookDeliveryStatus, WebhookEvent,
+    WebhookEventKind,
+};
+pub use repo::{WebhookRepo, WebhookRepository};
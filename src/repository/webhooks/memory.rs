@@ -0,0 +1,251 @@
+use std::sync::Arc;
+
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+
+use super::models::{
+    NewWebhook, NewWebhookEvent, Webhook, WebhookDelivery, WebhookDeliveryStatus, WebhookEvent,
+};
+use super::repo::WebhookRepo;
+use crate::repository::RepositoryError;
+use crate::repository::memory::{MemoryStore, ensure_chat, ensure_user, now, paginate};
+
+/// [`WebhookRepo`] over a [`MemoryStore`].
+#[derive(Clone)]
+pub struct InMemoryWebhookRepository {
+    store: Arc<MemoryStore>,
+}
+
+impl InMemoryWebhookRepository {
+    pub fn new(store: Arc<MemoryStore>) -> Self {
+        Self { store }
+    }
+}
+
+impl WebhookRepo for InMemoryWebhookRepository {
+    fn create_webhook(&self, webhook: NewWebhook) -> Result<Webhook, RepositoryError> {
+        let mut tables = self.store.write();
+        ensure_user(&tables, webhook.created_by)?;
+        if let Some(chat_id) = webhook.chat_id {
+            ensure_chat(&tables, chat_id)?;
+        }
+
+        let webhook = Webhook {
+            id: Uuid::new_v4(),
+            created_by: webhook.created_by,
+            chat_id: webhook.chat_id,
+            url: webhook.url,
+            secret: webhook.secret,
+            events: webhook.events,
+            created_at: now(),
+        };
+        tables.webhooks.push(webhook.clone());
+
+        Ok(webhook)
+    }
+
+    fn find_webhook(&self, id: Uuid) -> Result<Option<Webhook>, RepositoryError> {
+        let tables = self.store.read();
+        Ok(tables.webhooks.iter().find(|w| w.id == id).cloned())
+    }
+
+    fn list_webhooks(&self, created_by: Uuid) -> Result<Vec<Webhook>, RepositoryError> {
+        let tables = self.store.read();
+
+        let mut webhooks: Vec<Webhook> = tables
+            .webhooks
+            .iter()
+            .filter(|w| w.created_by == created_by)
+            .cloned()
+            .collect();
+        webhooks.sort_by(|a, b| a.created_at.cmp(&b.created_at).then(a.id.cmp(&b.id)));
+
+        Ok(webhooks)
+    }
+
+    fn delete_webhook(&self, id: Uuid) -> Result<(), RepositoryError> {
+        let mut tables = self.store.write();
+
+        if !tables.webhooks.iter().any(|w| w.id == id) {
+            return Err(RepositoryError::NotFound);
+        }
+
+        tables.webhooks.retain(|w| w.id != id);
+        tables.webhook_deliveries.retain(|d| d.webhook_id != id);
+
+        Ok(())
+    }
+
+    fn record_event(&self, event: NewWebhookEvent) -> Result<WebhookEvent, RepositoryError> {
+        let mut tables = self.store.write();
+
+        let event = WebhookEvent {
+            id: Uuid::new_v4(),
+            kind: event.kind,
+            chat_id: event.chat_id,
+            payload: event.payload,
+            created_at: now(),
+            dispatched_at: None,
+        };
+        tables.webhook_events.push(event.clone());
+
+        Ok(event)
+    }
+
+    fn find_event(&self, id: Uuid) -> Result<Option<WebhookEvent>, RepositoryError> {
+        let tables = self.store.read();
+        Ok(tables.webhook_events.iter().find(|e| e.id == id).cloned())
+    }
+
+    fn dispatch_events(&self, limit: i64) -> Result<usize, RepositoryError> {
+        let mut tables = self.store.write();
+
+        let mut events: Vec<WebhookEvent> = tables
+            .webhook_events
+            .iter()
+            .filter(|e| e.dispatched_at.is_none())
+            .cloned()
+            .collect();
+        events.sort_by_key(|e| e.created_at);
+        events.truncate(limit.max(0) as usize);
+
+        let now = now();
+        for event in &events {
+            let webhook_ids: Vec<Uuid> = tables
+                .webhooks
+                .iter()
+                .filter(|w| w.chat_id.is_none_or(|chat_id| chat_id == event.chat_id))
+                .filter(|w| w.events.contains(&event.kind))
+                .filter(|w| w.created_at <= event.created_at)
+                .map(|w| w.id)
+                .collect();
+
+            for webhook_id in webhook_ids {
+                if tables
+                    .webhook_deliveries
+                    .iter()
+                    .any(|d| d.webhook_id == webhook_id && d.event_id == event.id)
+                {
+                    continue;
+                }
+
+                tables.webhook_deliveries.push(WebhookDelivery {
+                    id: Uuid::new_v4(),
+                    webhook_id,
+                    event_id: event.id,
+                    status: WebhookDeliveryStatus::Pending.as_str().to_string(),
+                    attempts: 0,
+                    next_attempt_at: now,
+                    response_status: None,
+                    last_error: None,
+                    created_at: now,
+                    completed_at: None,
+                });
+            }
+
+            if let Some(stored) = tables.webhook_events.iter_mut().find(|e| e.id == event.id) {
+                stored.dispatched_at = Some(now);
+            }
+        }
+
+        Ok(events.len())
+    }
+
+    fn claim_due_deliveries(
+        &self,
+        now: NaiveDateTime,
+        lease_until: NaiveDateTime,
+        limit: i64,
+    ) -> Result<Vec<WebhookDelivery>, RepositoryError> {
+        let mut tables = self.store.write();
+
+        let mut due: Vec<&mut WebhookDelivery> = tables
+            .webhook_deliveries
+            .iter_mut()
+            .filter(|d| d.status == WebhookDeliveryStatus::Pending.as_str())
+            .filter(|d| d.next_attempt_at <= now)
+            .collect();
+        due.sort_by_key(|d| d.next_attempt_at);
+
+        Ok(due
+            .into_iter()
+            .take(limit.max(0) as usize)
+            .map(|delivery| {
+                delivery.next_attempt_at = lease_until;
+                delivery.clone()
+            })
+            .collect())
+    }
+
+    fn reschedule_delivery(
+        &self,
+        id: Uuid,
+        next_attempt_at: NaiveDateTime,
+        response_status: Option<i32>,
+        error: &str,
+    ) -> Result<WebhookDelivery, RepositoryError> {
+        let mut tables = self.store.write();
+
+        let delivery = tables
+            .webhook_deliveries
+            .iter_mut()
+            .find(|d| d.id == id)
+            .ok_or(RepositoryError::NotFound)?;
+        delivery.attempts += 1;
+        delivery.next_attempt_at = next_attempt_at;
+        delivery.response_status = response_status;
+        delivery.last_error = Some(error.to_string());
+
+        Ok(delivery.clone())
+    }
+
+    fn complete_delivery(
+        &self,
+        id: Uuid,
+        status: WebhookDeliveryStatus,
+        response_status: Option<i32>,
+        error: Option<&str>,
+    ) -> Result<WebhookDelivery, RepositoryError> {
+        let mut tables = self.store.write();
+
+        let delivery = tables
+            .webhook_deliveries
+            .iter_mut()
+            .find(|d| d.id == id)
+            .ok_or(RepositoryError::NotFound)?;
+        delivery.status = status.as_str().to_string();
+        delivery.attempts += 1;
+        delivery.response_status = response_status;
+        delivery.last_error = error.map(str::to_string);
+        delivery.completed_at = Some(now());
+
+        Ok(delivery.clone())
+    }
+
+    fn list_deliveries(
+        &self,
+        webhook_id: Uuid,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<(WebhookDelivery, WebhookEvent)>, RepositoryError> {
+        let tables = self.store.read();
+
+        let mut deliveries: Vec<(WebhookDelivery, WebhookEvent)> = tables
+            .webhook_deliveries
+            .iter()
+            .filter(|d| d.webhook_id == webhook_id)
+            .filter_map(|d| {
+                let event = tables.webhook_events.iter().find(|e| e.id == d.event_id)?;
+                Some((d.clone(), event.clone()))
+            })
+            .collect();
+        deliveries.sort_by(|(a, a_event), (b, b_event)| {
+            b.created_at
+                .cmp(&a.created_at)
+                .then(b_event.created_at.cmp(&a_event.created_at))
+                .then(a.id.cmp(&b.id))
+        });
+
+        Ok(paginate(deliveries.into_iter(), limit, offset))
+    }
+}
@@ -0,0 +1,114 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use uuid::Uuid;
+
+use crate::schema::{webhook_deliveries, webhook_events, webhooks};
+
+/// An external system subscribed to the events of one chat, or of every
+/// chat when `chat_id` is `None`.
+#[derive(Debug, Clone, Queryable, Selectable, Identifiable)]
+#[diesel(table_name = webhooks)]
+pub struct Webhook {
+    pub id: Uuid,
+    pub created_by: Uuid,
+    pub chat_id: Option<Uuid>,
+    pub url: String,
+    /// Key of the HMAC every delivery is signed with.
+    pub secret: String,
+    /// [`WebhookEventKind`] names.
+    pub events: Vec<String>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = webhooks)]
+pub struct NewWebhook {
+    pub created_by: Uuid,
+    pub chat_id: Option<Uuid>,
+    pub url: String,
+    pub secret: String,
+    pub events: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookEventKind {
+    MessageCreated,
+    MemberJoined,
+    ChatRenamed,
+}
+
+impl WebhookEventKind {
+    pub const ALL: [Self; 3] = [Self::MessageCreated, Self::MemberJoined, Self::ChatRenamed];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::MessageCreated => "message.created",
+            Self::MemberJoined => "member.joined",
+            Self::ChatRenamed => "chat.renamed",
+        }
+    }
+
+    pub fn parse(kind: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|k| k.as_str() == kind)
+    }
+}
+
+/// An entry of the outbox.
+#[derive(Debug, Clone, Queryable, Selectable, Identifiable)]
+#[diesel(table_name = webhook_events)]
+pub struct WebhookEvent {
+    pub id: Uuid,
+    /// A [`WebhookEventKind`] name.
+    pub kind: String,
+    pub chat_id: Uuid,
+    /// JSON describing the event.
+    pub payload: String,
+    pub created_at: NaiveDateTime,
+    /// When the event was fanned out to the webhooks subscribed to it.
+    pub dispatched_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = webhook_events)]
+pub struct NewWebhookEvent {
+    pub kind: String,
+    pub chat_id: Uuid,
+    pub payload: String,
+}
+
+/// One event sent to one webhook: pending until it is delivered or the
+/// attempts run out.
+#[derive(Debug, Clone, Queryable, Selectable, Identifiable)]
+#[diesel(table_name = webhook_deliveries)]
+pub struct WebhookDelivery {
+    pub id: Uuid,
+    pub webhook_id: Uuid,
+    pub event_id: Uuid,
+    /// A [`WebhookDeliveryStatus`] name.
+    pub status: String,
+    /// Attempts made so far.
+    pub attempts: i32,
+    pub next_attempt_at: NaiveDateTime,
+    /// HTTP status of the latest attempt, if the endpoint answered.
+    pub response_status: Option<i32>,
+    pub last_error: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub completed_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookDeliveryStatus {
+    Pending,
+    Delivered,
+    Failed,
+}
+
+impl WebhookDeliveryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Delivered => "delivered",
+            Self::Failed => "failed",
+        }
+    }
+}
@@ -0,0 +1,298 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use uuid::Uuid;
+
+use super::models::{
+    NewWebhook, NewWebhookEvent, Webhook, WebhookDelivery, WebhookDeliveryStatus, WebhookEvent,
+};
+use crate::repository::RepositoryError;
+use crate::repository::connection::PgSource;
+use crate::schema::{webhook_deliveries, webhook_events, webhooks};
+
+pub trait WebhookRepo: Send + Sync {
+    fn create_webhook(&self, webhook: NewWebhook) -> Result<Webhook, RepositoryError>;
+
+    fn find_webhook(&self, id: Uuid) -> Result<Option<Webhook>, RepositoryError>;
+
+    /// Webhooks `created_by` set up, oldest first.
+    fn list_webhooks(&self, created_by: Uuid) -> Result<Vec<Webhook>, RepositoryError>;
+
+    /// Removes the webhook along with its deliveries.
+    fn delete_webhook(&self, id: Uuid) -> Result<(), RepositoryError>;
+
+    /// Adds an event to the outbox. Call it on the repository of the
+    /// transaction making the change, so the event commits with it.
+    fn record_event(&self, event: NewWebhookEvent) -> Result<WebhookEvent, RepositoryError>;
+
+    fn find_event(&self, id: Uuid) -> Result<Option<WebhookEvent>, RepositoryError>;
+
+    /// Takes up to `limit` undispatched events, oldest first, creates a
+    /// pending delivery for every webhook that was subscribed to each when
+    /// it happened, and marks them dispatched. Returns how many events were
+    /// dispatched.
+    fn dispatch_events(&self, limit: i64) -> Result<usize, RepositoryError>;
+
+    /// Claims up to `limit` pending deliveries due at `now` by moving their
+    /// next attempt to `lease_until`, skipping rows other transactions hold.
+    fn claim_due_deliveries(
+        &self,
+        now: NaiveDateTime,
+        lease_until: NaiveDateTime,
+        limit: i64,
+    ) -> Result<Vec<WebhookDelivery>, RepositoryError>;
+
+    /// Records a failed attempt, with the endpoint's status if it answered,
+    /// and when the delivery is due again.
+    fn reschedule_delivery(
+        &self,
+        id: Uuid,
+        next_attempt_at: NaiveDateTime,
+        response_status: Option<i32>,
+        error: &str,
+    ) -> Result<WebhookDelivery, RepositoryError>;
+
+    /// Counts the final attempt and settles the delivery as `status`.
+    fn complete_delivery(
+        &self,
+        id: Uuid,
+        status: WebhookDeliveryStatus,
+        response_status: Option<i32>,
+        error: Option<&str>,
+    ) -> Result<WebhookDelivery, RepositoryError>;
+
+    /// Deliveries to the webhook with their events, most recent first.
+    fn list_deliveries(
+        &self,
+        webhook_id: Uuid,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<(WebhookDelivery, WebhookEvent)>, RepositoryError>;
+}
+
+#[derive(Clone)]
+pub struct WebhookRepository {
+    db: PgSource,
+}
+
+impl WebhookRepository {
+    pub fn new(db: PgSource) -> Self {
+        Self { db }
+    }
+}
+
+impl WebhookRepo for WebhookRepository {
+    #[tracing::instrument(skip(self, webhook), fields(created_by = %webhook.created_by))]
+    fn create_webhook(&self, webhook: NewWebhook) -> Result<Webhook, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        diesel::insert_into(webhooks::table)
+            .values(&webhook)
+            .returning(Webhook::as_returning())
+            .get_result(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    fn find_webhook(&self, id: Uuid) -> Result<Option<Webhook>, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        webhooks::table
+            .find(id)
+            .select(Webhook::as_select())
+            .first(&mut *conn)
+            .optional()
+            .map_err(RepositoryError::from)
+    }
+
+    fn list_webhooks(&self, created_by: Uuid) -> Result<Vec<Webhook>, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        webhooks::table
+            .filter(webhooks::created_by.eq(created_by))
+            .order((webhooks::created_at.asc(), webhooks::id.asc()))
+            .select(Webhook::as_select())
+            .load(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    #[tracing::instrument(skip(self))]
+    fn delete_webhook(&self, id: Uuid) -> Result<(), RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        let deleted = diesel::delete(webhooks::table.find(id)).execute(&mut *conn)?;
+
+        if deleted == 0 {
+            return Err(RepositoryError::NotFound);
+        }
+
+        Ok(())
+    }
+
+    fn record_event(&self, event: NewWebhookEvent) -> Result<WebhookEvent, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        diesel::insert_into(webhook_events::table)
+            .values(&event)
+            .returning(WebhookEvent::as_returning())
+            .get_result(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+
+    fn find_event(&self, id: Uuid) -> Result<Option<WebhookEvent>, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        webhook_events::table
+            .find(id)
+            .select(WebhookEvent::as_select())
+            .first(&mut *conn)
+            .optional()
+            .map_err(RepositoryError::from)
+    }
+
+    fn dispatch_events(&self, limit: i64) -> Result<usize, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        conn.transaction(|conn| {
+            let events: Vec<WebhookEvent> = webhook_events::table
+                .filter(webhook_events::dispatched_at.is_null())
+                .order(webhook_events::created_at.asc())
+                .limit(limit)
+                .select(WebhookEvent::as_select())
+                .for_update()
+                .skip_locked()
+                .load(conn)?;
+
+            for event in &events {
+                let webhook_ids: Vec<Uuid> = webhooks::table
+                    .filter(
+                        webhooks::chat_id
+                            .eq(event.chat_id)
+                            .or(webhooks::chat_id.is_null()),
+                    )
+                    .filter(webhooks::events.contains(vec![event.kind.clone()]))
+                    .filter(webhooks::created_at.le(event.created_at))
+                    .select(webhooks::id)
+                    .load(conn)?;
+
+                let deliveries: Vec<_> = webhook_ids
+                    .into_iter()
+                    .map(|webhook_id| {
+                        (
+                            webhook_deliveries::webhook_id.eq(webhook_id),
+                            webhook_deliveries::event_id.eq(event.id),
+                        )
+                    })
+                    .collect();
+
+                if !deliveries.is_empty() {
+                    diesel::insert_into(webhook_deliveries::table)
+                        .values(&deliveries)
+                        .on_conflict_do_nothing()
+                        .execute(conn)?;
+                }
+            }
+
+            let ids: Vec<Uuid> = events.iter().map(|e| e.id).collect();
+            diesel::update(webhook_events::table.filter(webhook_events::id.eq_any(&ids)))
+                .set(webhook_events::dispatched_at.eq(diesel::dsl::now))
+                .execute(conn)?;
+
+            Ok::<_, RepositoryError>(events.len())
+        })
+    }
+
+    fn claim_due_deliveries(
+        &self,
+        now: NaiveDateTime,
+        lease_until: NaiveDateTime,
+        limit: i64,
+    ) -> Result<Vec<WebhookDelivery>, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        conn.transaction(|conn| {
+            let ids: Vec<Uuid> = webhook_deliveries::table
+                .filter(webhook_deliveries::status.eq(WebhookDeliveryStatus::Pending.as_str()))
+                .filter(webhook_deliveries::next_attempt_at.le(now))
+                .order(webhook_deliveries::next_attempt_at.asc())
+                .limit(limit)
+                .select(webhook_deliveries::id)
+                .for_update()
+                .skip_locked()
+                .load(conn)?;
+
+            diesel::update(webhook_deliveries::table.filter(webhook_deliveries::id.eq_any(&ids)))
+                .set(webhook_deliveries::next_attempt_at.eq(lease_until))
+                .returning(WebhookDelivery::as_returning())
+                .get_results(conn)
+        })
+        .map_err(RepositoryError::from)
+    }
+
+    fn reschedule_delivery(
+        &self,
+        id: Uuid,
+        next_attempt_at: NaiveDateTime,
+        response_status: Option<i32>,
+        error: &str,
+    ) -> Result<WebhookDelivery, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        diesel::update(webhook_deliveries::table.find(id))
+            .set((
+                webhook_deliveries::attempts.eq(webhook_deliveries::attempts + 1),
+                webhook_deliveries::next_attempt_at.eq(next_attempt_at),
+                webhook_deliveries::response_status.eq(response_status),
+                webhook_deliveries::last_error.eq(error),
+            ))
+            .returning(WebhookDelivery::as_returning())
+            .get_result(&mut *conn)
+            .optional()?
+            .ok_or(RepositoryError::NotFound)
+    }
+
+    #[tracing::instrument(skip(self, error))]
+    fn complete_delivery(
+        &self,
+        id: Uuid,
+        status: WebhookDeliveryStatus,
+        response_status: Option<i32>,
+        error: Option<&str>,
+    ) -> Result<WebhookDelivery, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        diesel::update(webhook_deliveries::table.find(id))
+            .set((
+                webhook_deliveries::status.eq(status.as_str()),
+                webhook_deliveries::attempts.eq(webhook_deliveries::attempts + 1),
+                webhook_deliveries::response_status.eq(response_status),
+                webhook_deliveries::last_error.eq(error),
+                webhook_deliveries::completed_at.eq(diesel::dsl::now),
+            ))
+            .returning(WebhookDelivery::as_returning())
+            .get_result(&mut *conn)
+            .optional()?
+            .ok_or(RepositoryError::NotFound)
+    }
+
+    fn list_deliveries(
+        &self,
+        webhook_id: Uuid,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<(WebhookDelivery, WebhookEvent)>, RepositoryError> {
+        let mut conn = self.db.conn()?;
+
+        webhook_deliveries::table
+            .inner_join(webhook_events::table)
+            .filter(webhook_deliveries::webhook_id.eq(webhook_id))
+            .order((
+                webhook_deliveries::created_at.desc(),
+                webhook_events::created_at.desc(),
+                webhook_deliveries::id.asc(),
+            ))
+            .limit(limit)
+            .offset(offset)
+            .select((WebhookDelivery::as_select(), WebhookEvent::as_select()))
+            .load(&mut *conn)
+            .map_err(RepositoryError::from)
+    }
+}
@@ -1,5 +1,24 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    attachment_chunks (attachment_id, chunk_offset) {
+        attachment_id -> Uuid,
+        chunk_offset -> Int8,
+        size -> Int8,
+    }
+}
+
+diesel::table! {
+    attachments (id) {
+        id -> Uuid,
+        uploader_id -> Uuid,
+        size -> Int8,
+        uploaded_size -> Int8,
+        completed_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     auth_users (id) {
         id -> Uuid,
@@ -11,6 +30,7 @@ diesel::table! {
         is_active -> Bool,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        token_version -> Int4,
     }
 }
 
@@ -22,6 +42,21 @@ diesel::table! {
         created_by -> Uuid,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        epoch -> Int4,
+        message_ttl_seconds -> Nullable<Int4>,
+        allow_forwarding -> Bool,
+        allow_polls -> Bool,
+    }
+}
+
+diesel::table! {
+    chat_rekeys (chat_id, epoch) {
+        chat_id -> Uuid,
+        epoch -> Int4,
+        #[max_length = 20]
+        reason -> Varchar,
+        user_id -> Nullable<Uuid>,
+        created_at -> Timestamp,
     }
 }
 
@@ -32,6 +67,77 @@ diesel::table! {
         user_id -> Uuid,
         invited_by -> Nullable<Uuid>,
         joined_at -> Timestamp,
+        last_read_at -> Timestamp,
+        muted -> Bool,
+    }
+}
+
+diesel::table! {
+    devices (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        #[max_length = 100]
+        name -> Varchar,
+        created_at -> Timestamp,
+        last_seen_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    identity_keys (device_id) {
+        user_id -> Uuid,
+        identity_key -> Text,
+        signed_prekey_id -> Int4,
+        signed_prekey -> Text,
+        signed_prekey_signature -> Text,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        device_id -> Uuid,
+    }
+}
+
+diesel::table! {
+    key_log_entries (leaf_index) {
+        leaf_index -> Int8,
+        user_id -> Uuid,
+        device_id -> Uuid,
+        identity_key -> Text,
+        timestamp_ms -> Int8,
+        leaf_hash -> Bytea,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    key_log_heads (tree_size) {
+        tree_size -> Int8,
+        timestamp_ms -> Int8,
+        root_hash -> Bytea,
+        signature -> Bytea,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    key_log_nodes (level, node_index) {
+        level -> Int2,
+        node_index -> Int8,
+        hash -> Bytea,
+    }
+}
+
+diesel::table! {
+    message_envelopes (message_id, device_id) {
+        message_id -> Uuid,
+        device_id -> Uuid,
+        ciphertext -> Text,
+    }
+}
+
+diesel::table! {
+    message_attachments (message_id, attachment_id) {
+        message_id -> Uuid,
+        attachment_id -> Uuid,
     }
 }
 
@@ -40,11 +146,116 @@ diesel::table! {
         id -> Uuid,
         chat_id -> Uuid,
         sender_id -> Uuid,
-        encrypted_content -> Text,
+        encrypted_content -> Nullable<Text>,
+        created_at -> Timestamp,
+        sender_device_id -> Nullable<Uuid>,
+        epoch -> Nullable<Int4>,
+        #[max_length = 32]
+        expires_at -> Nullable<Timestamp>,
+        view_once -> Bool,
+        kind -> Varchar,
+        payload -> Nullable<Text>,
+        mentions -> Array<Uuid>,
+        mention_all -> Bool,
+        forwarded_from_chat_id -> Nullable<Uuid>,
+        forwarded_from_message_id -> Nullable<Uuid>,
+        forwarded_from_sender_id -> Nullable<Uuid>,
+    }
+}
+
+diesel::table! {
+    one_time_prekeys (id) {
+        id -> Int8,
+        key_id -> Int4,
+        public_key -> Text,
+        created_at -> Timestamp,
+        device_id -> Uuid,
+    }
+}
+
+diesel::table! {
+    permissions (id) {
+        id -> Int4,
+        #[max_length = 100]
+        name -> Varchar,
+        #[max_length = 255]
+        description -> Nullable<Varchar>,
         created_at -> Timestamp,
     }
 }
 
+diesel::table! {
+    pinned_messages (message_id) {
+        message_id -> Uuid,
+        chat_id -> Uuid,
+        pinned_by -> Uuid,
+        pinned_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    poll_votes (message_id, user_id, option_index) {
+        message_id -> Uuid,
+        user_id -> Uuid,
+        option_index -> Int4,
+        voted_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    polls (message_id) {
+        message_id -> Uuid,
+        question -> Text,
+        options -> Array<Text>,
+        multiple_choice -> Bool,
+        anonymous -> Bool,
+        closes_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    push_dead_letters (id) {
+        id -> Uuid,
+        device_id -> Uuid,
+        #[max_length = 16]
+        kind -> Varchar,
+        url -> Text,
+        attempts -> Int4,
+        error -> Text,
+        created_at -> Timestamp,
+        failed_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    push_endpoints (device_id) {
+        device_id -> Uuid,
+        #[max_length = 16]
+        kind -> Varchar,
+        url -> Text,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    push_jobs (id) {
+        id -> Uuid,
+        device_id -> Uuid,
+        attempts -> Int4,
+        next_attempt_at -> Timestamp,
+        last_error -> Nullable<Text>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    role_permissions (role_id, permission_id) {
+        role_id -> Int4,
+        permission_id -> Int4,
+    }
+}
+
 diesel::table! {
     roles (id) {
         id -> Int4,
@@ -56,9 +267,145 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    scheduled_messages (id) {
+        id -> Uuid,
+        chat_id -> Uuid,
+        sender_id -> Uuid,
+        sender_device_id -> Uuid,
+        send_at -> Timestamp,
+        envelopes -> Nullable<Text>,
+        epoch -> Nullable<Int4>,
+        ciphertext -> Nullable<Text>,
+        attachment_ids -> Array<Uuid>,
+        view_once -> Bool,
+        #[max_length = 16]
+        status -> Varchar,
+        error -> Nullable<Text>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        mentions -> Array<Uuid>,
+        mention_all -> Bool,
+    }
+}
+
+diesel::table! {
+    sender_keys (id) {
+        id -> Int8,
+        chat_id -> Uuid,
+        epoch -> Int4,
+        sender_id -> Uuid,
+        sender_device_id -> Uuid,
+        recipient_device_id -> Uuid,
+        ciphertext -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    webhook_deliveries (id) {
+        id -> Uuid,
+        webhook_id -> Uuid,
+        event_id -> Uuid,
+        #[max_length = 16]
+        status -> Varchar,
+        attempts -> Int4,
+        next_attempt_at -> Timestamp,
+        response_status -> Nullable<Int4>,
+        last_error -> Nullable<Text>,
+        created_at -> Timestamp,
+        completed_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    webhook_events (id) {
+        id -> Uuid,
+        #[max_length = 32]
+        kind -> Varchar,
+        chat_id -> Uuid,
+        payload -> Text,
+        created_at -> Timestamp,
+        dispatched_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    webhooks (id) {
+        id -> Uuid,
+        created_by -> Uuid,
+        chat_id -> Nullable<Uuid>,
+        url -> Text,
+        secret -> Text,
+        events -> Array<Text>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::joinable!(attachment_chunks -> attachments (attachment_id));
+diesel::joinable!(attachments -> auth_users (uploader_id));
 diesel::joinable!(auth_users -> roles (role_id));
 diesel::joinable!(chats -> auth_users (created_by));
 diesel::joinable!(chat_members -> chats (chat_id));
+diesel::joinable!(chat_rekeys -> auth_users (user_id));
+diesel::joinable!(chat_rekeys -> chats (chat_id));
+diesel::joinable!(devices -> auth_users (user_id));
+diesel::joinable!(identity_keys -> auth_users (user_id));
+diesel::joinable!(identity_keys -> devices (device_id));
+diesel::joinable!(message_attachments -> attachments (attachment_id));
+diesel::joinable!(message_attachments -> messages (message_id));
+diesel::joinable!(message_envelopes -> devices (device_id));
+diesel::joinable!(message_envelopes -> messages (message_id));
 diesel::joinable!(messages -> chats (chat_id));
+diesel::joinable!(one_time_prekeys -> devices (device_id));
+diesel::joinable!(pinned_messages -> auth_users (pinned_by));
+diesel::joinable!(pinned_messages -> chats (chat_id));
+diesel::joinable!(pinned_messages -> messages (message_id));
+diesel::joinable!(poll_votes -> auth_users (user_id));
+diesel::joinable!(poll_votes -> polls (message_id));
+diesel::joinable!(polls -> messages (message_id));
+diesel::joinable!(push_endpoints -> devices (device_id));
+diesel::joinable!(push_jobs -> push_endpoints (device_id));
+diesel::joinable!(role_permissions -> permissions (permission_id));
+diesel::joinable!(role_permissions -> roles (role_id));
+diesel::joinable!(scheduled_messages -> auth_users (sender_id));
+diesel::joinable!(scheduled_messages -> chats (chat_id));
+diesel::joinable!(scheduled_messages -> devices (sender_device_id));
+diesel::joinable!(sender_keys -> auth_users (sender_id));
+diesel::joinable!(sender_keys -> chats (chat_id));
+diesel::joinable!(webhook_deliveries -> webhook_events (event_id));
+diesel::joinable!(webhook_deliveries -> webhooks (webhook_id));
+diesel::joinable!(webhooks -> auth_users (created_by));
+diesel::joinable!(webhooks -> chats (chat_id));
 
-diesel::allow_tables_to_appear_in_same_query!(auth_users, chats, chat_members, messages, roles,);
+diesel::allow_tables_to_appear_in_same_query!(
+    attachment_chunks,
+    attachments,
+    auth_users,
+    chats,
+    chat_members,
+    chat_rekeys,
+    devices,
+    identity_keys,
+    key_log_entries,
+    key_log_heads,
+    key_log_nodes,
+    message_attachments,
+    message_envelopes,
+    messages,
+    one_time_prekeys,
+    permissions,
+    pinned_messages,
+    poll_votes,
+    polls,
+    push_dead_letters,
+    push_endpoints,
+    push_jobs,
+    role_permissions,
+    roles,
+    scheduled_messages,
+    sender_keys,
+    webhook_deliveries,
+    webhook_events,
+    webhooks,
+);
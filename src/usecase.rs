@@ -1,8 +1,29 @@
+pub mod attachments;
 pub mod auth;
+mod backoff;
+mod blocking;
 pub mod chat;
+pub mod devices;
 mod factory;
+pub mod keys;
+pub mod push;
 mod root;
+pub mod webhooks;
 
-pub use auth::{AuthError, AuthResponse, AuthService, UserInfo};
-pub use chat::{ChatError, ChatInfo, ChatMemberInfo, ChatService, MessageInfo};
-pub use root::Service;
+pub use attachments::{AttachmentDownload, AttachmentError, AttachmentInfo, AttachmentService};
+pub use auth::{AuthError, AuthResponse, AuthService, Principal, RoleInfo, UserInfo, UserPage};
+pub use chat::{
+    ChatError, ChatInfo, ChatMemberInfo, ChatService, ForwardedFrom, MentionInfo, MessageBody,
+    MessageInfo, MessageOptions, PinInfo, PollDraft, PollInfo, PollOptionInfo, ReadStateInfo,
+    RekeyInfo, ScheduledDelivery, ScheduledMessageInfo, SenderKeyBundle, SenderKeyInfo,
+};
+pub use devices::{DeviceError, DeviceInfo, DeviceService};
+pub use keys::{
+    ConsistencyProof, DeviceBundle, InclusionProof, KeyError, KeyInclusionProofs, KeyLogEntryInfo,
+    KeyService, OneTimePrekeyInfo, PrekeyBundle, PrekeyStatus, SignedPrekeyInfo, TreeHeadInfo,
+};
+pub use push::{PushDispatch, PushEndpointInfo, PushError, PushService};
+pub use root::{Service, ServiceConfig};
+pub use webhooks::{
+    WebhookDeliveryInfo, WebhookDispatch, WebhookError, WebhookInfo, WebhookService,
+};
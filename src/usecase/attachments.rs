@@ -0,0 +1,5 @@
+pub mod error;
+pub mod service;
+
+pub use error::AttachmentError;
+pub use service::{AttachmentDownload, AttachmentInfo, AttachmentService};
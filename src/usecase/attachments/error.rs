@@ -0,0 +1,69 @@
+use thiserror::Error;
+
+use crate::repository::RepositoryError;
+use crate::repository::blobs::BlobError;
+
+#[derive(Debug, Error)]
+pub enum AttachmentError {
+    #[error("Invalid attachment size: {0}")]
+    InvalidSize(String),
+
+    #[error("Attachment too large: at most {0} bytes")]
+    TooLarge(i64),
+
+    #[error("Attachment storage quota exceeded: at most {0} bytes per user")]
+    QuotaExceeded(i64),
+
+    #[error("Chunk too large: at most {0} bytes per request")]
+    ChunkTooLarge(usize),
+
+    #[error("Invalid chunk: {0}")]
+    InvalidChunk(String),
+
+    #[error("Attachment not found")]
+    NotFound,
+
+    #[error("Upload offset mismatch: expected {expected}")]
+    OffsetMismatch { expected: i64 },
+
+    #[error("Attachment upload is not complete")]
+    Incomplete,
+
+    #[error("Service temporarily unavailable")]
+    Unavailable,
+
+    #[error("Internal error: {0}")]
+    Internal(String),
+}
+
+impl From<tokio::task::JoinError> for AttachmentError {
+    fn from(err: tokio::task::JoinError) -> Self {
+        Self::Internal(err.to_string())
+    }
+}
+
+impl From<RepositoryError> for AttachmentError {
+    fn from(err: RepositoryError) -> Self {
+        match err {
+            RepositoryError::Unavailable(cause) => {
+                tracing::error!("Database unavailable: {}", cause);
+                Self::Unavailable
+            }
+            err => Self::Internal(err.to_string()),
+        }
+    }
+}
+
+impl From<BlobError> for AttachmentError {
+    fn from(err: BlobError) -> Self {
+        match err {
+            BlobError::Unavailable(cause) => {
+                tracing::error!("Blob storage unavailable: {}", cause);
+                Self::Unavailable
+            }
+            // Metadata says the chunk exists, so a missing blob is our fault.
+            BlobError::NotFound => Self::Internal("Attachment blob missing".to_string()),
+            BlobError::Internal(cause) => Self::Internal(cause),
+        }
+    }
+}
@@ -0,0 +1,373 @@
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use super::error::AttachmentError;
+use crate::config::attachments::AttachmentConfig;
+use crate::repository::Repository;
+use crate::repository::attachments::{Attachment, AttachmentChunk, NewAttachment};
+use crate::repository::blobs::BlobStore;
+use crate::usecase::blocking::run_blocking;
+
+/// Chunks read ahead of a download's client.
+const DOWNLOAD_BUFFERED_CHUNKS: usize = 2;
+
+/// Attachment uploads and downloads. Clients encrypt files before upload, so
+/// the service only sees opaque bytes; the key travels inside the (also
+/// encrypted) message that references the attachment.
+///
+/// Uploads are either a single request or resumable: create the upload with
+/// its total size, then send chunks in order at the current offset.
+#[derive(Clone)]
+pub struct AttachmentService {
+    repo: Repository,
+    blobs: Arc<dyn BlobStore>,
+    max_size: i64,
+    max_chunk_size: usize,
+    user_quota: i64,
+    stale_after: Duration,
+}
+
+/// An attachment being read from blob storage one chunk at a time.
+pub struct AttachmentDownload {
+    pub size: i64,
+    /// The chunks in order. An error ends the download part way through.
+    pub chunks: mpsc::Receiver<Result<Vec<u8>, AttachmentError>>,
+}
+
+pub struct AttachmentInfo {
+    pub id: Uuid,
+    pub uploader_id: Uuid,
+    pub size: i64,
+    pub uploaded_size: i64,
+    pub complete: bool,
+    pub created_at: String,
+}
+
+impl AttachmentService {
+    pub fn new(repo: Repository, config: &AttachmentConfig, blobs: Arc<dyn BlobStore>) -> Self {
+        Self {
+            repo,
+            blobs,
+            max_size: config.max_size,
+            max_chunk_size: config.max_chunk_size,
+            user_quota: config.user_quota,
+            stale_after: Duration::seconds(config.stale_upload_seconds),
+        }
+    }
+
+    pub fn max_size(&self) -> i64 {
+        self.max_size
+    }
+
+    /// Largest body accepted by a single upload or chunk request.
+    pub fn max_chunk_size(&self) -> usize {
+        self.max_chunk_size
+    }
+
+    /// Stores a whole attachment from one request.
+    #[tracing::instrument(skip(self, data), fields(size = data.len()))]
+    pub async fn upload(
+        &self,
+        uploader_id: Uuid,
+        data: Vec<u8>,
+    ) -> Result<AttachmentInfo, AttachmentError> {
+        self.check_chunk(&data)?;
+        self.check_size(data.len() as i64)?;
+
+        let this = self.clone();
+        run_blocking(move || {
+            this.repo.transaction(|tx| {
+                this.check_quota(tx, uploader_id, data.len() as i64)?;
+
+                let attachment = tx.attachments.create_attachment(NewAttachment {
+                    uploader_id,
+                    size: data.len() as i64,
+                })?;
+
+                this.store_chunk(tx, &attachment, &data)
+            })
+        })
+        .await
+    }
+
+    /// Starts a resumable upload of `size` bytes. The whole size counts
+    /// against the uploader's quota from the start.
+    #[tracing::instrument(skip(self))]
+    pub async fn create_upload(
+        &self,
+        uploader_id: Uuid,
+        size: i64,
+    ) -> Result<AttachmentInfo, AttachmentError> {
+        self.check_size(size)?;
+
+        let this = self.clone();
+        run_blocking(move || {
+            this.repo.transaction(|tx| {
+                this.check_quota(tx, uploader_id, size)?;
+
+                let attachment = tx
+                    .attachments
+                    .create_attachment(NewAttachment { uploader_id, size })?;
+
+                Ok(AttachmentInfo::from(attachment))
+            })
+        })
+        .await
+    }
+
+    /// Appends `data` to an upload. `offset` must equal the bytes received so
+    /// far, which lets a client resume after a dropped connection by asking
+    /// for the upload's progress first.
+    #[tracing::instrument(skip(self, data), fields(size = data.len()))]
+    pub async fn upload_chunk(
+        &self,
+        uploader_id: Uuid,
+        attachment_id: Uuid,
+        offset: i64,
+        data: Vec<u8>,
+    ) -> Result<AttachmentInfo, AttachmentError> {
+        self.check_chunk(&data)?;
+
+        let this = self.clone();
+        run_blocking(move || {
+            this.repo.transaction(|tx| {
+                let attachment = tx
+                    .attachments
+                    .lock_attachment(attachment_id)?
+                    .filter(|a| a.uploader_id == uploader_id)
+                    .ok_or(AttachmentError::NotFound)?;
+
+                if offset != attachment.uploaded_size {
+                    return Err(AttachmentError::OffsetMismatch {
+                        expected: attachment.uploaded_size,
+                    });
+                }
+
+                if offset + data.len() as i64 > attachment.size {
+                    return Err(AttachmentError::InvalidChunk(
+                        "Chunk extends past the attachment size".to_string(),
+                    ));
+                }
+
+                this.store_chunk(tx, &attachment, &data)
+            })
+        })
+        .await
+    }
+
+    /// Progress of one of the caller's uploads.
+    pub async fn get_upload(
+        &self,
+        uploader_id: Uuid,
+        attachment_id: Uuid,
+    ) -> Result<AttachmentInfo, AttachmentError> {
+        let this = self.clone();
+        run_blocking(move || {
+            let attachment = this
+                .repo
+                .attachments
+                .find_attachment(attachment_id)?
+                .filter(|a| a.uploader_id == uploader_id)
+                .ok_or(AttachmentError::NotFound)?;
+
+            Ok(AttachmentInfo::from(attachment))
+        })
+        .await
+    }
+
+    /// Starts reading the attachment. Only the uploader and members of a
+    /// chat where it was sent may read it; anyone else gets `NotFound`.
+    ///
+    /// Chunks are fetched as the receiver drains them, so at most a couple
+    /// are held in memory however large the attachment is.
+    #[tracing::instrument(skip(self))]
+    pub async fn download(
+        &self,
+        user_id: Uuid,
+        attachment_id: Uuid,
+    ) -> Result<AttachmentDownload, AttachmentError> {
+        let this = self.clone();
+        let (size, chunks) = run_blocking(move || {
+            let attachment = this
+                .repo
+                .attachments
+                .find_attachment(attachment_id)?
+                .ok_or(AttachmentError::NotFound)?;
+
+            if attachment.uploader_id != user_id
+                && !this
+                    .repo
+                    .attachments
+                    .is_shared_with(attachment_id, user_id)?
+            {
+                return Err(AttachmentError::NotFound);
+            }
+
+            if attachment.completed_at.is_none() {
+                return Err(AttachmentError::Incomplete);
+            }
+
+            let chunks = this.repo.attachments.get_chunks(attachment_id)?;
+            if chunks.iter().map(|c| c.size).sum::<i64>() != attachment.size {
+                return Err(AttachmentError::Internal(
+                    "Stored chunks do not add up to the attachment size".to_string(),
+                ));
+            }
+
+            Ok((attachment.size, chunks))
+        })
+        .await?;
+
+        let (tx, rx) = mpsc::channel(DOWNLOAD_BUFFERED_CHUNKS);
+        let blobs = self.blobs.clone();
+        let span = tracing::Span::current();
+        tokio::task::spawn_blocking(move || {
+            let _span = span.entered();
+
+            for chunk in chunks {
+                let data = blobs
+                    .get(&chunk.blob_key())
+                    .map_err(AttachmentError::from)
+                    .and_then(|data| {
+                        if data.len() as i64 == chunk.size {
+                            Ok(data)
+                        } else {
+                            Err(AttachmentError::Internal(format!(
+                                "Stored chunk at offset {} has the wrong size",
+                                chunk.chunk_offset
+                            )))
+                        }
+                    });
+                let failed = data.is_err();
+
+                // The client went away; stop reading.
+                if tx.blocking_send(data).is_err() || failed {
+                    break;
+                }
+            }
+        });
+
+        Ok(AttachmentDownload { size, chunks: rx })
+    }
+
+    /// Deletes uploads that no message references once they are older than
+    /// the configured age, whether they were never finished or never sent,
+    /// `batch_size` per pass. Returns how many went.
+    pub async fn sweep_stale_uploads(&self, batch_size: i64) -> Result<usize, AttachmentError> {
+        let this = self.clone();
+        run_blocking(move || {
+            let created_before = (Utc::now() - this.stale_after).naive_utc();
+            let mut total = 0;
+
+            loop {
+                let stale = this
+                    .repo
+                    .attachments
+                    .find_unattached(created_before, batch_size)?;
+                let chunks = this.repo.attachments.delete_unattached(&stale)?;
+                delete_blobs(this.blobs.as_ref(), chunks);
+                total += stale.len();
+
+                if (stale.len() as i64) < batch_size {
+                    return Ok(total);
+                }
+            }
+        })
+        .await
+    }
+
+    /// Writes the blob first so a failed write leaves no chunk row behind.
+    /// A retry at the same offset overwrites the same key.
+    fn store_chunk(
+        &self,
+        tx: &Repository,
+        attachment: &Attachment,
+        data: &[u8],
+    ) -> Result<AttachmentInfo, AttachmentError> {
+        let chunk = AttachmentChunk {
+            attachment_id: attachment.id,
+            chunk_offset: attachment.uploaded_size,
+            size: data.len() as i64,
+        };
+
+        self.blobs.put(&chunk.blob_key(), data)?;
+
+        let attachment = tx.attachments.add_chunk(chunk)?;
+
+        Ok(AttachmentInfo::from(attachment))
+    }
+
+    fn check_size(&self, size: i64) -> Result<(), AttachmentError> {
+        if size <= 0 {
+            return Err(AttachmentError::InvalidSize(
+                "Attachment cannot be empty".to_string(),
+            ));
+        }
+
+        if size > self.max_size {
+            return Err(AttachmentError::TooLarge(self.max_size));
+        }
+
+        Ok(())
+    }
+
+    fn check_quota(
+        &self,
+        tx: &Repository,
+        uploader_id: Uuid,
+        size: i64,
+    ) -> Result<(), AttachmentError> {
+        if tx.attachments.storage_used(uploader_id)? + size > self.user_quota {
+            return Err(AttachmentError::QuotaExceeded(self.user_quota));
+        }
+
+        Ok(())
+    }
+
+    fn check_chunk(&self, data: &[u8]) -> Result<(), AttachmentError> {
+        if data.is_empty() {
+            return Err(AttachmentError::InvalidChunk(
+                "Chunk cannot be empty".to_string(),
+            ));
+        }
+
+        if data.len() > self.max_chunk_size {
+            return Err(AttachmentError::ChunkTooLarge(self.max_chunk_size));
+        }
+
+        Ok(())
+    }
+}
+
+/// Removes the blobs of deleted chunks. Their rows are already gone, so a
+/// failure is only logged; the blob stays behind in the store.
+pub(crate) fn delete_blobs(blobs: &dyn BlobStore, chunks: Vec<AttachmentChunk>) {
+    for chunk in chunks {
+        if let Err(e) = blobs.delete(&chunk.blob_key()) {
+            tracing::warn!(
+                attachment_id = %chunk.attachment_id,
+                "Failed to delete attachment blob: {}",
+                e
+            );
+        }
+    }
+}
+
+impl From<Attachment> for AttachmentInfo {
+    fn from(attachment: Attachment) -> Self {
+        Self {
+            id: attachment.id,
+            uploader_id: attachment.uploader_id,
+            size: attachment.size,
+            uploaded_size: attachment.uploaded_size,
+            complete: attachment.completed_at.is_some(),
+            created_at: attachment
+                .created_at
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string(),
+        }
+    }
+}
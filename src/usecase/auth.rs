@@ -1,6 +1,12 @@
 pub mod error;
 pub mod jwt;
+pub mod password;
+pub mod permissions;
 pub mod service;
+pub mod throttle;
 
 pub use error::AuthError;
-pub use service::{AuthResponse, AuthService, UserInfo};
+pub use service::{
+    ADMIN_ROLE_ID, AuthResponse, AuthService, DEFAULT_ROLE_ID, Principal, RoleInfo, UserInfo,
+    UserPage,
+};
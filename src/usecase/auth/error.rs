@@ -1,5 +1,7 @@
 use thiserror::Error;
 
+use crate::repository::RepositoryError;
+
 #[derive(Error, Debug)]
 pub enum AuthError {
     #[error("Username already exists")]
@@ -14,18 +16,57 @@ pub enum AuthError {
     #[error("User not found")]
     UserNotFound,
 
+    #[error("Role not found")]
+    RoleNotFound,
+
+    #[error("Role already exists")]
+    RoleExists,
+
+    #[error("Invalid role name: {0}")]
+    InvalidRoleName(String),
+
+    #[error("Unknown permission: {0}")]
+    UnknownPermission(String),
+
+    #[error("Insufficient permissions")]
+    Forbidden,
+
     #[error("Invalid credentials")]
     InvalidCredentials,
 
     #[error("User is deactivated")]
     UserDeactivated,
 
+    #[error("Too many login attempts, retry after {retry_after} seconds")]
+    TooManyAttempts { retry_after: u64 },
+
     #[error("Token generation failed: {0}")]
     TokenGenerationFailed(String),
 
     #[error("Token validation failed: {0}")]
     TokenValidationFailed(String),
 
+    #[error("Service temporarily unavailable")]
+    Unavailable,
+
     #[error("Internal error: {0}")]
     Internal(String),
 }
+
+impl From<tokio::task::JoinError> for AuthError {
+    fn from(err: tokio::task::JoinError) -> Self {
+        Self::Internal(err.to_string())
+    }
+}
+
+impl From<RepositoryError> for AuthError {
+    fn from(err: RepositoryError) -> Self {
+        match err {
+            RepositoryError::Unavailable(cause) => {
+                tracing::error!("Database unavailable: {}", cause);
+                Self::Unavailable
+            }
+            err => Self::Internal(err.to_string()),
+        }
+    }
+}
@@ -1,67 +1,274 @@
-use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, encode};
+use std::collections::HashMap;
+
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use chrono::{DateTime, Utc};
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+    OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+};
+use jsonwebtoken::{
+    Algorithm, DecodingKey, EncodingKey, Header, Validation, decode, decode_header, encode,
+};
+use rsa::RsaPublicKey;
+use rsa::pkcs1::DecodeRsaPublicKey;
+use rsa::pkcs8::DecodePublicKey;
+use rsa::traits::PublicKeyParts;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use super::error::AuthError;
+use crate::config::jwt::{JwtAlgorithm, JwtConfig};
+
+/// DER prefix of an Ed25519 SubjectPublicKeyInfo; the raw key follows it.
+const ED25519_SPKI_PREFIX: [u8; 12] = [
+    0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00,
+];
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String,
     pub user_id: String,
     pub role_id: i32,
+    #[serde(default)]
+    pub ver: i32,
     pub exp: i64,
     pub iat: i64,
 }
 
+#[derive(Clone)]
+struct VerificationKey {
+    algorithm: Algorithm,
+    key: DecodingKey,
+}
+
+/// Verifies `kid`-less HS256 tokens, for good or, after a move to
+/// asymmetric keys, until a deadline.
+#[derive(Clone)]
+struct SharedSecret {
+    key: DecodingKey,
+    until: Option<DateTime<Utc>>,
+}
+
+/// Issues and verifies access tokens.
+///
+/// With HS256 tokens carry no `kid` and are checked against the shared
+/// secret. With RS256/EdDSA every token names its signing key, and retired
+/// public keys stay in the verification set (and the JWKS) after a rotation;
+/// tokens from before a move off HS256 are only accepted until
+/// `legacy_hs256_until`.
+#[derive(Clone)]
 pub struct JwtService {
-    secret: String,
+    algorithm: Algorithm,
+    signing_kid: Option<String>,
+    encoding_key: EncodingKey,
+    verification_keys: HashMap<String, VerificationKey>,
+    shared_secret: Option<SharedSecret>,
+    jwks: JwkSet,
     expiration_hours: i64,
 }
 
 impl JwtService {
-    pub fn new(secret: String, expiration_hours: i64) -> Self {
-        Self {
-            secret,
-            expiration_hours,
+    pub fn new(config: &JwtConfig) -> Result<Self, String> {
+        let shared_secret = match (&config.secret, config.legacy_hs256_until) {
+            (Some(secret), _) if config.algorithm == JwtAlgorithm::Hs256 => Some(SharedSecret {
+                key: DecodingKey::from_secret(secret.as_bytes()),
+                until: None,
+            }),
+            (Some(secret), Some(until)) if until > Utc::now() => {
+                tracing::warn!(%until, "Accepting legacy HS256 tokens without a key id");
+                Some(SharedSecret {
+                    key: DecodingKey::from_secret(secret.as_bytes()),
+                    until: Some(until),
+                })
+            }
+            _ => None,
+        };
+
+        let mut verification_keys = HashMap::new();
+        let mut jwks = JwkSet { keys: Vec::new() };
+
+        let (algorithm, signing_kid, encoding_key) = match (&config.algorithm, &config.signing_key)
+        {
+            (JwtAlgorithm::Hs256, _) => {
+                let secret = config
+                    .secret
+                    .as_ref()
+                    .ok_or("JWT secret is not configured")?;
+                (
+                    Algorithm::HS256,
+                    None,
+                    EncodingKey::from_secret(secret.as_bytes()),
+                )
+            }
+            (JwtAlgorithm::Rs256 | JwtAlgorithm::EdDsa, Some(signing_key)) => {
+                let (algorithm, jwk) = public_jwk(&signing_key.kid, &signing_key.public_pem)?;
+
+                let expected = match config.algorithm {
+                    JwtAlgorithm::Rs256 => Algorithm::RS256,
+                    _ => Algorithm::EdDSA,
+                };
+                if algorithm != expected {
+                    return Err(format!(
+                        "JWT key {} does not match JWT_ALGORITHM",
+                        signing_key.kid
+                    ));
+                }
+
+                let encoding_key = match algorithm {
+                    Algorithm::RS256 => {
+                        EncodingKey::from_rsa_pem(signing_key.private_pem.as_bytes())
+                    }
+                    _ => EncodingKey::from_ed_pem(signing_key.private_pem.as_bytes()),
+                }
+                .map_err(|e| format!("Invalid JWT private key {}: {}", signing_key.kid, e))?;
+
+                verification_keys.insert(
+                    signing_key.kid.clone(),
+                    verification_key(algorithm, &signing_key.public_pem)?,
+                );
+                jwks.keys.push(jwk);
+
+                (algorithm, Some(signing_key.kid.clone()), encoding_key)
+            }
+            (_, None) => return Err("JWT signing key is not configured".to_string()),
+        };
+
+        for retired in &config.retired_keys {
+            let (algorithm, jwk) = public_jwk(&retired.kid, &retired.public_pem)?;
+            verification_keys.insert(
+                retired.kid.clone(),
+                verification_key(algorithm, &retired.public_pem)?,
+            );
+            jwks.keys.push(jwk);
         }
+
+        Ok(Self {
+            algorithm,
+            signing_kid,
+            encoding_key,
+            verification_keys,
+            shared_secret,
+            jwks,
+            expiration_hours: config.expiration_hours,
+        })
     }
 
-    pub fn generate_token(&self, user_id: Uuid, role_id: i32) -> Result<String, AuthError> {
-        let now = chrono::Utc::now();
+    pub fn generate_token(
+        &self,
+        user_id: Uuid,
+        role_id: i32,
+        token_version: i32,
+    ) -> Result<String, AuthError> {
+        let now = Utc::now();
         let exp = now + chrono::Duration::hours(self.expiration_hours);
 
         let claims = Claims {
             sub: user_id.to_string(),
             user_id: user_id.to_string(),
             role_id,
+            ver: token_version,
             exp: exp.timestamp(),
             iat: now.timestamp(),
         };
 
-        encode(
-            &Header::default(),
-            &claims,
-            &EncodingKey::from_secret(self.secret.as_bytes()),
-        )
-        .map_err(|e| AuthError::TokenGenerationFailed(e.to_string()))
+        let mut header = Header::new(self.algorithm);
+        header.kid = self.signing_kid.clone();
+
+        encode(&header, &claims, &self.encoding_key)
+            .map_err(|e| AuthError::TokenGenerationFailed(e.to_string()))
     }
 
     pub fn validate_token(&self, token: &str) -> Result<Claims, AuthError> {
-        decode::<Claims>(
-            token,
-            &DecodingKey::from_secret(self.secret.as_bytes()),
-            &Validation::default(),
-        )
-        .map(|data| data.claims)
-        .map_err(|e| AuthError::TokenValidationFailed(e.to_string()))
+        let header =
+            decode_header(token).map_err(|e| AuthError::TokenValidationFailed(e.to_string()))?;
+
+        let (algorithm, key) = match header.kid {
+            Some(kid) => {
+                let key = self.verification_keys.get(&kid).ok_or_else(|| {
+                    AuthError::TokenValidationFailed(format!("Unknown key id: {}", kid))
+                })?;
+                (key.algorithm, &key.key)
+            }
+            None => {
+                let secret = self
+                    .shared_secret
+                    .as_ref()
+                    .filter(|secret| secret.until.is_none_or(|until| Utc::now() < until))
+                    .ok_or_else(|| {
+                        AuthError::TokenValidationFailed("Missing key id".to_string())
+                    })?;
+                (Algorithm::HS256, &secret.key)
+            }
+        };
+
+        decode::<Claims>(token, key, &Validation::new(algorithm))
+            .map(|data| data.claims)
+            .map_err(|e| AuthError::TokenValidationFailed(e.to_string()))
+    }
+
+    /// Public verification keys, empty when tokens are signed with HS256.
+    pub fn jwks(&self) -> &JwkSet {
+        &self.jwks
     }
 }
 
-impl Clone for JwtService {
-    fn clone(&self) -> Self {
-        Self {
-            secret: self.secret.clone(),
-            expiration_hours: self.expiration_hours,
-        }
+fn verification_key(algorithm: Algorithm, public_pem: &str) -> Result<VerificationKey, String> {
+    let key = match algorithm {
+        Algorithm::RS256 => DecodingKey::from_rsa_pem(public_pem.as_bytes()),
+        _ => DecodingKey::from_ed_pem(public_pem.as_bytes()),
     }
+    .map_err(|e| format!("Invalid JWT public key: {}", e))?;
+
+    Ok(VerificationKey { algorithm, key })
+}
+
+/// Detects the key type of a public PEM and builds its JWK representation.
+fn public_jwk(kid: &str, public_pem: &str) -> Result<(Algorithm, Jwk), String> {
+    let parsed =
+        pem::parse(public_pem).map_err(|e| format!("Invalid JWT public key {}: {}", kid, e))?;
+    let der = parsed.contents();
+
+    let (algorithm, key_algorithm, parameters) = if parsed.tag() == "PUBLIC KEY"
+        && der.len() == ED25519_SPKI_PREFIX.len() + 32
+        && der.starts_with(&ED25519_SPKI_PREFIX)
+    {
+        (
+            Algorithm::EdDSA,
+            KeyAlgorithm::EdDSA,
+            AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                key_type: OctetKeyPairType::OctetKeyPair,
+                curve: EllipticCurve::Ed25519,
+                x: URL_SAFE_NO_PAD.encode(&der[ED25519_SPKI_PREFIX.len()..]),
+            }),
+        )
+    } else {
+        let rsa_key = match parsed.tag() {
+            "RSA PUBLIC KEY" => RsaPublicKey::from_pkcs1_der(der).map_err(|e| e.to_string()),
+            _ => RsaPublicKey::from_public_key_der(der).map_err(|e| e.to_string()),
+        }
+        .map_err(|e| format!("Unsupported JWT public key {}: {}", kid, e))?;
+
+        (
+            Algorithm::RS256,
+            KeyAlgorithm::RS256,
+            AlgorithmParameters::RSA(RSAKeyParameters {
+                key_type: RSAKeyType::RSA,
+                n: URL_SAFE_NO_PAD.encode(rsa_key.n().to_bytes_be()),
+                e: URL_SAFE_NO_PAD.encode(rsa_key.e().to_bytes_be()),
+            }),
+        )
+    };
+
+    let jwk = Jwk {
+        common: CommonParameters {
+            public_key_use: Some(PublicKeyUse::Signature),
+            key_algorithm: Some(key_algorithm),
+            key_id: Some(kid.to_string()),
+            ..Default::default()
+        },
+        algorithm: parameters,
+    };
+
+    Ok((algorithm, jwk))
 }
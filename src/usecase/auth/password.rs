@@ -0,0 +1,94 @@
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, SaltString};
+use argon2::{Algorithm, Argon2, Params, PasswordHasher as _, PasswordVerifier as _, Version};
+
+use super::error::AuthError;
+use crate::config::password::PasswordHashConfig;
+
+/// Outcome of checking a password against a stored hash.
+#[derive(Debug, Clone, Copy)]
+pub struct Verification {
+    pub valid: bool,
+    /// The stored hash uses bcrypt or outdated Argon2 parameters.
+    pub needs_rehash: bool,
+}
+
+/// Hashes new passwords with Argon2id and verifies both Argon2 and legacy
+/// bcrypt hashes, telling them apart by their PHC/modular-crypt prefix.
+#[derive(Clone)]
+pub struct PasswordHasher {
+    params: Params,
+}
+
+impl PasswordHasher {
+    pub fn new(config: &PasswordHashConfig) -> Result<Self, String> {
+        let params = Params::new(
+            config.argon2_memory_kib,
+            config.argon2_iterations,
+            config.argon2_parallelism,
+            None,
+        )
+        .map_err(|e| format!("Invalid Argon2 parameters: {}", e))?;
+
+        Ok(Self { params })
+    }
+
+    pub fn hash(&self, password: &str) -> Result<String, AuthError> {
+        let salt = SaltString::generate(&mut OsRng);
+
+        self.argon2()
+            .hash_password(password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+            .map_err(|e| AuthError::Internal(e.to_string()))
+    }
+
+    pub fn verify(&self, password: &str, stored_hash: &str) -> Result<Verification, AuthError> {
+        if is_bcrypt(stored_hash) {
+            let valid = bcrypt::verify(password, stored_hash)
+                .map_err(|e| AuthError::Internal(e.to_string()))?;
+
+            return Ok(Verification {
+                valid,
+                needs_rehash: true,
+            });
+        }
+
+        let parsed =
+            PasswordHash::new(stored_hash).map_err(|e| AuthError::Internal(e.to_string()))?;
+
+        let valid = self
+            .argon2()
+            .verify_password(password.as_bytes(), &parsed)
+            .is_ok();
+
+        Ok(Verification {
+            valid,
+            needs_rehash: self.is_outdated(&parsed),
+        })
+    }
+
+    fn is_outdated(&self, parsed: &PasswordHash<'_>) -> bool {
+        if parsed.algorithm != Algorithm::Argon2id.ident() {
+            return true;
+        }
+
+        match Params::try_from(parsed) {
+            Ok(params) => {
+                params.m_cost() != self.params.m_cost()
+                    || params.t_cost() != self.params.t_cost()
+                    || params.p_cost() != self.params.p_cost()
+            }
+            Err(_) => true,
+        }
+    }
+
+    fn argon2(&self) -> Argon2<'static> {
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, self.params.clone())
+    }
+}
+
+fn is_bcrypt(hash: &str) -> bool {
+    ["$2a$", "$2b$", "$2x$", "$2y$"]
+        .iter()
+        .any(|prefix| hash.starts_with(prefix))
+}
@@ -0,0 +1,11 @@
+//! Permission names seeded by the `create_permissions` and `create_webhooks`
+//! migrations.
+
+pub const USERS_READ: &str = "users.read";
+pub const USERS_DEACTIVATE: &str = "users.deactivate";
+pub const USERS_LOGOUT: &str = "users.logout";
+pub const USERS_ASSIGN_ROLE: &str = "users.assign_role";
+pub const ROLES_READ: &str = "roles.read";
+pub const ROLES_MANAGE: &str = "roles.manage";
+pub const CHATS_MODERATE: &str = "chats.moderate";
+pub const WEBHOOKS_MANAGE: &str = "webhooks.manage";
@@ -1,18 +1,34 @@
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+
+use jsonwebtoken::jwk::JwkSet;
 use uuid::Uuid;
 
 use super::error::AuthError;
 use super::jwt::JwtService;
+use super::password::PasswordHasher;
+use super::throttle::LoginThrottle;
 use crate::config::jwt::JwtConfig;
-use crate::repository::Repository;
-use crate::repository::auth::{AuthUser, NewAuthUser};
+use crate::config::password::PasswordHashConfig;
+use crate::config::throttle::LoginThrottleConfig;
+use crate::repository::auth::{AuthUser, NewAuthUser, NewRole, Permission, Role};
+use crate::repository::{Repository, RepositoryError};
+use crate::usecase::blocking::run_blocking;
 
 const MIN_USERNAME_LENGTH: usize = 3;
 const MAX_USERNAME_LENGTH: usize = 100;
 const MIN_PASSWORD_LENGTH: usize = 8;
+const MAX_ROLE_NAME_LENGTH: usize = 50;
+const MAX_USERS_PER_PAGE: i64 = 200;
+
+pub const ADMIN_ROLE_ID: i32 = 0;
+pub const DEFAULT_ROLE_ID: i32 = 1;
 
 pub struct AuthService {
     repo: Repository,
     jwt: JwtService,
+    throttle: LoginThrottle,
+    passwords: PasswordHasher,
 }
 
 #[derive(Debug, Clone)]
@@ -31,100 +47,536 @@ pub struct UserInfo {
     pub created_at: chrono::NaiveDateTime,
 }
 
+/// Authenticated caller together with the permissions granted by its role.
+#[derive(Debug, Clone)]
+pub struct Principal {
+    pub user_id: Uuid,
+    pub role_id: i32,
+    pub permissions: HashSet<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RoleInfo {
+    pub role: Role,
+    pub permissions: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct UserPage {
+    pub users: Vec<UserInfo>,
+    pub total: i64,
+    /// The page size and offset actually used, after clamping.
+    pub limit: i64,
+    pub offset: i64,
+}
+
 impl AuthService {
-    pub fn new(repo: Repository, jwt_config: &JwtConfig) -> Self {
-        let jwt = JwtService::new(jwt_config.secret.clone(), jwt_config.expiration_hours);
-        Self { repo, jwt }
+    pub fn new(
+        repo: Repository,
+        jwt_config: &JwtConfig,
+        throttle_config: &LoginThrottleConfig,
+        password_config: &PasswordHashConfig,
+    ) -> Result<Self, String> {
+        let jwt = JwtService::new(jwt_config)?;
+        let throttle = LoginThrottle::new(throttle_config.clone());
+        let passwords = PasswordHasher::new(password_config)?;
+        Ok(Self {
+            repo,
+            jwt,
+            throttle,
+            passwords,
+        })
+    }
+
+    #[tracing::instrument(skip(self, password))]
+    pub async fn create_user(
+        &self,
+        username: String,
+        password: String,
+    ) -> Result<AuthUser, AuthError> {
+        let this = self.clone();
+        run_blocking(move || this.insert_user(username, password, DEFAULT_ROLE_ID)).await
     }
 
+    /// Creates the configured admin account on first start. An existing user
+    /// with the same name is never promoted, so a squatted username cannot be
+    /// turned into an admin by the bootstrap config.
     #[tracing::instrument(skip(self, password))]
-    pub fn create_user(
+    pub async fn ensure_admin(&self, username: String, password: String) -> Result<(), AuthError> {
+        let this = self.clone();
+        run_blocking(move || match this.repo.auth.find_by_username(&username)? {
+            Some(user) if user.role_id == ADMIN_ROLE_ID => Ok(()),
+            Some(_) => {
+                tracing::warn!("Bootstrap admin username is taken by a non-admin user, skipping");
+                Ok(())
+            }
+            None => {
+                this.insert_user(username, password, ADMIN_ROLE_ID)?;
+                tracing::info!("Bootstrap admin account created");
+                Ok(())
+            }
+        })
+        .await
+    }
+
+    /// Gives `user_id` the role `role_id` on behalf of `actor_id`. Only
+    /// admins may grant or take away the admin role; anyone else can only
+    /// move users between roles whose permissions they hold themselves.
+    #[tracing::instrument(skip(self))]
+    pub async fn assign_role(
+        &self,
+        actor_id: Uuid,
+        user_id: Uuid,
+        role_id: i32,
+    ) -> Result<UserInfo, AuthError> {
+        let this = self.clone();
+        run_blocking(move || {
+            let user = this
+                .repo
+                .auth
+                .find_by_id(user_id)?
+                .ok_or(AuthError::UserNotFound)?;
+
+            let role = this
+                .repo
+                .auth
+                .find_role_by_id(role_id)?
+                .ok_or(AuthError::RoleNotFound)?;
+
+            let actor = this.find_actor(actor_id)?;
+
+            if actor.role_id != ADMIN_ROLE_ID {
+                if role.id == ADMIN_ROLE_ID || user.role_id == ADMIN_ROLE_ID {
+                    return Err(AuthError::Forbidden);
+                }
+
+                for affected in [role.id, user.role_id] {
+                    let permissions = this.repo.auth.find_role_permissions(affected)?;
+                    this.check_grantable(&actor, &permissions)?;
+                }
+            }
+
+            let user = this.repo.auth.update_user_role(user_id, role.id)?;
+
+            Ok(UserInfo::from((user, role)))
+        })
+        .await
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn list_users(
+        &self,
+        search: Option<String>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<UserPage, AuthError> {
+        let this = self.clone();
+        run_blocking(move || {
+            let search = search.as_deref().map(str::trim).filter(|s| !s.is_empty());
+            let limit = limit.clamp(1, MAX_USERS_PER_PAGE);
+            let offset = offset.max(0);
+
+            let users = this.repo.auth.list_users(search, limit, offset)?;
+
+            let total = this.repo.auth.count_users(search)?;
+
+            Ok(UserPage {
+                users: users.into_iter().map(UserInfo::from).collect(),
+                total,
+                limit,
+                offset,
+            })
+        })
+        .await
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn deactivate_user(
+        &self,
+        actor_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<UserInfo, AuthError> {
+        let this = self.clone();
+        run_blocking(move || {
+            let user = this
+                .repo
+                .auth
+                .find_by_id(user_id)?
+                .ok_or(AuthError::UserNotFound)?;
+
+            let actor = this.find_actor(actor_id)?;
+            check_target(&actor, &user)?;
+
+            let user = this.repo.auth.deactivate_user(user_id)?;
+
+            this.user_info(user)
+        })
+        .await
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn reactivate_user(
+        &self,
+        actor_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<UserInfo, AuthError> {
+        let this = self.clone();
+        run_blocking(move || {
+            let user = this
+                .repo
+                .auth
+                .find_by_id(user_id)?
+                .ok_or(AuthError::UserNotFound)?;
+
+            let actor = this.find_actor(actor_id)?;
+            check_target(&actor, &user)?;
+
+            let user = this.repo.auth.reactivate_user(user_id)?;
+
+            this.user_info(user)
+        })
+        .await
+    }
+
+    /// Invalidates every token issued to the user so far.
+    #[tracing::instrument(skip(self))]
+    pub async fn force_logout(&self, actor_id: Uuid, user_id: Uuid) -> Result<(), AuthError> {
+        let this = self.clone();
+        run_blocking(move || {
+            let user = this
+                .repo
+                .auth
+                .find_by_id(user_id)?
+                .ok_or(AuthError::UserNotFound)?;
+
+            let actor = this.find_actor(actor_id)?;
+            check_target(&actor, &user)?;
+
+            this.repo.auth.revoke_tokens(user_id)?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn list_roles(&self) -> Result<Vec<RoleInfo>, AuthError> {
+        let this = self.clone();
+        run_blocking(move || {
+            let roles = this.repo.auth.find_all_roles()?;
+
+            let mut permissions: HashMap<i32, Vec<String>> = HashMap::new();
+            for (role_id, name) in this.repo.auth.find_all_role_permissions()? {
+                permissions.entry(role_id).or_default().push(name);
+            }
+
+            Ok(roles
+                .into_iter()
+                .map(|role| RoleInfo {
+                    permissions: permissions.remove(&role.id).unwrap_or_default(),
+                    role,
+                })
+                .collect())
+        })
+        .await
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn list_permissions(&self) -> Result<Vec<Permission>, AuthError> {
+        let this = self.clone();
+        run_blocking(move || {
+            this.repo
+                .auth
+                .find_all_permissions()
+                .map_err(AuthError::from)
+        })
+        .await
+    }
+
+    /// Creates a role on behalf of `actor_id`, who must hold every permission
+    /// it grants unless they are an admin.
+    #[tracing::instrument(skip(self))]
+    pub async fn create_role(
+        &self,
+        actor_id: Uuid,
+        name: String,
+        description: Option<String>,
+        permissions: Vec<String>,
+    ) -> Result<RoleInfo, AuthError> {
+        let this = self.clone();
+        run_blocking(move || {
+            let name = name.trim().to_string();
+            this.validate_role_name(&name)?;
+
+            let permission_ids = this.resolve_permissions(&permissions)?;
+
+            let actor = this.find_actor(actor_id)?;
+            this.check_grantable(&actor, &permissions)?;
+
+            if this.repo.auth.role_name_exists(&name)? {
+                return Err(AuthError::RoleExists);
+            }
+
+            let role = this.repo.transaction(|tx| {
+                let role =
+                    tx.auth
+                        .create_role(NewRole { name, description })
+                        .map_err(|e| match e {
+                            RepositoryError::Conflict(_) => AuthError::RoleExists,
+                            e => AuthError::from(e),
+                        })?;
+
+                tx.auth.set_role_permissions(role.id, &permission_ids)?;
+
+                Ok::<_, AuthError>(role)
+            })?;
+
+            this.role_info(role)
+        })
+        .await
+    }
+
+    /// Replaces the permission set of a role on behalf of `actor_id`. The
+    /// seeded admin role is kept immutable so the last way to manage roles
+    /// cannot be removed by mistake. Anyone but an admin can only edit roles
+    /// they do not hold, and only within the permissions they hold.
+    #[tracing::instrument(skip(self))]
+    pub async fn set_role_permissions(
+        &self,
+        actor_id: Uuid,
+        role_id: i32,
+        permissions: Vec<String>,
+    ) -> Result<RoleInfo, AuthError> {
+        let this = self.clone();
+        run_blocking(move || {
+            if role_id == ADMIN_ROLE_ID {
+                return Err(AuthError::Forbidden);
+            }
+
+            let role = this
+                .repo
+                .auth
+                .find_role_by_id(role_id)?
+                .ok_or(AuthError::RoleNotFound)?;
+
+            let actor = this.find_actor(actor_id)?;
+            if actor.role_id != ADMIN_ROLE_ID {
+                if role.id == actor.role_id {
+                    return Err(AuthError::Forbidden);
+                }
+
+                let current = this.repo.auth.find_role_permissions(role.id)?;
+                this.check_grantable(&actor, &current)?;
+            }
+
+            let permission_ids = this.resolve_permissions(&permissions)?;
+            this.check_grantable(&actor, &permissions)?;
+
+            this.repo
+                .auth
+                .set_role_permissions(role.id, &permission_ids)?;
+
+            this.role_info(role)
+        })
+        .await
+    }
+
+    fn insert_user(
         &self,
         username: String,
         password: String,
-        role_id: Option<i32>,
+        role_id: i32,
     ) -> Result<AuthUser, AuthError> {
         self.validate_username(&username)?;
         self.validate_password(&password)?;
 
-        if self
-            .repo
-            .auth
-            .username_exists(&username)
-            .map_err(|e| AuthError::Internal(e))?
-        {
+        if self.repo.auth.username_exists(&username)? {
             return Err(AuthError::UsernameExists);
         }
 
-        let password_hash = bcrypt::hash(&password, bcrypt::DEFAULT_COST)
-            .map_err(|e| AuthError::Internal(e.to_string()))?;
+        let password_hash = self.passwords.hash(&password)?;
 
         let new_user = NewAuthUser {
             username,
             password_hash,
-            role_id: role_id.unwrap_or(1),
+            role_id,
         };
 
-        self.repo
-            .auth
-            .create_user(new_user)
-            .map_err(|e| AuthError::Internal(e))
+        // The existence check above races with concurrent registrations; the
+        // unique index is what actually decides.
+        self.repo.auth.create_user(new_user).map_err(|e| match e {
+            RepositoryError::Conflict(_) => AuthError::UsernameExists,
+            e => AuthError::from(e),
+        })
     }
 
     #[tracing::instrument(skip(self, password))]
-    pub fn login(&self, username: String, password: String) -> Result<AuthResponse, AuthError> {
-        let user = self
-            .repo
-            .auth
-            .find_by_username(&username)
-            .map_err(|_| AuthError::InvalidCredentials)?;
+    pub async fn login(
+        &self,
+        username: String,
+        password: String,
+        client_ip: Option<IpAddr>,
+    ) -> Result<AuthResponse, AuthError> {
+        let this = self.clone();
+        run_blocking(move || {
+            this.throttle.check(&username, client_ip)?;
 
-        if !user.is_active {
-            return Err(AuthError::UserDeactivated);
-        }
+            let user = match this.repo.auth.find_by_username(&username)? {
+                Some(user) => user,
+                None => {
+                    this.throttle.record_failure(&username, client_ip);
+                    return Err(AuthError::InvalidCredentials);
+                }
+            };
 
-        let valid = bcrypt::verify(&password, &user.password_hash)
-            .map_err(|e| AuthError::Internal(e.to_string()))?;
+            if !user.is_active {
+                return Err(AuthError::UserDeactivated);
+            }
 
-        if !valid {
-            return Err(AuthError::InvalidCredentials);
-        }
+            let verification = this.passwords.verify(&password, &user.password_hash)?;
 
-        let token = self.jwt.generate_token(user.id, user.role_id)?;
+            if !verification.valid {
+                tracing::warn!("Failed login attempt");
+                this.throttle.record_failure(&username, client_ip);
+                return Err(AuthError::InvalidCredentials);
+            }
 
-        Ok(AuthResponse { user, token })
-    }
+            this.throttle.record_success(&username);
 
-    #[tracing::instrument(skip(self))]
-    pub fn get_user_by_id(&self, user_id: Uuid) -> Result<UserInfo, AuthError> {
-        let user = self
-            .repo
-            .auth
-            .find_by_id(user_id)
-            .map_err(|_| AuthError::UserNotFound)?;
+            if verification.needs_rehash {
+                this.rehash_password(user.id, &password);
+            }
 
-        let role = self
-            .repo
-            .auth
-            .find_role_by_id(user.role_id)
-            .map_err(|e| AuthError::Internal(e))?;
+            let token = this
+                .jwt
+                .generate_token(user.id, user.role_id, user.token_version)?;
 
-        Ok(UserInfo {
-            id: user.id,
-            username: user.username,
-            role_id: user.role_id,
-            role_name: role.name,
-            is_active: user.is_active,
-            created_at: user.created_at,
+            Ok(AuthResponse { user, token })
         })
+        .await
     }
 
     #[tracing::instrument(skip(self))]
-    pub fn validate_token(&self, token: &str) -> Result<(Uuid, i32), AuthError> {
+    pub async fn get_user_by_id(&self, user_id: Uuid) -> Result<UserInfo, AuthError> {
+        let this = self.clone();
+        run_blocking(move || {
+            let user = this
+                .repo
+                .auth
+                .find_by_id(user_id)?
+                .ok_or(AuthError::UserNotFound)?;
+
+            this.user_info(user)
+        })
+        .await
+    }
+
+    /// Checks the signature and then the stored account state, so deactivation,
+    /// forced logout and role changes apply to tokens that are already issued.
+    #[tracing::instrument(skip(self, token))]
+    pub async fn validate_token(&self, token: &str) -> Result<Principal, AuthError> {
         let claims = self.jwt.validate_token(token)?;
         let user_id = Uuid::parse_str(&claims.user_id)
             .map_err(|e| AuthError::TokenValidationFailed(e.to_string()))?;
-        Ok((user_id, claims.role_id))
+
+        let this = self.clone();
+        run_blocking(move || {
+            let user = this
+                .repo
+                .auth
+                .find_by_id(user_id)?
+                .ok_or_else(|| AuthError::TokenValidationFailed("Unknown user".to_string()))?;
+
+            if !user.is_active {
+                return Err(AuthError::UserDeactivated);
+            }
+
+            if claims.ver != user.token_version {
+                return Err(AuthError::TokenValidationFailed(
+                    "Token has been revoked".to_string(),
+                ));
+            }
+
+            let permissions = this.repo.auth.find_role_permissions(user.role_id)?;
+
+            Ok(Principal {
+                user_id: user.id,
+                role_id: user.role_id,
+                permissions: permissions.into_iter().collect(),
+            })
+        })
+        .await
+    }
+
+    fn find_actor(&self, actor_id: Uuid) -> Result<AuthUser, AuthError> {
+        self.repo
+            .auth
+            .find_by_id(actor_id)?
+            .ok_or(AuthError::UserNotFound)
+    }
+
+    /// Admins may hand out any permission; everyone else only those their
+    /// own role holds.
+    fn check_grantable(&self, actor: &AuthUser, permissions: &[String]) -> Result<(), AuthError> {
+        if actor.role_id == ADMIN_ROLE_ID {
+            return Ok(());
+        }
+
+        let held = self.repo.auth.find_role_permissions(actor.role_id)?;
+        if !permissions.iter().all(|p| held.contains(p)) {
+            return Err(AuthError::Forbidden);
+        }
+
+        Ok(())
+    }
+
+    fn role_info(&self, role: Role) -> Result<RoleInfo, AuthError> {
+        let permissions = self.repo.auth.find_role_permissions(role.id)?;
+
+        Ok(RoleInfo { role, permissions })
+    }
+
+    fn resolve_permissions(&self, names: &[String]) -> Result<Vec<i32>, AuthError> {
+        let found = self.repo.auth.find_permissions_by_names(names)?;
+
+        if let Some(unknown) = names
+            .iter()
+            .find(|name| !found.iter().any(|p| &p.name == *name))
+        {
+            return Err(AuthError::UnknownPermission(unknown.clone()));
+        }
+
+        Ok(found.into_iter().map(|p| p.id).collect())
+    }
+
+    pub fn jwks(&self) -> &JwkSet {
+        self.jwt.jwks()
+    }
+
+    /// Upgrades a legacy hash after a successful login. Failure only delays
+    /// the upgrade to the next login, so it is logged rather than returned.
+    fn rehash_password(&self, user_id: Uuid, password: &str) {
+        let result = self.passwords.hash(password).and_then(|hash| {
+            self.repo
+                .auth
+                .update_password_hash(user_id, &hash)
+                .map_err(AuthError::from)
+        });
+
+        match result {
+            Ok(()) => tracing::info!("Password hash upgraded to Argon2id"),
+            Err(e) => tracing::warn!("Failed to upgrade password hash: {}", e),
+        }
+    }
+
+    fn user_info(&self, user: AuthUser) -> Result<UserInfo, AuthError> {
+        let role = self
+            .repo
+            .auth
+            .find_role_by_id(user.role_id)?
+            .ok_or(AuthError::RoleNotFound)?;
+
+        Ok(UserInfo::from((user, role)))
     }
 
     fn validate_username(&self, username: &str) -> Result<(), AuthError> {
@@ -152,6 +604,21 @@ impl AuthService {
         Ok(())
     }
 
+    fn validate_role_name(&self, name: &str) -> Result<(), AuthError> {
+        if name.is_empty() {
+            return Err(AuthError::InvalidRoleName(
+                "Role name cannot be empty".to_string(),
+            ));
+        }
+        if name.len() > MAX_ROLE_NAME_LENGTH {
+            return Err(AuthError::InvalidRoleName(format!(
+                "Role name must be at most {} characters",
+                MAX_ROLE_NAME_LENGTH
+            )));
+        }
+        Ok(())
+    }
+
     fn validate_password(&self, password: &str) -> Result<(), AuthError> {
         if password.len() < MIN_PASSWORD_LENGTH {
             return Err(AuthError::InvalidPassword(format!(
@@ -163,11 +630,37 @@ impl AuthService {
     }
 }
 
+/// Only admins may act on admin accounts or on their own, so an operator
+/// cannot lock the admins out.
+fn check_target(actor: &AuthUser, target: &AuthUser) -> Result<(), AuthError> {
+    if actor.role_id != ADMIN_ROLE_ID && (target.role_id == ADMIN_ROLE_ID || target.id == actor.id)
+    {
+        return Err(AuthError::Forbidden);
+    }
+
+    Ok(())
+}
+
+impl From<(AuthUser, Role)> for UserInfo {
+    fn from((user, role): (AuthUser, Role)) -> Self {
+        Self {
+            id: user.id,
+            username: user.username,
+            role_id: user.role_id,
+            role_name: role.name,
+            is_active: user.is_active,
+            created_at: user.created_at,
+        }
+    }
+}
+
 impl Clone for AuthService {
     fn clone(&self) -> Self {
         Self {
             repo: self.repo.clone(),
             jwt: self.jwt.clone(),
+            throttle: self.throttle.clone(),
+            passwords: self.passwords.clone(),
         }
     }
 }
@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::error::AuthError;
+use crate::config::throttle::LoginThrottleConfig;
+
+/// Upper bound on tracked keys before stale entries are pruned.
+const PRUNE_THRESHOLD: usize = 10_000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum ThrottleKey {
+    Username(String),
+    Ip(IpAddr),
+}
+
+#[derive(Debug)]
+struct AttemptState {
+    failures: u32,
+    last_failure: Instant,
+    locked_until: Option<Instant>,
+}
+
+/// Tracks failed logins per username and per client IP.
+///
+/// Once a key reaches its failure budget it is locked out, and every further
+/// failure doubles the lockout up to the configured cap. Counters reset after
+/// a full window passes without failures.
+#[derive(Clone)]
+pub struct LoginThrottle {
+    config: LoginThrottleConfig,
+    attempts: Arc<Mutex<HashMap<ThrottleKey, AttemptState>>>,
+}
+
+impl LoginThrottle {
+    pub fn new(config: LoginThrottleConfig) -> Self {
+        Self {
+            config,
+            attempts: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Rejects the attempt before any password hashing if either key is locked.
+    pub fn check(&self, username: &str, ip: Option<IpAddr>) -> Result<(), AuthError> {
+        let now = Instant::now();
+        let attempts = self.attempts.lock().unwrap_or_else(|e| e.into_inner());
+
+        let retry_after = Self::keys(username, ip)
+            .filter_map(|key| attempts.get(&key)?.locked_until)
+            .filter(|until| *until > now)
+            .map(|until| until - now)
+            .max();
+
+        match retry_after {
+            Some(remaining) => Err(AuthError::TooManyAttempts {
+                retry_after: remaining.as_secs().max(1),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    pub fn record_failure(&self, username: &str, ip: Option<IpAddr>) {
+        let now = Instant::now();
+        let window = Duration::from_secs(self.config.failure_window_seconds);
+        let mut attempts = self.attempts.lock().unwrap_or_else(|e| e.into_inner());
+
+        if attempts.len() > PRUNE_THRESHOLD {
+            attempts.retain(|_, state| !state.is_stale(now, window));
+        }
+
+        for key in Self::keys(username, ip) {
+            let limit = match key {
+                ThrottleKey::Username(_) => self.config.max_failures_per_username,
+                ThrottleKey::Ip(_) => self.config.max_failures_per_ip,
+            };
+
+            let state = attempts.entry(key).or_insert(AttemptState {
+                failures: 0,
+                last_failure: now,
+                locked_until: None,
+            });
+
+            if state.is_stale(now, window) {
+                state.failures = 0;
+                state.locked_until = None;
+            }
+
+            state.failures += 1;
+            state.last_failure = now;
+
+            if state.failures >= limit {
+                state.locked_until = Some(now + self.lockout_for(state.failures - limit));
+            }
+        }
+    }
+
+    /// Clears the username counter; the IP counter keeps decaying on its own
+    /// so one valid account cannot be used to reset a spraying client.
+    pub fn record_success(&self, username: &str) {
+        let mut attempts = self.attempts.lock().unwrap_or_else(|e| e.into_inner());
+        attempts.remove(&ThrottleKey::Username(username.to_string()));
+    }
+
+    fn lockout_for(&self, excess_failures: u32) -> Duration {
+        let multiplier = 2u64.saturating_pow(excess_failures);
+        let seconds = self
+            .config
+            .lockout_base_seconds
+            .saturating_mul(multiplier)
+            .min(self.config.lockout_max_seconds);
+
+        Duration::from_secs(seconds)
+    }
+
+    fn keys(username: &str, ip: Option<IpAddr>) -> impl Iterator<Item = ThrottleKey> {
+        std::iter::once(ThrottleKey::Username(username.to_string())).chain(ip.map(ThrottleKey::Ip))
+    }
+}
+
+impl AttemptState {
+    fn is_stale(&self, now: Instant, window: Duration) -> bool {
+        let locked = self.locked_until.is_some_and(|until| until > now);
+        !locked && now.duration_since(self.last_failure) > window
+    }
+}
@@ -0,0 +1,27 @@
+use chrono::Duration;
+
+const MAX_DELAY_SECONDS: i64 = 3600;
+
+/// Exponential backoff for the background dispatchers: the wait after the
+/// `n`-th failed attempt is the base delay doubled `n - 1` times, up to an
+/// hour.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Backoff {
+    base_seconds: i64,
+}
+
+impl Backoff {
+    pub(crate) fn new(base_seconds: i64) -> Self {
+        Self { base_seconds }
+    }
+
+    pub(crate) fn delay(&self, attempts: i32) -> Duration {
+        let factor = 1_i64 << (attempts - 1).clamp(0, 20);
+
+        Duration::seconds(
+            self.base_seconds
+                .saturating_mul(factor)
+                .min(MAX_DELAY_SECONDS),
+        )
+    }
+}
@@ -0,0 +1,14 @@
+use tokio::task::JoinError;
+
+/// Runs synchronous use-case code (password hashing, pooled Diesel queries)
+/// on tokio's blocking pool so it never stalls the async worker threads.
+/// The caller's tracing span is carried over to the blocking thread.
+pub(crate) async fn run_blocking<T, E, F>(f: F) -> Result<T, E>
+where
+    F: FnOnce() -> Result<T, E> + Send + 'static,
+    T: Send + 'static,
+    E: From<JoinError> + Send + 'static,
+{
+    let span = tracing::Span::current();
+    tokio::task::spawn_blocking(move || span.in_scope(f)).await?
+}
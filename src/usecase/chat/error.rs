@@ -1,4 +1,7 @@
 use thiserror::Error;
+use uuid::Uuid;
+
+use crate::repository::RepositoryError;
 
 #[derive(Debug, Error)]
 pub enum ChatError {
@@ -14,9 +17,87 @@ pub enum ChatError {
     #[error("User is already a member")]
     AlreadyMember,
 
+    #[error("User is not a member of this chat")]
+    MemberNotFound,
+
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
     #[error("Invalid chat name: {0}")]
     InvalidChatName(String),
 
+    #[error("Invalid message: {0}")]
+    InvalidMessage(String),
+
+    #[error("Invalid message timer: {0}")]
+    InvalidMessageTtl(String),
+
+    #[error("Invalid attachment: {0}")]
+    InvalidAttachment(String),
+
+    #[error("Invalid schedule: {0}")]
+    InvalidSchedule(String),
+
+    #[error("Scheduled message not found")]
+    ScheduledMessageNotFound,
+
+    #[error("Message not found")]
+    MessageNotFound,
+
+    #[error("The chat does not allow forwarding its messages")]
+    ForwardingDisabled,
+
+    #[error("Invalid poll: {0}")]
+    InvalidPoll(String),
+
+    #[error("The poll is closed")]
+    PollClosed,
+
+    #[error("The chat does not allow polls")]
+    PollsDisabled,
+
+    #[error("Message is not pinned")]
+    PinNotFound,
+
+    #[error("A chat can have at most {0} pinned messages")]
+    TooManyPins(usize),
+
+    #[error("Unknown device")]
+    UnknownDevice,
+
+    /// The chat moved on since the client last distributed its sender key.
+    #[error("Chat epoch is now {current}")]
+    StaleEpoch { current: i32 },
+
+    /// The envelopes of a message do not match the chat's current devices;
+    /// the client has to refresh its device list and resend.
+    #[error("Envelopes do not match the chat's devices")]
+    DeviceMismatch {
+        missing: Vec<Uuid>,
+        stale: Vec<Uuid>,
+    },
+
+    #[error("Service temporarily unavailable")]
+    Unavailable,
+
     #[error("Internal error: {0}")]
     Internal(String),
 }
+
+impl From<tokio::task::JoinError> for ChatError {
+    fn from(err: tokio::task::JoinError) -> Self {
+        Self::Internal(err.to_string())
+    }
+}
+
+impl From<RepositoryError> for ChatError {
+    fn from(err: RepositoryError) -> Self {
+        match err {
+            RepositoryError::Unavailable(cause) => {
+                tracing::error!("Database unavailable: {}", cause);
+                Self::Unavailable
+            }
+            err => Self::Internal(err.to_string()),
+        }
+    }
+}
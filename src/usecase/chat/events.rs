@@ -0,0 +1,75 @@
+use uuid::Uuid;
+
+use crate::repository::chat::Message;
+use crate::repository::webhooks::WebhookEventKind;
+
+/// A change in a chat that webhooks can subscribe to. Events describe who
+/// did what, never message content: that stays end-to-end encrypted.
+#[derive(Debug, Clone)]
+pub enum ChatEvent {
+    MessageCreated {
+        message_id: Uuid,
+        sender_id: Uuid,
+        sender_device_id: Option<Uuid>,
+        /// The [`MessageKind`](crate::repository::chat::MessageKind) name.
+        message_kind: String,
+    },
+    /// A member was added, by invitation or by creating the chat.
+    MemberJoined {
+        user_id: Uuid,
+        invited_by: Option<Uuid>,
+    },
+    ChatRenamed {
+        old_name: String,
+        name: String,
+        renamed_by: Uuid,
+    },
+}
+
+impl ChatEvent {
+    pub fn message_created(message: &Message) -> Self {
+        Self::MessageCreated {
+            message_id: message.id,
+            sender_id: message.sender_id,
+            sender_device_id: message.sender_device_id,
+            message_kind: message.kind.clone(),
+        }
+    }
+
+    pub fn kind(&self) -> WebhookEventKind {
+        match self {
+            Self::MessageCreated { .. } => WebhookEventKind::MessageCreated,
+            Self::MemberJoined { .. } => WebhookEventKind::MemberJoined,
+            Self::ChatRenamed { .. } => WebhookEventKind::ChatRenamed,
+        }
+    }
+
+    pub fn payload(&self) -> serde_json::Value {
+        match self {
+            Self::MessageCreated {
+                message_id,
+                sender_id,
+                sender_device_id,
+                message_kind,
+            } => serde_json::json!({
+                "message_id": message_id,
+                "sender_id": sender_id,
+                "sender_device_id": sender_device_id,
+                "message_kind": message_kind,
+            }),
+            Self::MemberJoined {
+                user_id,
+                invited_by,
+            } => serde_json::json!({ "user_id": user_id, "invited_by": invited_by }),
+            Self::ChatRenamed {
+                old_name,
+                name,
+                renamed_by,
+            } => serde_json::json!({
+                "old_name": old_name,
+                "name": name,
+                "renamed_by": renamed_by,
+            }),
+        }
+    }
+}
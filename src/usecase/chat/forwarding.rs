@@ -0,0 +1,151 @@
+use chrono::Utc;
+use uuid::Uuid;
+
+use super::error::ChatError;
+use super::notice::Notice;
+use super::service::{ChatInfo, ChatService, MessageBody, MessageInfo, MessageOptions};
+use crate::repository::chat::{Message, MessageKind};
+use crate::usecase::blocking::run_blocking;
+
+/// Where a forwarded message first came from. Forwarding a forward keeps
+/// pointing at the original.
+#[derive(Clone, Copy, Debug)]
+pub struct ForwardedFrom {
+    pub chat_id: Uuid,
+    pub message_id: Uuid,
+    pub sender_id: Uuid,
+}
+
+impl ForwardedFrom {
+    pub(super) fn of(message: &Message) -> Option<Self> {
+        Some(Self {
+            chat_id: message.forwarded_from_chat_id?,
+            message_id: message.forwarded_from_message_id?,
+            sender_id: message.forwarded_from_sender_id?,
+        })
+    }
+}
+
+impl ChatService {
+    /// Forwards a message into `chat_id`. The server never sees plaintext,
+    /// so the client re-encrypts the content for the target chat and sends
+    /// it in `body` like any other message; the server checks that the
+    /// caller can read the source and that its chat allows forwarding, and
+    /// records where the content came from.
+    #[tracing::instrument(skip(self, body))]
+    pub async fn forward_message(
+        &self,
+        chat_id: Uuid,
+        sender_id: Uuid,
+        sender_device_id: Uuid,
+        source_message_id: Uuid,
+        body: MessageBody,
+        options: MessageOptions,
+    ) -> Result<MessageInfo, ChatError> {
+        let this = self.clone();
+        run_blocking(move || {
+            let source = this
+                .repo
+                .chat
+                .get_message_by_id(source_message_id)?
+                .filter(|m| {
+                    m.expires_at
+                        .is_none_or(|expires_at| expires_at > Utc::now().naive_utc())
+                })
+                .ok_or(ChatError::MessageNotFound)?;
+
+            if !this.repo.chat.is_member(source.chat_id, sender_id)? {
+                return Err(ChatError::NotMember);
+            }
+
+            if source.kind != MessageKind::Text.as_str() {
+                return Err(ChatError::InvalidMessage(
+                    "Only user messages can be forwarded".to_string(),
+                ));
+            }
+
+            if source.view_once {
+                return Err(ChatError::InvalidMessage(
+                    "View-once messages cannot be forwarded".to_string(),
+                ));
+            }
+
+            let source_chat = this
+                .repo
+                .chat
+                .find_chat_by_id(source.chat_id)?
+                .ok_or(ChatError::ChatNotFound)?;
+
+            if !source_chat.allow_forwarding {
+                return Err(ChatError::ForwardingDisabled);
+            }
+
+            let forwarded_from = ForwardedFrom::of(&source).unwrap_or(ForwardedFrom {
+                chat_id: source.chat_id,
+                message_id: source.id,
+                sender_id: source.sender_id,
+            });
+
+            let message = Self::post_message(
+                &this.repo,
+                chat_id,
+                sender_id,
+                sender_device_id,
+                body,
+                options,
+                Some(forwarded_from),
+            )?;
+            Self::queue_wake_ups(&this.repo, &message);
+
+            Ok(message)
+        })
+        .await
+    }
+
+    /// Allows or stops forwarding messages out of the chat. Messages already
+    /// forwarded stay where they are. The change is posted to the chat as a
+    /// `forwarding_changed` notice.
+    #[tracing::instrument(skip(self))]
+    pub async fn set_allow_forwarding(
+        &self,
+        chat_id: Uuid,
+        actor_id: Uuid,
+        actor_can_moderate: bool,
+        allow_forwarding: bool,
+    ) -> Result<ChatInfo, ChatError> {
+        let this = self.clone();
+        run_blocking(move || {
+            this.repo.transaction(|tx| {
+                let chat = tx
+                    .chat
+                    .find_chat_by_id(chat_id)?
+                    .ok_or(ChatError::ChatNotFound)?;
+
+                if chat.created_by != actor_id && !actor_can_moderate {
+                    return Err(ChatError::Forbidden(
+                        "Only the chat creator or a moderator can change forwarding".to_string(),
+                    ));
+                }
+
+                if chat.allow_forwarding == allow_forwarding {
+                    return Self::chat_info(tx, chat);
+                }
+
+                let chat = tx.chat.set_allow_forwarding(chat_id, allow_forwarding)?;
+
+                Self::post_notice(
+                    tx,
+                    chat_id,
+                    actor_id,
+                    Notice::ForwardingChanged {
+                        allow_forwarding,
+                        changed_by: actor_id,
+                    },
+                )?;
+
+                Self::chat_info(tx, chat)
+            })
+        })
+        .await
+    }
+}
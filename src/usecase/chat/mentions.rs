@@ -0,0 +1,191 @@
+use uuid::Uuid;
+
+use super::error::ChatError;
+use super::service::{ChatService, MessageOptions};
+use crate::repository::Repository;
+use crate::repository::RepositoryError;
+use crate::repository::chat::{Chat, Mention, ReadState};
+use crate::usecase::auth::permissions;
+use crate::usecase::blocking::run_blocking;
+
+const MAX_MENTIONS_PER_MESSAGE: usize = 50;
+const MAX_MENTIONS_PER_PAGE: i64 = 200;
+
+/// Where a member stopped reading a chat, what came in since, and whether
+/// they muted it.
+pub struct ReadStateInfo {
+    pub unread_count: i64,
+    /// Unread messages that mention the member, by name or through `@all`.
+    pub mention_count: i64,
+    pub last_read_at: String,
+    pub muted: bool,
+}
+
+pub struct MentionInfo {
+    pub message_id: Uuid,
+    pub chat_id: Uuid,
+    pub sender_id: Uuid,
+    /// The message mentioned everyone rather than the member by name.
+    pub mention_all: bool,
+    /// The message is at or before the member's read marker.
+    pub read: bool,
+    pub created_at: String,
+}
+
+impl ChatService {
+    /// Marks the chat read up to and including `message_id`. Reading an
+    /// older message leaves the marker where it is.
+    pub async fn mark_read(
+        &self,
+        chat_id: Uuid,
+        user_id: Uuid,
+        message_id: Uuid,
+    ) -> Result<ReadStateInfo, ChatError> {
+        let this = self.clone();
+        run_blocking(move || {
+            let is_member = this.repo.chat.is_member(chat_id, user_id)?;
+
+            if !is_member {
+                return Err(ChatError::NotMember);
+            }
+
+            let message = this
+                .repo
+                .chat
+                .get_message_by_id(message_id)?
+                .filter(|m| m.chat_id == chat_id)
+                .ok_or(ChatError::MessageNotFound)?;
+
+            this.repo
+                .chat
+                .mark_read(chat_id, user_id, message.created_at)?;
+
+            Self::read_state(&this.repo, chat_id, user_id)?.ok_or(ChatError::NotMember)
+        })
+        .await
+    }
+
+    /// Mutes or unmutes the chat for the caller. Muted chats still notify
+    /// of messages that mention the caller.
+    pub async fn set_muted(
+        &self,
+        chat_id: Uuid,
+        user_id: Uuid,
+        muted: bool,
+    ) -> Result<ReadStateInfo, ChatError> {
+        let this = self.clone();
+        run_blocking(move || {
+            this.repo
+                .chat
+                .set_muted(chat_id, user_id, muted)
+                .map_err(|e| match e {
+                    RepositoryError::NotFound => ChatError::NotMember,
+                    e => ChatError::from(e),
+                })?;
+
+            Self::read_state(&this.repo, chat_id, user_id)?.ok_or(ChatError::NotMember)
+        })
+        .await
+    }
+
+    /// Messages that mention the caller across their chats, newest first.
+    pub async fn get_mentions(
+        &self,
+        user_id: Uuid,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<MentionInfo>, ChatError> {
+        let this = self.clone();
+        run_blocking(move || {
+            let mentions = this.repo.chat.get_mentions(
+                user_id,
+                limit.clamp(1, MAX_MENTIONS_PER_PAGE),
+                offset.max(0),
+            )?;
+
+            Ok(mentions.into_iter().map(MentionInfo::from).collect())
+        })
+        .await
+    }
+
+    /// Mentioned users have to be members of the chat. Mentioning everyone
+    /// is for the chat's creator and for roles with `chats.moderate`; the
+    /// role is looked up here so scheduled messages are checked again when
+    /// they are posted.
+    pub(super) fn check_mentions(
+        repo: &Repository,
+        chat: &Chat,
+        sender_id: Uuid,
+        options: &MessageOptions,
+    ) -> Result<(), ChatError> {
+        if options.mentions.len() > MAX_MENTIONS_PER_MESSAGE {
+            return Err(ChatError::InvalidMessage(format!(
+                "At most {} mentions per message",
+                MAX_MENTIONS_PER_MESSAGE
+            )));
+        }
+
+        for user_id in &options.mentions {
+            if !repo.chat.is_member(chat.id, *user_id)? {
+                return Err(ChatError::InvalidMessage(format!(
+                    "Mentioned user {} is not a member of the chat",
+                    user_id
+                )));
+            }
+        }
+
+        if options.mention_all && chat.created_by != sender_id {
+            let sender = repo
+                .auth
+                .find_by_id(sender_id)?
+                .ok_or(ChatError::NotMember)?;
+            let granted = repo.auth.find_role_permissions(sender.role_id)?;
+
+            if !granted.iter().any(|p| p == permissions::CHATS_MODERATE) {
+                return Err(ChatError::Forbidden(
+                    "Only the chat creator or a moderator can mention everyone".to_string(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The member's read state in the chat; `None` for non-members.
+    pub(super) fn read_state(
+        repo: &Repository,
+        chat_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Option<ReadStateInfo>, ChatError> {
+        Ok(repo
+            .chat
+            .get_read_states(user_id, &[chat_id])?
+            .into_iter()
+            .next()
+            .map(ReadStateInfo::from))
+    }
+}
+
+impl From<ReadState> for ReadStateInfo {
+    fn from(state: ReadState) -> Self {
+        Self {
+            unread_count: state.unread_count,
+            mention_count: state.mention_count,
+            last_read_at: state.last_read_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+            muted: state.muted,
+        }
+    }
+}
+
+impl From<Mention> for MentionInfo {
+    fn from(mention: Mention) -> Self {
+        Self {
+            message_id: mention.message_id,
+            chat_id: mention.chat_id,
+            sender_id: mention.sender_id,
+            mention_all: mention.mention_all,
+            read: mention.created_at <= mention.last_read_at,
+            created_at: mention.created_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+        }
+    }
+}
@@ -1,5 +1,22 @@
 pub mod error;
+pub mod events;
+pub mod forwarding;
+pub mod mentions;
+pub mod notice;
+pub mod pins;
+pub mod polls;
+pub mod scheduled;
 pub mod service;
 
 pub use error::ChatError;
-pub use service::{ChatInfo, ChatMemberInfo, ChatService, MessageInfo};
+pub use events::ChatEvent;
+pub use forwarding::ForwardedFrom;
+pub use mentions::{MentionInfo, ReadStateInfo};
+pub use notice::Notice;
+pub use pins::PinInfo;
+pub use polls::{PollDraft, PollInfo, PollOptionInfo};
+pub use scheduled::{ScheduledDelivery, ScheduledMessageInfo};
+pub use service::{
+    ChatInfo, ChatMemberInfo, ChatService, MessageBody, MessageInfo, MessageOptions, RekeyInfo,
+    SenderKeyBundle, SenderKeyInfo,
+};
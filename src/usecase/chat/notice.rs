@@ -0,0 +1,114 @@
+use uuid::Uuid;
+
+use crate::repository::chat::MessageKind;
+
+/// A server-generated entry in the chat timeline. Its kind tells clients
+/// what happened and the payload, stored unencrypted, who was involved.
+#[derive(Debug, Clone)]
+pub enum Notice {
+    /// A member joined without an invitation: the creator of a new chat.
+    MemberJoined {
+        user_id: Uuid,
+    },
+    MemberInvited {
+        user_id: Uuid,
+        invited_by: Uuid,
+    },
+    MemberLeft {
+        user_id: Uuid,
+    },
+    MemberRemoved {
+        user_id: Uuid,
+        removed_by: Uuid,
+    },
+    ChatRenamed {
+        old_name: String,
+        name: String,
+        renamed_by: Uuid,
+    },
+    MessageTtlChanged {
+        ttl_seconds: Option<i32>,
+        changed_by: Uuid,
+    },
+    ForwardingChanged {
+        allow_forwarding: bool,
+        changed_by: Uuid,
+    },
+    PollsChanged {
+        allow_polls: bool,
+        changed_by: Uuid,
+    },
+    MessagePinned {
+        message_id: Uuid,
+        pinned_by: Uuid,
+    },
+    MessageUnpinned {
+        message_id: Uuid,
+        unpinned_by: Uuid,
+    },
+}
+
+impl Notice {
+    pub fn kind(&self) -> MessageKind {
+        match self {
+            Self::MemberJoined { .. } => MessageKind::MemberJoined,
+            Self::MemberInvited { .. } => MessageKind::MemberInvited,
+            Self::MemberLeft { .. } => MessageKind::MemberLeft,
+            Self::MemberRemoved { .. } => MessageKind::MemberRemoved,
+            Self::ChatRenamed { .. } => MessageKind::ChatRenamed,
+            Self::MessageTtlChanged { .. } => MessageKind::MessageTtlChanged,
+            Self::ForwardingChanged { .. } => MessageKind::ForwardingChanged,
+            Self::PollsChanged { .. } => MessageKind::PollsChanged,
+            Self::MessagePinned { .. } => MessageKind::MessagePinned,
+            Self::MessageUnpinned { .. } => MessageKind::MessageUnpinned,
+        }
+    }
+
+    pub fn payload(&self) -> serde_json::Value {
+        match self {
+            Self::MemberJoined { user_id } | Self::MemberLeft { user_id } => {
+                serde_json::json!({ "user_id": user_id })
+            }
+            Self::MemberInvited {
+                user_id,
+                invited_by,
+            } => serde_json::json!({ "user_id": user_id, "invited_by": invited_by }),
+            Self::MemberRemoved {
+                user_id,
+                removed_by,
+            } => serde_json::json!({ "user_id": user_id, "removed_by": removed_by }),
+            Self::ChatRenamed {
+                old_name,
+                name,
+                renamed_by,
+            } => serde_json::json!({
+                "old_name": old_name,
+                "name": name,
+                "renamed_by": renamed_by,
+            }),
+            Self::MessageTtlChanged {
+                ttl_seconds,
+                changed_by,
+            } => serde_json::json!({ "ttl_seconds": ttl_seconds, "changed_by": changed_by }),
+            Self::ForwardingChanged {
+                allow_forwarding,
+                changed_by,
+            } => serde_json::json!({
+                "allow_forwarding": allow_forwarding,
+                "changed_by": changed_by,
+            }),
+            Self::PollsChanged {
+                allow_polls,
+                changed_by,
+            } => serde_json::json!({ "allow_polls": allow_polls, "changed_by": changed_by }),
+            Self::MessagePinned {
+                message_id,
+                pinned_by,
+            } => serde_json::json!({ "message_id": message_id, "pinned_by": pinned_by }),
+            Self::MessageUnpinned {
+                message_id,
+                unpinned_by,
+            } => serde_json::json!({ "message_id": message_id, "unpinned_by": unpinned_by }),
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use chrono::Utc;
+use uuid::Uuid;
+
+use super::error::ChatError;
+use super::notice::Notice;
+use super::service::ChatService;
+use crate::repository::Repository;
+use crate::repository::RepositoryError;
+use crate::repository::chat::{Chat, MessageKind, NewPinnedMessage, PinnedMessage};
+use crate::usecase::blocking::run_blocking;
+
+const MAX_PINS_PER_CHAT: usize = 50;
+
+pub struct PinInfo {
+    pub message_id: Uuid,
+    pub chat_id: Uuid,
+    pub pinned_by: Uuid,
+    pub pinned_at: String,
+}
+
+impl ChatService {
+    /// Pins a user message for everyone in the chat. Pinning a pinned
+    /// message changes nothing.
+    pub async fn pin_message(
+        &self,
+        chat_id: Uuid,
+        actor_id: Uuid,
+        actor_can_moderate: bool,
+        message_id: Uuid,
+    ) -> Result<PinInfo, ChatError> {
+        let this = self.clone();
+        run_blocking(move || {
+            this.repo.transaction(|tx| {
+                lock_pins(tx, chat_id, actor_id, actor_can_moderate)?;
+
+                let message = tx
+                    .chat
+                    .get_message_by_id(message_id)?
+                    .filter(|m| m.chat_id == chat_id)
+                    .filter(|m| {
+                        m.expires_at
+                            .is_none_or(|expires_at| expires_at > Utc::now().naive_utc())
+                    })
+                    .ok_or(ChatError::MessageNotFound)?;
+
+                if message.kind != MessageKind::Text.as_str()
+                    && message.kind != MessageKind::Poll.as_str()
+                {
+                    return Err(ChatError::InvalidMessage(
+                        "Only user messages can be pinned".to_string(),
+                    ));
+                }
+
+                if message.view_once {
+                    return Err(ChatError::InvalidMessage(
+                        "View-once messages cannot be pinned".to_string(),
+                    ));
+                }
+
+                if let Some(pin) = tx.chat.find_pin(message_id)? {
+                    return Ok(PinInfo::from(pin));
+                }
+
+                if tx.chat.get_pins(chat_id)?.len() >= MAX_PINS_PER_CHAT {
+                    return Err(ChatError::TooManyPins(MAX_PINS_PER_CHAT));
+                }
+
+                let pin = tx.chat.pin_message(NewPinnedMessage {
+                    message_id,
+                    chat_id,
+                    pinned_by: actor_id,
+                })?;
+
+                Self::post_notice(
+                    tx,
+                    chat_id,
+                    actor_id,
+                    Notice::MessagePinned {
+                        message_id,
+                        pinned_by: actor_id,
+                    },
+                )?;
+
+                Ok(PinInfo::from(pin))
+            })
+        })
+        .await
+    }
+
+    pub async fn unpin_message(
+        &self,
+        chat_id: Uuid,
+        actor_id: Uuid,
+        actor_can_moderate: bool,
+        message_id: Uuid,
+    ) -> Result<(), ChatError> {
+        let this = self.clone();
+        run_blocking(move || {
+            this.repo.transaction(|tx| {
+                lock_pins(tx, chat_id, actor_id, actor_can_moderate)?;
+
+                match tx.chat.unpin_message(chat_id, message_id) {
+                    Ok(()) => {}
+                    Err(RepositoryError::NotFound) => return Err(ChatError::PinNotFound),
+                    Err(e) => return Err(e.into()),
+                }
+
+                Self::post_notice(
+                    tx,
+                    chat_id,
+                    actor_id,
+                    Notice::MessageUnpinned {
+                        message_id,
+                        unpinned_by: actor_id,
+                    },
+                )?;
+
+                Ok(())
+            })
+        })
+        .await
+    }
+
+    /// The chat's pins, newest first.
+    pub async fn get_pins(&self, chat_id: Uuid, user_id: Uuid) -> Result<Vec<PinInfo>, ChatError> {
+        let this = self.clone();
+        run_blocking(move || {
+            let is_member = this.repo.chat.is_member(chat_id, user_id)?;
+
+            if !is_member {
+                return Err(ChatError::NotMember);
+            }
+
+            let pins = this.repo.chat.get_pins(chat_id)?;
+
+            Ok(pins.into_iter().map(PinInfo::from).collect())
+        })
+        .await
+    }
+}
+
+/// Locks the chat against concurrent pin changes, which keeps the limit
+/// exact, once the actor is known to be allowed to make them.
+fn lock_pins(
+    tx: &Repository,
+    chat_id: Uuid,
+    actor_id: Uuid,
+    actor_can_moderate: bool,
+) -> Result<Chat, ChatError> {
+    let chat = tx.chat.lock_chat(chat_id)?.ok_or(ChatError::ChatNotFound)?;
+
+    if chat.created_by != actor_id && !actor_can_moderate {
+        return Err(ChatError::Forbidden(
+            "Only the chat creator or a moderator can pin messages".to_string(),
+        ));
+    }
+
+    Ok(chat)
+}
+
+impl From<PinnedMessage> for PinInfo {
+    fn from(pin: PinnedMessage) -> Self {
+        Self {
+            message_id: pin.message_id,
+            chat_id: pin.chat_id,
+            pinned_by: pin.pinned_by,
+            pinned_at: pin.pinned_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+        }
+    }
+}
@@ -0,0 +1,372 @@
+use std::collections::HashMap;
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use uuid::Uuid;
+
+use super::error::ChatError;
+use super::events::ChatEvent;
+use super::notice::Notice;
+use super::service::{ChatInfo, ChatService, MessageInfo};
+use crate::repository::Repository;
+use crate::repository::chat::{MessageKind, NewMessage, Poll, PollVote};
+use crate::usecase::blocking::run_blocking;
+
+const MAX_POLL_OPTIONS: usize = 10;
+const MAX_POLL_QUESTION_CHARS: usize = 300;
+const MAX_POLL_OPTION_CHARS: usize = 100;
+
+/// A poll as the creator writes it.
+#[derive(Debug, Clone)]
+pub struct PollDraft {
+    pub question: String,
+    /// Between 2 and 10 distinct options.
+    pub options: Vec<String>,
+    pub multiple_choice: bool,
+    /// Tallies leave out who voted for what.
+    pub anonymous: bool,
+    pub closes_at: Option<NaiveDateTime>,
+}
+
+/// A poll with its live tallies, as seen by one member.
+pub struct PollInfo {
+    pub question: String,
+    pub options: Vec<PollOptionInfo>,
+    pub multiple_choice: bool,
+    pub anonymous: bool,
+    pub closes_at: Option<String>,
+    pub closed: bool,
+    /// Members who picked at least one option.
+    pub voter_count: i64,
+    /// Indexes of the options the caller picked.
+    pub own_votes: Vec<i32>,
+}
+
+pub struct PollOptionInfo {
+    pub text: String,
+    pub vote_count: i64,
+    /// Who picked the option; `None` in anonymous polls.
+    pub voters: Option<Vec<Uuid>>,
+}
+
+impl ChatService {
+    /// Posts a poll. Unlike other messages its question and options are
+    /// stored in the clear, so the server can tally votes; chats can turn
+    /// polls off.
+    #[tracing::instrument(skip(self))]
+    pub async fn create_poll(
+        &self,
+        chat_id: Uuid,
+        sender_id: Uuid,
+        draft: PollDraft,
+    ) -> Result<MessageInfo, ChatError> {
+        check_poll(&draft)?;
+
+        let this = self.clone();
+        run_blocking(move || {
+            let message = this.repo.transaction(|tx| {
+                if !tx.chat.is_member(chat_id, sender_id)? {
+                    return Err(ChatError::NotMember);
+                }
+
+                let chat = tx
+                    .chat
+                    .find_chat_by_id(chat_id)?
+                    .ok_or(ChatError::ChatNotFound)?;
+
+                if !chat.allow_polls {
+                    return Err(ChatError::PollsDisabled);
+                }
+
+                let expires_at = chat
+                    .message_ttl_seconds
+                    .map(|ttl| Utc::now().naive_utc() + Duration::seconds(ttl.into()));
+
+                let message = tx.chat.create_message(
+                    NewMessage {
+                        chat_id,
+                        sender_id,
+                        sender_device_id: None,
+                        encrypted_content: None,
+                        epoch: None,
+                        kind: MessageKind::Poll.as_str().to_string(),
+                        payload: None,
+                        expires_at,
+                        view_once: false,
+                        mentions: Vec::new(),
+                        mention_all: false,
+                        forwarded_from_chat_id: None,
+                        forwarded_from_message_id: None,
+                        forwarded_from_sender_id: None,
+                    },
+                    &HashMap::new(),
+                )?;
+
+                let poll = tx.chat.create_poll(Poll {
+                    message_id: message.id,
+                    question: draft.question,
+                    options: draft.options,
+                    multiple_choice: draft.multiple_choice,
+                    anonymous: draft.anonymous,
+                    closes_at: draft.closes_at,
+                })?;
+
+                Self::record_event(tx, chat_id, ChatEvent::message_created(&message))?;
+
+                Ok(MessageInfo {
+                    poll: Some(PollInfo::tally(poll, &[], sender_id)),
+                    ..MessageInfo::from(message)
+                })
+            })?;
+            Self::queue_wake_ups(&this.repo, &message);
+
+            Ok(message)
+        })
+        .await
+    }
+
+    /// Replaces the caller's votes in a poll with `option_indexes`; an empty
+    /// list withdraws them. Single-choice polls take at most one option.
+    #[tracing::instrument(skip(self))]
+    pub async fn vote(
+        &self,
+        chat_id: Uuid,
+        user_id: Uuid,
+        message_id: Uuid,
+        mut option_indexes: Vec<i32>,
+    ) -> Result<PollInfo, ChatError> {
+        option_indexes.sort();
+        option_indexes.dedup();
+
+        let this = self.clone();
+        run_blocking(move || {
+            this.repo.transaction(|tx| {
+                if !tx.chat.is_member(chat_id, user_id)? {
+                    return Err(ChatError::NotMember);
+                }
+
+                let now = Utc::now().naive_utc();
+                tx.chat
+                    .get_message_by_id(message_id)?
+                    .filter(|m| m.chat_id == chat_id)
+                    .filter(|m| m.expires_at.is_none_or(|expires_at| expires_at > now))
+                    .ok_or(ChatError::MessageNotFound)?;
+                let poll = tx
+                    .chat
+                    .get_polls(&[message_id])?
+                    .into_iter()
+                    .next()
+                    .ok_or(ChatError::MessageNotFound)?;
+
+                let chat = tx
+                    .chat
+                    .find_chat_by_id(chat_id)?
+                    .ok_or(ChatError::ChatNotFound)?;
+
+                if !chat.allow_polls {
+                    return Err(ChatError::PollsDisabled);
+                }
+
+                if poll.closes_at.is_some_and(|closes_at| closes_at <= now) {
+                    return Err(ChatError::PollClosed);
+                }
+
+                if !poll.multiple_choice && option_indexes.len() > 1 {
+                    return Err(ChatError::InvalidPoll(
+                        "The poll takes a single option".to_string(),
+                    ));
+                }
+
+                if option_indexes
+                    .iter()
+                    .any(|index| usize::try_from(*index).map_or(true, |i| i >= poll.options.len()))
+                {
+                    return Err(ChatError::InvalidPoll(format!(
+                        "Options are numbered 0 to {}",
+                        poll.options.len() - 1
+                    )));
+                }
+
+                tx.chat
+                    .set_poll_votes(message_id, user_id, &option_indexes)?;
+
+                let votes = tx.chat.get_poll_votes(&[message_id])?;
+
+                Ok(PollInfo::tally(poll, &votes, user_id))
+            })
+        })
+        .await
+    }
+
+    /// Allows or stops new polls and votes in the chat. The change is posted
+    /// to the chat as a `polls_changed` notice.
+    #[tracing::instrument(skip(self))]
+    pub async fn set_allow_polls(
+        &self,
+        chat_id: Uuid,
+        actor_id: Uuid,
+        actor_can_moderate: bool,
+        allow_polls: bool,
+    ) -> Result<ChatInfo, ChatError> {
+        let this = self.clone();
+        run_blocking(move || {
+            this.repo.transaction(|tx| {
+                let chat = tx
+                    .chat
+                    .find_chat_by_id(chat_id)?
+                    .ok_or(ChatError::ChatNotFound)?;
+
+                if chat.created_by != actor_id && !actor_can_moderate {
+                    return Err(ChatError::Forbidden(
+                        "Only the chat creator or a moderator can change polls".to_string(),
+                    ));
+                }
+
+                if chat.allow_polls == allow_polls {
+                    return Self::chat_info(tx, chat);
+                }
+
+                let chat = tx.chat.set_allow_polls(chat_id, allow_polls)?;
+
+                Self::post_notice(
+                    tx,
+                    chat_id,
+                    actor_id,
+                    Notice::PollsChanged {
+                        allow_polls,
+                        changed_by: actor_id,
+                    },
+                )?;
+
+                Self::chat_info(tx, chat)
+            })
+        })
+        .await
+    }
+
+    /// Tallies of the polls among `message_ids`, keyed by message, as seen
+    /// by `user_id`.
+    pub(super) fn polls(
+        repo: &Repository,
+        message_ids: &[Uuid],
+        user_id: Uuid,
+    ) -> Result<HashMap<Uuid, PollInfo>, ChatError> {
+        let polls = repo.chat.get_polls(message_ids)?;
+
+        if polls.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let poll_ids: Vec<Uuid> = polls.iter().map(|p| p.message_id).collect();
+        let mut votes: HashMap<Uuid, Vec<PollVote>> = HashMap::new();
+        for vote in repo.chat.get_poll_votes(&poll_ids)? {
+            votes.entry(vote.message_id).or_default().push(vote);
+        }
+
+        Ok(polls
+            .into_iter()
+            .map(|poll| {
+                let message_id = poll.message_id;
+                let votes = votes.remove(&message_id).unwrap_or_default();
+                (message_id, PollInfo::tally(poll, &votes, user_id))
+            })
+            .collect())
+    }
+}
+
+impl PollInfo {
+    fn tally(poll: Poll, votes: &[PollVote], user_id: Uuid) -> Self {
+        let mut voters: Vec<Uuid> = votes.iter().map(|v| v.user_id).collect();
+        voters.sort();
+        voters.dedup();
+
+        let options = poll
+            .options
+            .into_iter()
+            .enumerate()
+            .map(|(index, text)| {
+                let picked: Vec<Uuid> = votes
+                    .iter()
+                    .filter(|v| v.option_index as usize == index)
+                    .map(|v| v.user_id)
+                    .collect();
+
+                PollOptionInfo {
+                    text,
+                    vote_count: picked.len() as i64,
+                    voters: (!poll.anonymous).then_some(picked),
+                }
+            })
+            .collect();
+
+        let mut own_votes: Vec<i32> = votes
+            .iter()
+            .filter(|v| v.user_id == user_id)
+            .map(|v| v.option_index)
+            .collect();
+        own_votes.sort();
+
+        Self {
+            question: poll.question,
+            options,
+            multiple_choice: poll.multiple_choice,
+            anonymous: poll.anonymous,
+            closes_at: poll
+                .closes_at
+                .map(|closes_at| closes_at.format("%Y-%m-%d %H:%M:%S").to_string()),
+            closed: poll
+                .closes_at
+                .is_some_and(|closes_at| closes_at <= Utc::now().naive_utc()),
+            voter_count: voters.len() as i64,
+            own_votes,
+        }
+    }
+}
+
+fn check_poll(draft: &PollDraft) -> Result<(), ChatError> {
+    if draft.question.trim().is_empty() {
+        return Err(ChatError::InvalidPoll(
+            "Question cannot be empty".to_string(),
+        ));
+    }
+
+    if draft.question.chars().count() > MAX_POLL_QUESTION_CHARS {
+        return Err(ChatError::InvalidPoll(format!(
+            "Question is limited to {} characters",
+            MAX_POLL_QUESTION_CHARS
+        )));
+    }
+
+    if !(2..=MAX_POLL_OPTIONS).contains(&draft.options.len()) {
+        return Err(ChatError::InvalidPoll(format!(
+            "A poll has between 2 and {} options",
+            MAX_POLL_OPTIONS
+        )));
+    }
+
+    for (index, option) in draft.options.iter().enumerate() {
+        if option.trim().is_empty() || option.chars().count() > MAX_POLL_OPTION_CHARS {
+            return Err(ChatError::InvalidPoll(format!(
+                "Options must be between 1 and {} characters",
+                MAX_POLL_OPTION_CHARS
+            )));
+        }
+
+        if draft.options[..index].contains(option) {
+            return Err(ChatError::InvalidPoll(format!(
+                "Option \"{}\" is listed twice",
+                option
+            )));
+        }
+    }
+
+    if draft
+        .closes_at
+        .is_some_and(|closes_at| closes_at <= Utc::now().naive_utc())
+    {
+        return Err(ChatError::InvalidPoll(
+            "closes_at must be in the future".to_string(),
+        ));
+    }
+
+    Ok(())
+}
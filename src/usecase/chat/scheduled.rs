@@ -0,0 +1,383 @@
+use std::collections::HashMap;
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use uuid::Uuid;
+
+use super::error::ChatError;
+use super::service::{ChatService, MessageBody, MessageOptions};
+use crate::repository::Repository;
+use crate::repository::scheduled_messages::{
+    NewScheduledMessage, ScheduledMessage, ScheduledMessageContent,
+};
+use crate::usecase::blocking::run_blocking;
+
+const MAX_SCHEDULED_MESSAGES_PER_USER: i64 = 100;
+const MAX_SCHEDULE_AHEAD_DAYS: i64 = 365;
+
+pub struct ScheduledMessageInfo {
+    pub id: Uuid,
+    pub chat_id: Uuid,
+    pub sender_device_id: Uuid,
+    pub send_at: String,
+    pub attachment_ids: Vec<Uuid>,
+    pub view_once: bool,
+    pub mentions: Vec<Uuid>,
+    pub mention_all: bool,
+    /// `pending`, or `failed` once the scheduler gave up on it.
+    pub status: String,
+    /// Why the message could not be posted.
+    pub error: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// What one run of the scheduler did.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScheduledDelivery {
+    pub sent: usize,
+    pub failed: usize,
+}
+
+/// How a scheduled message is stored: per-device envelopes as JSON, or the
+/// sender-key epoch and ciphertext.
+struct StoredBody {
+    envelopes: Option<String>,
+    epoch: Option<i32>,
+    ciphertext: Option<String>,
+}
+
+impl ChatService {
+    /// Stores a message to be posted at `send_at`. It is checked like a
+    /// message sent now, so mistakes surface right away; the scheduler checks
+    /// it again when it is due.
+    pub async fn schedule_message(
+        &self,
+        chat_id: Uuid,
+        sender_id: Uuid,
+        sender_device_id: Uuid,
+        send_at: NaiveDateTime,
+        body: MessageBody,
+        options: MessageOptions,
+    ) -> Result<ScheduledMessageInfo, ChatError> {
+        let this = self.clone();
+        run_blocking(move || {
+            check_send_at(send_at)?;
+            Self::check_message(
+                &this.repo,
+                chat_id,
+                sender_id,
+                sender_device_id,
+                &body,
+                &options,
+            )?;
+
+            let scheduled = this
+                .repo
+                .scheduled_messages
+                .count_scheduled_messages(sender_id)?;
+
+            if scheduled >= MAX_SCHEDULED_MESSAGES_PER_USER {
+                return Err(ChatError::InvalidSchedule(format!(
+                    "At most {} scheduled messages",
+                    MAX_SCHEDULED_MESSAGES_PER_USER
+                )));
+            }
+
+            let stored = StoredBody::try_from(body)?;
+            let message =
+                this.repo
+                    .scheduled_messages
+                    .create_scheduled_message(NewScheduledMessage {
+                        chat_id,
+                        sender_id,
+                        sender_device_id,
+                        send_at,
+                        envelopes: stored.envelopes,
+                        epoch: stored.epoch,
+                        ciphertext: stored.ciphertext,
+                        attachment_ids: options.attachment_ids,
+                        view_once: options.view_once,
+                        mentions: options.mentions,
+                        mention_all: options.mention_all,
+                    })?;
+
+            Ok(ScheduledMessageInfo::from(message))
+        })
+        .await
+    }
+
+    /// The caller's pending and failed scheduled messages, soonest first.
+    pub async fn list_scheduled_messages(
+        &self,
+        user_id: Uuid,
+        chat_id: Option<Uuid>,
+    ) -> Result<Vec<ScheduledMessageInfo>, ChatError> {
+        let this = self.clone();
+        run_blocking(move || {
+            let messages = this
+                .repo
+                .scheduled_messages
+                .list_scheduled_messages(user_id, chat_id)?;
+
+            Ok(messages
+                .into_iter()
+                .map(ScheduledMessageInfo::from)
+                .collect())
+        })
+        .await
+    }
+
+    /// Replaces a scheduled message's content, re-encrypted on
+    /// `sender_device_id`, and optionally its time. A failed message becomes
+    /// pending again.
+    pub async fn update_scheduled_message(
+        &self,
+        scheduled_id: Uuid,
+        user_id: Uuid,
+        sender_device_id: Uuid,
+        send_at: Option<NaiveDateTime>,
+        body: MessageBody,
+        options: MessageOptions,
+    ) -> Result<ScheduledMessageInfo, ChatError> {
+        let this = self.clone();
+        run_blocking(move || {
+            if let Some(send_at) = send_at {
+                check_send_at(send_at)?;
+            }
+
+            this.repo.transaction(|tx| {
+                let scheduled = find_own(tx, scheduled_id, user_id)?;
+
+                Self::check_message(
+                    tx,
+                    scheduled.chat_id,
+                    user_id,
+                    sender_device_id,
+                    &body,
+                    &options,
+                )?;
+
+                let stored = StoredBody::try_from(body)?;
+                let message = tx.scheduled_messages.update_scheduled_message(
+                    scheduled_id,
+                    ScheduledMessageContent {
+                        sender_device_id,
+                        send_at: send_at.unwrap_or(scheduled.send_at),
+                        envelopes: stored.envelopes,
+                        epoch: stored.epoch,
+                        ciphertext: stored.ciphertext,
+                        attachment_ids: options.attachment_ids,
+                        view_once: options.view_once,
+                        mentions: options.mentions,
+                        mention_all: options.mention_all,
+                    },
+                )?;
+
+                Ok(ScheduledMessageInfo::from(message))
+            })
+        })
+        .await
+    }
+
+    pub async fn cancel_scheduled_message(
+        &self,
+        scheduled_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<(), ChatError> {
+        let this = self.clone();
+        run_blocking(move || {
+            this.repo.transaction(|tx| {
+                find_own(tx, scheduled_id, user_id)?;
+                tx.scheduled_messages
+                    .delete_scheduled_message(scheduled_id)?;
+
+                Ok(())
+            })
+        })
+        .await
+    }
+
+    /// Posts up to `batch_size` due messages, one transaction each. A message
+    /// the chat no longer accepts is marked failed instead.
+    ///
+    /// Each message stays locked from claim to post, and other instances skip
+    /// locked rows, so no message is posted twice.
+    pub async fn deliver_scheduled_messages(
+        &self,
+        batch_size: i64,
+    ) -> Result<ScheduledDelivery, ChatError> {
+        let this = self.clone();
+        run_blocking(move || {
+            let now = Utc::now().naive_utc();
+            let mut delivery = ScheduledDelivery::default();
+
+            while ((delivery.sent + delivery.failed) as i64) < batch_size {
+                match this.deliver_next_scheduled(now)? {
+                    Some(true) => delivery.sent += 1,
+                    Some(false) => delivery.failed += 1,
+                    None => break,
+                }
+            }
+
+            Ok(delivery)
+        })
+        .await
+    }
+
+    /// Posts the earliest due message. Returns whether it was sent, or `None`
+    /// when nothing is due.
+    fn deliver_next_scheduled(&self, now: NaiveDateTime) -> Result<Option<bool>, ChatError> {
+        let mut claimed = None;
+        let mut delivered = None;
+
+        let outcome = self.repo.transaction(|tx| {
+            let Some(scheduled) = tx.scheduled_messages.claim_due_scheduled_message(now)? else {
+                return Ok(None);
+            };
+            claimed = Some(scheduled.id);
+
+            let options = MessageOptions {
+                attachment_ids: scheduled.attachment_ids.clone(),
+                view_once: scheduled.view_once,
+                mentions: scheduled.mentions.clone(),
+                mention_all: scheduled.mention_all,
+            };
+            let posted = scheduled_body(&scheduled).and_then(|body| {
+                Self::post_message(
+                    tx,
+                    scheduled.chat_id,
+                    scheduled.sender_id,
+                    scheduled.sender_device_id,
+                    body,
+                    options,
+                    None,
+                )
+            });
+
+            match posted {
+                Ok(message) => {
+                    tx.scheduled_messages
+                        .delete_scheduled_message(scheduled.id)?;
+                    tracing::info!(scheduled_id = %scheduled.id, message_id = %message.id, "Scheduled message sent");
+                    delivered = Some(message);
+
+                    Ok(Some(true))
+                }
+                Err(e @ (ChatError::Unavailable | ChatError::Internal(_))) => Err(e),
+                Err(e) => {
+                    tracing::info!(scheduled_id = %scheduled.id, error = %e, "Scheduled message rejected");
+                    tx.scheduled_messages
+                        .mark_scheduled_message_failed(scheduled.id, &e.to_string())?;
+
+                    Ok(Some(false))
+                }
+            }
+        });
+
+        if let (Ok(_), Some(message)) = (&outcome, &delivered) {
+            Self::queue_wake_ups(&self.repo, message);
+        }
+
+        // The transaction rolled back; park the message so it does not hold
+        // up the queue on every run.
+        match (outcome, claimed) {
+            (Err(ChatError::Internal(cause)), Some(scheduled_id)) => {
+                tracing::error!(%scheduled_id, "Failed to send scheduled message: {}", cause);
+                self.repo
+                    .scheduled_messages
+                    .mark_scheduled_message_failed(scheduled_id, "Internal error")?;
+
+                Ok(Some(false))
+            }
+            (outcome, _) => outcome,
+        }
+    }
+}
+
+fn check_send_at(send_at: NaiveDateTime) -> Result<(), ChatError> {
+    let now = Utc::now().naive_utc();
+
+    if send_at <= now {
+        return Err(ChatError::InvalidSchedule(
+            "send_at must be in the future".to_string(),
+        ));
+    }
+
+    if send_at > now + Duration::days(MAX_SCHEDULE_AHEAD_DAYS) {
+        return Err(ChatError::InvalidSchedule(format!(
+            "send_at must be within {} days",
+            MAX_SCHEDULE_AHEAD_DAYS
+        )));
+    }
+
+    Ok(())
+}
+
+/// The caller's scheduled message, locked against the scheduler. Someone
+/// else's is reported as missing.
+fn find_own(
+    repo: &Repository,
+    scheduled_id: Uuid,
+    user_id: Uuid,
+) -> Result<ScheduledMessage, ChatError> {
+    repo.scheduled_messages
+        .lock_scheduled_message(scheduled_id)?
+        .filter(|m| m.sender_id == user_id)
+        .ok_or(ChatError::ScheduledMessageNotFound)
+}
+
+fn scheduled_body(scheduled: &ScheduledMessage) -> Result<MessageBody, ChatError> {
+    match (&scheduled.envelopes, scheduled.epoch, &scheduled.ciphertext) {
+        (Some(envelopes), _, _) => serde_json::from_str::<HashMap<Uuid, String>>(envelopes)
+            .map(MessageBody::Envelopes)
+            .map_err(|e| ChatError::Internal(e.to_string())),
+        (None, Some(epoch), Some(ciphertext)) => Ok(MessageBody::SenderKey {
+            epoch,
+            ciphertext: ciphertext.clone(),
+        }),
+        _ => Err(ChatError::Internal(
+            "scheduled message without a body".to_string(),
+        )),
+    }
+}
+
+impl TryFrom<MessageBody> for StoredBody {
+    type Error = ChatError;
+
+    fn try_from(body: MessageBody) -> Result<Self, ChatError> {
+        match body {
+            MessageBody::Envelopes(envelopes) => Ok(Self {
+                envelopes: Some(
+                    serde_json::to_string(&envelopes)
+                        .map_err(|e| ChatError::Internal(e.to_string()))?,
+                ),
+                epoch: None,
+                ciphertext: None,
+            }),
+            MessageBody::SenderKey { epoch, ciphertext } => Ok(Self {
+                envelopes: None,
+                epoch: Some(epoch),
+                ciphertext: Some(ciphertext),
+            }),
+        }
+    }
+}
+
+impl From<ScheduledMessage> for ScheduledMessageInfo {
+    fn from(message: ScheduledMessage) -> Self {
+        Self {
+            id: message.id,
+            chat_id: message.chat_id,
+            sender_device_id: message.sender_device_id,
+            send_at: message.send_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+            attachment_ids: message.attachment_ids,
+            view_once: message.view_once,
+            mentions: message.mentions,
+            mention_all: message.mention_all,
+            status: message.status,
+            error: message.error,
+            created_at: message.created_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+            updated_at: message.updated_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+        }
+    }
+}
@@ -1,12 +1,37 @@
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
 use uuid::Uuid;
 
 use super::error::ChatError;
-use crate::repository::Repository;
-use crate::repository::chat::{Chat, Message};
+use super::events::ChatEvent;
+use super::forwarding::ForwardedFrom;
+use super::mentions::ReadStateInfo;
+use super::notice::Notice;
+use super::pins::PinInfo;
+use super::polls::PollInfo;
+use crate::repository::blobs::BlobStore;
+use crate::repository::chat::{
+    Chat, ChatMemberDetails, ChatRekey, MemberFilter, Message, MessageKind, NewMessage,
+    PinnedMessage, ReadState, RekeyReason,
+};
+use crate::repository::sender_keys::{NewSenderKey, SenderKey};
+use crate::repository::webhooks::NewWebhookEvent;
+use crate::repository::{Repository, RepositoryError};
+use crate::usecase::attachments::service::delete_blobs;
+use crate::usecase::blocking::run_blocking;
+use crate::usecase::devices::DeviceInfo;
+
+const MAX_ATTACHMENTS_PER_MESSAGE: usize = 10;
+const MAX_MESSAGE_TTL_SECONDS: i32 = 365 * 24 * 60 * 60;
+const MAX_MEMBERS_PER_PAGE: i64 = 500;
 
 #[derive(Clone)]
 pub struct ChatService {
-    repo: Repository,
+    pub(super) repo: Repository,
+    /// Attachments of reaped messages are deleted from here.
+    blobs: Arc<dyn BlobStore>,
 }
 
 pub struct ChatInfo {
@@ -14,207 +39,1034 @@ pub struct ChatInfo {
     pub name: String,
     pub created_by: Uuid,
     pub created_at: String,
+    pub epoch: i32,
+    pub message_ttl_seconds: Option<i32>,
+    pub allow_forwarding: bool,
+    pub allow_polls: bool,
+    /// The most recently pinned message.
+    pub latest_pin: Option<PinInfo>,
+    /// The requesting member's unread counts, on listings and lookups.
+    pub read_state: Option<ReadStateInfo>,
 }
 
 pub struct MessageInfo {
     pub id: Uuid,
     pub chat_id: Uuid,
     pub sender_id: Uuid,
-    pub encrypted_content: String,
+    pub sender_device_id: Option<Uuid>,
+    /// The envelope addressed to the reading device, or the shared content
+    /// of messages sent before devices existed.
+    pub encrypted_content: Option<String>,
+    /// Set on sender-key messages: the epoch whose keys decrypt them.
+    pub epoch: Option<i32>,
+    /// `text` for user messages; anything else is a server notice described
+    /// by `payload`.
+    pub kind: String,
+    pub payload: Option<serde_json::Value>,
+    /// Attachments sent with the message, readable by the chat's members.
+    pub attachment_ids: Vec<Uuid>,
+    pub expires_at: Option<String>,
+    pub view_once: bool,
+    pub mentions: Vec<Uuid>,
+    pub mention_all: bool,
+    pub forwarded_from: Option<ForwardedFrom>,
+    /// The poll of a `poll` message, with its current tallies.
+    pub poll: Option<PollInfo>,
+    pub created_at: String,
+}
+
+/// Optional parts of an outgoing message.
+#[derive(Debug, Clone, Default)]
+pub struct MessageOptions {
+    /// Fully uploaded attachments of the sender, at most 10.
+    pub attachment_ids: Vec<Uuid>,
+    /// Deliver the message once to each recipient device. Needs per-device
+    /// envelopes, since each device's copy is deleted as it is read.
+    pub view_once: bool,
+    /// Members to notify even if they muted the chat, at most 50. The server
+    /// cannot read the text, so clients send these alongside it.
+    pub mentions: Vec<Uuid>,
+    /// Mention every member; see [`ChatService::check_mentions`].
+    pub mention_all: bool,
+}
+
+/// How a message is encrypted for the chat's devices.
+pub enum MessageBody {
+    /// One ciphertext per device other than the sending one.
+    Envelopes(HashMap<Uuid, String>),
+    /// A single ciphertext under the sender's key for the given epoch.
+    SenderKey { epoch: i32, ciphertext: String },
+}
+
+/// Sender keys addressed to one device for one epoch.
+pub struct SenderKeyBundle {
+    pub epoch: i32,
+    pub sender_keys: Vec<SenderKeyInfo>,
+}
+
+pub struct SenderKeyInfo {
+    pub sender_id: Uuid,
+    pub sender_device_id: Uuid,
+    pub ciphertext: String,
+    pub created_at: String,
+}
+
+pub struct RekeyInfo {
+    pub epoch: i32,
+    pub reason: String,
+    pub user_id: Option<Uuid>,
     pub created_at: String,
 }
 
 pub struct ChatMemberInfo {
     pub user_id: Uuid,
     pub username: String,
+    pub role_id: i32,
+    pub invited_by: Option<Uuid>,
     pub joined_at: String,
 }
 
 impl ChatService {
-    pub fn new(repo: Repository) -> Self {
-        Self { repo }
+    pub fn new(repo: Repository, blobs: Arc<dyn BlobStore>) -> Self {
+        Self { repo, blobs }
     }
 
-    pub fn create_chat(&self, name: String, creator_id: Uuid) -> Result<ChatInfo, ChatError> {
-        if name.trim().is_empty() {
-            return Err(ChatError::InvalidChatName(
-                "Chat name cannot be empty".to_string(),
-            ));
-        }
+    pub async fn create_chat(&self, name: String, creator_id: Uuid) -> Result<ChatInfo, ChatError> {
+        check_chat_name(&name)?;
 
-        if name.len() > 255 {
-            return Err(ChatError::InvalidChatName("Chat name too long".to_string()));
-        }
+        let this = self.clone();
+        run_blocking(move || {
+            this.repo.transaction(|tx| {
+                let chat = tx.chat.create_chat(name, creator_id)?;
 
-        let chat = self
-            .repo
-            .chat
-            .create_chat(name, creator_id)
-            .map_err(|e| ChatError::Internal(e))?;
+                tx.chat.add_member(chat.id, creator_id, None)?;
 
-        self.repo
-            .chat
-            .add_member(chat.id, creator_id, None)
-            .map_err(|e| ChatError::Internal(e))?;
+                Self::post_notice(
+                    tx,
+                    chat.id,
+                    creator_id,
+                    Notice::MemberJoined {
+                        user_id: creator_id,
+                    },
+                )?;
+
+                Self::record_event(
+                    tx,
+                    chat.id,
+                    ChatEvent::MemberJoined {
+                        user_id: creator_id,
+                        invited_by: None,
+                    },
+                )?;
 
-        Ok(ChatInfo::from(chat))
+                Ok(ChatInfo::from(chat))
+            })
+        })
+        .await
     }
 
-    pub fn get_user_chats(&self, user_id: Uuid) -> Result<Vec<ChatInfo>, ChatError> {
-        let chats = self
-            .repo
-            .chat
-            .get_user_chats(user_id)
-            .map_err(|e| ChatError::Internal(e))?;
+    pub async fn get_user_chats(&self, user_id: Uuid) -> Result<Vec<ChatInfo>, ChatError> {
+        let this = self.clone();
+        run_blocking(move || {
+            let chats = this.repo.chat.get_user_chats(user_id)?;
+            let chat_ids: Vec<Uuid> = chats.iter().map(|chat| chat.id).collect();
+            let mut latest_pins: HashMap<Uuid, PinnedMessage> = this
+                .repo
+                .chat
+                .get_latest_pins(&chat_ids)?
+                .into_iter()
+                .map(|pin| (pin.chat_id, pin))
+                .collect();
+            let mut read_states: HashMap<Uuid, ReadState> = this
+                .repo
+                .chat
+                .get_read_states(user_id, &chat_ids)?
+                .into_iter()
+                .map(|state| (state.chat_id, state))
+                .collect();
 
-        Ok(chats.into_iter().map(ChatInfo::from).collect())
+            Ok(chats
+                .into_iter()
+                .map(|chat| {
+                    let latest_pin = latest_pins.remove(&chat.id).map(PinInfo::from);
+                    let read_state = read_states.remove(&chat.id).map(ReadStateInfo::from);
+                    ChatInfo {
+                        latest_pin,
+                        read_state,
+                        ..ChatInfo::from(chat)
+                    }
+                })
+                .collect())
+        })
+        .await
     }
 
-    pub fn get_chat(&self, chat_id: Uuid, user_id: Uuid) -> Result<ChatInfo, ChatError> {
-        let is_member = self
-            .repo
-            .chat
-            .is_member(chat_id, user_id)
-            .map_err(|e| ChatError::Internal(e))?;
+    pub async fn get_chat(&self, chat_id: Uuid, user_id: Uuid) -> Result<ChatInfo, ChatError> {
+        let this = self.clone();
+        run_blocking(move || {
+            let is_member = this.repo.chat.is_member(chat_id, user_id)?;
 
-        if !is_member {
-            return Err(ChatError::NotMember);
-        }
+            if !is_member {
+                return Err(ChatError::NotMember);
+            }
 
-        let chat = self
-            .repo
-            .chat
-            .find_chat_by_id(chat_id)
-            .map_err(|e| ChatError::Internal(e))?
-            .ok_or(ChatError::ChatNotFound)?;
+            let chat = this
+                .repo
+                .chat
+                .find_chat_by_id(chat_id)?
+                .ok_or(ChatError::ChatNotFound)?;
 
-        Ok(ChatInfo::from(chat))
+            Ok(ChatInfo {
+                read_state: Self::read_state(&this.repo, chat_id, user_id)?,
+                ..Self::chat_info(&this.repo, chat)?
+            })
+        })
+        .await
     }
 
-    pub fn invite_user_by_username(
+    pub async fn invite_user_by_username(
         &self,
         chat_id: Uuid,
         username: String,
         inviter_id: Uuid,
     ) -> Result<(), ChatError> {
-        let is_member = self
-            .repo
-            .chat
-            .is_member(chat_id, inviter_id)
-            .map_err(|e| ChatError::Internal(e))?;
+        let this = self.clone();
+        run_blocking(move || {
+            this.repo.transaction(|tx| {
+                let is_member = tx.chat.is_member(chat_id, inviter_id)?;
 
-        if !is_member {
-            return Err(ChatError::NotMember);
-        }
+                if !is_member {
+                    return Err(ChatError::NotMember);
+                }
 
-        let user = self
-            .repo
-            .auth
-            .find_by_username(&username)
-            .map_err(|_| ChatError::UserNotFound(username.clone()))?;
+                let user = tx
+                    .auth
+                    .find_by_username(&username)?
+                    .ok_or_else(|| ChatError::UserNotFound(username.clone()))?;
 
-        let already_member = self
-            .repo
-            .chat
-            .is_member(chat_id, user.id)
-            .map_err(|e| ChatError::Internal(e))?;
+                // The primary key on chat_members settles concurrent invites.
+                tx.chat
+                    .add_member(chat_id, user.id, Some(inviter_id))
+                    .map_err(|e| match e {
+                        RepositoryError::Conflict(_) => ChatError::AlreadyMember,
+                        e => ChatError::from(e),
+                    })?;
+
+                tx.chat
+                    .advance_epoch(chat_id, RekeyReason::Invite, user.id)?;
+
+                Self::post_notice(
+                    tx,
+                    chat_id,
+                    inviter_id,
+                    Notice::MemberInvited {
+                        user_id: user.id,
+                        invited_by: inviter_id,
+                    },
+                )?;
+
+                Self::record_event(
+                    tx,
+                    chat_id,
+                    ChatEvent::MemberJoined {
+                        user_id: user.id,
+                        invited_by: Some(inviter_id),
+                    },
+                )?;
+
+                Ok(())
+            })
+        })
+        .await
+    }
+
+    /// Removes the caller from the chat and starts a new epoch, so their
+    /// devices receive no sender keys from here on.
+    #[tracing::instrument(skip(self))]
+    pub async fn leave_chat(&self, chat_id: Uuid, user_id: Uuid) -> Result<(), ChatError> {
+        let this = self.clone();
+        run_blocking(move || {
+            this.repo.transaction(|tx| {
+                tx.chat
+                    .remove_member(chat_id, user_id)
+                    .map_err(|e| match e {
+                        RepositoryError::NotFound => ChatError::NotMember,
+                        e => ChatError::from(e),
+                    })?;
+
+                Self::post_notice(tx, chat_id, user_id, Notice::MemberLeft { user_id })?;
+
+                Self::rekey_without(tx, chat_id, RekeyReason::Leave, user_id)
+            })
+        })
+        .await
+    }
+
+    /// Removes `user_id` from the chat. Allowed for the chat's creator and
+    /// for holders of `chats.moderate`; the creator cannot be removed.
+    #[tracing::instrument(skip(self))]
+    pub async fn remove_member(
+        &self,
+        chat_id: Uuid,
+        actor_id: Uuid,
+        actor_can_moderate: bool,
+        user_id: Uuid,
+    ) -> Result<(), ChatError> {
+        let this = self.clone();
+        run_blocking(move || {
+            this.repo.transaction(|tx| {
+                let chat = tx
+                    .chat
+                    .find_chat_by_id(chat_id)?
+                    .ok_or(ChatError::ChatNotFound)?;
+
+                if chat.created_by != actor_id && !actor_can_moderate {
+                    return Err(ChatError::Forbidden(
+                        "Only the chat creator or a moderator can remove members".to_string(),
+                    ));
+                }
+
+                if chat.created_by == user_id {
+                    return Err(ChatError::Forbidden(
+                        "The chat creator cannot be removed".to_string(),
+                    ));
+                }
+
+                tx.chat
+                    .remove_member(chat_id, user_id)
+                    .map_err(|e| match e {
+                        RepositoryError::NotFound => ChatError::MemberNotFound,
+                        e => ChatError::from(e),
+                    })?;
+
+                Self::post_notice(
+                    tx,
+                    chat_id,
+                    actor_id,
+                    Notice::MemberRemoved {
+                        user_id,
+                        removed_by: actor_id,
+                    },
+                )?;
+
+                Self::rekey_without(tx, chat_id, RekeyReason::Kick, user_id)
+            })
+        })
+        .await
+    }
+
+    /// Renames the chat. Allowed for the chat's creator and for holders of
+    /// `chats.moderate`; the change is posted as a `chat_renamed` notice.
+    #[tracing::instrument(skip(self))]
+    pub async fn rename_chat(
+        &self,
+        chat_id: Uuid,
+        actor_id: Uuid,
+        actor_can_moderate: bool,
+        name: String,
+    ) -> Result<ChatInfo, ChatError> {
+        check_chat_name(&name)?;
+
+        let this = self.clone();
+        run_blocking(move || {
+            this.repo.transaction(|tx| {
+                let chat = tx
+                    .chat
+                    .find_chat_by_id(chat_id)?
+                    .ok_or(ChatError::ChatNotFound)?;
+
+                if chat.created_by != actor_id && !actor_can_moderate {
+                    return Err(ChatError::Forbidden(
+                        "Only the chat creator or a moderator can rename the chat".to_string(),
+                    ));
+                }
+
+                if chat.name == name {
+                    return Self::chat_info(tx, chat);
+                }
+
+                let old_name = chat.name;
+                let chat = tx.chat.rename_chat(chat_id, &name)?;
+
+                Self::post_notice(
+                    tx,
+                    chat_id,
+                    actor_id,
+                    Notice::ChatRenamed {
+                        old_name: old_name.clone(),
+                        name: name.clone(),
+                        renamed_by: actor_id,
+                    },
+                )?;
+
+                Self::record_event(
+                    tx,
+                    chat_id,
+                    ChatEvent::ChatRenamed {
+                        old_name,
+                        name,
+                        renamed_by: actor_id,
+                    },
+                )?;
+
+                Self::chat_info(tx, chat)
+            })
+        })
+        .await
+    }
 
-        if already_member {
-            return Err(ChatError::AlreadyMember);
+    /// Sets how long new messages live, or turns the timer off with `None`.
+    /// Messages already sent keep their expiry. The change is posted to the
+    /// chat as a `message_ttl_changed` notice.
+    #[tracing::instrument(skip(self))]
+    pub async fn set_message_ttl(
+        &self,
+        chat_id: Uuid,
+        actor_id: Uuid,
+        actor_can_moderate: bool,
+        ttl_seconds: Option<i32>,
+    ) -> Result<ChatInfo, ChatError> {
+        if ttl_seconds.is_some_and(|ttl| !(1..=MAX_MESSAGE_TTL_SECONDS).contains(&ttl)) {
+            return Err(ChatError::InvalidMessageTtl(format!(
+                "Timer must be between 1 and {} seconds",
+                MAX_MESSAGE_TTL_SECONDS
+            )));
         }
 
-        self.repo
-            .chat
-            .add_member(chat_id, user.id, Some(inviter_id))
-            .map_err(|e| ChatError::Internal(e))?;
+        let this = self.clone();
+        run_blocking(move || {
+            this.repo.transaction(|tx| {
+                let chat = tx
+                    .chat
+                    .find_chat_by_id(chat_id)?
+                    .ok_or(ChatError::ChatNotFound)?;
 
-        Ok(())
+                if chat.created_by != actor_id && !actor_can_moderate {
+                    return Err(ChatError::Forbidden(
+                        "Only the chat creator or a moderator can change the message timer"
+                            .to_string(),
+                    ));
+                }
+
+                if chat.message_ttl_seconds == ttl_seconds {
+                    return Self::chat_info(tx, chat);
+                }
+
+                let chat = tx.chat.set_message_ttl(chat_id, ttl_seconds)?;
+
+                Self::post_notice(
+                    tx,
+                    chat_id,
+                    actor_id,
+                    Notice::MessageTtlChanged {
+                        ttl_seconds,
+                        changed_by: actor_id,
+                    },
+                )?;
+
+                Self::chat_info(tx, chat)
+            })
+        })
+        .await
+    }
+
+    /// Deletes messages past their expiry and fully delivered view-once
+    /// messages, `batch_size` per transaction, along with attachments no
+    /// other message references. Returns how many messages went.
+    ///
+    pub async fn reap_expired_messages(&self, batch_size: i64) -> Result<usize, ChatError> {
+        let this = self.clone();
+        run_blocking(move || {
+            let now = Utc::now().naive_utc();
+            let mut total = 0;
+
+            loop {
+                let (deleted, chunks) = this.repo.transaction(|tx| {
+                    let message_ids = tx.chat.find_expired_messages(now, batch_size)?;
+                    let attachment_ids: BTreeSet<Uuid> = tx
+                        .attachments
+                        .get_message_attachments(&message_ids)?
+                        .into_iter()
+                        .map(|(_, attachment_id)| attachment_id)
+                        .collect();
+
+                    let deleted = tx.chat.delete_messages(&message_ids)?;
+                    let chunks = tx
+                        .attachments
+                        .delete_unattached(&attachment_ids.into_iter().collect::<Vec<_>>())?;
+
+                    Ok::<_, ChatError>((deleted, chunks))
+                })?;
+                total += deleted;
+
+                delete_blobs(this.blobs.as_ref(), chunks);
+
+                if (deleted as i64) < batch_size {
+                    return Ok(total);
+                }
+            }
+        })
+        .await
+    }
+
+    /// Membership changes since `since_epoch`, each of which obliges the
+    /// caller's devices to distribute a new sender key.
+    pub async fn get_rekeys(
+        &self,
+        chat_id: Uuid,
+        user_id: Uuid,
+        since_epoch: i32,
+    ) -> Result<Vec<RekeyInfo>, ChatError> {
+        let this = self.clone();
+        run_blocking(move || {
+            let is_member = this.repo.chat.is_member(chat_id, user_id)?;
+
+            if !is_member {
+                return Err(ChatError::NotMember);
+            }
+
+            let rekeys = this.repo.chat.get_rekeys(chat_id, since_epoch)?;
+
+            Ok(rekeys.into_iter().map(RekeyInfo::from).collect())
+        })
+        .await
+    }
+
+    /// Stores the caller device's sender key for the current epoch, encrypted
+    /// once per other device in the chat.
+    #[tracing::instrument(skip(self, keys), fields(devices = keys.len()))]
+    pub async fn distribute_sender_key(
+        &self,
+        chat_id: Uuid,
+        user_id: Uuid,
+        device_id: Uuid,
+        epoch: i32,
+        keys: HashMap<Uuid, String>,
+    ) -> Result<(), ChatError> {
+        let this = self.clone();
+        run_blocking(move || {
+            let is_member = this.repo.chat.is_member(chat_id, user_id)?;
+
+            if !is_member {
+                return Err(ChatError::NotMember);
+            }
+
+            ensure_own_device(&this.repo, user_id, device_id)?;
+            Self::check_epoch(&this.repo, chat_id, epoch)?;
+            Self::check_envelopes(&this.repo, chat_id, device_id, &keys)?;
+
+            let rows: Vec<NewSenderKey> = keys
+                .into_iter()
+                .map(|(recipient_device_id, ciphertext)| NewSenderKey {
+                    chat_id,
+                    epoch,
+                    sender_id: user_id,
+                    sender_device_id: device_id,
+                    recipient_device_id,
+                    ciphertext,
+                })
+                .collect();
+
+            this.repo.sender_keys.store_sender_keys(&rows)?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    /// Sender keys addressed to one of the caller's devices, for `epoch` or
+    /// the chat's current one.
+    pub async fn get_sender_keys(
+        &self,
+        chat_id: Uuid,
+        user_id: Uuid,
+        device_id: Uuid,
+        epoch: Option<i32>,
+    ) -> Result<SenderKeyBundle, ChatError> {
+        let this = self.clone();
+        run_blocking(move || {
+            let is_member = this.repo.chat.is_member(chat_id, user_id)?;
+
+            if !is_member {
+                return Err(ChatError::NotMember);
+            }
+
+            ensure_own_device(&this.repo, user_id, device_id)?;
+
+            let epoch = match epoch {
+                Some(epoch) => epoch,
+                None => Self::current_epoch(&this.repo, chat_id)?,
+            };
+
+            let keys = this
+                .repo
+                .sender_keys
+                .get_sender_keys(chat_id, epoch, device_id)?;
+
+            Ok(SenderKeyBundle {
+                epoch,
+                sender_keys: keys.into_iter().map(SenderKeyInfo::from).collect(),
+            })
+        })
+        .await
     }
 
-    pub fn get_chat_members(
+    pub async fn get_chat_members(
         &self,
         chat_id: Uuid,
         user_id: Uuid,
+        mut filter: MemberFilter,
+        limit: i64,
+        offset: i64,
     ) -> Result<Vec<ChatMemberInfo>, ChatError> {
-        let is_member = self
-            .repo
-            .chat
-            .is_member(chat_id, user_id)
-            .map_err(|e| ChatError::Internal(e))?;
+        let this = self.clone();
+        run_blocking(move || {
+            let is_member = this.repo.chat.is_member(chat_id, user_id)?;
+
+            if !is_member {
+                return Err(ChatError::NotMember);
+            }
+
+            filter.username_prefix = filter
+                .username_prefix
+                .map(|prefix| prefix.trim().to_string())
+                .filter(|prefix| !prefix.is_empty());
+
+            let members = this.repo.chat.get_chat_members(
+                chat_id,
+                &filter,
+                limit.clamp(1, MAX_MEMBERS_PER_PAGE),
+                offset.max(0),
+            )?;
+
+            Ok(members.into_iter().map(ChatMemberInfo::from).collect())
+        })
+        .await
+    }
+
+    /// Every device of every member, so senders know whom to encrypt for.
+    pub async fn get_chat_devices(
+        &self,
+        chat_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Vec<DeviceInfo>, ChatError> {
+        let this = self.clone();
+        run_blocking(move || {
+            let is_member = this.repo.chat.is_member(chat_id, user_id)?;
+
+            if !is_member {
+                return Err(ChatError::NotMember);
+            }
+
+            let devices = this.repo.devices.list_chat_devices(chat_id)?;
+
+            Ok(devices.into_iter().map(DeviceInfo::from).collect())
+        })
+        .await
+    }
+
+    /// Sends a message either as one envelope per device, which have to
+    /// cover exactly the chat's devices other than the sending one, or as a
+    /// single sender-key ciphertext for the current epoch.
+    #[tracing::instrument(skip(self, body))]
+    pub async fn send_message(
+        &self,
+        chat_id: Uuid,
+        sender_id: Uuid,
+        sender_device_id: Uuid,
+        body: MessageBody,
+        options: MessageOptions,
+    ) -> Result<MessageInfo, ChatError> {
+        let this = self.clone();
+        run_blocking(move || {
+            let message = Self::post_message(
+                &this.repo,
+                chat_id,
+                sender_id,
+                sender_device_id,
+                body,
+                options,
+                None,
+            )?;
+            Self::queue_wake_ups(&this.repo, &message);
+
+            Ok(message)
+        })
+        .await
+    }
+
+    /// Messages as seen by one of the caller's devices.
+    pub async fn get_messages(
+        &self,
+        chat_id: Uuid,
+        user_id: Uuid,
+        device_id: Uuid,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<MessageInfo>, ChatError> {
+        let this = self.clone();
+        run_blocking(move || {
+            let is_member = this.repo.chat.is_member(chat_id, user_id)?;
+
+            if !is_member {
+                return Err(ChatError::NotMember);
+            }
+
+            ensure_own_device(&this.repo, user_id, device_id)?;
+            this.repo.devices.touch_device(device_id)?;
+
+            let messages = this
+                .repo
+                .chat
+                .get_chat_messages(chat_id, device_id, limit, offset)?;
+
+            // A view-once message goes to whichever read deletes the
+            // device's envelope first; concurrent reads drop it.
+            let view_once: Vec<Uuid> = messages
+                .iter()
+                .filter(|m| m.view_once)
+                .map(|m| m.id)
+                .collect();
+            let consumed: BTreeSet<Uuid> = this
+                .repo
+                .chat
+                .consume_view_once(device_id, &view_once)?
+                .into_iter()
+                .collect();
+            let messages: Vec<Message> = messages
+                .into_iter()
+                .filter(|m| !m.view_once || consumed.contains(&m.id))
+                .collect();
+
+            let message_ids: Vec<Uuid> = messages.iter().map(|m| m.id).collect();
+            let mut attachments: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+            for (message_id, attachment_id) in this
+                .repo
+                .attachments
+                .get_message_attachments(&message_ids)?
+            {
+                attachments
+                    .entry(message_id)
+                    .or_default()
+                    .push(attachment_id);
+            }
+
+            let mut polls = Self::polls(&this.repo, &message_ids, user_id)?;
+
+            Ok(messages
+                .into_iter()
+                .map(|message| {
+                    let attachment_ids = attachments.remove(&message.id).unwrap_or_default();
+                    let poll = polls.remove(&message.id);
+                    MessageInfo {
+                        attachment_ids,
+                        poll,
+                        ..MessageInfo::from(message)
+                    }
+                })
+                .collect())
+        })
+        .await
+    }
+
+    /// Everything a message must satisfy to be posted right now. Returns the
+    /// chat, whose timer the message inherits.
+    pub(super) fn check_message(
+        repo: &Repository,
+        chat_id: Uuid,
+        sender_id: Uuid,
+        sender_device_id: Uuid,
+        body: &MessageBody,
+        options: &MessageOptions,
+    ) -> Result<Chat, ChatError> {
+        let is_member = repo.chat.is_member(chat_id, sender_id)?;
 
         if !is_member {
             return Err(ChatError::NotMember);
         }
 
-        let members = self
-            .repo
+        ensure_own_device(repo, sender_id, sender_device_id)?;
+        Self::check_attachments(repo, sender_id, &options.attachment_ids)?;
+
+        if options.view_once && matches!(body, MessageBody::SenderKey { .. }) {
+            return Err(ChatError::InvalidMessage(
+                "View-once messages need per-device envelopes".to_string(),
+            ));
+        }
+
+        let chat = repo
             .chat
-            .get_chat_members(chat_id)
-            .map_err(|e| ChatError::Internal(e))?;
-
-        let mut result = Vec::new();
-        for member in members {
-            if let Ok(user) = self.repo.auth.find_by_id(member.user_id) {
-                result.push(ChatMemberInfo {
-                    user_id: member.user_id,
-                    username: user.username,
-                    joined_at: member.joined_at.format("%Y-%m-%d %H:%M:%S").to_string(),
-                });
+            .find_chat_by_id(chat_id)?
+            .ok_or(ChatError::ChatNotFound)?;
+        Self::check_mentions(repo, &chat, sender_id, options)?;
+
+        match body {
+            MessageBody::Envelopes(envelopes) => {
+                Self::check_envelopes(repo, chat_id, sender_device_id, envelopes)?;
+            }
+            MessageBody::SenderKey { epoch, ciphertext } => {
+                if ciphertext.is_empty() {
+                    return Err(ChatError::InvalidMessage(
+                        "Ciphertext cannot be empty".to_string(),
+                    ));
+                }
+
+                Self::check_epoch(repo, chat_id, *epoch)?;
             }
         }
 
-        Ok(result)
+        Ok(chat)
     }
 
-    pub fn send_message(
-        &self,
+    /// Posts a user message, either sent right away, by the scheduler, or
+    /// as a forward of `forwarded_from`.
+    pub(super) fn post_message(
+        repo: &Repository,
         chat_id: Uuid,
         sender_id: Uuid,
-        encrypted_content: String,
+        sender_device_id: Uuid,
+        body: MessageBody,
+        options: MessageOptions,
+        forwarded_from: Option<ForwardedFrom>,
     ) -> Result<MessageInfo, ChatError> {
-        let is_member = self
-            .repo
+        let chat =
+            Self::check_message(repo, chat_id, sender_id, sender_device_id, &body, &options)?;
+        let MessageOptions {
+            attachment_ids,
+            view_once,
+            mut mentions,
+            mention_all,
+        } = options;
+        mentions.sort();
+        mentions.dedup();
+        let expires_at = chat
+            .message_ttl_seconds
+            .map(|ttl| Utc::now().naive_utc() + Duration::seconds(ttl.into()));
+
+        let mut new_message = NewMessage {
+            chat_id,
+            sender_id,
+            sender_device_id: Some(sender_device_id),
+            encrypted_content: None,
+            epoch: None,
+            kind: MessageKind::Text.as_str().to_string(),
+            payload: None,
+            expires_at,
+            view_once,
+            mentions,
+            mention_all,
+            forwarded_from_chat_id: forwarded_from.map(|origin| origin.chat_id),
+            forwarded_from_message_id: forwarded_from.map(|origin| origin.message_id),
+            forwarded_from_sender_id: forwarded_from.map(|origin| origin.sender_id),
+        };
+
+        let envelopes = match body {
+            MessageBody::Envelopes(envelopes) => envelopes,
+            MessageBody::SenderKey { epoch, ciphertext } => {
+                new_message.encrypted_content = Some(ciphertext);
+                new_message.epoch = Some(epoch);
+                HashMap::new()
+            }
+        };
+
+        let created = repo.transaction(|tx| {
+            let message = tx.chat.create_message(new_message, &envelopes)?;
+            tx.attachments
+                .attach_to_message(message.id, &attachment_ids)?;
+            Self::record_event(tx, chat_id, ChatEvent::message_created(&message))?;
+            Ok::<_, RepositoryError>(message)
+        });
+
+        // A device removed since the check fails the envelope's foreign
+        // key; report it like any other stale device.
+        let message = match created {
+            Ok(message) => message,
+            Err(RepositoryError::MissingReference(cause)) => {
+                Self::check_envelopes(repo, chat_id, sender_device_id, &envelopes)?;
+                return Err(ChatError::Internal(cause));
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut info = MessageInfo::from(message);
+        info.attachment_ids = attachment_ids;
+
+        Ok(info)
+    }
+
+    /// The chat with its latest pin.
+    pub(super) fn chat_info(repo: &Repository, chat: Chat) -> Result<ChatInfo, ChatError> {
+        let latest_pin = repo.chat.get_latest_pins(&[chat.id])?.into_iter().next();
+
+        Ok(ChatInfo {
+            latest_pin: latest_pin.map(PinInfo::from),
+            ..ChatInfo::from(chat)
+        })
+    }
+
+    /// Posts a server notice about something `actor_id` did in the chat.
+    /// Notices carry no ciphertext, so every member's devices see them.
+    pub(super) fn post_notice(
+        repo: &Repository,
+        chat_id: Uuid,
+        actor_id: Uuid,
+        notice: Notice,
+    ) -> Result<Message, ChatError> {
+        let notice = repo.chat.create_message(
+            NewMessage {
+                chat_id,
+                sender_id: actor_id,
+                sender_device_id: None,
+                encrypted_content: None,
+                epoch: None,
+                kind: notice.kind().as_str().to_string(),
+                payload: Some(notice.payload().to_string()),
+                expires_at: None,
+                view_once: false,
+                mentions: Vec::new(),
+                mention_all: false,
+                forwarded_from_chat_id: None,
+                forwarded_from_message_id: None,
+                forwarded_from_sender_id: None,
+            },
+            &HashMap::new(),
+        )?;
+
+        Ok(notice)
+    }
+
+    /// Adds an event to the webhook outbox. Pass the transaction's
+    /// repository, so the event is only published if the change commits.
+    pub(super) fn record_event(
+        repo: &Repository,
+        chat_id: Uuid,
+        event: ChatEvent,
+    ) -> Result<(), RepositoryError> {
+        repo.webhooks.record_event(NewWebhookEvent {
+            kind: event.kind().as_str().to_string(),
+            chat_id,
+            payload: event.payload().to_string(),
+        })?;
+
+        Ok(())
+    }
+
+    /// Queues wake-ups for the members to notify of a message that has just
+    /// been committed. A lost wake-up only delays the message until the
+    /// device next syncs, so failures are logged rather than returned.
+    pub(super) fn queue_wake_ups(repo: &Repository, message: &MessageInfo) {
+        let queued = repo
             .chat
-            .is_member(chat_id, sender_id)
-            .map_err(|e| ChatError::Internal(e))?;
+            .get_members_to_notify(
+                message.chat_id,
+                message.sender_id,
+                &message.mentions,
+                message.mention_all,
+            )
+            .and_then(|user_ids| repo.push.enqueue_wake_ups(&user_ids));
 
-        if !is_member {
-            return Err(ChatError::NotMember);
+        if let Err(e) = queued {
+            tracing::warn!(message_id = %message.id, "Failed to queue wake-ups: {}", e);
         }
+    }
 
-        let message = self
-            .repo
+    fn current_epoch(repo: &Repository, chat_id: Uuid) -> Result<i32, ChatError> {
+        let chat = repo
             .chat
-            .create_message(chat_id, sender_id, encrypted_content)
-            .map_err(|e| ChatError::Internal(e))?;
+            .find_chat_by_id(chat_id)?
+            .ok_or(ChatError::ChatNotFound)?;
 
-        Ok(MessageInfo::from(message))
+        Ok(chat.epoch)
     }
 
-    pub fn get_messages(
-        &self,
+    fn check_epoch(repo: &Repository, chat_id: Uuid, epoch: i32) -> Result<(), ChatError> {
+        let current = Self::current_epoch(repo, chat_id)?;
+
+        if epoch != current {
+            return Err(ChatError::StaleEpoch { current });
+        }
+
+        Ok(())
+    }
+
+    /// A message may only carry the sender's own, fully uploaded attachments.
+    fn check_attachments(
+        repo: &Repository,
+        sender_id: Uuid,
+        attachment_ids: &[Uuid],
+    ) -> Result<(), ChatError> {
+        if attachment_ids.len() > MAX_ATTACHMENTS_PER_MESSAGE {
+            return Err(ChatError::InvalidAttachment(format!(
+                "At most {} attachments per message",
+                MAX_ATTACHMENTS_PER_MESSAGE
+            )));
+        }
+
+        let unique: BTreeSet<&Uuid> = attachment_ids.iter().collect();
+        if unique.len() != attachment_ids.len() {
+            return Err(ChatError::InvalidAttachment(
+                "Duplicate attachment".to_string(),
+            ));
+        }
+
+        for attachment_id in attachment_ids {
+            let attachment = repo
+                .attachments
+                .find_attachment(*attachment_id)?
+                .filter(|a| a.uploader_id == sender_id)
+                .ok_or_else(|| {
+                    ChatError::InvalidAttachment(format!("Unknown attachment {}", attachment_id))
+                })?;
+
+            if attachment.completed_at.is_none() {
+                return Err(ChatError::InvalidAttachment(format!(
+                    "Attachment {} is not fully uploaded",
+                    attachment_id
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Drops the removed member's sender keys and starts a new epoch.
+    fn rekey_without(
+        tx: &Repository,
         chat_id: Uuid,
+        reason: RekeyReason,
         user_id: Uuid,
-        limit: i64,
-        offset: i64,
-    ) -> Result<Vec<MessageInfo>, ChatError> {
-        let is_member = self
-            .repo
-            .chat
-            .is_member(chat_id, user_id)
-            .map_err(|e| ChatError::Internal(e))?;
+    ) -> Result<(), ChatError> {
+        let dropped = tx.sender_keys.delete_user_sender_keys(chat_id, user_id)?;
+        let rekey = tx.chat.advance_epoch(chat_id, reason, user_id)?;
 
-        if !is_member {
-            return Err(ChatError::NotMember);
-        }
+        tracing::info!(epoch = rekey.epoch, dropped, "Chat rekeyed");
 
-        let messages = self
-            .repo
-            .chat
-            .get_chat_messages(chat_id, limit, offset)
-            .map_err(|e| ChatError::Internal(e))?;
+        Ok(())
+    }
 
-        Ok(messages.into_iter().map(MessageInfo::from).collect())
+    fn check_envelopes(
+        repo: &Repository,
+        chat_id: Uuid,
+        sender_device_id: Uuid,
+        envelopes: &HashMap<Uuid, String>,
+    ) -> Result<(), ChatError> {
+        let expected: BTreeSet<Uuid> = repo
+            .devices
+            .list_chat_devices(chat_id)?
+            .into_iter()
+            .map(|device| device.id)
+            .filter(|id| *id != sender_device_id)
+            .collect();
+        let given: BTreeSet<Uuid> = envelopes.keys().copied().collect();
+
+        let missing: Vec<Uuid> = expected.difference(&given).copied().collect();
+        let stale: Vec<Uuid> = given.difference(&expected).copied().collect();
+
+        if !missing.is_empty() || !stale.is_empty() {
+            return Err(ChatError::DeviceMismatch { missing, stale });
+        }
+
+        Ok(())
     }
 }
 
@@ -225,18 +1077,98 @@ impl From<Chat> for ChatInfo {
             name: chat.name,
             created_by: chat.created_by,
             created_at: chat.created_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+            epoch: chat.epoch,
+            message_ttl_seconds: chat.message_ttl_seconds,
+            allow_forwarding: chat.allow_forwarding,
+            allow_polls: chat.allow_polls,
+            latest_pin: None,
+            read_state: None,
         }
     }
 }
 
 impl From<Message> for MessageInfo {
     fn from(msg: Message) -> Self {
+        let forwarded_from = ForwardedFrom::of(&msg);
+
         Self {
             id: msg.id,
             chat_id: msg.chat_id,
             sender_id: msg.sender_id,
+            sender_device_id: msg.sender_device_id,
             encrypted_content: msg.encrypted_content,
+            epoch: msg.epoch,
+            kind: msg.kind,
+            payload: msg
+                .payload
+                .and_then(|payload| serde_json::from_str(&payload).ok()),
+            attachment_ids: Vec::new(),
+            expires_at: msg
+                .expires_at
+                .map(|expires_at| expires_at.format("%Y-%m-%d %H:%M:%S").to_string()),
+            view_once: msg.view_once,
+            mentions: msg.mentions,
+            mention_all: msg.mention_all,
+            forwarded_from,
+            poll: None,
             created_at: msg.created_at.format("%Y-%m-%d %H:%M:%S").to_string(),
         }
     }
 }
+
+impl From<SenderKey> for SenderKeyInfo {
+    fn from(key: SenderKey) -> Self {
+        Self {
+            sender_id: key.sender_id,
+            sender_device_id: key.sender_device_id,
+            ciphertext: key.ciphertext,
+            created_at: key.created_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+        }
+    }
+}
+
+impl From<ChatRekey> for RekeyInfo {
+    fn from(rekey: ChatRekey) -> Self {
+        Self {
+            epoch: rekey.epoch,
+            reason: rekey.reason,
+            user_id: rekey.user_id,
+            created_at: rekey.created_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+        }
+    }
+}
+
+fn check_chat_name(name: &str) -> Result<(), ChatError> {
+    if name.trim().is_empty() {
+        return Err(ChatError::InvalidChatName(
+            "Chat name cannot be empty".to_string(),
+        ));
+    }
+
+    if name.len() > 255 {
+        return Err(ChatError::InvalidChatName("Chat name too long".to_string()));
+    }
+
+    Ok(())
+}
+
+fn ensure_own_device(repo: &Repository, user_id: Uuid, device_id: Uuid) -> Result<(), ChatError> {
+    repo.devices
+        .find_device(device_id)?
+        .filter(|device| device.user_id == user_id)
+        .ok_or(ChatError::UnknownDevice)?;
+
+    Ok(())
+}
+
+impl From<ChatMemberDetails> for ChatMemberInfo {
+    fn from(member: ChatMemberDetails) -> Self {
+        Self {
+            user_id: member.user_id,
+            username: member.username,
+            role_id: member.role_id,
+            invited_by: member.invited_by,
+            joined_at: member.joined_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+        }
+    }
+}
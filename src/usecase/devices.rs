@@ -0,0 +1,5 @@
+pub mod error;
+pub mod service;
+
+pub use error::DeviceError;
+pub use service::{DeviceInfo, DeviceService};
@@ -0,0 +1,40 @@
+use thiserror::Error;
+
+use crate::repository::RepositoryError;
+
+#[derive(Debug, Error)]
+pub enum DeviceError {
+    #[error("Invalid device name: {0}")]
+    InvalidDeviceName(String),
+
+    #[error("Unknown device")]
+    UnknownDevice,
+
+    #[error("Too many devices: at most {0} per account")]
+    TooManyDevices(i64),
+
+    #[error("Service temporarily unavailable")]
+    Unavailable,
+
+    #[error("Internal error: {0}")]
+    Internal(String),
+}
+
+impl From<tokio::task::JoinError> for DeviceError {
+    fn from(err: tokio::task::JoinError) -> Self {
+        Self::Internal(err.to_string())
+    }
+}
+
+impl From<RepositoryError> for DeviceError {
+    fn from(err: RepositoryError) -> Self {
+        match err {
+            RepositoryError::Unavailable(cause) => {
+                tracing::error!("Database unavailable: {}", cause);
+                Self::Unavailable
+            }
+            RepositoryError::NotFound => Self::UnknownDevice,
+            err => Self::Internal(err.to_string()),
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use uuid::Uuid;
+
+use super::error::DeviceError;
+use crate::repository::Repository;
+use crate::repository::devices::{Device, NewDevice};
+use crate::usecase::blocking::run_blocking;
+
+const MAX_DEVICES_PER_USER: i64 = 10;
+const MAX_DEVICE_NAME_LENGTH: usize = 100;
+
+/// Devices registered under an account. Every device holds its own keys and
+/// receives its own copy of each message.
+#[derive(Clone)]
+pub struct DeviceService {
+    repo: Repository,
+}
+
+pub struct DeviceInfo {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub created_at: String,
+}
+
+impl DeviceService {
+    pub fn new(repo: Repository) -> Self {
+        Self { repo }
+    }
+
+    #[tracing::instrument(skip(self, name))]
+    pub async fn register_device(
+        &self,
+        user_id: Uuid,
+        name: String,
+    ) -> Result<DeviceInfo, DeviceError> {
+        let this = self.clone();
+        run_blocking(move || {
+            let name = name.trim().to_string();
+
+            if name.is_empty() {
+                return Err(DeviceError::InvalidDeviceName(
+                    "Device name cannot be empty".to_string(),
+                ));
+            }
+
+            if name.chars().count() > MAX_DEVICE_NAME_LENGTH {
+                return Err(DeviceError::InvalidDeviceName(
+                    "Device name too long".to_string(),
+                ));
+            }
+
+            this.repo.transaction(|tx| {
+                if tx.devices.count_user_devices(user_id)? >= MAX_DEVICES_PER_USER {
+                    return Err(DeviceError::TooManyDevices(MAX_DEVICES_PER_USER));
+                }
+
+                let device = tx.devices.create_device(NewDevice { user_id, name })?;
+
+                Ok(DeviceInfo::from(device))
+            })
+        })
+        .await
+    }
+
+    pub async fn list_devices(&self, user_id: Uuid) -> Result<Vec<DeviceInfo>, DeviceError> {
+        let this = self.clone();
+        run_blocking(move || {
+            let devices = this.repo.devices.list_user_devices(user_id)?;
+
+            Ok(devices.into_iter().map(DeviceInfo::from).collect())
+        })
+        .await
+    }
+
+    /// Removes one of the caller's devices along with its keys and the
+    /// envelopes addressed to it.
+    #[tracing::instrument(skip(self))]
+    pub async fn remove_device(&self, user_id: Uuid, device_id: Uuid) -> Result<(), DeviceError> {
+        let this = self.clone();
+        run_blocking(move || {
+            this.repo.transaction(|tx| {
+                tx.devices
+                    .find_device(device_id)?
+                    .filter(|device| device.user_id == user_id)
+                    .ok_or(DeviceError::UnknownDevice)?;
+
+                tx.devices.delete_device(device_id)?;
+
+                Ok(())
+            })
+        })
+        .await
+    }
+}
+
+impl From<Device> for DeviceInfo {
+    fn from(device: Device) -> Self {
+        Self {
+            id: device.id,
+            user_id: device.user_id,
+            name: device.name,
+            created_at: device.created_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+        }
+    }
+}
@@ -1,23 +1,68 @@
+use std::sync::Arc;
+
+use super::attachments::service::AttachmentService;
 use super::auth::service::AuthService;
 use super::chat::service::ChatService;
-use crate::config::jwt::JwtConfig;
+use super::devices::service::DeviceService;
+use super::keys::service::KeyService;
+use super::push::service::PushService;
+use super::root::ServiceConfig;
+use super::webhooks::service::WebhookService;
 use crate::repository::Repository;
+use crate::repository::blobs::{self, BlobStore};
 
 pub(super) struct Factory {
     repo: Repository,
-    jwt_config: JwtConfig,
+    config: ServiceConfig,
+    /// Shared by uploads and by the reaper that deletes expired messages.
+    blobs: Arc<dyn BlobStore>,
 }
 
 impl Factory {
-    pub(super) fn new(repo: Repository, jwt_config: JwtConfig) -> Self {
-        Self { repo, jwt_config }
+    pub(super) fn new(repo: Repository, config: ServiceConfig) -> Result<Self, String> {
+        let blobs = blobs::open(&config.attachments.storage)?;
+
+        Ok(Self {
+            repo,
+            config,
+            blobs,
+        })
     }
 
-    pub(super) fn create_auth_service(&self) -> AuthService {
-        AuthService::new(self.repo.clone(), &self.jwt_config)
+    pub(super) fn create_auth_service(&self) -> Result<AuthService, String> {
+        AuthService::new(
+            self.repo.clone(),
+            &self.config.jwt,
+            &self.config.login_throttle,
+            &self.config.password,
+        )
     }
 
     pub(super) fn create_chat_service(&self) -> ChatService {
-        ChatService::new(self.repo.clone())
+        ChatService::new(self.repo.clone(), self.blobs.clone())
+    }
+
+    pub(super) fn create_device_service(&self) -> DeviceService {
+        DeviceService::new(self.repo.clone())
+    }
+
+    pub(super) fn create_key_service(&self) -> Result<KeyService, String> {
+        KeyService::new(self.repo.clone(), &self.config.keys)
+    }
+
+    pub(super) fn create_attachment_service(&self) -> AttachmentService {
+        AttachmentService::new(
+            self.repo.clone(),
+            &self.config.attachments,
+            self.blobs.clone(),
+        )
+    }
+
+    pub(super) fn create_push_service(&self) -> Result<PushService, String> {
+        PushService::new(self.repo.clone(), &self.config.push)
+    }
+
+    pub(super) fn create_webhook_service(&self) -> WebhookService {
+        WebhookService::new(self.repo.clone(), &self.config.webhooks)
     }
 }
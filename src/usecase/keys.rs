@@ -0,0 +1,10 @@
+pub mod error;
+pub mod log;
+pub mod merkle;
+pub mod service;
+
+pub use error::KeyError;
+pub use service::{
+    ConsistencyProof, DeviceBundle, InclusionProof, KeyInclusionProofs, KeyLogEntryInfo,
+    KeyService, OneTimePrekeyInfo, PrekeyBundle, PrekeyStatus, SignedPrekeyInfo, TreeHeadInfo,
+};
@@ -0,0 +1,54 @@
+use thiserror::Error;
+
+use crate::repository::RepositoryError;
+
+#[derive(Debug, Error)]
+pub enum KeyError {
+    #[error("No keys published for this user")]
+    KeysNotFound,
+
+    #[error("Unknown device")]
+    UnknownDevice,
+
+    #[error("Invalid key: {0}")]
+    InvalidKey(String),
+
+    #[error("Too many prekeys: {0}")]
+    TooManyPrekeys(String),
+
+    #[error("Prekey id already in use")]
+    DuplicatePrekey,
+
+    #[error("The key log is empty")]
+    LogEmpty,
+
+    #[error("No logged keys for this user")]
+    KeyNotLogged,
+
+    #[error("Invalid tree size: {0}")]
+    InvalidTreeSize(String),
+
+    #[error("Service temporarily unavailable")]
+    Unavailable,
+
+    #[error("Internal error: {0}")]
+    Internal(String),
+}
+
+impl From<tokio::task::JoinError> for KeyError {
+    fn from(err: tokio::task::JoinError) -> Self {
+        Self::Internal(err.to_string())
+    }
+}
+
+impl From<RepositoryError> for KeyError {
+    fn from(err: RepositoryError) -> Self {
+        match err {
+            RepositoryError::Unavailable(cause) => {
+                tracing::error!("Database unavailable: {}", cause);
+                Self::Unavailable
+            }
+            err => Self::Internal(err.to_string()),
+        }
+    }
+}
@@ -0,0 +1,109 @@
+//! Encoding and signing of the key transparency log.
+
+use std::sync::Arc;
+
+use ring::rand::SystemRandom;
+use ring::signature::{ED25519, Ed25519KeyPair, KeyPair, UnparsedPublicKey};
+use uuid::Uuid;
+
+use super::merkle::Hash;
+
+const LEAF_VERSION: u8 = 0;
+/// `version` and `signature_type` of an RFC 6962 `TreeHeadSignature`.
+const TREE_HEAD_VERSION: u8 = 0;
+const TREE_HASH_SIGNATURE_TYPE: u8 = 1;
+
+/// The leaf input clients hash to recompute a leaf:
+/// `version (1) || user_id (16) || device_id (16) || timestamp_ms (8, big
+/// endian) || identity key bytes`.
+pub fn leaf_input(
+    user_id: Uuid,
+    device_id: Uuid,
+    timestamp_ms: i64,
+    identity_key: &[u8],
+) -> Vec<u8> {
+    let mut input = Vec::with_capacity(41 + identity_key.len());
+    input.push(LEAF_VERSION);
+    input.extend_from_slice(user_id.as_bytes());
+    input.extend_from_slice(device_id.as_bytes());
+    input.extend_from_slice(&timestamp_ms.to_be_bytes());
+    input.extend_from_slice(identity_key);
+    input
+}
+
+/// The bytes a tree head signature covers, laid out as an RFC 6962
+/// `TreeHeadSignature`.
+pub fn tree_head_input(timestamp_ms: i64, tree_size: i64, root_hash: &Hash) -> Vec<u8> {
+    let mut input = Vec::with_capacity(50);
+    input.push(TREE_HEAD_VERSION);
+    input.push(TREE_HASH_SIGNATURE_TYPE);
+    input.extend_from_slice(&timestamp_ms.to_be_bytes());
+    input.extend_from_slice(&tree_size.to_be_bytes());
+    input.extend_from_slice(root_hash);
+    input
+}
+
+/// Checks a tree head signature against the log's Ed25519 public key.
+pub fn verify_tree_head(
+    public_key: &[u8],
+    timestamp_ms: i64,
+    tree_size: i64,
+    root_hash: &Hash,
+    signature: &[u8],
+) -> bool {
+    UnparsedPublicKey::new(&ED25519, public_key)
+        .verify(
+            &tree_head_input(timestamp_ms, tree_size, root_hash),
+            signature,
+        )
+        .is_ok()
+}
+
+/// Signs tree heads with the log's Ed25519 key.
+#[derive(Clone)]
+pub struct LogSigner {
+    key_pair: Arc<Ed25519KeyPair>,
+}
+
+impl LogSigner {
+    /// Loads a PKCS#8 PEM private key, or generates a throwaway key when none
+    /// is configured. Heads signed by a throwaway key cannot be verified
+    /// after a restart.
+    pub fn new(private_pem: Option<&str>) -> Result<Self, String> {
+        let key_pair = match private_pem {
+            Some(private_pem) => {
+                let parsed = pem::parse(private_pem)
+                    .map_err(|e| format!("Invalid key log signing key: {}", e))?;
+
+                Ed25519KeyPair::from_pkcs8_maybe_unchecked(parsed.contents())
+                    .map_err(|e| format!("Invalid key log signing key: {}", e))?
+            }
+            None => {
+                tracing::warn!(
+                    "KEY_LOG_SIGNING_KEY_PATH not set, signing tree heads with a temporary key"
+                );
+
+                let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new())
+                    .map_err(|e| format!("Failed to generate key log signing key: {}", e))?;
+
+                Ed25519KeyPair::from_pkcs8(pkcs8.as_ref())
+                    .map_err(|e| format!("Failed to generate key log signing key: {}", e))?
+            }
+        };
+
+        Ok(Self {
+            key_pair: Arc::new(key_pair),
+        })
+    }
+
+    pub fn public_key(&self) -> &[u8] {
+        self.key_pair.public_key().as_ref()
+    }
+
+    pub fn sign_tree_head(&self, timestamp_ms: i64, tree_size: i64, root_hash: &Hash) -> Vec<u8> {
+        self.key_pair
+            .sign(&tree_head_input(timestamp_ms, tree_size, root_hash))
+            .as_ref()
+            .to_vec()
+    }
+}
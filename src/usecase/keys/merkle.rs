@@ -0,0 +1,294 @@
+//! Merkle tree hashing and proofs as specified by RFC 6962 (Certificate
+//! Transparency), with the verification algorithms of RFC 9162.
+//!
+//! Leaves and interior nodes are domain-separated (`0x00` and `0x01`
+//! prefixes), so a leaf can never be passed off as a subtree.
+//!
+//! A stored log keeps the root of every perfect subtree as it completes
+//! ([`append`]). Any subtree the proofs need is then a right fold over at
+//! most one stored root per level ([`subtree_nodes`]), so neither appends
+//! nor proofs read the whole log.
+
+use std::ops::Range;
+
+use ring::digest::{SHA256, digest};
+
+pub type Hash = [u8; 32];
+
+/// The root of a perfect subtree: the `2^level` leaves from
+/// `index << level` on. Level 0 nodes are the leaf hashes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Node {
+    pub level: u32,
+    pub index: u64,
+}
+
+/// `MTH({})`, the root of the empty tree.
+pub fn empty_root() -> Hash {
+    sha256(&[&[]])
+}
+
+pub fn leaf_hash(data: &[u8]) -> Hash {
+    sha256(&[&[0x00], data])
+}
+
+pub fn node_hash(left: &Hash, right: &Hash) -> Hash {
+    sha256(&[&[0x01], left, right])
+}
+
+/// `MTH(D[n])` over leaf hashes.
+pub fn root(leaves: &[Hash]) -> Hash {
+    match leaves.len() {
+        0 => empty_root(),
+        1 => leaves[0],
+        n => {
+            let (left, right) = leaves.split_at(split_point(n as u64) as usize);
+            node_hash(&root(left), &root(right))
+        }
+    }
+}
+
+/// The audit path `PATH(m, D[n])` proving that leaf `m` is in the tree.
+/// Empty when `m` is out of range.
+pub fn inclusion_proof(index: usize, leaves: &[Hash]) -> Vec<Hash> {
+    subtree_roots(leaves, inclusion_ranges(index as u64, leaves.len() as u64))
+}
+
+/// `PROOF(m, D[n])`, proving that the tree of the first `m` leaves is a
+/// prefix of the tree of all of them. Empty when `m` is 0 or not below `n`.
+pub fn consistency_proof(size: usize, leaves: &[Hash]) -> Vec<Hash> {
+    subtree_roots(leaves, consistency_ranges(size as u64, leaves.len() as u64))
+}
+
+fn subtree_roots(leaves: &[Hash], ranges: Vec<Range<u64>>) -> Vec<Hash> {
+    ranges
+        .into_iter()
+        .map(|range| root(&leaves[range.start as usize..range.end as usize]))
+        .collect()
+}
+
+/// The leaf ranges whose roots make up `PATH(m, D[n])`, in proof order.
+pub fn inclusion_ranges(index: u64, size: u64) -> Vec<Range<u64>> {
+    let mut ranges = Vec::new();
+    if index < size {
+        inclusion_subranges(index, 0..size, &mut ranges);
+    }
+
+    ranges
+}
+
+fn inclusion_subranges(index: u64, range: Range<u64>, ranges: &mut Vec<Range<u64>>) {
+    if range.end - range.start == 1 {
+        return;
+    }
+
+    let mid = range.start + split_point(range.end - range.start);
+    if index < mid {
+        inclusion_subranges(index, range.start..mid, ranges);
+        ranges.push(mid..range.end);
+    } else {
+        inclusion_subranges(index, mid..range.end, ranges);
+        ranges.push(range.start..mid);
+    }
+}
+
+/// The leaf ranges whose roots make up `PROOF(m, D[n])`, in proof order.
+pub fn consistency_ranges(first: u64, second: u64) -> Vec<Range<u64>> {
+    let mut ranges = Vec::new();
+    if first > 0 && first < second {
+        consistency_subranges(first, 0..second, true, &mut ranges);
+    }
+
+    ranges
+}
+
+fn consistency_subranges(
+    first: u64,
+    range: Range<u64>,
+    complete: bool,
+    ranges: &mut Vec<Range<u64>>,
+) {
+    if first == range.end {
+        if !complete {
+            ranges.push(range);
+        }
+        return;
+    }
+
+    let mid = range.start + split_point(range.end - range.start);
+    if first <= mid {
+        consistency_subranges(first, range.start..mid, complete, ranges);
+        ranges.push(mid..range.end);
+    } else {
+        consistency_subranges(first, mid..range.end, false, ranges);
+        ranges.push(range.start..mid);
+    }
+}
+
+/// The perfect subtrees, left to right, that `MTH(D[range])` folds over.
+/// `range` must be the whole tree or a subtree of it, as the ranges above
+/// are, so that each piece is aligned.
+pub fn subtree_nodes(range: Range<u64>) -> Vec<Node> {
+    let mut nodes = Vec::new();
+    let mut start = range.start;
+
+    while start < range.end {
+        let level = (range.end - start).ilog2();
+        nodes.push(Node {
+            level,
+            index: start >> level,
+        });
+        start += 1 << level;
+    }
+
+    nodes
+}
+
+/// `MTH(D[range])` from the roots of its [`subtree_nodes`], in order.
+pub fn subtree_root(roots: &[Hash]) -> Hash {
+    match roots.split_last() {
+        None => empty_root(),
+        Some((last, rest)) => rest
+            .iter()
+            .rev()
+            .fold(*last, |right, left| node_hash(left, &right)),
+    }
+}
+
+/// The left siblings that leaf `index` is hashed with as it completes
+/// perfect subtrees, lowest first; together, the log's frontier.
+pub fn append_siblings(index: u64) -> Vec<Node> {
+    (0..u64::BITS)
+        .take_while(|level| (index >> level) & 1 == 1)
+        .map(|level| Node {
+            level,
+            index: (index >> level) - 1,
+        })
+        .collect()
+}
+
+/// The perfect subtrees completed by appending leaf `index`: the leaf
+/// itself, then one parent per sibling. `siblings` holds the roots of
+/// [`append_siblings`], in that order.
+pub fn append(index: u64, leaf: Hash, siblings: &[Hash]) -> Vec<(Node, Hash)> {
+    let mut node = Node { level: 0, index };
+    let mut hash = leaf;
+    let mut completed = vec![(node, hash)];
+
+    for sibling in siblings {
+        hash = node_hash(sibling, &hash);
+        node = Node {
+            level: node.level + 1,
+            index: node.index >> 1,
+        };
+        completed.push((node, hash));
+    }
+
+    completed
+}
+
+/// Checks an audit path for leaf `index` of a tree with `tree_size` leaves.
+pub fn verify_inclusion(
+    index: u64,
+    tree_size: u64,
+    leaf_hash: &Hash,
+    proof: &[Hash],
+    root: &Hash,
+) -> bool {
+    if index >= tree_size {
+        return false;
+    }
+
+    let (mut f, mut s) = (index, tree_size - 1);
+    let mut r = *leaf_hash;
+
+    for p in proof {
+        if s == 0 {
+            return false;
+        }
+
+        if f & 1 == 1 || f == s {
+            r = node_hash(p, &r);
+            while f & 1 == 0 && f != 0 {
+                f >>= 1;
+                s >>= 1;
+            }
+        } else {
+            r = node_hash(&r, p);
+        }
+
+        f >>= 1;
+        s >>= 1;
+    }
+
+    s == 0 && r == *root
+}
+
+/// Checks that the tree with root `first_root` and `first` leaves is a
+/// prefix of the one with root `second_root` and `second` leaves.
+pub fn verify_consistency(
+    first: u64,
+    second: u64,
+    first_root: &Hash,
+    second_root: &Hash,
+    proof: &[Hash],
+) -> bool {
+    if first == 0 || first > second {
+        return false;
+    }
+
+    if first == second {
+        return proof.is_empty() && first_root == second_root;
+    }
+
+    let mut path = Vec::with_capacity(proof.len() + 1);
+    if first.is_power_of_two() {
+        path.push(*first_root);
+    }
+    path.extend_from_slice(proof);
+
+    let Some((seed, rest)) = path.split_first() else {
+        return false;
+    };
+
+    let (mut f, mut s) = (first - 1, second - 1);
+    while f & 1 == 1 {
+        f >>= 1;
+        s >>= 1;
+    }
+
+    let (mut fr, mut sr) = (*seed, *seed);
+    for c in rest {
+        if s == 0 {
+            return false;
+        }
+
+        if f & 1 == 1 || f == s {
+            fr = node_hash(c, &fr);
+            sr = node_hash(c, &sr);
+            while f & 1 == 0 && f != 0 {
+                f >>= 1;
+                s >>= 1;
+            }
+        } else {
+            sr = node_hash(&sr, c);
+        }
+
+        f >>= 1;
+        s >>= 1;
+    }
+
+    fr == *first_root && sr == *second_root && s == 0
+}
+
+/// The largest power of two below `n`, for `n > 1`.
+fn split_point(n: u64) -> u64 {
+    1 << (n - 1).ilog2()
+}
+
+fn sha256(parts: &[&[u8]]) -> Hash {
+    let data = parts.concat();
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(digest(&SHA256, &data).as_ref());
+    hash
+}
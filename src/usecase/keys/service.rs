@@ -0,0 +1,656 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use chrono::Utc;
+use uuid::Uuid;
+
+use super::error::KeyError;
+use super::log::{LogSigner, leaf_input};
+use super::merkle::{self, Hash, Node};
+use crate::config::keys::KeyDirectoryConfig;
+use crate::repository::chat::{MessageKind, NewMessage};
+use crate::repository::key_log::{KeyLogEntry, KeyLogNode, NewKeyLogEntry, SignedTreeHead};
+use crate::repository::keys::{IdentityKey, NewIdentityKey, NewOneTimePrekey, OneTimePrekey};
+use crate::repository::{Repository, RepositoryError};
+use crate::usecase::blocking::run_blocking;
+
+/// Curve25519 public keys are 32 bytes, or 33 with the type byte some
+/// clients prepend.
+const PUBLIC_KEY_LENGTHS: [usize; 2] = [32, 33];
+const SIGNATURE_LENGTH: usize = 64;
+const MAX_LOG_ENTRIES_PER_PAGE: i64 = 1000;
+
+/// Directory of public keys for X3DH-style session setup. The server only
+/// stores and hands out public material; it never sees private keys.
+///
+/// Every identity key is also appended to a Merkle log, so clients can
+/// check that the directory shows everyone the same, append-only history.
+#[derive(Clone)]
+pub struct KeyService {
+    repo: Repository,
+    signer: LogSigner,
+    prekey_low_watermark: i64,
+    max_prekeys_per_upload: usize,
+    max_stored_prekeys: i64,
+}
+
+pub struct SignedPrekeyInfo {
+    pub key_id: i32,
+    pub public_key: String,
+    pub signature: String,
+}
+
+pub struct OneTimePrekeyInfo {
+    pub key_id: i32,
+    pub public_key: String,
+}
+
+/// Keys for starting a session with every device of a user.
+pub struct PrekeyBundle {
+    pub user_id: Uuid,
+    pub devices: Vec<DeviceBundle>,
+}
+
+pub struct DeviceBundle {
+    pub device_id: Uuid,
+    pub identity_key: String,
+    pub signed_prekey: SignedPrekeyInfo,
+    /// `None` once the stock is exhausted; clients then fall back to a
+    /// session without a one-time prekey.
+    pub one_time_prekey: Option<OneTimePrekeyInfo>,
+}
+
+pub struct PrekeyStatus {
+    pub remaining: i64,
+    pub low_watermark: i64,
+    pub needs_replenish: bool,
+}
+
+/// A signed root of the key log. Hashes and the signature are base64.
+pub struct TreeHeadInfo {
+    pub tree_size: i64,
+    pub timestamp: i64,
+    pub root_hash: String,
+    pub signature: String,
+}
+
+pub struct KeyLogEntryInfo {
+    pub leaf_index: i64,
+    pub user_id: Uuid,
+    pub device_id: Uuid,
+    pub identity_key: String,
+    pub timestamp: i64,
+    pub leaf_hash: String,
+}
+
+pub struct InclusionProof {
+    pub entry: KeyLogEntryInfo,
+    pub audit_path: Vec<String>,
+}
+
+/// Proofs that a user's current identity keys are in the tree of
+/// `tree_head`.
+pub struct KeyInclusionProofs {
+    pub tree_head: TreeHeadInfo,
+    pub proofs: Vec<InclusionProof>,
+}
+
+pub struct ConsistencyProof {
+    pub first: i64,
+    pub second: i64,
+    pub proof: Vec<String>,
+}
+
+impl KeyService {
+    pub fn new(repo: Repository, config: &KeyDirectoryConfig) -> Result<Self, String> {
+        Ok(Self {
+            repo,
+            signer: LogSigner::new(config.log_signing_key_pem.as_deref())?,
+            prekey_low_watermark: config.prekey_low_watermark,
+            max_prekeys_per_upload: config.max_prekeys_per_upload,
+            max_stored_prekeys: config.max_stored_prekeys,
+        })
+    }
+
+    /// Publishes the identity key and signed prekey of one of the caller's
+    /// devices. Changing the identity key discards the one-time prekeys
+    /// issued under the old one, logs the new key and tells the user's chats
+    /// about it.
+    #[tracing::instrument(skip(self, identity_key, signed_prekey))]
+    pub async fn publish_identity(
+        &self,
+        user_id: Uuid,
+        device_id: Uuid,
+        identity_key: String,
+        signed_prekey: SignedPrekeyInfo,
+    ) -> Result<PrekeyStatus, KeyError> {
+        let this = self.clone();
+        run_blocking(move || {
+            validate_public_key("identity_key", &identity_key)?;
+            validate_key_id(signed_prekey.key_id)?;
+            validate_public_key("signed_prekey", &signed_prekey.public_key)?;
+            validate_signature(&signed_prekey.signature)?;
+
+            this.repo.transaction(|tx| {
+                ensure_own_device(tx, user_id, device_id)?;
+
+                let previous = tx.keys.find_identity(device_id)?;
+                let changed = previous.is_some_and(|p| p.identity_key != identity_key);
+
+                if changed {
+                    let discarded = tx.keys.delete_prekeys(device_id)?;
+                    tracing::info!(
+                        discarded,
+                        "Identity key changed, discarded one-time prekeys"
+                    );
+                }
+
+                let logged = this.log_identity(tx, user_id, device_id, &identity_key)?;
+
+                if changed {
+                    announce_key_change(tx, user_id, device_id, logged.as_ref())?;
+                }
+
+                tx.keys.upsert_identity(NewIdentityKey {
+                    device_id,
+                    user_id,
+                    identity_key,
+                    signed_prekey_id: signed_prekey.key_id,
+                    signed_prekey: signed_prekey.public_key,
+                    signed_prekey_signature: signed_prekey.signature,
+                })?;
+
+                Ok(this.status(tx.keys.count_prekeys(device_id)?))
+            })
+        })
+        .await
+    }
+
+    /// Adds a batch of one-time prekeys for one of the caller's devices. The
+    /// device's identity key has to be published first, since prekeys are
+    /// only usable alongside it.
+    #[tracing::instrument(skip(self, prekeys), fields(count = prekeys.len()))]
+    pub async fn upload_prekeys(
+        &self,
+        user_id: Uuid,
+        device_id: Uuid,
+        prekeys: Vec<OneTimePrekeyInfo>,
+    ) -> Result<PrekeyStatus, KeyError> {
+        let this = self.clone();
+        run_blocking(move || {
+            if prekeys.is_empty() {
+                return Err(KeyError::InvalidKey("No prekeys given".to_string()));
+            }
+
+            if prekeys.len() > this.max_prekeys_per_upload {
+                return Err(KeyError::TooManyPrekeys(format!(
+                    "at most {} per upload",
+                    this.max_prekeys_per_upload
+                )));
+            }
+
+            for prekey in &prekeys {
+                validate_key_id(prekey.key_id)?;
+                validate_public_key("one_time_prekey", &prekey.public_key)?;
+            }
+
+            let rows: Vec<NewOneTimePrekey> = prekeys
+                .into_iter()
+                .map(|prekey| NewOneTimePrekey {
+                    device_id,
+                    key_id: prekey.key_id,
+                    public_key: prekey.public_key,
+                })
+                .collect();
+
+            this.repo.transaction(|tx| {
+                ensure_own_device(tx, user_id, device_id)?;
+
+                tx.keys
+                    .find_identity(device_id)?
+                    .ok_or(KeyError::KeysNotFound)?;
+
+                let stored = tx.keys.count_prekeys(device_id)?;
+                if stored + rows.len() as i64 > this.max_stored_prekeys {
+                    return Err(KeyError::TooManyPrekeys(format!(
+                        "at most {} stored, {} already on the server",
+                        this.max_stored_prekeys, stored
+                    )));
+                }
+
+                tx.keys.add_prekeys(&rows).map_err(|e| match e {
+                    RepositoryError::Conflict(_) => KeyError::DuplicatePrekey,
+                    e => KeyError::from(e),
+                })?;
+
+                Ok(this.status(stored + rows.len() as i64))
+            })
+        })
+        .await
+    }
+
+    pub async fn prekey_status(
+        &self,
+        user_id: Uuid,
+        device_id: Uuid,
+    ) -> Result<PrekeyStatus, KeyError> {
+        let this = self.clone();
+        run_blocking(move || {
+            ensure_own_device(&this.repo, user_id, device_id)?;
+
+            Ok(this.status(this.repo.keys.count_prekeys(device_id)?))
+        })
+        .await
+    }
+
+    /// Returns the key bundles for starting a session with every device of
+    /// `user_id` that has published keys, consuming one one-time prekey per
+    /// device.
+    #[tracing::instrument(skip(self))]
+    pub async fn fetch_bundle(&self, user_id: Uuid) -> Result<PrekeyBundle, KeyError> {
+        let this = self.clone();
+        run_blocking(move || {
+            let identities = this.repo.keys.find_user_identities(user_id)?;
+
+            if identities.is_empty() {
+                return Err(KeyError::KeysNotFound);
+            }
+
+            let mut devices = Vec::with_capacity(identities.len());
+            for identity in identities {
+                let device_id = identity.device_id;
+                let one_time_prekey = this.repo.keys.take_prekey(device_id)?;
+
+                let remaining = this.repo.keys.count_prekeys(device_id)?;
+                if remaining < this.prekey_low_watermark {
+                    tracing::warn!(
+                        %user_id,
+                        %device_id,
+                        remaining,
+                        exhausted = one_time_prekey.is_none(),
+                        "One-time prekey stock is low"
+                    );
+                }
+
+                devices.push(DeviceBundle::new(identity, one_time_prekey));
+            }
+
+            Ok(PrekeyBundle { user_id, devices })
+        })
+        .await
+    }
+
+    /// The latest signed tree head of the key log.
+    pub async fn tree_head(&self) -> Result<TreeHeadInfo, KeyError> {
+        let this = self.clone();
+        run_blocking(move || Ok(TreeHeadInfo::from(this.head_at(None)?))).await
+    }
+
+    /// The base64 Ed25519 public key that verifies tree heads.
+    pub fn log_public_key(&self) -> String {
+        STANDARD.encode(self.signer.public_key())
+    }
+
+    /// Log entries from `start` on, for auditors replaying the log.
+    pub async fn get_log_entries(
+        &self,
+        start: i64,
+        limit: i64,
+    ) -> Result<Vec<KeyLogEntryInfo>, KeyError> {
+        let this = self.clone();
+        run_blocking(move || {
+            let entries = this
+                .repo
+                .key_log
+                .get_entries(start.max(0), limit.clamp(1, MAX_LOG_ENTRIES_PER_PAGE))?;
+
+            Ok(entries.into_iter().map(KeyLogEntryInfo::from).collect())
+        })
+        .await
+    }
+
+    /// Audit paths for the current identity key of each of the user's
+    /// devices, against the head of `tree_size` (the latest by default).
+    /// Devices whose current key is not in that tree are left out.
+    #[tracing::instrument(skip(self))]
+    pub async fn prove_keys(
+        &self,
+        user_id: Uuid,
+        tree_size: Option<i64>,
+    ) -> Result<KeyInclusionProofs, KeyError> {
+        let this = self.clone();
+        run_blocking(move || {
+            let head = this.head_at(tree_size)?;
+
+            let mut proofs = Vec::new();
+            for identity in this.repo.keys.find_user_identities(user_id)? {
+                let Some(entry) = this.repo.key_log.find_latest_entry(identity.device_id)? else {
+                    continue;
+                };
+
+                if entry.identity_key != identity.identity_key || entry.leaf_index >= head.tree_size
+                {
+                    continue;
+                }
+
+                let audit_path = range_roots(
+                    &this.repo,
+                    merkle::inclusion_ranges(entry.leaf_index as u64, head.tree_size as u64),
+                )?;
+                proofs.push(InclusionProof {
+                    entry: KeyLogEntryInfo::from(entry),
+                    audit_path: encode_hashes(&audit_path),
+                });
+            }
+
+            if proofs.is_empty() {
+                return Err(KeyError::KeyNotLogged);
+            }
+
+            Ok(KeyInclusionProofs {
+                tree_head: TreeHeadInfo::from(head),
+                proofs,
+            })
+        })
+        .await
+    }
+
+    /// Proof that the tree of `first` leaves is a prefix of the tree of
+    /// `second` leaves (the latest by default).
+    pub async fn consistency_proof(
+        &self,
+        first: i64,
+        second: Option<i64>,
+    ) -> Result<ConsistencyProof, KeyError> {
+        let this = self.clone();
+        run_blocking(move || {
+            let second = this.head_at(second)?.tree_size;
+
+            if first < 1 || first > second {
+                return Err(KeyError::InvalidTreeSize(format!(
+                    "first must be between 1 and {}",
+                    second
+                )));
+            }
+
+            let proof = range_roots(
+                &this.repo,
+                merkle::consistency_ranges(first as u64, second as u64),
+            )?;
+
+            Ok(ConsistencyProof {
+                first,
+                second,
+                proof: encode_hashes(&proof),
+            })
+        })
+        .await
+    }
+
+    /// Appends the device's identity key to the log, unless it already is
+    /// the device's latest entry, and signs the resulting tree head. The
+    /// new leaf is hashed up the frontier, so an append reads and writes
+    /// one node per level.
+    fn log_identity(
+        &self,
+        tx: &Repository,
+        user_id: Uuid,
+        device_id: Uuid,
+        identity_key: &str,
+    ) -> Result<Option<KeyLogEntry>, KeyError> {
+        let latest = tx.key_log.find_latest_entry(device_id)?;
+        if latest.is_some_and(|entry| entry.identity_key == identity_key) {
+            return Ok(None);
+        }
+
+        let timestamp_ms = Utc::now().timestamp_millis();
+        let key_bytes = decode("identity_key", identity_key)?;
+        let leaf_hash =
+            merkle::leaf_hash(&leaf_input(user_id, device_id, timestamp_ms, &key_bytes));
+
+        let entry = tx.key_log.append_entry(NewKeyLogEntry {
+            user_id,
+            device_id,
+            identity_key: identity_key.to_string(),
+            timestamp_ms,
+            leaf_hash: leaf_hash.to_vec(),
+        })?;
+
+        let index = entry.leaf_index as u64;
+        let siblings = node_hashes(tx, &merkle::append_siblings(index))?;
+        tx.key_log.add_nodes(
+            merkle::append(index, leaf_hash, &siblings)
+                .into_iter()
+                .map(|(node, hash)| KeyLogNode {
+                    level: node.level as i16,
+                    node_index: node.index as i64,
+                    hash: hash.to_vec(),
+                })
+                .collect(),
+        )?;
+
+        let tree_size = entry.leaf_index + 1;
+        let root_hash = merkle::subtree_root(&node_hashes(
+            tx,
+            &merkle::subtree_nodes(0..tree_size as u64),
+        )?);
+
+        tx.key_log.add_head(SignedTreeHead {
+            tree_size,
+            timestamp_ms,
+            root_hash: root_hash.to_vec(),
+            signature: self
+                .signer
+                .sign_tree_head(timestamp_ms, tree_size, &root_hash),
+        })?;
+
+        tracing::info!(leaf_index = entry.leaf_index, "Identity key logged");
+
+        Ok(Some(entry))
+    }
+
+    /// The head of `tree_size`, or the latest one. Every size up to the
+    /// latest has a head, since each append signs one.
+    fn head_at(&self, tree_size: Option<i64>) -> Result<SignedTreeHead, KeyError> {
+        let latest = self.repo.key_log.latest_head()?.ok_or(KeyError::LogEmpty)?;
+
+        match tree_size {
+            None => Ok(latest),
+            Some(size) if size < 1 || size > latest.tree_size => Err(KeyError::InvalidTreeSize(
+                format!("tree_size must be between 1 and {}", latest.tree_size),
+            )),
+            Some(size) => self
+                .repo
+                .key_log
+                .find_head(size)?
+                .ok_or_else(|| KeyError::Internal(format!("No tree head for size {}", size))),
+        }
+    }
+
+    fn status(&self, remaining: i64) -> PrekeyStatus {
+        PrekeyStatus {
+            remaining,
+            low_watermark: self.prekey_low_watermark,
+            needs_replenish: remaining < self.prekey_low_watermark,
+        }
+    }
+}
+
+impl DeviceBundle {
+    fn new(identity: IdentityKey, one_time_prekey: Option<OneTimePrekey>) -> Self {
+        Self {
+            device_id: identity.device_id,
+            identity_key: identity.identity_key,
+            signed_prekey: SignedPrekeyInfo {
+                key_id: identity.signed_prekey_id,
+                public_key: identity.signed_prekey,
+                signature: identity.signed_prekey_signature,
+            },
+            one_time_prekey: one_time_prekey.map(|prekey| OneTimePrekeyInfo {
+                key_id: prekey.key_id,
+                public_key: prekey.public_key,
+            }),
+        }
+    }
+}
+
+impl From<SignedTreeHead> for TreeHeadInfo {
+    fn from(head: SignedTreeHead) -> Self {
+        Self {
+            tree_size: head.tree_size,
+            timestamp: head.timestamp_ms,
+            root_hash: STANDARD.encode(head.root_hash),
+            signature: STANDARD.encode(head.signature),
+        }
+    }
+}
+
+impl From<KeyLogEntry> for KeyLogEntryInfo {
+    fn from(entry: KeyLogEntry) -> Self {
+        Self {
+            leaf_index: entry.leaf_index,
+            user_id: entry.user_id,
+            device_id: entry.device_id,
+            identity_key: entry.identity_key,
+            timestamp: entry.timestamp_ms,
+            leaf_hash: STANDARD.encode(entry.leaf_hash),
+        }
+    }
+}
+
+/// Posts a notice into every chat of the user that one of their devices
+/// now has a different identity key, so members know to re-verify it.
+fn announce_key_change(
+    tx: &Repository,
+    user_id: Uuid,
+    device_id: Uuid,
+    entry: Option<&KeyLogEntry>,
+) -> Result<(), KeyError> {
+    let payload = serde_json::json!({
+        "user_id": user_id,
+        "device_id": device_id,
+        "leaf_index": entry.map(|entry| entry.leaf_index),
+    })
+    .to_string();
+
+    for chat in tx.chat.get_user_chats(user_id)? {
+        tx.chat.create_message(
+            NewMessage {
+                chat_id: chat.id,
+                sender_id: user_id,
+                sender_device_id: Some(device_id),
+                encrypted_content: None,
+                epoch: None,
+                kind: MessageKind::IdentityKeyChanged.as_str().to_string(),
+                payload: Some(payload.clone()),
+                expires_at: None,
+                view_once: false,
+                mentions: Vec::new(),
+                mention_all: false,
+                forwarded_from_chat_id: None,
+                forwarded_from_message_id: None,
+                forwarded_from_sender_id: None,
+            },
+            &HashMap::new(),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Stored subtree roots of the key log, in the order asked for.
+fn node_hashes(repo: &Repository, nodes: &[Node]) -> Result<Vec<Hash>, KeyError> {
+    let ids: Vec<(i16, i64)> = nodes
+        .iter()
+        .map(|node| (node.level as i16, node.index as i64))
+        .collect();
+
+    let stored: HashMap<(i16, i64), Vec<u8>> = repo
+        .key_log
+        .get_nodes(&ids)?
+        .into_iter()
+        .map(|node| ((node.level, node.node_index), node.hash))
+        .collect();
+
+    ids.iter()
+        .map(|(level, index)| {
+            let hash = stored.get(&(*level, *index)).ok_or_else(|| {
+                KeyError::Internal(format!("Key log node {} at level {} missing", index, level))
+            })?;
+
+            Hash::try_from(hash.as_slice())
+                .map_err(|_| KeyError::Internal("Malformed hash in key log".to_string()))
+        })
+        .collect()
+}
+
+/// Roots of the given leaf ranges, folded from stored subtrees read in one
+/// query.
+fn range_roots(repo: &Repository, ranges: Vec<Range<u64>>) -> Result<Vec<Hash>, KeyError> {
+    let pieces: Vec<Vec<Node>> = ranges.into_iter().map(merkle::subtree_nodes).collect();
+    let hashes = node_hashes(repo, &pieces.concat())?;
+
+    let mut rest = hashes.as_slice();
+    Ok(pieces
+        .iter()
+        .map(|nodes| {
+            let (roots, tail) = rest.split_at(nodes.len());
+            rest = tail;
+            merkle::subtree_root(roots)
+        })
+        .collect())
+}
+
+fn encode_hashes(hashes: &[Hash]) -> Vec<String> {
+    hashes.iter().map(|hash| STANDARD.encode(hash)).collect()
+}
+
+fn ensure_own_device(repo: &Repository, user_id: Uuid, device_id: Uuid) -> Result<(), KeyError> {
+    repo.devices
+        .find_device(device_id)?
+        .filter(|device| device.user_id == user_id)
+        .ok_or(KeyError::UnknownDevice)?;
+
+    Ok(())
+}
+
+fn validate_key_id(key_id: i32) -> Result<(), KeyError> {
+    if key_id < 0 {
+        return Err(KeyError::InvalidKey(
+            "Key ids must not be negative".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+fn validate_public_key(field: &str, value: &str) -> Result<(), KeyError> {
+    let decoded = decode(field, value)?;
+
+    if !PUBLIC_KEY_LENGTHS.contains(&decoded.len()) {
+        return Err(KeyError::InvalidKey(format!(
+            "{} must be a 32-byte public key",
+            field
+        )));
+    }
+
+    Ok(())
+}
+
+fn validate_signature(value: &str) -> Result<(), KeyError> {
+    if decode("signature", value)?.len() != SIGNATURE_LENGTH {
+        return Err(KeyError::InvalidKey(
+            "signature must be 64 bytes".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+fn decode(field: &str, value: &str) -> Result<Vec<u8>, KeyError> {
+    STANDARD
+        .decode(value)
+        .map_err(|_| KeyError::InvalidKey(format!("{} is not valid base64", field)))
+}
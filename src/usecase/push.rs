@@ -0,0 +1,5 @@
+pub mod error;
+pub mod service;
+
+pub use error::PushError;
+pub use service::{PushDispatch, PushEndpointInfo, PushService};
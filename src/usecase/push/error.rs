@@ -0,0 +1,43 @@
+use thiserror::Error;
+
+use crate::repository::RepositoryError;
+
+#[derive(Debug, Error)]
+pub enum PushError {
+    #[error("Invalid push endpoint: {0}")]
+    InvalidEndpoint(String),
+
+    #[error("Unknown device")]
+    UnknownDevice,
+
+    #[error("Push endpoint not found")]
+    EndpointNotFound,
+
+    #[error("Web Push is not configured")]
+    WebPushDisabled,
+
+    #[error("Service temporarily unavailable")]
+    Unavailable,
+
+    #[error("Internal error: {0}")]
+    Internal(String),
+}
+
+impl From<tokio::task::JoinError> for PushError {
+    fn from(err: tokio::task::JoinError) -> Self {
+        Self::Internal(err.to_string())
+    }
+}
+
+impl From<RepositoryError> for PushError {
+    fn from(err: RepositoryError) -> Self {
+        match err {
+            RepositoryError::Unavailable(cause) => {
+                tracing::error!("Database unavailable: {}", cause);
+                Self::Unavailable
+            }
+            RepositoryError::NotFound => Self::EndpointNotFound,
+            err => Self::Internal(err.to_string()),
+        }
+    }
+}
@@ -0,0 +1,252 @@
+use std::sync::Arc;
+
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use chrono::{Duration, NaiveDateTime, Utc};
+use uuid::Uuid;
+
+use super::error::PushError;
+use crate::config::push::PushConfig;
+use crate::repository::Repository;
+use crate::repository::push::{NewPushEndpoint, PushEndpoint, PushEndpointKind, PushJob};
+use crate::repository::push_providers::{self, PushProvider, PushProviderError};
+use crate::usecase::backoff::Backoff;
+use crate::usecase::blocking::run_blocking;
+
+/// How long a claimed wake-up is left to one dispatcher before others may
+/// pick it up again.
+const JOB_LEASE_SECONDS: i64 = 300;
+
+/// Wake-ups for devices that are offline when a message arrives. Devices
+/// register an endpoint; posting a message queues one job per device of
+/// every member to notify, and the dispatcher sends them through a
+/// [`PushProvider`], retrying with exponential backoff and dead-lettering
+/// jobs that keep failing.
+#[derive(Clone)]
+pub struct PushService {
+    repo: Repository,
+    provider: Arc<dyn PushProvider>,
+    max_attempts: i32,
+    backoff: Backoff,
+    online_window: Duration,
+}
+
+pub struct PushEndpointInfo {
+    pub device_id: Uuid,
+    pub kind: String,
+    pub url: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// What one dispatcher run did.
+#[derive(Debug, Default)]
+pub struct PushDispatch {
+    pub claimed: usize,
+    pub sent: usize,
+    /// Dropped because the device was online.
+    pub skipped: usize,
+    pub retried: usize,
+    /// Dead-lettered, or dropped with an endpoint that is gone.
+    pub failed: usize,
+}
+
+enum Outcome {
+    Sent,
+    Skipped,
+    Retried,
+    Failed,
+}
+
+impl PushService {
+    pub fn new(repo: Repository, config: &PushConfig) -> Result<Self, String> {
+        Ok(Self::with_provider(
+            repo,
+            config,
+            push_providers::open(config)?,
+        ))
+    }
+
+    pub fn with_provider(
+        repo: Repository,
+        config: &PushConfig,
+        provider: Arc<dyn PushProvider>,
+    ) -> Self {
+        Self {
+            repo,
+            provider,
+            max_attempts: config.max_attempts,
+            backoff: Backoff::new(config.retry_base_seconds),
+            online_window: Duration::seconds(config.online_window_seconds),
+        }
+    }
+
+    /// The base64url key to create Web Push subscriptions with.
+    pub fn vapid_public_key(&self) -> Result<String, PushError> {
+        self.provider
+            .vapid_public_key()
+            .map(|key| URL_SAFE_NO_PAD.encode(key))
+            .ok_or(PushError::WebPushDisabled)
+    }
+
+    /// Sets where one of the caller's devices is woken up, replacing any
+    /// earlier endpoint.
+    #[tracing::instrument(skip(self, url))]
+    pub async fn set_endpoint(
+        &self,
+        user_id: Uuid,
+        device_id: Uuid,
+        kind: String,
+        url: String,
+    ) -> Result<PushEndpointInfo, PushError> {
+        let kind = PushEndpointKind::parse(&kind).ok_or_else(|| {
+            PushError::InvalidEndpoint("kind must be webhook or web_push".to_string())
+        })?;
+        self.provider
+            .check_endpoint(kind, &url)
+            .map_err(PushError::InvalidEndpoint)?;
+
+        let this = self.clone();
+        run_blocking(move || {
+            ensure_own_device(&this.repo, user_id, device_id)?;
+
+            let endpoint = this.repo.push.upsert_endpoint(NewPushEndpoint {
+                device_id,
+                kind: kind.as_str().to_string(),
+                url,
+            })?;
+
+            Ok(PushEndpointInfo::from(endpoint))
+        })
+        .await
+    }
+
+    pub async fn get_endpoint(
+        &self,
+        user_id: Uuid,
+        device_id: Uuid,
+    ) -> Result<PushEndpointInfo, PushError> {
+        let this = self.clone();
+        run_blocking(move || {
+            ensure_own_device(&this.repo, user_id, device_id)?;
+
+            let endpoint = this
+                .repo
+                .push
+                .find_endpoint(device_id)?
+                .ok_or(PushError::EndpointNotFound)?;
+
+            Ok(PushEndpointInfo::from(endpoint))
+        })
+        .await
+    }
+
+    /// Stops waking the device up, dropping any pending wake-up.
+    #[tracing::instrument(skip(self))]
+    pub async fn remove_endpoint(&self, user_id: Uuid, device_id: Uuid) -> Result<(), PushError> {
+        let this = self.clone();
+        run_blocking(move || {
+            ensure_own_device(&this.repo, user_id, device_id)?;
+
+            this.repo.push.delete_endpoint(device_id)?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    /// Sends up to `batch_size` due wake-ups. Devices that fetched messages
+    /// within the online window are skipped; failures are retried with
+    /// exponential backoff until the attempts run out.
+    ///
+    /// Claimed jobs are leased, so concurrent dispatchers never send the
+    /// same one.
+    pub async fn dispatch_wake_ups(&self, batch_size: i64) -> Result<PushDispatch, PushError> {
+        let this = self.clone();
+        run_blocking(move || {
+            let now = Utc::now().naive_utc();
+            let jobs = this.repo.push.claim_due_jobs(
+                now,
+                now + Duration::seconds(JOB_LEASE_SECONDS),
+                batch_size,
+            )?;
+
+            let mut dispatch = PushDispatch {
+                claimed: jobs.len(),
+                ..PushDispatch::default()
+            };
+
+            for job in jobs {
+                match this.dispatch(job, now)? {
+                    Outcome::Sent => dispatch.sent += 1,
+                    Outcome::Skipped => dispatch.skipped += 1,
+                    Outcome::Retried => dispatch.retried += 1,
+                    Outcome::Failed => dispatch.failed += 1,
+                }
+            }
+
+            Ok(dispatch)
+        })
+        .await
+    }
+
+    fn dispatch(&self, job: PushJob, now: NaiveDateTime) -> Result<Outcome, PushError> {
+        let device = self.repo.devices.find_device(job.device_id)?;
+        let endpoint = self.repo.push.find_endpoint(job.device_id)?;
+
+        let (Some(device), Some(endpoint)) = (device, endpoint) else {
+            // Removed since the claim, taking the job with it.
+            return Ok(Outcome::Skipped);
+        };
+
+        if device.last_seen_at > now - self.online_window {
+            self.repo.push.delete_job(job.id)?;
+            return Ok(Outcome::Skipped);
+        }
+
+        match self.provider.wake(&endpoint) {
+            Ok(()) => {
+                self.repo.push.delete_job(job.id)?;
+                Ok(Outcome::Sent)
+            }
+            Err(PushProviderError::Gone) => {
+                tracing::info!(device_id = %job.device_id, "Push endpoint gone, removing it");
+                self.repo.push.delete_endpoint(job.device_id)?;
+                Ok(Outcome::Failed)
+            }
+            Err(PushProviderError::Unavailable(cause)) if job.attempts + 1 < self.max_attempts => {
+                let next_attempt_at = now + self.backoff.delay(job.attempts + 1);
+                self.repo
+                    .push
+                    .reschedule_job(job.id, next_attempt_at, &cause)?;
+                Ok(Outcome::Retried)
+            }
+            Err(e) => {
+                tracing::warn!(device_id = %job.device_id, error = %e, "Giving up on wake-up");
+                self.repo.push.dead_letter_job(job.id, &e.to_string())?;
+                Ok(Outcome::Failed)
+            }
+        }
+    }
+}
+
+fn ensure_own_device(repo: &Repository, user_id: Uuid, device_id: Uuid) -> Result<(), PushError> {
+    repo.devices
+        .find_device(device_id)?
+        .filter(|device| device.user_id == user_id)
+        .ok_or(PushError::UnknownDevice)?;
+
+    Ok(())
+}
+
+impl From<PushEndpoint> for PushEndpointInfo {
+    fn from(endpoint: PushEndpoint) -> Self {
+        Self {
+            device_id: endpoint.device_id,
+            kind: endpoint.kind,
+            url: endpoint.url,
+            created_at: endpoint.created_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+            updated_at: endpoint.updated_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+        }
+    }
+}
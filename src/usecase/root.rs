@@ -1,22 +1,56 @@
+use super::attachments::service::AttachmentService;
 use super::auth::service::AuthService;
 use super::chat::service::ChatService;
+use super::devices::service::DeviceService;
 use super::factory::Factory;
+use super::keys::service::KeyService;
+use super::push::service::PushService;
+use super::webhooks::service::WebhookService;
+use crate::config::attachments::AttachmentConfig;
 use crate::config::jwt::JwtConfig;
+use crate::config::keys::KeyDirectoryConfig;
+use crate::config::password::PasswordHashConfig;
+use crate::config::push::PushConfig;
+use crate::config::throttle::LoginThrottleConfig;
+use crate::config::webhooks::WebhookConfig;
 use crate::repository::Repository;
 
 pub struct Service {
     pub auth: AuthService,
     pub chat: ChatService,
+    pub devices: DeviceService,
+    pub keys: KeyService,
+    pub attachments: AttachmentService,
+    pub push: PushService,
+    pub webhooks: WebhookService,
+}
+
+/// Settings of the use-case services, one section of
+/// [`Config`](crate::config::Config) each.
+#[derive(Clone)]
+pub struct ServiceConfig {
+    pub jwt: JwtConfig,
+    pub login_throttle: LoginThrottleConfig,
+    pub password: PasswordHashConfig,
+    pub keys: KeyDirectoryConfig,
+    pub attachments: AttachmentConfig,
+    pub push: PushConfig,
+    pub webhooks: WebhookConfig,
 }
 
 impl Service {
-    pub fn new(repo: Repository, jwt_config: JwtConfig) -> Self {
-        let factory = Factory::new(repo, jwt_config);
+    pub fn new(repo: Repository, config: ServiceConfig) -> Result<Self, String> {
+        let factory = Factory::new(repo, config)?;
 
-        Self {
-            auth: factory.create_auth_service(),
+        Ok(Self {
+            auth: factory.create_auth_service()?,
             chat: factory.create_chat_service(),
-        }
+            devices: factory.create_device_service(),
+            keys: factory.create_key_service()?,
+            attachments: factory.create_attachment_service(),
+            push: factory.create_push_service()?,
+            webhooks: factory.create_webhook_service(),
+        })
     }
 }
 
@@ -25,6 +59,11 @@ impl Clone for Service {
         Self {
             auth: self.auth.clone(),
             chat: self.chat.clone(),
+            devices: self.devices.clone(),
+            keys: self.keys.clone(),
+            attachments: self.attachments.clone(),
+            push: self.push.clone(),
+            webhooks: self.webhooks.clone(),
         }
     }
 }
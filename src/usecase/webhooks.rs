@@ -0,0 +1,6 @@
+pub mod error;
+pub mod service;
+pub mod signature;
+
+pub use error::WebhookError;
+pub use service::{WebhookDeliveryInfo, WebhookDispatch, WebhookInfo, WebhookService};
@@ -0,0 +1,43 @@
+use thiserror::Error;
+
+use crate::repository::RepositoryError;
+
+#[derive(Debug, Error)]
+pub enum WebhookError {
+    #[error("Invalid webhook: {0}")]
+    InvalidWebhook(String),
+
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
+    #[error("Chat not found")]
+    ChatNotFound,
+
+    #[error("Webhook not found")]
+    WebhookNotFound,
+
+    #[error("Service temporarily unavailable")]
+    Unavailable,
+
+    #[error("Internal error: {0}")]
+    Internal(String),
+}
+
+impl From<tokio::task::JoinError> for WebhookError {
+    fn from(err: tokio::task::JoinError) -> Self {
+        Self::Internal(err.to_string())
+    }
+}
+
+impl From<RepositoryError> for WebhookError {
+    fn from(err: RepositoryError) -> Self {
+        match err {
+            RepositoryError::Unavailable(cause) => {
+                tracing::error!("Database unavailable: {}", cause);
+                Self::Unavailable
+            }
+            RepositoryError::NotFound => Self::WebhookNotFound,
+            err => Self::Internal(err.to_string()),
+        }
+    }
+}
@@ -0,0 +1,446 @@
+use std::sync::Arc;
+
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use chrono::{Duration, NaiveDateTime, Utc};
+use ring::rand::{SecureRandom, SystemRandom};
+use uuid::Uuid;
+
+use super::error::WebhookError;
+use super::signature;
+use crate::config::webhooks::WebhookConfig;
+use crate::repository::Repository;
+use crate::repository::webhook_sender::{self, WebhookSender};
+use crate::repository::webhooks::{
+    NewWebhook, Webhook, WebhookDelivery, WebhookDeliveryStatus, WebhookEvent, WebhookEventKind,
+};
+use crate::usecase::backoff::Backoff;
+use crate::usecase::blocking::run_blocking;
+
+/// How long a claimed delivery is left to one dispatcher before others may
+/// pick it up again.
+const DELIVERY_LEASE_SECONDS: i64 = 300;
+const MAX_DELIVERIES_PER_PAGE: i64 = 200;
+const SECRET_BYTES: usize = 32;
+
+/// Outgoing webhooks for chat events. Chat changes write events to an
+/// outbox in their own transaction; the dispatcher fans each event out to
+/// the webhooks subscribed to it and POSTs it, signed with the webhook's
+/// secret, retrying with exponential backoff. Every delivery is kept as the
+/// webhook's delivery log.
+#[derive(Clone)]
+pub struct WebhookService {
+    repo: Repository,
+    sender: Arc<dyn WebhookSender>,
+    max_attempts: i32,
+    backoff: Backoff,
+}
+
+pub struct WebhookInfo {
+    pub id: Uuid,
+    pub created_by: Uuid,
+    /// `None` for webhooks that receive the events of every chat.
+    pub chat_id: Option<Uuid>,
+    pub url: String,
+    pub events: Vec<String>,
+    /// Only returned when the webhook is created.
+    pub secret: Option<String>,
+    pub created_at: String,
+}
+
+pub struct WebhookDeliveryInfo {
+    pub id: Uuid,
+    pub event_id: Uuid,
+    pub event: String,
+    pub chat_id: Uuid,
+    pub payload: serde_json::Value,
+    pub status: String,
+    pub attempts: i32,
+    /// When the next attempt is due, while the delivery is pending.
+    pub next_attempt_at: Option<String>,
+    pub response_status: Option<i32>,
+    pub last_error: Option<String>,
+    pub created_at: String,
+    pub completed_at: Option<String>,
+}
+
+/// What one delivery run did.
+#[derive(Debug, Default)]
+pub struct WebhookDispatch {
+    pub claimed: usize,
+    pub delivered: usize,
+    pub retried: usize,
+    /// Out of attempts.
+    pub failed: usize,
+}
+
+enum Outcome {
+    Delivered,
+    Retried,
+    Failed,
+    /// The webhook was removed since the claim.
+    Gone,
+}
+
+impl WebhookService {
+    pub fn new(repo: Repository, config: &WebhookConfig) -> Self {
+        Self::with_sender(repo, config, webhook_sender::open(config))
+    }
+
+    pub fn with_sender(
+        repo: Repository,
+        config: &WebhookConfig,
+        sender: Arc<dyn WebhookSender>,
+    ) -> Self {
+        Self {
+            repo,
+            sender,
+            max_attempts: config.max_attempts,
+            backoff: Backoff::new(config.retry_base_seconds),
+        }
+    }
+
+    /// Subscribes `url` to `events` of one chat, or of every chat when
+    /// `chat_id` is `None`. Chat webhooks can be set up by the chat's
+    /// creator; holders of `webhooks.manage` can set up any webhook. The
+    /// returned secret is not shown again.
+    #[tracing::instrument(skip(self, url))]
+    pub async fn create_webhook(
+        &self,
+        actor_id: Uuid,
+        actor_can_manage: bool,
+        chat_id: Option<Uuid>,
+        url: String,
+        events: Vec<String>,
+    ) -> Result<WebhookInfo, WebhookError> {
+        let events = check_events(&events)?;
+        self.sender
+            .check_url(&url)
+            .map_err(WebhookError::InvalidWebhook)?;
+        let secret = generate_secret()?;
+
+        let this = self.clone();
+        run_blocking(move || {
+            match chat_id {
+                Some(chat_id) => {
+                    let chat = this
+                        .repo
+                        .chat
+                        .find_chat_by_id(chat_id)?
+                        .ok_or(WebhookError::ChatNotFound)?;
+
+                    if chat.created_by != actor_id && !actor_can_manage {
+                        return Err(WebhookError::Forbidden(
+                            "Only the chat creator can add webhooks to the chat".to_string(),
+                        ));
+                    }
+                }
+                None if !actor_can_manage => {
+                    return Err(WebhookError::Forbidden(
+                        "Webhooks for every chat need the webhooks.manage permission".to_string(),
+                    ));
+                }
+                None => {}
+            }
+
+            let webhook = this.repo.webhooks.create_webhook(NewWebhook {
+                created_by: actor_id,
+                chat_id,
+                url,
+                secret,
+                events,
+            })?;
+
+            Ok(WebhookInfo {
+                secret: Some(webhook.secret.clone()),
+                ..WebhookInfo::from(webhook)
+            })
+        })
+        .await
+    }
+
+    /// Webhooks the caller set up.
+    pub async fn list_webhooks(&self, actor_id: Uuid) -> Result<Vec<WebhookInfo>, WebhookError> {
+        let this = self.clone();
+        run_blocking(move || {
+            let webhooks = this.repo.webhooks.list_webhooks(actor_id)?;
+
+            Ok(webhooks.into_iter().map(WebhookInfo::from).collect())
+        })
+        .await
+    }
+
+    pub async fn get_webhook(
+        &self,
+        actor_id: Uuid,
+        actor_can_manage: bool,
+        webhook_id: Uuid,
+    ) -> Result<WebhookInfo, WebhookError> {
+        let this = self.clone();
+        run_blocking(move || {
+            let webhook = find_visible(&this.repo, actor_id, actor_can_manage, webhook_id)?;
+
+            Ok(WebhookInfo::from(webhook))
+        })
+        .await
+    }
+
+    /// Unsubscribes the webhook. Deliveries still pending are dropped.
+    #[tracing::instrument(skip(self))]
+    pub async fn delete_webhook(
+        &self,
+        actor_id: Uuid,
+        actor_can_manage: bool,
+        webhook_id: Uuid,
+    ) -> Result<(), WebhookError> {
+        let this = self.clone();
+        run_blocking(move || {
+            find_visible(&this.repo, actor_id, actor_can_manage, webhook_id)?;
+
+            this.repo.webhooks.delete_webhook(webhook_id)?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    /// The webhook's deliveries, most recent first.
+    pub async fn list_deliveries(
+        &self,
+        actor_id: Uuid,
+        actor_can_manage: bool,
+        webhook_id: Uuid,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<WebhookDeliveryInfo>, WebhookError> {
+        let this = self.clone();
+        run_blocking(move || {
+            find_visible(&this.repo, actor_id, actor_can_manage, webhook_id)?;
+
+            let deliveries = this.repo.webhooks.list_deliveries(
+                webhook_id,
+                limit.clamp(1, MAX_DELIVERIES_PER_PAGE),
+                offset.max(0),
+            )?;
+
+            Ok(deliveries
+                .into_iter()
+                .map(WebhookDeliveryInfo::from)
+                .collect())
+        })
+        .await
+    }
+
+    /// Fans up to `batch_size` outbox events out to their webhooks. Returns
+    /// how many events were taken.
+    pub async fn dispatch_events(&self, batch_size: i64) -> Result<usize, WebhookError> {
+        let this = self.clone();
+        run_blocking(move || Ok(this.repo.webhooks.dispatch_events(batch_size)?)).await
+    }
+
+    /// Attempts up to `batch_size` due deliveries. Failed attempts, whether
+    /// the endpoint could not be reached or answered with anything but 2xx,
+    /// are retried with exponential backoff until the attempts run out.
+    ///
+    /// Claimed deliveries are leased, so concurrent dispatchers never send
+    /// the same one.
+    pub async fn deliver_due(&self, batch_size: i64) -> Result<WebhookDispatch, WebhookError> {
+        let this = self.clone();
+        run_blocking(move || {
+            let now = Utc::now().naive_utc();
+            let deliveries = this.repo.webhooks.claim_due_deliveries(
+                now,
+                now + Duration::seconds(DELIVERY_LEASE_SECONDS),
+                batch_size,
+            )?;
+
+            let mut dispatch = WebhookDispatch {
+                claimed: deliveries.len(),
+                ..WebhookDispatch::default()
+            };
+
+            for delivery in deliveries {
+                match this.deliver(delivery, now)? {
+                    Outcome::Delivered => dispatch.delivered += 1,
+                    Outcome::Retried => dispatch.retried += 1,
+                    Outcome::Failed => dispatch.failed += 1,
+                    Outcome::Gone => {}
+                }
+            }
+
+            Ok(dispatch)
+        })
+        .await
+    }
+
+    fn deliver(
+        &self,
+        delivery: WebhookDelivery,
+        now: NaiveDateTime,
+    ) -> Result<Outcome, WebhookError> {
+        let webhook = self.repo.webhooks.find_webhook(delivery.webhook_id)?;
+        let event = self.repo.webhooks.find_event(delivery.event_id)?;
+
+        let (Some(webhook), Some(event)) = (webhook, event) else {
+            return Ok(Outcome::Gone);
+        };
+
+        let body = serde_json::json!({
+            "id": event.id,
+            "type": event.kind,
+            "chat_id": event.chat_id,
+            "created_at": format_time(event.created_at),
+            "data": parse_payload(&event.payload),
+        })
+        .to_string();
+        let timestamp = Utc::now().timestamp();
+        let headers = [
+            ("x-webhook-id", delivery.id.to_string()),
+            ("x-webhook-event", event.kind.clone()),
+            ("x-webhook-timestamp", timestamp.to_string()),
+            (
+                "x-webhook-signature",
+                signature::sign(&webhook.secret, timestamp, &body),
+            ),
+        ];
+
+        let (response_status, error) = match self.sender.post(&webhook.url, &headers, body) {
+            Ok(response) if (200..300).contains(&response.status) => {
+                self.repo.webhooks.complete_delivery(
+                    delivery.id,
+                    WebhookDeliveryStatus::Delivered,
+                    Some(response.status.into()),
+                    None,
+                )?;
+                return Ok(Outcome::Delivered);
+            }
+            Ok(response) if response.excerpt.is_empty() => (
+                Some(i32::from(response.status)),
+                format!("Endpoint returned {}", response.status),
+            ),
+            Ok(response) => (
+                Some(i32::from(response.status)),
+                format!(
+                    "Endpoint returned {}: {}",
+                    response.status, response.excerpt
+                ),
+            ),
+            Err(cause) => (None, cause),
+        };
+
+        if delivery.attempts + 1 < self.max_attempts {
+            let next_attempt_at = now + self.backoff.delay(delivery.attempts + 1);
+            self.repo.webhooks.reschedule_delivery(
+                delivery.id,
+                next_attempt_at,
+                response_status,
+                &error,
+            )?;
+            return Ok(Outcome::Retried);
+        }
+
+        tracing::warn!(
+            webhook_id = %webhook.id,
+            delivery_id = %delivery.id,
+            error = %error,
+            "Giving up on webhook delivery"
+        );
+        self.repo.webhooks.complete_delivery(
+            delivery.id,
+            WebhookDeliveryStatus::Failed,
+            response_status,
+            Some(&error),
+        )?;
+
+        Ok(Outcome::Failed)
+    }
+}
+
+/// The webhook, if the caller set it up or may manage every webhook. Others
+/// get `WebhookNotFound`, so ids of other users' webhooks are not revealed.
+fn find_visible(
+    repo: &Repository,
+    actor_id: Uuid,
+    actor_can_manage: bool,
+    webhook_id: Uuid,
+) -> Result<Webhook, WebhookError> {
+    repo.webhooks
+        .find_webhook(webhook_id)?
+        .filter(|webhook| webhook.created_by == actor_id || actor_can_manage)
+        .ok_or(WebhookError::WebhookNotFound)
+}
+
+/// The event names, deduplicated in a fixed order.
+fn check_events(events: &[String]) -> Result<Vec<String>, WebhookError> {
+    if events.is_empty() {
+        return Err(WebhookError::InvalidWebhook(
+            "Subscribe to at least one event".to_string(),
+        ));
+    }
+
+    if let Some(unknown) = events.iter().find(|e| WebhookEventKind::parse(e).is_none()) {
+        return Err(WebhookError::InvalidWebhook(format!(
+            "Unknown event {}",
+            unknown
+        )));
+    }
+
+    Ok(WebhookEventKind::ALL
+        .into_iter()
+        .map(|kind| kind.as_str())
+        .filter(|kind| events.iter().any(|e| e == kind))
+        .map(str::to_string)
+        .collect())
+}
+
+fn generate_secret() -> Result<String, WebhookError> {
+    let mut secret = [0u8; SECRET_BYTES];
+    SystemRandom::new()
+        .fill(&mut secret)
+        .map_err(|_| WebhookError::Internal("Failed to generate a secret".to_string()))?;
+
+    Ok(URL_SAFE_NO_PAD.encode(secret))
+}
+
+fn parse_payload(payload: &str) -> serde_json::Value {
+    serde_json::from_str(payload).unwrap_or(serde_json::Value::Null)
+}
+
+fn format_time(time: NaiveDateTime) -> String {
+    time.format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+impl From<Webhook> for WebhookInfo {
+    fn from(webhook: Webhook) -> Self {
+        Self {
+            id: webhook.id,
+            created_by: webhook.created_by,
+            chat_id: webhook.chat_id,
+            url: webhook.url,
+            events: webhook.events,
+            secret: None,
+            created_at: format_time(webhook.created_at),
+        }
+    }
+}
+
+impl From<(WebhookDelivery, WebhookEvent)> for WebhookDeliveryInfo {
+    fn from((delivery, event): (WebhookDelivery, WebhookEvent)) -> Self {
+        let pending = delivery.status == WebhookDeliveryStatus::Pending.as_str();
+
+        Self {
+            id: delivery.id,
+            event_id: event.id,
+            event: event.kind,
+            chat_id: event.chat_id,
+            payload: parse_payload(&event.payload),
+            status: delivery.status,
+            attempts: delivery.attempts,
+            next_attempt_at: pending.then(|| format_time(delivery.next_attempt_at)),
+            response_status: delivery.response_status,
+            last_error: delivery.last_error,
+            created_at: format_time(delivery.created_at),
+            completed_at: delivery.completed_at.map(format_time),
+        }
+    }
+}
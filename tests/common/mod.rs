@@ -0,0 +1,325 @@
+//! Shared setup for the database-free test suites.
+
+#![allow(dead_code)]
+
+pub mod recording;
+
+use std::path::Path;
+
+use axum::Router;
+use axum::body::{Body, to_bytes};
+use axum::http::{HeaderName, Request, StatusCode, header};
+use axum::response::Response;
+use ring::rand::SystemRandom;
+use ring::signature::{ECDSA_P256_SHA256_FIXED_SIGNING, EcdsaKeyPair};
+use serde_json::Value;
+use tower::ServiceExt;
+
+use msg_service::api::http::router::create_router;
+use msg_service::api::http::state::AppState;
+use msg_service::config::attachments::{AttachmentConfig, BlobStorageConfig};
+use msg_service::config::jwt::{JwtAlgorithm, JwtConfig};
+use msg_service::config::keys::KeyDirectoryConfig;
+use msg_service::config::password::PasswordHashConfig;
+use msg_service::config::push::{PushConfig, VapidConfig};
+use msg_service::config::throttle::LoginThrottleConfig;
+use msg_service::config::webhooks::WebhookConfig;
+use msg_service::repository::Repository;
+use msg_service::usecase::{Service, ServiceConfig};
+
+pub const PASSWORD: &str = "Password123";
+pub const ADMIN_USERNAME: &str = "admin";
+pub const ADMIN_PASSWORD: &str = "AdminPass123";
+
+pub fn jwt_config() -> JwtConfig {
+    JwtConfig {
+        algorithm: JwtAlgorithm::Hs256,
+        secret: Some("test-secret".to_string()),
+        signing_key: None,
+        retired_keys: Vec::new(),
+        legacy_hs256_until: None,
+        expiration_hours: 1,
+    }
+}
+
+pub fn throttle_config() -> LoginThrottleConfig {
+    LoginThrottleConfig {
+        max_failures_per_username: 5,
+        max_failures_per_ip: 20,
+        failure_window_seconds: 900,
+        lockout_base_seconds: 30,
+        lockout_max_seconds: 3600,
+    }
+}
+
+/// Deliberately weak Argon2 parameters to keep the suites fast.
+pub fn password_config() -> PasswordHashConfig {
+    PasswordHashConfig {
+        argon2_memory_kib: 64,
+        argon2_iterations: 1,
+        argon2_parallelism: 1,
+    }
+}
+
+pub fn key_config() -> KeyDirectoryConfig {
+    KeyDirectoryConfig {
+        prekey_low_watermark: 2,
+        max_prekeys_per_upload: 5,
+        max_stored_prekeys: 8,
+        log_signing_key_pem: None,
+    }
+}
+
+/// Small limits so tests can exercise them with a few bytes.
+pub fn attachment_config() -> AttachmentConfig {
+    AttachmentConfig {
+        storage: BlobStorageConfig::Memory,
+        max_size: 64,
+        max_chunk_size: 16,
+        user_quota: 100,
+        stale_upload_seconds: 1,
+        sweep_interval_seconds: 1,
+    }
+}
+
+/// Immediate retries and no online window, so every device gets wake-ups
+/// and failures can be replayed without waiting. Web Push is signed with a
+/// fresh key, and endpoints may be local so tests can point them at a mock.
+pub fn push_config() -> PushConfig {
+    PushConfig {
+        interval_seconds: 1,
+        batch_size: 10,
+        max_attempts: 3,
+        retry_base_seconds: 0,
+        online_window_seconds: 0,
+        vapid: Some(VapidConfig {
+            private_key_pem: vapid_key_pem(),
+            subject: "mailto:ops@example.com".to_string(),
+        }),
+        allow_private_endpoints: true,
+    }
+}
+
+/// Immediate retries, so failed deliveries can be replayed without waiting,
+/// and local URLs so tests can point webhooks at a mock.
+pub fn webhook_config() -> WebhookConfig {
+    WebhookConfig {
+        interval_seconds: 1,
+        batch_size: 10,
+        max_attempts: 3,
+        retry_base_seconds: 0,
+        allow_private_urls: true,
+    }
+}
+
+pub fn vapid_key_pem() -> String {
+    let pkcs8 =
+        EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &SystemRandom::new())
+            .expect("generate VAPID key");
+    pem::encode(&pem::Pem::new("PRIVATE KEY", pkcs8.as_ref().to_vec()))
+}
+
+pub fn service_config() -> ServiceConfig {
+    ServiceConfig {
+        jwt: jwt_config(),
+        login_throttle: throttle_config(),
+        password: password_config(),
+        keys: key_config(),
+        attachments: attachment_config(),
+        push: push_config(),
+        webhooks: webhook_config(),
+    }
+}
+
+pub fn service_with(repo: Repository) -> Service {
+    Service::new(repo, service_config()).expect("use-case layer")
+}
+
+/// Use-case layer over a fresh in-memory store with the admin bootstrapped.
+pub async fn service() -> Service {
+    bootstrapped(service_with(Repository::in_memory())).await
+}
+
+/// Like [`service`], but attachment blobs are files under `root`, so tests
+/// can see which ones are left behind.
+pub async fn service_with_blobs_in(root: &Path) -> Service {
+    let config = ServiceConfig {
+        attachments: AttachmentConfig {
+            storage: BlobStorageConfig::Filesystem {
+                root: root.to_path_buf(),
+            },
+            ..attachment_config()
+        },
+        ..service_config()
+    };
+
+    bootstrapped(Service::new(Repository::in_memory(), config).expect("use-case layer")).await
+}
+
+async fn bootstrapped(service: Service) -> Service {
+    service
+        .auth
+        .ensure_admin(ADMIN_USERNAME.to_string(), ADMIN_PASSWORD.to_string())
+        .await
+        .expect("bootstrap admin");
+    service
+}
+
+pub async fn router() -> Router {
+    create_router(AppState::new(service().await))
+}
+
+/// A router behind a proxy that reports clients in `X-Forwarded-For`.
+pub async fn proxied_router() -> Router {
+    let state = AppState::new(service().await)
+        .with_client_ip_header(HeaderName::from_static("x-forwarded-for"));
+
+    create_router(state)
+}
+
+pub async fn send(
+    router: &Router,
+    method: &str,
+    uri: &str,
+    token: Option<&str>,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    into_json(send_raw(router, method, uri, token, body).await).await
+}
+
+/// Like [`send`], naming the caller's device in `X-Device-Id`.
+pub async fn send_as_device(
+    router: &Router,
+    method: &str,
+    uri: &str,
+    token: &str,
+    device_id: &str,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    let request = request(method, uri, Some(token), Some(device_id), body);
+
+    into_json(router.clone().oneshot(request).await.unwrap()).await
+}
+
+pub async fn send_raw(
+    router: &Router,
+    method: &str,
+    uri: &str,
+    token: Option<&str>,
+    body: Option<Value>,
+) -> Response {
+    let request = request(method, uri, token, None, body);
+
+    router.clone().oneshot(request).await.unwrap()
+}
+
+/// Sends a raw `application/octet-stream` body with extra headers.
+pub async fn send_bytes(
+    router: &Router,
+    method: &str,
+    uri: &str,
+    token: &str,
+    headers: &[(&str, String)],
+    body: Vec<u8>,
+) -> Response {
+    let mut request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .header(header::CONTENT_TYPE, "application/octet-stream");
+
+    for (name, value) in headers {
+        request = request.header(*name, value);
+    }
+
+    let request = request.body(Body::from(body)).unwrap();
+
+    router.clone().oneshot(request).await.unwrap()
+}
+
+fn request(
+    method: &str,
+    uri: &str,
+    token: Option<&str>,
+    device_id: Option<&str>,
+    body: Option<Value>,
+) -> Request<Body> {
+    let mut request = Request::builder().method(method).uri(uri);
+
+    if let Some(token) = token {
+        request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+    }
+
+    if let Some(device_id) = device_id {
+        request = request.header("x-device-id", device_id);
+    }
+
+    let request = match body {
+        Some(body) => request
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string())),
+        None => request.body(Body::empty()),
+    };
+
+    request.unwrap()
+}
+
+pub async fn into_json(response: Response) -> (StatusCode, Value) {
+    let status = response.status();
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let json = if bytes.is_empty() {
+        Value::Null
+    } else {
+        serde_json::from_slice(&bytes).unwrap_or(Value::Null)
+    };
+
+    (status, json)
+}
+
+/// Registers (when needed) and logs in over HTTP, returning the token.
+pub async fn register_and_login(router: &Router, username: &str) -> String {
+    let credentials = serde_json::json!({ "username": username, "password": PASSWORD });
+    send(
+        router,
+        "POST",
+        "/auth/register",
+        None,
+        Some(credentials.clone()),
+    )
+    .await;
+    login(router, username, PASSWORD).await
+}
+
+pub async fn login(router: &Router, username: &str, password: &str) -> String {
+    let (status, body) = send(
+        router,
+        "POST",
+        "/auth/login",
+        None,
+        Some(serde_json::json!({ "username": username, "password": password })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "login failed: {}", body);
+
+    body["token"].as_str().unwrap().to_string()
+}
+
+/// Registers a device for the token's account, returning its id.
+pub async fn register_device(router: &Router, token: &str, name: &str) -> String {
+    let (status, body) = send(
+        router,
+        "POST",
+        "/devices",
+        Some(token),
+        Some(serde_json::json!({ "name": name })),
+    )
+    .await;
+    assert_eq!(
+        status,
+        StatusCode::CREATED,
+        "device registration failed: {}",
+        body
+    );
+
+    body["id"].as_str().unwrap().to_string()
+}
@@ -0,0 +1,78 @@
+//! An in-process HTTP endpoint that records every request it gets and
+//! answers with a status the test picks.
+
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::sync::{Arc, Mutex};
+
+use axum::Router;
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode, Uri};
+use serde_json::Value;
+
+pub struct Delivery {
+    pub path: String,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+impl Delivery {
+    pub fn header(&self, name: &str) -> &str {
+        self.headers[name].to_str().unwrap()
+    }
+
+    pub fn json(&self) -> Value {
+        serde_json::from_slice(&self.body).unwrap()
+    }
+}
+
+pub struct Endpoint {
+    deliveries: Mutex<Vec<Delivery>>,
+    status: AtomicU16,
+}
+
+impl Endpoint {
+    /// Answers every further request with `status`; error statuses come
+    /// with a long body that spans several lines.
+    pub fn answer(&self, status: StatusCode) {
+        self.status.store(status.as_u16(), Ordering::SeqCst);
+    }
+
+    pub fn take(&self) -> Vec<Delivery> {
+        std::mem::take(&mut self.deliveries.lock().unwrap())
+    }
+}
+
+async fn record(
+    State(endpoint): State<Arc<Endpoint>>,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Bytes,
+) -> (StatusCode, String) {
+    endpoint.deliveries.lock().unwrap().push(Delivery {
+        path: uri.path().to_string(),
+        headers,
+        body,
+    });
+
+    let status = StatusCode::from_u16(endpoint.status.load(Ordering::SeqCst)).unwrap();
+    match status.is_success() {
+        true => (status, String::new()),
+        false => (status, format!("receiver is down\r\n{}", "x".repeat(1000))),
+    }
+}
+
+/// Starts an endpoint that answers `status` until told otherwise, and
+/// returns it with its base URL.
+pub async fn start(status: StatusCode) -> (Arc<Endpoint>, String) {
+    let endpoint = Arc::new(Endpoint {
+        deliveries: Mutex::default(),
+        status: AtomicU16::new(status.as_u16()),
+    });
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let app = Router::new().fallback(record).with_state(endpoint.clone());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    (endpoint, base)
+}
@@ -0,0 +1,220 @@
+//! Token signing with asymmetric keys, key rotation and the JWKS. The RSA
+//! key is a fixture, since generating one takes a while; Ed25519 keys are
+//! fresh for every test.
+
+mod common;
+
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use chrono::{Duration, Utc};
+use jsonwebtoken::jwk::AlgorithmParameters;
+use jsonwebtoken::{Algorithm, decode_header};
+use ring::rand::SystemRandom;
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use uuid::Uuid;
+
+use msg_service::config::jwt::{JwtAlgorithm, JwtConfig, JwtSigningKey, JwtVerificationKey};
+use msg_service::usecase::auth::error::AuthError;
+use msg_service::usecase::auth::jwt::JwtService;
+
+/// DER prefix of an Ed25519 SubjectPublicKeyInfo.
+const ED25519_SPKI_PREFIX: [u8; 12] = [
+    0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00,
+];
+
+fn rsa_key(kid: &str) -> JwtSigningKey {
+    JwtSigningKey {
+        kid: kid.to_string(),
+        private_pem: include_str!("fixtures/jwt-rs256.pem").to_string(),
+        public_pem: include_str!("fixtures/jwt-rs256.pub.pem").to_string(),
+    }
+}
+
+fn ed25519_key(kid: &str) -> JwtSigningKey {
+    let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).expect("generate key");
+    let pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+    let mut spki = ED25519_SPKI_PREFIX.to_vec();
+    spki.extend_from_slice(pair.public_key().as_ref());
+
+    JwtSigningKey {
+        kid: kid.to_string(),
+        private_pem: pem::encode(&pem::Pem::new("PRIVATE KEY", pkcs8.as_ref().to_vec())),
+        public_pem: pem::encode(&pem::Pem::new("PUBLIC KEY", spki)),
+    }
+}
+
+fn retired(key: &JwtSigningKey) -> JwtVerificationKey {
+    JwtVerificationKey {
+        kid: key.kid.clone(),
+        public_pem: key.public_pem.clone(),
+    }
+}
+
+/// Signs with `signing_key` and no shared secret.
+fn service(
+    algorithm: JwtAlgorithm,
+    signing_key: &JwtSigningKey,
+    retired_keys: Vec<JwtVerificationKey>,
+) -> JwtService {
+    JwtService::new(&JwtConfig {
+        algorithm,
+        secret: None,
+        signing_key: Some(signing_key.clone()),
+        retired_keys,
+        ..common::jwt_config()
+    })
+    .expect("jwt service")
+}
+
+fn issue(jwt: &JwtService) -> (Uuid, String) {
+    let user_id = Uuid::new_v4();
+    let token = jwt.generate_token(user_id, 1, 3).unwrap();
+
+    (user_id, token)
+}
+
+#[test]
+fn rs256_tokens_round_trip_and_publish_the_modulus() {
+    let jwt = service(JwtAlgorithm::Rs256, &rsa_key("rsa-1"), Vec::new());
+    let (user_id, token) = issue(&jwt);
+
+    let header = decode_header(&token).unwrap();
+    assert_eq!(header.alg, Algorithm::RS256);
+    assert_eq!(header.kid.as_deref(), Some("rsa-1"));
+
+    let claims = jwt.validate_token(&token).unwrap();
+    assert_eq!(claims.user_id, user_id.to_string());
+    assert_eq!((claims.role_id, claims.ver), (1, 3));
+
+    let keys = &jwt.jwks().keys;
+    assert_eq!(keys.len(), 1);
+    assert_eq!(keys[0].common.key_id.as_deref(), Some("rsa-1"));
+    let AlgorithmParameters::RSA(rsa) = &keys[0].algorithm else {
+        panic!("expected an RSA key");
+    };
+    assert_eq!(URL_SAFE_NO_PAD.decode(&rsa.n).unwrap().len(), 256);
+    assert_eq!(rsa.e, "AQAB");
+}
+
+#[test]
+fn eddsa_tokens_round_trip_and_publish_the_public_key() {
+    let key = ed25519_key("ed-1");
+    let jwt = service(JwtAlgorithm::EdDsa, &key, Vec::new());
+    let (user_id, token) = issue(&jwt);
+
+    let header = decode_header(&token).unwrap();
+    assert_eq!(header.alg, Algorithm::EdDSA);
+    assert_eq!(header.kid.as_deref(), Some("ed-1"));
+    assert_eq!(
+        jwt.validate_token(&token).unwrap().user_id,
+        user_id.to_string()
+    );
+
+    let keys = &jwt.jwks().keys;
+    assert_eq!(keys.len(), 1);
+    let AlgorithmParameters::OctetKeyPair(okp) = &keys[0].algorithm else {
+        panic!("expected an Ed25519 key");
+    };
+    let der = pem::parse(&key.public_pem).unwrap();
+    assert_eq!(
+        URL_SAFE_NO_PAD.decode(&okp.x).unwrap(),
+        der.contents()[ED25519_SPKI_PREFIX.len()..]
+    );
+}
+
+#[test]
+fn retired_keys_keep_verifying_after_a_rotation() {
+    let old = ed25519_key("2026-04");
+    let new = rsa_key("2026-10");
+    let (user_id, token) = issue(&service(JwtAlgorithm::EdDsa, &old, Vec::new()));
+
+    let rotated = service(JwtAlgorithm::Rs256, &new, vec![retired(&old)]);
+    assert_eq!(
+        rotated.validate_token(&token).unwrap().user_id,
+        user_id.to_string()
+    );
+    let kids: Vec<_> = rotated
+        .jwks()
+        .keys
+        .iter()
+        .map(|key| key.common.key_id.as_deref().unwrap())
+        .collect();
+    assert_eq!(kids, ["2026-10", "2026-04"]);
+
+    // New tokens are signed with the new key only.
+    let (_, fresh) = issue(&rotated);
+    assert_eq!(
+        decode_header(&fresh).unwrap().kid.as_deref(),
+        Some("2026-10")
+    );
+
+    // Once the old key is dropped, so are its tokens.
+    let dropped = service(JwtAlgorithm::Rs256, &new, Vec::new());
+    assert!(matches!(
+        dropped.validate_token(&token),
+        Err(AuthError::TokenValidationFailed(_))
+    ));
+}
+
+#[test]
+fn unknown_key_ids_are_rejected() {
+    let (_, token) = issue(&service(
+        JwtAlgorithm::EdDsa,
+        &ed25519_key("ed-1"),
+        Vec::new(),
+    ));
+
+    let other = service(JwtAlgorithm::EdDsa, &ed25519_key("ed-2"), Vec::new());
+    let result = other.validate_token(&token);
+    assert!(
+        matches!(&result, Err(AuthError::TokenValidationFailed(e)) if e.contains("Unknown key id"))
+    );
+
+    // A key that reuses the kid does not verify the signature either.
+    let impostor = service(JwtAlgorithm::EdDsa, &ed25519_key("ed-1"), Vec::new());
+    assert!(impostor.validate_token(&token).is_err());
+}
+
+#[test]
+fn tokens_without_a_key_id_need_the_shared_secret() {
+    let hs256 = JwtService::new(&common::jwt_config()).unwrap();
+    let (_, token) = issue(&hs256);
+    assert!(decode_header(&token).unwrap().kid.is_none());
+    assert!(hs256.validate_token(&token).is_ok());
+    assert!(hs256.jwks().keys.is_empty());
+
+    let jwt = service(JwtAlgorithm::EdDsa, &ed25519_key("ed-1"), Vec::new());
+    let result = jwt.validate_token(&token);
+    assert!(
+        matches!(&result, Err(AuthError::TokenValidationFailed(e)) if e.contains("Missing key id"))
+    );
+}
+
+#[test]
+fn legacy_hs256_tokens_are_accepted_only_until_the_deadline() {
+    let (user_id, token) = issue(&JwtService::new(&common::jwt_config()).unwrap());
+    let key = ed25519_key("ed-1");
+    let moved = |until| {
+        JwtService::new(&JwtConfig {
+            algorithm: JwtAlgorithm::EdDsa,
+            signing_key: Some(key.clone()),
+            legacy_hs256_until: until,
+            ..common::jwt_config()
+        })
+        .unwrap()
+    };
+
+    let open = moved(Some(Utc::now() + Duration::hours(1)));
+    assert_eq!(
+        open.validate_token(&token).unwrap().user_id,
+        user_id.to_string()
+    );
+
+    // A secret left in the config without a deadline is not a way in.
+    for closed in [moved(Some(Utc::now() - Duration::seconds(1))), moved(None)] {
+        assert!(matches!(
+            closed.validate_token(&token),
+            Err(AuthError::TokenValidationFailed(_))
+        ));
+    }
+}
@@ -0,0 +1,168 @@
+//! Checks that password hashing and database work stay off the async workers:
+//! latency of an unrelated endpoint must not grow while logins are hammered.
+//!
+//! Needs a PostgreSQL database configured through the usual `POSTGRES_*`
+//! variables, so it is ignored by default:
+//!
+//! ```sh
+//! cargo test --release --test load -- --ignored --nocapture
+//! ```
+
+use std::time::{Duration, Instant};
+
+use axum::Router;
+use axum::body::Body;
+use axum::http::{Request, StatusCode, header};
+use tower::ServiceExt;
+
+use msg_service::api::http::router::create_router;
+use msg_service::api::http::state::AppState;
+use msg_service::bootstrap::Postgres;
+use msg_service::config::attachments::AttachmentConfig;
+use msg_service::config::jwt::{JwtAlgorithm, JwtConfig};
+use msg_service::config::keys::KeyDirectoryConfig;
+use msg_service::config::password::PasswordHashConfig;
+use msg_service::config::postgres::PostgresConfig;
+use msg_service::config::push::PushConfig;
+use msg_service::config::throttle::LoginThrottleConfig;
+use msg_service::config::webhooks::WebhookConfig;
+use msg_service::repository::Repository;
+use msg_service::usecase::{Service, ServiceConfig};
+
+const LOGIN_WORKERS: usize = 32;
+const HEALTH_SAMPLES: usize = 200;
+const MAX_LOADED_P99: Duration = Duration::from_millis(50);
+
+fn build_router() -> Router {
+    dotenv::dotenv().ok();
+
+    let postgres = Postgres::new(&PostgresConfig::new().expect("postgres config"))
+        .expect("postgres connection");
+    let repo = Repository::new(postgres);
+
+    let jwt = JwtConfig {
+        algorithm: JwtAlgorithm::Hs256,
+        secret: Some("load-test-secret".to_string()),
+        signing_key: None,
+        retired_keys: Vec::new(),
+        legacy_hs256_until: None,
+        expiration_hours: 1,
+    };
+    let throttle = LoginThrottleConfig {
+        max_failures_per_username: u32::MAX,
+        max_failures_per_ip: u32::MAX,
+        failure_window_seconds: 1,
+        lockout_base_seconds: 0,
+        lockout_max_seconds: 0,
+    };
+    let config = ServiceConfig {
+        jwt,
+        login_throttle: throttle,
+        password: PasswordHashConfig::new().expect("password hash config"),
+        keys: KeyDirectoryConfig::new().expect("key directory config"),
+        attachments: AttachmentConfig::new().expect("attachment config"),
+        push: PushConfig::new().expect("push config"),
+        webhooks: WebhookConfig::new().expect("webhook config"),
+    };
+
+    let uc = Service::new(repo, config).expect("use-case layer");
+    create_router(AppState::new(uc))
+}
+
+fn json_request(uri: &str, body: String) -> Request<Body> {
+    Request::post(uri)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body))
+        .unwrap()
+}
+
+async fn health_latencies(router: &Router) -> Vec<Duration> {
+    let mut samples = Vec::with_capacity(HEALTH_SAMPLES);
+
+    for _ in 0..HEALTH_SAMPLES {
+        let started = Instant::now();
+        let response = router
+            .clone()
+            .oneshot(Request::get("/health").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        samples.push(started.elapsed());
+
+        assert_eq!(response.status(), StatusCode::OK);
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+
+    samples.sort();
+    samples
+}
+
+fn percentile(sorted: &[Duration], p: usize) -> Duration {
+    sorted[(sorted.len() * p / 100).min(sorted.len() - 1)]
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+#[ignore = "requires PostgreSQL"]
+async fn health_latency_stays_flat_under_login_load() {
+    let router = build_router();
+
+    let username = format!("load_{}", &uuid::Uuid::new_v4().simple().to_string()[..12]);
+    let credentials = format!(r#"{{"username":"{username}","password":"LoadTest123"}}"#);
+
+    let response = router
+        .clone()
+        .oneshot(json_request("/auth/register", credentials.clone()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let idle = health_latencies(&router).await;
+
+    let stop = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let workers: Vec<_> = (0..LOGIN_WORKERS)
+        .map(|_| {
+            let router = router.clone();
+            let credentials = credentials.clone();
+            let stop = stop.clone();
+            tokio::spawn(async move {
+                let mut logins = 0usize;
+                while !stop.load(std::sync::atomic::Ordering::Relaxed) {
+                    let response = router
+                        .clone()
+                        .oneshot(json_request("/auth/login", credentials.clone()))
+                        .await
+                        .unwrap();
+                    assert_eq!(response.status(), StatusCode::OK);
+                    logins += 1;
+                }
+                logins
+            })
+        })
+        .collect();
+
+    // Let the login workers saturate the hasher before sampling.
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    let loaded = health_latencies(&router).await;
+
+    stop.store(true, std::sync::atomic::Ordering::Relaxed);
+    let mut logins = 0;
+    for worker in workers {
+        logins += worker.await.unwrap();
+    }
+
+    println!(
+        "idle p50={:?} p99={:?}; under load p50={:?} p99={:?}; {} logins",
+        percentile(&idle, 50),
+        percentile(&idle, 99),
+        percentile(&loaded, 50),
+        percentile(&loaded, 99),
+        logins,
+    );
+
+    assert!(logins > 0, "login workers made no progress");
+    assert!(
+        percentile(&loaded, 99) < MAX_LOADED_P99,
+        "health p99 {:?} exceeded {:?} under login load",
+        percentile(&loaded, 99),
+        MAX_LOADED_P99,
+    );
+}
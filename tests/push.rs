@@ -0,0 +1,441 @@
+//! Wake-ups for offline devices. The HTTP provider runs against an
+//! in-process endpoint from `common::recording`.
+
+mod common;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use axum::body::Bytes;
+use axum::http::{Request, StatusCode};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use http_body_util::Full;
+use ring::signature::{ECDSA_P256_SHA256_FIXED, UnparsedPublicKey};
+use uuid::Uuid;
+
+use common::recording;
+use msg_service::config::push::PushConfig;
+use msg_service::repository::Repository;
+use msg_service::repository::http_client::{self, Destinations};
+use msg_service::repository::push::{PushEndpoint, PushEndpointKind};
+use msg_service::repository::push_providers::{HttpPushProvider, PushProvider, PushProviderError};
+use msg_service::usecase::MessageBody::Envelopes;
+use msg_service::usecase::{MessageOptions, PushError, PushService, Service};
+
+struct Member {
+    id: Uuid,
+    device: Uuid,
+}
+
+async fn member(service: &Service, username: &str) -> Member {
+    let id = service
+        .auth
+        .create_user(username.to_string(), common::PASSWORD.to_string())
+        .await
+        .unwrap()
+        .id;
+    let device = service
+        .devices
+        .register_device(id, "Phone".to_string())
+        .await
+        .unwrap()
+        .id;
+
+    Member { id, device }
+}
+
+/// A chat of `alice` with everyone in `others`.
+async fn chat(service: &Service, alice: &Member, others: &[(&str, &Member)]) -> Uuid {
+    let chat = service
+        .chat
+        .create_chat("Team".to_string(), alice.id)
+        .await
+        .unwrap();
+
+    for (username, _) in others {
+        service
+            .chat
+            .invite_user_by_username(chat.id, username.to_string(), alice.id)
+            .await
+            .unwrap();
+    }
+
+    chat.id
+}
+
+async fn send(
+    service: &Service,
+    chat_id: Uuid,
+    sender: &Member,
+    recipients: &[&Member],
+    options: MessageOptions,
+) {
+    let envelopes: HashMap<Uuid, String> = recipients
+        .iter()
+        .map(|r| (r.device, "ciphertext".to_string()))
+        .collect();
+
+    service
+        .chat
+        .send_message(
+            chat_id,
+            sender.id,
+            sender.device,
+            Envelopes(envelopes),
+            options,
+        )
+        .await
+        .unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn wake_ups_reach_offline_members_and_are_signed_for_web_push() {
+    let (endpoint, base) = recording::start(StatusCode::CREATED).await;
+    let service = common::service_with(Repository::in_memory());
+    let alice = member(&service, "alice").await;
+    let bob = member(&service, "bob").await;
+    let carol = member(&service, "carol").await;
+    let chat_id = chat(&service, &alice, &[("bob", &bob), ("carol", &carol)]).await;
+
+    for (who, kind, path) in [
+        (&alice, "webhook", "/hook/alice"),
+        (&bob, "web_push", "/push/bob"),
+        (&carol, "webhook", "/hook/carol"),
+    ] {
+        service
+            .push
+            .set_endpoint(
+                who.id,
+                who.device,
+                kind.to_string(),
+                format!("{}{}", base, path),
+            )
+            .await
+            .unwrap();
+    }
+    service
+        .chat
+        .set_muted(chat_id, carol.id, true)
+        .await
+        .unwrap();
+
+    // Neither the sender nor a member who muted the chat is woken up.
+    send(
+        &service,
+        chat_id,
+        &alice,
+        &[&bob, &carol],
+        MessageOptions::default(),
+    )
+    .await;
+    let dispatch = service.push.dispatch_wake_ups(10).await.unwrap();
+    assert_eq!((dispatch.claimed, dispatch.sent), (1, 1));
+
+    let deliveries = endpoint.take();
+    assert_eq!(deliveries.len(), 1);
+    let push = &deliveries[0];
+    assert_eq!(push.path, "/push/bob");
+    assert!(push.body.is_empty());
+    assert_eq!(push.headers["ttl"], "86400");
+
+    let authorization = push.headers["authorization"].to_str().unwrap();
+    let (token, key) = authorization
+        .strip_prefix("vapid t=")
+        .and_then(|rest| rest.split_once(", k="))
+        .expect("vapid authorization");
+    assert_eq!(key, service.push.vapid_public_key().unwrap());
+
+    let (signing_input, signature) = token.rsplit_once('.').unwrap();
+    UnparsedPublicKey::new(
+        &ECDSA_P256_SHA256_FIXED,
+        URL_SAFE_NO_PAD.decode(key).unwrap(),
+    )
+    .verify(
+        signing_input.as_bytes(),
+        &URL_SAFE_NO_PAD.decode(signature).unwrap(),
+    )
+    .expect("valid VAPID signature");
+    let claims: serde_json::Value = serde_json::from_slice(
+        &URL_SAFE_NO_PAD
+            .decode(signing_input.split('.').nth(1).unwrap())
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(claims["aud"], base);
+    assert_eq!(claims["sub"], "mailto:ops@example.com");
+    assert!(claims["exp"].as_i64().unwrap() > chrono::Utc::now().timestamp());
+
+    // A mention gets through the mute; webhooks name the device and carry
+    // nothing else.
+    send(
+        &service,
+        chat_id,
+        &alice,
+        &[&bob, &carol],
+        MessageOptions {
+            mentions: vec![carol.id],
+            ..MessageOptions::default()
+        },
+    )
+    .await;
+    let dispatch = service.push.dispatch_wake_ups(10).await.unwrap();
+    assert_eq!(dispatch.sent, 2);
+
+    let deliveries = endpoint.take();
+    let hook = deliveries
+        .iter()
+        .find(|d| d.path == "/hook/carol")
+        .expect("carol woken up");
+    let body: serde_json::Value = serde_json::from_slice(&hook.body).unwrap();
+    assert_eq!(
+        body,
+        serde_json::json!({"type": "wake_up", "device_id": carol.device})
+    );
+
+    // So does @all.
+    send(
+        &service,
+        chat_id,
+        &alice,
+        &[&bob, &carol],
+        MessageOptions {
+            mention_all: true,
+            ..MessageOptions::default()
+        },
+    )
+    .await;
+    let dispatch = service.push.dispatch_wake_ups(10).await.unwrap();
+    assert_eq!(dispatch.sent, 2);
+    let mut paths: Vec<String> = endpoint.take().into_iter().map(|d| d.path).collect();
+    paths.sort();
+    assert_eq!(paths, ["/hook/carol", "/push/bob"]);
+
+    let dispatch = service.push.dispatch_wake_ups(10).await.unwrap();
+    assert_eq!(dispatch.claimed, 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn failing_wake_ups_are_retried_then_dead_lettered() {
+    let (endpoint, base) = recording::start(StatusCode::CREATED).await;
+    let repo = Repository::in_memory();
+    let service = common::service_with(repo.clone());
+    let alice = member(&service, "alice").await;
+    let bob = member(&service, "bob").await;
+    let chat_id = chat(&service, &alice, &[("bob", &bob)]).await;
+    let url = format!("{}/hook/bob", base);
+    service
+        .push
+        .set_endpoint(bob.id, bob.device, "webhook".to_string(), url.clone())
+        .await
+        .unwrap();
+
+    // Retries come right away with no backoff base, and the third failure
+    // uses up the attempts.
+    endpoint.answer(StatusCode::SERVICE_UNAVAILABLE);
+    send(
+        &service,
+        chat_id,
+        &alice,
+        &[&bob],
+        MessageOptions::default(),
+    )
+    .await;
+    for _ in 0..2 {
+        let dispatch = service.push.dispatch_wake_ups(10).await.unwrap();
+        assert_eq!(dispatch.retried, 1);
+    }
+    let dispatch = service.push.dispatch_wake_ups(10).await.unwrap();
+    assert_eq!(dispatch.failed, 1);
+    assert_eq!(endpoint.take().len(), 3);
+
+    let dead_letters = repo.push.list_dead_letters(10, 0).unwrap();
+    assert_eq!(dead_letters.len(), 1);
+    assert_eq!(dead_letters[0].device_id, bob.device);
+    assert_eq!(dead_letters[0].url, url);
+    assert_eq!(dead_letters[0].attempts, 3);
+    assert!(dead_letters[0].error.contains("503"));
+
+    // A refusal is final on the first attempt.
+    endpoint.answer(StatusCode::BAD_REQUEST);
+    send(
+        &service,
+        chat_id,
+        &alice,
+        &[&bob],
+        MessageOptions::default(),
+    )
+    .await;
+    let dispatch = service.push.dispatch_wake_ups(10).await.unwrap();
+    assert_eq!(dispatch.failed, 1);
+    let dead_letters = repo.push.list_dead_letters(10, 0).unwrap();
+    assert_eq!(dead_letters.len(), 2);
+    assert!(
+        dead_letters
+            .iter()
+            .any(|d| d.attempts == 1 && d.error.contains("400"))
+    );
+
+    // A gone endpoint is dropped instead.
+    endpoint.answer(StatusCode::GONE);
+    send(
+        &service,
+        chat_id,
+        &alice,
+        &[&bob],
+        MessageOptions::default(),
+    )
+    .await;
+    let dispatch = service.push.dispatch_wake_ups(10).await.unwrap();
+    assert_eq!(dispatch.failed, 1);
+    assert!(matches!(
+        service.push.get_endpoint(bob.id, bob.device).await,
+        Err(PushError::EndpointNotFound)
+    ));
+    assert_eq!(repo.push.list_dead_letters(10, 0).unwrap().len(), 2);
+
+    send(
+        &service,
+        chat_id,
+        &alice,
+        &[&bob],
+        MessageOptions::default(),
+    )
+    .await;
+    let dispatch = service.push.dispatch_wake_ups(10).await.unwrap();
+    assert_eq!(dispatch.claimed, 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn endpoints_must_be_public_unless_allowed() {
+    let provider = HttpPushProvider::new(&PushConfig {
+        allow_private_endpoints: false,
+        ..common::push_config()
+    })
+    .unwrap();
+
+    for url in [
+        "http://127.0.0.1/push",
+        "http://10.1.2.3/push",
+        "http://169.254.169.254/latest/meta-data",
+        "http://[::1]/push",
+        "http://[::ffff:192.168.0.1]/push",
+        "http://localhost/push",
+        "ftp://push.example.com/push",
+    ] {
+        assert!(
+            provider
+                .check_endpoint(PushEndpointKind::Webhook, url)
+                .is_err(),
+            "{}",
+            url
+        );
+    }
+    for url in ["https://push.example.com/send/abc", "http://8.8.8.8/push"] {
+        assert!(
+            provider
+                .check_endpoint(PushEndpointKind::Webhook, url)
+                .is_ok()
+        );
+    }
+
+    // Names are checked once resolved, so one that points at a private
+    // address is never connected to.
+    let (endpoint, base) = recording::start(StatusCode::CREATED).await;
+    let url = base.replace("127.0.0.1", "localhost");
+    let request = || Request::post(&url).body(Full::new(Bytes::new())).unwrap();
+
+    let public_only = http_client::client(Destinations::PublicOnly);
+    assert!(public_only.request(request()).await.is_err());
+    assert!(endpoint.take().is_empty());
+
+    let any = http_client::client(Destinations::Any);
+    assert!(any.request(request()).await.is_ok());
+    assert_eq!(endpoint.take().len(), 1);
+}
+
+/// Records wake-ups instead of sending them.
+#[derive(Default)]
+struct RecordingProvider {
+    woken: Mutex<Vec<Uuid>>,
+}
+
+impl PushProvider for RecordingProvider {
+    fn check_endpoint(&self, _kind: PushEndpointKind, _url: &str) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn wake(&self, endpoint: &PushEndpoint) -> Result<(), PushProviderError> {
+        self.woken.lock().unwrap().push(endpoint.device_id);
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn devices_that_fetched_recently_are_not_woken_up() {
+    let repo = Repository::in_memory();
+    let service = common::service_with(repo.clone());
+    let provider = Arc::new(RecordingProvider::default());
+    let push = PushService::with_provider(
+        repo,
+        &PushConfig {
+            online_window_seconds: 1,
+            ..common::push_config()
+        },
+        provider.clone(),
+    );
+    let alice = member(&service, "alice").await;
+    let bob = member(&service, "bob").await;
+    let chat_id = chat(&service, &alice, &[("bob", &bob)]).await;
+    push.set_endpoint(
+        bob.id,
+        bob.device,
+        "webhook".to_string(),
+        "http://push.invalid/bob".to_string(),
+    )
+    .await
+    .unwrap();
+
+    // A device that was just registered counts as online.
+    send(
+        &service,
+        chat_id,
+        &alice,
+        &[&bob],
+        MessageOptions::default(),
+    )
+    .await;
+    let dispatch = push.dispatch_wake_ups(10).await.unwrap();
+    assert_eq!((dispatch.skipped, dispatch.sent), (1, 0));
+
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+    send(
+        &service,
+        chat_id,
+        &alice,
+        &[&bob],
+        MessageOptions::default(),
+    )
+    .await;
+    let dispatch = push.dispatch_wake_ups(10).await.unwrap();
+    assert_eq!(dispatch.sent, 1);
+    assert_eq!(*provider.woken.lock().unwrap(), vec![bob.device]);
+
+    // Fetching messages puts it back online.
+    service
+        .chat
+        .get_messages(chat_id, bob.id, bob.device, 50, 0)
+        .await
+        .unwrap();
+    send(
+        &service,
+        chat_id,
+        &alice,
+        &[&bob],
+        MessageOptions::default(),
+    )
+    .await;
+    let dispatch = push.dispatch_wake_ups(10).await.unwrap();
+    assert_eq!(dispatch.skipped, 1);
+    assert_eq!(provider.woken.lock().unwrap().len(), 1);
+}
@@ -0,0 +1,1462 @@
+//! HTTP layer end to end through the router, backed by in-memory storage.
+
+mod common;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode, header};
+use serde_json::json;
+use tower::ServiceExt;
+
+use common::{
+    ADMIN_PASSWORD, ADMIN_USERNAME, PASSWORD, into_json, login, register_and_login,
+    register_device, send, send_as_device, send_bytes,
+};
+
+#[tokio::test]
+async fn health_is_public() {
+    let router = common::router().await;
+
+    let (status, body) = send(&router, "GET", "/health", None, None).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["status"], "ok");
+}
+
+#[tokio::test]
+async fn register_login_and_me() {
+    let router = common::router().await;
+
+    let (status, body) = send(
+        &router,
+        "POST",
+        "/auth/register",
+        None,
+        Some(json!({ "username": "alice", "password": PASSWORD, "role_id": 0 })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(body["role_id"], 1, "role_id in the request must be ignored");
+
+    let token = login(&router, "alice", PASSWORD).await;
+    let (status, body) = send(&router, "GET", "/auth/me", Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["username"], "alice");
+    assert_eq!(body["role_name"], "user");
+}
+
+#[tokio::test]
+async fn auth_errors_map_to_status_codes() {
+    let router = common::router().await;
+    register_and_login(&router, "alice").await;
+
+    let (status, body) = send(
+        &router,
+        "POST",
+        "/auth/register",
+        None,
+        Some(json!({ "username": "alice", "password": PASSWORD })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["code"], "USERNAME_EXISTS");
+
+    let (status, body) = send(
+        &router,
+        "POST",
+        "/auth/login",
+        None,
+        Some(json!({ "username": "alice", "password": "WrongPass123" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(body["code"], "INVALID_CREDENTIALS");
+}
+
+#[tokio::test]
+async fn lockout_sets_retry_after() {
+    let router = common::router().await;
+    register_and_login(&router, "alice").await;
+    let wrong = json!({ "username": "alice", "password": "WrongPass123" });
+
+    for _ in 0..common::throttle_config().max_failures_per_username {
+        send(&router, "POST", "/auth/login", None, Some(wrong.clone())).await;
+    }
+
+    let response = common::send_raw(&router, "POST", "/auth/login", None, Some(wrong)).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(response.headers().contains_key(header::RETRY_AFTER));
+}
+
+#[tokio::test]
+async fn proxied_logins_are_throttled_per_forwarded_client() {
+    let router = common::proxied_router().await;
+    register_and_login(&router, "alice").await;
+
+    let login_from = |forwarded_for: &'static str, username: String, password: &'static str| {
+        let request = Request::post("/auth/login")
+            .header("x-forwarded-for", forwarded_for)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(
+                json!({ "username": username, "password": password }).to_string(),
+            ))
+            .unwrap();
+
+        let router = router.clone();
+        async move { router.oneshot(request).await.unwrap().status() }
+    };
+
+    // A client spraying passwords across accounts uses up its own budget,
+    // and prepending made-up addresses does not get it a fresh one.
+    let attacker = "203.0.113.7";
+    for i in 0..common::throttle_config().max_failures_per_ip {
+        let status = login_from(attacker, format!("user{}", i), "WrongPass123").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+    for forwarded_for in [attacker, "198.51.100.9, 203.0.113.7"] {
+        let status = login_from(forwarded_for, "alice".to_string(), PASSWORD).await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    // Other clients behind the same proxy are not locked out with it.
+    let status = login_from("198.51.100.9", "alice".to_string(), PASSWORD).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn protected_routes_require_a_valid_token() {
+    let router = common::router().await;
+
+    let (status, _) = send(&router, "GET", "/chats", None, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, _) = send(&router, "GET", "/chats", Some("not-a-jwt"), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn admin_routes_check_permissions() {
+    let router = common::router().await;
+    let user_token = register_and_login(&router, "alice").await;
+    let admin_token = login(&router, ADMIN_USERNAME, ADMIN_PASSWORD).await;
+
+    let (status, body) = send(&router, "GET", "/admin/users", Some(&user_token), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["code"], "FORBIDDEN");
+
+    let (status, body) = send(
+        &router,
+        "GET",
+        "/admin/users?search=ali",
+        Some(&admin_token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["total"], 1);
+    assert_eq!(body["users"][0]["username"], "alice");
+}
+
+#[tokio::test]
+async fn deactivated_users_lose_access() {
+    let router = common::router().await;
+    let user_token = register_and_login(&router, "alice").await;
+    let admin_token = login(&router, ADMIN_USERNAME, ADMIN_PASSWORD).await;
+
+    let (_, me) = send(&router, "GET", "/auth/me", Some(&user_token), None).await;
+    let user_id = me["id"].as_str().unwrap();
+
+    let (status, body) = send(
+        &router,
+        "POST",
+        &format!("/admin/users/{}/deactivate", user_id),
+        Some(&admin_token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["is_active"], false);
+
+    let (status, _) = send(&router, "GET", "/auth/me", Some(&user_token), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn custom_roles_over_http() {
+    let router = common::router().await;
+    let admin_token = login(&router, ADMIN_USERNAME, ADMIN_PASSWORD).await;
+    let user_token = register_and_login(&router, "auditor").await;
+    let (_, me) = send(&router, "GET", "/auth/me", Some(&user_token), None).await;
+
+    let (status, role) = send(
+        &router,
+        "POST",
+        "/admin/roles",
+        Some(&admin_token),
+        Some(json!({ "name": "auditor", "permissions": ["users.read"] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let (status, _) = send(
+        &router,
+        "PUT",
+        &format!("/admin/users/{}/role", me["id"].as_str().unwrap()),
+        Some(&admin_token),
+        Some(json!({ "role_id": role["id"] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = send(&router, "GET", "/admin/users", Some(&user_token), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = send(&router, "GET", "/admin/roles", Some(&user_token), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Holding users.assign_role does not let anyone hand out the admin role.
+    let (_, assigner) = send(
+        &router,
+        "POST",
+        "/admin/roles",
+        Some(&admin_token),
+        Some(json!({ "name": "assigner", "permissions": ["users.read", "users.assign_role"] })),
+    )
+    .await;
+    let uri = format!("/admin/users/{}/role", me["id"].as_str().unwrap());
+    let (status, _) = send(
+        &router,
+        "PUT",
+        &uri,
+        Some(&admin_token),
+        Some(json!({ "role_id": assigner["id"] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let user_token = login(&router, "auditor", PASSWORD).await;
+    let (status, body) = send(
+        &router,
+        "PUT",
+        &uri,
+        Some(&user_token),
+        Some(json!({ "role_id": 0 })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["code"], "FORBIDDEN");
+}
+
+#[tokio::test]
+async fn chat_flow() {
+    let router = common::router().await;
+    let alice = register_and_login(&router, "alice").await;
+    let bob = register_and_login(&router, "bob").await;
+    let alice_phone = register_device(&router, &alice, "Phone").await;
+    let bob_phone = register_device(&router, &bob, "Phone").await;
+
+    let (status, chat) = send(
+        &router,
+        "POST",
+        "/chats",
+        Some(&alice),
+        Some(json!({ "name": "Team" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let chat_id = chat["id"].as_str().unwrap();
+
+    let (status, body) = send_as_device(
+        &router,
+        "POST",
+        &format!("/chats/{}/messages", chat_id),
+        &bob,
+        &bob_phone,
+        Some(json!({ "envelopes": { alice_phone.clone(): "hi" } })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["code"], "NOT_MEMBER");
+
+    let (status, _) = send(
+        &router,
+        "POST",
+        &format!("/chats/{}/invite", chat_id),
+        Some(&alice),
+        Some(json!({ "username": "bob" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, body) = send(
+        &router,
+        "POST",
+        &format!("/chats/{}/invite", chat_id),
+        Some(&alice),
+        Some(json!({ "username": "bob" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "ALREADY_MEMBER");
+
+    let (status, body) = send(
+        &router,
+        "POST",
+        &format!("/chats/{}/messages", chat_id),
+        Some(&bob),
+        Some(json!({ "envelopes": { alice_phone.clone(): "hi" } })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "DEVICE_ID_REQUIRED");
+
+    let alice_laptop = register_device(&router, &alice, "Laptop").await;
+    let (status, body) = send_as_device(
+        &router,
+        "POST",
+        &format!("/chats/{}/messages", chat_id),
+        &bob,
+        &bob_phone,
+        Some(json!({ "envelopes": { alice_phone.clone(): "hi" } })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["code"], "DEVICE_MISMATCH");
+    assert_eq!(body["missing_devices"], json!([alice_laptop]));
+    assert_eq!(body["stale_devices"], json!([]));
+
+    let (_, devices) = send(
+        &router,
+        "GET",
+        &format!("/chats/{}/devices", chat_id),
+        Some(&bob),
+        None,
+    )
+    .await;
+    assert_eq!(devices.as_array().unwrap().len(), 3);
+
+    let (status, _) = send_as_device(
+        &router,
+        "POST",
+        &format!("/chats/{}/messages", chat_id),
+        &bob,
+        &bob_phone,
+        Some(json!({ "envelopes": { alice_phone.clone(): "hi", alice_laptop.clone(): "hi too" } })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let (status, messages) = send_as_device(
+        &router,
+        "GET",
+        &format!("/chats/{}/messages", chat_id),
+        &alice,
+        &alice_laptop,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(messages.as_array().unwrap().len(), 3);
+    assert_eq!(messages[0]["encrypted_content"], "hi too");
+    assert_eq!(messages[0]["sender_device_id"], bob_phone.as_str());
+    assert_eq!(messages[1]["kind"], "member_invited");
+    assert_eq!(messages[2]["kind"], "member_joined");
+
+    let (status, members) = send(
+        &router,
+        "GET",
+        &format!("/chats/{}/members", chat_id),
+        Some(&bob),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(members.as_array().unwrap().len(), 2);
+    assert_eq!(members[0]["invited_by"], serde_json::Value::Null);
+
+    let (status, members) = send(
+        &router,
+        "GET",
+        &format!("/chats/{}/members?username_prefix=bo&limit=1", chat_id),
+        Some(&alice),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(members.as_array().unwrap().len(), 1);
+    assert_eq!(members[0]["username"], "bob");
+    let (_, me) = send(&router, "GET", "/auth/me", Some(&alice), None).await;
+    assert_eq!(members[0]["invited_by"], me["id"]);
+}
+
+#[tokio::test]
+async fn jwks_is_empty_for_hs256() {
+    let router = common::router().await;
+
+    let (status, body) = send(&router, "GET", "/.well-known/jwks.json", None, None).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["keys"], json!([]));
+}
+
+#[tokio::test]
+async fn key_directory_over_http() {
+    let router = common::router().await;
+    let alice = register_and_login(&router, "alice").await;
+    let bob = register_and_login(&router, "bob").await;
+    let (_, me) = send(&router, "GET", "/auth/me", Some(&alice), None).await;
+    let bundle_uri = format!("/users/{}/prekey-bundle", me["id"].as_str().unwrap());
+    let key = "A".repeat(43) + "=";
+    let phone = register_device(&router, &alice, "Phone").await;
+
+    let (status, body) = send(&router, "POST", &bundle_uri, Some(&bob), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["code"], "KEYS_NOT_FOUND");
+
+    let (status, _) = send_as_device(
+        &router,
+        "PUT",
+        "/keys/identity",
+        &alice,
+        &phone,
+        Some(json!({
+            "identity_key": key,
+            "signed_prekey": { "key_id": 1, "public_key": key, "signature": "A".repeat(86) + "==" },
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, body) = send_as_device(
+        &router,
+        "POST",
+        "/keys/prekeys",
+        &alice,
+        &phone,
+        Some(json!({ "prekeys": [{ "key_id": 7, "public_key": key }] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(body["remaining"], 1);
+    assert_eq!(body["needs_replenish"], true);
+
+    let (status, body) = send(&router, "POST", &bundle_uri, Some(&bob), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["devices"][0]["device_id"], phone.as_str());
+    assert_eq!(body["devices"][0]["one_time_prekey"]["key_id"], 7);
+    assert_eq!(body["devices"][0]["signed_prekey"]["key_id"], 1);
+
+    let (_, body) = send(&router, "POST", &bundle_uri, Some(&bob), None).await;
+    assert_eq!(
+        body["devices"][0]["one_time_prekey"],
+        serde_json::Value::Null
+    );
+
+    let (status, body) =
+        send_as_device(&router, "GET", "/keys/prekeys/status", &alice, &phone, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["remaining"], 0);
+}
+
+#[tokio::test]
+async fn key_transparency_log_over_http() {
+    let router = common::router().await;
+    let alice = register_and_login(&router, "alice").await;
+    let (_, me) = send(&router, "GET", "/auth/me", Some(&alice), None).await;
+    let inclusion_uri = format!("/users/{}/keys/inclusion", me["id"].as_str().unwrap());
+    let phone = register_device(&router, &alice, "Phone").await;
+
+    let (status, body) = send(&router, "GET", "/keys/log/head", None, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["code"], "KEY_LOG_EMPTY");
+
+    for key in ["A", "E"] {
+        let key = key.repeat(43) + "=";
+        let (status, _) = send_as_device(
+            &router,
+            "PUT",
+            "/keys/identity",
+            &alice,
+            &phone,
+            Some(json!({
+                "identity_key": key,
+                "signed_prekey": { "key_id": 1, "public_key": key, "signature": "A".repeat(86) + "==" },
+            })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    let (status, head) = send(&router, "GET", "/keys/log/head", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(head["tree_size"], 2);
+
+    let (status, body) = send(&router, "GET", "/keys/log/public-key", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["algorithm"], "Ed25519");
+
+    let (status, _) = send(&router, "GET", &inclusion_uri, None, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, body) = send(&router, "GET", &inclusion_uri, Some(&alice), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["tree_head"]["root_hash"], head["root_hash"]);
+    assert_eq!(body["proofs"][0]["entry"]["leaf_index"], 1);
+    assert_eq!(body["proofs"][0]["audit_path"].as_array().unwrap().len(), 1);
+
+    let (status, body) = send(
+        &router,
+        "GET",
+        &format!("{}?tree_size=1", inclusion_uri),
+        Some(&alice),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["code"], "KEY_NOT_LOGGED");
+
+    let (status, body) = send(&router, "GET", "/keys/log/consistency?first=1", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["second"], 2);
+    assert_eq!(body["proof"].as_array().unwrap().len(), 1);
+
+    let (status, body) = send(&router, "GET", "/keys/log/consistency?first=3", None, None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "INVALID_TREE_SIZE");
+
+    let (status, body) = send(&router, "GET", "/keys/log/entries", Some(&alice), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body.as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn sender_keys_and_rekeys_over_http() {
+    let router = common::router().await;
+    let alice = register_and_login(&router, "alice").await;
+    let bob = register_and_login(&router, "bob").await;
+    let alice_phone = register_device(&router, &alice, "Phone").await;
+    let bob_phone = register_device(&router, &bob, "Phone").await;
+    let (_, chat) = send(
+        &router,
+        "POST",
+        "/chats",
+        Some(&alice),
+        Some(json!({ "name": "Team" })),
+    )
+    .await;
+    let chat_id = chat["id"].as_str().unwrap();
+    send(
+        &router,
+        "POST",
+        &format!("/chats/{}/invite", chat_id),
+        Some(&alice),
+        Some(json!({ "username": "bob" })),
+    )
+    .await;
+
+    let sender_keys_uri = format!("/chats/{}/sender-keys", chat_id);
+    let (status, body) = send_as_device(
+        &router,
+        "PUT",
+        &sender_keys_uri,
+        &alice,
+        &alice_phone,
+        Some(json!({ "epoch": 0, "sender_keys": { bob_phone.clone(): "k" } })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["code"], "STALE_EPOCH");
+    assert_eq!(body["current_epoch"], 1);
+
+    let (status, _) = send_as_device(
+        &router,
+        "PUT",
+        &sender_keys_uri,
+        &alice,
+        &alice_phone,
+        Some(json!({ "epoch": 1, "sender_keys": { bob_phone.clone(): "k" } })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, body) =
+        send_as_device(&router, "GET", &sender_keys_uri, &bob, &bob_phone, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["epoch"], 1);
+    assert_eq!(body["sender_keys"][0]["ciphertext"], "k");
+
+    let (status, body) = send_as_device(
+        &router,
+        "POST",
+        &format!("/chats/{}/messages", chat_id),
+        &alice,
+        &alice_phone,
+        Some(json!({
+            "envelopes": { bob_phone.clone(): "x" },
+            "sender_key": { "epoch": 1, "ciphertext": "x" },
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "INVALID_MESSAGE");
+
+    let (_, alice_info) = send(&router, "GET", "/auth/me", Some(&alice), None).await;
+    let (status, body) = send(
+        &router,
+        "DELETE",
+        &format!(
+            "/chats/{}/members/{}",
+            chat_id,
+            alice_info["id"].as_str().unwrap()
+        ),
+        Some(&bob),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["code"], "FORBIDDEN");
+
+    let (status, _) = send(
+        &router,
+        "POST",
+        &format!("/chats/{}/leave", chat_id),
+        Some(&bob),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (_, rekeys) = send(
+        &router,
+        "GET",
+        &format!("/chats/{}/rekeys?since_epoch=1", chat_id),
+        Some(&alice),
+        None,
+    )
+    .await;
+    assert_eq!(rekeys[0]["epoch"], 2);
+    assert_eq!(rekeys[0]["reason"], "leave");
+}
+
+#[tokio::test]
+async fn attachments_over_http() {
+    let router = common::router().await;
+    let alice = register_and_login(&router, "alice").await;
+    let bob = register_and_login(&router, "bob").await;
+    let phone = register_device(&router, &alice, "Phone").await;
+    let length = |n: usize| vec![("content-length", n.to_string())];
+
+    let response = send_bytes(
+        &router,
+        "POST",
+        "/attachments",
+        &alice,
+        &[],
+        b"photo".to_vec(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::LENGTH_REQUIRED);
+
+    let response = send_bytes(
+        &router,
+        "POST",
+        "/attachments",
+        &alice,
+        &length(17),
+        vec![0; 17],
+    )
+    .await;
+    let (status, body) = into_json(response).await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    assert_eq!(body["code"], "CHUNK_TOO_LARGE");
+
+    let response = send_bytes(
+        &router,
+        "POST",
+        "/attachments",
+        &alice,
+        &length(5),
+        b"photo".to_vec(),
+    )
+    .await;
+    let (status, photo) = into_json(response).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(photo["complete"], true);
+    let photo_uri = format!("/attachments/{}", photo["id"].as_str().unwrap());
+
+    let (status, upload) = send(
+        &router,
+        "POST",
+        "/attachments/uploads",
+        Some(&alice),
+        Some(json!({ "size": 20 })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let upload_uri = format!("/attachments/uploads/{}", upload["id"].as_str().unwrap());
+
+    let chunk = |offset: i64, n: usize| {
+        let mut headers = length(n);
+        headers.push(("upload-offset", offset.to_string()));
+        headers
+    };
+    let response = send_bytes(
+        &router,
+        "PATCH",
+        &upload_uri,
+        &alice,
+        &chunk(0, 16),
+        vec![1; 16],
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = send_bytes(
+        &router,
+        "PATCH",
+        &upload_uri,
+        &alice,
+        &chunk(0, 4),
+        vec![2; 4],
+    )
+    .await;
+    let (status, body) = into_json(response).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["code"], "OFFSET_MISMATCH");
+    assert_eq!(body["expected_offset"], 16);
+
+    let (status, body) = send(&router, "GET", &upload_uri, Some(&alice), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["uploaded_size"], 16);
+    let response = send_bytes(
+        &router,
+        "PATCH",
+        &upload_uri,
+        &alice,
+        &chunk(16, 4),
+        vec![2; 4],
+    )
+    .await;
+    let (_, body) = into_json(response).await;
+    assert_eq!(body["complete"], true);
+
+    let (status, _) = send(&router, "GET", &photo_uri, Some(&bob), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (_, chat) = send(
+        &router,
+        "POST",
+        "/chats",
+        Some(&alice),
+        Some(json!({ "name": "Team" })),
+    )
+    .await;
+    let chat_id = chat["id"].as_str().unwrap();
+    send(
+        &router,
+        "POST",
+        &format!("/chats/{}/invite", chat_id),
+        Some(&alice),
+        Some(json!({ "username": "bob" })),
+    )
+    .await;
+    let (status, message) = send_as_device(
+        &router,
+        "POST",
+        &format!("/chats/{}/messages", chat_id),
+        &alice,
+        &phone,
+        Some(json!({ "envelopes": {}, "attachment_ids": [photo["id"], upload["id"]] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(message["attachment_ids"].as_array().unwrap().len(), 2);
+
+    let response = common::send_raw(&router, "GET", &photo_uri, Some(&bob), None).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[header::CONTENT_TYPE],
+        "application/octet-stream"
+    );
+    let data = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert_eq!(&data[..], b"photo");
+}
+
+#[tokio::test]
+async fn rename_chat_over_http() {
+    let router = common::router().await;
+    let alice = register_and_login(&router, "alice").await;
+    let bob = register_and_login(&router, "bob").await;
+    let phone = register_device(&router, &alice, "Phone").await;
+    let (_, chat) = send(
+        &router,
+        "POST",
+        "/chats",
+        Some(&alice),
+        Some(json!({ "name": "Team" })),
+    )
+    .await;
+    let chat_id = chat["id"].as_str().unwrap();
+    send(
+        &router,
+        "POST",
+        &format!("/chats/{}/invite", chat_id),
+        Some(&alice),
+        Some(json!({ "username": "bob" })),
+    )
+    .await;
+    let name_uri = format!("/chats/{}/name", chat_id);
+
+    let (status, body) = send(
+        &router,
+        "PUT",
+        &name_uri,
+        Some(&bob),
+        Some(json!({ "name": "Bob's" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["code"], "FORBIDDEN");
+
+    let (status, body) = send(
+        &router,
+        "PUT",
+        &name_uri,
+        Some(&alice),
+        Some(json!({ "name": "" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "INVALID_CHAT_NAME");
+
+    let (status, body) = send(
+        &router,
+        "PUT",
+        &name_uri,
+        Some(&alice),
+        Some(json!({ "name": "Core Team" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["name"], "Core Team");
+
+    let (status, messages) = send_as_device(
+        &router,
+        "GET",
+        &format!("/chats/{}/messages", chat_id),
+        &alice,
+        &phone,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(messages[0]["kind"], "chat_renamed");
+    assert_eq!(messages[0]["payload"]["old_name"], "Team");
+    assert_eq!(messages[0]["encrypted_content"], serde_json::Value::Null);
+}
+
+#[tokio::test]
+async fn message_timer_over_http() {
+    let router = common::router().await;
+    let alice = register_and_login(&router, "alice").await;
+    let bob = register_and_login(&router, "bob").await;
+    let phone = register_device(&router, &alice, "Phone").await;
+    let (_, chat) = send(
+        &router,
+        "POST",
+        "/chats",
+        Some(&alice),
+        Some(json!({ "name": "Team" })),
+    )
+    .await;
+    let chat_id = chat["id"].as_str().unwrap();
+    assert_eq!(chat["message_ttl_seconds"], serde_json::Value::Null);
+    send(
+        &router,
+        "POST",
+        &format!("/chats/{}/invite", chat_id),
+        Some(&alice),
+        Some(json!({ "username": "bob" })),
+    )
+    .await;
+    let ttl_uri = format!("/chats/{}/message-ttl", chat_id);
+
+    let (status, body) = send(
+        &router,
+        "PUT",
+        &ttl_uri,
+        Some(&bob),
+        Some(json!({ "ttl_seconds": 60 })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["code"], "FORBIDDEN");
+
+    let (status, body) = send(
+        &router,
+        "PUT",
+        &ttl_uri,
+        Some(&alice),
+        Some(json!({ "ttl_seconds": -5 })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "INVALID_MESSAGE_TTL");
+
+    let (status, body) = send(
+        &router,
+        "PUT",
+        &ttl_uri,
+        Some(&alice),
+        Some(json!({ "ttl_seconds": 86400 })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["message_ttl_seconds"], 86400);
+
+    let (status, message) = send_as_device(
+        &router,
+        "POST",
+        &format!("/chats/{}/messages", chat_id),
+        &alice,
+        &phone,
+        Some(json!({ "envelopes": {} })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert!(message["expires_at"].is_string());
+    assert_eq!(message["view_once"], false);
+
+    let (status, body) = send(
+        &router,
+        "PUT",
+        &ttl_uri,
+        Some(&alice),
+        Some(json!({ "ttl_seconds": null })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["message_ttl_seconds"], serde_json::Value::Null);
+}
+
+#[tokio::test]
+async fn scheduled_messages_over_http() {
+    let router = common::router().await;
+    let alice = register_and_login(&router, "alice").await;
+    let phone = register_device(&router, &alice, "Phone").await;
+    let (_, chat) = send(
+        &router,
+        "POST",
+        "/chats",
+        Some(&alice),
+        Some(json!({ "name": "Team" })),
+    )
+    .await;
+    let chat_id = chat["id"].as_str().unwrap();
+    let messages_uri = format!("/chats/{}/messages", chat_id);
+    let tomorrow = (chrono::Utc::now() + chrono::Duration::days(1)).to_rfc3339();
+
+    let (status, body) = send_as_device(
+        &router,
+        "POST",
+        &messages_uri,
+        &alice,
+        &phone,
+        Some(json!({ "envelopes": {}, "send_at": "tomorrow" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "INVALID_SCHEDULE");
+
+    let (status, scheduled) = send_as_device(
+        &router,
+        "POST",
+        &messages_uri,
+        &alice,
+        &phone,
+        Some(json!({ "envelopes": {}, "send_at": tomorrow })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    assert_eq!(scheduled["status"], "pending");
+    assert_eq!(scheduled["chat_id"], chat_id);
+    let scheduled_uri = format!("/scheduled-messages/{}", scheduled["id"].as_str().unwrap());
+
+    let (status, list) = send(
+        &router,
+        "GET",
+        &format!("/scheduled-messages?chat_id={}", chat_id),
+        Some(&alice),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(list.as_array().unwrap().len(), 1);
+
+    let (status, updated) = send_as_device(
+        &router,
+        "PUT",
+        &scheduled_uri,
+        &alice,
+        &phone,
+        Some(json!({ "envelopes": {}, "view_once": true })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(updated["view_once"], true);
+    assert_eq!(updated["send_at"], scheduled["send_at"]);
+
+    let (status, _) = send(&router, "DELETE", &scheduled_uri, Some(&alice), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, body) = send(&router, "DELETE", &scheduled_uri, Some(&alice), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["code"], "SCHEDULED_MESSAGE_NOT_FOUND");
+}
+
+#[tokio::test]
+async fn pins_over_http() {
+    let router = common::router().await;
+    let alice = register_and_login(&router, "alice").await;
+    let bob = register_and_login(&router, "bob").await;
+    let phone = register_device(&router, &alice, "Phone").await;
+    let (_, chat) = send(
+        &router,
+        "POST",
+        "/chats",
+        Some(&alice),
+        Some(json!({ "name": "Team" })),
+    )
+    .await;
+    let chat_id = chat["id"].as_str().unwrap();
+    assert_eq!(chat["latest_pin"], serde_json::Value::Null);
+    send(
+        &router,
+        "POST",
+        &format!("/chats/{}/invite", chat_id),
+        Some(&alice),
+        Some(json!({ "username": "bob" })),
+    )
+    .await;
+    let (_, message) = send_as_device(
+        &router,
+        "POST",
+        &format!("/chats/{}/messages", chat_id),
+        &alice,
+        &phone,
+        Some(json!({ "envelopes": {} })),
+    )
+    .await;
+    let pin_uri = format!(
+        "/chats/{}/pins/{}",
+        chat_id,
+        message["id"].as_str().unwrap()
+    );
+
+    let (status, body) = send(&router, "PUT", &pin_uri, Some(&bob), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["code"], "FORBIDDEN");
+
+    let (status, pin) = send(&router, "PUT", &pin_uri, Some(&alice), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(pin["message_id"], message["id"]);
+
+    let (status, pins) = send(
+        &router,
+        "GET",
+        &format!("/chats/{}/pins", chat_id),
+        Some(&bob),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(pins.as_array().unwrap().len(), 1);
+    let (_, chat) = send(
+        &router,
+        "GET",
+        &format!("/chats/{}", chat_id),
+        Some(&bob),
+        None,
+    )
+    .await;
+    assert_eq!(chat["latest_pin"]["message_id"], message["id"]);
+
+    let (status, _) = send(&router, "DELETE", &pin_uri, Some(&alice), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, body) = send(&router, "DELETE", &pin_uri, Some(&alice), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["code"], "PIN_NOT_FOUND");
+}
+
+#[tokio::test]
+async fn mentions_over_http() {
+    let router = common::router().await;
+    let alice = register_and_login(&router, "alice").await;
+    let bob = register_and_login(&router, "bob").await;
+    let phone = register_device(&router, &alice, "Phone").await;
+    let (_, chat) = send(
+        &router,
+        "POST",
+        "/chats",
+        Some(&alice),
+        Some(json!({ "name": "Team" })),
+    )
+    .await;
+    let chat_id = chat["id"].as_str().unwrap();
+    send(
+        &router,
+        "POST",
+        &format!("/chats/{}/invite", chat_id),
+        Some(&alice),
+        Some(json!({ "username": "bob" })),
+    )
+    .await;
+    let (_, members) = send(
+        &router,
+        "GET",
+        &format!("/chats/{}/members?username_prefix=bob", chat_id),
+        Some(&alice),
+        None,
+    )
+    .await;
+    let bob_id = members[0]["user_id"].clone();
+
+    let (status, body) = send(
+        &router,
+        "PUT",
+        &format!("/chats/{}/mute", chat_id),
+        Some(&bob),
+        Some(json!({ "muted": true })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["muted"], true);
+
+    let (status, message) = send_as_device(
+        &router,
+        "POST",
+        &format!("/chats/{}/messages", chat_id),
+        &alice,
+        &phone,
+        Some(json!({ "envelopes": {}, "mentions": [bob_id] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(message["mentions"], json!([bob_id]));
+    assert_eq!(message["mention_all"], false);
+
+    let (status, chats) = send(&router, "GET", "/chats", Some(&bob), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(chats[0]["read_state"]["unread_count"], 1);
+    assert_eq!(chats[0]["read_state"]["mention_count"], 1);
+
+    let (status, mentions) = send(&router, "GET", "/mentions", Some(&bob), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(mentions.as_array().unwrap().len(), 1);
+    assert_eq!(mentions[0]["message_id"], message["id"]);
+    assert_eq!(mentions[0]["read"], false);
+
+    let (status, body) = send(
+        &router,
+        "PUT",
+        &format!("/chats/{}/read", chat_id),
+        Some(&bob),
+        Some(json!({ "message_id": message["id"] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["unread_count"], 0);
+    assert_eq!(body["mention_count"], 0);
+
+    let (status, body) = send(
+        &router,
+        "PUT",
+        &format!("/chats/{}/read", chat_id),
+        Some(&bob),
+        Some(json!({ "message_id": uuid::Uuid::new_v4() })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["code"], "MESSAGE_NOT_FOUND");
+}
+
+#[tokio::test]
+async fn forwarding_over_http() {
+    let router = common::router().await;
+    let alice = register_and_login(&router, "alice").await;
+    let phone = register_device(&router, &alice, "Phone").await;
+    let mut chat_ids = Vec::new();
+    for name in ["Team", "Notes"] {
+        let (_, chat) = send(
+            &router,
+            "POST",
+            "/chats",
+            Some(&alice),
+            Some(json!({ "name": name })),
+        )
+        .await;
+        assert_eq!(chat["allow_forwarding"], true);
+        chat_ids.push(chat["id"].as_str().unwrap().to_string());
+    }
+    let (_, original) = send_as_device(
+        &router,
+        "POST",
+        &format!("/chats/{}/messages", chat_ids[0]),
+        &alice,
+        &phone,
+        Some(json!({ "envelopes": {} })),
+    )
+    .await;
+    assert!(original["forwarded_from"].is_null());
+
+    let (status, forward) = send_as_device(
+        &router,
+        "POST",
+        &format!("/chats/{}/messages/forward", chat_ids[1]),
+        &alice,
+        &phone,
+        Some(json!({ "message_id": original["id"], "envelopes": {} })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(forward["chat_id"], chat_ids[1]);
+    assert_eq!(forward["forwarded_from"]["chat_id"], chat_ids[0]);
+    assert_eq!(forward["forwarded_from"]["message_id"], original["id"]);
+    assert_eq!(
+        forward["forwarded_from"]["sender_id"],
+        original["sender_id"]
+    );
+
+    let (status, chat) = send(
+        &router,
+        "PUT",
+        &format!("/chats/{}/forwarding", chat_ids[0]),
+        Some(&alice),
+        Some(json!({ "allow_forwarding": false })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(chat["allow_forwarding"], false);
+
+    let (status, body) = send_as_device(
+        &router,
+        "POST",
+        &format!("/chats/{}/messages/forward", chat_ids[1]),
+        &alice,
+        &phone,
+        Some(json!({ "message_id": original["id"], "envelopes": {} })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["code"], "FORWARDING_DISABLED");
+}
+
+#[tokio::test]
+async fn polls_over_http() {
+    let router = common::router().await;
+    let alice = register_and_login(&router, "alice").await;
+    let phone = register_device(&router, &alice, "Phone").await;
+    let (_, chat) = send(
+        &router,
+        "POST",
+        "/chats",
+        Some(&alice),
+        Some(json!({ "name": "Team" })),
+    )
+    .await;
+    let chat_id = chat["id"].as_str().unwrap();
+    assert_eq!(chat["allow_polls"], true);
+
+    let (status, body) = send(
+        &router,
+        "POST",
+        &format!("/chats/{}/polls", chat_id),
+        Some(&alice),
+        Some(json!({ "question": "Lunch?", "options": ["Pizza"] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "INVALID_POLL");
+
+    let (status, message) = send(
+        &router,
+        "POST",
+        &format!("/chats/{}/polls", chat_id),
+        Some(&alice),
+        Some(json!({
+            "question": "Lunch?",
+            "options": ["Pizza", "Sushi"],
+            "closes_at": "2999-01-01T00:00:00Z",
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(message["kind"], "poll");
+    assert_eq!(message["poll"]["closes_at"], "2999-01-01 00:00:00");
+    assert_eq!(message["poll"]["options"][1]["text"], "Sushi");
+
+    let (status, poll) = send(
+        &router,
+        "PUT",
+        &format!(
+            "/chats/{}/polls/{}/votes",
+            chat_id,
+            message["id"].as_str().unwrap()
+        ),
+        Some(&alice),
+        Some(json!({ "options": [1] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(poll["own_votes"], json!([1]));
+    assert_eq!(poll["options"][1]["vote_count"], 1);
+
+    let (_, messages) = send_as_device(
+        &router,
+        "GET",
+        &format!("/chats/{}/messages", chat_id),
+        &alice,
+        &phone,
+        None,
+    )
+    .await;
+    assert_eq!(messages[0]["poll"]["voter_count"], 1);
+
+    let (status, chat) = send(
+        &router,
+        "PUT",
+        &format!("/chats/{}/allow-polls", chat_id),
+        Some(&alice),
+        Some(json!({ "allow_polls": false })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(chat["allow_polls"], false);
+
+    let (status, body) = send(
+        &router,
+        "POST",
+        &format!("/chats/{}/polls", chat_id),
+        Some(&alice),
+        Some(json!({ "question": "Dinner?", "options": ["Yes", "No"] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["code"], "POLLS_DISABLED");
+}
+
+#[tokio::test]
+async fn push_endpoints_over_http() {
+    let router = common::router().await;
+    let alice = register_and_login(&router, "alice").await;
+    let bob = register_and_login(&router, "bob").await;
+    let phone = register_device(&router, &alice, "Phone").await;
+    let uri = format!("/devices/{}/push-endpoint", phone);
+
+    let (status, key) = send(&router, "GET", "/push/vapid-public-key", Some(&alice), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(key["public_key"].as_str().unwrap().len(), 87);
+
+    let (status, body) = send(&router, "GET", &uri, Some(&alice), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["code"], "PUSH_ENDPOINT_NOT_FOUND");
+
+    let endpoint = json!({ "kind": "web_push", "url": "https://push.example.com/send/abc" });
+    let (status, body) = send(&router, "PUT", &uri, Some(&alice), Some(endpoint.clone())).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["device_id"], phone);
+    assert_eq!(body["kind"], "web_push");
+
+    let (status, body) = send(&router, "GET", &uri, Some(&alice), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["url"], "https://push.example.com/send/abc");
+
+    let (status, body) = send(&router, "PUT", &uri, Some(&bob), Some(endpoint)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["code"], "UNKNOWN_DEVICE");
+
+    for invalid in [
+        json!({ "kind": "sms", "url": "http://push.internal/send/abc" }),
+        json!({ "kind": "webhook", "url": "ftp://push.example.com/abc" }),
+        json!({ "kind": "webhook", "url": "not a url" }),
+    ] {
+        let (status, body) = send(&router, "PUT", &uri, Some(&alice), Some(invalid)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["code"], "INVALID_PUSH_ENDPOINT");
+    }
+
+    let (status, _) = send(&router, "DELETE", &uri, Some(&alice), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send(&router, "DELETE", &uri, Some(&alice), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn webhooks_over_http() {
+    let router = common::router().await;
+    let admin = login(&router, ADMIN_USERNAME, ADMIN_PASSWORD).await;
+    let alice = register_and_login(&router, "alice").await;
+    let (_, chat) = send(
+        &router,
+        "POST",
+        "/chats",
+        Some(&alice),
+        Some(json!({ "name": "Team" })),
+    )
+    .await;
+
+    let webhook = json!({
+        "chat_id": chat["id"],
+        "url": "http://hooks.internal/chat",
+        "events": ["message.created"],
+    });
+    let (status, created) = send(&router, "POST", "/webhooks", Some(&alice), Some(webhook)).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(created["events"], json!(["message.created"]));
+    assert!(created["secret"].as_str().is_some());
+
+    let global = json!({ "url": "http://hooks.internal/all", "events": ["member.joined"] });
+    let (status, body) = send(
+        &router,
+        "POST",
+        "/webhooks",
+        Some(&alice),
+        Some(global.clone()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["code"], "FORBIDDEN");
+    let (status, _) = send(&router, "POST", "/webhooks", Some(&admin), Some(global)).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let invalid = json!({ "url": "http://hooks.internal/all", "events": ["chat.deleted"] });
+    let (status, body) = send(&router, "POST", "/webhooks", Some(&admin), Some(invalid)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "INVALID_WEBHOOK");
+
+    let (status, listed) = send(&router, "GET", "/webhooks", Some(&alice), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(listed.as_array().unwrap().len(), 1);
+    assert!(listed[0].get("secret").is_none());
+
+    let uri = format!("/webhooks/{}", created["id"].as_str().unwrap());
+    let (status, deliveries) = send(
+        &router,
+        "GET",
+        &format!("{}/deliveries", uri),
+        Some(&alice),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(deliveries, json!([]));
+
+    let (status, body) = send(&router, "GET", &uri, Some(&admin), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["url"], "http://hooks.internal/chat");
+
+    let (status, _) = send(&router, "DELETE", &uri, Some(&alice), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, body) = send(&router, "GET", &uri, Some(&alice), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["code"], "WEBHOOK_NOT_FOUND");
+}
@@ -0,0 +1,2843 @@
+//! Use-case layer against the in-memory repositories.
+
+mod common;
+
+use base64::Engine;
+use msg_service::repository::auth::NewAuthUser;
+use msg_service::repository::chat::MemberFilter;
+use msg_service::repository::{Repository, RepositoryError};
+use msg_service::usecase::auth::permissions;
+use msg_service::usecase::keys::log::{leaf_input, verify_tree_head};
+use msg_service::usecase::keys::merkle::{self, Hash};
+use std::collections::HashMap;
+
+use msg_service::usecase::MessageBody::{self, Envelopes};
+use msg_service::usecase::MessageOptions;
+use msg_service::usecase::{
+    AttachmentDownload, AttachmentError, AuthError, ChatError, DeviceError, KeyError,
+    OneTimePrekeyInfo, PollDraft, Service, SignedPrekeyInfo,
+};
+
+use common::{ADMIN_PASSWORD, ADMIN_USERNAME, PASSWORD};
+
+async fn register(service: &Service, username: &str) -> uuid::Uuid {
+    service
+        .auth
+        .create_user(username.to_string(), PASSWORD.to_string())
+        .await
+        .expect("register")
+        .id
+}
+
+async fn device(service: &Service, user_id: uuid::Uuid) -> uuid::Uuid {
+    service
+        .devices
+        .register_device(user_id, "Phone".to_string())
+        .await
+        .expect("register device")
+        .id
+}
+
+async fn token_for(service: &Service, username: &str) -> String {
+    service
+        .auth
+        .login(username.to_string(), PASSWORD.to_string(), None)
+        .await
+        .expect("login")
+        .token
+}
+
+async fn read_chunks(mut download: AttachmentDownload) -> Vec<Vec<u8>> {
+    let mut chunks = Vec::new();
+    while let Some(chunk) = download.chunks.recv().await {
+        chunks.push(chunk.expect("chunk"));
+    }
+    chunks
+}
+
+/// Files under `dir`, however deeply nested.
+fn files_under(dir: &std::path::Path) -> usize {
+    std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .map(|path| if path.is_dir() { files_under(&path) } else { 1 })
+        .sum()
+}
+
+async fn admin_id(service: &Service) -> uuid::Uuid {
+    let page = service
+        .auth
+        .list_users(Some(ADMIN_USERNAME.to_string()), 1, 0)
+        .await
+        .unwrap();
+    page.users[0].id
+}
+
+#[tokio::test]
+async fn registration_assigns_the_default_role() {
+    let service = common::service().await;
+
+    let user = service
+        .auth
+        .create_user("alice".to_string(), PASSWORD.to_string())
+        .await
+        .unwrap();
+
+    assert_eq!(user.role_id, 1);
+    assert!(user.is_active);
+    assert!(user.password_hash.starts_with("$argon2id$"));
+}
+
+#[tokio::test]
+async fn registration_rejects_duplicates_and_invalid_input() {
+    let service = common::service().await;
+    register(&service, "alice").await;
+
+    let duplicate = service
+        .auth
+        .create_user("alice".to_string(), PASSWORD.to_string())
+        .await;
+    assert!(matches!(duplicate, Err(AuthError::UsernameExists)));
+
+    let short_name = service
+        .auth
+        .create_user("al".to_string(), PASSWORD.to_string())
+        .await;
+    assert!(matches!(short_name, Err(AuthError::InvalidUsername(_))));
+
+    let bad_chars = service
+        .auth
+        .create_user("al ice".to_string(), PASSWORD.to_string())
+        .await;
+    assert!(matches!(bad_chars, Err(AuthError::InvalidUsername(_))));
+
+    let weak_password = service
+        .auth
+        .create_user("bob".to_string(), "short".to_string())
+        .await;
+    assert!(matches!(weak_password, Err(AuthError::InvalidPassword(_))));
+}
+
+#[tokio::test]
+async fn login_issues_a_token_that_validates() {
+    let service = common::service().await;
+    let user_id = register(&service, "alice").await;
+
+    let token = token_for(&service, "alice").await;
+    let principal = service.auth.validate_token(&token).await.unwrap();
+
+    assert_eq!(principal.user_id, user_id);
+    assert_eq!(principal.role_id, 1);
+    assert!(principal.permissions.is_empty());
+}
+
+#[tokio::test]
+async fn login_rejects_wrong_password_and_unknown_user() {
+    let service = common::service().await;
+    register(&service, "alice").await;
+
+    let wrong_password = service
+        .auth
+        .login("alice".to_string(), "WrongPass123".to_string(), None)
+        .await;
+    assert!(matches!(wrong_password, Err(AuthError::InvalidCredentials)));
+
+    let unknown = service
+        .auth
+        .login("nobody".to_string(), PASSWORD.to_string(), None)
+        .await;
+    assert!(matches!(unknown, Err(AuthError::InvalidCredentials)));
+}
+
+#[tokio::test]
+async fn repeated_failures_lock_the_username() {
+    let service = common::service().await;
+    register(&service, "alice").await;
+
+    for _ in 0..common::throttle_config().max_failures_per_username {
+        let _ = service
+            .auth
+            .login("alice".to_string(), "WrongPass123".to_string(), None)
+            .await;
+    }
+
+    let locked = service
+        .auth
+        .login("alice".to_string(), PASSWORD.to_string(), None)
+        .await;
+    assert!(matches!(
+        locked,
+        Err(AuthError::TooManyAttempts { retry_after }) if retry_after > 0
+    ));
+}
+
+#[tokio::test]
+async fn bcrypt_hashes_are_upgraded_on_login() {
+    let repo = Repository::in_memory();
+    let service = common::service_with(repo.clone());
+
+    let legacy_hash = bcrypt::hash(PASSWORD, 4).unwrap();
+    let user = repo
+        .auth
+        .create_user(NewAuthUser {
+            username: "legacy".to_string(),
+            password_hash: legacy_hash,
+            role_id: 1,
+        })
+        .unwrap();
+
+    token_for(&service, "legacy").await;
+
+    let stored = repo.auth.find_by_id(user.id).unwrap().unwrap();
+    assert!(stored.password_hash.starts_with("$argon2id$"));
+    token_for(&service, "legacy").await;
+}
+
+#[tokio::test]
+async fn ensure_admin_never_promotes_an_existing_user() {
+    let service = common::service_with(Repository::in_memory());
+    register(&service, "root").await;
+
+    service
+        .auth
+        .ensure_admin("root".to_string(), ADMIN_PASSWORD.to_string())
+        .await
+        .unwrap();
+
+    let token = token_for(&service, "root").await;
+    let principal = service.auth.validate_token(&token).await.unwrap();
+    assert_eq!(principal.role_id, 1);
+}
+
+#[tokio::test]
+async fn bootstrap_admin_holds_every_permission() {
+    let service = common::service().await;
+
+    let token = service
+        .auth
+        .login(ADMIN_USERNAME.to_string(), ADMIN_PASSWORD.to_string(), None)
+        .await
+        .unwrap()
+        .token;
+    let principal = service.auth.validate_token(&token).await.unwrap();
+
+    let all = service.auth.list_permissions().await.unwrap();
+    assert_eq!(principal.permissions.len(), all.len());
+    assert!(principal.permissions.contains(permissions::ROLES_MANAGE));
+}
+
+#[tokio::test]
+async fn deactivation_and_forced_logout_revoke_existing_tokens() {
+    let service = common::service().await;
+    let user_id = register(&service, "alice").await;
+    let admin = admin_id(&service).await;
+
+    let token = token_for(&service, "alice").await;
+    service.auth.force_logout(admin, user_id).await.unwrap();
+    assert!(matches!(
+        service.auth.validate_token(&token).await,
+        Err(AuthError::TokenValidationFailed(_))
+    ));
+
+    let token = token_for(&service, "alice").await;
+    service.auth.deactivate_user(admin, user_id).await.unwrap();
+    assert!(service.auth.validate_token(&token).await.is_err());
+    assert!(matches!(
+        service
+            .auth
+            .login("alice".to_string(), PASSWORD.to_string(), None)
+            .await,
+        Err(AuthError::UserDeactivated)
+    ));
+
+    service.auth.reactivate_user(admin, user_id).await.unwrap();
+    token_for(&service, "alice").await;
+}
+
+#[tokio::test]
+async fn operators_cannot_lock_out_admins_or_themselves() {
+    let service = common::service().await;
+    let admin = admin_id(&service).await;
+    let frank = register(&service, "frank").await;
+    let grace = register(&service, "grace").await;
+
+    let operator = service
+        .auth
+        .create_role(
+            admin,
+            "operator".to_string(),
+            None,
+            vec![
+                permissions::USERS_DEACTIVATE.to_string(),
+                permissions::USERS_LOGOUT.to_string(),
+            ],
+        )
+        .await
+        .unwrap();
+    service
+        .auth
+        .assign_role(admin, frank, operator.role.id)
+        .await
+        .unwrap();
+
+    for target in [admin, frank] {
+        let logout = service.auth.force_logout(frank, target).await;
+        assert!(matches!(logout, Err(AuthError::Forbidden)));
+        let deactivate = service.auth.deactivate_user(frank, target).await;
+        assert!(matches!(deactivate, Err(AuthError::Forbidden)));
+    }
+    assert!(service.auth.get_user_by_id(admin).await.unwrap().is_active);
+
+    // Other users are fair game, and admins may act on anyone.
+    service.auth.force_logout(frank, grace).await.unwrap();
+    service.auth.deactivate_user(frank, grace).await.unwrap();
+    service.auth.reactivate_user(frank, grace).await.unwrap();
+    service.auth.force_logout(admin, frank).await.unwrap();
+    service.auth.deactivate_user(admin, frank).await.unwrap();
+}
+
+#[tokio::test]
+async fn custom_roles_grant_their_permissions() {
+    let service = common::service().await;
+    let user_id = register(&service, "moderator").await;
+    let admin = admin_id(&service).await;
+
+    let role = service
+        .auth
+        .create_role(
+            admin,
+            "auditor".to_string(),
+            Some("Read-only access".to_string()),
+            vec![permissions::USERS_READ.to_string()],
+        )
+        .await
+        .unwrap();
+    service
+        .auth
+        .assign_role(admin, user_id, role.role.id)
+        .await
+        .unwrap();
+
+    let token = token_for(&service, "moderator").await;
+    let principal = service.auth.validate_token(&token).await.unwrap();
+    assert_eq!(principal.role_id, role.role.id);
+    assert!(principal.permissions.contains(permissions::USERS_READ));
+    assert!(!principal.permissions.contains(permissions::ROLES_MANAGE));
+
+    let updated = service
+        .auth
+        .set_role_permissions(
+            admin,
+            role.role.id,
+            vec![permissions::ROLES_READ.to_string()],
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        updated.permissions,
+        vec![permissions::ROLES_READ.to_string()]
+    );
+}
+
+#[tokio::test]
+async fn role_management_rejects_invalid_changes() {
+    let service = common::service().await;
+    let admin = admin_id(&service).await;
+
+    let unknown = service
+        .auth
+        .create_role(admin, "x-role".to_string(), None, vec!["nope".to_string()])
+        .await;
+    assert!(matches!(unknown, Err(AuthError::UnknownPermission(name)) if name == "nope"));
+
+    let duplicate = service
+        .auth
+        .create_role(admin, "user".to_string(), None, Vec::new())
+        .await;
+    assert!(matches!(duplicate, Err(AuthError::RoleExists)));
+
+    let builtin = service
+        .auth
+        .set_role_permissions(admin, 0, Vec::new())
+        .await;
+    assert!(matches!(builtin, Err(AuthError::Forbidden)));
+
+    let missing = service
+        .auth
+        .set_role_permissions(admin, 999, Vec::new())
+        .await;
+    assert!(matches!(missing, Err(AuthError::RoleNotFound)));
+}
+
+#[tokio::test]
+async fn role_assigners_cannot_grant_more_than_they_hold() {
+    let service = common::service().await;
+    let admin = admin_id(&service).await;
+    let carol = register(&service, "carol").await;
+    let dave = register(&service, "dave").await;
+
+    let mut roles = HashMap::new();
+    for (name, granted) in [
+        (
+            "assigner",
+            vec![permissions::USERS_READ, permissions::USERS_ASSIGN_ROLE],
+        ),
+        ("auditor", vec![permissions::USERS_READ]),
+        ("role-manager", vec![permissions::ROLES_MANAGE]),
+    ] {
+        let role = service
+            .auth
+            .create_role(
+                admin,
+                name.to_string(),
+                None,
+                granted.into_iter().map(str::to_string).collect(),
+            )
+            .await
+            .unwrap();
+        roles.insert(name, role.role.id);
+    }
+    service
+        .auth
+        .assign_role(admin, carol, roles["assigner"])
+        .await
+        .unwrap();
+
+    let user = service
+        .auth
+        .assign_role(carol, dave, roles["auditor"])
+        .await
+        .unwrap();
+    assert_eq!(user.role_id, roles["auditor"]);
+
+    for (user_id, role_id) in [
+        (carol, 0),
+        (dave, 0),
+        (dave, roles["role-manager"]),
+        (admin, 1),
+    ] {
+        let result = service.auth.assign_role(carol, user_id, role_id).await;
+        assert!(matches!(result, Err(AuthError::Forbidden)));
+    }
+
+    service
+        .auth
+        .assign_role(admin, dave, roles["role-manager"])
+        .await
+        .unwrap();
+    let demote = service.auth.assign_role(carol, dave, 1).await;
+    assert!(matches!(demote, Err(AuthError::Forbidden)));
+}
+
+#[tokio::test]
+async fn role_managers_cannot_grant_more_than_they_hold() {
+    let service = common::service().await;
+    let admin = admin_id(&service).await;
+    let erin = register(&service, "erin").await;
+
+    let manager = service
+        .auth
+        .create_role(
+            admin,
+            "role-manager".to_string(),
+            None,
+            vec![
+                permissions::ROLES_MANAGE.to_string(),
+                permissions::USERS_READ.to_string(),
+            ],
+        )
+        .await
+        .unwrap()
+        .role
+        .id;
+    service
+        .auth
+        .assign_role(admin, erin, manager)
+        .await
+        .unwrap();
+
+    // Roles within what the manager holds are fine.
+    let auditor = service
+        .auth
+        .create_role(
+            erin,
+            "auditor".to_string(),
+            None,
+            vec![permissions::USERS_READ.to_string()],
+        )
+        .await
+        .unwrap()
+        .role
+        .id;
+    service
+        .auth
+        .set_role_permissions(erin, auditor, Vec::new())
+        .await
+        .unwrap();
+
+    let wider = vec![permissions::USERS_ASSIGN_ROLE.to_string()];
+    let created = service
+        .auth
+        .create_role(erin, "assigner".to_string(), None, wider.clone())
+        .await;
+    assert!(matches!(created, Err(AuthError::Forbidden)));
+    let widened = service
+        .auth
+        .set_role_permissions(erin, auditor, wider.clone())
+        .await;
+    assert!(matches!(widened, Err(AuthError::Forbidden)));
+
+    // Nor can a manager rewrite their own role, even within what it holds.
+    let own = service
+        .auth
+        .set_role_permissions(erin, manager, vec![permissions::ROLES_MANAGE.to_string()])
+        .await;
+    assert!(matches!(own, Err(AuthError::Forbidden)));
+
+    // Admins may grant anything.
+    let assigner = service
+        .auth
+        .create_role(admin, "assigner".to_string(), None, wider.clone())
+        .await
+        .unwrap();
+    assert_eq!(assigner.permissions, wider);
+    let role = service
+        .auth
+        .set_role_permissions(admin, manager, wider.clone())
+        .await
+        .unwrap();
+    assert_eq!(role.permissions, wider);
+}
+
+#[tokio::test]
+async fn list_users_searches_and_paginates() {
+    let service = common::service().await;
+    for name in ["carol", "alice", "bob", "alicia"] {
+        register(&service, name).await;
+    }
+
+    let page = service
+        .auth
+        .list_users(Some("ALI".to_string()), 10, 0)
+        .await
+        .unwrap();
+    let names: Vec<_> = page.users.iter().map(|u| u.username.as_str()).collect();
+    assert_eq!(names, ["alice", "alicia"]);
+    assert_eq!(page.total, 2);
+
+    let page = service.auth.list_users(None, 2, 1).await.unwrap();
+    let names: Vec<_> = page.users.iter().map(|u| u.username.as_str()).collect();
+    assert_eq!(names, ["alice", "alicia"]);
+    assert_eq!(page.total, 5);
+
+    // Out-of-range paging is clamped rather than passed to the database.
+    let page = service.auth.list_users(None, -5, -3).await.unwrap();
+    assert_eq!((page.limit, page.offset), (1, 0));
+    assert_eq!(page.users.len(), 1);
+    let page = service.auth.list_users(None, i64::MAX, 0).await.unwrap();
+    assert_eq!(page.limit, 200);
+    assert_eq!(page.users.len(), 5);
+}
+
+#[tokio::test]
+async fn creating_a_chat_makes_the_creator_a_member() {
+    let service = common::service().await;
+    let alice = register(&service, "alice").await;
+
+    let chat = service
+        .chat
+        .create_chat("Team".to_string(), alice)
+        .await
+        .unwrap();
+
+    let chats = service.chat.get_user_chats(alice).await.unwrap();
+    assert_eq!(chats.len(), 1);
+    assert_eq!(chats[0].id, chat.id);
+
+    let members = service
+        .chat
+        .get_chat_members(chat.id, alice, MemberFilter::default(), 50, 0)
+        .await
+        .unwrap();
+    assert_eq!(members.len(), 1);
+    assert_eq!(members[0].username, "alice");
+}
+
+#[tokio::test]
+async fn chat_names_are_validated() {
+    let service = common::service().await;
+    let alice = register(&service, "alice").await;
+
+    let empty = service.chat.create_chat("  ".to_string(), alice).await;
+    assert!(matches!(empty, Err(ChatError::InvalidChatName(_))));
+
+    let long = service.chat.create_chat("x".repeat(256), alice).await;
+    assert!(matches!(long, Err(ChatError::InvalidChatName(_))));
+}
+
+#[tokio::test]
+async fn invitations_require_membership_and_a_known_user() {
+    let service = common::service().await;
+    let alice = register(&service, "alice").await;
+    let bob = register(&service, "bob").await;
+    let chat = service
+        .chat
+        .create_chat("Team".to_string(), alice)
+        .await
+        .unwrap();
+
+    let outsider = service
+        .chat
+        .invite_user_by_username(chat.id, "alice".to_string(), bob)
+        .await;
+    assert!(matches!(outsider, Err(ChatError::NotMember)));
+
+    service
+        .chat
+        .invite_user_by_username(chat.id, "bob".to_string(), alice)
+        .await
+        .unwrap();
+
+    let again = service
+        .chat
+        .invite_user_by_username(chat.id, "bob".to_string(), alice)
+        .await;
+    assert!(matches!(again, Err(ChatError::AlreadyMember)));
+
+    let unknown = service
+        .chat
+        .invite_user_by_username(chat.id, "nobody".to_string(), alice)
+        .await;
+    assert!(matches!(unknown, Err(ChatError::UserNotFound(name)) if name == "nobody"));
+}
+
+#[tokio::test]
+async fn members_are_listed_with_filters_and_pagination() {
+    let service = common::service().await;
+    let alice = register(&service, "alice").await;
+    let chat = service
+        .chat
+        .create_chat("Team".to_string(), alice)
+        .await
+        .unwrap();
+    for name in ["bob", "bobby", "carol"] {
+        register(&service, name).await;
+        service
+            .chat
+            .invite_user_by_username(chat.id, name.to_string(), alice)
+            .await
+            .unwrap();
+    }
+    service
+        .chat
+        .invite_user_by_username(chat.id, ADMIN_USERNAME.to_string(), alice)
+        .await
+        .unwrap();
+
+    let bobs = service
+        .chat
+        .get_chat_members(
+            chat.id,
+            alice,
+            MemberFilter {
+                username_prefix: Some("BOB".to_string()),
+                ..MemberFilter::default()
+            },
+            50,
+            0,
+        )
+        .await
+        .unwrap();
+    let names: Vec<_> = bobs.iter().map(|m| m.username.as_str()).collect();
+    assert_eq!(names, ["bob", "bobby"]);
+    assert!(bobs.iter().all(|m| m.invited_by == Some(alice)));
+
+    let admins = service
+        .chat
+        .get_chat_members(
+            chat.id,
+            alice,
+            MemberFilter {
+                role_id: Some(0),
+                ..MemberFilter::default()
+            },
+            50,
+            0,
+        )
+        .await
+        .unwrap();
+    assert_eq!(admins.len(), 1);
+    assert_eq!(admins[0].username, ADMIN_USERNAME);
+
+    let page = service
+        .chat
+        .get_chat_members(chat.id, alice, MemberFilter::default(), 2, 1)
+        .await
+        .unwrap();
+    assert_eq!(page.len(), 2);
+    assert_ne!(page[0].user_id, alice);
+
+    let clamped = service
+        .chat
+        .get_chat_members(chat.id, alice, MemberFilter::default(), -1, -10)
+        .await
+        .unwrap();
+    assert_eq!(clamped.len(), 1);
+    let all = service
+        .chat
+        .get_chat_members(chat.id, alice, MemberFilter::default(), i64::MAX, 0)
+        .await
+        .unwrap();
+    assert_eq!(all.len(), 5);
+}
+
+#[tokio::test]
+async fn only_members_can_read_and_write_messages() {
+    let service = common::service().await;
+    let alice = register(&service, "alice").await;
+    let mallory = register(&service, "mallory").await;
+    let chat = service
+        .chat
+        .create_chat("Team".to_string(), alice)
+        .await
+        .unwrap();
+
+    let mallory_phone = device(&service, mallory).await;
+
+    let send = service
+        .chat
+        .send_message(
+            chat.id,
+            mallory,
+            mallory_phone,
+            Envelopes(HashMap::new()),
+            MessageOptions::default(),
+        )
+        .await;
+    assert!(matches!(send, Err(ChatError::NotMember)));
+
+    let read = service
+        .chat
+        .get_messages(chat.id, mallory, mallory_phone, 50, 0)
+        .await;
+    assert!(matches!(read, Err(ChatError::NotMember)));
+
+    let get = service.chat.get_chat(chat.id, mallory).await;
+    assert!(matches!(get, Err(ChatError::NotMember)));
+}
+
+#[tokio::test]
+async fn messages_are_listed_newest_first() {
+    let service = common::service().await;
+    let alice = register(&service, "alice").await;
+    let phone = device(&service, alice).await;
+    let laptop = device(&service, alice).await;
+    let chat = service
+        .chat
+        .create_chat("Team".to_string(), alice)
+        .await
+        .unwrap();
+
+    for i in 0..5 {
+        let envelopes = HashMap::from([(laptop, format!("m{}", i))]);
+        service
+            .chat
+            .send_message(
+                chat.id,
+                alice,
+                phone,
+                Envelopes(envelopes),
+                MessageOptions::default(),
+            )
+            .await
+            .unwrap();
+    }
+
+    let page = service
+        .chat
+        .get_messages(chat.id, alice, laptop, 2, 1)
+        .await
+        .unwrap();
+    let contents: Vec<_> = page
+        .iter()
+        .map(|m| m.encrypted_content.as_deref().unwrap())
+        .collect();
+    assert_eq!(contents, ["m3", "m2"]);
+}
+
+#[tokio::test]
+async fn failed_transactions_leave_no_partial_writes() {
+    let repo = Repository::in_memory();
+    let service = common::service_with(repo.clone());
+    let alice = register(&service, "alice").await;
+
+    let result: Result<(), RepositoryError> = repo.transaction(|tx| {
+        let chat = tx.chat.create_chat("Team".to_string(), alice)?;
+        tx.chat.add_member(chat.id, alice, None)?;
+        tx.chat.add_member(chat.id, alice, None)?;
+        Ok(())
+    });
+
+    assert!(matches!(result, Err(RepositoryError::Conflict(_))));
+    assert!(repo.chat.get_user_chats(alice).unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn missing_references_are_not_reported_as_duplicates() {
+    let repo = Repository::in_memory();
+    let service = common::service_with(repo.clone());
+    let alice = register(&service, "alice").await;
+    let chat = repo.chat.create_chat("Team".to_string(), alice).unwrap();
+
+    let result = repo.chat.add_member(chat.id, uuid::Uuid::new_v4(), None);
+    assert!(matches!(result, Err(RepositoryError::MissingReference(_))));
+    let result = repo.chat.add_member(uuid::Uuid::new_v4(), alice, None);
+    assert!(matches!(result, Err(RepositoryError::MissingReference(_))));
+
+    repo.chat.add_member(chat.id, alice, None).unwrap();
+    let result = repo.chat.add_member(chat.id, alice, None);
+    assert!(matches!(result, Err(RepositoryError::Conflict(_))));
+}
+
+#[tokio::test]
+async fn concurrent_writes_fail_the_transaction_instead_of_being_lost() {
+    let repo = Repository::in_memory();
+    let service = common::service_with(repo.clone());
+    let alice = register(&service, "alice").await;
+
+    let mut inside = None;
+    let mut outside = None;
+    let result: Result<(), RepositoryError> = repo.transaction(|tx| {
+        inside = Some(tx.chat.create_chat("Inside".to_string(), alice)?.id);
+        outside = Some(repo.chat.create_chat("Outside".to_string(), alice)?.id);
+        Ok(())
+    });
+
+    assert!(matches!(result, Err(RepositoryError::Conflict(_))));
+    assert!(
+        repo.chat
+            .find_chat_by_id(inside.unwrap())
+            .unwrap()
+            .is_none()
+    );
+    assert!(
+        repo.chat
+            .find_chat_by_id(outside.unwrap())
+            .unwrap()
+            .is_some()
+    );
+}
+
+fn key(byte: u8) -> String {
+    base64::engine::general_purpose::STANDARD.encode([byte; 32])
+}
+
+fn signed_prekey() -> SignedPrekeyInfo {
+    SignedPrekeyInfo {
+        key_id: 1,
+        public_key: key(2),
+        signature: base64::engine::general_purpose::STANDARD.encode([3; 64]),
+    }
+}
+
+fn prekeys(ids: std::ops::Range<i32>) -> Vec<OneTimePrekeyInfo> {
+    ids.map(|key_id| OneTimePrekeyInfo {
+        key_id,
+        public_key: key(key_id as u8),
+    })
+    .collect()
+}
+
+#[tokio::test]
+async fn prekey_bundles_consume_one_time_prekeys_in_order() {
+    let service = common::service().await;
+    let alice = register(&service, "alice").await;
+    let phone = device(&service, alice).await;
+
+    let missing = service.keys.fetch_bundle(alice).await;
+    assert!(matches!(missing, Err(KeyError::KeysNotFound)));
+
+    service
+        .keys
+        .publish_identity(alice, phone, key(1), signed_prekey())
+        .await
+        .unwrap();
+    let status = service
+        .keys
+        .upload_prekeys(alice, phone, prekeys(10..13))
+        .await
+        .unwrap();
+    assert_eq!(status.remaining, 3);
+    assert!(!status.needs_replenish);
+
+    let mut bundle = service.keys.fetch_bundle(alice).await.unwrap();
+    assert_eq!(bundle.devices.len(), 1);
+    let bundle = bundle.devices.remove(0);
+    assert_eq!(bundle.device_id, phone);
+    assert_eq!(bundle.identity_key, key(1));
+    assert_eq!(bundle.signed_prekey.key_id, 1);
+    assert_eq!(bundle.one_time_prekey.unwrap().key_id, 10);
+
+    let mut bundle = service.keys.fetch_bundle(alice).await.unwrap();
+    assert_eq!(bundle.devices.remove(0).one_time_prekey.unwrap().key_id, 11);
+    let status = service.keys.prekey_status(alice, phone).await.unwrap();
+    assert_eq!(status.remaining, 1);
+    assert!(status.needs_replenish);
+
+    service.keys.fetch_bundle(alice).await.unwrap();
+    let exhausted = service.keys.fetch_bundle(alice).await.unwrap();
+    assert!(exhausted.devices[0].one_time_prekey.is_none());
+}
+
+#[tokio::test]
+async fn prekey_uploads_are_validated() {
+    let service = common::service().await;
+    let alice = register(&service, "alice").await;
+    let phone = device(&service, alice).await;
+
+    let unpublished = service
+        .keys
+        .upload_prekeys(alice, phone, prekeys(0..1))
+        .await;
+    assert!(matches!(unpublished, Err(KeyError::KeysNotFound)));
+
+    let bad_key = service
+        .keys
+        .publish_identity(alice, phone, "not base64!".to_string(), signed_prekey())
+        .await;
+    assert!(matches!(bad_key, Err(KeyError::InvalidKey(_))));
+
+    service
+        .keys
+        .publish_identity(alice, phone, key(1), signed_prekey())
+        .await
+        .unwrap();
+
+    let too_many = service
+        .keys
+        .upload_prekeys(alice, phone, prekeys(0..6))
+        .await;
+    assert!(matches!(too_many, Err(KeyError::TooManyPrekeys(_))));
+
+    service
+        .keys
+        .upload_prekeys(alice, phone, prekeys(0..5))
+        .await
+        .unwrap();
+    let duplicate = service
+        .keys
+        .upload_prekeys(alice, phone, prekeys(4..5))
+        .await;
+    assert!(matches!(duplicate, Err(KeyError::DuplicatePrekey)));
+
+    let over_stock = service
+        .keys
+        .upload_prekeys(alice, phone, prekeys(5..9))
+        .await;
+    assert!(matches!(over_stock, Err(KeyError::TooManyPrekeys(_))));
+    assert_eq!(
+        service
+            .keys
+            .prekey_status(alice, phone)
+            .await
+            .unwrap()
+            .remaining,
+        5
+    );
+}
+
+#[tokio::test]
+async fn changing_the_identity_key_discards_old_prekeys() {
+    let service = common::service().await;
+    let alice = register(&service, "alice").await;
+    let phone = device(&service, alice).await;
+    service
+        .keys
+        .publish_identity(alice, phone, key(1), signed_prekey())
+        .await
+        .unwrap();
+    service
+        .keys
+        .upload_prekeys(alice, phone, prekeys(0..3))
+        .await
+        .unwrap();
+
+    let rotated_signed_prekey = service
+        .keys
+        .publish_identity(alice, phone, key(1), signed_prekey())
+        .await
+        .unwrap();
+    assert_eq!(rotated_signed_prekey.remaining, 3);
+
+    let new_identity = service
+        .keys
+        .publish_identity(alice, phone, key(9), signed_prekey())
+        .await
+        .unwrap();
+    assert_eq!(new_identity.remaining, 0);
+}
+
+#[tokio::test]
+async fn keys_are_published_per_device_and_bundled_together() {
+    let service = common::service().await;
+    let alice = register(&service, "alice").await;
+    let bob = register(&service, "bob").await;
+    let phone = device(&service, alice).await;
+    let laptop = device(&service, alice).await;
+    let bob_phone = device(&service, bob).await;
+
+    let foreign = service
+        .keys
+        .publish_identity(alice, bob_phone, key(1), signed_prekey())
+        .await;
+    assert!(matches!(foreign, Err(KeyError::UnknownDevice)));
+
+    for (device_id, identity) in [(phone, key(1)), (laptop, key(2))] {
+        service
+            .keys
+            .publish_identity(alice, device_id, identity, signed_prekey())
+            .await
+            .unwrap();
+    }
+    service
+        .keys
+        .upload_prekeys(alice, laptop, prekeys(0..2))
+        .await
+        .unwrap();
+
+    let bundle = service.keys.fetch_bundle(alice).await.unwrap();
+    let by_device: HashMap<_, _> = bundle
+        .devices
+        .iter()
+        .map(|d| (d.device_id, d.one_time_prekey.as_ref().map(|k| k.key_id)))
+        .collect();
+    assert_eq!(by_device, HashMap::from([(phone, None), (laptop, Some(0))]));
+}
+
+fn decode_hash(value: &str) -> Hash {
+    base64::engine::general_purpose::STANDARD
+        .decode(value)
+        .unwrap()
+        .try_into()
+        .unwrap()
+}
+
+fn hex(hash: &Hash) -> String {
+    hash.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[test]
+fn merkle_proofs_verify_for_every_tree_size() {
+    assert_eq!(
+        hex(&merkle::empty_root()),
+        "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+    );
+    assert_eq!(
+        hex(&merkle::leaf_hash(b"")),
+        "6e340b9cffb37a989ca544e6bb780a2c78901d3fb33738768511a30617afa01d"
+    );
+
+    let leaves: Vec<Hash> = (0..9u8).map(|i| merkle::leaf_hash(&[i])).collect();
+
+    for n in 1..=leaves.len() {
+        let tree = &leaves[..n];
+        let root = merkle::root(tree);
+
+        for m in 0..n {
+            let path = merkle::inclusion_proof(m, tree);
+            assert!(merkle::verify_inclusion(
+                m as u64, n as u64, &tree[m], &path, &root
+            ));
+            assert!(!merkle::verify_inclusion(
+                m as u64,
+                n as u64,
+                &merkle::leaf_hash(b"x"),
+                &path,
+                &root
+            ));
+        }
+
+        for m in 1..=n {
+            let proof = merkle::consistency_proof(m, tree);
+            let old_root = merkle::root(&tree[..m]);
+            assert!(merkle::verify_consistency(
+                m as u64, n as u64, &old_root, &root, &proof
+            ));
+            if m < n {
+                assert!(!merkle::verify_consistency(
+                    m as u64,
+                    n as u64,
+                    &merkle::leaf_hash(b"x"),
+                    &root,
+                    &proof
+                ));
+            }
+        }
+    }
+}
+
+#[test]
+fn stored_subtrees_give_the_same_roots_and_proofs() {
+    let leaves: Vec<Hash> = (0..33u8).map(|i| merkle::leaf_hash(&[i])).collect();
+    let mut stored: HashMap<merkle::Node, Hash> = HashMap::new();
+    let lookup = |stored: &HashMap<merkle::Node, Hash>, range| {
+        let roots: Vec<Hash> = merkle::subtree_nodes(range)
+            .iter()
+            .map(|node| stored[node])
+            .collect();
+        merkle::subtree_root(&roots)
+    };
+
+    for (index, leaf) in leaves.iter().enumerate() {
+        let n = index + 1;
+
+        // An append reads the frontier: one sibling per trailing one bit.
+        let siblings: Vec<Hash> = merkle::append_siblings(index as u64)
+            .iter()
+            .map(|node| stored[node])
+            .collect();
+        assert_eq!(siblings.len(), index.trailing_ones() as usize);
+        stored.extend(merkle::append(index as u64, *leaf, &siblings));
+
+        let tree = &leaves[..n];
+        assert_eq!(
+            merkle::subtree_nodes(0..n as u64).len(),
+            n.count_ones() as usize
+        );
+        assert_eq!(lookup(&stored, 0..n as u64), merkle::root(tree));
+
+        for m in 0..n {
+            let path: Vec<Hash> = merkle::inclusion_ranges(m as u64, n as u64)
+                .into_iter()
+                .map(|range| lookup(&stored, range))
+                .collect();
+            assert_eq!(path, merkle::inclusion_proof(m, tree));
+        }
+
+        for m in 1..=n {
+            let proof: Vec<Hash> = merkle::consistency_ranges(m as u64, n as u64)
+                .into_iter()
+                .map(|range| lookup(&stored, range))
+                .collect();
+            assert_eq!(proof, merkle::consistency_proof(m, tree));
+        }
+    }
+}
+
+#[tokio::test]
+async fn identity_keys_are_logged_with_verifiable_proofs() {
+    let service = common::service().await;
+    let alice = register(&service, "alice").await;
+    let bob = register(&service, "bob").await;
+    let phone = device(&service, alice).await;
+    let laptop = device(&service, alice).await;
+    let bob_phone = device(&service, bob).await;
+
+    assert!(matches!(
+        service.keys.tree_head().await,
+        Err(KeyError::LogEmpty)
+    ));
+
+    for (user_id, device_id, identity) in [
+        (alice, phone, key(1)),
+        (alice, laptop, key(2)),
+        (bob, bob_phone, key(3)),
+        (alice, phone, key(1)),
+        (alice, phone, key(4)),
+    ] {
+        service
+            .keys
+            .publish_identity(user_id, device_id, identity, signed_prekey())
+            .await
+            .unwrap();
+    }
+
+    // Re-publishing an unchanged key does not add a leaf.
+    let head = service.keys.tree_head().await.unwrap();
+    assert_eq!(head.tree_size, 4);
+
+    let public_key = base64::engine::general_purpose::STANDARD
+        .decode(service.keys.log_public_key())
+        .unwrap();
+    let root = decode_hash(&head.root_hash);
+    let signature = base64::engine::general_purpose::STANDARD
+        .decode(&head.signature)
+        .unwrap();
+    assert!(verify_tree_head(
+        &public_key,
+        head.timestamp,
+        head.tree_size,
+        &root,
+        &signature
+    ));
+    assert!(!verify_tree_head(
+        &public_key,
+        head.timestamp,
+        3,
+        &root,
+        &signature
+    ));
+
+    let proofs = service.keys.prove_keys(alice, None).await.unwrap();
+    let mut proven: Vec<_> = proofs
+        .proofs
+        .iter()
+        .map(|proof| (proof.entry.device_id, proof.entry.leaf_index))
+        .collect();
+    proven.sort_by_key(|(_, index)| *index);
+    assert_eq!(proven, vec![(laptop, 1), (phone, 3)]);
+
+    for proof in &proofs.proofs {
+        let identity = base64::engine::general_purpose::STANDARD
+            .decode(&proof.entry.identity_key)
+            .unwrap();
+        let leaf = merkle::leaf_hash(&leaf_input(
+            proof.entry.user_id,
+            proof.entry.device_id,
+            proof.entry.timestamp,
+            &identity,
+        ));
+        assert_eq!(leaf, decode_hash(&proof.entry.leaf_hash));
+
+        let path: Vec<Hash> = proof.audit_path.iter().map(|h| decode_hash(h)).collect();
+        assert!(merkle::verify_inclusion(
+            proof.entry.leaf_index as u64,
+            head.tree_size as u64,
+            &leaf,
+            &path,
+            &root
+        ));
+    }
+
+    // In the tree of two leaves only the laptop's current key is present.
+    let older = service.keys.prove_keys(alice, Some(2)).await.unwrap();
+    assert_eq!(older.tree_head.tree_size, 2);
+    assert_eq!(older.proofs.len(), 1);
+    assert_eq!(older.proofs[0].entry.device_id, laptop);
+    assert!(matches!(
+        service.keys.prove_keys(bob, Some(2)).await,
+        Err(KeyError::KeyNotLogged)
+    ));
+
+    let consistency = service.keys.consistency_proof(2, None).await.unwrap();
+    let proof: Vec<Hash> = consistency.proof.iter().map(|h| decode_hash(h)).collect();
+    assert!(merkle::verify_consistency(
+        2,
+        4,
+        &decode_hash(&older.tree_head.root_hash),
+        &root,
+        &proof
+    ));
+
+    // A tree of three leaves has an incomplete right subtree.
+    let odd = service.keys.prove_keys(alice, Some(3)).await.unwrap();
+    let odd_root = decode_hash(&odd.tree_head.root_hash);
+    let laptop_proof = &odd.proofs[0];
+    let path: Vec<Hash> = laptop_proof
+        .audit_path
+        .iter()
+        .map(|h| decode_hash(h))
+        .collect();
+    assert!(merkle::verify_inclusion(
+        1,
+        3,
+        &decode_hash(&laptop_proof.entry.leaf_hash),
+        &path,
+        &odd_root
+    ));
+    let consistency = service.keys.consistency_proof(3, None).await.unwrap();
+    let proof: Vec<Hash> = consistency.proof.iter().map(|h| decode_hash(h)).collect();
+    assert!(merkle::verify_consistency(3, 4, &odd_root, &root, &proof));
+
+    for (first, second) in [(0, None), (3, Some(2)), (1, Some(5))] {
+        assert!(matches!(
+            service.keys.consistency_proof(first, second).await,
+            Err(KeyError::InvalidTreeSize(_))
+        ));
+    }
+
+    let entries = service.keys.get_log_entries(1, 2).await.unwrap();
+    let indexes: Vec<_> = entries.iter().map(|e| e.leaf_index).collect();
+    assert_eq!(indexes, vec![1, 2]);
+}
+
+#[tokio::test]
+async fn identity_key_changes_are_announced_in_chats() {
+    let service = common::service().await;
+    let alice = register(&service, "alice").await;
+    let bob = register(&service, "bob").await;
+    let alice_phone = device(&service, alice).await;
+    let bob_phone = device(&service, bob).await;
+    let chat = service
+        .chat
+        .create_chat("Team".to_string(), alice)
+        .await
+        .unwrap();
+    service
+        .chat
+        .invite_user_by_username(chat.id, "bob".to_string(), alice)
+        .await
+        .unwrap();
+
+    for identity in [key(1), key(1), key(2)] {
+        service
+            .keys
+            .publish_identity(alice, alice_phone, identity, signed_prekey())
+            .await
+            .unwrap();
+    }
+
+    let messages = service
+        .chat
+        .get_messages(chat.id, bob, bob_phone, 50, 0)
+        .await
+        .unwrap();
+    assert_eq!(messages.len(), 3);
+    assert_eq!(messages[0].kind, "identity_key_changed");
+    assert_eq!(messages[0].sender_id, alice);
+    assert!(messages[0].encrypted_content.is_none());
+
+    let payload = messages[0].payload.as_ref().unwrap();
+    assert_eq!(payload["device_id"], alice_phone.to_string());
+    assert_eq!(payload["leaf_index"], 1);
+}
+
+#[tokio::test]
+async fn envelopes_must_match_the_chats_devices() {
+    let service = common::service().await;
+    let alice = register(&service, "alice").await;
+    let bob = register(&service, "bob").await;
+    let phone = device(&service, alice).await;
+    let laptop = device(&service, alice).await;
+    let bob_phone = device(&service, bob).await;
+    let chat = service
+        .chat
+        .create_chat("Team".to_string(), alice)
+        .await
+        .unwrap();
+    service
+        .chat
+        .invite_user_by_username(chat.id, "bob".to_string(), alice)
+        .await
+        .unwrap();
+
+    let foreign = service
+        .chat
+        .send_message(
+            chat.id,
+            alice,
+            bob_phone,
+            Envelopes(HashMap::new()),
+            MessageOptions::default(),
+        )
+        .await;
+    assert!(matches!(foreign, Err(ChatError::UnknownDevice)));
+
+    let stale_device = uuid::Uuid::new_v4();
+    let mismatch = service
+        .chat
+        .send_message(
+            chat.id,
+            alice,
+            phone,
+            Envelopes(HashMap::from([
+                (laptop, "l".to_string()),
+                (stale_device, "x".to_string()),
+            ])),
+            MessageOptions::default(),
+        )
+        .await;
+    match mismatch {
+        Err(ChatError::DeviceMismatch { missing, stale }) => {
+            assert_eq!(missing, [bob_phone]);
+            assert_eq!(stale, [stale_device]);
+        }
+        other => panic!("expected a device mismatch, got {:?}", other.err()),
+    }
+
+    let sent = service
+        .chat
+        .send_message(
+            chat.id,
+            alice,
+            phone,
+            Envelopes(HashMap::from([
+                (laptop, "l".to_string()),
+                (bob_phone, "b".to_string()),
+            ])),
+            MessageOptions::default(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(sent.sender_device_id, Some(phone));
+    assert!(sent.encrypted_content.is_none());
+
+    for (user, device_id, expected) in [(alice, laptop, "l"), (bob, bob_phone, "b")] {
+        let messages = service
+            .chat
+            .get_messages(chat.id, user, device_id, 50, 0)
+            .await
+            .unwrap();
+        assert_eq!(messages[0].encrypted_content.as_deref(), Some(expected));
+    }
+
+    // The sending device has no envelope of its own.
+    let own = service
+        .chat
+        .get_messages(chat.id, alice, phone, 50, 0)
+        .await
+        .unwrap();
+    assert!(own.iter().all(|m| m.kind != "text"));
+}
+
+#[tokio::test]
+async fn removing_a_device_drops_it_from_chats() {
+    let service = common::service().await;
+    let alice = register(&service, "alice").await;
+    let bob = register(&service, "bob").await;
+    let phone = device(&service, alice).await;
+    let laptop = device(&service, alice).await;
+    let chat = service
+        .chat
+        .create_chat("Team".to_string(), alice)
+        .await
+        .unwrap();
+
+    let foreign = service.devices.remove_device(bob, laptop).await;
+    assert!(matches!(foreign, Err(DeviceError::UnknownDevice)));
+
+    service.devices.remove_device(alice, laptop).await.unwrap();
+    assert_eq!(service.devices.list_devices(alice).await.unwrap().len(), 1);
+
+    let stale = service
+        .chat
+        .send_message(
+            chat.id,
+            alice,
+            phone,
+            Envelopes(HashMap::from([(laptop, "l".to_string())])),
+            MessageOptions::default(),
+        )
+        .await;
+    assert!(matches!(stale, Err(ChatError::DeviceMismatch { .. })));
+
+    let read = service
+        .chat
+        .get_messages(chat.id, alice, laptop, 50, 0)
+        .await;
+    assert!(matches!(read, Err(ChatError::UnknownDevice)));
+}
+
+#[tokio::test]
+async fn device_registration_is_validated_and_limited() {
+    let service = common::service().await;
+    let alice = register(&service, "alice").await;
+
+    let blank = service
+        .devices
+        .register_device(alice, "  ".to_string())
+        .await;
+    assert!(matches!(blank, Err(DeviceError::InvalidDeviceName(_))));
+
+    for _ in 0..10 {
+        device(&service, alice).await;
+    }
+    let over = service
+        .devices
+        .register_device(alice, "One more".to_string())
+        .await;
+    assert!(matches!(over, Err(DeviceError::TooManyDevices(10))));
+}
+
+#[tokio::test]
+async fn membership_changes_rekey_the_chat() {
+    let service = common::service().await;
+    let alice = register(&service, "alice").await;
+    let bob = register(&service, "bob").await;
+    let carol = register(&service, "carol").await;
+    let alice_phone = device(&service, alice).await;
+    let bob_phone = device(&service, bob).await;
+    let carol_phone = device(&service, carol).await;
+    let chat = service
+        .chat
+        .create_chat("Team".to_string(), alice)
+        .await
+        .unwrap();
+    assert_eq!(chat.epoch, 0);
+    for username in ["bob", "carol"] {
+        service
+            .chat
+            .invite_user_by_username(chat.id, username.to_string(), alice)
+            .await
+            .unwrap();
+    }
+
+    let keys = HashMap::from([
+        (alice_phone, "for alice".to_string()),
+        (carol_phone, "for carol".to_string()),
+    ]);
+    let stale = service
+        .chat
+        .distribute_sender_key(chat.id, bob, bob_phone, 1, keys.clone())
+        .await;
+    assert!(matches!(stale, Err(ChatError::StaleEpoch { current: 2 })));
+    service
+        .chat
+        .distribute_sender_key(chat.id, bob, bob_phone, 2, keys)
+        .await
+        .unwrap();
+
+    let bundle = service
+        .chat
+        .get_sender_keys(chat.id, carol, carol_phone, None)
+        .await
+        .unwrap();
+    assert_eq!(bundle.epoch, 2);
+    assert_eq!(bundle.sender_keys.len(), 1);
+    assert_eq!(bundle.sender_keys[0].sender_device_id, bob_phone);
+    assert_eq!(bundle.sender_keys[0].ciphertext, "for carol");
+
+    let group_message = |epoch| MessageBody::SenderKey {
+        epoch,
+        ciphertext: "group".to_string(),
+    };
+    service
+        .chat
+        .send_message(
+            chat.id,
+            bob,
+            bob_phone,
+            group_message(2),
+            MessageOptions::default(),
+        )
+        .await
+        .unwrap();
+    let messages = service
+        .chat
+        .get_messages(chat.id, alice, alice_phone, 50, 0)
+        .await
+        .unwrap();
+    assert_eq!(messages[0].encrypted_content.as_deref(), Some("group"));
+    assert_eq!(messages[0].epoch, Some(2));
+
+    service
+        .chat
+        .remove_member(chat.id, alice, false, carol)
+        .await
+        .unwrap();
+
+    let rekeys = service.chat.get_rekeys(chat.id, bob, 1).await.unwrap();
+    let rekeys: Vec<_> = rekeys
+        .iter()
+        .map(|r| (r.epoch, r.reason.as_str(), r.user_id))
+        .collect();
+    assert_eq!(
+        rekeys,
+        [(2, "invite", Some(carol)), (3, "kick", Some(carol))]
+    );
+
+    let after_kick = service
+        .chat
+        .send_message(
+            chat.id,
+            bob,
+            bob_phone,
+            group_message(2),
+            MessageOptions::default(),
+        )
+        .await;
+    assert!(matches!(
+        after_kick,
+        Err(ChatError::StaleEpoch { current: 3 })
+    ));
+    let removed = service
+        .chat
+        .get_sender_keys(chat.id, carol, carol_phone, Some(2))
+        .await;
+    assert!(matches!(removed, Err(ChatError::NotMember)));
+
+    // Earlier epochs stay readable for the members who remain.
+    let alice_keys = service
+        .chat
+        .get_sender_keys(chat.id, alice, alice_phone, Some(2))
+        .await
+        .unwrap();
+    assert_eq!(alice_keys.sender_keys.len(), 1);
+}
+
+#[tokio::test]
+async fn only_the_creator_or_a_moderator_removes_members() {
+    let service = common::service().await;
+    let alice = register(&service, "alice").await;
+    let bob = register(&service, "bob").await;
+    let carol = register(&service, "carol").await;
+    let dave = register(&service, "dave").await;
+    let chat = service
+        .chat
+        .create_chat("Team".to_string(), alice)
+        .await
+        .unwrap();
+    for username in ["bob", "carol"] {
+        service
+            .chat
+            .invite_user_by_username(chat.id, username.to_string(), alice)
+            .await
+            .unwrap();
+    }
+
+    let by_member = service.chat.remove_member(chat.id, bob, false, carol).await;
+    assert!(matches!(by_member, Err(ChatError::Forbidden(_))));
+
+    let creator = service.chat.remove_member(chat.id, bob, true, alice).await;
+    assert!(matches!(creator, Err(ChatError::Forbidden(_))));
+
+    let stranger = service
+        .chat
+        .remove_member(chat.id, alice, false, dave)
+        .await;
+    assert!(matches!(stranger, Err(ChatError::MemberNotFound)));
+
+    service
+        .chat
+        .remove_member(chat.id, dave, true, carol)
+        .await
+        .unwrap();
+
+    service.chat.leave_chat(chat.id, bob).await.unwrap();
+    let again = service.chat.leave_chat(chat.id, bob).await;
+    assert!(matches!(again, Err(ChatError::NotMember)));
+
+    let chat = service.chat.get_chat(chat.id, alice).await.unwrap();
+    assert_eq!(chat.epoch, 4);
+}
+
+#[tokio::test]
+async fn membership_and_name_changes_are_posted_as_notices() {
+    let service = common::service().await;
+    let alice = register(&service, "alice").await;
+    let bob = register(&service, "bob").await;
+    let carol = register(&service, "carol").await;
+    let alice_phone = device(&service, alice).await;
+    let chat = service
+        .chat
+        .create_chat("Team".to_string(), alice)
+        .await
+        .unwrap();
+    for username in ["bob", "carol"] {
+        service
+            .chat
+            .invite_user_by_username(chat.id, username.to_string(), alice)
+            .await
+            .unwrap();
+    }
+
+    let by_member = service
+        .chat
+        .rename_chat(chat.id, bob, false, "Mine".to_string())
+        .await;
+    assert!(matches!(by_member, Err(ChatError::Forbidden(_))));
+    let empty = service
+        .chat
+        .rename_chat(chat.id, alice, false, " ".to_string())
+        .await;
+    assert!(matches!(empty, Err(ChatError::InvalidChatName(_))));
+
+    let renamed = service
+        .chat
+        .rename_chat(chat.id, alice, false, "Core Team".to_string())
+        .await
+        .unwrap();
+    assert_eq!(renamed.name, "Core Team");
+    service
+        .chat
+        .rename_chat(chat.id, alice, false, "Core Team".to_string())
+        .await
+        .unwrap();
+
+    service
+        .chat
+        .remove_member(chat.id, alice, false, carol)
+        .await
+        .unwrap();
+    service.chat.leave_chat(chat.id, bob).await.unwrap();
+
+    let messages = service
+        .chat
+        .get_messages(chat.id, alice, alice_phone, 50, 0)
+        .await
+        .unwrap();
+    let kinds: Vec<&str> = messages.iter().rev().map(|m| m.kind.as_str()).collect();
+    assert_eq!(
+        kinds,
+        [
+            "member_joined",
+            "member_invited",
+            "member_invited",
+            "chat_renamed",
+            "member_removed",
+            "member_left",
+        ]
+    );
+    let invited = messages[4].payload.as_ref().unwrap();
+    assert_eq!(invited["user_id"], bob.to_string());
+    assert_eq!(invited["invited_by"], alice.to_string());
+    let renamed = messages[2].payload.as_ref().unwrap();
+    assert_eq!(renamed["old_name"], "Team");
+    assert_eq!(renamed["name"], "Core Team");
+    let removed = messages[1].payload.as_ref().unwrap();
+    assert_eq!(removed["user_id"], carol.to_string());
+    assert_eq!(removed["removed_by"], alice.to_string());
+    assert_eq!(messages[0].sender_id, bob);
+    assert!(messages.iter().all(|m| m.sender_device_id.is_none()));
+}
+
+#[tokio::test]
+async fn mentions_drive_counters_and_bypass_mute() {
+    let service = common::service().await;
+    let alice = register(&service, "alice").await;
+    let bob = register(&service, "bob").await;
+    let carol = register(&service, "carol").await;
+    let dave = register(&service, "dave").await;
+    let alice_phone = device(&service, alice).await;
+    let bob_phone = device(&service, bob).await;
+    let carol_phone = device(&service, carol).await;
+    let chat = service
+        .chat
+        .create_chat("Team".to_string(), alice)
+        .await
+        .unwrap();
+    for username in ["bob", "carol"] {
+        service
+            .chat
+            .invite_user_by_username(chat.id, username.to_string(), alice)
+            .await
+            .unwrap();
+    }
+    let envelopes = |recipients: [uuid::Uuid; 2]| {
+        Envelopes(
+            recipients
+                .into_iter()
+                .map(|device_id| (device_id, "hi".to_string()))
+                .collect(),
+        )
+    };
+    let send = |sender, sender_device, recipients, mentions: Vec<uuid::Uuid>, mention_all| {
+        service.chat.send_message(
+            chat.id,
+            sender,
+            sender_device,
+            envelopes(recipients),
+            MessageOptions {
+                mentions,
+                mention_all,
+                ..MessageOptions::default()
+            },
+        )
+    };
+
+    service.chat.set_muted(chat.id, carol, true).await.unwrap();
+
+    let stranger = send(
+        bob,
+        bob_phone,
+        [alice_phone, carol_phone],
+        vec![dave],
+        false,
+    )
+    .await;
+    assert!(matches!(stranger, Err(ChatError::InvalidMessage(_))));
+    let everyone = send(bob, bob_phone, [alice_phone, carol_phone], vec![], true).await;
+    assert!(matches!(everyone, Err(ChatError::Forbidden(_))));
+
+    let mentioned = send(
+        bob,
+        bob_phone,
+        [alice_phone, carol_phone],
+        vec![carol, carol],
+        false,
+    )
+    .await
+    .unwrap();
+    assert_eq!(mentioned.mentions, vec![carol]);
+    let plain = send(alice, alice_phone, [bob_phone, carol_phone], vec![], false)
+        .await
+        .unwrap();
+    let all = send(alice, alice_phone, [bob_phone, carol_phone], vec![], true)
+        .await
+        .unwrap();
+
+    let chats = service.chat.get_user_chats(carol).await.unwrap();
+    let state = chats[0].read_state.as_ref().unwrap();
+    assert_eq!((state.unread_count, state.mention_count), (3, 2));
+    assert!(state.muted);
+
+    let mentions = service.chat.get_mentions(carol, 50, 0).await.unwrap();
+    assert_eq!(mentions.len(), 2);
+    assert_eq!(mentions[0].message_id, all.id);
+    assert!(mentions[0].mention_all);
+    assert!(!mentions[1].read);
+    let clamped = service.chat.get_mentions(carol, 0, -1).await.unwrap();
+    assert_eq!(clamped.len(), 1);
+    assert_eq!(clamped[0].message_id, all.id);
+
+    let state = service
+        .chat
+        .mark_read(chat.id, carol, plain.id)
+        .await
+        .unwrap();
+    assert_eq!((state.unread_count, state.mention_count), (1, 1));
+    let state = service
+        .chat
+        .mark_read(chat.id, carol, mentioned.id)
+        .await
+        .unwrap();
+    assert_eq!(state.unread_count, 1);
+    let mentions = service.chat.get_mentions(carol, 50, 0).await.unwrap();
+    assert!(!mentions[0].read);
+    assert!(mentions[1].read);
+
+    let elsewhere = service.chat.mark_read(chat.id, dave, plain.id).await;
+    assert!(matches!(elsewhere, Err(ChatError::NotMember)));
+}
+
+#[tokio::test]
+async fn resumable_uploads_follow_the_offset() {
+    let service = common::service().await;
+    let alice = register(&service, "alice").await;
+    let bob = register(&service, "bob").await;
+
+    let empty = service.attachments.create_upload(alice, 0).await;
+    assert!(matches!(empty, Err(AttachmentError::InvalidSize(_))));
+    let too_large = service.attachments.create_upload(alice, 65).await;
+    assert!(matches!(too_large, Err(AttachmentError::TooLarge(64))));
+
+    let upload = service.attachments.create_upload(alice, 20).await.unwrap();
+    assert!(!upload.complete);
+
+    let oversized = service
+        .attachments
+        .upload_chunk(alice, upload.id, 0, vec![0; 17])
+        .await;
+    assert!(matches!(oversized, Err(AttachmentError::ChunkTooLarge(16))));
+
+    let first = service
+        .attachments
+        .upload_chunk(alice, upload.id, 0, (0..16).collect())
+        .await
+        .unwrap();
+    assert_eq!(first.uploaded_size, 16);
+
+    let replayed = service
+        .attachments
+        .upload_chunk(alice, upload.id, 0, (0..16).collect())
+        .await;
+    assert!(matches!(
+        replayed,
+        Err(AttachmentError::OffsetMismatch { expected: 16 })
+    ));
+    let past_end = service
+        .attachments
+        .upload_chunk(alice, upload.id, 16, vec![0; 5])
+        .await;
+    assert!(matches!(past_end, Err(AttachmentError::InvalidChunk(_))));
+    let by_other = service
+        .attachments
+        .upload_chunk(bob, upload.id, 16, vec![0; 4])
+        .await;
+    assert!(matches!(by_other, Err(AttachmentError::NotFound)));
+    let incomplete = service.attachments.download(alice, upload.id).await;
+    assert!(matches!(incomplete, Err(AttachmentError::Incomplete)));
+
+    let done = service
+        .attachments
+        .upload_chunk(alice, upload.id, 16, (16..20).collect())
+        .await
+        .unwrap();
+    assert!(done.complete);
+    assert_eq!(
+        service
+            .attachments
+            .get_upload(alice, upload.id)
+            .await
+            .unwrap()
+            .uploaded_size,
+        20
+    );
+
+    let download = service
+        .attachments
+        .download(alice, upload.id)
+        .await
+        .unwrap();
+    assert_eq!(download.size, 20);
+    let chunks = read_chunks(download).await;
+    assert!(chunks.len() > 1);
+    assert_eq!(chunks.concat(), (0..20).collect::<Vec<u8>>());
+}
+
+#[tokio::test]
+async fn attachments_are_readable_once_sent_to_a_chat() {
+    let service = common::service().await;
+    let alice = register(&service, "alice").await;
+    let bob = register(&service, "bob").await;
+    let carol = register(&service, "carol").await;
+    let alice_phone = device(&service, alice).await;
+    let bob_phone = device(&service, bob).await;
+    let chat = service
+        .chat
+        .create_chat("Team".to_string(), alice)
+        .await
+        .unwrap();
+    service
+        .chat
+        .invite_user_by_username(chat.id, "bob".to_string(), alice)
+        .await
+        .unwrap();
+
+    let photo = service
+        .attachments
+        .upload(alice, b"encrypted photo".to_vec())
+        .await
+        .unwrap();
+    assert!(photo.complete);
+    let unsent = service.attachments.download(bob, photo.id).await;
+    assert!(matches!(unsent, Err(AttachmentError::NotFound)));
+
+    let pending = service.attachments.create_upload(alice, 30).await.unwrap();
+    for attachment_ids in [vec![pending.id], vec![photo.id, photo.id]] {
+        let rejected = service
+            .chat
+            .send_message(
+                chat.id,
+                alice,
+                alice_phone,
+                Envelopes(HashMap::from([(bob_phone, "hi".to_string())])),
+                MessageOptions {
+                    attachment_ids,
+                    ..Default::default()
+                },
+            )
+            .await;
+        assert!(matches!(rejected, Err(ChatError::InvalidAttachment(_))));
+    }
+
+    let sent = service
+        .chat
+        .send_message(
+            chat.id,
+            alice,
+            alice_phone,
+            Envelopes(HashMap::from([(bob_phone, "hi".to_string())])),
+            MessageOptions {
+                attachment_ids: vec![photo.id],
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(sent.attachment_ids, [photo.id]);
+
+    let messages = service
+        .chat
+        .get_messages(chat.id, bob, bob_phone, 50, 0)
+        .await
+        .unwrap();
+    assert_eq!(messages[0].attachment_ids, [photo.id]);
+
+    let download = service.attachments.download(bob, photo.id).await.unwrap();
+    assert_eq!(read_chunks(download).await.concat(), b"encrypted photo");
+    let outsider = service.attachments.download(carol, photo.id).await;
+    assert!(matches!(outsider, Err(AttachmentError::NotFound)));
+}
+
+#[tokio::test]
+async fn uploads_count_against_the_uploader_quota() {
+    let service = common::service().await;
+    let alice = register(&service, "alice").await;
+    let bob = register(&service, "bob").await;
+
+    // Unfinished uploads count at their full size.
+    service.attachments.create_upload(alice, 64).await.unwrap();
+    let over = service.attachments.create_upload(alice, 40).await;
+    assert!(matches!(over, Err(AttachmentError::QuotaExceeded(100))));
+
+    for _ in 0..2 {
+        service
+            .attachments
+            .upload(alice, vec![7; 16])
+            .await
+            .unwrap();
+    }
+    let over = service.attachments.upload(alice, vec![7; 5]).await;
+    assert!(matches!(over, Err(AttachmentError::QuotaExceeded(100))));
+
+    service.attachments.create_upload(bob, 64).await.unwrap();
+}
+
+#[tokio::test]
+async fn stale_uploads_are_swept_with_their_blobs() {
+    let root = std::env::temp_dir().join(format!("stale-blobs-{}", uuid::Uuid::new_v4()));
+    let service = common::service_with_blobs_in(&root).await;
+    let alice = register(&service, "alice").await;
+    let bob = register(&service, "bob").await;
+    let alice_phone = device(&service, alice).await;
+    let bob_phone = device(&service, bob).await;
+    let chat = service
+        .chat
+        .create_chat("Team".to_string(), alice)
+        .await
+        .unwrap();
+    service
+        .chat
+        .invite_user_by_username(chat.id, "bob".to_string(), alice)
+        .await
+        .unwrap();
+
+    let abandoned = service.attachments.create_upload(alice, 20).await.unwrap();
+    service
+        .attachments
+        .upload_chunk(alice, abandoned.id, 0, vec![1; 16])
+        .await
+        .unwrap();
+    let unsent = service
+        .attachments
+        .upload(alice, vec![2; 16])
+        .await
+        .unwrap();
+    let sent = service
+        .attachments
+        .upload(alice, vec![3; 16])
+        .await
+        .unwrap();
+    service
+        .chat
+        .send_message(
+            chat.id,
+            alice,
+            alice_phone,
+            Envelopes(HashMap::from([(bob_phone, "see attached".to_string())])),
+            MessageOptions {
+                attachment_ids: vec![sent.id],
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(files_under(&root), 3);
+
+    // Fresh uploads may still be finished or sent.
+    assert_eq!(
+        service.attachments.sweep_stale_uploads(10).await.unwrap(),
+        0
+    );
+
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+    assert_eq!(service.attachments.sweep_stale_uploads(1).await.unwrap(), 2);
+    assert_eq!(
+        service.attachments.sweep_stale_uploads(10).await.unwrap(),
+        0
+    );
+
+    assert_eq!(files_under(&root), 1);
+    for attachment_id in [abandoned.id, unsent.id] {
+        let gone = service.attachments.get_upload(alice, attachment_id).await;
+        assert!(matches!(gone, Err(AttachmentError::NotFound)));
+    }
+    let download = service.attachments.download(bob, sent.id).await.unwrap();
+    assert_eq!(read_chunks(download).await.concat(), vec![3; 16]);
+
+    // The swept uploads no longer count against the quota.
+    service.attachments.create_upload(alice, 64).await.unwrap();
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[tokio::test]
+async fn message_timer_is_set_by_admins_and_announced() {
+    let service = common::service().await;
+    let alice = register(&service, "alice").await;
+    let bob = register(&service, "bob").await;
+    let bob_phone = device(&service, bob).await;
+    let alice_phone = device(&service, alice).await;
+    let chat = service
+        .chat
+        .create_chat("Team".to_string(), alice)
+        .await
+        .unwrap();
+    service
+        .chat
+        .invite_user_by_username(chat.id, "bob".to_string(), alice)
+        .await
+        .unwrap();
+
+    let by_member = service
+        .chat
+        .set_message_ttl(chat.id, bob, false, Some(60))
+        .await;
+    assert!(matches!(by_member, Err(ChatError::Forbidden(_))));
+    let out_of_range = service
+        .chat
+        .set_message_ttl(chat.id, alice, false, Some(0))
+        .await;
+    assert!(matches!(out_of_range, Err(ChatError::InvalidMessageTtl(_))));
+
+    let updated = service
+        .chat
+        .set_message_ttl(chat.id, bob, true, Some(3600))
+        .await
+        .unwrap();
+    assert_eq!(updated.message_ttl_seconds, Some(3600));
+
+    let sent = service
+        .chat
+        .send_message(
+            chat.id,
+            alice,
+            alice_phone,
+            Envelopes(HashMap::from([(bob_phone, "hi".to_string())])),
+            MessageOptions::default(),
+        )
+        .await
+        .unwrap();
+    assert!(sent.expires_at.is_some());
+
+    let messages = service
+        .chat
+        .get_messages(chat.id, bob, bob_phone, 50, 0)
+        .await
+        .unwrap();
+    assert_eq!(messages.len(), 4);
+    assert_eq!(messages[1].kind, "message_ttl_changed");
+    let payload = messages[1].payload.as_ref().unwrap();
+    assert_eq!(payload["ttl_seconds"], 3600);
+    assert_eq!(payload["changed_by"], bob.to_string());
+    assert!(messages[1].expires_at.is_none());
+}
+
+#[tokio::test]
+async fn expired_messages_are_hidden_then_reaped() {
+    let service = common::service().await;
+    let alice = register(&service, "alice").await;
+    let bob = register(&service, "bob").await;
+    let alice_phone = device(&service, alice).await;
+    let bob_phone = device(&service, bob).await;
+    let chat = service
+        .chat
+        .create_chat("Team".to_string(), alice)
+        .await
+        .unwrap();
+    service
+        .chat
+        .invite_user_by_username(chat.id, "bob".to_string(), alice)
+        .await
+        .unwrap();
+    service
+        .chat
+        .set_message_ttl(chat.id, alice, false, Some(1))
+        .await
+        .unwrap();
+    service
+        .chat
+        .send_message(
+            chat.id,
+            alice,
+            alice_phone,
+            Envelopes(HashMap::from([(bob_phone, "soon gone".to_string())])),
+            MessageOptions::default(),
+        )
+        .await
+        .unwrap();
+
+    let read = |service: Service| async move {
+        service
+            .chat
+            .get_messages(chat.id, bob, bob_phone, 50, 0)
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|m| m.kind == "text")
+            .count()
+    };
+    assert_eq!(read(service.clone()).await, 1);
+
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+    assert_eq!(read(service.clone()).await, 0);
+
+    assert_eq!(service.chat.reap_expired_messages(100).await.unwrap(), 1);
+    assert_eq!(service.chat.reap_expired_messages(100).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn reaping_messages_deletes_their_attachment_blobs() {
+    let root = std::env::temp_dir().join(format!("reaped-blobs-{}", uuid::Uuid::new_v4()));
+    let service = common::service_with_blobs_in(&root).await;
+    let alice = register(&service, "alice").await;
+    let bob = register(&service, "bob").await;
+    let alice_phone = device(&service, alice).await;
+    let bob_phone = device(&service, bob).await;
+    let chat = service
+        .chat
+        .create_chat("Team".to_string(), alice)
+        .await
+        .unwrap();
+    service
+        .chat
+        .invite_user_by_username(chat.id, "bob".to_string(), alice)
+        .await
+        .unwrap();
+    service
+        .chat
+        .set_message_ttl(chat.id, alice, false, Some(1))
+        .await
+        .unwrap();
+
+    let photo = service.attachments.create_upload(alice, 20).await.unwrap();
+    for (offset, chunk) in [(0, vec![1; 16]), (16, vec![2; 4])] {
+        service
+            .attachments
+            .upload_chunk(alice, photo.id, offset, chunk)
+            .await
+            .unwrap();
+    }
+    let note = service
+        .attachments
+        .upload(alice, b"encrypted note".to_vec())
+        .await
+        .unwrap();
+    assert_eq!(files_under(&root), 3);
+
+    service
+        .chat
+        .send_message(
+            chat.id,
+            alice,
+            alice_phone,
+            Envelopes(HashMap::from([(bob_phone, "see attached".to_string())])),
+            MessageOptions {
+                attachment_ids: vec![photo.id, note.id],
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+    assert_eq!(service.chat.reap_expired_messages(100).await.unwrap(), 1);
+
+    assert_eq!(files_under(&root), 0);
+    for attachment_id in [photo.id, note.id] {
+        let gone = service.attachments.download(alice, attachment_id).await;
+        assert!(matches!(gone, Err(AttachmentError::NotFound)));
+    }
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[tokio::test]
+async fn view_once_messages_are_delivered_once_per_device() {
+    let service = common::service().await;
+    let alice = register(&service, "alice").await;
+    let bob = register(&service, "bob").await;
+    let alice_phone = device(&service, alice).await;
+    let bob_phone = device(&service, bob).await;
+    let bob_laptop = device(&service, bob).await;
+    let chat = service
+        .chat
+        .create_chat("Team".to_string(), alice)
+        .await
+        .unwrap();
+    service
+        .chat
+        .invite_user_by_username(chat.id, "bob".to_string(), alice)
+        .await
+        .unwrap();
+    let view_once = MessageOptions {
+        view_once: true,
+        ..Default::default()
+    };
+
+    let sender_key = service
+        .chat
+        .send_message(
+            chat.id,
+            alice,
+            alice_phone,
+            MessageBody::SenderKey {
+                epoch: 1,
+                ciphertext: "photo".to_string(),
+            },
+            view_once.clone(),
+        )
+        .await;
+    assert!(matches!(sender_key, Err(ChatError::InvalidMessage(_))));
+
+    let sent = service
+        .chat
+        .send_message(
+            chat.id,
+            alice,
+            alice_phone,
+            Envelopes(HashMap::from([
+                (bob_phone, "photo for phone".to_string()),
+                (bob_laptop, "photo for laptop".to_string()),
+            ])),
+            view_once,
+        )
+        .await
+        .unwrap();
+    assert!(sent.view_once);
+
+    let first = service
+        .chat
+        .get_messages(chat.id, bob, bob_phone, 50, 0)
+        .await
+        .unwrap();
+    assert_eq!(
+        first[0].encrypted_content.as_deref(),
+        Some("photo for phone")
+    );
+    let again = service
+        .chat
+        .get_messages(chat.id, bob, bob_phone, 50, 0)
+        .await
+        .unwrap();
+    assert!(again.iter().all(|m| !m.view_once));
+
+    assert_eq!(service.chat.reap_expired_messages(100).await.unwrap(), 0);
+    let laptop = service
+        .chat
+        .get_messages(chat.id, bob, bob_laptop, 50, 0)
+        .await
+        .unwrap();
+    assert_eq!(laptop.iter().filter(|m| m.view_once).count(), 1);
+    assert_eq!(service.chat.reap_expired_messages(100).await.unwrap(), 1);
+}
+
+#[tokio::test]
+async fn scheduled_messages_are_posted_when_due() {
+    let service = common::service().await;
+    let alice = register(&service, "alice").await;
+    let bob = register(&service, "bob").await;
+    let alice_phone = device(&service, alice).await;
+    let bob_phone = device(&service, bob).await;
+    let chat = service
+        .chat
+        .create_chat("Team".to_string(), alice)
+        .await
+        .unwrap();
+    service
+        .chat
+        .invite_user_by_username(chat.id, "bob".to_string(), alice)
+        .await
+        .unwrap();
+    let now = chrono::Utc::now().naive_utc();
+    let envelopes = || Envelopes(HashMap::from([(bob_phone, "later".to_string())]));
+
+    let in_the_past = service
+        .chat
+        .schedule_message(
+            chat.id,
+            alice,
+            alice_phone,
+            now - chrono::Duration::seconds(5),
+            envelopes(),
+            MessageOptions::default(),
+        )
+        .await;
+    assert!(matches!(in_the_past, Err(ChatError::InvalidSchedule(_))));
+
+    let scheduled = service
+        .chat
+        .schedule_message(
+            chat.id,
+            alice,
+            alice_phone,
+            now + chrono::Duration::seconds(1),
+            envelopes(),
+            MessageOptions::default(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(scheduled.status, "pending");
+    let epoch = service.chat.get_chat(chat.id, alice).await.unwrap().epoch;
+    let sender_key = service
+        .chat
+        .schedule_message(
+            chat.id,
+            alice,
+            alice_phone,
+            now + chrono::Duration::seconds(1),
+            MessageBody::SenderKey {
+                epoch,
+                ciphertext: "group".to_string(),
+            },
+            MessageOptions::default(),
+        )
+        .await
+        .unwrap();
+
+    let not_theirs = service
+        .chat
+        .cancel_scheduled_message(scheduled.id, bob)
+        .await;
+    assert!(matches!(
+        not_theirs,
+        Err(ChatError::ScheduledMessageNotFound)
+    ));
+    let early = service.chat.deliver_scheduled_messages(10).await.unwrap();
+    assert_eq!(early.sent + early.failed, 0);
+
+    // The sender-key message is stale once the chat rekeys.
+    register(&service, "carol").await;
+    service
+        .chat
+        .invite_user_by_username(chat.id, "carol".to_string(), alice)
+        .await
+        .unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+
+    let delivery = service.chat.deliver_scheduled_messages(10).await.unwrap();
+    assert_eq!((delivery.sent, delivery.failed), (1, 1));
+
+    let messages = service
+        .chat
+        .get_messages(chat.id, bob, bob_phone, 50, 0)
+        .await
+        .unwrap();
+    assert_eq!(messages.len(), 4);
+    assert_eq!(messages[0].encrypted_content.as_deref(), Some("later"));
+    assert_eq!(messages[0].sender_device_id, Some(alice_phone));
+
+    let pending = service
+        .chat
+        .list_scheduled_messages(alice, Some(chat.id))
+        .await
+        .unwrap();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].id, sender_key.id);
+    assert_eq!(pending[0].status, "failed");
+    assert!(pending[0].error.is_some());
+
+    let retried = service
+        .chat
+        .update_scheduled_message(
+            sender_key.id,
+            alice,
+            alice_phone,
+            Some(chrono::Utc::now().naive_utc() + chrono::Duration::hours(1)),
+            MessageBody::SenderKey {
+                epoch: epoch + 1,
+                ciphertext: "group".to_string(),
+            },
+            MessageOptions::default(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(retried.status, "pending");
+    assert!(retried.error.is_none());
+
+    service
+        .chat
+        .cancel_scheduled_message(sender_key.id, alice)
+        .await
+        .unwrap();
+    let gone = service
+        .chat
+        .list_scheduled_messages(alice, None)
+        .await
+        .unwrap();
+    assert!(gone.is_empty());
+}
+
+#[tokio::test]
+async fn pins_are_limited_and_announced() {
+    let service = common::service().await;
+    let alice = register(&service, "alice").await;
+    let bob = register(&service, "bob").await;
+    let alice_phone = device(&service, alice).await;
+    let bob_phone = device(&service, bob).await;
+    let chat = service
+        .chat
+        .create_chat("Team".to_string(), alice)
+        .await
+        .unwrap();
+    service
+        .chat
+        .invite_user_by_username(chat.id, "bob".to_string(), alice)
+        .await
+        .unwrap();
+    let mut sent = Vec::new();
+    for i in 0..51 {
+        let message = service
+            .chat
+            .send_message(
+                chat.id,
+                alice,
+                alice_phone,
+                Envelopes(HashMap::from([(bob_phone, format!("m{}", i))])),
+                MessageOptions::default(),
+            )
+            .await
+            .unwrap();
+        sent.push(message.id);
+    }
+
+    let by_member = service.chat.pin_message(chat.id, bob, false, sent[0]).await;
+    assert!(matches!(by_member, Err(ChatError::Forbidden(_))));
+    let unknown = service
+        .chat
+        .pin_message(chat.id, alice, false, uuid::Uuid::new_v4())
+        .await;
+    assert!(matches!(unknown, Err(ChatError::MessageNotFound)));
+
+    let pin = service
+        .chat
+        .pin_message(chat.id, bob, true, sent[0])
+        .await
+        .unwrap();
+    assert_eq!(pin.pinned_by, bob);
+    let again = service
+        .chat
+        .pin_message(chat.id, alice, false, sent[0])
+        .await
+        .unwrap();
+    assert_eq!(again.pinned_by, bob);
+
+    let messages = service
+        .chat
+        .get_messages(chat.id, bob, bob_phone, 1, 0)
+        .await
+        .unwrap();
+    assert_eq!(messages[0].kind, "message_pinned");
+    assert_eq!(
+        messages[0].payload.as_ref().unwrap()["message_id"],
+        sent[0].to_string()
+    );
+    let notice = service
+        .chat
+        .pin_message(chat.id, alice, false, messages[0].id)
+        .await;
+    assert!(matches!(notice, Err(ChatError::InvalidMessage(_))));
+
+    for message_id in &sent[1..50] {
+        service
+            .chat
+            .pin_message(chat.id, alice, false, *message_id)
+            .await
+            .unwrap();
+    }
+    let over = service
+        .chat
+        .pin_message(chat.id, alice, false, sent[50])
+        .await;
+    assert!(matches!(over, Err(ChatError::TooManyPins(50))));
+
+    let pins = service.chat.get_pins(chat.id, bob).await.unwrap();
+    assert_eq!(pins.len(), 50);
+    assert_eq!(pins[0].message_id, sent[49]);
+    let info = service.chat.get_chat(chat.id, bob).await.unwrap();
+    assert_eq!(info.latest_pin.unwrap().message_id, sent[49]);
+
+    service
+        .chat
+        .unpin_message(chat.id, alice, false, sent[49])
+        .await
+        .unwrap();
+    let twice = service
+        .chat
+        .unpin_message(chat.id, alice, false, sent[49])
+        .await;
+    assert!(matches!(twice, Err(ChatError::PinNotFound)));
+    let chats = service.chat.get_user_chats(bob).await.unwrap();
+    assert_eq!(chats[0].latest_pin.as_ref().unwrap().message_id, sent[48]);
+    let messages = service
+        .chat
+        .get_messages(chat.id, bob, bob_phone, 1, 0)
+        .await
+        .unwrap();
+    assert_eq!(messages[0].kind, "message_unpinned");
+}
+
+#[tokio::test]
+async fn forwards_keep_the_original_provenance() {
+    let service = common::service().await;
+    let alice = register(&service, "alice").await;
+    let bob = register(&service, "bob").await;
+    let carol = register(&service, "carol").await;
+    let alice_phone = device(&service, alice).await;
+    let bob_phone = device(&service, bob).await;
+    let carol_phone = device(&service, carol).await;
+    let team = service
+        .chat
+        .create_chat("Team".to_string(), alice)
+        .await
+        .unwrap();
+    service
+        .chat
+        .invite_user_by_username(team.id, "bob".to_string(), alice)
+        .await
+        .unwrap();
+    let family = service
+        .chat
+        .create_chat("Family".to_string(), alice)
+        .await
+        .unwrap();
+    service
+        .chat
+        .invite_user_by_username(family.id, "carol".to_string(), alice)
+        .await
+        .unwrap();
+    let original = service
+        .chat
+        .send_message(
+            team.id,
+            bob,
+            bob_phone,
+            Envelopes(HashMap::from([(alice_phone, "hi".to_string())])),
+            MessageOptions::default(),
+        )
+        .await
+        .unwrap();
+    assert!(original.forwarded_from.is_none());
+
+    let forward = service
+        .chat
+        .forward_message(
+            family.id,
+            alice,
+            alice_phone,
+            original.id,
+            Envelopes(HashMap::from([(carol_phone, "hi".to_string())])),
+            MessageOptions::default(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(forward.chat_id, family.id);
+    assert_eq!(forward.sender_id, alice);
+    let origin = forward.forwarded_from.unwrap();
+    assert_eq!(
+        (origin.chat_id, origin.message_id, origin.sender_id),
+        (team.id, original.id, bob)
+    );
+
+    let back = service
+        .chat
+        .forward_message(
+            team.id,
+            alice,
+            alice_phone,
+            forward.id,
+            Envelopes(HashMap::from([(bob_phone, "hi".to_string())])),
+            MessageOptions::default(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(back.forwarded_from.unwrap().message_id, original.id);
+
+    let outsider = service
+        .chat
+        .forward_message(
+            family.id,
+            carol,
+            carol_phone,
+            original.id,
+            Envelopes(HashMap::from([(alice_phone, "hi".to_string())])),
+            MessageOptions::default(),
+        )
+        .await;
+    assert!(matches!(outsider, Err(ChatError::NotMember)));
+    let not_in_target = service
+        .chat
+        .forward_message(
+            family.id,
+            bob,
+            bob_phone,
+            original.id,
+            Envelopes(HashMap::from([(carol_phone, "hi".to_string())])),
+            MessageOptions::default(),
+        )
+        .await;
+    assert!(matches!(not_in_target, Err(ChatError::NotMember)));
+
+    let by_member = service
+        .chat
+        .set_allow_forwarding(team.id, bob, false, false)
+        .await;
+    assert!(matches!(by_member, Err(ChatError::Forbidden(_))));
+    let info = service
+        .chat
+        .set_allow_forwarding(team.id, alice, false, false)
+        .await
+        .unwrap();
+    assert!(!info.allow_forwarding);
+    let messages = service
+        .chat
+        .get_messages(team.id, bob, bob_phone, 1, 0)
+        .await
+        .unwrap();
+    assert_eq!(messages[0].kind, "forwarding_changed");
+
+    let disallowed = service
+        .chat
+        .forward_message(
+            family.id,
+            alice,
+            alice_phone,
+            original.id,
+            Envelopes(HashMap::from([(carol_phone, "hi".to_string())])),
+            MessageOptions::default(),
+        )
+        .await;
+    assert!(matches!(disallowed, Err(ChatError::ForwardingDisabled)));
+    let notice = service
+        .chat
+        .forward_message(
+            family.id,
+            alice,
+            alice_phone,
+            messages[0].id,
+            Envelopes(HashMap::from([(carol_phone, "hi".to_string())])),
+            MessageOptions::default(),
+        )
+        .await;
+    assert!(matches!(notice, Err(ChatError::InvalidMessage(_))));
+}
+
+#[tokio::test]
+async fn polls_tally_votes_until_closed() {
+    let service = common::service().await;
+    let alice = register(&service, "alice").await;
+    let bob = register(&service, "bob").await;
+    let carol = register(&service, "carol").await;
+    let bob_phone = device(&service, bob).await;
+    let chat = service
+        .chat
+        .create_chat("Team".to_string(), alice)
+        .await
+        .unwrap();
+    service
+        .chat
+        .invite_user_by_username(chat.id, "bob".to_string(), alice)
+        .await
+        .unwrap();
+    let draft = |options: &[&str], multiple_choice, anonymous| PollDraft {
+        question: "Lunch?".to_string(),
+        options: options.iter().map(|o| o.to_string()).collect(),
+        multiple_choice,
+        anonymous,
+        closes_at: None,
+    };
+
+    let single = service
+        .chat
+        .create_poll(chat.id, alice, draft(&["Pizza"], false, false))
+        .await;
+    assert!(matches!(single, Err(ChatError::InvalidPoll(_))));
+    let twice = service
+        .chat
+        .create_poll(chat.id, alice, draft(&["Pizza", "Pizza"], false, false))
+        .await;
+    assert!(matches!(twice, Err(ChatError::InvalidPoll(_))));
+    let outsider = service
+        .chat
+        .create_poll(chat.id, carol, draft(&["Pizza", "Sushi"], false, false))
+        .await;
+    assert!(matches!(outsider, Err(ChatError::NotMember)));
+
+    let poll = service
+        .chat
+        .create_poll(chat.id, alice, draft(&["Pizza", "Sushi"], false, false))
+        .await
+        .unwrap();
+    assert_eq!(poll.kind, "poll");
+    assert_eq!(poll.poll.as_ref().unwrap().options.len(), 2);
+
+    let both = service.chat.vote(chat.id, bob, poll.id, vec![0, 1]).await;
+    assert!(matches!(both, Err(ChatError::InvalidPoll(_))));
+    let unknown = service.chat.vote(chat.id, bob, poll.id, vec![2]).await;
+    assert!(matches!(unknown, Err(ChatError::InvalidPoll(_))));
+    service
+        .chat
+        .vote(chat.id, bob, poll.id, vec![0])
+        .await
+        .unwrap();
+    let tally = service
+        .chat
+        .vote(chat.id, alice, poll.id, vec![1])
+        .await
+        .unwrap();
+    assert_eq!(tally.voter_count, 2);
+    assert_eq!(tally.own_votes, vec![1]);
+    assert_eq!(tally.options[0].voters.as_deref(), Some(&[bob][..]));
+    let changed = service
+        .chat
+        .vote(chat.id, bob, poll.id, vec![1])
+        .await
+        .unwrap();
+    assert_eq!(
+        (changed.options[0].vote_count, changed.options[1].vote_count),
+        (0, 2)
+    );
+
+    let anonymous = service
+        .chat
+        .create_poll(chat.id, bob, draft(&["Yes", "No", "Maybe"], true, true))
+        .await
+        .unwrap();
+    let tally = service
+        .chat
+        .vote(chat.id, alice, anonymous.id, vec![0, 2])
+        .await
+        .unwrap();
+    assert_eq!(tally.own_votes, vec![0, 2]);
+    assert!(tally.options.iter().all(|o| o.voters.is_none()));
+
+    let messages = service
+        .chat
+        .get_messages(chat.id, bob, bob_phone, 2, 0)
+        .await
+        .unwrap();
+    assert_eq!(messages[0].id, anonymous.id);
+    let live = messages[0].poll.as_ref().unwrap();
+    assert_eq!(live.voter_count, 1);
+    assert!(live.own_votes.is_empty());
+    assert_eq!(messages[1].poll.as_ref().unwrap().own_votes, vec![1]);
+    let chats = service.chat.get_user_chats(bob).await.unwrap();
+    assert_eq!(chats[0].read_state.as_ref().unwrap().unread_count, 1);
+
+    let closing = service
+        .chat
+        .create_poll(
+            chat.id,
+            alice,
+            PollDraft {
+                closes_at: Some(chrono::Utc::now().naive_utc() + chrono::Duration::seconds(1)),
+                ..draft(&["Pizza", "Sushi"], false, false)
+            },
+        )
+        .await
+        .unwrap();
+    assert!(!closing.poll.unwrap().closed);
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+    let closed = service.chat.vote(chat.id, bob, closing.id, vec![0]).await;
+    assert!(matches!(closed, Err(ChatError::PollClosed)));
+
+    let by_member = service
+        .chat
+        .set_allow_polls(chat.id, bob, false, false)
+        .await;
+    assert!(matches!(by_member, Err(ChatError::Forbidden(_))));
+    let info = service
+        .chat
+        .set_allow_polls(chat.id, alice, false, false)
+        .await
+        .unwrap();
+    assert!(!info.allow_polls);
+    let disabled = service.chat.vote(chat.id, bob, poll.id, vec![0]).await;
+    assert!(matches!(disabled, Err(ChatError::PollsDisabled)));
+    let disabled = service
+        .chat
+        .create_poll(chat.id, alice, draft(&["Pizza", "Sushi"], false, false))
+        .await;
+    assert!(matches!(disabled, Err(ChatError::PollsDisabled)));
+}
@@ -0,0 +1,508 @@
+//! Outgoing webhooks for chat events. Deliveries go to an in-process
+//! endpoint from `common::recording`.
+
+mod common;
+
+use std::collections::HashMap;
+
+use axum::http::StatusCode;
+use ring::hmac;
+use uuid::Uuid;
+
+use common::recording;
+use msg_service::config::webhooks::WebhookConfig;
+use msg_service::repository::Repository;
+use msg_service::repository::webhook_sender::{HttpWebhookSender, WebhookSender};
+use msg_service::usecase::MessageBody::Envelopes;
+use msg_service::usecase::{MessageOptions, Service, WebhookError};
+
+struct Member {
+    id: Uuid,
+    device: Uuid,
+}
+
+async fn member(service: &Service, username: &str) -> Member {
+    let id = service
+        .auth
+        .create_user(username.to_string(), common::PASSWORD.to_string())
+        .await
+        .unwrap()
+        .id;
+    let device = service
+        .devices
+        .register_device(id, "Phone".to_string())
+        .await
+        .unwrap()
+        .id;
+
+    Member { id, device }
+}
+
+async fn send(service: &Service, chat_id: Uuid, sender: &Member, recipients: &[&Member]) -> Uuid {
+    let envelopes: HashMap<Uuid, String> = recipients
+        .iter()
+        .map(|r| (r.device, "ciphertext".to_string()))
+        .collect();
+
+    service
+        .chat
+        .send_message(
+            chat_id,
+            sender.id,
+            sender.device,
+            Envelopes(envelopes),
+            MessageOptions::default(),
+        )
+        .await
+        .unwrap()
+        .id
+}
+
+fn events(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+/// Checks `x-webhook-signature` against `body` the way a receiver would.
+fn signature_matches(delivery: &recording::Delivery, secret: &str, body: &[u8]) -> bool {
+    let signature = delivery
+        .header("x-webhook-signature")
+        .strip_prefix("sha256=")
+        .expect("sha256 signature");
+    let tag: Vec<u8> = (0..signature.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&signature[i..i + 2], 16).unwrap())
+        .collect();
+
+    let mut signed = format!("{}.", delivery.header("x-webhook-timestamp")).into_bytes();
+    signed.extend_from_slice(body);
+
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    hmac::verify(&key, &signed, &tag).is_ok()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn chat_events_are_signed_and_delivered_to_subscribed_webhooks() {
+    let (endpoint, base) = recording::start(StatusCode::NO_CONTENT).await;
+    let service = common::service_with(Repository::in_memory());
+    let alice = member(&service, "alice").await;
+    let bob = member(&service, "bob").await;
+    let chat = service
+        .chat
+        .create_chat("Team".to_string(), alice.id)
+        .await
+        .unwrap();
+    let other_chat = service
+        .chat
+        .create_chat("Elsewhere".to_string(), alice.id)
+        .await
+        .unwrap();
+
+    let chat_hook = service
+        .webhooks
+        .create_webhook(
+            alice.id,
+            false,
+            Some(chat.id),
+            format!("{}/chat", base),
+            events(&["chat.renamed", "message.created", "chat.renamed"]),
+        )
+        .await
+        .unwrap();
+    assert_eq!(chat_hook.events, ["message.created", "chat.renamed"]);
+    let global_hook = service
+        .webhooks
+        .create_webhook(
+            alice.id,
+            true,
+            None,
+            format!("{}/global", base),
+            events(&["member.joined"]),
+        )
+        .await
+        .unwrap();
+
+    service
+        .chat
+        .invite_user_by_username(chat.id, "bob".to_string(), alice.id)
+        .await
+        .unwrap();
+    service
+        .chat
+        .rename_chat(chat.id, alice.id, false, "Renamed".to_string())
+        .await
+        .unwrap();
+    service
+        .chat
+        .rename_chat(other_chat.id, alice.id, false, "Ignored".to_string())
+        .await
+        .unwrap();
+    let message_id = send(&service, chat.id, &alice, &[&bob]).await;
+
+    // The creators joining their chats happened before the webhooks existed;
+    // the notices posted along the way are not events.
+    assert_eq!(service.webhooks.dispatch_events(10).await.unwrap(), 6);
+    let dispatch = service.webhooks.deliver_due(10).await.unwrap();
+    assert_eq!((dispatch.claimed, dispatch.delivered), (3, 3));
+    assert_eq!(service.webhooks.dispatch_events(10).await.unwrap(), 0);
+    assert_eq!(service.webhooks.deliver_due(10).await.unwrap().claimed, 0);
+
+    let mut deliveries = endpoint.take();
+    deliveries.sort_by_key(|d| (d.path.clone(), d.header("x-webhook-event").to_string()));
+    let summary: Vec<(&str, &str)> = deliveries
+        .iter()
+        .map(|d| (d.path.as_str(), d.header("x-webhook-event")))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("/chat", "chat.renamed"),
+            ("/chat", "message.created"),
+            ("/global", "member.joined"),
+        ]
+    );
+
+    let chat_secret = chat_hook.secret.as_deref().unwrap();
+    for delivery in &deliveries[..2] {
+        assert!(signature_matches(delivery, chat_secret, &delivery.body));
+        assert_eq!(delivery.headers["content-type"], "application/json");
+    }
+    let global_secret = global_hook.secret.as_deref().unwrap();
+    assert!(signature_matches(
+        &deliveries[2],
+        global_secret,
+        &deliveries[2].body
+    ));
+    assert!(!signature_matches(
+        &deliveries[2],
+        chat_secret,
+        &deliveries[2].body
+    ));
+
+    let renamed = deliveries[0].json();
+    assert_eq!(renamed["type"], "chat.renamed");
+    assert_eq!(renamed["chat_id"], chat.id.to_string());
+    assert_eq!(renamed["data"]["old_name"], "Team");
+    assert_eq!(renamed["data"]["name"], "Renamed");
+
+    let created = deliveries[1].json();
+    assert_eq!(created["data"]["message_id"], message_id.to_string());
+    assert_eq!(
+        created["data"]["sender_device_id"],
+        alice.device.to_string()
+    );
+    assert!(created["data"].get("encrypted_content").is_none());
+
+    let joined = deliveries[2].json();
+    assert_eq!(joined["data"]["user_id"], bob.id.to_string());
+    assert_eq!(joined["data"]["invited_by"], alice.id.to_string());
+
+    // The signature covers the exact body.
+    let tampered = String::from_utf8_lossy(&deliveries[0].body).replace("Renamed", "Hijacked");
+    assert!(!signature_matches(
+        &deliveries[0],
+        chat_secret,
+        tampered.as_bytes()
+    ));
+
+    let log = service
+        .webhooks
+        .list_deliveries(alice.id, false, chat_hook.id, 50, 0)
+        .await
+        .unwrap();
+    assert_eq!(log.len(), 2);
+    assert!(log.iter().all(|d| d.status == "delivered"
+        && d.attempts == 1
+        && d.response_status == Some(204)
+        && d.next_attempt_at.is_none()
+        && d.completed_at.is_some()));
+    assert_eq!(log[0].event, "message.created");
+    assert_eq!(log[1].event, "chat.renamed");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn failing_deliveries_are_retried_then_marked_failed() {
+    let (endpoint, base) = recording::start(StatusCode::NO_CONTENT).await;
+    let service = common::service_with(Repository::in_memory());
+    let alice = member(&service, "alice").await;
+    let bob = member(&service, "bob").await;
+    let chat = service
+        .chat
+        .create_chat("Team".to_string(), alice.id)
+        .await
+        .unwrap();
+    service
+        .chat
+        .invite_user_by_username(chat.id, "bob".to_string(), alice.id)
+        .await
+        .unwrap();
+    let hook = service
+        .webhooks
+        .create_webhook(
+            alice.id,
+            false,
+            Some(chat.id),
+            format!("{}/hook", base),
+            events(&["message.created"]),
+        )
+        .await
+        .unwrap();
+
+    endpoint.answer(StatusCode::SERVICE_UNAVAILABLE);
+    send(&service, chat.id, &alice, &[&bob]).await;
+    service.webhooks.dispatch_events(10).await.unwrap();
+
+    // The test config allows three attempts with no delay between them.
+    for _ in 0..2 {
+        let dispatch = service.webhooks.deliver_due(10).await.unwrap();
+        assert_eq!((dispatch.claimed, dispatch.retried), (1, 1));
+    }
+    let pending = service
+        .webhooks
+        .list_deliveries(alice.id, false, hook.id, 50, 0)
+        .await
+        .unwrap();
+    assert_eq!(pending[0].status, "pending");
+    assert_eq!(pending[0].attempts, 2);
+    assert!(pending[0].next_attempt_at.is_some());
+
+    let dispatch = service.webhooks.deliver_due(10).await.unwrap();
+    assert_eq!((dispatch.claimed, dispatch.failed), (1, 1));
+    assert_eq!(service.webhooks.deliver_due(10).await.unwrap().claimed, 0);
+
+    // Every attempt carried the same delivery id, so receivers can dedupe.
+    let attempts = endpoint.take();
+    assert_eq!(attempts.len(), 3);
+    assert!(
+        attempts
+            .iter()
+            .all(|a| a.header("x-webhook-id") == attempts[0].header("x-webhook-id"))
+    );
+
+    endpoint.answer(StatusCode::OK);
+    send(&service, chat.id, &alice, &[&bob]).await;
+    service.webhooks.dispatch_events(10).await.unwrap();
+    assert_eq!(service.webhooks.deliver_due(10).await.unwrap().delivered, 1);
+
+    let log = service
+        .webhooks
+        .list_deliveries(alice.id, false, hook.id, 50, 0)
+        .await
+        .unwrap();
+    assert_eq!(log.len(), 2);
+    assert_eq!(log[0].status, "delivered");
+    assert_eq!(log[0].response_status, Some(200));
+    assert_eq!(log[1].status, "failed");
+    assert_eq!(log[1].attempts, 3);
+    assert_eq!(log[1].response_status, Some(503));
+
+    // Only a short excerpt of the answer is kept, on one line.
+    let error = log[1].last_error.as_deref().unwrap();
+    assert!(error.starts_with("Endpoint returned 503: receiver is down xxx"));
+    assert!(error.len() < 150);
+    assert!(!error.contains(char::is_control));
+
+    // Pages are clamped to at least one delivery.
+    let page = service
+        .webhooks
+        .list_deliveries(alice.id, false, hook.id, 0, -5)
+        .await
+        .unwrap();
+    assert_eq!(page.len(), 1);
+    assert_eq!(page[0].id, log[0].id);
+
+    // Unreachable endpoints are retried the same way.
+    let closed = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let closed_url = format!("http://{}/hook", closed.local_addr().unwrap());
+    drop(closed);
+    let unreachable = service
+        .webhooks
+        .create_webhook(
+            alice.id,
+            false,
+            Some(chat.id),
+            closed_url,
+            events(&["message.created"]),
+        )
+        .await
+        .unwrap();
+    send(&service, chat.id, &alice, &[&bob]).await;
+    service.webhooks.dispatch_events(10).await.unwrap();
+    let dispatch = service.webhooks.deliver_due(10).await.unwrap();
+    assert_eq!((dispatch.delivered, dispatch.retried), (1, 1));
+
+    let log = service
+        .webhooks
+        .list_deliveries(alice.id, false, unreachable.id, 50, 0)
+        .await
+        .unwrap();
+    assert_eq!(log[0].response_status, None);
+    assert!(log[0].last_error.is_some());
+
+    // Removing the webhook drops what is still pending.
+    service
+        .webhooks
+        .delete_webhook(alice.id, false, unreachable.id)
+        .await
+        .unwrap();
+    assert_eq!(service.webhooks.deliver_due(10).await.unwrap().claimed, 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn webhook_urls_must_be_public_unless_allowed() {
+    let sender = HttpWebhookSender::new(&WebhookConfig {
+        allow_private_urls: false,
+        ..common::webhook_config()
+    });
+
+    for url in [
+        "http://127.0.0.1/hook",
+        "http://192.168.1.10/hook",
+        "http://169.254.169.254/latest/meta-data",
+        "http://[fd00::1]/hook",
+        "http://localhost:8080/hook",
+        "http://100.64.0.1/hook",
+    ] {
+        assert!(sender.check_url(url).is_err(), "{}", url);
+    }
+    assert!(
+        sender
+            .check_url("https://integrations.example.com/hooks/chat")
+            .is_ok()
+    );
+
+    // URLs stored while private addresses were allowed are refused too.
+    let (endpoint, base) = recording::start(StatusCode::NO_CONTENT).await;
+    let posted = tokio::task::spawn_blocking(move || {
+        sender.post(&format!("{}/hook", base), &[], "{}".to_string())
+    })
+    .await
+    .unwrap();
+    assert!(posted.is_err());
+    assert!(endpoint.take().is_empty());
+}
+
+#[tokio::test]
+async fn webhooks_are_limited_to_chat_creators_and_managers() {
+    let service = common::service_with(Repository::in_memory());
+    let alice = member(&service, "alice").await;
+    let bob = member(&service, "bob").await;
+    let chat = service
+        .chat
+        .create_chat("Team".to_string(), alice.id)
+        .await
+        .unwrap();
+    service
+        .chat
+        .invite_user_by_username(chat.id, "bob".to_string(), alice.id)
+        .await
+        .unwrap();
+    let url = "http://hooks.internal/chat".to_string();
+
+    let err = service
+        .webhooks
+        .create_webhook(
+            bob.id,
+            false,
+            Some(chat.id),
+            url.clone(),
+            events(&["message.created"]),
+        )
+        .await
+        .err()
+        .unwrap();
+    assert!(matches!(err, WebhookError::Forbidden(_)));
+
+    let err = service
+        .webhooks
+        .create_webhook(
+            alice.id,
+            false,
+            None,
+            url.clone(),
+            events(&["chat.renamed"]),
+        )
+        .await
+        .err()
+        .unwrap();
+    assert!(matches!(err, WebhookError::Forbidden(_)));
+
+    let err = service
+        .webhooks
+        .create_webhook(
+            alice.id,
+            false,
+            Some(Uuid::new_v4()),
+            url.clone(),
+            events(&["chat.renamed"]),
+        )
+        .await
+        .err()
+        .unwrap();
+    assert!(matches!(err, WebhookError::ChatNotFound));
+
+    for (url, events) in [
+        (url.clone(), Vec::new()),
+        (url.clone(), events(&["message.deleted"])),
+        (
+            "ftp://hooks.example.com".to_string(),
+            events(&["chat.renamed"]),
+        ),
+    ] {
+        let err = service
+            .webhooks
+            .create_webhook(alice.id, false, Some(chat.id), url, events)
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(err, WebhookError::InvalidWebhook(_)));
+    }
+
+    // A manager may set up webhooks on chats they did not create.
+    let hook = service
+        .webhooks
+        .create_webhook(
+            bob.id,
+            true,
+            Some(chat.id),
+            url,
+            events(&["message.created"]),
+        )
+        .await
+        .unwrap();
+
+    let listed = service.webhooks.list_webhooks(bob.id).await.unwrap();
+    assert_eq!(listed.len(), 1);
+    assert!(listed[0].secret.is_none());
+    assert!(
+        service
+            .webhooks
+            .list_webhooks(alice.id)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+
+    let err = service
+        .webhooks
+        .get_webhook(alice.id, false, hook.id)
+        .await
+        .err()
+        .unwrap();
+    assert!(matches!(err, WebhookError::WebhookNotFound));
+    let err = service
+        .webhooks
+        .list_deliveries(alice.id, false, hook.id, 50, 0)
+        .await
+        .err()
+        .unwrap();
+    assert!(matches!(err, WebhookError::WebhookNotFound));
+    assert_eq!(
+        service
+            .webhooks
+            .get_webhook(alice.id, true, hook.id)
+            .await
+            .unwrap()
+            .id,
+        hook.id
+    );
+}